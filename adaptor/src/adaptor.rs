@@ -5,9 +5,10 @@ use config::PREVIEW;
 use ratatui::prelude::Rect;
 use shared::RoCell;
 use tokio::{fs, sync::mpsc::UnboundedSender};
+use tracing::warn;
 
 use super::{Iterm2, Kitty};
-use crate::{ueberzug::Ueberzug, Sixel, TMUX};
+use crate::{ueberzug::Ueberzug, Blocks, Sixel, TMUX};
 
 static IMAGE_SHOWN: AtomicBool = AtomicBool::new(false);
 
@@ -24,10 +25,25 @@ pub enum Adaptor {
 	X11,
 	Wayland,
 	Chafa,
+
+	// Built-in, when none of the above is available
+	Blocks,
 }
 
 impl Adaptor {
 	pub(super) fn detect() -> Self {
+		match PREVIEW.image_protocol.as_str() {
+			"auto" => {}
+			"kitty" => return Self::Kitty,
+			"iterm2" => return Self::Iterm2,
+			"sixel" => return Self::Sixel,
+			"x11" => return Self::X11,
+			"wayland" => return Self::Wayland,
+			"chafa" => return Self::Chafa,
+			"blocks" => return Self::Blocks,
+			s => warn!("unknown image_protocol {s:?}, detecting it from the terminal instead"),
+		}
+
 		let vars = [
 			("KITTY_WINDOW_ID", Self::Kitty),
 			("KONSOLE_VERSION", Self::Kitty),
@@ -92,14 +108,31 @@ impl ToString for Adaptor {
 			Self::X11 => "x11",
 			Self::Wayland => "wayland",
 			Self::Chafa => "chafa",
+			Self::Blocks => "blocks",
 		}
 		.to_string()
 	}
 }
 
 impl Adaptor {
-	pub(super) fn start(self) {
-		UEBERZUG.init(if self.needs_ueberzug() { Ueberzug::start(self).ok() } else { None });
+	// Starts Überzug++ if it's needed, or falls back to the blocks if it's not installed
+	pub(super) fn start(self) -> Self {
+		if !self.needs_ueberzug() {
+			UEBERZUG.init(None);
+			return self;
+		}
+
+		match Ueberzug::start(self) {
+			Ok(tx) => {
+				UEBERZUG.init(Some(tx));
+				self
+			}
+			Err(e) => {
+				warn!("failed to start ueberzug for {}, falling back to blocks: {e}", self.to_string());
+				UEBERZUG.init(None);
+				Self::Blocks
+			}
+		}
 	}
 
 	pub async fn image_show(self, mut path: &Path, rect: Rect) -> Result<()> {
@@ -115,6 +148,7 @@ impl Adaptor {
 			Self::Kitty => Kitty::image_show(path, rect).await,
 			Self::Iterm2 => Iterm2::image_show(path, rect).await,
 			Self::Sixel => Sixel::image_show(path, rect).await,
+			Self::Blocks => Blocks::image_show(path, rect).await,
 			_ => Ok(if let Some(tx) = &*UEBERZUG {
 				tx.send(Some((path.to_path_buf(), rect)))?;
 			}),
@@ -130,6 +164,7 @@ impl Adaptor {
 			Self::Kitty => Kitty::image_hide(),
 			Self::Iterm2 => Iterm2::image_hide(rect),
 			Self::Sixel => Sixel::image_hide(rect),
+			Self::Blocks => Blocks::image_hide(rect),
 			_ => Ok(if let Some(tx) = &*UEBERZUG {
 				tx.send(None)?;
 			}),
@@ -138,6 +173,6 @@ impl Adaptor {
 
 	#[inline]
 	pub(super) fn needs_ueberzug(self) -> bool {
-		!matches!(self, Self::Kitty | Self::Iterm2 | Self::Sixel)
+		!matches!(self, Self::Kitty | Self::Iterm2 | Self::Sixel | Self::Blocks)
 	}
 }
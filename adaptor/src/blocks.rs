@@ -0,0 +1,77 @@
+use std::{io::{stdout, BufWriter, Write}, path::Path};
+
+use anyhow::{bail, Result};
+use image::{imageops::FilterType, DynamicImage};
+use ratatui::prelude::Rect;
+use shared::Term;
+
+use crate::{Image, Sixel};
+
+// Renders an image with half blocks in true colors, two pixels per cell stacked vertically,
+// for the terminals without any graphics protocol, and without Überzug++ installed
+pub(super) struct Blocks;
+
+impl Blocks {
+	pub(super) async fn image_show(path: &Path, rect: Rect) -> Result<()> {
+		let img = Image::crop(path, (rect.width, rect.height)).await?;
+		let rows = Self::encode(img, rect).await?;
+
+		Self::image_hide(rect)?;
+		let stdout = BufWriter::new(stdout().lock());
+		Term::move_lock(stdout, (0, 0), |stdout| {
+			for (y, row) in rows.iter().enumerate() {
+				Term::move_to(stdout, rect.x, rect.y + y as u16)?;
+				stdout.write_all(row)?;
+			}
+			Ok(())
+		})
+	}
+
+	// Same as Sixel, the cells of the blocks are cleared rather than drawn over
+	#[inline]
+	pub(super) fn image_hide(rect: Rect) -> Result<()> { Sixel::image_hide(rect) }
+
+	async fn encode(img: DynamicImage, rect: Rect) -> Result<Vec<Vec<u8>>> {
+		if img.width() == 0 || img.height() == 0 {
+			bail!("image is empty");
+		}
+
+		tokio::task::spawn_blocking(move || {
+			let (w, h) = (rect.width as u32, rect.height as u32 * 2);
+			let img = img.resize(w, h, FilterType::Triangle).into_rgba8();
+
+			let mut rows = Vec::with_capacity(img.height() as usize / 2 + 1);
+			for y in (0..img.height()).step_by(2) {
+				let mut buf = Vec::with_capacity(img.width() as usize * 40);
+				for x in 0..img.width() {
+					let top = Some(img.get_pixel(x, y).0).filter(|p| p[3] >= 128);
+					let bottom =
+						Some(y + 1).filter(|&y| y < img.height()).map(|y| img.get_pixel(x, y).0);
+					let bottom = bottom.filter(|p| p[3] >= 128);
+
+					// The transparent pixels are left in the background color of the terminal
+					match (top, bottom) {
+						(Some(t), Some(b)) => write!(
+							buf,
+							"\x1b[38;2;{};{};{};48;2;{};{};{}m▀",
+							t[0],
+							t[1],
+							t[2],
+							b[0],
+							b[1],
+							b[2]
+						)?,
+						(Some(t), None) => write!(buf, "\x1b[49;38;2;{};{};{}m▀", t[0], t[1], t[2])?,
+						(None, Some(b)) => write!(buf, "\x1b[49;38;2;{};{};{}m▄", b[0], b[1], b[2])?,
+						(None, None) => write!(buf, "\x1b[0m ")?,
+					}
+				}
+
+				write!(buf, "\x1b[0m")?;
+				rows.push(buf);
+			}
+			Ok(rows)
+		})
+		.await?
+	}
+}
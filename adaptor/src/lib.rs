@@ -1,6 +1,7 @@
 #![allow(clippy::unit_arg)]
 
 mod adaptor;
+mod blocks;
 mod image;
 mod iterm2;
 mod kitty;
@@ -8,6 +9,7 @@ mod sixel;
 mod ueberzug;
 
 use adaptor::*;
+use blocks::*;
 use iterm2::*;
 use kitty::*;
 use shared::RoCell;
@@ -29,6 +31,5 @@ pub fn init() {
 	CLOSE.init(if *TMUX { "\x1b\\" } else { "" });
 	ESCAPE.init(if *TMUX { "\x1b\x1b" } else { "\x1b" });
 
-	ADAPTOR.init(Adaptor::detect());
-	ADAPTOR.start();
+	ADAPTOR.init(Adaptor::detect().start());
 }
@@ -10,7 +10,7 @@ pub(super) struct Ueberzug;
 
 impl Ueberzug {
 	pub(super) fn start(adaptor: Adaptor) -> Result<UnboundedSender<Option<(PathBuf, Rect)>>> {
-		let mut child = Self::create_demon(adaptor).ok();
+		let mut child = Some(Self::create_demon(adaptor)?);
 		let (tx, mut rx) = mpsc::unbounded_channel();
 
 		tokio::spawn(async move {
@@ -1,48 +1,143 @@
-use core::{emit, files::FilesOp, input::InputMode, Event};
-use std::ffi::OsString;
+use core::{emit, files::FilesOp, input::InputMode, Event, Hook};
+use std::{ffi::OsString, mem, path::PathBuf, time::Duration};
 
 use anyhow::{Ok, Result};
-use config::{keymap::{Exec, Key, KeymapLayer}, BOOT};
-use crossterm::event::KeyEvent;
-use shared::{expand_url, Term};
-use tokio::sync::oneshot;
+use config::{keymap::{Exec, Key, KeymapLayer}, manager::{DateFormat, Linemode, StatusVar}, BOOT, MANAGER};
+use crossterm::event::{KeyEvent, MouseEvent};
+use shared::{expand_url, Term, Url};
+use tokio::{select, sync::oneshot, time::{self, Instant, MissedTickBehavior}};
 
-use crate::{Ctx, Executor, Logs, Root, Signals};
+use crate::{Ctx, Executor, Logs, Picker, Root, Signals};
 
 pub(super) struct App {
 	cx:      Ctx,
 	term:    Option<Term>,
 	signals: Signals,
+	picked:  Vec<Url>,
 }
 
 impl App {
 	pub(super) async fn run() -> Result<()> {
 		let _log = Logs::init()?;
+		let picker = BOOT.pick.map(|_| Picker::start()).transpose()?;
 		let term = Term::start()?;
 
 		let signals = Signals::start()?;
-		let mut app = Self { cx: Ctx::new(), term: Some(term), signals };
-
-		while let Some(event) = app.signals.recv().await {
-			match event {
-				Event::Quit => {
-					app.dispatch_quit();
-					break;
-				}
-				Event::Key(key) => app.dispatch_key(key),
-				Event::Paste(str) => app.dispatch_paste(str),
-				Event::Render(_) => app.dispatch_render(),
-				Event::Resize(..) => app.dispatch_resize(),
-				Event::Stop(state, tx) => app.dispatch_stop(state, tx),
-				Event::Call(exec, layer) => app.dispatch_call(exec, layer),
-				event => app.dispatch_module(event),
+		let mut app = Self { cx: Ctx::new(), term: Some(term), signals, picked: vec![] };
+		Term::set_mouse(app.cx.manager.mouse_enabled())?;
+
+		if let Some(p) = &BOOT.reveal {
+			let exec = Exec::call("reveal", vec![p.to_string_lossy().into_owned()]);
+			emit!(Call(exec.vec(), KeymapLayer::Manager));
+		}
+
+		let interval = |period: Duration| {
+			let mut interval = time::interval_at(Instant::now() + period, period);
+			interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+			interval
+		};
+
+		// The current directory is read again on the interval only while it isn't watched,
+		// so the loop doesn't wake up for it otherwise
+		let period = Duration::from_secs(MANAGER.refresh_interval);
+		let mut poll = (!period.is_zero()).then(|| interval(period));
+
+		// The relative times of the files are rendered again as they go by, if they're shown
+		let relative =
+			MANAGER.linemode == Linemode::Mtime && MANAGER.date_format == DateFormat::Relative;
+		let mut ticks = relative.then(|| interval(Duration::from_secs(60)));
+
+		// The free space is read again every while, only if it's shown
+		let space = [StatusVar::Free, StatusVar::FreePercent, StatusVar::Total];
+		let space = MANAGER.status_line.contains(&space);
+		let mut space = space.then(|| interval(Duration::from_secs(10)));
+
+		loop {
+			let polling = poll.is_some() && app.cx.manager.polling();
+			let event = select! {
+				event = app.signals.recv() => event,
+				_ = async { poll.as_mut().unwrap().tick().await }, if polling => {
+					app.cx.manager.poll();
+					continue;
+				}
+				_ = async { ticks.as_mut().unwrap().tick().await }, if ticks.is_some() => {
+					app.dispatch_render();
+					continue;
+				}
+				_ = async { space.as_mut().unwrap().tick().await }, if space.is_some() => {
+					if app.cx.manager.space_update() {
+						app.dispatch_render();
+					}
+					continue;
+				}
+			};
+
+			let Some(event) = event else {
+				break;
+			};
+			if !app.dispatch(event) {
+				break;
 			}
 		}
+
+		// Printed once the terminal is given back
+		let picked = mem::take(&mut app.picked);
+		drop(app);
+		if let Some(picker) = picker {
+			drop(_log);
+			picker.finish(&picked);
+		}
 		Ok(())
 	}
 
-	fn dispatch_quit(&mut self) {
-		if let Some(p) = &BOOT.cwd_file {
+	// Without a terminal, where the events are only the ones emitted, for the app driven by the tests
+	#[cfg(test)]
+	pub(super) fn headless() -> Self {
+		let signals = Signals::headless();
+		Self { cx: Ctx::new(), term: None, signals, picked: vec![] }
+	}
+
+	#[cfg(test)]
+	#[inline]
+	pub(super) fn cx(&self) -> &Ctx { &self.cx }
+
+	#[cfg(test)]
+	#[inline]
+	pub(super) async fn recv(&mut self) -> Option<Event> { self.signals.recv().await }
+
+	// Handles the event, false once it's quitting
+	pub(super) fn dispatch(&mut self, event: Event) -> bool {
+		match event {
+			Event::Quit(canceled) => {
+				self.dispatch_quit(canceled);
+				return false;
+			}
+			Event::Pick(picked) => {
+				self.picked = picked;
+				self.dispatch_quit(false);
+				return false;
+			}
+			Event::Key(key) => self.dispatch_key(key),
+			Event::Mouse(mouse) => self.dispatch_mouse(mouse),
+			Event::Paste(str) => self.dispatch_paste(str),
+			Event::Render(_) => self.dispatch_render(),
+			Event::Resize(..) => self.dispatch_resize(),
+			Event::Stop(state, tx) => self.dispatch_stop(state, tx),
+			Event::Call(exec, layer) => self.dispatch_call(exec, layer),
+			event => self.dispatch_module(event),
+		}
+		self.cx.manager.hooks();
+		true
+	}
+
+	fn dispatch_quit(&mut self, canceled: bool) {
+		self.cx.manager.tabs().save();
+		if canceled {
+			return;
+		}
+
+		// A remote one is of no use to the shell it's written for
+		if let Some(p) = BOOT.cwd_file.as_ref().filter(|_| !self.cx.manager.cwd().is_sftp()) {
 			let cwd = self.cx.manager.cwd().as_os_str();
 
 			#[cfg(target_os = "windows")]
@@ -64,6 +159,12 @@ impl App {
 		}
 	}
 
+	fn dispatch_mouse(&mut self, mouse: MouseEvent) {
+		if self.cx.layer() == KeymapLayer::Manager && self.cx.manager.mouse(mouse) {
+			emit!(Render);
+		}
+	}
+
 	fn dispatch_paste(&mut self, str: String) {
 		if self.cx.layer() == KeymapLayer::Input {
 			let input = &mut self.cx.input;
@@ -99,6 +200,7 @@ impl App {
 			self.term = None;
 		} else {
 			self.term = Some(Term::start().unwrap());
+			Term::set_mouse(self.cx.manager.mouse_enabled()).ok();
 			self.signals.stop_term(false);
 			emit!(Render);
 			emit!(Hover);
@@ -126,13 +228,23 @@ impl App {
 			}
 			Event::Refresh => {
 				manager.refresh();
+				tasks.precache_size_cancel(manager.cwd());
 			}
+			Event::Visited(cwd) => manager.visited(&cwd),
 			Event::Files(op) => {
-				let calc = matches!(op, FilesOp::Full(..) | FilesOp::Part(..));
+				let calc = matches!(op, FilesOp::Full(..) | FilesOp::Part(..) | FilesOp::Expand(..));
 				let b = match op {
 					FilesOp::Full(..) => manager.update_read(op),
 					FilesOp::Part(..) => manager.update_read(op),
 					FilesOp::Size(..) => manager.update_read(op),
+					FilesOp::SizePartial(..) => manager.update_read(op),
+					FilesOp::SizeCut(..) => manager.update_read(op),
+					FilesOp::Children(..) => manager.update_read(op),
+					FilesOp::Ranks(..) => manager.update_read(op),
+					FilesOp::Git(..) => manager.update_read(op),
+					FilesOp::Branch(..) => manager.update_read(op),
+					FilesOp::Xattrs(..) => manager.update_read(op),
+					FilesOp::Expand(..) => manager.update_read(op),
 					FilesOp::IOErr(..) => manager.update_ioerr(op),
 				};
 				if b {
@@ -140,12 +252,18 @@ impl App {
 				}
 				if calc {
 					tasks.precache_size(&manager.current().files);
+					tasks.precache_children(&manager.current().files);
+					tasks.precache_ranks(&manager.current().files);
 				}
 			}
+			Event::Sorted(url, by, order) => {
+				tracing::debug!("sorted {} by {by:?} into {order:016x}", url.display());
+			}
 			Event::Pages(page) => {
 				if manager.current().page == page {
 					let targets = self.cx.manager.current().paginate();
 					tasks.precache_mime(targets, &self.cx.manager.mimetype);
+					self.cx.manager.current().probe_xattrs();
 				}
 			}
 			Event::Mimetype(mimes) => {
@@ -168,6 +286,23 @@ impl App {
 					self.cx.manager.peek(false, self.cx.image_layer());
 				}
 			}
+			Event::Matches(matches) => {
+				manager.active_mut().preview_matches(matches);
+				self.cx.manager.peek(false, self.cx.image_layer());
+			}
+			Event::Message(text, error) => {
+				if manager.message_set(text, error) {
+					emit!(Render);
+				}
+			}
+			Event::Record(exec) => {
+				manager.repeat_record(&exec);
+			}
+			Event::Theme => {
+				if manager.theme_reload() {
+					emit!(Render);
+				}
+			}
 			Event::Preview(lock) => {
 				if manager.active_mut().update_preview(lock) {
 					emit!(Render);
@@ -178,6 +313,11 @@ impl App {
 				self.cx.select.show(opt, tx);
 				emit!(Render);
 			}
+			Event::SelectPush(items) => {
+				if self.cx.select.push(items) {
+					emit!(Render);
+				}
+			}
 			Event::Input(opt, tx) => {
 				self.cx.input.show(opt, tx);
 				emit!(Render);
@@ -200,15 +340,32 @@ impl App {
 						use std::os::unix::ffi::OsStrExt;
 						std::fs::write(p, paths.as_bytes()).ok();
 					}
-					return emit!(Quit);
+					return emit!(Quit(false));
 				}
 
+				if Hook::Open.enabled() {
+					Hook::Open.emit(targets.iter().map(|(p, _)| Url::from(PathBuf::from(p))).collect());
+				}
 				if let Some(opener) = opener {
 					tasks.file_open_with(&opener, &targets.into_iter().map(|(f, _)| f).collect::<Vec<_>>());
 				} else {
 					tasks.file_open(&targets);
 				}
 			}
+			Event::Prefer(mime, opener) => {
+				tasks.file_open_prefer(mime, opener);
+			}
+			Event::Rename(targets) => {
+				tasks.file_rename(targets);
+			}
+			Event::Land(targets) => {
+				manager.land(targets);
+			}
+			Event::Landed(target) => {
+				if manager.landed(target) {
+					emit!(Render);
+				}
+			}
 			Event::Progress(percent, left) => {
 				tasks.progress = (percent, left);
 				emit!(Render);
@@ -1,9 +1,8 @@
 use core::{help::Help, input::Input, manager::Manager, select::Select, tasks::Tasks, which::Which, Position};
 
-use config::keymap::KeymapLayer;
-use crossterm::terminal::WindowSize;
+use config::{keymap::KeymapLayer, manager::LayoutMode};
 use ratatui::prelude::Rect;
-use shared::Term;
+use unicode_width::UnicodeWidthStr;
 
 pub struct Ctx {
 	pub manager: Manager,
@@ -26,41 +25,21 @@ impl Ctx {
 		}
 	}
 
+	#[inline]
 	pub(super) fn area(&self, pos: &Position) -> Rect {
-		let WindowSize { columns, rows, .. } = Term::size();
-
-		let (x, y) = match pos {
-			Position::None => return Rect::default(),
-			Position::Top(Rect { mut x, mut y, width, height }) => {
-				x = x.min(columns.saturating_sub(*width));
-				y = y.min(rows.saturating_sub(*height));
-				((columns / 2).saturating_sub(width / 2) + x, y)
-			}
-			Position::Hovered(rect @ Rect { mut x, y, width, height }) => {
-				let Some(r) =
-					self.manager.hovered().and_then(|h| self.manager.current().rect_current(h.url()))
-				else {
-					return self.area(&Position::Top(*rect));
-				};
-
-				x = x.min(columns.saturating_sub(*width));
-				if y + height + r.y + r.height > rows {
-					(x + r.x, r.y.saturating_sub(height.saturating_sub(1)))
-				} else {
-					(x + r.x, y + r.y + r.height)
-				}
-			}
-		};
-
-		let (w, h) = pos.dimension().unwrap();
-		Rect { x, y, width: w.min(columns.saturating_sub(x)), height: h.min(rows.saturating_sub(y)) }
+		pos.area(self.manager.hovered().and_then(|h| self.manager.current().rect_current(h.url())))
 	}
 
 	#[inline]
 	pub(super) fn cursor(&self) -> Option<(u16, u16)> {
 		if self.input.visible {
 			let Rect { x, y, .. } = self.area(&self.input.position);
-			return Some((x + 1 + self.input.cursor(), y + 1));
+			let (cx, cy) = self.input.cursor();
+			return Some((x + 1 + cx, y + 1 + cy));
+		}
+		if self.select.visible && self.select.in_filter() {
+			let Rect { x, y, .. } = self.area(&self.select.position);
+			return Some((x + 1 + self.select.title().width() as u16, y));
 		}
 		if let Some((x, y)) = self.help.cursor() {
 			return Some((x, y));
@@ -87,6 +66,12 @@ impl Ctx {
 
 	#[inline]
 	pub(super) fn image_layer(&self) -> bool {
-		!matches!(self.layer(), KeymapLayer::Which | KeymapLayer::Help | KeymapLayer::Tasks)
+		// There's no preview to show the images in with the two panes of the `commander` layout
+		self.manager.layout == LayoutMode::Miller
+			&& match self.layer() {
+				KeymapLayer::Which => !self.which.popup() && !self.help.visible() && !self.tasks.visible,
+				KeymapLayer::Help | KeymapLayer::Tasks => false,
+				_ => true,
+			}
 	}
 }
@@ -1,12 +1,26 @@
-use core::{emit, files::FilesSorter, input::InputMode};
+use core::{emit, files::FilesSorter, input::InputMode, manager::{JumpKind, ListingOpt}, tasks::{Checksum, PasteOpt, Preserve, TaskKind}};
+use std::collections::HashSet;
 
-use config::{keymap::{Control, Exec, Key, KeymapLayer}, manager::SortBy, KEYMAP};
+use config::{keymap::{Control, Exec, Key, KeymapLayer}, manager::{Collation, LayoutMode, SortBy}, tasks::ConflictPolicy, BOOT, KEYMAP, MANAGER};
 use shared::{optional_bool, Url};
 
 use super::Ctx;
 
 pub(super) struct Executor;
 
+// The commands that change the files, which `--pick` only picks from
+const PICK_DENIED: &[&str] = &[
+	"paste", "gather", "link", "symlink", "remove", "restore", "touch", "chmod", "chown", "create",
+	"rename", "replace", "dedupe", "archive", "extract", "shell", "subshell",
+];
+
+// The commands that work on the local files only, which the remote ones are refused with
+const REMOTE_DENIED: &[&str] = &[
+	"open", "edit", "link", "symlink", "touch", "chmod", "chown", "create", "rename", "replace",
+	"dedupe", "archive", "extract", "shell", "subshell", "xattrs", "info_edit", "diff", "search",
+	"select_matches", "reveal", "bookmark_set",
+];
+
 impl Executor {
 	pub(super) fn handle(cx: &mut Ctx, key: Key) -> bool {
 		let layer = cx.layer();
@@ -22,18 +36,19 @@ impl Executor {
 			return true;
 		}
 
-		for Control { on, exec, .. } in KEYMAP.get(layer) {
-			if on.is_empty() || on[0] != key {
-				continue;
-			}
+		if layer == KeymapLayer::Select && cx.select.type_(&key) {
+			return true;
+		}
 
-			return if on.len() > 1 {
-				cx.which.show(&key, layer)
-			} else {
-				Self::dispatch(cx, exec, layer)
-			};
+		// The longer bindings starting with the key are waited on, even if it's a binding of its own
+		let controls = KEYMAP.get(layer);
+		if controls.iter().any(|c| c.on.len() > 1 && c.on[0] == key) {
+			return cx.which.show(&key, layer);
+		}
+		match controls.iter().find(|c| c.on.len() == 1 && c.on[0] == key) {
+			Some(Control { exec, .. }) => Self::dispatch(cx, exec, layer),
+			None => false,
 		}
-		false
 	}
 
 	#[inline]
@@ -46,42 +61,118 @@ impl Executor {
 				KeymapLayer::Select => Self::select(cx, e),
 				KeymapLayer::Input => Self::input(cx, e),
 				KeymapLayer::Help => Self::help(cx, e),
-				KeymapLayer::Which => unreachable!(),
+				KeymapLayer::Which => Self::which(cx, e),
 			};
 		}
 		render
 	}
 
 	fn manager(cx: &mut Ctx, exec: &Exec) -> bool {
+		// The count goes to the motion right after it, and is given up for any other command
+		let count = if exec.cmd == "count" { None } else { cx.manager.count_take() };
+		if BOOT.pick.is_some() && PICK_DENIED.contains(&exec.cmd.as_str()) {
+			emit!(Message(format!("`{}` isn't available while picking files", exec.cmd), true));
+			return false;
+		}
+		if REMOTE_DENIED.contains(&exec.cmd.as_str()) && Self::remote(cx, exec) {
+			emit!(Message(format!("`{}` isn't supported for remote files", exec.cmd), true));
+			return false;
+		}
+		cx.manager.repeat_record(exec);
+
 		match exec.cmd.as_str() {
 			"escape" => cx.manager.active_mut().escape(),
-			"quit" => cx.manager.quit(&cx.tasks),
+			"quit" => cx.manager.quit(&cx.tasks, exec.named.contains_key("cancel")),
 			"close" => cx.manager.close(&cx.tasks),
 			"suspend" => cx.manager.suspend(),
+			"palette" => cx.manager.palette(),
+			"repeat" => match cx.manager.repeat().cloned() {
+				Some(last) => Self::manager(cx, &last),
+				None => {
+					emit!(Message("Nothing to repeat yet".to_owned(), false));
+					false
+				}
+			},
 
 			// Navigation
+			"count" => {
+				let digit = exec.args.get(0).and_then(|s| s.parse().ok()).filter(|&d| d < 10);
+				digit.is_some_and(|d| cx.manager.count_push(d))
+			}
 			"arrow" => {
-				let step = exec.args.get(0).and_then(|s| s.parse().ok()).unwrap_or(0);
+				let step: isize = exec.args.get(0).and_then(|s| s.parse().ok()).unwrap_or(0);
+				let step = step.saturating_mul(count.unwrap_or(1) as isize);
+				if cx.manager.active().preview().focused() {
+					cx.manager.active_mut().preview_arrow(step);
+					return cx.manager.peek(true, cx.image_layer());
+				}
 				cx.manager.active_mut().arrow(step)
 			}
+			"arrow_kind" => {
+				let Ok(kind) = JumpKind::try_from(exec.args.get(0).cloned().unwrap_or_default()) else {
+					return false;
+				};
+				let wrap = exec.named.contains_key("wrap")
+					|| (!exec.named.contains_key("no-wrap") && MANAGER.arrow_wrap);
+				cx.manager.arrow_kind(kind, exec.named.contains_key("previous"), wrap)
+			}
+			"goto" => {
+				let index = match count {
+					Some(n) => n - 1,
+					None if exec.args.get(0).is_some_and(|s| s == "bottom") => usize::MAX,
+					None => 0,
+				};
+				cx.manager.active_mut().goto(index)
+			}
 			"peek" => {
 				let step = exec.args.get(0).and_then(|s| s.parse().ok()).unwrap_or(0);
 				cx.manager.active_mut().preview_arrow(step);
 				cx.manager.peek(true, cx.image_layer())
 			}
+			"preview_focus" => {
+				cx.manager.active_mut().preview_focus(exec.args.get(0).and_then(|s| optional_bool(s)))
+			}
+			"preview_pin" => cx.manager.preview_pin(exec.args.get(0).and_then(|s| optional_bool(s))),
+			"preview_full" => cx.manager.active_mut().preview_full(),
+			"peek_match" => {
+				let step = exec.args.get(0).and_then(|s| s.parse().ok()).unwrap_or(1);
+				cx.manager.active_mut().preview_jump_match(step);
+				cx.manager.peek(true, cx.image_layer())
+			}
+			"syntect_theme" => cx.manager.syntect_theme(exec.args.get(0).map(|s| s.as_str())),
 			"leave" => cx.manager.active_mut().leave(),
-			"enter" => cx.manager.active_mut().enter(),
-			"back" => cx.manager.active_mut().back(),
-			"forward" => cx.manager.active_mut().forward(),
+			"enter" => {
+				let flag = |yes: &str, no: &str, default: bool| {
+					exec.named.contains_key(yes) || (!exec.named.contains_key(no) && default)
+				};
+				cx.manager.active_mut().enter(
+					flag("follow", "no-follow", MANAGER.follow_symlink),
+					flag("smart", "no-smart", MANAGER.smart_enter),
+				)
+			}
+			"tree" => cx.manager.active_mut().tree(exec.args.get(0).and_then(|s| s.parse().ok())),
+			"expand" => cx.manager.active_mut().expand(),
+			"collapse" => cx.manager.active_mut().collapse(),
+			"back" => futures::executor::block_on(cx.manager.active_mut().back()),
+			"forward" => futures::executor::block_on(cx.manager.active_mut().forward()),
 			"cd" => {
 				let url = exec.args.get(0).map(Url::from).unwrap_or_default();
 				if exec.named.contains_key("interactive") {
 					cx.manager.active_mut().cd_interactive(url)
+				} else if exec.named.contains_key("preview") {
+					cx.manager.active_mut().cd_preview(url)
 				} else {
+					// Back where the preview started first, for that to be left in the backstack
+					let origin = exec.named.get("origin").map(|o| Url::parse_sftp(o).unwrap_or(Url::from(o)));
+					let b = origin.is_some_and(|o| cx.manager.active_mut().cd_unpreview(o));
 					emit!(Cd(url));
-					false
+					b
 				}
 			}
+			"reveal" => {
+				let url = exec.args.get(0).map(Url::from).unwrap_or_default();
+				cx.manager.active_mut().reveal(url)
+			}
 
 			// Selection
 			"select" => {
@@ -92,51 +183,220 @@ impl Executor {
 				let state = exec.named.get("state").cloned().unwrap_or("none".to_string());
 				cx.manager.active_mut().select_all(optional_bool(&state))
 			}
+			"select_matches" => {
+				let state = exec.named.get("state").map_or(Some(true), |s| optional_bool(s));
+				cx.manager.active_mut().select_matches(
+					exec.args.get(0).map(|s| s.as_str()),
+					exec.named.contains_key("glob"),
+					exec.named.contains_key("sensitive"),
+					state,
+				)
+			}
 			"visual_mode" => cx.manager.active_mut().visual_mode(exec.named.contains_key("unset")),
 
 			// Operation
+			"open" if BOOT.pick.is_some() => cx.manager.pick(),
 			"open" => cx.manager.open(exec.named.contains_key("interactive")),
+			"edit" => cx.manager.edit(),
+			"diff" => cx.manager.diff(),
 			"yank" => cx.manager.yank(exec.named.contains_key("cut")),
+			"yank_cycle" => {
+				let step = exec.args.get(0).and_then(|s| s.parse().ok()).unwrap_or(1);
+				cx.manager.yank_cycle(step)
+			}
 			"paste" => {
+				let Some(dest) = cx.manager.paste_dest(exec.named.contains_key("into")) else {
+					return false;
+				};
+				let Some(set) = cx.manager.yanked().active() else {
+					return false;
+				};
+				if set.moved {
+					emit!(Message("The files yanked have been moved already".to_owned(), true));
+					return false;
+				}
+
+				let opt = Self::paste_opt(exec);
+				if set.cut {
+					let b = cx.tasks.file_cut(&set.urls, dest, opt);
+					cx.manager.yanked_mut().consume();
+					b
+				} else {
+					cx.tasks.file_copy(&set.urls, dest, opt)
+				}
+			}
+			"gather" => {
+				// Those in the current directory are where they'd be pasted already
 				let dest = cx.manager.cwd().to_owned();
-				let (cut, src) = cx.manager.yanked();
+				let src = cx.manager.active().selected_everywhere().into_iter();
+				let src: HashSet<_> = src.filter(|u| u.parent_url().as_ref() != Some(&dest)).collect();
+				if src.is_empty() {
+					emit!(Message("No files are selected in the other directories".to_owned(), true));
+					return false;
+				}
 
-				let force = exec.named.contains_key("force");
-				if *cut {
-					cx.tasks.file_cut(src, dest, force)
+				let opt = Self::paste_opt(exec);
+				let keep = exec.named.contains_key("keep") || opt.dry_run;
+				if exec.named.contains_key("cut") {
+					cx.tasks.file_cut(&src, dest, opt);
 				} else {
-					cx.tasks.file_copy(src, dest, force, exec.named.contains_key("follow"))
+					cx.tasks.file_copy(&src, dest, opt);
 				}
+				!keep && cx.manager.active_mut().unselect_everywhere()
+			}
+			"link" => {
+				let dest = cx.manager.cwd().to_owned();
+				let Some(set) = cx.manager.yanked().active() else {
+					return false;
+				};
+				cx.tasks.file_link(&set.urls, dest, true, false)
+			}
+			"symlink" => {
+				let dest = cx.manager.cwd().to_owned();
+				let relative = exec.named.contains_key("relative");
+				let Some(set) = cx.manager.yanked().active() else {
+					return false;
+				};
+				cx.tasks.file_link(&set.urls, dest, false, relative)
 			}
 			"remove" => {
+				let targets: Vec<_> = cx.manager.selected().into_iter().map(|f| f.url_owned()).collect();
+				cx.manager.bookmark_removed(&targets);
+				let dry_run = exec.named.contains_key("dry-run");
+				cx.tasks.file_remove(targets, exec.named.contains_key("permanently"), dry_run)
+			}
+			"restore" => cx.tasks.file_restore(),
+			"touch" => {
+				let targets: Vec<_> = if exec.args.is_empty() {
+					cx.manager.selected().into_iter().map(|f| f.url_owned()).collect()
+				} else {
+					exec.args.iter().map(|name| cx.manager.cwd().join(name)).collect()
+				};
+				let spec = if exec.named.contains_key("interactive") {
+					None
+				} else {
+					Some(exec.named.get("time").cloned().unwrap_or_default())
+				};
+				cx.tasks.file_touch(targets, spec, exec.named.contains_key("atime"))
+			}
+			#[cfg(unix)]
+			"chmod" => {
 				let targets = cx.manager.selected().into_iter().map(|f| f.url_owned()).collect();
-				cx.tasks.file_remove(targets, exec.named.contains_key("permanently"))
+				let recursive = exec.named.contains_key("recursive");
+				cx.tasks.file_chmod(targets, exec.args.get(0).cloned(), recursive)
 			}
-			"create" => cx.manager.create(),
+			#[cfg(unix)]
+			"chown" => {
+				let targets = cx.manager.selected().into_iter().map(|f| f.url_owned()).collect();
+				let recursive = exec.named.contains_key("recursive");
+				cx.tasks.file_chown(targets, exec.args.get(0).cloned(), recursive)
+			}
+			#[cfg(not(unix))]
+			"chmod" | "chown" => {
+				let text = format!("`{}` isn't supported on this platform", exec.cmd);
+				emit!(Message(text, true));
+				false
+			}
+			"create" => cx.tasks.file_create(cx.manager.cwd()),
 			"rename" => cx.manager.rename(),
+			#[cfg(target_os = "linux")]
+			"devices" => cx.manager.devices(),
+			#[cfg(not(target_os = "linux"))]
+			"devices" => {
+				emit!(Message("`devices` is only supported on Linux".to_owned(), true));
+				false
+			}
+			"archive" => {
+				let targets = cx.manager.selected().into_iter().map(|f| f.url_owned()).collect();
+				let cwd = cx.manager.cwd().to_regular();
+				cx.tasks.file_archive(cwd, targets, exec.args.get(0).cloned())
+			}
+			"extract" => {
+				let targets = cx.manager.selected().into_iter().map(|f| f.url_owned()).collect();
+				let cwd = cx.manager.cwd().to_regular();
+				cx.tasks.file_extract(cwd, targets, exec.named.contains_key("sub"))
+			}
+			"dedupe" => cx.tasks.file_dedupe(cx.manager.cwd().clone(), cx.manager.active().show_hidden()),
+			"replace" => {
+				let targets = cx.manager.selected().into_iter().map(|f| f.url_owned()).collect();
+				let (find, with) = (exec.args.get(0).cloned(), exec.args.get(1).cloned());
+				cx.tasks.file_replace(targets, find, with, exec.named.contains_key("regex"))
+			}
 			"copy" => cx.manager.active().copy(exec.args.get(0).map(|s| s.as_str()).unwrap_or("")),
+			"copy_listing" => cx.manager.active().copy_listing(ListingOpt {
+				format: exec.args.get(0).and_then(|s| s.clone().try_into().ok()).unwrap_or_default(),
+				slash:  exec.named.contains_key("slash"),
+				size:   exec.named.contains_key("size"),
+				date:   exec.named.contains_key("date"),
+				save:   exec.named.contains_key("save"),
+			}),
+			"subshell" => cx.manager.active().subshell(),
+			"xattrs" => cx.manager.active().xattrs(),
 			"shell" => cx.manager.active().shell(
 				exec.args.get(0).map(|e| e.as_str()).unwrap_or(""),
 				exec.named.contains_key("block"),
 				exec.named.contains_key("confirm"),
 			),
 			"hidden" => {
-				cx.manager.active_mut().set_show_hidden(match exec.args.get(0).map(|s| s.as_str()) {
+				let b = cx.manager.active_mut().set_show_hidden(match exec.args.get(0).map(|s| s.as_str()) {
+					Some("show") => Some(true),
+					Some("hide") => Some(false),
+					_ => None,
+				});
+				cx.tasks.precache_size(&cx.manager.current().files);
+				cx.tasks.precache_ranks(&cx.manager.current().files);
+				b
+			}
+			"ignored" => {
+				cx.manager.active_mut().set_show_ignored(match exec.args.get(0).map(|s| s.as_str()) {
 					Some("show") => Some(true),
 					Some("hide") => Some(false),
 					_ => None,
 				})
 			}
+			"info" => {
+				cx.manager.active_mut().set_info(exec.args.get(0).and_then(|s| optional_bool(s)))
+			}
+			"info_edit" => cx.manager.info_edit(exec.args.get(0).map_or("", |s| s.as_str())),
+			"line_numbers" => cx.manager.active_mut().set_line_numbers(
+				exec.args.get(0).and_then(|s| optional_bool(s)),
+			),
+			"relative_numbers" => cx.manager.active_mut().set_relative_numbers(
+				exec.args.get(0).and_then(|s| optional_bool(s)),
+			),
+			"hex" => cx.manager.active_mut().preview_hex(exec.args.get(0).and_then(|s| optional_bool(s))),
 			"search" => match exec.args.get(0).map(|s| s.as_str()).unwrap_or("") {
 				"rg" => cx.manager.active_mut().search(true),
+				"grep" => cx.manager.active_mut().grep(),
 				"fd" => cx.manager.active_mut().search(false),
+				"locate" => cx.manager.active_mut().locate(),
 				_ => cx.manager.active_mut().search_stop(),
 			},
 			"jump" => match exec.args.get(0).map(|s| s.as_str()).unwrap_or("") {
 				"fzf" => cx.manager.active_mut().jump(true),
+				"zoxide" if exec.args.len() > 1 => cx.manager.active().zoxide(exec.args[1..].to_vec()),
 				"zoxide" => cx.manager.active_mut().jump(false),
+				"label" => {
+					let current = cx.manager.current();
+					cx.which.show_labels(current.window().len(), current.cursor() - current.offset())
+				}
 				_ => false,
 			},
+			"bookmark_set" => cx.manager.bookmark_set(exec.args.get(0).map(|s| s.as_str())),
+			"bookmark_jump" => cx.manager.bookmark_jump(exec.args.get(0).map(|s| s.as_str())),
+			"bookmark_delete" => cx.manager.bookmark_delete(exec.args.get(0).map(|s| s.as_str())),
+			"override_set" => cx.manager.override_set(),
+			"override_clear" => cx.manager.override_clear(),
+			"bookmark_prune" => cx.manager.bookmark_prune(),
+
+			// Places
+			"places" => cx.manager.set_places(exec.args.get(0).and_then(|s| optional_bool(s))),
+			"place_jump" => cx.manager.place_jump(exec.args.get(0).and_then(|s| s.parse().ok())),
+			"place_add" => cx.manager.place_add(),
+			"place_remove" => cx.manager.place_remove(exec.args.get(0).and_then(|s| s.parse().ok())),
+			"place_move" => {
+				cx.manager.place_move(exec.args.get(0).and_then(|s| s.parse().ok()).unwrap_or(1))
+			}
 
 			// Find
 			"find" => {
@@ -146,15 +406,73 @@ impl Executor {
 			}
 			"find_arrow" => cx.manager.active_mut().find_arrow(exec.named.contains_key("previous")),
 
+			// Filter
+			"filter" => {
+				let query = exec.args.get(0).map(|s| s.as_str());
+				let glob = exec.named.contains_key("glob");
+				let sensitive = exec.named.contains_key("sensitive");
+				cx.manager.active_mut().filter(query, glob, sensitive)
+			}
+			"quick_filter" => {
+				cx.manager.active_mut().quick_filter(exec.args.get(0).map(|s| s.as_str()))
+			}
+
 			// Sorting
 			"sort" => {
+				let by = SortBy::try_from(exec.args.get(0).cloned().unwrap_or_default())
+					.unwrap_or_default();
+				let old = *cx.manager.active().sorter();
+				let seed = if by == SortBy::Random { FilesSorter::roll() } else { old.seed };
+				let collation = exec
+					.named
+					.get("collation")
+					.and_then(|s| Collation::try_from(s.clone()).ok())
+					.unwrap_or(old.collation);
+				// Off unless given, as the other flags, where neither of them mixes hidden files in
+				let hidden_last = if exec.named.contains_key("hidden_last") {
+					Some(true)
+				} else if exec.named.contains_key("hidden_first") {
+					Some(false)
+				} else {
+					None
+				};
+
 				let b = cx.manager.active_mut().set_sorter(FilesSorter {
-					by:        SortBy::try_from(exec.args.get(0).cloned().unwrap_or_default())
-						.unwrap_or_default(),
-					reverse:   exec.named.contains_key("reverse"),
-					dir_first: exec.named.contains_key("dir_first"),
+					by,
+					sensitive:      exec.named.contains_key("sensitive"),
+					reverse:        exec.named.contains_key("reverse"),
+					dir_first:      exec.named.contains_key("dir_first"),
+					hidden_last,
+					kind_order:     old.kind_order,
+					size_recursive: old.size_recursive,
+					stable:         exec.named.contains_key("stable"),
+					collation,
+					tiebreak:       old.tiebreak,
+					split_ext:      old.split_ext,
+					leading_zeros:  old.leading_zeros,
+					thousands:      old.thousands,
+					seed,
+				});
+				cx.tasks.precache_size(&cx.manager.current().files);
+				cx.tasks.precache_children(&cx.manager.current().files);
+				cx.tasks.precache_ranks(&cx.manager.current().files);
+				b
+			}
+			"sort_parent" => {
+				let tab = cx.manager.active_mut();
+				let sorter = exec.args.get(0).and_then(|s| SortBy::try_from(s.clone()).ok()).map(|by| {
+					let seed = if by == SortBy::Random { FilesSorter::roll() } else { tab.sorter().seed };
+					let reverse = exec.named.contains_key("reverse");
+					FilesSorter { by, reverse, seed, ..*tab.sorter() }
 				});
+				tab.set_parent_sorter(sorter)
+			}
+			"sort_reverse_toggle" => cx.manager.active_mut().sort_reverse_toggle(),
+			"sort_cycle" => {
+				let b = cx.manager.active_mut().sort_cycle(exec.named.contains_key("previous"));
 				cx.tasks.precache_size(&cx.manager.current().files);
+				cx.tasks.precache_children(&cx.manager.current().files);
+				cx.tasks.precache_ranks(&cx.manager.current().files);
 				b
 			}
 
@@ -176,10 +494,51 @@ impl Executor {
 				let rel = exec.named.contains_key("relative");
 				cx.manager.tabs_mut().switch(step, rel)
 			}
+			"tab_send" => {
+				let idx = exec.args.get(0).and_then(|s| s.parse().ok()).unwrap_or(0);
+				let dest = if exec.named.contains_key("other") {
+					cx.manager.tabs().other()
+				} else {
+					cx.manager.tabs().get(idx, exec.named.contains_key("relative"))
+				};
+				let Some(dest) = dest else {
+					return false;
+				};
+
+				let dest = dest.cwd().clone();
+				let src = cx.manager.selected().into_iter().map(|f| f.url_owned()).collect();
+				if dest == *cx.manager.cwd() || !dest.is_regular() {
+					false
+				} else if exec.named.contains_key("copy") {
+					cx.tasks.file_copy(&src, dest, PasteOpt::default())
+				} else {
+					cx.tasks.file_cut(&src, dest, PasteOpt::default())
+				}
+			}
 			"tab_swap" => {
 				let step = exec.args.get(0).and_then(|s| s.parse().ok()).unwrap_or(0);
 				cx.manager.tabs_mut().swap(step)
 			}
+			"session" => match exec.args.get(0).map(|s| s.as_str()) {
+				Some("save") => {
+					cx.manager.tabs().save();
+					false
+				}
+				Some("restore") => cx.manager.tabs_mut().restore(),
+				_ => false,
+			},
+			"pane_switch" => cx.manager.tabs_mut().switch_other(),
+			"mouse" => cx.manager.set_mouse(exec.args.get(0).and_then(|s| optional_bool(s))),
+			"watch" => cx.manager.set_watch(exec.args.get(0).and_then(|s| optional_bool(s))),
+			"refresh" => {
+				emit!(Refresh);
+				false
+			}
+			"layout" => cx.manager.set_layout(match exec.args.get(0).map(|s| s.as_str()) {
+				Some("miller") => Some(LayoutMode::Miller),
+				Some("commander") => Some(LayoutMode::Commander),
+				_ => None,
+			}),
 
 			// Tasks
 			"tasks_show" => cx.tasks.toggle(),
@@ -191,17 +550,75 @@ impl Executor {
 		}
 	}
 
+	// The options of `paste` and `gather`
+	fn paste_opt(exec: &Exec) -> PasteOpt {
+		let mut opt = PasteOpt {
+			follow: exec.named.contains_key("follow"),
+			dry_run: exec.named.contains_key("dry-run"),
+			reflink: !exec.named.contains_key("no-reflink"),
+			preserve: Preserve {
+				mode:   !exec.named.contains_key("no-mode"),
+				times:  !exec.named.contains_key("no-times"),
+				xattrs: !exec.named.contains_key("no-xattrs"),
+			},
+			verify: exec.named.get("verify").and_then(|s| Checksum::try_from(s.clone()).ok()),
+			..Default::default()
+		};
+		if exec.named.contains_key("force") {
+			opt.conflict = ConflictPolicy::Overwrite;
+		} else if let Some(c) = exec.named.get("conflict") {
+			opt.conflict = ConflictPolicy::try_from(c.clone()).unwrap_or(opt.conflict);
+		}
+		if let Some(limit) = exec.named.get("limit").and_then(|s| s.parse().ok()) {
+			opt.limit = limit;
+		}
+		opt
+	}
+
+	// Whether the command is on remote files, the ones in view, or the ones yanked to be linked
+	fn remote(cx: &Ctx, exec: &Exec) -> bool {
+		if cx.manager.cwd().is_sftp() {
+			return true;
+		}
+		let yanked = cx.manager.yanked().active();
+		matches!(exec.cmd.as_str(), "link" | "symlink")
+			&& yanked.is_some_and(|s| s.urls.iter().any(|u| u.is_sftp()))
+	}
+
+	fn which(cx: &mut Ctx, exec: &Exec) -> bool {
+		match exec.cmd.as_str() {
+			"timeout" => {
+				let ticket = exec.args.get(0).and_then(|s| s.parse().ok());
+				ticket.is_some_and(|t| cx.which.timeout(t))
+			}
+			_ => false,
+		}
+	}
+
 	fn tasks(cx: &mut Ctx, exec: &Exec) -> bool {
 		match exec.cmd.as_str() {
-			"close" => cx.tasks.toggle(),
+			"close" => cx.tasks.close(),
 
-			"arrow" => {
-				let step = exec.args.get(0).and_then(|s| s.parse().ok()).unwrap_or(0);
-				if step > 0 { cx.tasks.next() } else { cx.tasks.prev() }
-			}
+			"arrow" => cx.tasks.arrow(exec.args.get(0).and_then(|s| s.parse().ok()).unwrap_or(0)),
 
 			"inspect" => cx.tasks.inspect(),
 			"cancel" => cx.tasks.cancel(),
+			"shift" => cx.tasks.shift(exec.args.get(0).and_then(|s| s.parse().ok())),
+			"prioritize" => cx.tasks.shift(None),
+			"pause" => cx.tasks.pause(match exec.args.get(0).map(|s| s.as_str()) {
+				Some("on") => Some(true),
+				Some("off") => Some(false),
+				_ => None,
+			}),
+			"concurrency" => cx.tasks.concurrency(
+				match exec.args.get(0).map(|s| s.as_str()) {
+					Some("file") => Some(TaskKind::File),
+					Some("precache") => Some(TaskKind::Precache),
+					_ => None,
+				},
+				exec.args.get(1).map(|s| s.as_str()).unwrap_or(""),
+			),
+			"limit" => cx.tasks.throttle(exec.args.get(0).and_then(|s| s.parse().ok())),
 
 			"help" => cx.help.toggle(cx.layer()),
 			_ => false,
@@ -211,11 +628,13 @@ impl Executor {
 	fn select(cx: &mut Ctx, exec: &Exec) -> bool {
 		match exec.cmd.as_str() {
 			"close" => cx.select.close(exec.named.contains_key("submit")),
+			"escape" => cx.select.escape(),
 
 			"arrow" => {
 				let step: isize = exec.args.get(0).and_then(|s| s.parse().ok()).unwrap_or(0);
 				if step > 0 { cx.select.next(step as usize) } else { cx.select.prev(step.unsigned_abs()) }
 			}
+			"filter" => cx.select.filter(),
 
 			"help" => cx.help.toggle(cx.layer()),
 			_ => false,
@@ -232,6 +651,17 @@ impl Executor {
 				let in_operating = exec.named.contains_key("in-operating");
 				return if in_operating { cx.input.move_in_operating(step) } else { cx.input.move_(step) };
 			}
+			"history" => {
+				let step = exec.args.get(0).and_then(|s| s.parse().ok()).unwrap_or(0);
+				return cx.input.history(step);
+			}
+			"history_clear" => return cx.input.history_clear(exec.args.get(0).map(|s| s.as_str())),
+
+			"backward" => return cx.input.backward(),
+			"forward" => return cx.input.forward(exec.named.contains_key("end-of-word")),
+			"kill" => return cx.input.kill(exec.args.get(0).map(|s| s.as_str()).unwrap_or("")),
+			"kill_paste" => return cx.input.kill_paste(exec.named.contains_key("older")),
+			"newline" => return cx.input.newline(exec.named.contains_key("below")),
 			_ => {}
 		}
 
@@ -240,8 +670,6 @@ impl Executor {
 				"insert" => cx.input.insert(exec.named.contains_key("append")),
 				"visual" => cx.input.visual(),
 
-				"backward" => cx.input.backward(),
-				"forward" => cx.input.forward(exec.named.contains_key("end-of-word")),
 				"delete" => {
 					cx.input.delete(exec.named.contains_key("cut"), exec.named.contains_key("insert"))
 				}
@@ -255,7 +683,13 @@ impl Executor {
 				"help" => cx.help.toggle(cx.layer()),
 				_ => false,
 			},
-			InputMode::Insert => false,
+			InputMode::Insert => match exec.cmd.as_str() {
+				"complete" => {
+					let step = exec.args.get(0).and_then(|s| s.parse().ok()).unwrap_or(1);
+					cx.input.complete(step)
+				}
+				_ => false,
+			},
 		}
 	}
 
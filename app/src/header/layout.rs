@@ -1,4 +1,5 @@
-use ratatui::{layout, prelude::{Buffer, Constraint, Direction, Rect}, style::{Color, Style}, widgets::{Paragraph, Widget}};
+use config::THEME;
+use ratatui::{layout, prelude::{Buffer, Constraint, Direction, Rect}, widgets::{Paragraph, Widget}};
 use shared::readable_path;
 
 use super::Tabs;
@@ -22,11 +23,13 @@ impl<'a> Widget for Layout<'a> {
 		let cwd = &self.cx.manager.current().cwd;
 		let location = if cwd.is_search() {
 			format!("{} (search: {})", readable_path(cwd), cwd.frag().unwrap())
+		} else if cwd.is_sftp() {
+			format!("{cwd:?}")
 		} else {
 			readable_path(cwd)
 		};
 
-		Paragraph::new(location).style(Style::new().fg(Color::Cyan)).render(chunks[0], buf);
+		Paragraph::new(location).style(THEME.header.cwd.get()).render(chunks[0], buf);
 
 		Tabs::new(self.cx).render(chunks[1], buf);
 	}
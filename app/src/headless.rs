@@ -0,0 +1,256 @@
+use core::{files::File, Event};
+use std::{collections::BTreeSet, fs, path::Path, sync::{Mutex, MutexGuard, Once, OnceLock}, time::{Duration, Instant}};
+
+use config::{keymap::{Exec, Key, KeymapLayer}, BOOT};
+use crossterm::event::KeyEvent;
+use ratatui::{backend::TestBackend, Terminal};
+use shared::{Term, Url};
+use tokio::{runtime::Runtime, time::timeout};
+
+use crate::{App, Ctx, Root};
+
+// The size of the terminal there isn't
+const COLUMNS: u16 = 120;
+const ROWS: u16 = 30;
+
+// How long the app is to be without an event, with no task running, to be settled, and how long
+// what's waited for with `until` has at most to come true
+const QUIET: Duration = Duration::from_millis(100);
+const DEADLINE: Duration = Duration::from_secs(5);
+
+// The options over the presets, the files sorted by their names for the order to be known, and the
+// images shown in the blocks with nothing asked of the terminal
+const YAZI: &str = r#"
+[manager]
+sort_by      = "alphabetical"
+sort_reverse = false
+
+[preview]
+image_protocol = "blocks"
+"#;
+
+// The one runtime the config and the tasks started with it are on, and the apps driven one at
+// a time, as the events of all of them are emitted to the one channel there is
+static RT: OnceLock<Runtime> = OnceLock::new();
+static INIT: Once = Once::new();
+static ONE: Mutex<()> = Mutex::new(());
+
+// The app driven by the tests without a terminal, given the keys as if they're pressed, run until
+// it's settled, with the state of the manager to look at afterwards, and the screen it draws, e.g.
+//
+// 	let mut h = Headless::new(&dir);
+// 	h.keys("jj");
+// 	assert_eq!(h.hovered().as_deref(), Some("c"));
+//
+// Only the presets are read, none of the config of the user, and the state is kept in a directory
+// of its own, made anew for each app.
+pub(super) struct Headless {
+	app:  App,
+	term: Terminal<TestBackend>,
+	rt:   &'static Runtime,
+
+	_one: MutexGuard<'static, ()>,
+}
+
+impl Headless {
+	// The app in `dir`, once the files of it are read
+	pub(super) fn new(dir: &Path) -> Self {
+		let one = ONE.lock().unwrap_or_else(|e| e.into_inner());
+		let rt = RT.get_or_init(|| {
+			tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap()
+		});
+		INIT.call_once(|| {
+			// Where it starts, with the state in it, gone along with it once it's done
+			let root = std::env::temp_dir().join(format!("yazi-headless-{}", std::process::id()));
+			fs::create_dir_all(&root).unwrap();
+			Term::set_headless(COLUMNS, ROWS);
+
+			let _guard = rt.enter();
+			config::init_bare(YAZI, root.clone(), root.join("state"));
+			core::init();
+			adaptor::init();
+		});
+		fs::remove_dir_all(&BOOT.cwd).ok();
+		fs::create_dir_all(&BOOT.state_dir).unwrap();
+
+		let app = {
+			let _guard = rt.enter();
+			App::headless()
+		};
+		let term = Terminal::new(TestBackend::new(COLUMNS, ROWS)).unwrap();
+		let mut h = Self { app, term, rt, _one: one };
+
+		let url = Url::from(dir);
+		h.send(Event::Cd(url.clone()));
+		h.until(|cx| cx.manager.cwd() == &url && cx.manager.current().files.version() > 0);
+		h
+	}
+
+	// Presses the keys one after another, each until the app is settled, where a key like `<C-a>`
+	// or `<Enter>` is in the brackets, as in the keymap
+	pub(super) fn keys(&mut self, keys: &str) {
+		let mut it = keys.chars();
+		while let Some(c) = it.next() {
+			let s = match c {
+				'<' if it.as_str().contains('>') => {
+					let (inner, rest) = it.as_str().split_once('>').unwrap();
+					let s = format!("<{inner}>");
+					it = rest.chars();
+					s
+				}
+				c => c.to_string(),
+			};
+			let key = Key::try_from(s).unwrap();
+			self.send(Event::Key(KeyEvent::from(&key)));
+		}
+	}
+
+	// Runs the command of the manager, as one bound in the keymap, e.g. `sort size --reverse`
+	pub(super) fn call(&mut self, exec: &str) {
+		let exec = Exec::try_from(exec).unwrap();
+		self.send(Event::Call(exec.vec(), KeymapLayer::Manager));
+	}
+
+	// Handles the event, and the ones it brings about, until the app is settled
+	pub(super) fn send(&mut self, event: Event) {
+		let _guard = self.rt.enter();
+		self.app.dispatch(event);
+		self.settle();
+	}
+
+	// Handles the events emitted, until all of the tasks are done, and there's no event for a while
+	// after, where what's read of the files off the tasks is waited for with `until` instead
+	pub(super) fn settle(&mut self) {
+		let (app, start) = (&mut self.app, Instant::now());
+		self.rt.block_on(async {
+			loop {
+				match timeout(QUIET, app.recv()).await {
+					Ok(Some(event)) => _ = app.dispatch(event),
+					_ if app.cx().tasks.len() == 0 || start.elapsed() > DEADLINE => break,
+					_ => {}
+				}
+			}
+		});
+	}
+
+	// Handles the events emitted until `f` is true, for what takes longer than a while to come
+	#[track_caller]
+	pub(super) fn until(&mut self, f: impl Fn(&Ctx) -> bool) {
+		let (app, start) = (&mut self.app, Instant::now());
+		let done = self.rt.block_on(async {
+			while !f(app.cx()) {
+				if start.elapsed() > DEADLINE {
+					return false;
+				}
+				if let Ok(Some(event)) = timeout(QUIET, app.recv()).await {
+					app.dispatch(event);
+				}
+			}
+			true
+		});
+		assert!(done, "not done in {DEADLINE:?}");
+		self.settle();
+	}
+
+	#[inline]
+	pub(super) fn cx(&self) -> &Ctx { self.app.cx() }
+
+	// The names of the files in view, as they're sorted and filtered
+	pub(super) fn names(&self) -> Vec<String> {
+		self.cx().manager.current().files.iter().map(name).collect()
+	}
+
+	#[inline]
+	pub(super) fn cursor(&self) -> usize { self.cx().manager.current().cursor() }
+
+	#[inline]
+	pub(super) fn hovered(&self) -> Option<String> { self.cx().manager.hovered().map(name) }
+
+	// The names of the files selected, not the hovered one if there's none
+	pub(super) fn selected(&self) -> Vec<String> {
+		let files = &self.cx().manager.current().files;
+		files.selected(&BTreeSet::new(), false).into_iter().map(name).collect()
+	}
+
+	// What the app draws, a line for each row, without the spaces at the end
+	pub(super) fn screen(&mut self) -> String {
+		let cx = self.app.cx();
+		self.term.draw(|f| f.render_widget(Root::new(cx), f.size())).unwrap();
+
+		let buf = self.term.backend().buffer();
+		let rows = buf.content.chunks(buf.area.width as usize);
+		let lines = rows.map(|r| r.iter().map(|c| c.symbol.as_str()).collect::<String>());
+		lines.map(|l| l.trim_end().to_owned() + "\n").collect()
+	}
+}
+
+impl Drop for Headless {
+	fn drop(&mut self) { fs::remove_dir_all(&BOOT.cwd).ok(); }
+}
+
+#[inline]
+fn name(f: &File) -> String { f.name_display().unwrap_or_default().into_owned() }
+
+// The files made in a directory of the test, those ending with a `/` as directories, and the others
+// of the number of bytes
+fn fixture(name: &str, files: &[(&str, usize)]) -> shared::TestDir {
+	let dir = shared::TestDir::new(name);
+	for &(f, len) in files {
+		match f.strip_suffix('/') {
+			Some(d) => fs::create_dir_all(dir.join(d)).unwrap(),
+			None => {
+				fs::create_dir_all(dir.join(f).parent().unwrap()).unwrap();
+				fs::write(dir.join(f), "a".repeat(len)).unwrap();
+			}
+		}
+	}
+	dir
+}
+
+#[test]
+fn test_navigation() {
+	let dir = fixture("headless-navigation", &[("d/", 0), ("d/x", 1), ("a", 1), ("b", 1), ("c", 1)]);
+	let mut h = Headless::new(&dir);
+	assert_eq!(h.names(), ["d", "a", "b", "c"]);
+	assert_eq!((h.cursor(), h.hovered().as_deref()), (0, Some("d")));
+
+	h.keys("jj");
+	assert_eq!(h.hovered().as_deref(), Some("b"));
+	h.keys("G");
+	assert_eq!(h.cursor(), 3);
+	h.keys("gg");
+	assert_eq!(h.cursor(), 0);
+
+	// Into the directory, and back out onto it
+	h.keys("l");
+	h.until(|cx| cx.manager.cwd() == &Url::from(dir.join("d")));
+	assert_eq!(h.names(), ["x"]);
+	h.keys("h");
+	h.until(|cx| cx.manager.cwd() == &Url::from(&*dir));
+	assert_eq!(h.hovered().as_deref(), Some("d"));
+
+	// Selected, and drawn
+	h.keys("j<Space><Space>");
+	assert_eq!(h.selected(), ["a", "b"]);
+	assert!(h.screen().contains(" c"));
+}
+
+#[test]
+fn test_sorting_filtering() {
+	let dir = fixture("headless-sorting", &[("a.rs", 3), ("b.md", 1), ("c.rs", 2), ("d/", 0)]);
+	let mut h = Headless::new(&dir);
+
+	h.keys(",s");
+	h.until(|cx| cx.manager.active().sorter().by == config::manager::SortBy::Size);
+	assert_eq!(h.names(), ["d", "b.md", "c.rs", "a.rs"]);
+	h.call("sort alphabetical --reverse");
+	assert_eq!(h.names(), ["d", "c.rs", "b.md", "a.rs"]);
+
+	// Filtered as it's typed, and cleared with `<Esc>`
+	h.keys("f");
+	h.keys("\\.rs");
+	h.until(|cx| cx.manager.current().files.len() == 2);
+	assert_eq!(h.names(), ["c.rs", "a.rs"]);
+	h.keys("<Enter><Esc>");
+	h.until(|cx| cx.manager.current().files.len() == 4);
+}
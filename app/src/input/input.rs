@@ -2,7 +2,8 @@ use core::input::InputMode;
 use std::ops::Range;
 
 use ansi_to_tui::IntoText;
-use ratatui::{buffer::Buffer, layout::Rect, style::{Color, Style}, text::{Line, Text}, widgets::{Block, BorderType, Borders, Clear, Paragraph, Widget}};
+use config::{INPUT, THEME};
+use ratatui::{buffer::Buffer, layout::{Alignment, Rect}, style::{Color, Style}, text::{Line, Span, Text}, widgets::{block::{Position as TitlePosition, Title}, Block, BorderType, Borders, Clear, Paragraph, Widget}};
 use shared::Term;
 
 use crate::Ctx;
@@ -23,28 +24,35 @@ impl<'a> Widget for Input<'a> {
 		let value = if let Ok(v) = input.value_pretty() {
 			v.into_text().unwrap()
 		} else {
-			Text::from(input.value())
+			Text::from(input.lines())
 		};
 
+		let mut block = Block::new()
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.border_style(THEME.border.popup.get())
+			.title({
+				let mut line = Line::from(input.title());
+				line.patch_style(Style::new().fg(Color::White));
+				line
+			});
+
+		// The mode is told by the shape of the cursor too, but not every terminal changes it
+		if INPUT.vi_mode {
+			let mode = if input.mode() == InputMode::Insert { " INSERT " } else { " NORMAL " };
+			block = block.title(
+				Title::from(Span::styled(mode, Style::new().fg(Color::White)))
+					.alignment(Alignment::Right)
+					.position(TitlePosition::Bottom),
+			);
+		}
+
 		Clear.render(area, buf);
-		Paragraph::new(value)
-			.block(
-				Block::new()
-					.borders(Borders::ALL)
-					.border_type(BorderType::Rounded)
-					.border_style(Style::new().fg(Color::Blue))
-					.title({
-						let mut line = Line::from(input.title());
-						line.patch_style(Style::new().fg(Color::White));
-						line
-					}),
-			)
-			.style(Style::new().fg(Color::White))
-			.render(area, buf);
+		Paragraph::new(value).block(block).style(Style::new().fg(Color::White)).render(area, buf);
 
-		if let Some(Range { start, end }) = input.selected() {
+		for (row, Range { start, end }) in input.selected() {
 			let x = win.width.min(area.x + 1 + start);
-			let y = win.height.min(area.y + 1);
+			let y = win.height.min(area.y + 1 + row);
 
 			buf.set_style(
 				Rect { x, y, width: (end - start).min(win.width - x), height: 1.min(win.height - y) },
@@ -52,6 +60,24 @@ impl<'a> Widget for Input<'a> {
 			)
 		}
 
+		// Why the value can't be submitted right below the box, or else the hint about it
+		if let Some(invalid) = &input.invalid {
+			let y = area.bottom();
+			if y < win.height {
+				let area = Rect { y, height: 1, ..area };
+				Clear.render(area, buf);
+				Paragraph::new(format!(" {invalid}")).style(Style::new().fg(Color::Red)).render(area, buf);
+			}
+		} else if let Some((hint, warn)) = &input.hint {
+			let y = area.bottom();
+			if y < win.height {
+				let area = Rect { y, height: 1, ..area };
+				let color = if *warn { Color::Red } else { Color::DarkGray };
+				Clear.render(area, buf);
+				Paragraph::new(format!(" {hint}")).style(Style::new().fg(color)).render(area, buf);
+			}
+		}
+
 		let _ = match input.mode() {
 			InputMode::Insert => Term::set_cursor_bar(),
 			_ => Term::set_cursor_block(),
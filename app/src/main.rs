@@ -4,10 +4,13 @@ mod app;
 mod context;
 mod executor;
 mod header;
+#[cfg(test)]
+mod headless;
 mod help;
 mod input;
 mod logs;
 mod manager;
+mod picker;
 mod root;
 mod select;
 mod signals;
@@ -19,6 +22,7 @@ use app::*;
 use context::*;
 use executor::*;
 use logs::*;
+use picker::*;
 use root::*;
 use signals::*;
 
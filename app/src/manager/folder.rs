@@ -1,8 +1,9 @@
-use core::files::File;
+use core::{external::GitStatus, files::File};
 
-use config::{MANAGER, THEME};
+use config::{manager::Linemode, MANAGER, THEME};
 use ratatui::{buffer::Buffer, layout::Rect, style::{Color, Modifier, Style}, text::{Line, Span}, widgets::{List, ListItem, Widget}};
 use shared::short_path;
+use unicode_width::UnicodeWidthStr;
 
 use crate::Ctx;
 
@@ -12,11 +13,23 @@ pub(super) struct Folder<'a> {
 	is_preview:   bool,
 	is_selection: bool,
 	is_find:      bool,
+	is_label:     bool,
+	is_numbers:   bool,
+	is_linemode:  bool,
 }
 
 impl<'a> Folder<'a> {
 	pub(super) fn new(cx: &'a Ctx, folder: &'a core::manager::Folder) -> Self {
-		Self { cx, folder, is_preview: false, is_selection: false, is_find: false }
+		Self {
+			cx,
+			folder,
+			is_preview: false,
+			is_selection: false,
+			is_find: false,
+			is_label: false,
+			is_numbers: false,
+			is_linemode: false,
+		}
 	}
 
 	#[inline]
@@ -36,6 +49,24 @@ impl<'a> Folder<'a> {
 		self.is_find = state;
 		self
 	}
+
+	#[inline]
+	pub(super) fn with_label(mut self, state: bool) -> Self {
+		self.is_label = state;
+		self
+	}
+
+	#[inline]
+	pub(super) fn with_numbers(mut self, state: bool) -> Self {
+		self.is_numbers = state;
+		self
+	}
+
+	#[inline]
+	pub(super) fn with_linemode(mut self, state: bool) -> Self {
+		self.is_linemode = state;
+		self
+	}
 }
 
 impl<'a> Folder<'a> {
@@ -49,15 +80,56 @@ impl<'a> Folder<'a> {
 			.unwrap_or("")
 	}
 
-	#[inline]
+	// By the rules of `LS_COLORS` if it's on and any of them matches, or else of `[filetype]`
 	fn file_style(&self, file: &File) -> Style {
-		let mimetype = &self.cx.manager.mimetype;
-		THEME
-			.filetypes
-			.iter()
-			.find(|x| x.matches(file.url(), mimetype.get(file.url()), file.is_dir()))
-			.map(|x| x.style.get())
-			.unwrap_or_else(Style::new)
+		let ls = THEME.ls_colors.as_ref();
+		let style = ls.and_then(|l| l.style(file.url(), file.ls_kind(), file.is_link()));
+		let style = style.unwrap_or_else(|| {
+			let mimetype = &self.cx.manager.mimetype;
+			let style = THEME
+				.filetypes
+				.iter()
+				.find(|x| x.matches(file.url(), mimetype.get(file.url()), file.is_dir()))
+				.map(|x| x.style.get())
+				.unwrap_or_else(Style::new);
+			if file.is_orphan() { style.patch(THEME.symlink.orphan.get()) } else { style }
+		});
+
+		if file.is_ignored() { style.add_modifier(Modifier::DIM) } else { style }
+	}
+
+	// The size or the time of modification of the file, as `linemode` in the config says,
+	// where the size of a directory is only known once it's calculated, marked with `(...)` while
+	// it's still being calculated, after the size so far if there's one, and with why if it's cut
+	// short of some of the directory
+	fn linemode(&self, file: &File) -> Option<String> {
+		match MANAGER.linemode {
+			Linemode::None => None,
+			Linemode::Size => {
+				let size = self.folder.files.size(file.url());
+				if self.folder.files.size_pending(file) {
+					return Some(size.map_or("(...)".to_owned(), |s| MANAGER.readable_size(s) + " (...)"));
+				}
+				let size =
+					size.or_else(|| file.is_file().then(|| file.length())).map(|s| MANAGER.readable_size(s));
+				match self.folder.files.size_cut(file.url()).and_then(|w| w.mark()) {
+					Some(mark) => Some(format!("{} {mark}", size.unwrap_or_default())),
+					None => size,
+				}
+			}
+			Linemode::Mtime => file.meta().modified().map(|t| MANAGER.readable_date(t)),
+		}
+	}
+
+	fn git_style(status: GitStatus) -> Style {
+		let git = &THEME.git;
+		match status {
+			GitStatus::Ignored => git.ignored.get(),
+			GitStatus::Untracked => git.untracked.get(),
+			GitStatus::Staged => git.staged.get(),
+			GitStatus::Modified => git.modified.get(),
+			GitStatus::Conflicted => git.conflicted.get(),
+		}
 	}
 }
 
@@ -72,6 +144,7 @@ impl<'a> Widget for Folder<'a> {
 			self.folder.window()
 		};
 
+		let (cursor, width) = (self.folder.cursor(), self.folder.files.len().to_string().len());
 		let items: Vec<_> = window
 			.iter()
 			.enumerate()
@@ -101,15 +174,59 @@ impl<'a> Widget for Folder<'a> {
 
 				let mut spans = Vec::with_capacity(10);
 
-				spans.push(Span::raw(format!(" {} ", Self::icon(f))));
-				spans.push(Span::raw(short_path(f.url(), &self.folder.cwd)));
+				// How far each file is from the cursor, and the line number of the hovered one
+				if self.is_numbers {
+					let n = self.folder.offset() + i;
+					spans.push(if n == cursor {
+						Span::raw(format!("{:>width$} ", n + 1))
+					} else {
+						let dim = Style::new().add_modifier(Modifier::DIM);
+						Span::styled(format!("{:>width$} ", n.abs_diff(cursor)), dim)
+					});
+				}
+
+				// The files of the expanded directories are indented under them, with only their names
+				let depth = self.folder.files.depth(f.url());
+				if depth > 0 {
+					spans.push(Span::raw("  ".repeat(depth)));
+				}
+
+				// The label takes the place of the icon, so that the names stay where they are
+				let icon = format!(" {} ", Self::icon(f));
+				match self.cx.which.label(i).filter(|_| self.is_label) {
+					Some((label, typed)) => {
+						let style = THEME.selection.label.get();
+						spans.push(Span::styled(&label[..typed], style.add_modifier(Modifier::DIM)));
+						spans.push(Span::styled(&label[typed..], style));
+						spans.push(Span::raw(" ".repeat(icon.width().saturating_sub(label.len()))));
+					}
+					None => spans.push(Span::raw(icon)),
+				}
+				spans.push(Span::raw(if depth > 0 {
+					f.name_display().unwrap_or_default().into_owned()
+				} else {
+					short_path(f.url(), &self.folder.cwd)
+				}));
 
 				if let Some(link_to) = f.link_to() {
 					if MANAGER.show_symlink {
-						spans.push(Span::raw(format!(" -> {}", link_to.display())));
+						let style =
+							if f.is_orphan() { THEME.symlink.orphan.get() } else { THEME.symlink.target.get() };
+						spans.push(Span::styled(format!(" -> {}", link_to.display()), style));
 					}
 				}
 
+				// `+` for the ACLs, like `ls` does, and `@` for any other extended attributes
+				if let Some(names) = self.folder.files.xattrs(f.url()).filter(|n| !n.is_empty()) {
+					let acl = names.iter().any(|n| n.starts_with("system.posix_acl_"));
+					let dim = Style::new().add_modifier(Modifier::DIM);
+					spans.push(Span::styled(if acl { " +" } else { " @" }, dim));
+				}
+
+				if let Some(status) = self.folder.files.git(f.url()) {
+					spans.push(Span::styled(format!(" {}", status.badge()), Self::git_style(status)));
+				}
+
 				if let Some(idx) = active
 					.finder()
 					.filter(|&f| hovered && self.is_find && f.has_matched())
@@ -127,6 +244,14 @@ impl<'a> Widget for Folder<'a> {
 					));
 				}
 
+				// At the end of the line, if there's room left for it after the name
+				if let Some(s) = self.linemode(f).filter(|_| self.is_linemode) {
+					let used: usize = spans.iter().map(|s| s.width()).sum();
+					if let Some(pad) = (area.width as usize).checked_sub(used + s.width() + 1) {
+						spans.push(Span::raw(format!("{:pad$}{s} ", "")));
+					}
+				}
+
 				ListItem::new(Line::from(spans)).style(style)
 			})
 			.collect();
@@ -0,0 +1,29 @@
+use ratatui::{buffer::Buffer, layout::Rect, style::{Modifier, Style}, text::{Line, Span}, widgets::{Paragraph, Widget, Wrap}};
+
+use crate::Ctx;
+
+// The details of the hovered file, in place of its preview, with the names lined up on the left
+pub(super) struct Info<'a> {
+	cx: &'a Ctx,
+}
+
+impl<'a> Info<'a> {
+	pub(super) fn new(cx: &'a Ctx) -> Self { Self { cx } }
+}
+
+impl<'a> Widget for Info<'a> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let rows = self.cx.manager.info();
+		let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0) + 2;
+
+		let bold = Style::new().add_modifier(Modifier::BOLD);
+		let lines: Vec<_> = rows
+			.into_iter()
+			.map(|(name, value)| {
+				Line::from(vec![Span::styled(format!("{name:<width$}"), bold), Span::raw(value)])
+			})
+			.collect();
+
+		Paragraph::new(lines).wrap(Wrap { trim: false }).render(area, buf);
+	}
+}
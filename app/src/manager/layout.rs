@@ -1,7 +1,7 @@
-use config::MANAGER;
+use config::{manager::LayoutMode, MANAGER, THEME};
 use ratatui::{buffer::Buffer, layout::{self, Constraint, Direction, Rect}, widgets::{Block, Borders, Padding, Widget}};
 
-use super::{Folder, Preview};
+use super::{Folder, Info, Places, Preview};
 use crate::Ctx;
 
 pub(crate) struct Layout<'a> {
@@ -10,10 +10,71 @@ pub(crate) struct Layout<'a> {
 
 impl<'a> Layout<'a> {
 	pub(crate) fn new(cx: &'a Ctx) -> Self { Self { cx } }
+
+	// The current directories of the active tab, and of the other one, side by side
+	fn render_commander(self, area: Rect, buf: &mut Buffer) {
+		let manager = &self.cx.manager;
+		let chunks = layout::Layout::new()
+			.direction(Direction::Horizontal)
+			.constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)].as_ref())
+			.split(area);
+
+		// The panes keep the order of their tabs
+		let (idx, other) = (manager.tabs().idx(), manager.tabs().other());
+		let (active_area, other_area) = match manager.tabs().other_idx() {
+			Some(i) if i < idx => (chunks[1], chunks[0]),
+			_ => (chunks[0], chunks[1]),
+		};
+
+		let block = |style| {
+			Block::new()
+				.borders(Borders::LEFT | Borders::RIGHT)
+				.border_style(style)
+				.padding(Padding::new(1, 0, 0, 0))
+		};
+
+		let active = block(THEME.tab.pane_active.get());
+		Folder::new(self.cx, manager.current())
+			.with_selection(manager.active().mode().is_visual())
+			.with_find(manager.active().finder().is_some())
+			.with_label(self.cx.which.in_labels())
+			.with_numbers(manager.active().relative_numbers())
+			.with_linemode(true)
+			.render(active.inner(active_area), buf);
+		active.render(active_area, buf);
+
+		let inactive = block(THEME.tab.pane_inactive.get());
+		if let Some(other) = other {
+			Folder::new(self.cx, other.current())
+				.with_linemode(true)
+				.render(inactive.inner(other_area), buf);
+		}
+		inactive.render(other_area, buf);
+	}
 }
 
 impl<'a> Widget for Layout<'a> {
 	fn render(self, area: Rect, buf: &mut Buffer) {
+		// The places take their columns off the left of the panes, see `places_width` of the config
+		let width = MANAGER.layout.places_width();
+		let area = if width > 0 {
+			let chunks = layout::Layout::new()
+				.direction(Direction::Horizontal)
+				.constraints([Constraint::Length(width), Constraint::Min(0)].as_ref())
+				.split(area);
+
+			let block = Block::new().borders(Borders::RIGHT).padding(Padding::new(1, 1, 0, 0));
+			Places::new(self.cx).render(block.inner(chunks[0]), buf);
+			block.render(chunks[0], buf);
+			chunks[1]
+		} else {
+			area
+		};
+
+		if self.cx.manager.layout == LayoutMode::Commander {
+			return self.render_commander(area, buf);
+		}
+
 		let layout = &MANAGER.layout;
 		let manager = &self.cx.manager;
 
@@ -40,11 +101,21 @@ impl<'a> Widget for Layout<'a> {
 		Folder::new(self.cx, manager.current())
 			.with_selection(manager.active().mode().is_visual())
 			.with_find(manager.active().finder().is_some())
+			.with_label(self.cx.which.in_labels())
+			.with_numbers(manager.active().relative_numbers())
+			.with_linemode(true)
 			.render(chunks[1], buf);
 
-		// Preview
-		let block = Block::new().borders(Borders::LEFT).padding(Padding::new(0, 1, 0, 0));
-		Preview::new(self.cx).render(block.inner(chunks[2]), buf);
+		// Preview, with its border lit while it's focused
+		let mut block = Block::new().borders(Borders::LEFT).padding(Padding::new(0, 1, 0, 0));
+		if manager.active().preview().focused() {
+			block = block.border_style(THEME.tab.pane_active.get());
+		}
+		if manager.active().info() {
+			Info::new(self.cx).render(block.inner(chunks[2]), buf);
+		} else {
+			Preview::new(self.cx).render(block.inner(chunks[2]), buf);
+		}
 		block.render(chunks[2], buf);
 	}
 }
@@ -1,7 +1,11 @@
 mod folder;
+mod info;
 mod layout;
+mod places;
 mod preview;
 
 use folder::*;
+use info::*;
 pub(super) use layout::*;
+use places::*;
 use preview::*;
@@ -0,0 +1,44 @@
+use core::manager;
+
+use config::THEME;
+use ratatui::{buffer::Buffer, layout::Rect, style::Style, text::Line, widgets::{List, ListItem, Widget}};
+
+use crate::Ctx;
+
+// The directories pinned to the sidebar, numbered for `place_jump`, with the current one
+// highlighted and the ones gone dimmed
+pub(super) struct Places<'a> {
+	cx: &'a Ctx,
+}
+
+impl<'a> Places<'a> {
+	pub(super) fn new(cx: &'a Ctx) -> Self { Self { cx } }
+}
+
+impl<'a> Widget for Places<'a> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let cwd = self.cx.manager.cwd();
+		let items: Vec<_> = self
+			.cx
+			.manager
+			.places()
+			.list()
+			.into_iter()
+			.take(area.height as usize)
+			.enumerate()
+			.map(|(i, (path, exists))| {
+				let style = if path == &**cwd {
+					THEME.places.current.get()
+				} else if !exists {
+					THEME.places.missing.get()
+				} else {
+					Style::new()
+				};
+				let name = format!("{} {}", i + 1, manager::Places::name(path));
+				ListItem::new(Line::styled(name, style))
+			})
+			.collect();
+
+		List::new(items).render(area, buf);
+	}
+}
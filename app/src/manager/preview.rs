@@ -1,7 +1,8 @@
-use core::manager::PreviewData;
+use core::{highlighter_gutter, manager::PreviewData, HighlightLimit};
 
 use ansi_to_tui::IntoText;
-use ratatui::{buffer::Buffer, layout::Rect, widgets::{Paragraph, Widget}};
+use config::THEME;
+use ratatui::{buffer::Buffer, layout::{Alignment, Rect}, text::Line, widgets::{Paragraph, Widget}};
 
 use super::Folder;
 use crate::Ctx;
@@ -12,31 +13,118 @@ pub(super) struct Preview<'a> {
 
 impl<'a> Preview<'a> {
 	pub(super) fn new(cx: &'a Ctx) -> Self { Self { cx } }
+
+	// Marks the bottom right, over the text, once the highlighting is cut short
+	fn limit(limit: Option<HighlightLimit>, area: Rect, buf: &mut Buffer) {
+		let Some(limit) = limit.filter(|_| area.height > 0) else {
+			return;
+		};
+
+		let mark = Line::styled(format!(" {} ", limit.mark()), THEME.preview.placeholder.get());
+		let area = Rect { y: area.bottom() - 1, height: 1, ..area };
+		Paragraph::new(mark).alignment(Alignment::Right).render(area, buf);
+	}
+
+	// In the middle of the pane
+	fn centered(lines: Vec<Line>, area: Rect, buf: &mut Buffer) {
+		let height = lines.len() as u16;
+		let y = area.y + area.height.saturating_sub(height) / 2;
+		let area = Rect { y, height: height.min(area.height), ..area };
+		Paragraph::new(lines).alignment(Alignment::Center).render(area, buf);
+	}
+
+	// Overlays the background of the matches of a content search on the syntax colors
+	fn matches(preview: &core::manager::Preview, area: Rect, buf: &mut Buffer) {
+		let (Some(lock), Some(matches)) = (&preview.lock, &preview.matches) else {
+			return;
+		};
+		if lock.url != matches.url {
+			return;
+		}
+
+		for (i, m) in matches.lines.iter().enumerate() {
+			let Some(row) = m.line.checked_sub(lock.skip + 1).filter(|&r| r < area.height as usize) else {
+				continue;
+			};
+
+			let style = if i == matches.current {
+				THEME.preview.matched_current.get()
+			} else {
+				THEME.preview.matched.get()
+			};
+			for col in m.cols.iter().flat_map(|r| r.clone()).take_while(|&c| c < area.width as usize) {
+				buf.get_mut(area.x + col as u16, area.y + row as u16).set_style(style);
+			}
+		}
+	}
 }
 
 impl<'a> Widget for Preview<'a> {
 	fn render(self, area: Rect, buf: &mut Buffer) {
 		let manager = &self.cx.manager;
-		let Some(hovered) = manager.hovered().map(|h| h.url()) else {
+		let Some(previewed) = manager.active().previewed() else {
 			return;
 		};
 
 		let preview = manager.active().preview();
-		if !preview.same_path(hovered) {
+		if !preview.same_path(previewed) {
 			return;
 		}
 
-		match &preview.lock.as_ref().unwrap().data {
+		let lock = preview.lock.as_ref().unwrap();
+		match &lock.data {
 			PreviewData::Folder => {
-				if let Some(folder) = manager.active().history(hovered) {
-					Folder::new(self.cx, folder).with_preview(true).render(area, buf);
+				// Told apart from one still being read, by having been updated
+				match manager.active().history(previewed) {
+					Some(f) if f.files.is_empty() && f.files.version() > 0 => {
+						let line = Line::styled("(empty)", THEME.preview.placeholder.get());
+						Self::centered(vec![line], area, buf);
+					}
+					Some(f) => Folder::new(self.cx, f).with_preview(true).render(area, buf),
+					None => {}
 				}
 			}
-			PreviewData::Text(s) => {
+			PreviewData::Text(s) | PreviewData::Hex(s) => {
 				let p = Paragraph::new(s.as_bytes().into_text().unwrap());
 				p.render(area, buf);
 			}
+			PreviewData::Code(s, total, limit) => {
+				let text = s.as_bytes().into_text().unwrap();
+				if !manager.active().line_numbers() {
+					Paragraph::new(text).render(area, buf);
+					Self::matches(preview, area, buf);
+					return Self::limit(*limit, area, buf);
+				}
+
+				// Right-aligned in a gutter as wide as the largest line number in the file,
+				// and one number per line of the text rather than per row
+				let partial = !s.trim_end_matches("\x1b[0m").ends_with('\n');
+				let lines = s.matches('\n').count() + partial as usize;
+				let width = (lock.skip + lines).max(*total).to_string().len() as u16 + 1;
+				let gutter = (1..=lines)
+					.map(|i| Line::from(format!("{:>w$} ", lock.skip + i, w = width as usize - 1)))
+					.collect::<Vec<_>>();
+
+				let width = width.min(area.width);
+				Paragraph::new(gutter)
+					.style(highlighter_gutter())
+					.render(Rect { width, ..area }, buf);
+
+				let area = Rect { x: area.x + width, width: area.width - width, ..area };
+				Paragraph::new(text).render(area, buf);
+				Self::matches(preview, area, buf);
+				Self::limit(*limit, area, buf);
+			}
 			PreviewData::Image => {}
+			PreviewData::Placeholder(p) => {
+				let theme = &THEME.preview;
+				let style = if p.error { &theme.placeholder_error } else { &theme.placeholder };
+				let lines = vec![
+					Line::styled(p.message.as_str(), style.get()),
+					Line::styled(p.detail.as_str(), theme.placeholder.get()),
+				];
+				Self::centered(lines, area, buf);
+			}
 		}
 	}
 }
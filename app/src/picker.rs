@@ -0,0 +1,51 @@
+use std::{io::Write, process};
+
+use anyhow::Result;
+use shared::Url;
+
+// The stdout the files picked with `--pick` are printed to, while the terminal takes its place
+// for the rest of the output, so that `$(yazi --pick)` gets only them
+pub(super) struct Picker {
+	stdout: Box<dyn Write>,
+}
+
+impl Picker {
+	#[cfg(unix)]
+	pub(super) fn start() -> Result<Self> {
+		use std::{fs::{File, OpenOptions}, io, os::fd::{AsRawFd, FromRawFd}};
+
+		let fd = unsafe { libc::dup(libc::STDOUT_FILENO) };
+		if fd < 0 {
+			return Err(io::Error::last_os_error().into());
+		}
+		let stdout = unsafe { File::from_raw_fd(fd) };
+
+		let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+		if unsafe { libc::dup2(tty.as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+			return Err(io::Error::last_os_error().into());
+		}
+		Ok(Self { stdout: Box::new(stdout) })
+	}
+
+	#[cfg(not(unix))]
+	pub(super) fn start() -> Result<Self> { Ok(Self { stdout: Box::new(std::io::stdout()) }) }
+
+	// Prints the files picked one per line, and exits with 0, or with 1 if none are
+	pub(super) fn finish(mut self, picked: &[Url]) -> ! {
+		for url in picked {
+			#[cfg(unix)]
+			{
+				use std::os::unix::ffi::OsStrExt;
+				self.stdout.write_all(url.as_os_str().as_bytes()).ok();
+			}
+			#[cfg(not(unix))]
+			{
+				self.stdout.write_all(url.to_string_lossy().as_bytes()).ok();
+			}
+			self.stdout.write_all(b"\n").ok();
+		}
+
+		self.stdout.flush().ok();
+		process::exit(if picked.is_empty() { 1 } else { 0 })
+	}
+}
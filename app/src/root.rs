@@ -38,7 +38,7 @@ impl<'a> Widget for Root<'a> {
 			help::Layout::new(self.cx).render(area, buf);
 		}
 
-		if self.cx.which.visible {
+		if self.cx.which.popup() && !self.cx.which.in_labels() {
 			which::Which::new(self.cx).render(area, buf);
 		}
 	}
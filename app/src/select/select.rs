@@ -1,4 +1,5 @@
-use ratatui::{buffer::Buffer, layout::Rect, style::{Color, Style}, widgets::{Block, BorderType, Borders, Clear, List, ListItem, Widget}};
+use config::THEME;
+use ratatui::{buffer::Buffer, layout::{Alignment, Rect}, style::{Color, Modifier, Style}, text::{Line, Span}, widgets::{block::{Position as TitlePosition, Title}, Block, BorderType, Borders, Clear, List, ListItem, Scrollbar, ScrollbarOrientation, ScrollbarState, Widget}};
 
 use crate::Ctx;
 
@@ -17,26 +18,56 @@ impl<'a> Widget for Select<'a> {
 
 		let items = select
 			.window()
-			.iter()
 			.enumerate()
-			.map(|(i, v)| {
-				if i != select.rel_cursor() {
-					return ListItem::new(format!("  {v}"));
+			.map(|(i, (v, matched))| {
+				let current = i == select.rel_cursor();
+				let mut spans = vec![Span::raw(if current { " " } else { "  " })];
+				for (j, c) in v.chars().enumerate() {
+					let style = if matched.contains(&j) {
+						Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+					} else {
+						Style::new()
+					};
+					spans.push(Span::styled(c.to_string(), style));
 				}
 
-				ListItem::new(format!(" {v}")).style(Style::new().fg(Color::Magenta))
+				let item = ListItem::new(Line::from(spans));
+				if current { item.style(Style::new().fg(Color::Magenta)) } else { item }
 			})
 			.collect::<Vec<_>>();
 
+		let mut block = Block::new()
+			.title(select.title())
+			.borders(Borders::ALL)
+			.border_type(BorderType::Rounded)
+			.border_style(THEME.border.popup.get());
+
+		// How deep the list is, when it's longer than what's in view
+		let (len, limit) = (select.len(), select.limit());
+		if len > limit {
+			block = block.title(
+				Title::from(format!(" {}/{len} ", select.cursor() + 1))
+					.alignment(Alignment::Right)
+					.position(TitlePosition::Bottom),
+			);
+		}
+
 		Clear.render(area, buf);
-		List::new(items)
-			.block(
-				Block::new()
-					.title(select.title())
-					.borders(Borders::ALL)
-					.border_type(BorderType::Rounded)
-					.border_style(Style::new().fg(Color::Blue)),
-			)
-			.render(area, buf);
+		List::new(items).block(block).render(area, buf);
+
+		if len > limit && area.height > 2 {
+			let track = Rect { x: area.right() - 1, y: area.y + 1, width: 1, height: area.height - 2 };
+			let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+				.begin_symbol(None)
+				.end_symbol(None)
+				.track_symbol(None)
+				.thumb_style(THEME.border.popup.get());
+
+			let mut state = ScrollbarState::default()
+				.content_length(len as u16)
+				.viewport_content_length(limit as u16)
+				.position(select.cursor() as u16);
+			ratatui::widgets::StatefulWidget::render(scrollbar, track, buf, &mut state);
+		}
 	}
 }
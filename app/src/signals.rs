@@ -1,7 +1,7 @@
 use core::Event;
 
 use anyhow::Result;
-use crossterm::event::{Event as CrosstermEvent, EventStream, KeyEvent, KeyEventKind};
+use crossterm::event::{Event as CrosstermEvent, EventStream, KeyEvent, KeyEventKind, MouseEventKind};
 use futures::StreamExt;
 use tokio::{select, sync::{mpsc::{self, UnboundedReceiver, UnboundedSender}, oneshot}, task::JoinHandle};
 
@@ -28,6 +28,14 @@ impl Signals {
 		Ok(signals)
 	}
 
+	// Only the events emitted, none of the terminal or of the system, for the app driven by the tests
+	#[cfg(test)]
+	pub(super) fn headless() -> Self {
+		let (tx, rx) = mpsc::unbounded_channel();
+		Event::init(tx.clone());
+		Self { tx, rx, term_stop_tx: None, term_stop_rx: None }
+	}
+
 	#[inline]
 	pub(super) async fn recv(&mut self) -> Option<Event> { self.rx.recv().await }
 
@@ -64,7 +72,7 @@ impl Signals {
 			while let Some(signal) = signals.next().await {
 				match signal {
 					SIGHUP | SIGTERM | SIGQUIT | SIGINT => {
-						if tx.send(Event::Quit).is_err() {
+						if tx.send(Event::Quit(false)).is_err() {
 							break;
 						}
 					}
@@ -92,6 +100,7 @@ impl Signals {
 							// We need to check key event kind;
 							// otherwise event will be dispatched twice.
 							CrosstermEvent::Key(key @ KeyEvent { kind: KeyEventKind::Press, .. }) => Event::Key(key),
+							CrosstermEvent::Mouse(m) if m.kind != MouseEventKind::Moved => Event::Mouse(m),
 							CrosstermEvent::Paste(str) => Event::Paste(str),
 							CrosstermEvent::Resize(cols, rows) => Event::Resize(cols, rows),
 							_ => continue,
@@ -0,0 +1,117 @@
+use config::{manager::{StatusToken, StatusVar}, MANAGER, THEME};
+use ratatui::{buffer::Buffer, layout::Rect, text::Span, widgets::Widget};
+use shared::{readable_path, MIME_DIR};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use super::Right;
+use crate::Ctx;
+
+// The status bar of the `status_line` template, in place of the built-in one
+pub(super) struct Custom<'a> {
+	cx:    &'a Ctx,
+	parts: &'a [Vec<StatusToken>; 3],
+}
+
+impl<'a> Custom<'a> {
+	pub(super) fn new(cx: &'a Ctx, parts: &'a [Vec<StatusToken>; 3]) -> Self { Self { cx, parts } }
+
+	fn value(&self, var: StatusVar) -> String {
+		let folder = self.cx.manager.current();
+		let tally = folder.files.tally();
+		match var {
+			StatusVar::Mode => self.cx.manager.active().mode().to_string(),
+			StatusVar::Path => readable_path(&folder.cwd),
+			StatusVar::Name => {
+				folder.hovered.as_ref().and_then(|h| h.name_display()).unwrap_or_default().into_owned()
+			}
+			StatusVar::Mime => match &folder.hovered {
+				Some(h) if h.is_dir() => MIME_DIR.to_owned(),
+				Some(h) => self.cx.manager.mimetype.get(h.url()).cloned().unwrap_or_default(),
+				None => String::new(),
+			},
+			StatusVar::Count => folder.files.len().to_string(),
+			StatusVar::Position => {
+				let length = folder.files.len();
+				format!("{}/{length}", (folder.cursor() + 1).min(length))
+			}
+			StatusVar::Selected if tally.is_empty() => String::new(),
+			StatusVar::Selected => (tally.files + tally.dirs).to_string(),
+			StatusVar::SelectedSize if tally.is_empty() => String::new(),
+			StatusVar::SelectedSize => MANAGER.readable_size(tally.bytes).trim().to_owned(),
+			StatusVar::Sort => Right::sort_abbr(self.cx),
+			StatusVar::Filter => folder.files.filter().map(|f| f.raw().to_owned()).unwrap_or_default(),
+			StatusVar::Branch => folder.branch.clone().unwrap_or_default(),
+			StatusVar::Free | StatusVar::FreePercent | StatusVar::Total => {
+				let Some((free, total)) = self.cx.manager.space() else {
+					return String::new();
+				};
+				match var {
+					StatusVar::Free => MANAGER.readable_size(free).trim().to_owned(),
+					StatusVar::Total => MANAGER.readable_size(total).trim().to_owned(),
+					_ => format!("{}%", if total == 0 { 0 } else { free * 100 / total }),
+				}
+			}
+			#[cfg(not(target_os = "windows"))]
+			StatusVar::Perm => {
+				let mode = folder.hovered.as_ref().map(|h| h.meta().mode());
+				mode.map(shared::file_mode).unwrap_or_default()
+			}
+			#[cfg(target_os = "windows")]
+			StatusVar::Perm => String::new(),
+		}
+	}
+
+	fn text(&self, part: &[StatusToken]) -> String {
+		part.iter().fold(String::new(), |mut s, t| {
+			match t {
+				StatusToken::Text(t) => s.push_str(t),
+				StatusToken::Var(v) => s.push_str(&self.value(*v)),
+			}
+			s
+		})
+	}
+
+	// Cut short to `width` with a `…` at the end, if it's wider than that
+	fn truncate(s: String, width: usize) -> String {
+		if s.width() <= width {
+			return s;
+		} else if width == 0 {
+			return String::new();
+		}
+
+		let mut out = String::with_capacity(width);
+		let mut w = 0;
+		for c in s.chars() {
+			w += c.width().unwrap_or(0);
+			if w >= width {
+				break;
+			}
+			out.push(c);
+		}
+		out.push('…');
+		out
+	}
+}
+
+impl Widget for Custom<'_> {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let style = self.cx.manager.active().mode().color(&THEME.status.primary).fg();
+		let [left, center, right] = self.parts;
+		let width = area.width as usize;
+
+		// The right is cut short last, then the left, and the center first
+		let right = Self::truncate(self.text(right), width);
+		let left = Self::truncate(self.text(left), width - right.width());
+		let room = width - left.width() - right.width();
+		let center = Self::truncate(self.text(center), room);
+
+		// In the middle if there's room for it to be, otherwise as close to it as can be
+		let x = (width - center.width()) / 2;
+		let x = x.clamp(left.width(), width - right.width() - center.width());
+
+		buf.set_span(area.x, area.y, &Span::styled(left, style), area.width);
+		buf.set_span(area.x + x as u16, area.y, &Span::styled(center, style), area.width);
+		let x = area.x + (width - right.width()) as u16;
+		buf.set_span(x, area.y, &Span::styled(right, style), area.width);
+	}
+}
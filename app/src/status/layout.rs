@@ -1,6 +1,7 @@
+use config::MANAGER;
 use ratatui::{buffer::Buffer, layout::{self, Constraint, Direction, Rect}, widgets::Widget};
 
-use super::{Left, Right};
+use super::{Custom, Left, Right};
 use crate::Ctx;
 
 pub(crate) struct Layout<'a> {
@@ -13,6 +14,10 @@ impl<'a> Layout<'a> {
 
 impl<'a> Widget for Layout<'a> {
 	fn render(self, area: Rect, buf: &mut Buffer) {
+		if let Some(parts) = MANAGER.status_line.parts() {
+			return Custom::new(self.cx, parts).render(area, buf);
+		}
+
 		let chunks = layout::Layout::new()
 			.direction(Direction::Horizontal)
 			.constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
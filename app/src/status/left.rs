@@ -1,6 +1,5 @@
-use config::THEME;
+use config::{MANAGER, THEME};
 use ratatui::{buffer::Buffer, layout::Rect, style::Modifier, text::{Line, Span}, widgets::{Paragraph, Widget}};
-use shared::readable_size;
 
 use crate::Ctx;
 
@@ -38,14 +37,51 @@ impl<'a> Widget for Left<'a> {
 			{
 				let size = if h.is_dir() { folder.files.size(h.url()) } else { None };
 				spans.push(Span::styled(
-					format!(" {} ", readable_size(size.unwrap_or(h.length()))),
+					format!(" {} ", MANAGER.readable_size(size.unwrap_or(h.length())).trim()),
 					body.bg().fg(**primary),
 				));
 				spans.push(Span::styled(&separator.closing, body.fg()));
 			}
 
-			// Filename
-			spans.push(Span::raw(format!(" {} ", h.name_display().unwrap())));
+			// Filename, or the report of the last command for a while
+			if self.cx.manager.message().is_none() {
+				spans.push(Span::raw(format!(" {} ", h.name_display().unwrap())));
+			}
+		}
+
+		// How many are selected, and their total size
+		let tally = folder.files.tally();
+		if !tally.is_empty() {
+			let plural = |n: usize, s: &str| format!("{n} {s}{}", if n == 1 { "" } else { "s" });
+			let mut parts = Vec::with_capacity(3);
+			if tally.files > 0 {
+				parts.push(plural(tally.files, "file"));
+			}
+			if tally.dirs > 0 {
+				parts.push(plural(tally.dirs, "dir"));
+			}
+			parts.push(MANAGER.readable_size(tally.bytes).trim().to_owned());
+			spans.push(Span::styled(format!(" {} ", parts.join(", ")), primary.fg()));
+		}
+
+		// The yanked files to paste, out of the sets of them remembered
+		let yanked = self.cx.manager.yanked();
+		if let Some(set) = yanked.active() {
+			let what = if set.moved { "moved" } else if set.cut { "cut" } else { "copied" };
+			let (idx, len) = yanked.position();
+			let color = mode.color(if set.cut { &THEME.status.danger } else { &THEME.status.info });
+			spans.push(Span::styled(format!(" {} {what} [{idx}/{len}] ", set.urls.len()), color.fg()));
+		}
+
+		// The file pinned to the preview
+		if let Some(pin) = self.cx.manager.active().preview().pin() {
+			let name = pin.url.file_name().unwrap_or_default().to_string_lossy();
+			spans.push(Span::styled(format!(" pinned {name} "), mode.color(&THEME.status.warning).fg()));
+		}
+
+		if let Some((text, error)) = self.cx.manager.message() {
+			let color = mode.color(if error { &THEME.status.danger } else { &THEME.status.success });
+			spans.push(Span::styled(format!(" {text} "), color.fg()));
 		}
 
 		Paragraph::new(Line::from(spans)).render(area, buf);
@@ -1,8 +1,10 @@
+mod custom;
 mod layout;
 mod left;
 mod progress;
 mod right;
 
+use custom::*;
 pub(super) use layout::*;
 use left::*;
 use progress::*;
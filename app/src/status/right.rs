@@ -1,4 +1,4 @@
-use config::THEME;
+use config::{manager::SortBy, THEME};
 use ratatui::{buffer::Buffer, layout::{Alignment, Rect}, text::{Line, Span}, widgets::{Paragraph, Widget}};
 
 use super::Progress;
@@ -32,6 +32,54 @@ impl<'a> Right<'a> {
 			.collect()
 	}
 
+	// The order the files are sorted in, and the filter with how many of them it matches, in short
+	fn sorting(&self) -> Vec<Span> {
+		// Colors
+		let mode = self.cx.manager.active().mode();
+		let tertiary = mode.color(&THEME.status.tertiary);
+		let warning = mode.color(&THEME.status.warning);
+
+		let short = |raw: &str| match raw.char_indices().nth(12) {
+			Some((i, _)) => format!("{}…", &raw[..i]),
+			None => raw.to_owned(),
+		};
+
+		let files = &self.cx.manager.current().files;
+		let mut spans = vec![Span::styled(format!(" {}", Self::sort_abbr(self.cx)), tertiary.fg())];
+		let filters: Vec<_> = [
+			files.filter().map(|f| format!("/{}", short(f.raw()))),
+			files.quick().map(|q| short(q.raw())),
+		]
+		.into_iter()
+		.flatten()
+		.collect();
+		if !filters.is_empty() {
+			let count = files.len();
+			spans.push(Span::styled(format!(" {} ({count})", filters.join(" ")), warning.fg()));
+		}
+		spans
+	}
+
+	// The order the files are sorted in, like `mtime↓`, with ` Aa` if it's case-sensitive
+	pub(super) fn sort_abbr(cx: &Ctx) -> String {
+		let sorter = cx.manager.active().sorter();
+		let by = match sorter.by {
+			SortBy::Alphabetical => "alpha",
+			SortBy::Created => "ctime",
+			SortBy::Modified => "mtime",
+			SortBy::Accessed => "atime",
+			SortBy::Extension => "ext",
+			SortBy::Natural => "nat",
+			SortBy::Size => "size",
+			SortBy::Children => "count",
+			SortBy::Random => "rand",
+			SortBy::External => "cmd",
+		};
+		let arrow = if sorter.reverse { "↓" } else { "↑" };
+		let case = if sorter.sensitive { " Aa" } else { "" };
+		format!("{by}{arrow}{case}")
+	}
+
 	fn position(&self) -> Vec<Span> {
 		// Colors
 		let mode = self.cx.manager.active().mode();
@@ -70,12 +118,19 @@ impl Widget for Right<'_> {
 		// Permissions
 		#[cfg(not(target_os = "windows"))]
 		if let Some(h) = &manager.hovered {
-			use std::os::unix::prelude::PermissionsExt;
-			spans.extend(self.permissions(&shared::file_mode(h.meta().permissions().mode())))
+			spans.extend(self.permissions(&shared::file_mode(h.meta().mode())))
+		}
+
+		// Sorting and filter, left out if there's no room for them
+		let sorting = self.sorting();
+		let position = self.position();
+		let width = |s: &[Span]| s.iter().map(|s| s.width()).sum::<usize>();
+		if width(&spans) + width(&sorting) + width(&position) <= area.width as usize {
+			spans.extend(sorting);
 		}
 
 		// Position
-		spans.extend(self.position());
+		spans.extend(position);
 
 		// Progress
 		let line = Line::from(spans);
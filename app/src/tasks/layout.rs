@@ -1,6 +1,10 @@
-use core::tasks::TASKS_PERCENT;
+use core::tasks::{TaskState, TaskSummary, TaskViewer, TASKS_PERCENT};
 
-use ratatui::{buffer::Buffer, layout::{self, Alignment, Constraint, Direction, Rect}, style::{Color, Modifier, Style}, widgets::{Block, BorderType, Borders, List, ListItem, Padding, Widget}};
+use ansi_to_tui::IntoText;
+use config::THEME;
+use ratatui::{buffer::Buffer, layout::{self, Alignment, Constraint, Direction, Rect}, style::{Color, Modifier, Style}, widgets::{Block, BorderType, Borders, List, ListItem, Padding, Paragraph, Widget}};
+
+use shared::{readable_duration, readable_size};
 
 use super::Clear;
 use crate::Ctx;
@@ -39,6 +43,34 @@ impl<'a> Layout<'a> {
 
 		chunk
 	}
+
+	// The percentage is indeterminate until all the files are found, e.g. a directory
+	// that's still being walked, the throughput and ETA are shown as soon as it's known
+	fn progress(v: &TaskSummary) -> String {
+		if v.todo == 0 {
+			return String::new();
+		}
+
+		let mut s = match v.percent {
+			Some(p) => format!(" — {p}%"),
+			None => format!(" — {} of {}+", readable_size(v.done), readable_size(v.todo)),
+		};
+		if v.speed > 0 {
+			s.push_str(&format!(", {}/s", readable_size(v.speed)));
+		}
+		if let (Some(_), Some(eta)) = (v.percent, v.eta) {
+			s.push_str(&format!(", {} left", readable_duration(eta)));
+		}
+		s
+	}
+
+	// The lines of the output in view, rendered as the text previews are, with the colors in them
+	fn viewer(viewer: &TaskViewer, area: Rect, buf: &mut Buffer) {
+		let s = viewer.window(area.height as usize).join("\n");
+		if let Ok(text) = s.as_bytes().into_text() {
+			Paragraph::new(text).render(area, buf);
+		}
+	}
 }
 
 impl<'a> Widget for Layout<'a> {
@@ -46,26 +78,63 @@ impl<'a> Widget for Layout<'a> {
 		let area = Self::area(area);
 
 		Clear.render(area, buf);
+		let [total, file, precache] = self.cx.tasks.concurrencies();
+		let title = match &self.cx.tasks.viewer {
+			Some(viewer) => viewer.title.clone(),
+			None => format!("Tasks (at most {total} at once, {file} file, {precache} precache)"),
+		};
 		let block = Block::new()
-			.title("Tasks")
+			.title(title)
 			.title_alignment(Alignment::Center)
 			.padding(Padding::new(0, 0, 1, 1))
 			.borders(Borders::ALL)
 			.border_type(BorderType::Rounded)
-			.border_style(Style::new().fg(Color::Rgb(128, 174, 250)));
+			.border_style(THEME.border.popup.get());
 		block.clone().render(area, buf);
 
+		if let Some(viewer) = &self.cx.tasks.viewer {
+			return Self::viewer(viewer, block.inner(area), buf);
+		}
+
 		let tasks = &self.cx.tasks;
 		let items = tasks
 			.paginate()
 			.iter()
 			.enumerate()
 			.map(|(i, v)| {
-				let mut item = ListItem::new(v.name.clone());
+				let mut name = v.name.clone();
+				if v.verified > 0 {
+					name.push_str(&format!(" (verified {} files, {} bytes hashed)", v.verified, v.hashed));
+				}
+				let mut style = Style::new();
+				match v.state {
+					TaskState::Queued => {
+						name.push_str(" (queued)");
+						style = style.add_modifier(Modifier::DIM);
+					}
+					TaskState::Running => name.push_str(&Self::progress(v)),
+					TaskState::Paused => {
+						name.push_str(&Self::progress(v));
+						name.push_str(" (paused)");
+						style = style.add_modifier(Modifier::DIM);
+					}
+					TaskState::Failed => {
+						name.push_str(&format!(" (failed {} of {})", v.failed, v.found));
+						style = style.fg(Color::Red);
+					}
+					TaskState::Completed => {
+						name.push_str(" (completed)");
+						style = style.fg(Color::Green).add_modifier(Modifier::DIM);
+					}
+					TaskState::Canceled => {
+						name.push_str(" (canceled)");
+						style = style.fg(Color::Yellow).add_modifier(Modifier::DIM);
+					}
+				}
 				if i == tasks.cursor {
-					item = item.style(Style::new().add_modifier(Modifier::UNDERLINED));
+					style = style.add_modifier(Modifier::UNDERLINED);
 				}
-				item
+				ListItem::new(name).style(style)
 			})
 			.collect::<Vec<_>>();
 
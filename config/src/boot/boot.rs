@@ -1,17 +1,41 @@
-use std::{env, fs, path::PathBuf, process};
+use std::{env, fs, io, path::PathBuf, process};
 
-use clap::{command, Parser};
-use shared::expand_path;
+use clap::{command, Parser, ValueEnum};
+use shared::{expand_path, nearest_existing};
 
 use crate::{Xdg, PREVIEW};
 
 #[derive(Debug)]
 pub struct Boot {
 	pub cwd:       PathBuf,
+	pub cwd_given: bool,
+	pub restore:   bool,
 	pub state_dir: PathBuf,
+	// The file to hover once started, or the path of which the nearest existing part is gone to
+	pub reveal:    Option<PathBuf>,
 
 	pub cwd_file:     Option<PathBuf>,
 	pub chooser_file: Option<PathBuf>,
+	pub pick:         Option<Pick>,
+}
+
+// What can be picked with `--pick`, the files, the directories, or either of them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Pick {
+	Any,
+	Files,
+	Dirs,
+}
+
+impl Pick {
+	#[inline]
+	pub fn allows(self, is_dir: bool) -> bool {
+		match self {
+			Self::Any => true,
+			Self::Files => !is_dir,
+			Self::Dirs => is_dir,
+		}
+	}
 }
 
 #[derive(Debug, Parser)]
@@ -22,12 +46,22 @@ struct Args {
 	#[arg(index = 1)]
 	cwd: Option<PathBuf>,
 
-	/// Write the cwd on exit to this file
+	/// Open the directory of this file with it hovered, or `-` to read it from stdin
+	#[arg(long)]
+	reveal:       Option<PathBuf>,
+	/// Write the cwd on exit to this file, unless quit with `quit --cancel`
 	#[arg(long)]
 	cwd_file:     Option<PathBuf>,
-	/// Write the selected files on open emitted by the chooser mode
+	/// Write the files opened to this file instead, one per line, and exit, for picking them
 	#[arg(long)]
 	chooser_file: Option<PathBuf>,
+	/// Pick files to print to stdout on exit, exiting with 1 if none are
+	#[arg(long, value_name = "KIND", num_args = 0..=1, default_missing_value = "any")]
+	pick:         Option<Pick>,
+
+	/// Restore the tabs left open on the last exit, with the files hovered, selected, and so on
+	#[arg(long, action)]
+	restore: bool,
 
 	/// Clear the cache directory
 	#[arg(long, action)]
@@ -38,22 +72,43 @@ impl Default for Boot {
 	fn default() -> Self {
 		let args = Args::parse();
 
-		let cwd = args.cwd.map(expand_path).filter(|p| p.is_dir()).or_else(|| env::current_dir().ok());
+		// A file given as the cwd is revealed
+		let reveal = match args.reveal {
+			Some(p) if p.as_os_str() == "-" => {
+				let mut s = String::new();
+				io::stdin().read_line(&mut s).ok();
+				Some(PathBuf::from(s.trim_end_matches(['\n', '\r'])))
+			}
+			Some(p) => Some(p),
+			None => args.cwd.clone().filter(|p| expand_path(p).is_file()),
+		};
+		let reveal = reveal.map(expand_path).filter(|p| !p.as_os_str().is_empty()).map(|p| {
+			env::current_dir().map(|c| c.join(&p)).unwrap_or(p)
+		});
+
+		let cwd_given = args.cwd.is_some() || reveal.is_some();
+		let cwd = match &reveal {
+			Some(p) => match nearest_existing(p).0 {
+				p if p.is_dir() => Some(p),
+				p => p.parent().map(|p| p.to_owned()),
+			},
+			None => args.cwd.map(expand_path).filter(|p| p.is_dir()),
+		};
+		let cwd = cwd.or_else(|| env::current_dir().ok());
 
 		let boot = Self {
 			cwd:       cwd.unwrap_or("/".into()),
+			cwd_given,
+			restore: args.restore,
 			state_dir: Xdg::state_dir().unwrap(),
+			reveal,
 
 			cwd_file:     args.cwd_file,
 			chooser_file: args.chooser_file,
+			pick:         args.pick,
 		};
 
-		if !boot.state_dir.is_dir() {
-			fs::create_dir_all(&boot.state_dir).unwrap();
-		}
-		if !PREVIEW.cache_dir.is_dir() {
-			fs::create_dir(&PREVIEW.cache_dir).unwrap();
-		}
+		boot.make_dirs();
 
 		if args.clear_cache {
 			if PREVIEW.cache_dir == Xdg::cache_dir() {
@@ -71,3 +126,32 @@ impl Default for Boot {
 		boot
 	}
 }
+
+impl Boot {
+	// Starting in `cwd` as if it's given, with none of the arguments read, and the state kept in
+	// `state_dir`
+	pub(crate) fn bare(cwd: PathBuf, state_dir: PathBuf) -> Self {
+		let boot = Self {
+			cwd,
+			cwd_given: true,
+			restore: false,
+			state_dir,
+			reveal: None,
+
+			cwd_file:     None,
+			chooser_file: None,
+			pick:         None,
+		};
+		boot.make_dirs();
+		boot
+	}
+
+	fn make_dirs(&self) {
+		if !self.state_dir.is_dir() {
+			fs::create_dir_all(&self.state_dir).unwrap();
+		}
+		if !PREVIEW.cache_dir.is_dir() {
+			fs::create_dir(&PREVIEW.cache_dir).unwrap();
+		}
+	}
+}
@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::MERGED_YAZI;
+
+// The shell commands run for what happens in the manager, with the files as their arguments
+#[derive(Debug)]
+pub struct Hooks {
+	pub enter:   Vec<String>,
+	pub open:    Vec<String>,
+	pub select:  Vec<String>,
+	pub timeout: Duration,
+}
+
+impl Default for Hooks {
+	fn default() -> Self {
+		#[derive(Deserialize)]
+		struct Outer {
+			hooks: Shadow,
+		}
+		#[derive(Deserialize)]
+		struct Shadow {
+			enter:   Vec<String>,
+			open:    Vec<String>,
+			select:  Vec<String>,
+			timeout: u64,
+		}
+
+		let hooks = toml::from_str::<Outer>(&MERGED_YAZI).unwrap().hooks;
+		Self {
+			enter:   hooks.enter,
+			open:    hooks.open,
+			select:  hooks.select,
+			timeout: Duration::from_millis(hooks.timeout),
+		}
+	}
+}
@@ -0,0 +1,3 @@
+mod hooks;
+
+pub use hooks::*;
@@ -0,0 +1,40 @@
+use serde::Deserialize;
+
+use crate::MERGED_YAZI;
+
+#[derive(Debug)]
+pub struct Input {
+	pub vi_mode:         bool,
+	pub history_size:    usize,
+	pub history_exclude: Vec<String>,
+}
+
+impl Default for Input {
+	fn default() -> Self {
+		#[derive(Deserialize)]
+		struct Outer {
+			input: Shadow,
+		}
+		#[derive(Deserialize)]
+		struct Shadow {
+			vi_mode:         bool,
+			history_size:    usize,
+			history_exclude: Vec<String>,
+		}
+
+		let input = toml::from_str::<Outer>(&MERGED_YAZI).unwrap().input;
+		Self {
+			vi_mode:         input.vi_mode,
+			history_size:    input.history_size,
+			history_exclude: input.history_exclude,
+		}
+	}
+}
+
+impl Input {
+	// Whether the values submitted to the prompts of `category` are remembered
+	#[inline]
+	pub fn remembers(&self, category: &str) -> bool {
+		self.history_size > 0 && !self.history_exclude.iter().any(|c| c == category)
+	}
+}
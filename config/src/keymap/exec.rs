@@ -101,6 +101,12 @@ impl Exec {
 	#[inline]
 	pub fn vec(self) -> Vec<Self> { vec![self] }
 
+	#[inline]
+	pub fn with(mut self, name: &str, value: impl ToString) -> Self {
+		self.named.insert(name.to_string(), value.to_string());
+		self
+	}
+
 	#[inline]
 	pub fn with_bool(mut self, name: &str, state: bool) -> Self {
 		if state {
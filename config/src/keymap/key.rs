@@ -24,6 +24,11 @@ impl Key {
 	pub fn is_enter(&self) -> bool {
 		matches!(self, Key { code: KeyCode::Enter, shift: false, ctrl: false, alt: false })
 	}
+
+	#[inline]
+	pub fn is_esc(&self) -> bool {
+		matches!(self, Key { code: KeyCode::Esc, shift: false, ctrl: false, alt: false })
+	}
 }
 
 impl Default for Key {
@@ -34,15 +39,36 @@ impl From<KeyEvent> for Key {
 	fn from(value: KeyEvent) -> Self {
 		let shift = matches!(value.code, KeyCode::Char(c) if c.is_ascii_uppercase());
 
+		// Shift+Tab comes as a key of its own, taken as `<S-Tab>` here
+		let (code, back) = match value.code {
+			KeyCode::BackTab => (KeyCode::Tab, true),
+			code => (code, false),
+		};
+
 		Self {
-			code:  value.code,
-			shift: shift || value.modifiers.contains(KeyModifiers::SHIFT),
+			code,
+			shift: shift || back || value.modifiers.contains(KeyModifiers::SHIFT),
 			ctrl:  value.modifiers.contains(KeyModifiers::CONTROL),
 			alt:   value.modifiers.contains(KeyModifiers::ALT),
 		}
 	}
 }
 
+// Back to the event it's from, for a key to be handled again, e.g. one ending a sequence
+impl From<&Key> for KeyEvent {
+	fn from(key: &Key) -> Self {
+		let mut modifiers = KeyModifiers::empty();
+		modifiers.set(KeyModifiers::SHIFT, key.shift);
+		modifiers.set(KeyModifiers::CONTROL, key.ctrl);
+		modifiers.set(KeyModifiers::ALT, key.alt);
+
+		match key.code {
+			KeyCode::Tab if key.shift => Self::new(KeyCode::BackTab, modifiers),
+			code => Self::new(code, modifiers),
+		}
+	}
+}
+
 impl TryFrom<String> for Key {
 	type Error = anyhow::Error;
 
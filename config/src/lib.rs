@@ -1,8 +1,12 @@
 #![allow(clippy::module_inception)]
 
+use std::path::PathBuf;
+
 use shared::RoCell;
 
-mod boot;
+pub mod boot;
+pub mod hooks;
+pub mod input;
 pub mod keymap;
 mod log;
 pub mod manager;
@@ -10,9 +14,11 @@ pub mod open;
 mod pattern;
 mod preset;
 pub mod preview;
+pub mod select;
 pub mod tasks;
 pub mod theme;
 mod validation;
+pub mod which;
 mod xdg;
 
 pub(crate) use pattern::*;
@@ -23,28 +29,63 @@ static MERGED_KEYMAP: RoCell<String> = RoCell::new();
 static MERGED_THEME: RoCell<String> = RoCell::new();
 static MERGED_YAZI: RoCell<String> = RoCell::new();
 
+pub static HOOKS: RoCell<hooks::Hooks> = RoCell::new();
+pub static INPUT: RoCell<input::Input> = RoCell::new();
 pub static KEYMAP: RoCell<keymap::Keymap> = RoCell::new();
 pub static LOG: RoCell<log::Log> = RoCell::new();
 pub static MANAGER: RoCell<manager::Manager> = RoCell::new();
 pub static OPEN: RoCell<open::Open> = RoCell::new();
 pub static PREVIEW: RoCell<preview::Preview> = RoCell::new();
+pub static SELECT: RoCell<select::Select> = RoCell::new();
 pub static TASKS: RoCell<tasks::Tasks> = RoCell::new();
-pub static THEME: RoCell<theme::Theme> = RoCell::new();
+// Swapped for a new one once the theme files are changed, where the old one is left as it is for
+// anything still reading it
+pub static THEME: RoCell<&'static theme::Theme> = RoCell::new();
+pub static WHICH: RoCell<which::Which> = RoCell::new();
 
 pub static BOOT: RoCell<boot::Boot> = RoCell::new();
 
+// The theme file of the user, the changes to which are reloaded
+#[inline]
+pub fn theme_file() -> Option<PathBuf> { Xdg::config_dir().map(|d| d.join("theme.toml")) }
+
+pub fn reload_theme() -> anyhow::Result<()> {
+	THEME.init(Box::leak(Box::new(theme::Theme::reload()?)));
+	Ok(())
+}
+
 pub fn init() {
 	MERGED_KEYMAP.with(Preset::keymap);
 	MERGED_THEME.with(Preset::theme);
 	MERGED_YAZI.with(Preset::yazi);
 
+	init_merged();
+	BOOT.with(Default::default);
+}
+
+// With the presets alone and the options of `yazi` over them, none of the config of the user, for
+// the app driven without a terminal, as by the tests, starting in `cwd` and keeping its state in
+// `state_dir`, not in the one of the user
+pub fn init_bare(yazi: &str, cwd: PathBuf, state_dir: PathBuf) {
+	let (keymap, theme, yazi) = Preset::bare(yazi);
+	MERGED_KEYMAP.init(keymap);
+	MERGED_THEME.init(theme);
+	MERGED_YAZI.init(yazi);
+
+	init_merged();
+	BOOT.init(boot::Boot::bare(cwd, state_dir));
+}
+
+fn init_merged() {
+	HOOKS.with(Default::default);
+	INPUT.with(Default::default);
 	KEYMAP.with(Default::default);
 	LOG.with(Default::default);
 	MANAGER.with(Default::default);
 	OPEN.with(Default::default);
 	PREVIEW.with(Default::default);
+	SELECT.with(Default::default);
 	TASKS.with(Default::default);
-	THEME.with(Default::default);
-
-	BOOT.with(Default::default);
+	THEME.with(|| Box::leak(Box::default()));
+	WHICH.with(Default::default);
 }
@@ -0,0 +1,27 @@
+use anyhow::bail;
+use serde::Deserialize;
+
+// Where `arrow` goes past the first or the last file, with the cursor there already
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum ArrowEdge {
+	#[default]
+	Stop,
+	Wrap,
+	// Up to the parent directory past the first file, and on to the next directory in the parent
+	// past the last one
+	Parent,
+}
+
+impl TryFrom<String> for ArrowEdge {
+	type Error = anyhow::Error;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		Ok(match s.as_str() {
+			"stop" => Self::Stop,
+			"wrap" => Self::Wrap,
+			"parent" => Self::Parent,
+			_ => bail!("invalid arrow_edge value: {s}"),
+		})
+	}
+}
@@ -0,0 +1,136 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::bail;
+use chrono::{format::{Item, StrftimeItems}, DateTime, Local};
+use serde::Deserialize;
+
+// What's shown at the end of each line of the files, see `linemode` in the config
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum Linemode {
+	#[default]
+	None,
+	Size,
+	Mtime,
+}
+
+impl TryFrom<String> for Linemode {
+	type Error = anyhow::Error;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		Ok(match s.as_str() {
+			"none" => Self::None,
+			"size" => Self::Size,
+			"mtime" => Self::Mtime,
+			_ => bail!("invalid linemode value: {s}"),
+		})
+	}
+}
+
+// The units of the sizes, the binary ones of 1024 bytes, e.g. `KiB`, or the SI ones of 1000, `kB`
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum SizeUnits {
+	#[default]
+	Binary,
+	Si,
+}
+
+impl TryFrom<String> for SizeUnits {
+	type Error = anyhow::Error;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		Ok(match s.as_str() {
+			"binary" => Self::Binary,
+			"si" => Self::Si,
+			_ => bail!("invalid size_units value: {s}"),
+		})
+	}
+}
+
+impl SizeUnits {
+	// The size with `precision` digits after the decimal point, padded so that the points of all of
+	// them line up in a column, where the bytes have none
+	pub fn format(self, size: u64, precision: usize) -> String {
+		let (base, units) = match self {
+			Self::Binary => (1024.0, ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"]),
+			Self::Si => (1000.0, ["B", "kB", "MB", "GB", "TB", "PB", "EB"]),
+		};
+
+		let (mut n, mut i) = (size as f64, 0);
+		while n >= base && i < units.len() - 1 {
+			n /= base;
+			i += 1;
+		}
+
+		let fract = if precision == 0 { 0 } else { precision + 1 };
+		if i == 0 {
+			format!("{size:>4}{:fract$} {:<3}", "", units[0])
+		} else {
+			format!("{:>width$.precision$} {:<3}", n, units[i], width = 4 + fract)
+		}
+	}
+}
+
+// How the times are shown, as a pattern of `strftime`, or how long ago they were
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum DateFormat {
+	Relative,
+	Pattern(String),
+}
+
+impl TryFrom<String> for DateFormat {
+	type Error = anyhow::Error;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		if s == "relative" {
+			return Ok(Self::Relative);
+		}
+		if s.is_empty() || StrftimeItems::new(&s).any(|i| i == Item::Error) {
+			bail!("invalid date_format value, neither `relative` nor a pattern of strftime: {s}");
+		}
+		Ok(Self::Pattern(s))
+	}
+}
+
+impl DateFormat {
+	pub fn format(&self, time: SystemTime) -> String {
+		match self {
+			Self::Relative => Self::relative(SystemTime::now().duration_since(time).unwrap_or_default()),
+			Self::Pattern(p) => DateTime::<Local>::from(time).format(p).to_string(),
+		}
+	}
+
+	fn relative(ago: Duration) -> String {
+		let secs = ago.as_secs();
+		let (n, unit) = match secs {
+			0..=59 => return "just now".to_owned(),
+			60..=3599 => (secs / 60, "minute"),
+			3600..=86399 => (secs / 3600, "hour"),
+			86400..=2591999 => (secs / 86400, "day"),
+			2592000..=31535999 => (secs / 2592000, "month"),
+			_ => (secs / 31536000, "year"),
+		};
+		format!("{n} {unit}{} ago", if n == 1 { "" } else { "s" })
+	}
+}
+
+#[test]
+fn test_format() {
+	assert_eq!(SizeUnits::Binary.format(512, 1), " 512   B  ");
+	assert_eq!(SizeUnits::Binary.format(1536, 1), "   1.5 KiB");
+	assert_eq!(SizeUnits::Si.format(1500, 2), "   1.50 kB ");
+	assert_eq!(SizeUnits::Si.format(999, 0), " 999 B  ");
+	assert_eq!(SizeUnits::Binary.format(5 << 30, 0), "   5 GiB");
+
+	let relative = |secs| DateFormat::relative(Duration::from_secs(secs));
+	assert_eq!(relative(30), "just now");
+	assert_eq!(relative(60), "1 minute ago");
+	assert_eq!(relative(3 * 86400 + 10), "3 days ago");
+
+	assert!(DateFormat::try_from("relative".to_owned()).is_ok_and(|f| f == DateFormat::Relative));
+	assert!(DateFormat::try_from("%Y-%m-%d %H:%M".to_owned()).is_ok());
+	assert!(DateFormat::try_from("%Q".to_owned()).is_err());
+	assert!(DateFormat::try_from(String::new()).is_err());
+}
@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use anyhow::bail;
+use serde::Deserialize;
+
+use crate::Pattern;
+
+// The files always shown or hidden whether the hidden ones are, the first rule matched winning,
+// e.g. `show .gitignore` or `hide node_modules/`, where the directories end with a `/`
+#[derive(Debug, Default, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+pub struct HiddenRules(Vec<(bool, Pattern)>);
+
+impl TryFrom<Vec<String>> for HiddenRules {
+	type Error = anyhow::Error;
+
+	fn try_from(rules: Vec<String>) -> Result<Self, Self::Error> {
+		let mut v = Vec::with_capacity(rules.len());
+		for rule in rules {
+			let (show, pattern) = match rule.split_once(' ') {
+				Some(("show", p)) => (true, p.trim()),
+				Some(("hide", p)) => (false, p.trim()),
+				_ => bail!("invalid hidden_rules value, not `show <pattern>` or `hide <pattern>`: {rule}"),
+			};
+			v.push((show, Pattern::try_from(pattern)?));
+		}
+		Ok(Self(v))
+	}
+}
+
+impl HiddenRules {
+	#[inline]
+	pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+	// Whether the file is hidden, by the first rule matching it, otherwise if it's a dotfile
+	// while the hidden files aren't shown
+	pub fn is_hidden(&self, path: &Path, is_dir: bool, dotfile: bool, show_hidden: bool) -> bool {
+		self
+			.0
+			.iter()
+			.find(|(_, p)| p.match_path(path, Some(is_dir)))
+			.map_or(dotfile && !show_hidden, |&(show, _)| !show)
+	}
+}
+
+#[test]
+fn test_hidden_rules() {
+	let rules = |v: &[&str]| HiddenRules::try_from(v.iter().map(|&s| s.into()).collect::<Vec<_>>());
+	let r = rules(&["show .gitignore", "hide *.pyc", "hide node_modules/", "show .*rc"]).unwrap();
+	let hidden =
+		|name: &str, dir, show| r.is_hidden(Path::new(name), dir, name.starts_with('.'), show);
+
+	// The dotfiles shown by a rule, and the others only along with the hidden files
+	assert!(!hidden(".gitignore", false, false));
+	assert!(!hidden(".bashrc", false, false));
+	assert!(hidden(".env", false, false));
+	assert!(!hidden(".env", false, true));
+
+	// Hidden even when the hidden files are shown, only the directories for a trailing `/`
+	assert!(hidden("a.pyc", false, true));
+	assert!(hidden("node_modules", true, true));
+	assert!(!hidden("node_modules", false, true));
+	assert!(!hidden("main.py", false, false));
+
+	// The first rule matched wins
+	let r = rules(&["hide .gitignore", "show .git*"]).unwrap();
+	assert!(r.is_hidden(Path::new(".gitignore"), false, true, true));
+	assert!(!r.is_hidden(Path::new(".gitattributes"), false, true, false));
+	let r = rules(&["show .git*", "hide .gitignore"]).unwrap();
+	assert!(!r.is_hidden(Path::new(".gitignore"), false, true, false));
+
+	assert!(rules(&["shown .gitignore"]).is_err());
+	assert!(rules(&["hide"]).is_err());
+}
@@ -1,7 +1,9 @@
+use std::sync::atomic::{AtomicU16, Ordering};
+
 use anyhow::bail;
 use crossterm::terminal::WindowSize;
 use ratatui::prelude::Rect;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use shared::Term;
 
 use super::{FOLDER_MARGIN, PREVIEW_BORDER, PREVIEW_MARGIN};
@@ -35,14 +37,47 @@ impl TryFrom<Vec<u32>> for ManagerLayout {
 	}
 }
 
+// The parent, the current and the preview side by side, or two panes of the tabs, like Norton
+// Commander, where the files are copied and moved to the other one
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(try_from = "String", rename_all = "lowercase")]
+pub enum LayoutMode {
+	#[default]
+	Miller,
+	Commander,
+}
+
+impl TryFrom<String> for LayoutMode {
+	type Error = anyhow::Error;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		Ok(match s.as_str() {
+			"miller" => Self::Miller,
+			"commander" => Self::Commander,
+			_ => bail!("invalid layout_mode value: {s}"),
+		})
+	}
+}
+
+// How wide the places sidebar is at the left of the panes, which are laid out in the rest of the
+// columns, `0` while it's hidden
+static PLACES: AtomicU16 = AtomicU16::new(0);
+
 impl ManagerLayout {
+	#[inline]
+	pub fn places_width(&self) -> u16 { PLACES.load(Ordering::Relaxed).min(Term::size().columns) }
+
+	#[inline]
+	pub fn set_places_width(&self, width: u16) { PLACES.store(width, Ordering::Relaxed) }
+
 	pub fn preview_rect(&self) -> Rect {
 		let WindowSize { columns, rows, .. } = Term::size();
+		let columns = columns - self.places_width();
 
 		let width = (columns as u32 * self.preview) as f64 / self.all as f64;
 		let width = if width.fract() > 0.5 { width.ceil() as u16 } else { width.floor() as u16 };
 
-		let x = columns.saturating_sub(width);
+		let x = columns.saturating_sub(width) + self.places_width();
 
 		Rect {
 			x:      x.saturating_add(PREVIEW_BORDER / 2),
@@ -55,11 +90,25 @@ impl ManagerLayout {
 	#[inline]
 	pub fn preview_height(&self) -> usize { self.preview_rect().height as usize }
 
+	// Inside the border on the right and the padding on the left of it
+	pub fn parent_rect(&self) -> Rect {
+		let WindowSize { columns, rows, .. } = Term::size();
+		let (places, columns) = (self.places_width(), columns - self.places_width());
+
+		Rect {
+			x:      places + 1,
+			y:      FOLDER_MARGIN / 2,
+			width:  ((columns as u32 * self.parent / self.all) as u16).saturating_sub(2),
+			height: rows.saturating_sub(FOLDER_MARGIN),
+		}
+	}
+
 	pub fn folder_rect(&self) -> Rect {
 		let WindowSize { columns, rows, .. } = Term::size();
+		let (places, columns) = (self.places_width(), columns - self.places_width());
 
 		Rect {
-			x:      (columns as u32 * self.parent / self.all) as u16,
+			x:      places + (columns as u32 * self.parent / self.all) as u16,
 			y:      FOLDER_MARGIN / 2,
 			width:  (columns as u32 * self.current / self.all) as u16,
 			height: rows.saturating_sub(FOLDER_MARGIN),
@@ -1,20 +1,65 @@
-use serde::Deserialize;
+use std::time::SystemTime;
 
-use super::{ManagerLayout, SortBy};
+use serde::{Deserialize, Deserializer};
+
+use super::{ArrowEdge, Collation, DateFormat, FileKind, HiddenRules, LayoutMode, Linemode, ManagerLayout, SizeUnits, SortBy, SortRules, StatusLine};
 use crate::MERGED_YAZI;
 
 #[derive(Debug, Deserialize)]
 pub struct Manager {
-	pub layout: ManagerLayout,
+	pub layout:      ManagerLayout,
+	pub layout_mode: LayoutMode,
 
 	// Sorting
-	pub sort_by:        SortBy,
-	pub sort_reverse:   bool,
-	pub sort_dir_first: bool,
+	pub sort_by:             SortBy,
+	pub sort_sensitive:      bool,
+	pub sort_reverse:        bool,
+	pub sort_dir_first:      bool,
+	pub sort_tiebreak:       SortBy,
+	pub sort_split_ext:      bool,
+	pub sort_leading_zeros:  bool,
+	pub sort_thousands:      bool,
+	pub sort_command:        String,
+	pub sort_collation:      Collation,
+	pub sort_kind_order:     Vec<FileKind>,
+	pub sort_size_recursive: bool,
+	pub sort_size_follow:    bool,
+	#[serde(deserialize_with = "sort_parent")]
+	pub sort_parent:         Option<SortBy>,
+	pub sort_rules:          SortRules,
 
 	// Display
-	pub show_hidden:  bool,
-	pub show_symlink: bool,
+	pub show_hidden:    bool,
+	pub hidden_rules:   HiddenRules,
+	pub show_ignored:   bool,
+	pub show_symlink:   bool,
+	pub follow_symlink: bool,
+	pub smart_enter:    bool,
+	pub gitignore:      bool,
+	pub git_status:     bool,
+
+	pub linemode:       Linemode,
+	pub size_units:     SizeUnits,
+	pub size_precision: usize,
+	pub date_format:    DateFormat,
+	pub status_line:    StatusLine,
+
+	pub relative_numbers: bool,
+	pub tree_depth:       usize,
+	pub max_depth:        usize,
+	pub arrow_wrap:       bool,
+	pub arrow_edge:       ArrowEdge,
+	pub follow_results:   bool,
+	pub restore_tabs:     bool,
+	pub places:           bool,
+	pub places_width:     u16,
+	pub mouse:            bool,
+	pub watch:            bool,
+	pub refresh_interval: u64,
+
+	// Jump
+	pub jump_list:     String,
+	pub jump_fzf_args: Vec<String>,
 }
 
 impl Default for Manager {
@@ -27,3 +72,22 @@ impl Default for Manager {
 		toml::from_str::<Outer>(&MERGED_YAZI).unwrap().manager
 	}
 }
+
+impl Manager {
+	#[inline]
+	pub fn readable_size(&self, size: u64) -> String {
+		self.size_units.format(size, self.size_precision)
+	}
+
+	#[inline]
+	pub fn readable_date(&self, time: SystemTime) -> String { self.date_format.format(time) }
+}
+
+// The method the parent directory is sorted by, or none to sort it as the current one, if empty
+fn sort_parent<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<SortBy>, D::Error> {
+	let s = String::deserialize(deserializer)?;
+	if s.is_empty() {
+		return Ok(None);
+	}
+	SortBy::try_from(s).map(Some).map_err(serde::de::Error::custom)
+}
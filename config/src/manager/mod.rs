@@ -1,10 +1,20 @@
+mod arrow;
+mod format;
+mod hidden;
 mod layout;
 mod manager;
+mod sort_rules;
 mod sorting;
+mod status;
 
+pub use arrow::*;
+pub use format::*;
+pub use hidden::*;
 pub use layout::*;
 pub use manager::*;
+pub use sort_rules::*;
 pub use sorting::*;
+pub use status::*;
 
 const FOLDER_MARGIN: u16 = 2;
 
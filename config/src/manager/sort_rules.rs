@@ -0,0 +1,118 @@
+use std::path::Path;
+
+use anyhow::bail;
+use serde::{Deserialize, Deserializer};
+use shared::expand_path;
+
+use super::SortBy;
+use crate::Pattern;
+
+// How the directories matching `path`, or mostly of the files of `mime`, are sorted when entered,
+// where the options left out are as they're for the others
+#[derive(Debug, Deserialize)]
+pub struct SortRule {
+	#[serde(default, deserialize_with = "path_pattern")]
+	pub path: Option<Pattern>,
+	pub mime: Option<Pattern>,
+
+	pub by:        Option<SortBy>,
+	pub sensitive: Option<bool>,
+	pub reverse:   Option<bool>,
+	pub dir_first: Option<bool>,
+}
+
+// The ones tried in order on entering a directory, the first one matched winning
+#[derive(Debug, Default, Deserialize)]
+#[serde(try_from = "Vec<SortRule>")]
+pub struct SortRules(Vec<SortRule>);
+
+impl TryFrom<Vec<SortRule>> for SortRules {
+	type Error = anyhow::Error;
+
+	fn try_from(rules: Vec<SortRule>) -> Result<Self, Self::Error> {
+		if rules.iter().any(|r| r.path.is_none() && r.mime.is_none()) {
+			bail!("invalid sort_rules value, a rule has neither `path` nor `mime`");
+		}
+		Ok(Self(rules))
+	}
+}
+
+impl SortRules {
+	#[inline]
+	pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+	// The first rule matching the directory, by its path, or by the types of its files, given as
+	// `mimes` with none for the unknown ones, where one with both has to match both
+	pub fn matched<'a>(
+		&self,
+		dir: &Path,
+		mimes: impl Iterator<Item = Option<&'a str>> + Clone,
+	) -> Option<&SortRule> {
+		self.0.iter().find(|r| {
+			r.path.as_ref().is_none_or(|p| p.match_path(dir, None))
+				&& r.mime.as_ref().is_none_or(|p| mostly(mimes.clone(), p))
+		})
+	}
+
+	// Whether any of them are told by the files in the directory, not by its path only
+	#[inline]
+	pub fn by_content(&self) -> bool { self.0.iter().any(|r| r.mime.is_some()) }
+}
+
+// Whether more than half of the files are of the types of `mime`, counting those of none known
+fn mostly<'a>(mimes: impl Iterator<Item = Option<&'a str>>, mime: &Pattern) -> bool {
+	let (mut n, mut of) = (0, 0);
+	for m in mimes {
+		of += 1;
+		n += m.is_some_and(|m| mime.matches(m)) as usize;
+	}
+	n * 2 > of
+}
+
+// A glob of the path, where a leading `~` is the home directory
+fn path_pattern<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Pattern>, D::Error> {
+	let s = String::deserialize(deserializer)?;
+	let slash = if s.ends_with('/') { "/" } else { "" };
+	let s = format!("{}{slash}", expand_path(&s).display());
+	Pattern::try_from(s).map(Some).map_err(serde::de::Error::custom)
+}
+
+#[test]
+fn test_sort_rules() {
+	let rules: SortRules = toml::from_str::<std::collections::HashMap<String, SortRules>>(
+		r#"rules = [
+			{ path = "/home/*/Downloads", by = "modified", reverse = true },
+			{ mime = "image/*", by = "modified" },
+			{ path = "/src/*", mime = "text/*", by = "natural", dir_first = false },
+		]"#,
+	)
+	.unwrap()
+	.remove("rules")
+	.unwrap();
+	let matched = |dir: &str, mimes: &[Option<&'static str>]| {
+		let r = rules.matched(Path::new(dir), mimes.iter().copied());
+		r.map(|r| (r.by, r.reverse, r.dir_first))
+	};
+
+	let downloads = Some((Some(SortBy::Modified), Some(true), None));
+	assert_eq!(matched("/home/me/Downloads", &[]), downloads);
+	assert_eq!(matched("/home/me/Downloads/a", &[]), None);
+	let code = Some((Some(SortBy::Natural), None, Some(false)));
+	assert_eq!(matched("/src/yazi", &[Some("text/x-rust")]), code);
+	assert_eq!(matched("/src/yazi", &[Some("application/zip")]), None);
+
+	// Most of the files, the ones of no type known counted as well
+	let images = Some((Some(SortBy::Modified), None, None));
+	assert_eq!(matched("/tmp", &[Some("image/png"), Some("image/jpeg"), None]), images);
+	assert_eq!(matched("/tmp", &[Some("image/png"), Some("text/plain")]), None);
+	assert_eq!(matched("/tmp", &[Some("image/png"), None]), None);
+	assert_eq!(matched("/tmp", &[]), None);
+	assert!(rules.by_content());
+
+	// The first one matched wins
+	assert_eq!(matched("/home/me/Downloads", &[Some("image/png")]), downloads);
+
+	let rule = |s: &str| toml::from_str::<std::collections::HashMap<String, SortRules>>(s);
+	assert!(rule("rules = [{ by = \"modified\" }]").is_err());
+	assert!(rule("rules = [{ path = \"~/a\" }]").is_ok_and(|r| !r["rules"].by_content()));
+}
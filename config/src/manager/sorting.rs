@@ -1,15 +1,43 @@
 use anyhow::bail;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
-#[serde(try_from = "String")]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(try_from = "String", rename_all = "lowercase")]
 pub enum SortBy {
 	#[default]
 	Alphabetical,
 	Created,
 	Modified,
+	Accessed,
+	Extension,
 	Natural,
 	Size,
+	Children,
+	Random,
+	External,
+}
+
+impl SortBy {
+	pub const ALL: [Self; 10] = [
+		Self::Alphabetical,
+		Self::Created,
+		Self::Modified,
+		Self::Accessed,
+		Self::Extension,
+		Self::Natural,
+		Self::Size,
+		Self::Children,
+		Self::Random,
+		Self::External,
+	];
+
+	// The one after it, or before it if `prev`, in the order of `ALL` going round, where
+	// `External` is passed over unless there's a command to sort with
+	pub fn cycle(self, prev: bool, external: bool) -> Self {
+		let all: Vec<_> = Self::ALL.into_iter().filter(|&b| external || b != Self::External).collect();
+		let i = all.iter().position(|&b| b == self).unwrap_or(0);
+		if prev { all[(i + all.len() - 1) % all.len()] } else { all[(i + 1) % all.len()] }
+	}
 }
 
 impl TryFrom<String> for SortBy {
@@ -20,9 +48,75 @@ impl TryFrom<String> for SortBy {
 			"alphabetical" => Self::Alphabetical,
 			"created" => Self::Created,
 			"modified" => Self::Modified,
+			"accessed" => Self::Accessed,
+			"extension" => Self::Extension,
 			"natural" => Self::Natural,
 			"size" => Self::Size,
+			"children" => Self::Children,
+			"random" => Self::Random,
+			"external" => Self::External,
 			_ => bail!("invalid sort_by value: {s}"),
 		})
 	}
 }
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum Collation {
+	#[default]
+	Codepoint,
+	Locale,
+}
+
+impl TryFrom<String> for Collation {
+	type Error = anyhow::Error;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		Ok(match s.as_str() {
+			"codepoint" => Self::Codepoint,
+			"locale" => Self::Locale,
+			_ => bail!("invalid sort_collation value: {s}"),
+		})
+	}
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum FileKind {
+	Dir,
+	LinkDir,
+	File,
+	LinkFile,
+	Other,
+}
+
+impl FileKind {
+	pub const ALL: [Self; 5] = [Self::Dir, Self::LinkDir, Self::File, Self::LinkFile, Self::Other];
+}
+
+impl TryFrom<String> for FileKind {
+	type Error = anyhow::Error;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		Ok(match s.as_str() {
+			"dir" => Self::Dir,
+			"link_dir" => Self::LinkDir,
+			"file" => Self::File,
+			"link_file" => Self::LinkFile,
+			"other" => Self::Other,
+			_ => bail!("invalid sort_kind_order value: {s}"),
+		})
+	}
+}
+
+#[test]
+fn test_sort_cycle() {
+	assert_eq!(SortBy::Alphabetical.cycle(false, false), SortBy::Created);
+	assert_eq!(SortBy::Alphabetical.cycle(true, false), SortBy::Random);
+	assert_eq!(SortBy::Alphabetical.cycle(true, true), SortBy::External);
+	assert_eq!(SortBy::Random.cycle(false, false), SortBy::Alphabetical);
+	assert_eq!(SortBy::Random.cycle(false, true), SortBy::External);
+
+	// Out of the cycle if there's nothing to sort with externally
+	assert_eq!(SortBy::External.cycle(false, false), SortBy::Created);
+}
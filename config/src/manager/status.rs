@@ -0,0 +1,151 @@
+use anyhow::bail;
+use serde::Deserialize;
+
+// What a variable of `status_line`, e.g. `{path}`, is replaced with as the status bar is rendered
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusVar {
+	Mode,
+	Path,
+	Name,
+	Mime,
+	Count,
+	Position,
+	Selected,
+	SelectedSize,
+	Sort,
+	Filter,
+	Branch,
+	Free,
+	FreePercent,
+	Total,
+	Perm,
+}
+
+impl TryFrom<&str> for StatusVar {
+	type Error = anyhow::Error;
+
+	fn try_from(s: &str) -> Result<Self, Self::Error> {
+		Ok(match s {
+			"mode" => Self::Mode,
+			"path" => Self::Path,
+			"name" => Self::Name,
+			"mime" => Self::Mime,
+			"count" => Self::Count,
+			"position" => Self::Position,
+			"selected" => Self::Selected,
+			"selected_size" => Self::SelectedSize,
+			"sort" => Self::Sort,
+			"filter" => Self::Filter,
+			"branch" => Self::Branch,
+			"free" => Self::Free,
+			"free_percent" => Self::FreePercent,
+			"total" => Self::Total,
+			"perm" => Self::Perm,
+			_ => bail!("invalid status_line value, unknown variable `{{{s}}}`"),
+		})
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StatusToken {
+	Text(String),
+	Var(StatusVar),
+}
+
+// The status bar of `status_line`, its left, center and right parts split by `{=}`, where one of
+// them puts the rest on the right, and two the middle in the center. The built-in one if empty
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct StatusLine(Option<[Vec<StatusToken>; 3]>);
+
+impl TryFrom<String> for StatusLine {
+	type Error = anyhow::Error;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		if s.is_empty() {
+			return Ok(Self(None));
+		}
+
+		let (mut parts, mut part, mut text) = (vec![], vec![], String::new());
+		let mut it = s.chars().peekable();
+		while let Some(c) = it.next() {
+			match c {
+				'{' | '}' if it.next_if_eq(&c).is_some() => text.push(c),
+				'}' => bail!("invalid status_line value, `}}` without a `{{` before it: {s}"),
+				'{' => {
+					let mut name = String::new();
+					loop {
+						match it.next() {
+							Some('}') => break,
+							Some(c) => name.push(c),
+							None => bail!("invalid status_line value, `{{{name}` isn't closed with a `}}`"),
+						}
+					}
+
+					if !text.is_empty() {
+						part.push(StatusToken::Text(std::mem::take(&mut text)));
+					}
+					if name == "=" {
+						parts.push(std::mem::take(&mut part));
+					} else {
+						part.push(StatusToken::Var(StatusVar::try_from(name.as_str())?));
+					}
+				}
+				c => text.push(c),
+			}
+		}
+
+		if !text.is_empty() {
+			part.push(StatusToken::Text(text));
+		}
+		parts.push(part);
+
+		let mut it = parts.into_iter();
+		Ok(Self(Some(match (it.next(), it.next(), it.next(), it.next()) {
+			(Some(l), None, ..) => [l, vec![], vec![]],
+			(Some(l), Some(r), None, _) => [l, vec![], r],
+			(Some(l), Some(c), Some(r), None) => [l, c, r],
+			_ => bail!("invalid status_line value, more than two `{{=}}` in it: {s}"),
+		})))
+	}
+}
+
+impl StatusLine {
+	#[inline]
+	pub fn parts(&self) -> Option<&[Vec<StatusToken>; 3]> { self.0.as_ref() }
+
+	// Whether any of `vars` is in it, for their values to be kept up to date only if they're shown
+	pub fn contains(&self, vars: &[StatusVar]) -> bool {
+		let mut tokens = self.parts().into_iter().flatten().flatten();
+		tokens.any(|t| matches!(t, StatusToken::Var(v) if vars.contains(v)))
+	}
+}
+
+#[test]
+fn test_status_line() {
+	use StatusToken::*;
+	let parse = |s: &str| StatusLine::try_from(s.to_owned());
+
+	assert_eq!(parse("").unwrap().parts(), None);
+	assert_eq!(
+		parse(" {mode} {path}{=}{{{count}}} files").unwrap().parts(),
+		Some(&[
+			vec![Text(" ".to_owned()), Var(StatusVar::Mode), Text(" ".to_owned()), Var(StatusVar::Path)],
+			vec![],
+			vec![Text("{".to_owned()), Var(StatusVar::Count), Text("} files".to_owned())],
+		])
+	);
+	assert_eq!(
+		parse("{name}{=}{branch}{=}{free}").unwrap().parts(),
+		Some(&[vec![Var(StatusVar::Name)], vec![Var(StatusVar::Branch)], vec![Var(StatusVar::Free)]])
+	);
+	assert!(parse("{path}{=}{free}").unwrap().contains(&[StatusVar::Free, StatusVar::Total]));
+	assert!(!parse("{path}{=}{free}").unwrap().contains(&[StatusVar::Total]));
+
+	// The offending token is named
+	let err = |s: &str| parse(s).unwrap_err().to_string();
+	assert!(err("{path} {size}").contains("`{size}`"));
+	assert!(err("{path").contains("`{path`"));
+	assert!(err("a}").contains("`}`"));
+	assert!(err("{=}{=}{=}").contains("`{=}`"));
+}
@@ -0,0 +1,18 @@
+use serde::Deserialize;
+
+// What `open` does with the one file hovered, without `--interactive`, such as with `<Enter>`
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OpenEnter {
+	// With the first opener of the rules, as with more files than one
+	#[default]
+	Open,
+	// In `$EDITOR`
+	Edit,
+	// Runs it if it's executable, asked for first, or opens it otherwise
+	Run,
+	// Asks whether to run or open an executable, edits a text file, and opens any other
+	Smart,
+	#[serde(rename = "none")]
+	Nothing,
+}
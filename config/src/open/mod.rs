@@ -1,5 +1,7 @@
+mod enter;
 mod open;
 mod opener;
 
+pub use enter::*;
 pub use open::*;
 pub use opener::*;
@@ -4,13 +4,16 @@ use indexmap::IndexSet;
 use serde::{Deserialize, Deserializer};
 use shared::MIME_DIR;
 
-use super::Opener;
+use super::{OpenEnter, Opener};
 use crate::{Pattern, MERGED_YAZI};
 
 #[derive(Debug)]
 pub struct Open {
-	openers: BTreeMap<String, IndexSet<Opener>>,
-	rules:   Vec<OpenRule>,
+	openers:   BTreeMap<String, IndexSet<Opener>>,
+	rules:     Vec<OpenRule>,
+	enter:     OpenEnter,
+	enter_ext: BTreeMap<String, OpenEnter>,
+	edit_at:   BTreeMap<String, String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -57,6 +60,22 @@ impl Open {
 		let flat = grouped.iter().flat_map(|&g| g).collect::<IndexSet<_>>();
 		flat.into_iter().filter(|&o| grouped.iter().all(|g| g.contains(o))).collect()
 	}
+
+	// What to do with a file of the extension `ext`, lowercased, by its own rule or the default one
+	#[inline]
+	pub fn enter(&self, ext: Option<&str>) -> OpenEnter {
+		ext.and_then(|e| self.enter_ext.get(e)).copied().unwrap_or(self.enter)
+	}
+
+	// The command to edit a file at `line` with `editor`, the `$EDITOR`, by the template of the
+	// editor, or at the top of the file with one it has no template for
+	pub fn edit_at(&self, editor: &str, line: usize) -> String {
+		let name = editor.split_whitespace().next().and_then(|e| Path::new(e).file_name());
+		match name.and_then(|n| self.edit_at.get(n.to_str()?)) {
+			Some(tpl) if line > 1 => format!("$EDITOR {}", tpl.replace("$LINE", &line.to_string())),
+			_ => r#"$EDITOR "$1""#.to_owned(),
+		}
+	}
 }
 
 impl<'de> Deserialize<'de> for Open {
@@ -71,11 +90,66 @@ impl<'de> Deserialize<'de> for Open {
 		}
 		#[derive(Deserialize)]
 		struct OuterOpen {
-			rules: Vec<OpenRule>,
+			rules:     Vec<OpenRule>,
+			#[serde(default)]
+			enter:     OpenEnter,
+			#[serde(default)]
+			enter_ext: BTreeMap<String, OpenEnter>,
+			#[serde(default)]
+			edit_at:   BTreeMap<String, String>,
 		}
 
 		let outer = Outer::deserialize(deserializer)?;
 		let openers = outer.opener.into_iter().map(|(k, v)| (k, IndexSet::from_iter(v))).collect();
-		Ok(Self { openers, rules: outer.open.rules })
+		let enter_ext =
+			outer.open.enter_ext.into_iter().map(|(k, v)| (k.to_lowercase(), v)).collect();
+		Ok(Self {
+			openers,
+			rules: outer.open.rules,
+			enter: outer.open.enter,
+			enter_ext,
+			edit_at: outer.open.edit_at,
+		})
 	}
 }
+
+#[test]
+fn test_enter() {
+	let open: Open = toml::from_str(
+		r#"
+		[opener]
+		[open]
+		rules = []
+		enter = "smart"
+		enter_ext = { SH = "edit", AppImage = "run" }
+		"#,
+	)
+	.unwrap();
+	assert_eq!(open.enter(Some("sh")), OpenEnter::Edit);
+	assert_eq!(open.enter(Some("appimage")), OpenEnter::Run);
+	assert_eq!(open.enter(Some("md")), OpenEnter::Smart);
+	assert_eq!(open.enter(None), OpenEnter::Smart);
+
+	let open: Open = toml::from_str("[opener]\n[open]\nrules = []\nenter = \"none\"").unwrap();
+	assert_eq!(open.enter(Some("sh")), OpenEnter::Nothing);
+}
+
+#[test]
+fn test_edit_at() {
+	let open: Open = toml::from_str(
+		r#"
+		[opener]
+		[open]
+		rules = []
+		edit_at = { nvim = '+$LINE "$1"', code = '-g "$1:$LINE"' }
+		"#,
+	)
+	.unwrap();
+	assert_eq!(open.edit_at("/usr/bin/nvim", 42), r#"$EDITOR +42 "$1""#);
+	assert_eq!(open.edit_at("code --wait", 7), r#"$EDITOR -g "$1:7""#);
+
+	// At the top for an unknown editor, or the first line
+	assert_eq!(open.edit_at("ed", 42), r#"$EDITOR "$1""#);
+	assert_eq!(open.edit_at("nvim", 1), r#"$EDITOR "$1""#);
+	assert_eq!(open.edit_at("", 42), r#"$EDITOR "$1""#);
+}
@@ -1,5 +1,6 @@
-use std::fs;
+use std::{fs, process};
 
+use anyhow::{bail, Result};
 use toml::Table;
 
 use crate::xdg::Xdg;
@@ -30,8 +31,12 @@ impl Preset {
 
 	fn merge_str(user: &str, base: &str) -> String {
 		let path = Xdg::config_dir().unwrap().join(user);
-		let mut user = fs::read_to_string(path).unwrap_or_default().parse::<Table>().unwrap();
+		Self::merge_over(&fs::read_to_string(path).unwrap_or_default(), base)
+	}
 
+	// The options of `user` over those of `base`
+	fn merge_over(user: &str, base: &str) -> String {
+		let mut user = user.parse::<Table>().unwrap();
 		let base = base.parse::<Table>().unwrap();
 		Self::merge(&mut user, &base, 2);
 		user.to_string()
@@ -42,9 +47,44 @@ impl Preset {
 		Self::merge_str("keymap.toml", include_str!("../preset/keymap.toml"))
 	}
 
+	// The presets alone, with the options of `yazi` over those of `yazi.toml`, none of the files of
+	// the user read
+	pub(crate) fn bare(yazi: &str) -> (String, String, String) {
+		(
+			Self::merge_over("", include_str!("../preset/keymap.toml")),
+			Self::theme_of("").unwrap(),
+			Self::merge_over(yazi, include_str!("../preset/yazi.toml")),
+		)
+	}
+
 	#[inline]
 	pub(crate) fn theme() -> String {
-		Self::merge_str("theme.toml", include_str!("../preset/theme.toml"))
+		Self::theme_checked().unwrap_or_else(|e| {
+			println!("Config `theme.toml` error: {e}");
+			process::exit(1);
+		})
+	}
+
+	// The theme of the user, over the bundled flavor it's of if any, over the preset
+	pub(crate) fn theme_checked() -> Result<String> {
+		let path = Xdg::config_dir().unwrap().join("theme.toml");
+		Self::theme_of(&fs::read_to_string(path).unwrap_or_default())
+	}
+
+	fn theme_of(user: &str) -> Result<String> {
+		let mut user = user.parse::<Table>()?;
+
+		let flavor = match user.remove("flavor") {
+			None => "",
+			Some(f) => match f.as_str() {
+				Some("dark") => "",
+				Some("light") => include_str!("../preset/flavors/light.toml"),
+				_ => bail!("unknown `flavor` {f}, expected \"dark\" or \"light\""),
+			},
+		};
+		Self::merge(&mut user, &flavor.parse()?, 2);
+		Self::merge(&mut user, &include_str!("../preset/theme.toml").parse()?, 2);
+		Ok(user.to_string())
 	}
 
 	#[inline]
@@ -1,3 +1,5 @@
 mod preview;
+mod previewer;
 
 pub use preview::*;
+pub use previewer::*;
@@ -4,6 +4,7 @@ use md5::{Digest, Md5};
 use serde::Deserialize;
 use shared::expand_path;
 
+use super::Previewer;
 use crate::{xdg::Xdg, MERGED_YAZI};
 
 #[derive(Debug)]
@@ -12,7 +13,22 @@ pub struct Preview {
 	pub max_width:  u32,
 	pub max_height: u32,
 
-	pub cache_dir: PathBuf,
+	pub cache_dir:          PathBuf,
+	pub highlight_cache:    usize,
+	pub highlight_max_line: usize,
+	pub highlight_max_time: u64,
+	pub highlight_max_size: u64,
+	pub line_numbers:       bool,
+	pub max_bytes:          usize,
+	pub max_read:           u64,
+	pub image_protocol:     String,
+	pub pdf_as_text:        bool,
+	pub document_pages:     usize,
+
+	pub previewers:        Vec<Previewer>,
+	pub previewer_timeout: u64,
+	pub previewer_cache:   usize,
+	pub debounce:          u64,
 }
 
 impl Default for Preview {
@@ -27,7 +43,22 @@ impl Default for Preview {
 			pub max_width:  u32,
 			pub max_height: u32,
 
-			pub cache_dir: Option<String>,
+			pub cache_dir:          Option<String>,
+			pub highlight_cache:    usize,
+			pub highlight_max_line: usize,
+			pub highlight_max_time: u64,
+			pub highlight_max_size: u64,
+			pub line_numbers:       bool,
+			pub max_bytes:          usize,
+			pub max_read:           u64,
+			pub image_protocol:     String,
+			pub pdf_as_text:        bool,
+			pub document_pages:     usize,
+
+			pub previewers:        Vec<Previewer>,
+			pub previewer_timeout: u64,
+			pub previewer_cache:   usize,
+			pub debounce:          u64,
 		}
 
 		let preview = toml::from_str::<Outer>(&MERGED_YAZI).unwrap().preview;
@@ -41,6 +72,21 @@ impl Default for Preview {
 			max_height: preview.max_height,
 
 			cache_dir,
+			highlight_cache: preview.highlight_cache,
+			highlight_max_line: preview.highlight_max_line,
+			highlight_max_time: preview.highlight_max_time,
+			highlight_max_size: preview.highlight_max_size,
+			line_numbers: preview.line_numbers,
+			max_bytes: preview.max_bytes,
+			max_read: preview.max_read,
+			image_protocol: preview.image_protocol,
+			pdf_as_text: preview.pdf_as_text,
+			document_pages: preview.document_pages,
+
+			previewers: preview.previewers,
+			previewer_timeout: preview.previewer_timeout,
+			previewer_cache: preview.previewer_cache,
+			debounce: preview.debounce,
 		}
 	}
 }
@@ -53,6 +99,11 @@ impl Preview {
 			.join(format!("{:x}", Md5::new_with_prefix(format!("{:?}///{}", path, skip)).finalize()))
 	}
 
+	#[inline]
+	pub fn previewer(&self, path: &Path, mime: &str) -> Option<&Previewer> {
+		self.previewers.iter().find(|p| p.matches(path, mime))
+	}
+
 	#[inline]
 	pub fn tmpfile(&self, prefix: &str) -> PathBuf {
 		let nanos = SystemTime::now().duration_since(time::UNIX_EPOCH).unwrap().as_nanos();
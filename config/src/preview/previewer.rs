@@ -0,0 +1,21 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::Pattern;
+
+// A shell command whose output is the preview of the files matching `name` or `mime`
+#[derive(Debug, Deserialize)]
+pub struct Previewer {
+	pub name: Option<Pattern>,
+	pub mime: Option<Pattern>,
+	pub exec: String,
+}
+
+impl Previewer {
+	#[inline]
+	pub fn matches(&self, path: &Path, mime: &str) -> bool {
+		self.mime.as_ref().is_some_and(|m| m.matches(mime))
+			|| self.name.as_ref().is_some_and(|n| n.match_path(path, Some(false)))
+	}
+}
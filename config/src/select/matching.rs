@@ -0,0 +1,22 @@
+use anyhow::bail;
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum SelectMatching {
+	#[default]
+	Fuzzy,
+	Exact,
+}
+
+impl TryFrom<String> for SelectMatching {
+	type Error = anyhow::Error;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		Ok(match s.as_str() {
+			"fuzzy" => Self::Fuzzy,
+			"exact" => Self::Exact,
+			_ => bail!("invalid matching value: {s}"),
+		})
+	}
+}
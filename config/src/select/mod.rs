@@ -0,0 +1,5 @@
+mod matching;
+mod select;
+
+pub use matching::*;
+pub use select::*;
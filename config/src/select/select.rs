@@ -0,0 +1,25 @@
+use serde::Deserialize;
+
+use super::SelectMatching;
+use crate::MERGED_YAZI;
+
+#[derive(Debug)]
+pub struct Select {
+	pub matching: SelectMatching,
+}
+
+impl Default for Select {
+	fn default() -> Self {
+		#[derive(Deserialize)]
+		struct Outer {
+			select: Shadow,
+		}
+		#[derive(Deserialize)]
+		struct Shadow {
+			matching: SelectMatching,
+		}
+
+		let select = toml::from_str::<Outer>(&MERGED_YAZI).unwrap().select;
+		Self { matching: select.matching }
+	}
+}
@@ -0,0 +1,28 @@
+use anyhow::bail;
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum ConflictPolicy {
+	Overwrite,
+	Skip,
+	Rename,
+	Number,
+	#[default]
+	Ask,
+}
+
+impl TryFrom<String> for ConflictPolicy {
+	type Error = anyhow::Error;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		Ok(match s.as_str() {
+			"overwrite" => Self::Overwrite,
+			"skip" => Self::Skip,
+			"rename" => Self::Rename,
+			"number" => Self::Number,
+			"ask" => Self::Ask,
+			_ => bail!("invalid conflict value: {s}"),
+		})
+	}
+}
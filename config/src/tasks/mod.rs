@@ -1,3 +1,5 @@
+mod conflict;
 mod tasks;
 
+pub use conflict::*;
 pub use tasks::*;
@@ -1,6 +1,7 @@
 use serde::Deserialize;
 use validator::Validate;
 
+use super::ConflictPolicy;
 use crate::{validation::check_validation, MERGED_YAZI};
 
 #[derive(Debug, Deserialize, Validate)]
@@ -9,8 +10,24 @@ pub struct Tasks {
 	pub micro_workers: u8,
 	#[validate(range(min = 5, message = "Cannot be less than 5"))]
 	pub macro_workers: u8,
+	#[validate(range(min = 1, message = "Cannot be less than 1"))]
+	pub file_workers: u8,
+	#[validate(range(min = 1, message = "Cannot be less than 1"))]
+	pub precache_workers: u8,
 	#[validate(range(min = 3, message = "Cannot be less than 3"))]
 	pub bizarre_retry: u8,
+
+	pub trash:                bool,
+	pub conflict:             ConflictPolicy,
+	pub bandwidth:            u64,
+	pub keep_partial:         bool,
+	pub confirm_count:        usize,
+	pub confirm_size:         u64,
+	pub confirm_outside_home: bool,
+	pub confirm_delete:       bool,
+	pub confirm_typed:        bool,
+	pub confirm_typed_count:  usize,
+	pub confirm_space:        bool,
 }
 
 impl Default for Tasks {
@@ -15,15 +15,16 @@ impl Default for Color {
 impl TryFrom<String> for Color {
 	type Error = anyhow::Error;
 
+	// A hex one such as `#80AEFA`, a name of the terminal colors such as `red` or `lightblue`, or
+	// the index of one of the 256 colors of the terminal, such as `208`
 	fn try_from(s: String) -> Result<Self, Self::Error> {
-		if s.len() < 7 {
-			bail!("Invalid color: {s}");
+		if !s.is_ascii() {
+			bail!("invalid color {s:?}");
+		}
+		match s.parse() {
+			Ok(c) => Ok(Self(c)),
+			Err(_) => bail!("invalid color {s:?}, expected one like \"#80AEFA\", \"red\" or \"208\""),
 		}
-		Ok(Self(style::Color::Rgb(
-			u8::from_str_radix(&s[1..3], 16)?,
-			u8::from_str_radix(&s[3..5], 16)?,
-			u8::from_str_radix(&s[5..7], 16)?,
-		)))
 	}
 }
 
@@ -45,3 +46,15 @@ pub struct ColorGroup {
 	pub select: Color,
 	pub unset:  Color,
 }
+
+#[test]
+fn test_color() {
+	let color = |s: &str| Color::try_from(s.to_owned()).map(|c| c.0);
+	assert_eq!(color("#80AEFA").unwrap(), style::Color::Rgb(0x80, 0xAE, 0xFA));
+	assert_eq!(color("light-blue").unwrap(), style::Color::LightBlue);
+	assert_eq!(color("208").unwrap(), style::Color::Indexed(208));
+
+	assert!(color("#80AEF").is_err());
+	assert!(color("#80AEFä").is_err());
+	assert!(color("purple").unwrap_err().to_string().contains("\"purple\""));
+}
@@ -0,0 +1,162 @@
+use std::{collections::HashMap, path::Path};
+
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::Pattern;
+
+// What a file is to `LS_COLORS`, as for a symlink, the file it points to, or `Orphan` if it's gone
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LsKind {
+	File,
+	Dir,
+	Exec,
+	Fifo,
+	Socket,
+	Block,
+	Char,
+	Orphan,
+}
+
+impl LsKind {
+	#[inline]
+	fn key(self) -> &'static str {
+		match self {
+			Self::File => "fi",
+			Self::Dir => "di",
+			Self::Exec => "ex",
+			Self::Fifo => "pi",
+			Self::Socket => "so",
+			Self::Block => "bd",
+			Self::Char => "cd",
+			Self::Orphan => "or",
+		}
+	}
+}
+
+// The rules of `LS_COLORS`, such as `di=01;34:ln=target:*.tar=01;31`, by the kinds of the files,
+// and by the patterns of the names of the regular ones, as `ls --color` has them
+#[derive(Debug, Default)]
+pub struct LsColors {
+	kinds:    HashMap<String, Style>,
+	patterns: Vec<(Pattern, Style)>,
+	// `ln=target`, the symlinks are colored as the files they point to
+	target:   bool,
+}
+
+impl LsColors {
+	// The invalid rules are left out, as `ls` does
+	pub fn parse(s: &str) -> Self {
+		let mut colors = Self::default();
+		for (key, codes) in s.split(':').filter_map(|entry| entry.split_once('=')) {
+			if key == "ln" && codes == "target" {
+				colors.target = true;
+				continue;
+			}
+			let Some(style) = sgr(codes) else {
+				continue;
+			};
+
+			if !key.starts_with('*') {
+				colors.kinds.insert(key.to_owned(), style);
+			} else if let Ok(pattern) = Pattern::try_from(key) {
+				colors.patterns.push((pattern, style));
+			}
+		}
+		colors
+	}
+
+	// The style of a file by its `kind` and `path`, and whether it's a symlink, none if no rule
+	// matches it, where an executable is only colored by the patterns if `ex` isn't given, and
+	// a symlink if `ln` isn't, in the same way as `ls`
+	pub fn style(&self, path: &Path, kind: LsKind, link: bool) -> Option<Style> {
+		if kind == LsKind::Orphan {
+			return self.kind(LsKind::Orphan).or_else(|| self.kinds.get("ln").copied());
+		}
+		if link && !self.target {
+			if let Some(style) = self.kinds.get("ln") {
+				return Some(*style);
+			}
+		}
+
+		match kind {
+			LsKind::Exec if self.kind(kind).is_some() => self.kind(kind),
+			LsKind::File | LsKind::Exec => self
+				.patterns
+				.iter()
+				.find(|(p, _)| p.match_path(path, Some(false)))
+				.map(|(_, style)| *style)
+				.or_else(|| self.kind(LsKind::File)),
+			_ => self.kind(kind),
+		}
+	}
+
+	#[inline]
+	fn kind(&self, kind: LsKind) -> Option<Style> { self.kinds.get(kind.key()).copied() }
+}
+
+// The style of the SGR codes, e.g. `01;38;5;208`, none if any of them isn't known
+fn sgr(codes: &str) -> Option<Style> {
+	let mut style = Style::new();
+	let mut codes = codes.split(';').map(|c| c.parse::<u8>().ok());
+	while let Some(code) = codes.next() {
+		match code? {
+			0 => style = Style::new(),
+			1 => style = style.add_modifier(Modifier::BOLD),
+			4 => style = style.add_modifier(Modifier::UNDERLINED),
+			n @ 30..=37 => style.fg = Some(Color::Indexed(n - 30)),
+			n @ 40..=47 => style.bg = Some(Color::Indexed(n - 40)),
+			n @ 90..=97 => style.fg = Some(Color::Indexed(n - 90 + 8)),
+			n @ 100..=107 => style.bg = Some(Color::Indexed(n - 100 + 8)),
+			39 => style.fg = None,
+			49 => style.bg = None,
+			n @ (38 | 48) => {
+				let color = match codes.next()?? {
+					5 => Color::Indexed(codes.next()??),
+					2 => Color::Rgb(codes.next()??, codes.next()??, codes.next()??),
+					_ => return None,
+				};
+				if n == 38 {
+					style.fg = Some(color);
+				} else {
+					style.bg = Some(color);
+				}
+			}
+			// Such as blinking, which aren't shown here
+			_ => {}
+		}
+	}
+	Some(style)
+}
+
+#[test]
+fn test_ls_colors() {
+	let ls = LsColors::parse("di=01;34:ex=01;32:pi=33:*.tar=01;31:*.png=38;5;208:*.x=38;9:fi=0");
+	let style = |path: &str, kind| ls.style(Path::new(path), kind, false);
+	let bold = |c| Some(Style::new().fg(Color::Indexed(c)).add_modifier(Modifier::BOLD));
+
+	assert_eq!(style("a", LsKind::Dir), bold(4));
+	assert_eq!(style("a.tar", LsKind::Dir), bold(4));
+	assert_eq!(style("a.tar", LsKind::File), bold(1));
+	assert_eq!(style("a.tar", LsKind::Exec), bold(2));
+	assert_eq!(style("a.png", LsKind::File), Some(Style::new().fg(Color::Indexed(208))));
+	assert_eq!(style("a.x", LsKind::File), Some(Style::new()));
+	assert_eq!(style("a", LsKind::Fifo), Some(Style::new().fg(Color::Indexed(3))));
+	assert_eq!(style("a", LsKind::Socket), None);
+
+	// The symlinks by `ln`, or by what they point to
+	assert_eq!(ls.style(Path::new("a"), LsKind::Dir, true), bold(4));
+	let ls = LsColors::parse("ln=01;36:di=01;34:*.tar=01;31");
+	assert_eq!(ls.style(Path::new("a.tar"), LsKind::File, true), bold(6));
+	assert_eq!(ls.style(Path::new("a.tar"), LsKind::Orphan, true), bold(6));
+	assert_eq!(ls.style(Path::new("a.tar"), LsKind::Exec, false), bold(1));
+	assert_eq!(ls.style(Path::new("a"), LsKind::File, false), None);
+	let ls = LsColors::parse("ln=target:or=31:*.tar=01;31");
+	assert_eq!(ls.style(Path::new("a.tar"), LsKind::File, true), bold(1));
+	assert_eq!(ls.style(Path::new("a"), LsKind::Orphan, true).unwrap().fg, Some(Color::Indexed(1)));
+
+	let rgb = Style::new().fg(Color::Rgb(1, 2, 3)).add_modifier(Modifier::UNDERLINED);
+	assert_eq!(sgr("38;2;1;2;3;4"), Some(rgb));
+	let reset = Style::new().fg(Color::Indexed(12)).bg(Color::Indexed(8));
+	assert_eq!(sgr("1;0;94;100"), Some(reset));
+	assert!(sgr("38;9").is_none() && sgr("x").is_none());
+}
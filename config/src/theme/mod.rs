@@ -1,11 +1,13 @@
 mod color;
 mod filetype;
 mod icon;
+mod ls_colors;
 mod style;
 mod theme;
 
 pub use color::*;
 pub use filetype::*;
 pub use icon::*;
+pub use ls_colors::*;
 pub use style::*;
 pub use theme::*;
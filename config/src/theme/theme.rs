@@ -1,18 +1,21 @@
-use std::path::PathBuf;
+use std::{env, path::PathBuf, process};
 
+use anyhow::Result;
 use serde::Deserialize;
 use shared::expand_path;
 use validator::Validate;
 
-use super::{ColorGroup, Filetype, Icon, Style};
-use crate::{validation::check_validation, MERGED_THEME};
+use super::{ColorGroup, Filetype, Icon, LsColors, Style};
+use crate::{Preset, MERGED_THEME};
 
 #[derive(Deserialize, Validate)]
 pub struct Tab {
-	pub active:    Style,
-	pub inactive:  Style,
+	pub active:        Style,
+	pub inactive:      Style,
+	pub pane_active:   Style,
+	pub pane_inactive: Style,
 	#[validate(range(min = 1, message = "Must be greater than 0"))]
-	pub max_width: u8,
+	pub max_width:     u8,
 }
 
 #[derive(Deserialize)]
@@ -44,6 +47,7 @@ pub struct Progress {
 #[derive(Deserialize)]
 pub struct Selection {
 	pub hovered: Style,
+	pub label:   Style,
 }
 
 #[derive(Deserialize)]
@@ -52,10 +56,47 @@ pub struct Marker {
 	pub selected:  Style,
 }
 
+#[derive(Deserialize)]
+pub struct Symlink {
+	pub target: Style,
+	pub orphan: Style,
+}
+
+#[derive(Deserialize)]
+pub struct Git {
+	pub modified:   Style,
+	pub staged:     Style,
+	pub untracked:  Style,
+	pub ignored:    Style,
+	pub conflicted: Style,
+}
+
 #[derive(Deserialize)]
 pub struct Preview {
-	pub hovered:       Style,
-	pub syntect_theme: PathBuf,
+	pub hovered:           Style,
+	pub matched:           Style,
+	pub matched_current:   Style,
+	pub placeholder:       Style,
+	pub placeholder_error: Style,
+	pub syntect_theme:     PathBuf,
+	pub syntect_theme_alt: PathBuf,
+}
+
+#[derive(Deserialize)]
+pub struct Places {
+	pub current: Style,
+	pub missing: Style,
+}
+
+#[derive(Deserialize)]
+pub struct Header {
+	pub cwd: Style,
+}
+
+// The borders of the popups, such as the input, the select, and the task manager
+#[derive(Deserialize)]
+pub struct Border {
+	pub popup: Style,
 }
 
 #[derive(Deserialize)]
@@ -65,21 +106,47 @@ pub struct Theme {
 	pub progress:  Progress,
 	pub selection: Selection,
 	pub marker:    Marker,
+	pub symlink:   Symlink,
+	pub git:       Git,
 	pub preview:   Preview,
+	pub places:    Places,
+	pub header:    Header,
+	pub border:    Border,
 	#[serde(rename = "filetype", deserialize_with = "Filetype::deserialize")]
 	pub filetypes: Vec<Filetype>,
+	#[serde(skip)]
+	pub ls_colors: Option<LsColors>,
 	#[serde(deserialize_with = "Icon::deserialize")]
 	pub icons:     Vec<Icon>,
 }
 
 impl Default for Theme {
 	fn default() -> Self {
-		let mut theme: Self = toml::from_str(&MERGED_THEME).unwrap();
+		Self::parse(&MERGED_THEME).unwrap_or_else(|e| {
+			println!("Config `theme.toml` error: {e}");
+			process::exit(1);
+		})
+	}
+}
 
-		check_validation(theme.tab.validate());
+impl Theme {
+	// The theme of the files as they're now, to take over from the one in use once they're changed
+	#[inline]
+	pub fn reload() -> Result<Self> { Self::parse(&Preset::theme_checked()?) }
 
-		theme.preview.syntect_theme = expand_path(&theme.preview.syntect_theme);
+	fn parse(s: &str) -> Result<Self> {
+		let mut theme: Self = toml::from_str(s)?;
+		theme.tab.validate()?;
 
-		theme
+		// The files are colored by `LS_COLORS` before the rules of `[filetype]`, if it's on
+		let table: toml::Table = s.parse()?;
+		let ls = table.get("filetype").and_then(|f| f.get("ls_colors")).and_then(|b| b.as_bool());
+		if ls == Some(true) {
+			theme.ls_colors = env::var("LS_COLORS").ok().map(|s| LsColors::parse(&s));
+		}
+
+		theme.preview.syntect_theme = expand_path(&theme.preview.syntect_theme);
+		theme.preview.syntect_theme_alt = expand_path(&theme.preview.syntect_theme_alt);
+		Ok(theme)
 	}
 }
@@ -0,0 +1,5 @@
+mod popup;
+mod which;
+
+pub use popup::*;
+pub use which::*;
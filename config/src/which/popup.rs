@@ -0,0 +1,25 @@
+use anyhow::bail;
+use serde::Deserialize;
+
+// When the keys continuing a sequence pop up, once the first of it is pressed
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum WhichPopup {
+	#[default]
+	Delayed,
+	Immediate,
+	Never,
+}
+
+impl TryFrom<String> for WhichPopup {
+	type Error = anyhow::Error;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		Ok(match s.as_str() {
+			"delayed" => Self::Delayed,
+			"immediate" => Self::Immediate,
+			"never" => Self::Never,
+			_ => bail!("invalid popup value: {s}"),
+		})
+	}
+}
@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::WhichPopup;
+use crate::MERGED_YAZI;
+
+#[derive(Debug)]
+pub struct Which {
+	pub popup:   WhichPopup,
+	pub delay:   Duration,
+	// Zero to wait for the next key of a sequence for good
+	pub timeout: Duration,
+}
+
+impl Default for Which {
+	fn default() -> Self {
+		#[derive(Deserialize)]
+		struct Outer {
+			which: Shadow,
+		}
+		#[derive(Deserialize)]
+		struct Shadow {
+			popup:   WhichPopup,
+			delay:   u64,
+			timeout: u64,
+		}
+
+		let which = toml::from_str::<Outer>(&MERGED_YAZI).unwrap().which;
+		let delay = match which.popup {
+			WhichPopup::Delayed => Duration::from_millis(which.delay),
+			_ => Duration::ZERO,
+		};
+		Self { popup: which.popup, delay, timeout: Duration::from_millis(which.timeout) }
+	}
+}
@@ -0,0 +1,219 @@
+use std::{fs::File, io::{self, Read, Seek, SeekFrom}, path::Path};
+
+use flate2::read::DeflateDecoder;
+use regex::bytes::Regex;
+
+// How many bytes from the top and the bottom of a PDF are looked at for its metadata
+const PDF_SNIFF: u64 = 1 << 20;
+
+// What's known about a document without extracting its text
+#[derive(Debug, Default, PartialEq)]
+pub struct DocumentMeta {
+	pub title:  Option<String>,
+	pub author: Option<String>,
+	pub pages:  Option<usize>,
+}
+
+impl DocumentMeta {
+	// Reads the metadata of a PDF, or of an office document in a zip, e.g. `.docx` and `.odt`
+	pub fn new(path: &Path, pdf: bool) -> io::Result<Self> {
+		let mut file = File::open(path)?;
+		if pdf { Self::pdf(&mut file) } else { Self::office(&mut file) }
+	}
+
+	pub fn render(&self, why: &str) -> String {
+		let unknown = || "Unknown".to_owned();
+		format!(
+			"Can't extract the text: {why}\n\nTitle:  {}\nAuthor: {}\nPages:  {}\n",
+			self.title.clone().unwrap_or_else(unknown),
+			self.author.clone().unwrap_or_else(unknown),
+			self.pages.map_or_else(unknown, |n| n.to_string())
+		)
+	}
+
+	// Looks for the page tree and the info dictionary in plain sight, which can be missed in
+	// the compressed object streams, then they're left unknown
+	fn pdf(file: &mut (impl Read + Seek)) -> io::Result<Self> {
+		let len = file.seek(SeekFrom::End(0))?;
+		let mut buf = Vec::new();
+		file.seek(SeekFrom::Start(0))?;
+		file.by_ref().take(PDF_SNIFF).read_to_end(&mut buf)?;
+		if len > PDF_SNIFF {
+			file.seek(SeekFrom::Start(len.saturating_sub(PDF_SNIFF).max(PDF_SNIFF)))?;
+			file.read_to_end(&mut buf)?;
+		}
+
+		let string = |key: &str| {
+			let re = Regex::new(&format!(r"/{key}\s*\(((?:[^()\\]|\\.)*)\)")).unwrap();
+			let s = re.captures(&buf)?.get(1)?.as_bytes();
+			let s = String::from_utf8_lossy(s).replace("\\(", "(").replace("\\)", ")");
+			Some(s).filter(|s| !s.is_empty())
+		};
+		let pages = Regex::new(r"/Type\s*/Pages\b[^>]*?/Count\s+(\d+)")
+			.unwrap()
+			.captures_iter(&buf)
+			.filter_map(|c| std::str::from_utf8(c.get(1)?.as_bytes()).ok()?.parse().ok())
+			.max();
+
+		Ok(Self { title: string("Title"), author: string("Author"), pages })
+	}
+
+	// Reads `docProps/{core,app}.xml` of a `.docx`, or `meta.xml` of a `.odt`,
+	// from where the central directory of the zip points to
+	fn office(file: &mut (impl Read + Seek)) -> io::Result<Self> {
+		let tag = |t: &str| Regex::new(&format!(r"<{t}(?:\s[^>]*)?>([^<]+)</{t}>")).unwrap();
+		let (title, creator) = (tag("dc:title"), tag("dc:creator"));
+		let (initial, pages) = (tag("meta:initial-creator"), tag("Pages"));
+		let count = Regex::new(r#"meta:page-count="(\d+)""#).unwrap();
+
+		let mut meta = Self::default();
+		for name in ["docProps/core.xml", "docProps/app.xml", "meta.xml"] {
+			let Some(xml) = Self::zip_entry(file, name)? else {
+				continue;
+			};
+
+			let find = |re: &Regex| {
+				let s = re.captures(&xml)?.get(1)?.as_bytes();
+				Some(String::from_utf8_lossy(s).trim().to_owned()).filter(|s| !s.is_empty())
+			};
+			meta.title = meta.title.or_else(|| find(&title));
+			meta.author = meta.author.or_else(|| find(&creator)).or_else(|| find(&initial));
+			meta.pages = meta.pages.or_else(|| find(&pages).or_else(|| find(&count))?.parse().ok());
+		}
+		Ok(meta)
+	}
+
+	fn zip_entry(file: &mut (impl Read + Seek), name: &str) -> io::Result<Option<Vec<u8>>> {
+		let u16_at = |b: &[u8], i: usize| u16::from_le_bytes([b[i], b[i + 1]]) as usize;
+		let u32_at = |b: &[u8], i: usize| u32::from_le_bytes(b[i..i + 4].try_into().unwrap()) as u64;
+
+		// The end of central directory record is in the last 64 KiB, along with the comment
+		let len = file.seek(SeekFrom::End(0))?;
+		let mut tail = Vec::new();
+		file.seek(SeekFrom::Start(len.saturating_sub(65557)))?;
+		file.read_to_end(&mut tail)?;
+		let eocd = tail.windows(4).rposition(|w| w == b"PK\x05\x06");
+		let Some(eocd) = eocd.filter(|&i| i + 22 <= tail.len()) else {
+			return Ok(None);
+		};
+
+		// Not to allocate for a central directory that can't be there, of a broken or a crafted zip
+		let (size, offset) = (u32_at(&tail, eocd + 12), u32_at(&tail, eocd + 16));
+		if offset > len || size > len - offset {
+			return Ok(None);
+		}
+
+		let mut dir = vec![0; size as usize];
+		file.seek(SeekFrom::Start(offset))?;
+		file.read_exact(&mut dir)?;
+
+		let mut i = 0;
+		while i + 46 <= dir.len() && &dir[i..i + 4] == b"PK\x01\x02" {
+			let (method, compressed) = (u16_at(&dir, i + 10), u32_at(&dir, i + 20));
+			let (n, m, k) = (u16_at(&dir, i + 28), u16_at(&dir, i + 30), u16_at(&dir, i + 32));
+			if dir.get(i + 46..i + 46 + n) != Some(name.as_bytes()) {
+				i += 46 + n + m + k;
+				continue;
+			}
+
+			let mut local = [0; 30];
+			file.seek(SeekFrom::Start(u32_at(&dir, i + 42)))?;
+			file.read_exact(&mut local)?;
+			file.seek(SeekFrom::Current((u16_at(&local, 26) + u16_at(&local, 28)) as i64))?;
+
+			let mut data = Vec::new();
+			match method {
+				0 => file.by_ref().take(compressed).read_to_end(&mut data)?,
+				8 => DeflateDecoder::new(file.by_ref().take(compressed)).read_to_end(&mut data)?,
+				_ => return Ok(None),
+			};
+			return Ok(Some(data));
+		}
+		Ok(None)
+	}
+}
+
+// Turns the form feeds after each page from `pdftotext` into lines that separate the pages
+pub fn page_breaks(text: &str, width: usize) -> String {
+	let mut s = String::with_capacity(text.len());
+	for (i, page) in text.trim_end_matches('\x0c').split('\x0c').enumerate() {
+		if i > 0 {
+			let title = format!(" Page {} ", i + 1);
+			s.push_str(&format!("\x1b[2m{title:─^width$}\x1b[0m\n"));
+		}
+		s.push_str(page);
+		if !page.is_empty() && !page.ends_with('\n') {
+			s.push('\n');
+		}
+	}
+	s
+}
+
+#[test]
+fn test_pdf_meta() {
+	let pdf = b"%PDF-1.4\n1 0 obj << /Type /Pages /Kids [3 0 R] /Count 12 >> endobj\n\
+		2 0 obj << /Type /Pages /Parent 1 0 R /Count 3 >> endobj\n\
+		9 0 obj << /Title (Notes \\(draft\\)) /Author (Jane Doe) >> endobj\n%%EOF";
+	let meta = DocumentMeta::pdf(&mut io::Cursor::new(pdf)).unwrap();
+	assert_eq!(meta, DocumentMeta {
+		title:  Some("Notes (draft)".to_owned()),
+		author: Some("Jane Doe".to_owned()),
+		pages:  Some(12),
+	});
+}
+
+#[test]
+fn test_office_meta() {
+	// A zip with the entries stored without compression
+	let entries = [
+		("mimetype", &b"application/vnd.oasis.opendocument.text"[..]),
+		("meta.xml", b"<office:meta><dc:title>Report</dc:title>\
+			<meta:initial-creator>Sam</meta:initial-creator>\
+			<meta:document-statistic meta:page-count=\"7\"/></office:meta>"),
+	];
+	let (mut zip, mut dir) = (Vec::new(), Vec::new());
+	for (name, data) in entries {
+		let (size, len) = ((data.len() as u32).to_le_bytes(), (name.len() as u16).to_le_bytes());
+
+		let mut local = b"PK\x03\x04".to_vec();
+		local.resize(30, 0);
+		local[18..22].copy_from_slice(&size);
+		local[26..28].copy_from_slice(&len);
+
+		let mut central = b"PK\x01\x02".to_vec();
+		central.resize(46, 0);
+		central[20..24].copy_from_slice(&size);
+		central[28..30].copy_from_slice(&len);
+		central[42..46].copy_from_slice(&(zip.len() as u32).to_le_bytes());
+
+		zip.extend(local.iter().chain(name.as_bytes()).chain(data));
+		dir.extend(central.iter().chain(name.as_bytes()));
+	}
+
+	let mut eocd = b"PK\x05\x06".to_vec();
+	eocd.resize(22, 0);
+	eocd[12..16].copy_from_slice(&(dir.len() as u32).to_le_bytes());
+	eocd[16..20].copy_from_slice(&(zip.len() as u32).to_le_bytes());
+	zip.extend(dir.iter().chain(&eocd));
+
+	let meta = DocumentMeta::office(&mut io::Cursor::new(&zip)).unwrap();
+	assert_eq!(meta, DocumentMeta {
+		title:  Some("Report".to_owned()),
+		author: Some("Sam".to_owned()),
+		pages:  Some(7),
+	});
+
+	// A central directory larger than the file is never read
+	let at = zip.len() - 10;
+	zip[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+	assert_eq!(DocumentMeta::office(&mut io::Cursor::new(zip)).unwrap(), DocumentMeta::default());
+}
+
+#[test]
+fn test_page_breaks() {
+	assert_eq!(
+		page_breaks("one\n\x0ctwo\x0c", 12),
+		"one\n\x1b[2m── Page 2 ──\x1b[0m\ntwo\n"
+	);
+	assert_eq!(page_breaks("", 12), "");
+}
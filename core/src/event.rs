@@ -1,19 +1,21 @@
 use std::{collections::BTreeMap, ffi::OsString};
 
 use anyhow::Result;
-use config::{keymap::{Exec, KeymapLayer}, open::Opener};
-use crossterm::event::KeyEvent;
+use config::{keymap::{Exec, KeymapLayer}, manager::SortBy, open::Opener};
+use crossterm::event::{KeyEvent, MouseEvent};
 use shared::{InputError, RoCell, Url};
 use tokio::sync::{mpsc::{self, UnboundedSender}, oneshot};
 
 use super::{files::{File, FilesOp}, input::InputOpt, select::SelectOpt};
-use crate::manager::PreviewLock;
+use crate::manager::{PreviewLock, PreviewMatches};
 
 static TX: RoCell<UnboundedSender<Event>> = RoCell::new();
 
 pub enum Event {
-	Quit,
+	Quit(bool),
+	Pick(Vec<Url>),
 	Key(KeyEvent),
+	Mouse(MouseEvent),
 	Paste(String),
 	Render(String),
 	Resize(u16, u16),
@@ -23,19 +25,31 @@ pub enum Event {
 	// Manager
 	Cd(Url),
 	Refresh,
+	// Entered for real, not shown in passing while the path is typed
+	Visited(Url),
 	Files(FilesOp),
+	Sorted(Url, SortBy, u64),
 	Pages(usize),
 	Mimetype(BTreeMap<Url, String>),
 	Hover(Option<File>),
 	Peek(Option<(usize, Url)>),
 	Preview(PreviewLock),
+	Matches(PreviewMatches),
+	Message(String, bool),
+	Record(Exec),
+	Theme,
 
 	// Input
 	Select(SelectOpt, oneshot::Sender<Result<usize>>),
+	SelectPush(Vec<String>),
 	Input(InputOpt, mpsc::UnboundedSender<Result<String, InputError>>),
 
 	// Tasks
 	Open(Vec<(OsString, String)>, Option<Opener>),
+	Prefer(String, Opener),
+	Rename(Vec<(Url, Url)>),
+	Land(Vec<Url>),
+	Landed(Url),
 	Progress(u8, u32),
 }
 
@@ -54,6 +68,12 @@ impl Event {
 
 #[macro_export]
 macro_rules! emit {
+	(Quit($canceled:expr)) => {
+		$crate::Event::Quit($canceled).emit();
+	};
+	(Pick($picked:expr)) => {
+		$crate::Event::Pick($picked).emit();
+	};
 	(Key($key:expr)) => {
 		$crate::Event::Key($key).emit();
 	};
@@ -74,9 +94,15 @@ macro_rules! emit {
 	(Cd($url:expr)) => {
 		$crate::Event::Cd($url).emit();
 	};
+	(Visited($cwd:expr)) => {
+		$crate::Event::Visited($cwd).emit();
+	};
 	(Files($op:expr)) => {
 		$crate::Event::Files($op).emit();
 	};
+	(Sorted($url:expr, $by:expr, $order:expr)) => {
+		$crate::Event::Sorted($url, $by, $order).emit();
+	};
 	(Pages($page:expr)) => {
 		$crate::Event::Pages($page).emit();
 	};
@@ -98,11 +124,23 @@ macro_rules! emit {
 	(Preview($lock:expr)) => {
 		$crate::Event::Preview($lock).emit();
 	};
+	(Matches($matches:expr)) => {
+		$crate::Event::Matches($matches).emit();
+	};
+	(Message($text:expr, $error:expr)) => {
+		$crate::Event::Message($text, $error).emit();
+	};
+	(Record($exec:expr)) => {
+		$crate::Event::Record($exec).emit();
+	};
 
 	(Select($opt:expr)) => {{
 		let (tx, rx) = tokio::sync::oneshot::channel();
 		$crate::Event::Select($opt, tx).wait(rx)
 	}};
+	(SelectPush($items:expr)) => {
+		$crate::Event::SelectPush($items).emit();
+	};
 	(Input($opt:expr)) => {{
 		let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
 		$crate::Event::Input($opt, tx).emit();
@@ -112,6 +150,18 @@ macro_rules! emit {
 	(Open($targets:expr, $opener:expr)) => {
 		$crate::Event::Open($targets, $opener).emit();
 	};
+	(Prefer($mime:expr, $opener:expr)) => {
+		$crate::Event::Prefer($mime, $opener).emit();
+	};
+	(Rename($targets:expr)) => {
+		$crate::Event::Rename($targets).emit();
+	};
+	(Land($targets:expr)) => {
+		$crate::Event::Land($targets).emit();
+	};
+	(Landed($target:expr)) => {
+		$crate::Event::Landed($target).emit();
+	};
 	(Progress($percent:expr, $tasks:expr)) => {
 		$crate::Event::Progress($percent, $tasks).emit();
 	};
@@ -0,0 +1,383 @@
+use std::{path::{Component, Path, PathBuf}, process::Stdio};
+
+use anyhow::{anyhow, bail, Result};
+use shared::{PeekError, Url};
+use tokio::{fs, io::AsyncReadExt, process::{Child, Command}};
+
+use super::{lsar_with, LSAR_LOCKED};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+	Zip,
+	TarGz,
+	TarZst,
+	SevenZ,
+}
+
+impl ArchiveFormat {
+	// Told by the extension of the name of the archive, case-insensitively
+	pub fn from_name(name: &str) -> Option<Self> {
+		let name = name.to_ascii_lowercase();
+		Some(if name.ends_with(".zip") {
+			Self::Zip
+		} else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+			Self::TarGz
+		} else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+			Self::TarZst
+		} else if name.ends_with(".7z") {
+			Self::SevenZ
+		} else {
+			return None;
+		})
+	}
+
+	#[inline]
+	pub fn tool(self) -> &'static str {
+		match self {
+			Self::Zip => "zip",
+			Self::TarGz | Self::TarZst => "tar",
+			Self::SevenZ => "7z",
+		}
+	}
+
+	// The entry that a line of the output of the tool tells is added, e.g. `  adding: a (stored 0%)`
+	// of `zip`, the whole line of `tar -v`, and `+ a` of `7z -bb1`
+	pub fn entry(self, line: &str) -> Option<&str> {
+		match self {
+			Self::Zip => {
+				let line = line.trim_start().strip_prefix("adding: ")?;
+				Some(line.rfind(" (").map_or(line, |i| &line[..i]))
+			}
+			Self::TarGz | Self::TarZst => Some(line).filter(|l| !l.is_empty()),
+			Self::SevenZ => line.strip_prefix("+ "),
+		}
+	}
+}
+
+pub struct ArchiveOpt {
+	pub cwd:     Url,
+	pub output:  Url,
+	pub format:  ArchiveFormat,
+	// Relative to `cwd`, for them to be extracted to where they're from
+	pub targets: Vec<PathBuf>,
+}
+
+pub fn archive(opt: &ArchiveOpt) -> Result<Child> {
+	let mut cmd = Command::new(opt.format.tool());
+	match opt.format {
+		// Without `--` to stop the options, a target that looks like one is told apart with `./`
+		ArchiveFormat::Zip => {
+			let targets = opt.targets.iter().map(|t| {
+				if t.to_string_lossy().starts_with('-') { Path::new(".").join(t) } else { t.clone() }
+			});
+			cmd.args(["-r", "-y"]).arg(&opt.output).args(targets)
+		}
+		ArchiveFormat::TarGz => cmd.arg("-czvf").arg(&opt.output).arg("--").args(&opt.targets),
+		ArchiveFormat::TarZst => {
+			cmd.args(["--zstd", "-cvf"]).arg(&opt.output).arg("--").args(&opt.targets)
+		}
+		ArchiveFormat::SevenZ => cmd.args(["a", "-bb1"]).arg(&opt.output).arg("--").args(&opt.targets),
+	};
+
+	cmd
+		.current_dir(&opt.cwd)
+		.stdin(Stdio::null())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.kill_on_drop(true)
+		.spawn()
+		.map_err(|e| anyhow!("failed to run `{}`, is it installed? ({e})", opt.format.tool()))
+}
+
+// How an archive is extracted, with `tar` for the tarballs, and `unar` for the rest of them, the
+// one that knows the most formats, e.g. zip, 7z and rar, and the encrypted ones among them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Extractor {
+	Tar,
+	Unar,
+}
+
+impl Extractor {
+	// Told by the extension of the name, or the magic bytes at the head of the file otherwise, and
+	// `None` if it isn't an archive
+	pub fn detect(name: &str, head: &[u8]) -> Option<Self> {
+		const TAR: &[&str] = &[
+			".tar", ".tar.gz", ".tgz", ".tar.zst", ".tzst", ".tar.xz", ".txz", ".tar.bz2", ".tbz2",
+		];
+		const UNAR: &[&str] = &[".zip", ".7z", ".rar", ".jar", ".gz", ".bz2", ".xz", ".cab", ".lzh"];
+
+		let name = name.to_ascii_lowercase();
+		if TAR.iter().any(|e| name.ends_with(e)) {
+			return Some(Self::Tar);
+		} else if UNAR.iter().any(|e| name.ends_with(e)) {
+			return Some(Self::Unar);
+		}
+
+		match head {
+			// Zstandard, that `unar` doesn't know of
+			[0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Self::Tar),
+			_ if head.get(257..262) == Some(b"ustar") => Some(Self::Tar),
+			[b'P', b'K', 3, 4, ..]
+			| [b'P', b'K', 5, 6, ..]
+			| [b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c, ..]
+			| [b'R', b'a', b'r', b'!', ..]
+			| [0x1f, 0x8b, ..]
+			| [b'B', b'Z', b'h', ..]
+			| [0xfd, b'7', b'z', b'X', b'Z', 0, ..] => Some(Self::Unar),
+			_ => None,
+		}
+	}
+
+	pub async fn of(path: &Path) -> Option<Self> {
+		let mut head = vec![0; 512];
+		let n = fs::File::open(path).await.ok()?.read(&mut head).await.ok()?;
+		Self::detect(&path.file_name()?.to_string_lossy(), &head[..n])
+	}
+
+	#[inline]
+	pub fn tool(self) -> &'static str {
+		match self {
+			Self::Tar => "tar",
+			Self::Unar => "unar",
+		}
+	}
+
+	// The entry that a line of the output of the tool tells is extracted, and whether it's done
+	// without an error, the whole line of `tar -v`, and `  a  (6 B)... OK.` of `unar`
+	pub fn extracted(self, line: &str) -> Option<(&str, bool)> {
+		match self {
+			Self::Tar => Some(line).filter(|l| !l.is_empty()).map(|l| (l, true)),
+			Self::Unar => {
+				let (entry, status) = line.strip_prefix("  ")?.rsplit_once("... ")?;
+				let entry = entry.rfind("  (").map_or(entry, |i| &entry[..i]);
+				Some((entry, status.starts_with("OK")))
+			}
+		}
+	}
+}
+
+pub enum Listed {
+	// With the symlinks among them and their targets, none of a target not told by the listing
+	Entries { names: Vec<String>, links: Vec<(String, Option<String>)>, encrypted: bool },
+	// The names are encrypted too, and can't be listed without the password
+	Locked,
+}
+
+// The names of the entries in the archive, where the ones of the directories end with `/`
+pub async fn archive_list(path: &Path, by: Extractor, password: Option<&str>) -> Result<Listed> {
+	if by == Extractor::Unar {
+		let files = match lsar_with(path, password).await {
+			Ok(files) => files,
+			Err(PeekError::Unexpected(e)) if e == LSAR_LOCKED => return Ok(Listed::Locked),
+			Err(e) => bail!("{e}"),
+		};
+
+		let encrypted = files.iter().any(|f| f.encrypted);
+		let links = files.iter().filter(|f| f.is_link).map(|f| (f.name.clone(), f.link_to.clone()));
+		let links = links.collect();
+		let names = files
+			.into_iter()
+			.map(|f| if f.is_dir && !f.name.ends_with('/') { format!("{}/", f.name) } else { f.name })
+			.collect();
+		return Ok(Listed::Entries { names, links, encrypted });
+	}
+
+	let tar = |verbose| async move {
+		let output = Command::new("tar")
+			.arg(if verbose { "-tvf" } else { "-tf" })
+			.arg(path)
+			.stdin(Stdio::null())
+			.kill_on_drop(true)
+			.output()
+			.await
+			.map_err(|e| anyhow!("failed to run `tar`: {e}"))?;
+
+		if !output.status.success() {
+			bail!("`tar` failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+		}
+		Ok(String::from_utf8_lossy(&output.stdout).lines().map(|l| l.to_owned()).collect::<Vec<_>>())
+	};
+
+	// The long listing has the entries in the same order, e.g. `lrwxrwxrwx ... a -> b` of a symlink,
+	// where the target is told apart by the name before it, as either of them can have ` -> ` in it
+	let (names, long) = (tar(false).await?, tar(true).await?);
+	let links = names
+		.iter()
+		.zip(&long)
+		.filter(|(_, l)| l.starts_with('l'))
+		.map(|(n, l)| {
+			let target = l.find(&format!(" {n} -> ")).map(|i| l[i + n.len() + 5..].to_owned());
+			(n.clone(), target)
+		})
+		.collect();
+	Ok(Listed::Entries { names, links, encrypted: false })
+}
+
+// Whether the password is the one of the archive, by extracting the files in it for a test, as
+// the listing of one whose names aren't encrypted takes any password
+pub async fn archive_test(path: &Path, password: &str) -> Result<bool> {
+	let output = Command::new("lsar")
+		.args(["-t", "-p", password])
+		.arg(path)
+		.stdin(Stdio::null())
+		.kill_on_drop(true)
+		.output()
+		.await
+		.map_err(|e| anyhow!("failed to run `lsar`: {e}"))?;
+
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	let failed = stdout.lines().filter_map(|l| Extractor::Unar.extracted(l)).any(|(_, ok)| !ok);
+	Ok(output.status.success() && !failed)
+}
+
+// Whether the entry stays in the directory it's extracted to, rather than escaping it with an
+// absolute path or `..`, known as zip slip
+pub fn is_safe_entry(entry: &str) -> bool {
+	!entry.is_empty()
+		&& entry.split(['/', '\\']).all(|c| c != "..")
+		&& Path::new(entry).components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+// Whether the target of the symlink `entry` stays in the directory it's extracted to, resolved
+// from the directory of the symlink, where an absolute one is taken for escaping it already
+pub fn is_safe_link(entry: &str, target: &str) -> bool {
+	let rooted = Path::new(target).components().any(|c| matches!(c, Component::Prefix(_)));
+	if target.is_empty() || target.starts_with(['/', '\\']) || rooted {
+		return false;
+	}
+
+	let parent = entry.trim_end_matches('/').rsplit_once('/').map_or("", |(p, _)| p);
+	let mut depth = 0usize;
+	for c in parent.split('/').chain(target.split(['/', '\\'])) {
+		match c {
+			"" | "." => {}
+			".." => match depth.checked_sub(1) {
+				Some(d) => depth = d,
+				None => return false,
+			},
+			_ => depth += 1,
+		}
+	}
+	true
+}
+
+// The name of an archive without the extension, e.g. `a` of `a.tar.gz`, for the directory to
+// extract it to
+pub fn archive_stem(name: &str) -> &str {
+	let stem = match name.rsplit_once('.') {
+		Some((stem, _)) if !stem.is_empty() => stem,
+		_ => return name,
+	};
+	match stem.len().checked_sub(4) {
+		Some(i) if i > 0 && stem.is_char_boundary(i) && stem[i..].eq_ignore_ascii_case(".tar") => {
+			&stem[..i]
+		}
+		_ => stem,
+	}
+}
+
+pub struct ExtractOpt {
+	pub archive:  Url,
+	pub to:       Url,
+	pub by:       Extractor,
+	pub password: Option<String>,
+	pub entries:  usize,
+}
+
+// Extracts the archive into `to`, overwriting the files there, where the password is given on the
+// command line, as there's no other way for `unar` to take it
+pub fn extract(opt: &ExtractOpt) -> Result<Child> {
+	let mut cmd = Command::new(opt.by.tool());
+	match opt.by {
+		Extractor::Tar => cmd.arg("-xvf").arg(&opt.archive).arg("-C").arg(&opt.to),
+		Extractor::Unar => {
+			if let Some(password) = &opt.password {
+				cmd.args(["-p", password]);
+			}
+			cmd.args(["-f", "-D", "-o"]).arg(&opt.to).arg(&opt.archive)
+		}
+	};
+
+	cmd
+		.stdin(Stdio::null())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.kill_on_drop(true)
+		.spawn()
+		.map_err(|e| anyhow!("failed to run `{}`, is it installed? ({e})", opt.by.tool()))
+}
+
+#[test]
+fn test_archive_format() {
+	use ArchiveFormat::*;
+
+	assert_eq!(ArchiveFormat::from_name("a.ZIP"), Some(Zip));
+	assert_eq!(ArchiveFormat::from_name("a.b.tar.gz"), Some(TarGz));
+	assert_eq!(ArchiveFormat::from_name("a.tgz"), Some(TarGz));
+	assert_eq!(ArchiveFormat::from_name("a.tar.zst"), Some(TarZst));
+	assert_eq!(ArchiveFormat::from_name("a.7z"), Some(SevenZ));
+	assert_eq!(ArchiveFormat::from_name("a.gz"), None);
+	assert_eq!(ArchiveFormat::from_name("zip"), None);
+
+	assert_eq!(Zip.entry("  adding: dir/a (b).txt (deflated 42%)"), Some("dir/a (b).txt"));
+	assert_eq!(Zip.entry("updating: a"), None);
+	assert_eq!(TarGz.entry("dir/a.txt"), Some("dir/a.txt"));
+	assert_eq!(SevenZ.entry("+ dir/a.txt"), Some("dir/a.txt"));
+	assert_eq!(SevenZ.entry("Everything is Ok"), None);
+}
+
+#[test]
+fn test_extract() {
+	use Extractor::*;
+
+	assert_eq!(Extractor::detect("a.TAR.ZST", b""), Some(Tar));
+	assert_eq!(Extractor::detect("a.rar", b""), Some(Unar));
+	assert_eq!(Extractor::detect("a.bin", b"PK\x03\x04rest"), Some(Unar));
+	assert_eq!(Extractor::detect("a", &[0x28, 0xb5, 0x2f, 0xfd]), Some(Tar));
+	let mut tar = vec![0; 512];
+	tar[257..262].copy_from_slice(b"ustar");
+	assert_eq!(Extractor::detect("a", &tar), Some(Tar));
+	assert_eq!(Extractor::detect("a.txt", b"hello"), None);
+
+	assert_eq!(Unar.extracted("  dir/a (1).txt  (6 B)... OK."), Some(("dir/a (1).txt", true)));
+	assert_eq!(Unar.extracted("  a.txt  (6 B)... Failed! (Wrong password)"), Some(("a.txt", false)));
+	assert_eq!(Unar.extracted("a.zip: Zip"), None);
+	assert_eq!(Tar.extracted("dir/"), Some(("dir/", true)));
+
+	assert!(is_safe_entry("a/b.txt") && is_safe_entry("./a") && is_safe_entry("a..b"));
+	assert!(!is_safe_entry("../a") && !is_safe_entry("a/../../b") && !is_safe_entry("/etc/passwd"));
+	assert!(!is_safe_entry("..\\a") && !is_safe_entry(""));
+
+	assert!(is_safe_link("a/b/l", "../c") && is_safe_link("a/l", "./b/../c"));
+	assert!(is_safe_link("l", "a") && is_safe_link("a/", "b"));
+	assert!(!is_safe_link("a/l", "../../etc") && !is_safe_link("l", "..") && !is_safe_link("l", ""));
+	assert!(!is_safe_link("a/l", "/etc/passwd") && !is_safe_link("a/l", "b/../../.."));
+
+	assert_eq!(archive_stem("a.tar.gz"), "a");
+	assert_eq!(archive_stem("a.b.ZIP"), "a.b");
+	assert_eq!(archive_stem(".tar.gz"), ".tar");
+	assert_eq!(archive_stem("a"), "a");
+	assert_eq!(archive_stem(".zip"), ".zip");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_archive_list() {
+	let dir = shared::TestDir::new("archive-list");
+	std::fs::create_dir(dir.join("d")).unwrap();
+	std::fs::write(dir.join("d/a"), "a").unwrap();
+	std::os::unix::fs::symlink("../../etc", dir.join("d/l")).unwrap();
+	std::os::unix::fs::symlink("a b", dir.join("d/x -> y")).unwrap();
+	let mut tar = std::process::Command::new("tar");
+	assert!(tar.current_dir(&*dir).args(["-cf", "t.tar", "d"]).status().unwrap().success());
+
+	let listed = shared::block_on(archive_list(&dir.join("t.tar"), Extractor::Tar, None));
+	let Ok(Listed::Entries { mut names, mut links, encrypted: false }) = listed else { panic!() };
+	names.sort();
+	links.sort();
+	assert_eq!(names, ["d/", "d/a", "d/l", "d/x -> y"]);
+	assert_eq!(links, [
+		("d/l".to_owned(), Some("../../etc".to_owned())),
+		("d/x -> y".to_owned(), Some("a b".to_owned()))
+	]);
+}
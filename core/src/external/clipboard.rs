@@ -37,21 +37,33 @@ pub async fn clipboard_get() -> Result<OsString> {
 	Ok(result.await?.map_err(|_| anyhow!("failed to get clipboard"))?.into())
 }
 
+// The commands to set the clipboard with in this environment, the ones of the display server first.
+// Over SSH without a forwarded display, there are none of them, and the terminal is asked instead
+#[cfg(not(target_os = "windows"))]
+fn clipboard_setters() -> Vec<(&'static str, Vec<&'static str>)> {
+	let has = |var| std::env::var_os(var).is_some_and(|v| !v.is_empty());
+
+	let mut all = vec![];
+	if cfg!(target_os = "macos") {
+		all.push(("pbcopy", vec![]));
+	}
+	if has("WAYLAND_DISPLAY") {
+		all.push(("wl-copy", vec![]));
+	}
+	if has("DISPLAY") {
+		all.push(("xclip", vec!["-selection", "clipboard"]));
+		all.push(("xsel", vec!["-ib"]));
+	}
+	all
+}
+
 #[cfg(not(target_os = "windows"))]
 pub async fn clipboard_set(s: impl AsRef<std::ffi::OsStr>) -> Result<()> {
 	use std::{os::unix::prelude::OsStrExt, process::Stdio};
 
-	use anyhow::bail;
 	use tokio::{io::AsyncWriteExt, process::Command};
 
-	let all = [
-		("pbcopy", vec![]),
-		("wl-copy", vec![]),
-		("xclip", vec!["-selection", "clipboard"]),
-		("xsel", vec!["-ib"]),
-	];
-
-	for (cmd, args) in all {
+	for (cmd, args) in clipboard_setters() {
 		let Ok(mut child) = Command::new(cmd)
 			.args(args)
 			.stdin(Stdio::piped())
@@ -74,7 +86,20 @@ pub async fn clipboard_set(s: impl AsRef<std::ffi::OsStr>) -> Result<()> {
 		}
 	}
 
-	bail!("failed to set clipboard")
+	clipboard_osc52(s.as_ref().as_bytes())
+}
+
+// Asks the terminal to set the clipboard with the OSC 52 escape sequence, which works over SSH,
+// though there's no way to know whether it's supported, or allowed, by the terminal
+#[cfg(not(target_os = "windows"))]
+fn clipboard_osc52(b: &[u8]) -> Result<()> {
+	use std::io::{stdout, Write};
+
+	use base64::{engine::general_purpose, Engine};
+
+	let mut stdout = stdout().lock();
+	write!(stdout, "\x1b]52;c;{}\x07", general_purpose::STANDARD.encode(b))?;
+	Ok(stdout.flush()?)
 }
 
 #[cfg(target_os = "windows")]
@@ -0,0 +1,210 @@
+use std::{collections::HashSet, env, fs, path::{Path, PathBuf}};
+
+use config::open::Opener;
+
+// An application of a `.desktop` file, as in the freedesktop.org Desktop Entry specification
+#[derive(Debug, PartialEq)]
+pub struct Desktop {
+	pub id:       String,
+	pub name:     String,
+	pub exec:     String,
+	pub terminal: bool,
+	mimes:        Vec<String>,
+}
+
+impl Desktop {
+	fn parse(id: String, s: &str) -> Option<Self> {
+		let (mut name, mut exec, mut mimes, mut terminal) = (None, None, vec![], false);
+		for (key, value) in entries(s, "Desktop Entry") {
+			match key {
+				"Type" if value != "Application" => return None,
+				"Hidden" if value == "true" => return None,
+				"Name" => name = Some(value.to_owned()),
+				"Exec" => exec = Some(value.to_owned()),
+				"MimeType" => mimes = value.split(';').filter(|m| !m.is_empty()).map(Into::into).collect(),
+				"Terminal" => terminal = value == "true",
+				_ => {}
+			}
+		}
+
+		let exec = exec.filter(|e| !e.is_empty())?;
+		let name = name.unwrap_or_else(|| id.trim_end_matches(".desktop").to_owned());
+		Some(Self { id, name, exec, terminal, mimes })
+	}
+
+	// The `Exec` line as an opener, with the field codes of the files replaced by the arguments of
+	// the shell, which are all given at the end if there's none of them
+	pub fn opener(&self) -> Opener {
+		let (mut exec, mut files, mut it) = (String::new(), false, self.exec.chars());
+		while let Some(c) = it.next() {
+			if c != '%' {
+				exec.push(c);
+				continue;
+			}
+			match it.next() {
+				Some('f' | 'u') => {
+					exec.push_str("\"$1\"");
+					files = true;
+				}
+				Some('F' | 'U') => {
+					exec.push_str("\"$@\"");
+					files = true;
+				}
+				Some('%') => exec.push('%'),
+				_ => {}
+			}
+		}
+
+		let exec = exec.trim().to_owned();
+		let exec = if files { exec } else { format!("{exec} \"$@\"") };
+		Opener {
+			spread:       exec.contains("$@"),
+			exec,
+			block:        self.terminal,
+			display_name: self.name.clone(),
+		}
+	}
+}
+
+// The applications of the system to open the files of `mime` with. There's no way to list the ones
+// registered for a type on macOS from the shell, so it's the default one, and none on Windows
+pub fn desktop_openers(mime: &str) -> Vec<Opener> {
+	if cfg!(target_os = "macos") {
+		let (exec, display_name) = (r#"open "$@""#.to_owned(), "Default application".to_owned());
+		vec![Opener { exec, block: false, display_name, spread: true }]
+	} else if cfg!(target_os = "windows") {
+		vec![]
+	} else {
+		desktop_apps(mime).iter().map(|a| a.opener()).collect()
+	}
+}
+
+// The applications that can open the files of `mime`, the default ones of `mimeapps.list` first
+pub fn desktop_apps(mime: &str) -> Vec<Desktop> {
+	let home = |var, default| {
+		env::var_os(var)
+			.filter(|s| !s.is_empty())
+			.map(PathBuf::from)
+			.or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(default)))
+	};
+	let dirs = |var, default: &str| {
+		let s = env::var(var).ok().filter(|s| !s.is_empty()).unwrap_or_else(|| default.to_owned());
+		s.split(':').map(PathBuf::from).collect::<Vec<_>>()
+	};
+
+	let data: Vec<_> = home("XDG_DATA_HOME", ".local/share")
+		.into_iter()
+		.chain(dirs("XDG_DATA_DIRS", "/usr/local/share:/usr/share"))
+		.map(|p| p.join("applications"))
+		.collect();
+	let lists = home("XDG_CONFIG_HOME", ".config")
+		.into_iter()
+		.chain(dirs("XDG_CONFIG_DIRS", "/etc/xdg"))
+		.chain(data.iter().cloned())
+		.filter_map(|p| fs::read_to_string(p.join("mimeapps.list")).ok());
+
+	let (mut defaults, mut added, mut removed) = (vec![], vec![], HashSet::new());
+	for s in lists {
+		associations(&s, "Default Applications", mime).for_each(|id| defaults.push(id));
+		associations(&s, "Added Associations", mime).for_each(|id| added.push(id));
+		removed.extend(associations(&s, "Removed Associations", mime));
+	}
+
+	// The first of the same ID wins, the directories are in the order of precedence
+	let (mut seen, mut apps) = (HashSet::new(), vec![]);
+	for dir in &data {
+		for (id, path) in desktop_files(dir) {
+			if seen.insert(id.clone()) {
+				if let Some(app) = fs::read_to_string(path).ok().and_then(|s| Desktop::parse(id, &s)) {
+					apps.push(app);
+				}
+			}
+		}
+	}
+
+	let rank = |app: &Desktop| {
+		let pos = |v: &Vec<String>| v.iter().position(|id| *id == app.id);
+		pos(&defaults).map_or_else(|| pos(&added).map_or((2, 0), |i| (1, i)), |i| (0, i))
+	};
+	apps.retain(|a| {
+		!removed.contains(&a.id)
+			&& (a.mimes.iter().any(|m| m == mime) || defaults.contains(&a.id) || added.contains(&a.id))
+	});
+	apps.sort_by(|a, b| rank(a).cmp(&rank(b)).then_with(|| a.name.cmp(&b.name)));
+	apps
+}
+
+// The `.desktop` files in `dir` with their IDs, where those in the subdirectories are prefixed
+// with the names of them, joined by `-`
+fn desktop_files(dir: &Path) -> Vec<(String, PathBuf)> {
+	let (mut files, mut stack) = (vec![], vec![(dir.to_path_buf(), String::new())]);
+	while let Some((dir, prefix)) = stack.pop() {
+		let Ok(it) = fs::read_dir(&dir) else {
+			continue;
+		};
+		for entry in it.flatten() {
+			let (path, name) = (entry.path(), entry.file_name().to_string_lossy().into_owned());
+			if path.is_dir() {
+				stack.push((path, format!("{prefix}{name}-")));
+			} else if name.ends_with(".desktop") {
+				files.push((format!("{prefix}{name}"), path));
+			}
+		}
+	}
+	files
+}
+
+// The keys and values of `group` in a file of the `.ini` like format,
+// leaving out the localized ones, e.g. `Name[fr]`
+fn entries<'a>(s: &'a str, group: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> {
+	let mut inside = false;
+	s.lines().filter_map(move |line| {
+		let line = line.trim();
+		if line.starts_with('[') {
+			inside = line == format!("[{group}]");
+			return None;
+		}
+		let (key, value) = line.split_once('=').filter(|_| inside && !line.starts_with('#'))?;
+		Some((key.trim(), value.trim())).filter(|(k, _)| !k.contains('['))
+	})
+}
+
+fn associations<'a>(
+	s: &'a str,
+	group: &'a str,
+	mime: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+	entries(s, group)
+		.filter(move |&(key, _)| key == mime)
+		.flat_map(|(_, value)| value.split(';'))
+		.filter(|id| !id.is_empty())
+		.map(Into::into)
+}
+
+#[test]
+fn test_desktop() {
+	let s = "[Desktop Entry]\nType=Application\nName=Image Viewer\nName[fr]=Visionneuse\n\
+	         Exec=viewer --new %U\nMimeType=image/png;image/jpeg;\n\n\
+	         [Desktop Action New]\nName=New Window\nExec=viewer --window\n";
+	let app = Desktop::parse("viewer.desktop".to_owned(), s).unwrap();
+	assert_eq!(app.name, "Image Viewer");
+	assert_eq!(app.mimes, ["image/png", "image/jpeg"]);
+	assert_eq!(app.opener().exec, r#"viewer --new "$@""#);
+	assert!(app.opener().spread);
+
+	let app =
+		|exec: &str| Desktop { exec: exec.to_owned(), ..Desktop::parse("a".into(), s).unwrap() };
+	assert_eq!(app("vim %f").opener().exec, r#"vim "$1""#);
+	assert!(!app("vim %f").opener().spread);
+	assert_eq!(app("player %i -x 100%%").opener().exec, r#"player  -x 100% "$@""#);
+	assert_eq!(app("editor").opener().exec, r#"editor "$@""#);
+
+	assert!(Desktop::parse("b".into(), "[Desktop Entry]\nType=Link\nExec=x\n").is_none());
+	assert!(Desktop::parse("b".into(), "[Desktop Entry]\nHidden=true\nExec=x\n").is_none());
+
+	let list = "[Default Applications]\nimage/png=viewer.desktop;\n\
+	            [Removed Associations]\nimage/png=gimp.desktop;other.desktop\n";
+	let ids: Vec<_> = associations(list, "Removed Associations", "image/png").collect();
+	assert_eq!(ids, ["gimp.desktop", "other.desktop"]);
+	assert_eq!(associations(list, "Default Applications", "image/jpeg").count(), 0);
+}
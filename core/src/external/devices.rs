@@ -0,0 +1,187 @@
+use std::{collections::HashSet, path::PathBuf, process::Stdio};
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+use tokio::{fs, process::Command};
+
+// A filesystem mounted, or a removable device that can be, whose `source` is the path of
+// the device, or where the filesystem is from, e.g. `host:/share` for NFS
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Device {
+	pub source:    String,
+	pub label:     Option<String>,
+	pub fstype:    String,
+	pub size:      Option<u64>,
+	pub mount:     Option<PathBuf>,
+	pub removable: bool,
+}
+
+impl Device {
+	// Whether it can be mounted or unmounted with `udisksctl`, as a block device
+	#[inline]
+	pub fn is_block(&self) -> bool { self.source.starts_with("/dev/") }
+}
+
+// The types of the filesystems that aren't on a device, e.g. `proc`, left out of the list
+const VIRTUAL: &[&str] = &[
+	"autofs", "binfmt_misc", "bpf", "cgroup", "cgroup2", "configfs", "debugfs", "devpts", "devtmpfs",
+	"efivarfs", "fusectl", "hugetlbfs", "mqueue", "nsfs", "overlay", "proc", "pstore", "ramfs",
+	"rpc_pipefs", "securityfs", "squashfs", "sysfs", "tmpfs", "tracefs",
+];
+
+// The filesystems mounted, from `/proc/mounts`, and the removable devices known to `lsblk`
+// that aren't, where the ones `lsblk` knows of are given their labels and sizes
+pub async fn devices() -> Result<Vec<Device>> {
+	let mounts = fs::read_to_string("/proc/mounts").await?;
+	let mut devices = parse_mounts(&mounts);
+
+	let output = Command::new("lsblk")
+		.args(["--json", "--bytes", "--output", "PATH,LABEL,FSTYPE,SIZE,MOUNTPOINT,RM"])
+		.stdin(Stdio::null())
+		.kill_on_drop(true)
+		.output()
+		.await;
+	let Ok(output) = output.map_err(|e| tracing::warn!("failed to run `lsblk`: {e}")) else {
+		return Ok(devices);
+	};
+
+	for blk in parse_lsblk(&String::from_utf8_lossy(&output.stdout)) {
+		if let Some(d) = devices.iter_mut().find(|d| d.source == blk.source) {
+			(d.label, d.size, d.removable) = (blk.label, blk.size, blk.removable);
+		} else if blk.removable && blk.mount.is_none() && !blk.fstype.is_empty() {
+			devices.push(blk);
+		}
+	}
+	Ok(devices)
+}
+
+fn parse_mounts(s: &str) -> Vec<Device> {
+	let mut seen = HashSet::new();
+	let mut devices = vec![];
+	for line in s.lines() {
+		let mut it = line.split_whitespace();
+		let (Some(source), Some(mount), Some(fstype)) = (it.next(), it.next(), it.next()) else {
+			continue;
+		};
+		if VIRTUAL.contains(&fstype) || source == "none" || !seen.insert(source.to_owned()) {
+			continue;
+		}
+
+		devices.push(Device {
+			source: unescape(source),
+			fstype: fstype.to_owned(),
+			mount: Some(PathBuf::from(unescape(mount))),
+			..Default::default()
+		});
+	}
+	devices
+}
+
+fn parse_lsblk(s: &str) -> Vec<Device> {
+	fn walk(v: &Value, devices: &mut Vec<Device>) {
+		for d in v.as_array().into_iter().flatten() {
+			let str = |k: &str| d[k].as_str().filter(|s| !s.is_empty()).map(|s| s.to_owned());
+			devices.push(Device {
+				source:    str("path").unwrap_or_default(),
+				label:     str("label"),
+				fstype:    str("fstype").unwrap_or_default(),
+				size:      d["size"].as_u64(),
+				mount:     str("mountpoint").map(PathBuf::from),
+				// A bool by the newer versions, and a string of `0` or `1` by the older ones
+				removable: d["rm"].as_bool().unwrap_or(d["rm"] == "1"),
+			});
+			walk(&d["children"], devices);
+		}
+	}
+
+	let mut devices = vec![];
+	if let Ok(v) = serde_json::from_str::<Value>(s) {
+		walk(&v["blockdevices"], &mut devices);
+	}
+	devices
+}
+
+// The spaces and the like are escaped by `/proc/mounts` as octal, e.g. `\040`
+fn unescape(s: &str) -> String {
+	let (mut out, b) = (Vec::with_capacity(s.len()), s.as_bytes());
+	let mut i = 0;
+	while i < b.len() {
+		let oct = b.get(i + 1..i + 4).and_then(|o| std::str::from_utf8(o).ok());
+		let oct = oct.and_then(|o| u8::from_str_radix(o, 8).ok());
+		match oct {
+			Some(c) if b[i] == b'\\' => {
+				out.push(c);
+				i += 4;
+			}
+			_ => {
+				out.push(b[i]);
+				i += 1;
+			}
+		}
+	}
+	String::from_utf8_lossy(&out).into_owned()
+}
+
+// Mounts the device with `udisksctl`, and tells where, without it ever asking for a password, so
+// that one that needs it fails with the error of `udisksctl` instead
+pub async fn udisks_mount(device: &str) -> Result<PathBuf> {
+	let stdout = udisksctl("mount", device).await?;
+	// Such as `Mounted /dev/sdb1 at /media/user/USB`
+	match stdout.trim().trim_end_matches('.').split_once(" at ") {
+		Some((_, mount)) => Ok(PathBuf::from(mount)),
+		None => bail!("unknown output of `udisksctl`: {stdout}"),
+	}
+}
+
+pub async fn udisks_unmount(device: &str) -> Result<()> {
+	udisksctl("unmount", device).await.map(|_| ())
+}
+
+async fn udisksctl(action: &str, device: &str) -> Result<String> {
+	let output = Command::new("udisksctl")
+		.args([action, "--no-user-interaction", "-b", device])
+		.stdin(Stdio::null())
+		.kill_on_drop(true)
+		.output()
+		.await
+		.map_err(|e| anyhow!("failed to run `udisksctl`, is udisks2 installed? ({e})"))?;
+
+	if !output.status.success() {
+		let err = String::from_utf8_lossy(&output.stderr);
+		bail!("failed to {action} {device}: {}", err.trim());
+	}
+	Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[test]
+fn test_devices() {
+	let mounts = "\
+/dev/nvme0n1p2 / ext4 rw,relatime 0 0
+proc /proc proc rw,nosuid 0 0
+tmpfs /run tmpfs rw,nosuid 0 0
+/dev/sdb1 /media/user/MY\\040USB vfat rw 0 0
+/dev/sdb1 /mnt/again vfat rw 0 0
+host:/share /mnt/nfs nfs4 rw 0 0
+";
+	let devices = parse_mounts(mounts);
+	let sources: Vec<_> = devices.iter().map(|d| d.source.as_str()).collect();
+	assert_eq!(sources, ["/dev/nvme0n1p2", "/dev/sdb1", "host:/share"]);
+	assert_eq!(devices[1].mount, Some(PathBuf::from("/media/user/MY USB")));
+	assert!(devices[1].is_block() && !devices[2].is_block());
+
+	let lsblk = r#"{"blockdevices": [{
+		"path": "/dev/sdc", "label": null, "fstype": null, "size": 16000000000,
+		"mountpoint": null, "rm": true,
+		"children": [{
+			"path": "/dev/sdc1", "label": "STICK", "fstype": "exfat", "size": 15999000000,
+			"mountpoint": null, "rm": "1"
+		}]
+	}]}"#;
+	let blk = parse_lsblk(lsblk);
+	assert_eq!(blk.len(), 2);
+	assert_eq!(blk[1].label.as_deref(), Some("STICK"));
+	assert_eq!(blk[1].size, Some(15999000000));
+	assert!(blk[0].removable && blk[1].removable && blk[1].mount.is_none());
+
+	assert_eq!(unescape("a\\040b\\011c\\"), "a b\tc\\");
+}
@@ -0,0 +1,66 @@
+use std::{path::Path, process::Stdio};
+
+use shared::PeekError;
+use tokio::{io::{AsyncBufReadExt, AsyncReadExt, BufReader}, process::Command};
+
+// How two files compare by `diff`, with the lines of the unified diff of them in view if they differ
+#[derive(Debug, PartialEq, Eq)]
+pub enum Diffed {
+	Same,
+	Binary,
+	Lines(Vec<String>),
+}
+
+// The `limit` lines of the diff from the one at `skip`, without reading any further into it
+pub async fn diff(old: &Path, new: &Path, skip: usize, limit: usize) -> Result<Diffed, PeekError> {
+	let mut child = Command::new("diff")
+		.arg("-u")
+		.arg("-L")
+		.arg(old)
+		.arg("-L")
+		.arg(new)
+		.arg("--")
+		.args([old, new])
+		.stdin(Stdio::null())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.kill_on_drop(true)
+		.spawn()
+		.map_err(|e| format!("failed to run `diff`: {e}"))?;
+
+	let mut i = 0;
+	let mut it = BufReader::new(child.stdout.take().unwrap()).lines();
+	let mut lines = Vec::with_capacity(limit);
+	while let Ok(Some(line)) = it.next_line().await {
+		i += 1;
+		if i == 1 && is_binary(&line) {
+			return Ok(Diffed::Binary);
+		} else if i > skip + limit {
+			break;
+		} else if i > skip {
+			lines.push(line);
+		}
+	}
+
+	if i == 0 {
+		let mut stderr = String::new();
+		child.stderr.take().unwrap().read_to_string(&mut stderr).await.ok();
+		return match child.wait().await?.code() {
+			Some(0) => Ok(Diffed::Same),
+			_ => Err(stderr.trim().to_owned().into()),
+		};
+	}
+
+	child.start_kill().ok();
+	if skip > 0 && i < skip + limit {
+		Err(PeekError::Exceed(i.saturating_sub(limit)))
+	} else {
+		Ok(Diffed::Lines(lines))
+	}
+}
+
+// Such as `Binary files a and b differ`, or `Files a and b differ` by some of the others
+#[inline]
+fn is_binary(line: &str) -> bool {
+	(line.starts_with("Binary files ") || line.starts_with("Files ")) && line.ends_with(" differ")
+}
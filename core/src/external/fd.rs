@@ -4,7 +4,7 @@ use anyhow::Result;
 use shared::Url;
 use tokio::{io::{AsyncBufReadExt, BufReader}, process::Command, sync::mpsc::{self, UnboundedReceiver}};
 
-use crate::files::File;
+use crate::files::{ignore_rules, File};
 
 pub struct FdOpt {
 	pub cwd:     Url,
@@ -31,8 +31,11 @@ pub fn fd(opt: FdOpt) -> Result<UnboundedReceiver<File>> {
 	let (tx, rx) = mpsc::unbounded_channel();
 
 	tokio::spawn(async move {
+		// Found as well if the config of the user for the command has it not respect `.gitignore`
+		let rules = ignore_rules(&opt.cwd).await;
 		while let Ok(Some(line)) = it.next_line().await {
-			if let Ok(file) = File::from(opt.cwd.join(line)).await {
+			if let Ok(mut file) = File::from(opt.cwd.join(line)).await {
+				file.ignore_by(rules.as_ref());
 				tx.send(file).ok();
 			}
 		}
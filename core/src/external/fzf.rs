@@ -1,27 +1,101 @@
-use std::process::Stdio;
+use std::{path::Path, process::Stdio};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
+use config::MANAGER;
+use ignore::WalkBuilder;
 use shared::Url;
-use tokio::{process::Command, sync::oneshot::{self, Receiver}};
+use tokio::{io::AsyncWriteExt, process::{Child, ChildStdin, Command}, sync::{mpsc, oneshot::{self, Receiver}}, task};
 
 pub struct FzfOpt {
 	pub cwd: Url,
 }
 
 pub fn fzf(opt: FzfOpt) -> Result<Receiver<Result<Url>>> {
-	let child =
-		Command::new("fzf").current_dir(&opt.cwd).kill_on_drop(true).stdout(Stdio::piped()).spawn()?;
+	let mut lister = list(&opt.cwd);
+	let stdin: Stdio = match lister.as_mut().and_then(|c| c.stdout.take()) {
+		Some(stdout) => stdout.try_into()?,
+		None => Stdio::piped(),
+	};
+
+	let mut child = Command::new("fzf")
+		.args(&MANAGER.jump_fzf_args)
+		.current_dir(&opt.cwd)
+		.kill_on_drop(true)
+		.stdin(stdin)
+		.stdout(Stdio::piped())
+		.spawn()?;
+
+	if let Some(stdin) = child.stdin.take() {
+		tokio::spawn(walk(opt.cwd.clone(), stdin));
+	}
 
 	let (tx, rx) = oneshot::channel();
 	tokio::spawn(async move {
-		if let Ok(output) = child.wait_with_output().await {
-			let selected = String::from_utf8_lossy(&output.stdout).trim().to_string();
-			if !selected.is_empty() {
-				tx.send(Ok(opt.cwd.join(selected))).ok();
-				return;
-			}
+		tx.send(wait(child, &opt.cwd).await).ok();
+		if let Some(mut lister) = lister {
+			lister.kill().await.ok();
 		}
-		tx.send(Err(anyhow::anyhow!("No match"))).ok();
 	});
 	Ok(rx)
 }
+
+fn list(cwd: &Path) -> Option<Child> {
+	let mut cmd = if MANAGER.jump_list.is_empty() {
+		let mut cmd = Command::new("fd");
+		cmd.arg("--color=never");
+		cmd
+	} else {
+		#[cfg(not(target_os = "windows"))]
+		let mut cmd = Command::new("sh");
+		#[cfg(not(target_os = "windows"))]
+		cmd.arg("-c").arg(&MANAGER.jump_list);
+
+		#[cfg(target_os = "windows")]
+		let mut cmd = Command::new("cmd");
+		#[cfg(target_os = "windows")]
+		cmd.arg("/C").arg(&MANAGER.jump_list);
+		cmd
+	};
+
+	cmd
+		.current_dir(cwd)
+		.kill_on_drop(true)
+		.stdin(Stdio::null())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::null())
+		.spawn()
+		.ok()
+}
+
+async fn walk(cwd: Url, mut stdin: ChildStdin) {
+	let (tx, mut rx) = mpsc::unbounded_channel();
+	task::spawn_blocking(move || {
+		for entry in WalkBuilder::new(&cwd).build().flatten().filter(|e| e.depth() > 0) {
+			let Ok(path) = entry.path().strip_prefix(&cwd) else {
+				continue;
+			};
+			if tx.send(format!("{}\n", path.display())).is_err() {
+				break;
+			}
+		}
+	});
+
+	while let Some(line) = rx.recv().await {
+		if stdin.write_all(line.as_bytes()).await.is_err() {
+			break;
+		}
+	}
+}
+
+async fn wait(child: Child, cwd: &Url) -> Result<Url> {
+	let output = child.wait_with_output().await?;
+	if output.status.code() == Some(130) {
+		bail!("canceled");
+	}
+
+	let selected = String::from_utf8_lossy(&output.stdout).trim().to_string();
+	if selected.is_empty() {
+		return Err(anyhow!("No match"));
+	}
+	Ok(cwd.join(selected))
+}
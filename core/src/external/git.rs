@@ -0,0 +1,146 @@
+use std::{collections::BTreeMap, path::{Component, Path}};
+
+use anyhow::{bail, Result};
+use shared::Url;
+use tokio::process::Command;
+
+// The status of a file in its git repository, from the least to the most worth noticing, so that
+// a directory takes the worst of the files in it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GitStatus {
+	Ignored,
+	Untracked,
+	Staged,
+	Modified,
+	Conflicted,
+}
+
+impl GitStatus {
+	fn parse(xy: &str) -> Option<Self> {
+		let (x, y) = (xy.chars().next()?, xy.chars().nth(1)?);
+		Some(match (x, y) {
+			('!', '!') => Self::Ignored,
+			('?', '?') => Self::Untracked,
+			('U', _) | (_, 'U') | ('A', 'A') | ('D', 'D') => Self::Conflicted,
+			(_, ' ') => Self::Staged,
+			_ => Self::Modified,
+		})
+	}
+
+	#[inline]
+	pub fn badge(self) -> &'static str {
+		match self {
+			Self::Ignored => "!",
+			Self::Untracked => "?",
+			Self::Staged => "+",
+			Self::Modified => "M",
+			Self::Conflicted => "U",
+		}
+	}
+}
+
+// The repository `dir` is in, where a `.git` directory or file is, as `gitignore` finds it
+pub fn git_root(dir: &Path) -> Option<&Path> { dir.ancestors().find(|p| p.join(".git").exists()) }
+
+// The branch checked out in the repository `dir` is in, read from its `HEAD` for it to be cheap
+// enough to read again on each change to the repository, or the short hash of the commit if it's
+// detached
+pub fn git_branch(dir: &Path) -> Option<String> {
+	let git = git_root(dir)?.join(".git");
+	// A worktree or a submodule has a file telling where its git directory is instead
+	let git = match std::fs::read_to_string(&git) {
+		Ok(s) => git.parent()?.join(s.strip_prefix("gitdir:")?.trim()),
+		Err(_) => git,
+	};
+	git_head(&std::fs::read_to_string(git.join("HEAD")).ok()?)
+}
+
+fn git_head(s: &str) -> Option<String> {
+	let s = s.trim();
+	if let Some(r) = s.strip_prefix("ref:") {
+		let r = r.trim();
+		return Some(r.strip_prefix("refs/heads/").unwrap_or(r).to_owned());
+	}
+	(s.len() >= 7 && s.chars().all(|c| c.is_ascii_hexdigit())).then(|| s[..7].to_owned())
+}
+
+// The status of the files in `dir`, with that of the directories being the worst of the files under
+// them, except for the ignored ones, which don't make the directories they're in ignored
+pub async fn git_status(dir: &Url) -> Result<BTreeMap<Url, GitStatus>> {
+	let Some(root) = git_root(dir) else {
+		return Ok(Default::default());
+	};
+
+	let output = Command::new("git")
+		.arg("-C")
+		.arg(dir)
+		.args(["status", "--porcelain", "-z", "--ignored", "--", "."])
+		.kill_on_drop(true)
+		.output()
+		.await?;
+	if !output.status.success() {
+		bail!("failed to get the git status: {}", String::from_utf8_lossy(&output.stderr).trim());
+	}
+	Ok(git_parse(root, dir, &String::from_utf8_lossy(&output.stdout)))
+}
+
+fn git_parse(root: &Path, dir: &Path, s: &str) -> BTreeMap<Url, GitStatus> {
+	let mut map = BTreeMap::new();
+	let mut it = s.split('\0');
+	while let Some(entry) = it.next() {
+		if entry.len() < 4 {
+			continue;
+		}
+
+		let (xy, path) = entry.split_at(3);
+		// The path a file is renamed or copied from comes after it
+		if xy.starts_with(['R', 'C']) {
+			it.next();
+		}
+
+		let Some(status) = GitStatus::parse(xy) else {
+			continue;
+		};
+		let Ok(rel) = root.join(path).strip_prefix(dir).map(|p| p.to_owned()) else {
+			continue;
+		};
+
+		let mut components = rel.components().filter(|c| matches!(c, Component::Normal(_)));
+		let Some(child) = components.next() else {
+			continue;
+		};
+		if status == GitStatus::Ignored && components.next().is_some() {
+			continue;
+		}
+
+		let url = Url::from(dir.join(child));
+		map.entry(url).and_modify(|s: &mut GitStatus| *s = (*s).max(status)).or_insert(status);
+	}
+	map
+}
+
+#[test]
+fn test_git_parse() {
+	let (root, dir) = (Path::new("/repo"), Path::new("/repo/src"));
+	let s = " M src/main.rs\0M  src/lib.rs\0?? src/new/\0!! src/target/\0!! src/sub/a.log\0\
+	         UU src/sub/conflict.rs\0A  src/sub/b.rs\0R  src/c.rs\0src/old.rs\0 M README.md\0";
+	let map = git_parse(root, dir, s);
+
+	let get = |name: &str| map.get(&Url::from(dir.join(name))).copied();
+	assert_eq!(get("main.rs"), Some(GitStatus::Modified));
+	assert_eq!(get("lib.rs"), Some(GitStatus::Staged));
+	assert_eq!(get("new"), Some(GitStatus::Untracked));
+	assert_eq!(get("target"), Some(GitStatus::Ignored));
+	assert_eq!(get("sub"), Some(GitStatus::Conflicted));
+	assert_eq!(get("c.rs"), Some(GitStatus::Staged));
+	assert_eq!(get("old.rs"), None);
+	assert_eq!(map.len(), 6);
+}
+
+#[test]
+fn test_git_head() {
+	assert_eq!(git_head("ref: refs/heads/main\n").as_deref(), Some("main"));
+	assert_eq!(git_head("ref: refs/heads/feat/x").as_deref(), Some("feat/x"));
+	assert_eq!(git_head("0123456789abcdef0123456789abcdef01234567\n").as_deref(), Some("0123456"));
+	assert_eq!(git_head("garbage"), None);
+}
@@ -0,0 +1,94 @@
+use std::{io::ErrorKind, process::Stdio};
+
+use anyhow::{anyhow, bail, Result};
+use shared::Url;
+use tokio::{fs, io::{AsyncBufReadExt, AsyncReadExt, BufReader}, process::{Child, Command}, select, sync::mpsc::{self, UnboundedReceiver}};
+
+pub struct LocateOpt {
+	pub subject: String,
+}
+
+// What the index of the system tells of a file whose name has the subject in it, where it's gone
+// if the index is stale, or the tool failed at the end, e.g. with no index built yet
+#[derive(Debug, PartialEq, Eq)]
+pub enum Located {
+	Found(Url),
+	Gone(Url),
+	Failed(String),
+}
+
+// The tools asked in turn, the first one installed is used
+#[cfg(target_os = "macos")]
+const TOOLS: &[(&str, &[&str])] = &[("mdfind", &["-name"])];
+#[cfg(not(target_os = "macos"))]
+const TOOLS: &[(&str, &[&str])] = &[("plocate", &["-i", "-b"]), ("locate", &["-i", "-b"])];
+
+fn spawn(subject: &str) -> Result<(&'static str, Child)> {
+	for &(tool, args) in TOOLS {
+		let child = Command::new(tool)
+			.args(args)
+			.arg(subject)
+			.kill_on_drop(true)
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.spawn();
+
+		match child {
+			Ok(child) => return Ok((tool, child)),
+			Err(e) if e.kind() == ErrorKind::NotFound => continue,
+			Err(e) => bail!("failed to run `{tool}`: {e}"),
+		}
+	}
+
+	let tools: Vec<_> = TOOLS.iter().map(|(t, _)| format!("`{t}`")).collect();
+	Err(anyhow!("no index to search, {} isn't installed", tools.join(" or ")))
+}
+
+pub fn locate(opt: LocateOpt) -> Result<UnboundedReceiver<Located>> {
+	let (tool, mut child) = spawn(&opt.subject)?;
+	let mut stderr = child.stderr.take().unwrap();
+	let stderr = tokio::spawn(async move {
+		let mut s = String::new();
+		stderr.read_to_string(&mut s).await.ok();
+		s
+	});
+
+	let mut it = BufReader::new(child.stdout.take().unwrap()).lines();
+	let (tx, rx) = mpsc::unbounded_channel();
+
+	tokio::spawn(async move {
+		loop {
+			let line = select! {
+				_ = tx.closed() => {
+					child.start_kill().ok();
+					return;
+				}
+				line = it.next_line() => line,
+			};
+
+			let Ok(Some(line)) = line else {
+				break;
+			};
+			let url = Url::from(line);
+			if fs::symlink_metadata(&url).await.is_ok() {
+				tx.send(Located::Found(url)).ok();
+			} else {
+				tx.send(Located::Gone(url)).ok();
+			}
+		}
+
+		let err = stderr.await.unwrap_or_default();
+		match child.wait().await {
+			Ok(status) if status.success() => {}
+			// `locate` exits with 1 if nothing is found, where nothing is on stderr either
+			Ok(_) if err.trim().is_empty() => {}
+			Ok(_) => {
+				tx.send(Located::Failed(format!("`{tool}` failed: {}", err.trim()))).ok();
+			}
+			Err(e) => {
+				tx.send(Located::Failed(format!("`{tool}` failed: {e}"))).ok();
+			}
+		}
+	});
+	Ok(rx)
+}
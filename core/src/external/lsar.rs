@@ -3,8 +3,9 @@ use std::path::Path;
 use anyhow::anyhow;
 use serde::Deserialize;
 use serde_json::Value;
-use shared::PeekError;
+use shared::{readable_size, PeekError};
 use tokio::process::Command;
+use unicode_width::UnicodeWidthStr;
 
 #[derive(Debug)]
 pub enum LsarAttr {
@@ -25,18 +26,50 @@ pub struct LsarFile {
 	pub compressed_size:  Option<usize>,
 	#[serde(rename = "XADCompressionName")]
 	pub compression_name: Option<String>,
+	// The target of a symlink, where the format keeps it apart from the data, as tar and rar do
+	#[serde(rename = "XADLinkDestination", default)]
+	pub link_to:          Option<String>,
 
 	#[serde(skip)]
 	pub attributes: Option<LsarAttr>,
+	#[serde(skip)]
+	pub is_dir:     bool,
+	#[serde(skip)]
+	pub is_link:    bool,
+	#[serde(skip)]
+	pub encrypted:  bool,
 }
 
+// Why the files of an archive can't be listed, with its names encrypted, but without a password
+// or with the wrong one
+pub const LSAR_LOCKED: &str = "The archive is password-protected, its contents can't be listed";
+
+// Lists all the files in the archive, from its central directory without extracting any of them
+#[inline]
+pub async fn lsar(path: &Path) -> Result<Vec<LsarFile>, PeekError> { lsar_with(path, None).await }
+
 #[allow(clippy::manual_map)]
-pub async fn lsar(path: &Path, skip: usize, limit: usize) -> Result<Vec<LsarFile>, PeekError> {
-	let output =
-		Command::new("lsar").args(["-j", "-jss"]).arg(path).kill_on_drop(true).output().await?;
+pub async fn lsar_with(path: &Path, password: Option<&str>) -> Result<Vec<LsarFile>, PeekError> {
+	let mut cmd = Command::new("lsar");
+	if let Some(password) = password {
+		cmd.args(["-p", password]);
+	}
+
+	let output = cmd
+		.args(["-j", "-jss"])
+		.arg(path)
+		.kill_on_drop(true)
+		.output()
+		.await
+		.map_err(|e| format!("Failed to run lsar to list the archive: {e}"))?;
 
 	if !output.status.success() {
-		return Err(String::from_utf8_lossy(&output.stderr).to_string().into());
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		return Err(if stderr.to_lowercase().contains("password") {
+			LSAR_LOCKED.into()
+		} else {
+			format!("The archive is corrupt or unsupported: {}", stderr.trim()).into()
+		});
 	}
 
 	#[derive(Deserialize)]
@@ -48,16 +81,8 @@ pub async fn lsar(path: &Path, skip: usize, limit: usize) -> Result<Vec<LsarFile
 	let output = String::from_utf8_lossy(&output.stdout);
 	let contents = serde_json::from_str::<Outer>(output.trim()).map_err(|e| anyhow!(e))?.contents;
 
-	let mut i = 0;
-	let mut files = Vec::with_capacity(limit);
+	let mut files = Vec::with_capacity(contents.len());
 	for content in contents {
-		i += 1;
-		if i > skip + limit {
-			break;
-		} else if i <= skip {
-			continue;
-		}
-
 		let attributes = if let Some(p) = content.get("XADPosixPermissions").and_then(|p| p.as_u64()) {
 			Some(LsarAttr::Posix(p as u16))
 		} else if let Some(a) = content.get("XADWindowsFileAttributes").and_then(|a| a.as_u64()) {
@@ -68,14 +93,85 @@ pub async fn lsar(path: &Path, skip: usize, limit: usize) -> Result<Vec<LsarFile
 			None
 		};
 
+		let flag = |key: &str| content.get(key).and_then(|v| v.as_u64()).is_some_and(|v| v != 0);
+		let (is_dir, is_link) = (flag("XADIsDirectory"), flag("XADIsLink"));
+		let encrypted = flag("XADIsEncrypted");
+
 		let mut file = serde_json::from_value::<LsarFile>(content).map_err(|e| anyhow!(e))?;
 		file.attributes = attributes;
+		file.is_dir = is_dir;
+		file.is_link = is_link;
+		file.encrypted = encrypted;
 		files.push(file);
 	}
+	Ok(files)
+}
+
+// Lays the files out as a tree of their paths, a line for each file and directory,
+// with the size of the files aligned to the right of `width`
+pub fn lsar_tree(files: &[LsarFile], width: usize) -> Vec<String> {
+	let mut entries: Vec<_> = files
+		.iter()
+		.map(|f| (f.name.split('/').filter(|&c| !c.is_empty() && c != ".").collect::<Vec<_>>(), f))
+		.collect();
+	entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+	let mut lines = Vec::with_capacity(entries.len());
+	let mut stack: Vec<&str> = Vec::new();
+	for (parts, file) in &entries {
+		let Some((&name, parents)) = parts.split_last() else {
+			continue;
+		};
+
+		// The directories not listed by themselves are still shown, from their descendants
+		let dirs = if file.is_dir { &parts[..] } else { parents };
+		let common = stack.iter().zip(dirs).take_while(|(a, b)| a == b).count();
+		stack.truncate(common);
+		for &dir in &dirs[common..] {
+			lines.push(format!("{}{dir}/", "  ".repeat(stack.len())));
+			stack.push(dir);
+		}
+		if file.is_dir {
+			continue;
+		}
 
-	if skip > 0 && files.len() < limit {
-		Err(PeekError::Exceed(i.saturating_sub(limit)))
-	} else {
-		Ok(files)
+		let lock = if file.encrypted { " 🔒" } else { "" };
+		let name = format!("{}{name}{lock}", "  ".repeat(stack.len()));
+		let size = file.size.map(|s| readable_size(s as u64)).unwrap_or_default();
+		let pad = width.saturating_sub(name.width() + size.len()).max(1);
+		lines.push(format!("{name}{}{size}", " ".repeat(pad)));
 	}
+	lines
+}
+
+#[test]
+fn test_lsar_tree() {
+	let file = |name: &str, size, is_dir| LsarFile {
+		name: name.to_owned(),
+		last_modified: String::new(),
+		size,
+		compressed_size: None,
+		compression_name: None,
+		link_to: None,
+		attributes: None,
+		is_dir,
+		is_link: false,
+		encrypted: name.ends_with(".key"),
+	};
+
+	let files = [
+		file("src/main.rs", Some(2048), false),
+		file("README.md", Some(10), false),
+		file("src/", None, true),
+		file("./src/ui/app.key", Some(0), false),
+		file("docs", None, true),
+	];
+	assert_eq!(lsar_tree(&files, 24), [
+		"README.md         10.0 B",
+		"docs/",
+		"src/",
+		"  main.rs         2.0 KB",
+		"  ui/",
+		"    app.key 🔒     0.0 B",
+	]);
 }
@@ -1,25 +1,45 @@
+mod archive;
 mod clipboard;
+mod desktop;
+mod devices;
+mod diff;
 mod fd;
 mod ffmpegthumbnailer;
 mod file;
 mod fzf;
+mod git;
 mod jq;
+mod locate;
 mod lsar;
+mod pandoc;
 mod pdftoppm;
+mod pdftotext;
+mod previewer;
 mod rg;
 mod shell;
+mod sort;
 mod unar;
 mod zoxide;
 
+pub use archive::*;
 pub use clipboard::*;
+pub use desktop::*;
+pub use devices::*;
+pub use diff::*;
 pub use fd::*;
 pub use ffmpegthumbnailer::*;
 pub use file::*;
 pub use fzf::*;
+pub use git::*;
 pub use jq::*;
+pub use locate::*;
 pub use lsar::*;
+pub use pandoc::*;
 pub use pdftoppm::*;
+pub use pdftotext::*;
+pub use previewer::*;
 pub use rg::*;
 pub use shell::*;
+pub use sort::*;
 pub use unar::*;
 pub use zoxide::*;
@@ -0,0 +1,19 @@
+use std::path::Path;
+
+use shared::PeekError;
+use tokio::process::Command;
+
+// The text of a document, e.g. `.docx` or `.odt`, without wrapping the lines
+pub async fn pandoc(path: &Path) -> Result<String, PeekError> {
+	let output = Command::new("pandoc")
+		.args(["--to", "plain", "--wrap", "none"])
+		.arg(path)
+		.kill_on_drop(true)
+		.output()
+		.await?;
+
+	if !output.status.success() {
+		return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned().into());
+	}
+	Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
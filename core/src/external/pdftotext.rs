@@ -0,0 +1,21 @@
+use std::path::Path;
+
+use shared::PeekError;
+use tokio::process::Command;
+
+// The text of the first `pages` pages with their layout, each followed by a form feed
+pub async fn pdftotext(path: &Path, pages: usize) -> Result<String, PeekError> {
+	let output = Command::new("pdftotext")
+		.args(["-layout", "-enc", "UTF-8", "-l"])
+		.arg(pages.max(1).to_string())
+		.arg(path)
+		.arg("-")
+		.kill_on_drop(true)
+		.output()
+		.await?;
+
+	if !output.status.success() {
+		return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned().into());
+	}
+	Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
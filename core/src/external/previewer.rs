@@ -0,0 +1,113 @@
+use std::{path::{Path, PathBuf}, sync::{Arc, OnceLock}, time::{Duration, SystemTime}};
+
+use config::PREVIEW;
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use shared::PeekError;
+use tokio::{fs, time::timeout};
+
+use super::{shell, ShellOpt};
+
+// The exit code of a previewer to show an image instead of text
+pub const PREVIEWER_IMAGE: i32 = 6;
+
+static PREVIEWED: OnceLock<Mutex<IndexMap<PathBuf, Previewed>>> = OnceLock::new();
+
+type Previewed = (SystemTime, (u16, u16), Arc<PreviewerOutput>);
+
+// What a previewer wants to be shown, told by its exit code
+#[derive(Debug, PartialEq)]
+pub enum PreviewerOutput {
+	// Exited with 0, the text printed
+	Text(String),
+	// Exited with `PREVIEWER_IMAGE`, the path to an image printed on the first line, and optionally
+	// where to show it in the pane as `x y w h` on the second, in cells
+	Image(PathBuf, Option<(u16, u16, u16, u16)>),
+}
+
+impl PreviewerOutput {
+	fn parse(code: Option<i32>, stdout: &[u8], stderr: &[u8]) -> Result<Self, String> {
+		let stdout = String::from_utf8_lossy(stdout);
+		match code {
+			Some(0) => Ok(Self::Text(stdout.into_owned())),
+			Some(PREVIEWER_IMAGE) => {
+				let mut lines = stdout.lines();
+				let path = lines.next().map(str::trim).filter(|s| !s.is_empty());
+				let Some(path) = path else {
+					return Err("The previewer printed no image path".to_owned());
+				};
+
+				let area = lines.next().and_then(|s| {
+					let n = s.split_whitespace().map(|n| n.parse().ok()).collect::<Option<Vec<_>>>()?;
+					if let [x, y, w, h] = n[..] { Some((x, y, w, h)) } else { None }
+				});
+				Ok(Self::Image(PathBuf::from(path), area))
+			}
+			_ => Err(format!(
+				"The previewer exited with {}: {}",
+				code.map_or("a signal".to_owned(), |c| c.to_string()),
+				String::from_utf8_lossy(stderr).trim()
+			)),
+		}
+	}
+}
+
+// Runs the previewer, where `%p` in `exec` is the path of the file, and `%w`/`%h` the size of the
+// preview pane. Its output is cached by the path, the modification time of the file, and the size,
+// for up to `previewer_cache` files, and it's killed once it runs longer than `previewer_timeout`
+pub async fn previewer(
+	exec: &str,
+	path: &Path,
+	(w, h): (u16, u16),
+) -> Result<Arc<PreviewerOutput>, PeekError> {
+	let mtime = fs::metadata(path).await?.modified()?;
+	let cache = PREVIEWED.get_or_init(Default::default);
+	if let Some((m, size, output)) = cache.lock().get(path) {
+		if *m == mtime && *size == (w, h) {
+			return Ok(output.clone());
+		}
+	}
+
+	#[cfg(not(target_os = "windows"))]
+	let cmd = exec.replace("%p", r#""$1""#);
+	#[cfg(target_os = "windows")]
+	let cmd = exec.replace("%p", &format!("\"{}\"", path.display()));
+	let cmd = cmd.replace("%w", &w.to_string()).replace("%h", &h.to_string());
+
+	let child = shell(ShellOpt { cmd: cmd.into(), args: vec![path.into()], piped: true })?;
+	let output = timeout(Duration::from_secs(PREVIEW.previewer_timeout), child.wait_with_output())
+		.await
+		.map_err(|_| "The previewer timed out")??;
+
+	let output = PreviewerOutput::parse(output.status.code(), &output.stdout, &output.stderr)?;
+	let output = Arc::new(output);
+	if PREVIEW.previewer_cache > 0 {
+		let mut cache = cache.lock();
+		cache.shift_remove(path);
+		cache.insert(path.to_path_buf(), (mtime, (w, h), output.clone()));
+		while cache.len() > PREVIEW.previewer_cache {
+			cache.shift_remove_index(0);
+		}
+	}
+	Ok(output)
+}
+
+#[test]
+fn test_previewer_output() {
+	use PreviewerOutput::*;
+
+	assert_eq!(PreviewerOutput::parse(Some(0), b"# Title\n", b""), Ok(Text("# Title\n".to_owned())));
+	assert_eq!(
+		PreviewerOutput::parse(Some(6), b"/tmp/a.png\n2 1 30 20\n", b""),
+		Ok(Image("/tmp/a.png".into(), Some((2, 1, 30, 20))))
+	);
+	assert_eq!(
+		PreviewerOutput::parse(Some(6), b"/tmp/a.png\n", b""),
+		Ok(Image("/tmp/a.png".into(), None))
+	);
+	assert!(PreviewerOutput::parse(Some(6), b"\n", b"").is_err());
+	assert_eq!(
+		PreviewerOutput::parse(Some(1), b"", b"no such file\n"),
+		Err("The previewer exited with 1: no such file".to_owned())
+	);
+}
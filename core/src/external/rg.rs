@@ -1,10 +1,11 @@
 use std::process::Stdio;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use serde_json::Value;
 use shared::Url;
-use tokio::{io::{AsyncBufReadExt, BufReader}, process::Command, sync::mpsc::{self, UnboundedReceiver}};
+use tokio::{io::{AsyncBufReadExt, BufReader}, process::Command, select, sync::mpsc::{self, UnboundedReceiver}};
 
-use crate::files::File;
+use crate::files::{ignore_rules, File};
 
 pub struct RgOpt {
 	pub cwd:     Url,
@@ -12,6 +13,15 @@ pub struct RgOpt {
 	pub subject: String,
 }
 
+pub struct RgMatch {
+	pub url:  Url,
+	pub line: u64,
+	pub text: String,
+	// The spans of the matches in the line, in bytes of the untrimmed text
+	pub spans: Vec<(usize, usize)>,
+	pub raw:   String,
+}
+
 pub fn rg(opt: RgOpt) -> Result<UnboundedReceiver<File>> {
 	let mut child = Command::new("rg")
 		.current_dir(&opt.cwd)
@@ -29,8 +39,11 @@ pub fn rg(opt: RgOpt) -> Result<UnboundedReceiver<File>> {
 	let (tx, rx) = mpsc::unbounded_channel();
 
 	tokio::spawn(async move {
+		// Found as well if the config of the user for the command has it not respect `.gitignore`
+		let rules = ignore_rules(&opt.cwd).await;
 		while let Ok(Some(line)) = it.next_line().await {
-			if let Ok(file) = File::from(opt.cwd.join(line)).await {
+			if let Ok(mut file) = File::from(opt.cwd.join(line)).await {
+				file.ignore_by(rules.as_ref());
 				tx.send(file).ok();
 			}
 		}
@@ -38,3 +51,63 @@ pub fn rg(opt: RgOpt) -> Result<UnboundedReceiver<File>> {
 	});
 	Ok(rx)
 }
+
+pub fn rg_matches(opt: RgOpt) -> Result<UnboundedReceiver<RgMatch>> {
+	let mut child = Command::new("rg")
+		.current_dir(&opt.cwd)
+		.args(["--json", "--smart-case"])
+		.arg(if opt.hidden { "--hidden" } else { "--no-hidden" })
+		.arg(&opt.subject)
+		.kill_on_drop(true)
+		.stdout(Stdio::piped())
+		.stderr(Stdio::null())
+		.spawn()
+		.map_err(|e| anyhow!("failed to run `rg`, is ripgrep installed? ({e})"))?;
+
+	let mut it = BufReader::new(child.stdout.take().unwrap()).lines();
+	let (tx, rx) = mpsc::unbounded_channel();
+
+	tokio::spawn(async move {
+		loop {
+			let line = select! {
+				_ = tx.closed() => {
+					child.start_kill().ok();
+					break;
+				}
+				line = it.next_line() => line,
+			};
+
+			let Ok(Some(line)) = line else {
+				break;
+			};
+			if let Some(m) = parse_match(&opt.cwd, &line) {
+				tx.send(m).ok();
+			}
+		}
+		child.wait().await.ok();
+	});
+	Ok(rx)
+}
+
+fn parse_match(cwd: &Url, line: &str) -> Option<RgMatch> {
+	let v: Value = serde_json::from_str(line).ok()?;
+	if v["type"] != "match" {
+		return None;
+	}
+
+	let data = &v["data"];
+	let raw = data["lines"]["text"].as_str().unwrap_or_default();
+	let spans = data["submatches"]
+		.as_array()
+		.map(|a| a.iter().filter_map(|m| Some((m["start"].as_u64()? as _, m["end"].as_u64()? as _))))
+		.map(|it| it.collect())
+		.unwrap_or_default();
+
+	Some(RgMatch {
+		url: cwd.join(data["path"]["text"].as_str()?),
+		line: data["line_number"].as_u64().unwrap_or(0),
+		text: raw.trim().to_owned(),
+		spans,
+		raw: raw.to_owned(),
+	})
+}
@@ -1,4 +1,4 @@
-use std::{ffi::OsString, process::Stdio};
+use std::{ffi::OsString, path::Path, process::Stdio};
 
 use anyhow::Result;
 use tokio::process::{Child, Command};
@@ -9,6 +9,16 @@ pub struct ShellOpt {
 	pub piped: bool,
 }
 
+// The shell of the user, to drop into with `subshell`
+pub fn login_shell() -> OsString {
+	#[cfg(not(target_os = "windows"))]
+	let (var, default) = ("SHELL", "sh");
+	#[cfg(target_os = "windows")]
+	let (var, default) = ("COMSPEC", "cmd");
+
+	std::env::var_os(var).filter(|s| !s.is_empty()).unwrap_or_else(|| default.into())
+}
+
 pub fn shell(opt: ShellOpt) -> Result<Child> {
 	#[cfg(not(target_os = "windows"))]
 	{
@@ -41,3 +51,69 @@ pub fn shell(opt: ShellOpt) -> Result<Child> {
 		)
 	}
 }
+
+// Replaces the placeholders of a shell command with the files, quoted:
+// `$f` is the hovered file, `$fx` the selected ones, `$d` the current directory,
+// and `$n` the name of the hovered file without the extension, while `\$f` is kept as is
+pub fn shell_expand(exec: &str, hovered: Option<&Path>, selected: &[&Path], cwd: &Path) -> String {
+	let quote = |p: Option<&Path>| shell_quote(&p.map(|p| p.to_string_lossy()).unwrap_or_default());
+
+	let (mut out, mut it) = (String::with_capacity(exec.len()), exec.char_indices().peekable());
+	while let Some((i, c)) = it.next() {
+		if c != '$' || exec[..i].ends_with('\\') {
+			out.push(c);
+			continue;
+		}
+
+		let name = &exec[i + 1..];
+		let end = name.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(name.len());
+		match &name[..end] {
+			"f" => out.push_str(&quote(hovered)),
+			"fx" => {
+				out.push_str(&selected.iter().map(|&p| quote(Some(p))).collect::<Vec<_>>().join(" "))
+			}
+			"d" => out.push_str(&quote(Some(cwd))),
+			"n" => out.push_str(&quote(hovered.and_then(|h| h.file_stem()).map(Path::new))),
+			_ => {
+				out.push(c);
+				continue;
+			}
+		}
+		for _ in 0..end {
+			it.next();
+		}
+	}
+	out
+}
+
+// Quotes `s` to be a single word of the shell, keeping it as is if there's nothing special in it
+pub fn shell_quote(s: &str) -> String {
+	if !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || "/._-+=:,@%".contains(c)) {
+		return s.to_owned();
+	}
+
+	#[cfg(not(target_os = "windows"))]
+	{
+		format!("'{}'", s.replace('\'', r"'\''"))
+	}
+	#[cfg(target_os = "windows")]
+	{
+		format!("\"{}\"", s.replace('"', "\"\""))
+	}
+}
+
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn test_shell_expand() {
+	let (cwd, hovered) = (Path::new("/tmp/my dir"), Path::new("/tmp/my dir/it's.tar.gz"));
+	let selected = [hovered, Path::new("/tmp/b")];
+	let expand = |s| shell_expand(s, Some(hovered), &selected, cwd);
+
+	assert_eq!(expand("cat $f"), r"cat '/tmp/my dir/it'\''s.tar.gz'");
+	assert_eq!(
+		expand("tar -cf $n.tar $fx"),
+		r"tar -cf 'it'\''s.tar'.tar '/tmp/my dir/it'\''s.tar.gz' /tmp/b"
+	);
+	assert_eq!(expand("cd $d && echo $fn $HOME \\$f $"), r"cd '/tmp/my dir' && echo $fn $HOME \$f $");
+	assert_eq!(shell_expand("rm $f", None, &[], cwd), "rm ''");
+}
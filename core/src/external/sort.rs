@@ -0,0 +1,57 @@
+use std::{path::Path, process::Stdio, time::Duration};
+
+use anyhow::{bail, Result};
+use tokio::{io::AsyncWriteExt, process::Command, time::timeout};
+
+// How long the command can run before it's killed, and the files are kept in the natural order
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+pub async fn sort(cmd: &str, cwd: &Path, names: &[String]) -> Result<Vec<String>> {
+	#[cfg(not(target_os = "windows"))]
+	let mut child = Command::new("sh")
+		.arg("-c")
+		.arg(cmd)
+		.current_dir(cwd)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::null())
+		.kill_on_drop(true)
+		.spawn()?;
+
+	#[cfg(target_os = "windows")]
+	let mut child = Command::new("cmd")
+		.arg("/C")
+		.arg(cmd)
+		.current_dir(cwd)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::null())
+		.kill_on_drop(true)
+		.spawn()?;
+
+	let mut stdin = child.stdin.take().unwrap();
+	let input = names.join("\n") + "\n";
+	let writer = async move { stdin.write_all(input.as_bytes()).await.ok() };
+
+	let output = timeout(TIMEOUT, async { tokio::join!(writer, child.wait_with_output()).1 });
+	let Ok(output) = output.await else {
+		bail!("sort command timed out after {TIMEOUT:?}");
+	};
+
+	let output = output?;
+	if !output.status.success() {
+		bail!("sort command exited with {}", output.status);
+	}
+
+	Ok(String::from_utf8_lossy(&output.stdout).lines().map(|s| s.to_owned()).collect())
+}
+
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn test_sort() {
+	let names = ["b".to_owned(), "c".to_owned(), "a".to_owned()];
+	let sort = |cmd| shared::block_on(sort(cmd, Path::new("/"), &names));
+
+	assert_eq!(sort("sort -r").unwrap(), ["c", "b", "a"]);
+	assert!(sort("exit 1").is_err());
+}
@@ -1,9 +1,11 @@
-use std::process::Stdio;
+use std::{io::ErrorKind, process::Stdio, sync::atomic::{AtomicBool, Ordering}};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use shared::Url;
 use tokio::{process::Command, sync::oneshot::{self, Receiver}};
 
+static MISSING: AtomicBool = AtomicBool::new(false);
+
 pub struct ZoxideOpt {
 	pub cwd: Url,
 }
@@ -29,3 +31,50 @@ pub fn zoxide(opt: ZoxideOpt) -> Result<Receiver<Result<Url>>> {
 	});
 	Ok(rx)
 }
+
+pub async fn zoxide_query(opt: ZoxideOpt, query: &[String]) -> Result<Url> {
+	let output = Command::new("zoxide")
+		.args(["query", "--exclude"])
+		.arg(&opt.cwd)
+		.arg("--")
+		.args(query)
+		.kill_on_drop(true)
+		.stdin(Stdio::null())
+		.stderr(Stdio::null())
+		.output()
+		.await
+		.map_err(|e| match e.kind() {
+			ErrorKind::NotFound => anyhow!("zoxide is not installed"),
+			_ => anyhow!(e),
+		})?;
+
+	let selected = String::from_utf8_lossy(&output.stdout).trim().to_string();
+	if !output.status.success() || selected.is_empty() {
+		bail!("No match for `{}`", query.join(" "));
+	}
+	Ok(Url::from(selected))
+}
+
+pub fn zoxide_add(url: &Url) {
+	if MISSING.load(Ordering::Relaxed) || !url.is_regular() {
+		return;
+	}
+
+	let url = url.clone();
+	tokio::spawn(async move {
+		let result = Command::new("zoxide")
+			.arg("add")
+			.arg(&url)
+			.kill_on_drop(true)
+			.stdin(Stdio::null())
+			.stdout(Stdio::null())
+			.stderr(Stdio::null())
+			.status()
+			.await;
+
+		if matches!(result, Err(e) if e.kind() == ErrorKind::NotFound) {
+			MISSING.store(true, Ordering::Relaxed);
+			tracing::info!("zoxide is not installed, directories will not be added to its database");
+		}
+	});
+}
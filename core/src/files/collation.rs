@@ -0,0 +1,61 @@
+use std::cmp::Ordering;
+
+#[cfg(feature = "icu")]
+use icu_collator::{Collator, CollatorOptions, Numeric, Strength};
+
+#[cfg(feature = "icu")]
+fn collator(sensitive: bool, numeric: bool) -> Option<&'static Collator> {
+	use std::sync::OnceLock;
+
+	static SENSITIVE: OnceLock<Option<Collator>> = OnceLock::new();
+	static SENSITIVE_NUMERIC: OnceLock<Option<Collator>> = OnceLock::new();
+	static INSENSITIVE: OnceLock<Option<Collator>> = OnceLock::new();
+	static INSENSITIVE_NUMERIC: OnceLock<Option<Collator>> = OnceLock::new();
+
+	let cell = match (sensitive, numeric) {
+		(true, false) => &SENSITIVE,
+		(true, true) => &SENSITIVE_NUMERIC,
+		(false, false) => &INSENSITIVE,
+		(false, true) => &INSENSITIVE_NUMERIC,
+	};
+
+	cell
+		.get_or_init(|| {
+			let mut options = CollatorOptions::new();
+			options.strength = Some(if sensitive { Strength::Tertiary } else { Strength::Secondary });
+			options.numeric = Some(if numeric { Numeric::On } else { Numeric::Off });
+			Collator::try_new(&locale()?.into(), options).ok()
+		})
+		.as_ref()
+}
+
+#[cfg(feature = "icu")]
+fn locale() -> Option<icu_locid::Locale> {
+	let s = ["LC_ALL", "LC_COLLATE", "LANG"]
+		.into_iter()
+		.find_map(|k| std::env::var(k).ok().filter(|s| !s.is_empty()))?;
+
+	let s = s.split(['.', '@']).next()?;
+	if s == "C" || s == "POSIX" {
+		return None;
+	}
+	s.replace('_', "-").parse().ok()
+}
+
+#[cfg(feature = "icu")]
+#[inline]
+pub(super) fn available() -> bool { collator(false, false).is_some() }
+
+#[cfg(not(feature = "icu"))]
+#[inline]
+pub(super) fn available() -> bool { false }
+
+#[cfg(feature = "icu")]
+#[inline]
+pub(super) fn collate(a: &str, b: &str, sensitive: bool, numeric: bool) -> Ordering {
+	collator(sensitive, numeric).map(|c| c.compare(a, b)).unwrap_or_else(|| a.cmp(b))
+}
+
+#[cfg(not(feature = "icu"))]
+#[inline]
+pub(super) fn collate(a: &str, b: &str, _: bool, _: bool) -> Ordering { a.cmp(b) }
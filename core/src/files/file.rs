@@ -1,38 +1,65 @@
-use std::{borrow::Cow, ffi::OsStr, fs::Metadata};
+use std::{borrow::Cow, ffi::OsStr, fs::Metadata, time::SystemTime};
 
 use anyhow::Result;
+use config::{manager::FileKind, theme::LsKind};
 use shared::Url;
 use tokio::fs;
 
+use super::Meta;
+use crate::sftp;
+
 #[derive(Clone, Debug)]
 pub struct File {
-	pub(super) url:       Url,
-	pub(super) meta:      Metadata,
-	pub(super) length:    u64,
-	pub(super) link_to:   Option<Url>,
-	pub(super) is_link:   bool,
-	pub(super) is_hidden: bool,
+	pub(super) url:        Url,
+	pub(super) meta:       Meta,
+	pub(super) length:     u64,
+	pub(super) link_to:    Option<Url>,
+	pub(super) is_link:    bool,
+	pub(super) is_hidden:  bool,
+	pub(super) is_ignored: bool,
 }
 
 impl File {
 	#[inline]
 	pub async fn from(url: Url) -> Result<Self> {
+		if url.is_sftp() {
+			let meta = sftp::metadata(&url, false).await?;
+			return Ok(Self::from_remote(url, meta).await);
+		}
+
 		let meta = fs::metadata(&url).await?;
 		Ok(Self::from_meta(url, meta).await)
 	}
 
-	pub async fn from_meta(url: Url, mut meta: Metadata) -> Self {
+	pub async fn from_meta(url: Url, meta: Metadata) -> Self {
 		let is_link = meta.is_symlink();
+		let mut meta = Meta::from(meta);
 		let mut link_to = None;
 
 		if is_link {
-			meta = fs::metadata(&url).await.unwrap_or(meta);
+			meta = fs::metadata(&url).await.map(Meta::from).unwrap_or(meta);
 			link_to = fs::read_link(&url).await.map(Url::from).ok();
 		}
+		Self::new(url, meta, link_to, is_link)
+	}
+
+	// Of a remote file by the metadata of its own, where a symlink is followed on the server,
+	// and points to a remote one as well
+	pub async fn from_remote(url: Url, mut meta: Meta) -> Self {
+		let is_link = meta.is_symlink();
+		let mut link_to = None;
+
+		if is_link {
+			meta = sftp::metadata(&url, true).await.unwrap_or(meta);
+			link_to = sftp::read_link(&url).await.ok();
+		}
+		Self::new(url, meta, link_to, is_link)
+	}
 
+	fn new(url: Url, meta: Meta, link_to: Option<Url>, is_link: bool) -> Self {
 		let length = meta.len();
 		let is_hidden = url.file_name().map(|s| s.to_string_lossy().starts_with('.')).unwrap_or(false);
-		Self { url, meta, length, link_to, is_link, is_hidden }
+		Self { url, meta, length, link_to, is_link, is_hidden, is_ignored: false }
 	}
 }
 
@@ -61,12 +88,18 @@ impl File {
 	#[inline]
 	pub fn stem(&self) -> Option<&OsStr> { self.url.file_stem() }
 
+	// The last extension lowercased, e.g. `gz` of `a.tar.GZ`, none for a dotfile like `.bashrc`
+	#[inline]
+	pub fn extension(&self) -> Option<String> {
+		self.url.extension().map(|e| e.to_string_lossy().to_lowercase())
+	}
+
 	#[inline]
 	pub fn parent(&self) -> Option<Url> { self.url.parent_url() }
 
 	// --- Meta
 	#[inline]
-	pub fn meta(&self) -> &Metadata { &self.meta }
+	pub fn meta(&self) -> &Meta { &self.meta }
 
 	#[inline]
 	pub fn is_file(&self) -> bool { self.meta.is_file() }
@@ -74,6 +107,53 @@ impl File {
 	#[inline]
 	pub fn is_dir(&self) -> bool { self.meta.is_dir() }
 
+	#[inline]
+	pub fn is_hidden(&self) -> bool { self.is_hidden }
+
+	#[inline]
+	pub fn is_ignored(&self) -> bool { self.is_ignored }
+
+	// A file that any of the user, the group or the others can execute by the mode, or on Windows,
+	// one of the extensions run by the shell
+	#[cfg(unix)]
+	#[inline]
+	pub fn is_executable(&self) -> bool { self.is_file() && self.meta.mode() & 0o111 != 0 }
+
+	#[cfg(windows)]
+	#[inline]
+	pub fn is_executable(&self) -> bool {
+		self.is_file() && matches!(self.extension().as_deref(), Some("exe" | "com" | "bat" | "cmd"))
+	}
+
+	pub fn kind(&self) -> FileKind {
+		match (self.is_dir(), self.is_link) {
+			(true, false) => FileKind::Dir,
+			(true, true) => FileKind::LinkDir,
+			(false, true) => FileKind::LinkFile,
+			(false, false) if self.is_file() => FileKind::File,
+			_ => FileKind::Other,
+		}
+	}
+
+	// What the file is to `LS_COLORS`, as a symlink the file it points to
+	pub fn ls_kind(&self) -> LsKind {
+		if self.is_orphan() {
+			return LsKind::Orphan;
+		} else if self.is_dir() {
+			return LsKind::Dir;
+		} else if self.is_executable() {
+			return LsKind::Exec;
+		}
+
+		match () {
+			_ if self.meta.is_fifo() => LsKind::Fifo,
+			_ if self.meta.is_socket() => LsKind::Socket,
+			_ if self.meta.is_block_device() => LsKind::Block,
+			_ if self.meta.is_char_device() => LsKind::Char,
+			_ => LsKind::File,
+		}
+	}
+
 	// --- Length
 	#[inline]
 	pub fn length(&self) -> u64 { self.length }
@@ -81,4 +161,59 @@ impl File {
 	// --- Link to
 	#[inline]
 	pub fn link_to(&self) -> Option<&Url> { self.link_to.as_ref() }
+
+	#[inline]
+	pub fn is_link(&self) -> bool { self.is_link }
+
+	// A symlink whose target is gone, the metadata of the link itself is kept for it
+	#[inline]
+	pub fn is_orphan(&self) -> bool { self.is_link && self.meta.is_symlink() }
+
+	// An orphan, or a symlink that can't even be read for where it points to
+	#[inline]
+	pub fn is_broken_symlink(&self) -> bool {
+		self.is_orphan() || (self.is_link && self.link_to.is_none())
+	}
+
+	// --- Stamp
+	// What tells the file apart from itself once changed, to know if a directory read again differs
+	#[inline]
+	pub fn stamp(&self) -> (Url, Option<SystemTime>, u64) {
+		(self.url.clone(), self.meta.modified(), self.length)
+	}
+}
+
+#[cfg(unix)]
+#[test]
+fn test_classify() {
+	use std::os::unix::fs::{symlink, PermissionsExt};
+
+	let dir = shared::TestDir::new("classify");
+	std::fs::create_dir_all(dir.join("d.ext")).unwrap();
+	for name in ["a.tar.GZ", ".bashrc", ".config.toml", "noext", "run.sh"] {
+		std::fs::write(dir.join(name), "").unwrap();
+	}
+	std::fs::set_permissions(dir.join("run.sh"), PermissionsExt::from_mode(0o744)).unwrap();
+	symlink(dir.join("gone"), dir.join("orphan")).unwrap();
+	symlink(dir.join("run.sh"), dir.join("link")).unwrap();
+
+	let file = |name: &str| {
+		let url = Url::from(dir.join(name));
+		let meta = std::fs::symlink_metadata(&url).unwrap();
+		shared::block_on(File::from_meta(url, meta))
+	};
+
+	assert_eq!(file("a.tar.GZ").extension().as_deref(), Some("gz"));
+	assert_eq!(file(".bashrc").extension(), None);
+	assert_eq!(file(".config.toml").extension().as_deref(), Some("toml"));
+	assert_eq!(file("noext").extension(), None);
+
+	assert!(file("run.sh").is_executable());
+	assert!(file("link").is_executable());
+	assert!(!file("noext").is_executable());
+	assert!(!file("d.ext").is_executable());
+
+	assert!(file("orphan").is_broken_symlink());
+	assert!(!file("link").is_broken_symlink());
+	assert!(!file("run.sh").is_broken_symlink());
 }
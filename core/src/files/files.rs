@@ -1,23 +1,53 @@
-use std::{collections::{BTreeMap, BTreeSet}, mem, ops::Deref, sync::atomic::Ordering};
+use std::{collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet, HashMap, HashSet}, hash::{Hash, Hasher}, mem, ops::Deref, sync::atomic::Ordering, time::{Duration, SystemTime}};
 
 use anyhow::Result;
 use config::{manager::SortBy, MANAGER};
-use shared::Url;
-use tokio::{fs, select, sync::mpsc::{self, UnboundedReceiver}};
+use futures::{stream, Stream};
+use shared::{Url, Walked};
+use tokio::{fs, select, sync::mpsc::{self, UnboundedReceiver}, time::{timeout_at, Instant}};
 
-use super::{File, FilesSorter, FILES_TICKET};
+use super::{ignore_rules, File, FilesSorter, Filter, QuickFilter, SelectedTally, FILES_TICKET};
+use crate::{external::GitStatus, sftp};
+
+// The first batch of a directory streamed is small for it to be shown soon, and each one after is
+// bigger, up to the most, for a large directory not to be sorted again for every few files of it
+const BATCH_FIRST: usize = 500;
+const BATCH_MAX: usize = 50_000;
+// How long a batch waits to be filled, once its first file is read
+const BATCH_WAIT: Duration = Duration::from_millis(50);
 
 pub struct Files {
 	items:   Vec<File>,
 	hidden:  Vec<File>,
+	// The files of the expanded directories as they're read, and all of the ones in view as a tree
+	tree:    BTreeMap<Url, Vec<File>>,
+	view:    Vec<File>,
 	ticket:  u64,
 	version: u64,
+	// The hash of the order the files are sorted in, to tell when it's changed
+	order:   u64,
 
 	sizes:    BTreeMap<Url, u64>,
-	selected: BTreeSet<Url>,
-
-	sorter:      FilesSorter,
-	show_hidden: bool,
+	// The directories whose sizes are still being calculated, with the ones so far in `sizes`
+	sizing:   HashSet<Url>,
+	// The directories whose sizes leave some of them out, with why
+	cut:      HashMap<Url, Walked>,
+	children: BTreeMap<Url, usize>,
+	// The places of the files in the output of the `sort_command`, for them to be sorted externally
+	ranks:    BTreeMap<Url, usize>,
+	git:      BTreeMap<Url, GitStatus>,
+	xattrs:   BTreeMap<Url, Vec<String>>,
+	// The files selected, with whether each is a directory and its size, counted in the tally,
+	// and how many times they've changed, for the `select` hook to tell they did
+	selected: BTreeMap<Url, (bool, u64)>,
+	tally:    SelectedTally,
+	revision: u64,
+
+	sorter:       FilesSorter,
+	filter:       Option<Filter>,
+	quick:        Option<QuickFilter>,
+	show_hidden:  bool,
+	show_ignored: bool,
 }
 
 impl Default for Files {
@@ -25,14 +55,28 @@ impl Default for Files {
 		Self {
 			items:   Default::default(),
 			hidden:  Default::default(),
+			tree:    Default::default(),
+			view:    Default::default(),
 			ticket:  Default::default(),
 			version: Default::default(),
+			order:   Default::default(),
 
 			sizes:    Default::default(),
+			sizing:   Default::default(),
+			cut:      Default::default(),
+			children: Default::default(),
+			ranks:    Default::default(),
+			git:      Default::default(),
+			xattrs:   Default::default(),
 			selected: Default::default(),
-
-			sorter:      Default::default(),
-			show_hidden: MANAGER.show_hidden,
+			tally:    Default::default(),
+			revision: 0,
+
+			sorter:       Default::default(),
+			filter:       None,
+			quick:        None,
+			show_hidden:  MANAGER.show_hidden,
+			show_ignored: MANAGER.show_ignored,
 		}
 	}
 }
@@ -40,32 +84,86 @@ impl Default for Files {
 impl Deref for Files {
 	type Target = Vec<File>;
 
-	fn deref(&self) -> &Self::Target { &self.items }
+	fn deref(&self) -> &Self::Target { if self.tree.is_empty() { &self.items } else { &self.view } }
 }
 
 impl Files {
 	pub async fn from_dir(url: &Url) -> Result<UnboundedReceiver<File>> {
+		if url.is_sftp() {
+			return Self::from_remote_dir(url).await;
+		}
+
 		let mut it = fs::read_dir(url).await?;
 		let (tx, rx) = mpsc::unbounded_channel();
 
+		let dir = url.clone();
 		tokio::spawn(async move {
+			let rules = ignore_rules(&dir).await;
+
 			while let Ok(Some(item)) = it.next_entry().await {
 				select! {
 					_ = tx.closed() => break,
 					Ok(meta) = item.metadata() => {
-						tx.send(File::from_meta(Url::from(item.path()), meta).await).ok();
+						let mut file = File::from_meta(Url::from(item.path()), meta).await;
+						file.ignore_by(rules.as_ref());
+						tx.send(file).ok();
 					}
 				}
 			}
 		});
 		Ok(rx)
 	}
+
+	// The files of the directory in batches as they're read, where the reading stops once the
+	// stream is dropped, such as on leaving the directory
+	#[inline]
+	pub async fn stream_dir(url: &Url) -> Result<impl Stream<Item = Vec<File>>> {
+		Ok(batched(Self::from_dir(url).await?))
+	}
+
+	// The entries of a remote directory come all at once, where the symlinks among them are followed
+	// one by one, and there's no `.gitignore` looked for
+	async fn from_remote_dir(url: &Url) -> Result<UnboundedReceiver<File>> {
+		let entries = sftp::read_dir(url).await?;
+		let (tx, rx) = mpsc::unbounded_channel();
+
+		tokio::spawn(async move {
+			for (url, meta) in entries {
+				select! {
+					_ = tx.closed() => break,
+					file = File::from_remote(url, meta) => { tx.send(file).ok(); }
+				}
+			}
+		});
+		Ok(rx)
+	}
+}
+
+// Of the ones received, each batch waiting for its first one as long as it takes, and for the rest
+// only as long as `BATCH_WAIT`
+fn batched<T>(rx: UnboundedReceiver<T>) -> impl Stream<Item = Vec<T>> {
+	stream::unfold((rx, BATCH_FIRST), |(mut rx, size)| async move {
+		let mut batch = vec![rx.recv().await?];
+		let deadline = Instant::now() + BATCH_WAIT;
+		while batch.len() < size {
+			match timeout_at(deadline, rx.recv()).await {
+				Ok(Some(item)) => batch.push(item),
+				_ => break,
+			}
+		}
+		Some((batch, (rx, (size * 4).min(BATCH_MAX))))
+	})
 }
 
 impl Files {
-	#[inline]
+	// The ones not read yet are counted as empty files, until they are
 	pub fn select(&mut self, url: &Url, state: Option<bool>) -> bool {
-		let old = self.selected.contains(url);
+		let weight = self.iter().find(|f| &f.url == url).map(|f| self.weigh(f)).unwrap_or_default();
+		self.select_weighed(url, weight, state)
+	}
+
+	fn select_weighed(&mut self, url: &Url, weight: (bool, u64), state: Option<bool>) -> bool {
+		let old = self.selected.contains_key(url);
 		let new = if let Some(new) = state { new } else { !old };
 
 		if new == old {
@@ -73,64 +171,86 @@ impl Files {
 		}
 
 		if new {
-			self.selected.insert(url.to_owned());
-		} else {
-			self.selected.remove(url);
+			self.selected.insert(url.to_owned(), weight);
+			self.tally.add(weight);
+		} else if let Some(weight) = self.selected.remove(url) {
+			self.tally.sub(weight);
 		}
+		self.revision += 1;
 		true
 	}
 
+	// Only the files in view are touched, the ones hidden or filtered out stay as they are
 	pub fn select_all(&mut self, state: Option<bool>) -> bool {
-		match state {
-			Some(true) => {
-				let b = if self.selected.len() < self.items.len() {
-					true
-				} else {
-					self.items.iter().any(|f| !self.selected.contains(&f.url))
-				};
-
-				self.selected = self.iter().map(|f| f.url_owned()).collect();
-				b
-			}
-			Some(false) => {
-				if self.selected.is_empty() {
-					return false;
-				}
+		let items = if self.tree.is_empty() { &self.items } else { &self.view };
+		if state == Some(false) && self.selected.is_empty() {
+			return false;
+		}
 
-				let b = self.items.iter().any(|f| self.selected.contains(&f.url));
-				self.selected.clear();
-				b
-			}
-			None => {
-				for item in &self.items {
-					if self.selected.contains(&item.url) {
-						self.selected.remove(&item.url);
-					} else {
-						self.selected.insert(item.url_owned());
-					}
-				}
-				!self.items.is_empty()
-			}
+		let weights: Vec<_> = items.iter().map(|f| (f.url_owned(), self.weigh(f))).collect();
+		let mut applied = false;
+		for (url, weight) in weights {
+			applied |= self.select_weighed(&url, weight, state);
 		}
+		applied
+	}
+
+	// Selects, deselects, or toggles the files in view whose name matches `filter`
+	pub fn select_matches(&mut self, filter: &Filter, state: Option<bool>) -> bool {
+		let urls: Vec<_> =
+			self.iter().filter(|f| filter.matches(f)).map(|f| f.url_owned()).collect();
+
+		let mut applied = false;
+		for url in urls {
+			applied |= self.select(&url, state);
+		}
+		applied
 	}
 
 	pub fn select_index(&mut self, indices: &BTreeSet<usize>, state: Option<bool>) -> bool {
 		let mut applied = false;
-		let paths: Vec<_> = self.pick(indices).iter().map(|f| f.url_owned()).collect();
+		let weights: Vec<_> =
+			self.pick(indices).iter().map(|f| (f.url_owned(), self.weigh(f))).collect();
 
-		for path in paths {
-			applied |= self.select(&path, state);
+		for (url, weight) in weights {
+			applied |= self.select_weighed(&url, weight, state);
 		}
 		applied
 	}
 
-	pub fn update_full(&mut self, mut items: Vec<File>) -> bool {
-		if !self.show_hidden {
-			(self.hidden, items) = items.into_iter().partition(|f| f.is_hidden);
+	// Whether the file is a directory, and its size, the one calculated for a directory if any
+	#[inline]
+	fn weigh(&self, file: &File) -> (bool, u64) {
+		let size = if file.is_dir() { self.sizes.get(&file.url).copied() } else { None };
+		(file.is_dir(), size.unwrap_or(file.length))
+	}
+
+	// Counts the selected files again as they're now, after they're read
+	fn reweigh(&mut self) {
+		if self.selected.is_empty() {
+			return;
+		}
+		for file in self.items.iter().chain(&self.hidden) {
+			let size = if file.is_dir() { self.sizes.get(&file.url).copied() } else { None };
+			let weight = (file.is_dir(), size.unwrap_or(file.length));
+			if let Some(w) = self.selected.get_mut(&file.url).filter(|w| **w != weight) {
+				self.tally.sub(*w);
+				self.tally.add(weight);
+				*w = weight;
+			}
 		}
+	}
+
+	pub fn update_full(&mut self, items: Vec<File>) -> bool {
+		let (hidden, mut items) = self.split(items);
+		self.hidden = hidden;
 		self.ticket = FILES_TICKET.fetch_add(1, Ordering::Relaxed);
-		self.sorter.sort(&mut items, &self.sizes);
+		self.sorter.sort_with(&mut items, &self.sizes, &self.children, &self.ranks);
 		self.items = items;
+		self.xattrs.clear();
+		self.reweigh();
+		self.rehash();
+		self.flatten();
 		self.version += 1;
 		true
 	}
@@ -141,15 +261,14 @@ impl Files {
 				return false;
 			}
 
-			if self.show_hidden {
-				self.items.extend(items);
-			} else {
-				let (hidden, items): (Vec<_>, Vec<_>) = items.into_iter().partition(|f| f.is_hidden);
-				self.items.extend(items);
-				self.hidden.extend(hidden);
-			}
+			let (hidden, items) = self.split(items);
+			self.items.extend(items);
+			self.hidden.extend(hidden);
+			self.reweigh();
 
-			self.sorter.sort(&mut self.items, &self.sizes);
+			self.sorter.sort_with(&mut self.items, &self.sizes, &self.children, &self.ranks);
+			self.rehash();
+			self.flatten();
 			self.version += 1;
 			return true;
 		}
@@ -161,18 +280,128 @@ impl Files {
 
 		self.items.clear();
 		self.hidden.clear();
+		self.rehash();
+		self.flatten();
 		self.version += 1;
 		true
 	}
 
 	pub fn update_size(&mut self, items: BTreeMap<Url, u64>) -> bool {
-		self.sizes.extend(items);
-		if self.sorter.by == SortBy::Size {
-			self.sorter.sort(&mut self.items, &self.sizes);
+		for url in items.keys() {
+			self.sizing.remove(url);
+			self.cut.remove(url);
+		}
+		self.apply_sizes(items)
+	}
+
+	// The sizes just updated that left some of the directories out
+	pub fn update_size_cut(&mut self, items: BTreeMap<Url, Walked>) -> bool {
+		self.cut.extend(items);
+		true
+	}
+
+	// The sizes so far of the directories still being calculated, where the ones already done are
+	// left as they are, in case of one coming in late
+	pub fn update_size_partial(&mut self, mut items: BTreeMap<Url, u64>) -> bool {
+		items.retain(|u, _| self.sizing.contains(u) || !self.sizes.contains_key(u));
+		if items.is_empty() {
+			return false;
+		}
+
+		self.sizing.extend(items.keys().cloned());
+		self.apply_sizes(items)
+	}
+
+	fn apply_sizes(&mut self, items: BTreeMap<Url, u64>) -> bool {
+		for (url, &size) in &items {
+			if let Some(w) = self.selected.get_mut(url).filter(|w| w.0 && w.1 != size) {
+				self.tally.sub(*w);
+				w.1 = size;
+				self.tally.add(*w);
+			}
+		}
+		self.sizes.extend(items.iter().map(|(u, &s)| (u.clone(), s)));
+		if self.sorter.by != SortBy::Size {
+			return true;
+		}
+
+		// Sorted again only if any of them is out of order with the ones next to it now
+		let moved = self.items.iter().enumerate().any(|(i, f)| {
+			items.contains_key(&f.url)
+				&& !self.sorter.size_in_order(&self.items, i, &self.sizes, &self.children)
+		});
+		if moved
+			&& (self
+				.sorter
+				.sort_with(&mut self.items, &self.sizes, &self.children, &self.ranks)
+				.is_reordered()
+				|| !self.tree.is_empty())
+		{
+			self.rehash();
+			self.flatten();
 			self.version += 1;
 		}
 		true
 	}
+
+	pub fn update_children(&mut self, items: BTreeMap<Url, usize>) -> bool {
+		self.children.extend(items);
+		if self.sorter.by == SortBy::Children
+			&& (self
+				.sorter
+				.sort_with(&mut self.items, &self.sizes, &self.children, &self.ranks)
+				.is_reordered()
+				|| !self.tree.is_empty())
+		{
+			self.rehash();
+			self.flatten();
+			self.version += 1;
+		}
+		true
+	}
+
+	pub fn update_ranks(&mut self, items: BTreeMap<Url, usize>) -> bool {
+		self.ranks.extend(items);
+		if self.sorter.by == SortBy::External
+			&& (self
+				.sorter
+				.sort_with(&mut self.items, &self.sizes, &self.children, &self.ranks)
+				.is_reordered()
+				|| !self.tree.is_empty())
+		{
+			self.rehash();
+			self.flatten();
+			self.version += 1;
+		}
+		true
+	}
+
+	// The status of the files is got for all of them at once, so it replaces the one before
+	pub fn update_git(&mut self, items: BTreeMap<Url, GitStatus>) -> bool {
+		if self.git == items {
+			return false;
+		}
+		self.git = items;
+		true
+	}
+
+	#[inline]
+	pub fn update_xattrs(&mut self, items: BTreeMap<Url, Vec<String>>) -> bool {
+		self.xattrs.extend(items);
+		true
+	}
+
+	// Expands `dir` with the files in it, if it's still in view and not collapsed meanwhile
+	pub fn update_expand(&mut self, dir: Url, items: Vec<File>) -> bool {
+		if !self.iter().any(|f| f.url == dir) {
+			return false;
+		}
+
+		self.tree.insert(dir, items);
+		self.flatten();
+		self.version += 1;
+		true
+	}
 }
 
 impl Files {
@@ -191,25 +420,89 @@ impl Files {
 	pub fn position(&self, url: &Url) -> Option<usize> { self.iter().position(|f| &f.url == url) }
 
 	#[inline]
-	pub fn duplicate(&self, idx: usize) -> Option<File> { self.items.get(idx).cloned() }
+	pub fn duplicate(&self, idx: usize) -> Option<File> { self.get(idx).cloned() }
 
 	// --- Version
 	#[inline]
 	pub fn version(&self) -> u64 { self.version }
 
+	#[inline]
+	pub fn order(&self) -> u64 { self.order }
+
 	// --- Sizes
 	#[inline]
 	pub fn size(&self, url: &Url) -> Option<u64> { self.sizes.get(url).copied() }
 
+	// Whether the size of the directory is yet to be known for good, while they're sorted by their
+	// recursive sizes, with the size so far if there's one
+	#[inline]
+	pub fn size_pending(&self, file: &File) -> bool {
+		file.is_dir()
+			&& self.sorter.by == SortBy::Size
+			&& self.sorter.size_recursive
+			&& (self.sizing.contains(&file.url) || !self.sizes.contains_key(&file.url))
+	}
+
+	// Why the size of the directory leaves some of it out, if it does
+	#[inline]
+	pub fn size_cut(&self, url: &Url) -> Option<Walked> { self.cut.get(url).copied() }
+
+	// --- Children
+	#[inline]
+	pub fn children(&self, url: &Url) -> Option<usize> { self.children.get(url).copied() }
+
+	#[inline]
+	pub fn rank(&self, url: &Url) -> Option<usize> { self.ranks.get(url).copied() }
+
+	// --- Git
+	#[inline]
+	pub fn git(&self, url: &Url) -> Option<GitStatus> { self.git.get(url).copied() }
+
+	// --- Xattrs
+	// The names of the extended attributes, only of the files in view once they're probed
+	pub fn xattrs(&self, url: &Url) -> Option<&[String]> {
+		self.xattrs.get(url).map(|v| v.as_slice())
+	}
+
+	// --- Tree
+	#[inline]
+	pub fn is_expanded(&self, url: &Url) -> bool { self.tree.contains_key(url) }
+
+	#[inline]
+	pub fn has_expanded(&self) -> bool { !self.tree.is_empty() }
+
+	// How deep the file is in the tree, `0` for the ones right in the directory
+	pub fn depth(&self, url: &Url) -> usize {
+		let mut depth = 0;
+		let mut parent = url.parent_url();
+		while let Some(p) = parent.filter(|p| self.tree.contains_key(p)) {
+			(depth, parent) = (depth + 1, p.parent_url());
+		}
+		depth
+	}
+
+	// Collapses `dir` along with the directories expanded under it, or all of them if not given
+	pub fn collapse(&mut self, dir: Option<&Url>) -> bool {
+		let len = self.tree.len();
+		self.tree.retain(|k, _| dir.is_some_and(|d| !k.starts_with(d)));
+		if self.tree.len() == len {
+			return false;
+		}
+
+		self.flatten();
+		self.version += 1;
+		true
+	}
+
 	// --- Selected
 	pub fn selected(&self, pending: &BTreeSet<usize>, unset: bool) -> Vec<&File> {
 		if self.selected.is_empty() && (unset || pending.is_empty()) {
 			return Vec::new();
 		}
 
-		let selected: BTreeSet<_> = self.selected.iter().collect();
+		let selected: BTreeSet<_> = self.selected.keys().collect();
 		let pending: BTreeSet<_> =
-			pending.iter().filter_map(|&i| self.items.get(i)).map(|f| &f.url).collect();
+			pending.iter().filter_map(|&i| self.get(i)).map(|f| &f.url).collect();
 
 		let selected: BTreeSet<_> = if unset {
 			selected.difference(&pending).cloned().collect()
@@ -218,7 +511,7 @@ impl Files {
 		};
 
 		let mut items = Vec::with_capacity(selected.len());
-		for item in &self.items {
+		for item in self.iter() {
 			if selected.contains(&item.url) {
 				items.push(item);
 			}
@@ -230,14 +523,24 @@ impl Files {
 	}
 
 	#[inline]
-	pub fn is_selected(&self, url: &Url) -> bool { self.selected.contains(url) }
+	pub fn is_selected(&self, url: &Url) -> bool { self.selected.contains_key(url) }
+
+	// Only those selected, without falling back to the hovered one
+	#[inline]
+	pub fn selected_urls(&self) -> Vec<Url> { self.selected.keys().cloned().collect() }
+
+	#[inline]
+	pub fn revision(&self) -> u64 { self.revision }
+
+	#[inline]
+	pub fn tally(&self) -> SelectedTally { self.tally }
 
 	#[inline]
 	pub fn has_selected(&self) -> bool {
 		if self.selected.is_empty() {
 			return false;
 		}
-		self.iter().any(|f| self.selected.contains(&f.url))
+		self.iter().any(|f| self.selected.contains_key(&f.url))
 	}
 
 	// --- Sorter
@@ -249,8 +552,48 @@ impl Files {
 			return false;
 		}
 		self.sorter = sorter;
+		if !self
+			.sorter
+			.sort_with(&mut self.items, &self.sizes, &self.children, &self.ranks)
+			.is_reordered()
+			&& self.tree.is_empty()
+		{
+			return false;
+		}
+
+		self.rehash();
+		self.flatten();
 		self.version += 1;
-		self.sorter.sort(&mut self.items, &self.sizes)
+		true
+	}
+
+	// --- Filter
+	#[inline]
+	pub fn filter(&self) -> Option<&Filter> { self.filter.as_ref() }
+
+	pub fn set_filter(&mut self, filter: Option<Filter>) -> bool {
+		let filter = filter.filter(|f| !f.is_empty());
+		if self.filter == filter {
+			return false;
+		}
+
+		self.filter = filter;
+		self.regroup();
+		true
+	}
+
+	#[inline]
+	pub fn quick(&self) -> Option<&QuickFilter> { self.quick.as_ref() }
+
+	pub fn set_quick(&mut self, quick: Option<QuickFilter>) -> bool {
+		let quick = quick.filter(|q| !q.is_empty());
+		if self.quick == quick {
+			return false;
+		}
+
+		self.quick = quick;
+		self.regroup();
+		true
 	}
 
 	// --- Show hidden
@@ -261,16 +604,148 @@ impl Files {
 			return false;
 		}
 
-		if state {
-			self.items.append(&mut self.hidden);
-			self.sorter.sort(&mut self.items, &self.sizes);
-		} else {
-			let items = mem::take(&mut self.items);
-			(self.hidden, self.items) = items.into_iter().partition(|f| f.is_hidden);
+		// The recursive sizes depend on it
+		self.show_hidden = state;
+		self.sizes.clear();
+		self.sizing.clear();
+		self.cut.clear();
+		self.regroup();
+		true
+	}
+
+	#[inline]
+	pub fn show_hidden(&self) -> bool { self.show_hidden }
+
+	// --- Show ignored
+	pub fn set_show_ignored(&mut self, state: bool) -> bool {
+		if state == self.show_ignored {
+			return false;
 		}
 
-		self.show_hidden = state;
-		self.version += 1;
+		self.show_ignored = state;
+		self.regroup();
 		true
 	}
+
+	// --- Total
+	#[inline]
+	pub fn total(&self) -> usize { self.items.len() + self.hidden.len() }
+
+	// The stamps of all the files, the hidden ones too, see `File::stamp`
+	pub fn stamps(&self) -> HashSet<(Url, Option<SystemTime>, u64)> {
+		self.items.iter().chain(&self.hidden).map(|f| f.stamp()).collect()
+	}
+
+	fn split(&self, items: Vec<File>) -> (Vec<File>, Vec<File>) {
+		let rules = &MANAGER.hidden_rules;
+		let filtered = self.filter.is_some() || self.quick.is_some();
+		if self.show_hidden && rules.is_empty() && self.show_ignored && !filtered {
+			return (Vec::new(), items);
+		}
+
+		let now = SystemTime::now();
+		items.into_iter().partition(|f| {
+			let size = || if f.is_dir() { self.sizes.get(&f.url).copied() } else { Some(f.length) };
+			rules.is_hidden(&f.url, f.is_dir(), f.is_hidden, self.show_hidden)
+				|| (f.is_ignored && !self.show_ignored)
+				|| self.filter.as_ref().is_some_and(|r| !r.matches(f))
+				|| self.quick.as_ref().is_some_and(|q| !q.matches(f, size(), now))
+		})
+	}
+
+	fn regroup(&mut self) {
+		let mut items = mem::take(&mut self.items);
+		items.append(&mut self.hidden);
+
+		let (hidden, mut items) = self.split(items);
+		self.sorter.sort_with(&mut items, &self.sizes, &self.children, &self.ranks);
+
+		(self.hidden, self.items) = (hidden, items);
+		self.rehash();
+		self.flatten();
+		self.version += 1;
+	}
+
+	fn rehash(&mut self) {
+		let mut h = DefaultHasher::new();
+		self.items.iter().for_each(|f| f.url.hash(&mut h));
+		self.order = h.finish();
+	}
+
+	// The files of the expanded directories go right under them, hidden, filtered and sorted as
+	// those of the directory itself are
+	fn flatten(&mut self) {
+		if self.tree.is_empty() {
+			self.view = Vec::new();
+			return;
+		}
+
+		let mut view = Vec::with_capacity(self.items.len());
+		self.flatten_into(&self.items, &mut view);
+		self.view = view;
+	}
+
+	fn flatten_into(&self, files: &[File], view: &mut Vec<File>) {
+		for file in files {
+			view.push(file.clone());
+			if let Some(items) = self.tree.get(&file.url) {
+				let (_, mut items) = self.split(items.clone());
+				self.sorter.sort_with(&mut items, &self.sizes, &self.children, &self.ranks);
+				self.flatten_into(&items, view);
+			}
+		}
+	}
+}
+
+#[test]
+fn test_batched() {
+	use tokio_stream::StreamExt;
+
+	shared::block_on(async {
+		let (tx, rx) = mpsc::unbounded_channel();
+		(0..20_000).for_each(|i| _ = tx.send(i));
+		drop(tx);
+		let sizes: Vec<_> = batched(rx).map(|b| b.len()).collect().await;
+		assert_eq!(sizes, [500, 2000, 8000, 9500]);
+
+		// The first batch isn't held back by the ones still being read
+		let (tx, rx) = mpsc::unbounded_channel();
+		(0..3).for_each(|i| _ = tx.send(i));
+		let rx = batched(rx);
+		tokio::pin!(rx);
+		assert_eq!(rx.next().await, Some(vec![0, 1, 2]));
+		tx.send(3).ok();
+		drop(tx);
+		assert_eq!(rx.next().await, Some(vec![3]));
+		assert_eq!(rx.next().await, None);
+	});
+}
+
+// How soon the first files of a large directory are shown, compared to all of them read at once
+#[test]
+#[ignore = "benchmark, run with `cargo test -p core --release -- --ignored --nocapture`"]
+fn bench_batched() {
+	use std::time::Instant;
+
+	use tokio_stream::StreamExt;
+
+	let dir = shared::TestDir::new("batched");
+	for i in 0..100_000 {
+		std::fs::write(dir.join(format!("f{i}")), "").unwrap();
+	}
+
+	// The presets, for whether `.gitignore` is respected as the files are read
+	config::init_bare("", dir.to_path_buf(), dir.join("state"));
+	let url = Url::from(&*dir);
+	shared::block_on(async {
+		let now = Instant::now();
+		let all: Vec<_> = Files::stream_dir(&url).await.unwrap().collect().await;
+		println!("all of the {} files: {:?}", all.concat().len(), now.elapsed());
+
+		let now = Instant::now();
+		let rx = Files::stream_dir(&url).await.unwrap();
+		tokio::pin!(rx);
+		let first = rx.next().await.unwrap();
+		println!("the first {} files: {:?}", first.len(), now.elapsed());
+	});
 }
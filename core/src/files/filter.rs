@@ -0,0 +1,100 @@
+use anyhow::Result;
+use glob::{MatchOptions, Pattern};
+use regex::{Regex, RegexBuilder};
+
+use super::File;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FilterKind {
+	#[default]
+	Regex,
+	Glob,
+}
+
+#[derive(Clone, Debug)]
+enum Matcher {
+	Regex(Regex),
+	Glob(Pattern),
+}
+
+#[derive(Clone, Debug)]
+pub struct Filter {
+	raw:            String,
+	kind:           FilterKind,
+	case_sensitive: bool,
+	matcher:        Option<Matcher>,
+}
+
+impl Filter {
+	pub fn new(raw: &str, kind: FilterKind, case_sensitive: bool) -> Result<Self> {
+		let matcher = if raw.is_empty() {
+			None
+		} else {
+			Some(match kind {
+				FilterKind::Regex => {
+					Matcher::Regex(RegexBuilder::new(raw).case_insensitive(!case_sensitive).build()?)
+				}
+				FilterKind::Glob => Matcher::Glob(Pattern::new(raw)?),
+			})
+		};
+
+		Ok(Self { raw: raw.to_owned(), kind, case_sensitive, matcher })
+	}
+
+	pub fn matches(&self, file: &File) -> bool {
+		let Some(matcher) = &self.matcher else {
+			return true;
+		};
+		let Some(name) = file.name() else {
+			return false;
+		};
+
+		let name = name.to_string_lossy();
+		match matcher {
+			Matcher::Regex(r) => r.is_match(&name),
+			Matcher::Glob(p) => p.matches_with(&name, MatchOptions {
+				case_sensitive: self.case_sensitive,
+				..Default::default()
+			}),
+		}
+	}
+}
+
+impl PartialEq for Filter {
+	fn eq(&self, other: &Self) -> bool {
+		self.raw == other.raw && self.kind == other.kind && self.case_sensitive == other.case_sensitive
+	}
+}
+
+impl Filter {
+	#[inline]
+	pub fn raw(&self) -> &str { &self.raw }
+
+	#[inline]
+	pub fn kind(&self) -> FilterKind { self.kind }
+
+	#[inline]
+	pub fn case_sensitive(&self) -> bool { self.case_sensitive }
+
+	#[inline]
+	pub fn is_empty(&self) -> bool { self.matcher.is_none() }
+}
+
+#[test]
+fn test_filter() {
+	use super::sorter::{test_files, test_names};
+
+	let items = test_files(&[("a.rs", 0), ("B.RS", 0), ("c.md", 0), ("dir/", 0)]);
+	let names = test_names(&items);
+	let pick = |f: &Filter| -> Vec<_> {
+		names.iter().zip(&items).filter(|(_, i)| f.matches(i)).map(|(&n, _)| n).collect()
+	};
+
+	assert_eq!(pick(&Filter::new("", FilterKind::Regex, false).unwrap()).len(), 4);
+	assert_eq!(pick(&Filter::new(r"\.rs$", FilterKind::Regex, false).unwrap()), ["a.rs", "B.RS"]);
+	assert_eq!(pick(&Filter::new(r"\.rs$", FilterKind::Regex, true).unwrap()), ["a.rs"]);
+	assert_eq!(pick(&Filter::new("*.RS", FilterKind::Glob, false).unwrap()), ["a.rs", "B.RS"]);
+	assert_eq!(pick(&Filter::new("*.RS", FilterKind::Glob, true).unwrap()), ["B.RS"]);
+	assert!(Filter::new("(", FilterKind::Regex, false).is_err());
+	assert!(Filter::new("[", FilterKind::Glob, false).is_err());
+}
@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use config::MANAGER;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use shared::Url;
+use tokio::task;
+
+use super::File;
+
+// The rules for the files of `dir`, read off the runtime, if they're respected and it's local
+pub(crate) async fn ignore_rules(dir: &Url) -> Option<Gitignore> {
+	if !MANAGER.gitignore || dir.is_sftp() {
+		return None;
+	}
+	let dir = dir.to_path_buf();
+	task::spawn_blocking(move || matcher(&dir)).await.ok().flatten()
+}
+
+pub(super) fn matcher(dir: &Path) -> Option<Gitignore> {
+	let dirs: Vec<_> = dir.ancestors().collect();
+	let n = dirs.iter().position(|p| p.join(".git").exists())?;
+
+	let mut builder = GitignoreBuilder::new(dirs[n]);
+	builder.add(dirs[n].join(".git/info/exclude"));
+
+	// From the root of the repository down to `dir`, so that deeper rules take precedence
+	for d in dirs[..=n].iter().rev() {
+		builder.add(d.join(".gitignore"));
+	}
+	builder.build().ok().filter(|g| !g.is_empty())
+}
+
+#[inline]
+pub(super) fn matches(matcher: &Gitignore, file: &File) -> bool {
+	matcher.matched_path_or_any_parents(&file.url, file.is_dir()).is_ignore()
+}
+
+impl File {
+	// Marks it as ignored or not by the rules, where there are none outside of a repository
+	#[inline]
+	pub(crate) fn ignore_by(&mut self, rules: Option<&Gitignore>) {
+		self.is_ignored = rules.is_some_and(|r| matches(r, self));
+	}
+}
+
+#[test]
+fn test_gitignore() {
+	let root = shared::TestDir::new("gitignore");
+	let sub = root.join("sub");
+	std::fs::create_dir_all(root.join(".git")).unwrap();
+	std::fs::create_dir_all(sub.join("target")).unwrap();
+	std::fs::write(root.join(".gitignore"), "*.log\ntarget/\n").unwrap();
+	std::fs::write(sub.join(".gitignore"), "!keep.log\n").unwrap();
+
+	let ignore = matcher(&sub).unwrap();
+	let (dir, file) = (std::fs::metadata(&root).unwrap(), std::fs::metadata(root.join(".gitignore")).unwrap());
+	let (dir, file) = (dir.into(), file.into());
+	let matched = |name: &str| {
+		matches(&ignore, &File {
+			url:        sub.join(name).into(),
+			meta:       if name == "target" { dir } else { file },
+			length:     0,
+			link_to:    None,
+			is_link:    false,
+			is_hidden:  false,
+			is_ignored: false,
+		})
+	};
+
+	assert!(matched("a.log"));
+	assert!(!matched("keep.log"));
+	assert!(!matched("a.rs"));
+	assert!(matched("target"));
+}
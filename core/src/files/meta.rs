@@ -0,0 +1,175 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use shared::Url;
+use tokio::fs;
+
+use crate::sftp;
+
+// The type of a file in the bits of its mode, as `st_mode` and SFTP have them
+const S_IFMT: u32 = 0o170000;
+const S_IFSOCK: u32 = 0o140000;
+const S_IFLNK: u32 = 0o120000;
+const S_IFREG: u32 = 0o100000;
+const S_IFBLK: u32 = 0o060000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFCHR: u32 = 0o020000;
+const S_IFIFO: u32 = 0o010000;
+
+// The metadata of a file, either read from the local filesystem, or of a remote one told by
+// the server, which only has some of it, the rest is zero or none
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Meta {
+	mode:     u32,
+	len:      u64,
+	uid:      u32,
+	gid:      u32,
+	ino:      u64,
+	dev:      u64,
+	nlink:    u64,
+	modified: Option<SystemTime>,
+	accessed: Option<SystemTime>,
+	created:  Option<SystemTime>,
+}
+
+impl From<std::fs::Metadata> for Meta {
+	fn from(m: std::fs::Metadata) -> Self { Self::from(&m) }
+}
+
+impl From<&std::fs::Metadata> for Meta {
+	#[cfg(unix)]
+	fn from(m: &std::fs::Metadata) -> Self {
+		use std::os::unix::fs::MetadataExt;
+		Self {
+			mode:     m.mode(),
+			len:      m.len(),
+			uid:      m.uid(),
+			gid:      m.gid(),
+			ino:      m.ino(),
+			dev:      m.dev(),
+			nlink:    m.nlink(),
+			modified: m.modified().ok(),
+			accessed: m.accessed().ok(),
+			created:  m.created().ok(),
+		}
+	}
+
+	#[cfg(not(unix))]
+	fn from(m: &std::fs::Metadata) -> Self {
+		let kind = match () {
+			_ if m.is_symlink() => S_IFLNK,
+			_ if m.is_dir() => S_IFDIR,
+			_ => S_IFREG,
+		};
+		Self {
+			mode:     kind | if m.permissions().readonly() { 0o444 } else { 0o644 },
+			len:      m.len(),
+			modified: m.modified().ok(),
+			accessed: m.accessed().ok(),
+			created:  m.created().ok(),
+			..Default::default()
+		}
+	}
+}
+
+impl Meta {
+	// Of the local or the remote file, the one a symlink points to if `follow`
+	pub async fn read(url: &Url, follow: bool) -> Result<Self> {
+		if url.is_sftp() {
+			return sftp::metadata(url, follow).await;
+		}
+		Ok(if follow { fs::metadata(url).await? } else { fs::symlink_metadata(url).await? }.into())
+	}
+
+	// Of a remote file, by the mode with the type bits, and the times in seconds since the epoch
+	pub(crate) fn remote(
+		mode: u32,
+		len: u64,
+		owner: Option<(u32, u32)>,
+		times: Option<(u32, u32)>,
+	) -> Self {
+		let time = |secs: u32| SystemTime::UNIX_EPOCH + Duration::from_secs(secs as u64);
+		let (uid, gid) = owner.unwrap_or_default();
+		Self {
+			mode,
+			len,
+			uid,
+			gid,
+			nlink: 1,
+			accessed: times.map(|(a, _)| time(a)),
+			modified: times.map(|(_, m)| time(m)),
+			..Default::default()
+		}
+	}
+
+	#[inline]
+	fn kind(&self) -> u32 { self.mode & S_IFMT }
+
+	#[inline]
+	pub fn is_dir(&self) -> bool { self.kind() == S_IFDIR }
+
+	#[inline]
+	pub fn is_file(&self) -> bool { self.kind() == S_IFREG }
+
+	#[inline]
+	pub fn is_symlink(&self) -> bool { self.kind() == S_IFLNK }
+
+	#[inline]
+	pub fn is_fifo(&self) -> bool { self.kind() == S_IFIFO }
+
+	#[inline]
+	pub fn is_socket(&self) -> bool { self.kind() == S_IFSOCK }
+
+	#[inline]
+	pub fn is_block_device(&self) -> bool { self.kind() == S_IFBLK }
+
+	#[inline]
+	pub fn is_char_device(&self) -> bool { self.kind() == S_IFCHR }
+
+	#[inline]
+	pub fn len(&self) -> u64 { self.len }
+
+	// The permissions along with the type bits, as `st_mode`
+	#[inline]
+	pub fn mode(&self) -> u32 { self.mode }
+
+	#[inline]
+	pub fn uid(&self) -> u32 { self.uid }
+
+	#[inline]
+	pub fn gid(&self) -> u32 { self.gid }
+
+	#[inline]
+	pub fn ino(&self) -> u64 { self.ino }
+
+	#[inline]
+	pub fn dev(&self) -> u64 { self.dev }
+
+	#[inline]
+	pub fn nlink(&self) -> u64 { self.nlink }
+
+	#[inline]
+	pub fn modified(&self) -> Option<SystemTime> { self.modified }
+
+	#[inline]
+	pub fn accessed(&self) -> Option<SystemTime> { self.accessed }
+
+	#[inline]
+	pub fn created(&self) -> Option<SystemTime> { self.created }
+}
+
+#[test]
+fn test_meta() {
+	let meta = Meta::remote(0o100755, 3, Some((1000, 100)), Some((1, 2)));
+	assert!(meta.is_file() && !meta.is_dir() && !meta.is_symlink());
+	assert_eq!((meta.len(), meta.uid(), meta.gid()), (3, 1000, 100));
+	assert_eq!(meta.modified(), Some(SystemTime::UNIX_EPOCH + Duration::from_secs(2)));
+	assert!(meta.created().is_none());
+
+	assert!(Meta::remote(0o040755, 0, None, None).is_dir());
+	assert!(Meta::remote(0o120777, 0, None, None).is_symlink());
+	assert!(Meta::remote(0o010644, 0, None, None).is_fifo());
+
+	let local = Meta::from(std::fs::metadata(std::env::temp_dir()).unwrap());
+	assert!(local.is_dir() && local.modified().is_some());
+}
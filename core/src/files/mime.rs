@@ -0,0 +1,286 @@
+use std::{collections::BTreeMap, path::Path, time::SystemTime};
+
+use anyhow::{bail, Result};
+use parking_lot::Mutex;
+use shared::{MimeKind, Url};
+use tokio::{fs, io::AsyncReadExt};
+
+use crate::{external, sftp};
+
+// How much of the head of a file is read to tell its type by
+const HEAD: usize = 1024;
+
+// The types told, by the path and the modification time of the file they're for, so that a file
+// is only read again once it's changed
+static CACHE: Mutex<BTreeMap<Url, (Option<SystemTime>, String)>> = Mutex::new(BTreeMap::new());
+const CACHE_LIMIT: usize = 10000;
+
+// The files, the magic numbers at their offsets, and the types they're of, checked in order
+#[rustfmt::skip]
+const MAGIC: &[(usize, &[u8], &str)] = &[
+	(0, b"\x89PNG\r\n\x1a\n", "image/png"),
+	(0, b"\xff\xd8\xff", "image/jpeg"),
+	(0, b"GIF87a", "image/gif"),
+	(0, b"GIF89a", "image/gif"),
+	(0, b"II*\0", "image/tiff"),
+	(0, b"MM\0*", "image/tiff"),
+	(0, b"\0\0\x01\0", "image/vnd.microsoft.icon"),
+	(0, b"%PDF-", "application/pdf"),
+	(0, b"\x1f\x8b", "application/gzip"),
+	(0, b"BZh", "application/x-bzip2"),
+	(0, b"\xfd7zXZ\0", "application/x-xz"),
+	(0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+	(0, b"Rar!\x1a\x07", "application/x-rar"),
+	(0, b"\x28\xb5\x2f\xfd", "application/zstd"),
+	(257, b"ustar", "application/x-tar"),
+	(0, b"\x7fELF", "application/x-executable"),
+	(0, b"\0asm", "application/wasm"),
+	(0, b"SQLite format 3\0", "application/vnd.sqlite3"),
+	(0, b"OggS", "audio/ogg"),
+	(0, b"fLaC", "audio/flac"),
+	(0, b"ID3", "audio/mpeg"),
+	(0, b"wOFF", "font/woff"),
+	(0, b"wOF2", "font/woff2"),
+	(0, b"OTTO", "font/otf"),
+];
+
+#[rustfmt::skip]
+const EXTENSIONS: &[(&str, &str)] = &[
+	("png", "image/png"), ("jpg", "image/jpeg"), ("jpeg", "image/jpeg"), ("gif", "image/gif"),
+	("webp", "image/webp"), ("bmp", "image/bmp"), ("tif", "image/tiff"), ("tiff", "image/tiff"),
+	("ico", "image/vnd.microsoft.icon"), ("heic", "image/heic"), ("avif", "image/avif"),
+	("svg", "image/svg+xml"),
+	("mp4", "video/mp4"), ("m4v", "video/mp4"), ("mkv", "video/x-matroska"), ("webm", "video/webm"),
+	("mov", "video/quicktime"), ("avi", "video/x-msvideo"),
+	("mp3", "audio/mpeg"), ("flac", "audio/flac"), ("ogg", "audio/ogg"), ("opus", "audio/ogg"),
+	("wav", "audio/x-wav"), ("m4a", "audio/x-m4a"),
+	("pdf", "application/pdf"), ("json", "application/json"), ("js", "application/javascript"),
+	("mjs", "application/javascript"), ("cjs", "application/javascript"),
+	("wasm", "application/wasm"),
+	("zip", "application/zip"), ("gz", "application/gzip"), ("tgz", "application/gzip"),
+	("tar", "application/x-tar"), ("bz2", "application/x-bzip2"), ("xz", "application/x-xz"),
+	("7z", "application/x-7z-compressed"), ("rar", "application/x-rar"), ("zst", "application/zstd"),
+	("jar", "application/java-archive"), ("epub", "application/epub+zip"),
+	("docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+	("xlsx", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+	("pptx", "application/vnd.openxmlformats-officedocument.presentationml.presentation"),
+	("odt", "application/vnd.oasis.opendocument.text"),
+	("txt", "text/plain"), ("md", "text/markdown"), ("csv", "text/csv"), ("html", "text/html"),
+	("htm", "text/html"), ("css", "text/css"), ("xml", "text/xml"), ("toml", "text/x-toml"),
+	("yaml", "text/x-yaml"), ("yml", "text/x-yaml"), ("ts", "text/x-typescript"),
+	("rs", "text/x-rust"), ("c", "text/x-c"), ("h", "text/x-c"), ("cpp", "text/x-c++"),
+	("hpp", "text/x-c++"), ("go", "text/x-go"), ("java", "text/x-java"),
+	("py", "text/x-script.python"), ("rb", "text/x-ruby"), ("lua", "text/x-lua"),
+	("sh", "text/x-shellscript"), ("bash", "text/x-shellscript"), ("zsh", "text/x-shellscript"),
+];
+
+// The types of the files neither the head nor the extension tell more of
+const GENERIC: &[&str] = &["text/plain", "application/octet-stream"];
+
+// The kinds of zip told apart by their extensions only
+const ZIPPED: &[&str] = &[
+	"application/java-archive",
+	"application/epub+zip",
+	"application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+	"application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+	"application/vnd.openxmlformats-officedocument.presentationml.presentation",
+];
+
+// The type the head of a file is of by its magic number, or by the ones of the containers that
+// tell what's in them, none if it doesn't have any known
+pub fn mime_sniff(head: &[u8]) -> Option<&'static str> {
+	let at = |i: usize, m: &[u8]| head.get(i..i + m.len()) == Some(m);
+	if let Some(&(_, _, mime)) = MAGIC.iter().find(|&&(i, m, _)| at(i, m)) {
+		return Some(mime);
+	}
+
+	Some(match () {
+		_ if at(0, b"RIFF") && at(8, b"WEBP") => "image/webp",
+		_ if at(0, b"RIFF") && at(8, b"WAVE") => "audio/x-wav",
+		_ if at(0, b"RIFF") && at(8, b"AVI ") => "video/x-msvideo",
+		_ if at(4, b"ftyp") => match head.get(8..12) {
+			Some(b"qt  ") => "video/quicktime",
+			Some(b"heic" | b"heix" | b"mif1") => "image/heic",
+			Some(b"avif") => "image/avif",
+			Some(b"M4A ") => "audio/x-m4a",
+			_ => "video/mp4",
+		},
+		_ if at(0, b"\x1a\x45\xdf\xa3") => {
+			if head.windows(4).any(|w| w == b"webm") { "video/webm" } else { "video/x-matroska" }
+		}
+		// The reserved bytes of a bitmap are zero, for it not to be text starting with `BM`
+		_ if at(0, b"BM") && at(6, b"\0\0\0\0") => "image/bmp",
+		_ if at(0, b"\xff\xfb") || at(0, b"\xff\xf3") || at(0, b"\xff\xf2") => "audio/mpeg",
+		_ if at(0, b"PK\x03\x04") => return Some(zip_mime(head).unwrap_or("application/zip")),
+		_ => return None,
+	})
+}
+
+// The type of an OpenDocument, told by the `mimetype` stored first in the zip of it
+fn zip_mime(head: &[u8]) -> Option<&'static str> {
+	let name = head.get(30..38).filter(|&n| n == b"mimetype")?;
+	let rest = &head[30 + name.len()..];
+	let end = rest.windows(2).position(|w| w == b"PK").unwrap_or(rest.len());
+	EXTENSIONS.iter().map(|&(_, m)| m).find(|m| m.as_bytes() == &rest[..end])
+}
+
+// Whether the head of a file is text, in UTF-8, where the last character may be cut off
+fn mime_text(head: &[u8]) -> bool {
+	let valid = match std::str::from_utf8(head) {
+		Ok(_) => true,
+		Err(e) => e.error_len().is_none(),
+	};
+	valid && !head.iter().any(|&b| b == 0 || (b < 0x20 && !b"\t\n\r\x0c\x1b".contains(&b)))
+}
+
+// The type of a script told by the interpreter of its shebang, e.g. `#!/usr/bin/env python3`
+fn mime_shebang(head: &[u8]) -> Option<&'static str> {
+	let line = head.strip_prefix(b"#!")?.split(|&b| b == b'\n').next()?;
+	let line = std::str::from_utf8(line).ok()?;
+	let mut words = line.split_whitespace();
+	let mut bin = words.next()?.rsplit('/').next()?;
+	if bin == "env" {
+		bin = words.find(|w| !w.starts_with('-'))?;
+	}
+
+	let bin = bin.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+	Some(match bin {
+		"sh" | "bash" | "zsh" | "fish" | "dash" | "ksh" => "text/x-shellscript",
+		"python" => "text/x-script.python",
+		"node" => "application/javascript",
+		"ruby" => "text/x-ruby",
+		"perl" => "text/x-perl",
+		"lua" => "text/x-lua",
+		_ => return None,
+	})
+}
+
+pub fn mime_by_ext(path: &Path) -> Option<&'static str> {
+	let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+	EXTENSIONS.iter().find(|&&(e, _)| e == ext).map(|&(_, m)| m)
+}
+
+// The type of a file by its head, taking the one of its extension if that doesn't tell, or if it's
+// text, for the kinds of text told apart by the extensions only, e.g. `application/json`
+pub fn mime_detect(path: &Path, head: &[u8]) -> &'static str {
+	if head.is_empty() {
+		return "inode/x-empty";
+	}
+
+	let ext = mime_by_ext(path);
+	if let Some(mime) = mime_sniff(head) {
+		// A zip of a kind of its own, like `.docx`
+		return match ext {
+			Some(e) if mime == "application/zip" && ZIPPED.contains(&e) => e,
+			_ => mime,
+		};
+	}
+
+	if !mime_text(head) {
+		let ext = ext.filter(|e| MimeKind::new(e) != MimeKind::Text);
+		return ext.unwrap_or("application/octet-stream");
+	}
+	match ext {
+		Some(e) if matches!(MimeKind::new(e), MimeKind::Text | MimeKind::JSON) => e,
+		Some(e @ "image/svg+xml") => e,
+		_ => mime_shebang(head).unwrap_or("text/plain"),
+	}
+}
+
+// The types of the regular files, following the symlinks, read from the cache if they haven't
+// changed since they were last told. Others, such as the directories, are left out. The ones that
+// are only known to be text or binary are asked of `file` then, which knows more of the rarer types
+pub async fn mimetypes(files: &[impl AsRef<Url>]) -> Result<BTreeMap<Url, String>> {
+	let (mut mimes, mut told) = (BTreeMap::new(), vec![]);
+	for url in files.iter().map(AsRef::as_ref) {
+		let Some(mtime) = stat(url).await else {
+			continue;
+		};
+		if let Some((_, m)) = CACHE.lock().get(url).filter(|(t, _)| t.is_some() && *t == mtime) {
+			mimes.insert(url.clone(), m.clone());
+			continue;
+		}
+
+		let Some(head) = head(url).await else {
+			continue;
+		};
+		told.push((url.clone(), mtime, mime_detect(url, &head)));
+	}
+
+	// `file` can't read the remote ones
+	let generic: Vec<_> = told
+		.iter()
+		.filter(|(u, _, m)| GENERIC.contains(m) && !u.is_sftp())
+		.map(|(u, ..)| u)
+		.collect();
+	let mut asked = if generic.is_empty() { None } else { external::file(&generic).await.ok() };
+
+	let mut cache = CACHE.lock();
+	if cache.len() + told.len() > CACHE_LIMIT {
+		cache.clear();
+	}
+	for (url, mtime, mime) in told {
+		let mime = asked.as_mut().and_then(|a| a.remove(&url)).unwrap_or_else(|| mime.to_owned());
+		cache.insert(url.clone(), (mtime, mime.clone()));
+		mimes.insert(url, mime);
+	}
+
+	if mimes.is_empty() {
+		bail!("failed to get mime types");
+	}
+	Ok(mimes)
+}
+
+// The time a regular file was modified at, following the symlinks, none if it isn't one
+async fn stat(url: &Url) -> Option<Option<SystemTime>> {
+	if url.is_sftp() {
+		let meta = sftp::metadata(url, true).await.ok()?;
+		return meta.is_file().then(|| meta.modified());
+	}
+
+	let meta = fs::metadata(url).await.ok()?;
+	meta.is_file().then(|| meta.modified().ok())
+}
+
+async fn head(url: &Url) -> Option<Vec<u8>> {
+	if url.is_sftp() {
+		return sftp::head(url, HEAD as u32).await.ok();
+	}
+
+	let mut head = Vec::with_capacity(HEAD);
+	let f = fs::File::open(url).await.ok()?;
+	f.take(HEAD as u64).read_to_end(&mut head).await.ok()?;
+	Some(head)
+}
+
+#[test]
+fn test_mime_detect() {
+	let detect = |name: &str, head: &[u8]| mime_detect(Path::new(name), head);
+
+	// By the content, whatever the extension
+	assert_eq!(detect("a.txt", b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), "image/png");
+	assert_eq!(detect("a", b"RIFF\0\0\0\0WEBPVP8 "), "image/webp");
+	assert_eq!(detect("a", b"\0\0\0\x18ftypisom"), "video/mp4");
+	assert_eq!(detect("a", b"%PDF-1.7\n"), "application/pdf");
+	assert_eq!(detect("", b""), "inode/x-empty");
+
+	// The kinds of zip and of text by the extension, or the shebang
+	assert_eq!(
+		detect("a.docx", b"PK\x03\x04\x14\0"),
+		"application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+	);
+	assert_eq!(detect("a.zip", b"PK\x03\x04\x14\0"), "application/zip");
+	let mut odt = b"PK\x03\x04".to_vec();
+	odt.resize(30, 0);
+	odt.extend(b"mimetypeapplication/vnd.oasis.opendocument.textPK\x03\x04");
+	assert_eq!(detect("a", &odt), "application/vnd.oasis.opendocument.text");
+	assert_eq!(detect("a.json", b"{\"a\": 1}"), "application/json");
+	assert_eq!(detect("a.png", b"not an image"), "text/plain");
+	assert_eq!(detect("run", b"#!/usr/bin/env python3\nprint()"), "text/x-script.python");
+	assert_eq!(detect("run", b"#!/bin/bash\n"), "text/x-shellscript");
+	assert_eq!(detect("a", "caf\u{e9}".as_bytes()), "text/plain");
+
+	// Text starting with the magic number of a bitmap, and a binary file of no extension known
+	assert_eq!(detect("a", b"BMW 320i\n"), "text/plain");
+	assert_eq!(detect("a.bin", b"\x01\x02\x03\0\xff"), "application/octet-stream");
+}
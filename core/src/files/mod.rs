@@ -1,9 +1,22 @@
+mod collation;
 mod file;
+mod filter;
 mod files;
+mod gitignore;
+mod meta;
+mod mime;
 mod op;
+mod quick;
 mod sorter;
+mod tally;
 
+pub(crate) use gitignore::ignore_rules;
 pub use file::*;
+pub use filter::*;
 pub use files::*;
+pub use meta::*;
+pub use mime::*;
 pub use op::*;
+pub use quick::*;
 pub use sorter::*;
+pub use tally::*;
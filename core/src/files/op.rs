@@ -1,9 +1,9 @@
 use std::{collections::BTreeMap, sync::atomic::{AtomicU64, Ordering}};
 
-use shared::Url;
+use shared::{Url, Walked};
 
 use super::File;
-use crate::emit;
+use crate::{emit, external::GitStatus};
 
 pub(super) static FILES_TICKET: AtomicU64 = AtomicU64::new(0);
 
@@ -12,6 +12,18 @@ pub enum FilesOp {
 	Full(Url, Vec<File>),
 	Part(Url, u64, Vec<File>),
 	Size(Url, BTreeMap<Url, u64>),
+	// The sizes so far of the directories still being calculated
+	SizePartial(Url, BTreeMap<Url, u64>),
+	// The directories of the sizes just updated that are cut short of some of them
+	SizeCut(Url, BTreeMap<Url, Walked>),
+	Children(Url, BTreeMap<Url, usize>),
+	// The places of the files in the output of the `sort_command`
+	Ranks(Url, BTreeMap<Url, usize>),
+	Git(Url, BTreeMap<Url, GitStatus>),
+	// The branch checked out in the repository the directory is in, for the `{branch}` of the status
+	Branch(Url, Option<String>),
+	Xattrs(Url, BTreeMap<Url, Vec<String>>),
+	Expand(Url, Url, Vec<File>),
 	IOErr(Url),
 }
 
@@ -22,6 +34,14 @@ impl FilesOp {
 			Self::Full(url, _) => url,
 			Self::Part(url, ..) => url,
 			Self::Size(url, _) => url,
+			Self::SizePartial(url, _) => url,
+			Self::SizeCut(url, _) => url,
+			Self::Children(url, _) => url,
+			Self::Ranks(url, _) => url,
+			Self::Git(url, _) => url,
+			Self::Branch(url, _) => url,
+			Self::Xattrs(url, _) => url,
+			Self::Expand(url, ..) => url,
 			Self::IOErr(url) => url,
 		}
 	}
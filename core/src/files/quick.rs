@@ -0,0 +1,148 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, bail, Result};
+
+use super::File;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Term {
+	Larger(u64),
+	Smaller(u64),
+	Within(u64, u64),
+	Older(Duration),
+	Newer(Duration),
+}
+
+// The files narrowed down by their sizes or ages, the terms of which all have to be met, such as
+// `>10M` for the ones larger than 10 MiB, `<7d` for the ones modified within 7 days, or `1M..1G`
+#[derive(Clone, Debug)]
+pub struct QuickFilter {
+	raw:   String,
+	terms: Vec<Term>,
+}
+
+impl QuickFilter {
+	pub fn new(raw: &str) -> Result<Self> {
+		let terms = raw.split_whitespace().map(Self::term).collect::<Result<_>>()?;
+		Ok(Self { raw: raw.trim().to_owned(), terms })
+	}
+
+	fn term(s: &str) -> Result<Term> {
+		if let Some((lo, hi)) = s.split_once("..") {
+			let (lo, hi) = (size(lo)?, size(hi)?);
+			if lo > hi {
+				bail!("`{s}` is an empty range");
+			}
+			return Ok(Term::Within(lo, hi));
+		}
+
+		let (larger, rest) = match (s.strip_prefix('>'), s.strip_prefix('<')) {
+			(Some(rest), _) => (true, rest),
+			(_, Some(rest)) => (false, rest),
+			_ => bail!("`{s}` isn't like `>10M`, `<7d` or `1M..1G`"),
+		};
+		Ok(match duration(rest) {
+			Some(d) if larger => Term::Older(d),
+			Some(d) => Term::Newer(d),
+			None if larger => Term::Larger(size(rest)?),
+			None => Term::Smaller(size(rest)?),
+		})
+	}
+
+	// Whether the file meets all the terms by `size`, the one of the content of a directory, or none
+	// if it isn't known yet, which a size never matches, and by its age at `now`
+	pub fn matches(&self, file: &File, size: Option<u64>, now: SystemTime) -> bool {
+		let age = || {
+			let modified = file.meta.modified();
+			modified.and_then(|t| now.duration_since(t).ok()).unwrap_or_default()
+		};
+
+		self.terms.iter().all(|&term| match term {
+			Term::Larger(n) => size.is_some_and(|s| s > n),
+			Term::Smaller(n) => size.is_some_and(|s| s < n),
+			Term::Within(lo, hi) => size.is_some_and(|s| (lo..=hi).contains(&s)),
+			Term::Older(d) => age() > d,
+			Term::Newer(d) => age() < d,
+		})
+	}
+}
+
+impl PartialEq for QuickFilter {
+	fn eq(&self, other: &Self) -> bool { self.terms == other.terms }
+}
+
+impl QuickFilter {
+	#[inline]
+	pub fn raw(&self) -> &str { &self.raw }
+
+	#[inline]
+	pub fn is_empty(&self) -> bool { self.terms.is_empty() }
+}
+
+// Bytes of the units K, M, G and T of 1024, with `B` or `iB` or not, e.g. `1.5G`, `10MiB` or `512`
+fn size(s: &str) -> Result<u64> {
+	let i = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+	let (n, unit) = s.split_at(i);
+	let n: f64 = n.parse().map_err(|_| anyhow!("`{s}` isn't a size"))?;
+
+	let unit = unit.to_ascii_lowercase();
+	let exp = match unit.strip_suffix("ib").or_else(|| unit.strip_suffix('b')).unwrap_or(&unit) {
+		"" => 0,
+		"k" => 1,
+		"m" => 2,
+		"g" => 3,
+		"t" => 4,
+		_ => bail!("`{s}` isn't a size of the units K, M, G or T"),
+	};
+	Ok((n * 1024f64.powi(exp)) as u64)
+}
+
+// Seconds, minutes, hours, days, weeks or years, e.g. `30s`, `10min`, `7d` or `1y`, where `m` is
+// left to the sizes as the mebibytes
+fn duration(s: &str) -> Option<Duration> {
+	let i = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+	let (n, unit) = s.split_at(i);
+	let secs = match unit {
+		"s" => 1,
+		"min" => 60,
+		"h" => 3600,
+		"d" => 86400,
+		"w" => 604800,
+		"y" => 31536000,
+		_ => return None,
+	};
+	Some(Duration::from_secs(n.parse::<u64>().ok()?.saturating_mul(secs)))
+}
+
+#[test]
+fn test_quick_filter() {
+	use super::sorter::test_files;
+
+	assert_eq!(size("512").unwrap(), 512);
+	assert_eq!(size("10M").unwrap(), 10 << 20);
+	assert_eq!(size("1.5g").unwrap(), 3 << 29);
+	assert_eq!(size("2KiB").unwrap(), size("2kb").unwrap());
+	assert!(size("10x").is_err() && size("M").is_err());
+	assert_eq!(duration("7d"), Some(Duration::from_secs(7 * 86400)));
+	assert_eq!(duration("10m"), None);
+
+	let items = test_files(&[("a", 100), ("b", 10 << 20), ("c", 2 << 30), ("dir/", 4096)]);
+	let now = SystemTime::now();
+	let pick = |raw: &str, now: SystemTime| -> Vec<_> {
+		let q = QuickFilter::new(raw).unwrap();
+		let size = |f: &File| if f.is_dir() { None } else { Some(f.length) };
+		items.iter().filter(|f| q.matches(f, size(f), now)).map(|f| f.url.to_str().unwrap()).collect()
+	};
+
+	assert_eq!(pick(">1M", now), ["b", "c"]);
+	assert_eq!(pick("<1k", now), ["a"]);
+	assert_eq!(pick("1M..1G", now), ["b"]);
+	assert_eq!(pick("", now).len(), 4);
+	assert_eq!(pick(">1M >30d", now).len(), 0);
+	assert_eq!(pick(">1M >30d", now + Duration::from_secs(31 * 86400)), ["b", "c"]);
+	assert_eq!(pick("<1y", now).len(), 4);
+
+	assert!(QuickFilter::new("10M").is_err());
+	assert!(QuickFilter::new("1G..1M").is_err());
+	assert_eq!(QuickFilter::new(" >1M  ").unwrap(), QuickFilter::new(">1048576").unwrap());
+}
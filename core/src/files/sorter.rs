@@ -1,60 +1,200 @@
-use std::{cmp::Ordering, collections::BTreeMap, mem};
+use std::{borrow::Cow, cmp::Ordering, collections::{hash_map::{DefaultHasher, RandomState}, BTreeMap}, ffi::OsStr, hash::{BuildHasher, Hash, Hasher}, path::Path, time::SystemTime};
 
-use config::{manager::SortBy, MANAGER};
+use config::{manager::{Collation, FileKind, SortBy}, MANAGER};
 use shared::Url;
 
-use super::File;
+use super::{collation, File};
 
 #[derive(Clone, Copy, PartialEq)]
 pub struct FilesSorter {
-	pub by:        SortBy,
-	pub reverse:   bool,
-	pub dir_first: bool,
+	pub by:             SortBy,
+	pub sensitive:      bool,
+	pub reverse:        bool,
+	pub dir_first:      bool,
+	pub hidden_last:    Option<bool>,
+	pub kind_order:     Option<[FileKind; 5]>,
+	pub size_recursive: bool,
+	pub stable:         bool,
+	pub collation:      Collation,
+	pub tiebreak:       SortBy,
+	pub split_ext:      bool,
+	pub leading_zeros:  bool,
+	pub thousands:      bool,
+	pub seed:           u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortChange {
+	Unchanged,
+	Reordered,
+}
+
+impl SortChange {
+	#[inline]
+	pub fn is_reordered(self) -> bool { self == Self::Reordered }
 }
 
 impl Default for FilesSorter {
 	fn default() -> Self {
 		Self {
-			by:        MANAGER.sort_by,
-			reverse:   MANAGER.sort_reverse,
-			dir_first: MANAGER.sort_dir_first,
+			by:             MANAGER.sort_by,
+			sensitive:      MANAGER.sort_sensitive,
+			reverse:        MANAGER.sort_reverse,
+			dir_first:      MANAGER.sort_dir_first,
+			hidden_last:    None,
+			kind_order:     Self::normalize_kinds(&MANAGER.sort_kind_order),
+			size_recursive: MANAGER.sort_size_recursive,
+			stable:         false,
+			collation:      MANAGER.sort_collation,
+			tiebreak:       MANAGER.sort_tiebreak,
+			split_ext:      MANAGER.sort_split_ext,
+			leading_zeros:  MANAGER.sort_leading_zeros,
+			thousands:      MANAGER.sort_thousands,
+			seed:           Self::roll(),
 		}
 	}
 }
 
 impl FilesSorter {
-	pub(super) fn sort(&self, items: &mut Vec<File>, sizes: &BTreeMap<Url, u64>) -> bool {
+	#[inline]
+	pub fn roll() -> u64 { RandomState::new().build_hasher().finish() }
+
+	pub fn normalize_kinds(kinds: &[FileKind]) -> Option<[FileKind; 5]> {
+		if kinds.is_empty() {
+			return None;
+		}
+
+		let mut order = Vec::with_capacity(FileKind::ALL.len());
+		for &kind in kinds.iter().chain(&FileKind::ALL) {
+			if !order.contains(&kind) {
+				order.push(kind);
+			}
+		}
+		order.try_into().ok()
+	}
+
+	/// Starts a sorter independent of the config, by the alphabetical order ignoring the case,
+	/// with the directories first, see [`FilesSorterBuilder`] for the rest.
+	///
+	/// ```
+	/// use std::collections::BTreeMap;
+	///
+	/// use config::manager::SortBy;
+	/// use core::files::{File, FilesSorter};
+	/// use shared::Url;
+	///
+	/// # #[tokio::main(flavor = "current_thread")]
+	/// # async fn main() {
+	/// let meta = std::fs::metadata(std::env::current_exe().unwrap()).unwrap();
+	/// let mut items = vec![];
+	/// for name in ["file10", "file2", "file1"] {
+	///     items.push(File::from_meta(Url::from(name), meta.clone()).await);
+	/// }
+	///
+	/// FilesSorter::builder().by(SortBy::Natural).sort(&mut items, &BTreeMap::new());
+	/// let names: Vec<_> = items.iter().map(|f| f.name_display().unwrap()).collect();
+	/// assert_eq!(names, ["file1", "file2", "file10"]);
+	/// # }
+	/// ```
+	#[inline]
+	pub fn builder() -> FilesSorterBuilder { FilesSorterBuilder::default() }
+
+	/// Sorts `items` in place, with `sizes` the calculated sizes of the directories, for
+	/// [`SortBy::Size`]. The directories are counted as empty for [`SortBy::Children`], and
+	/// [`SortBy::External`] sorts naturally, as the `sort_command` of the config is yet to run.
+	#[inline]
+	pub fn sort(&self, items: &mut [File], sizes: &BTreeMap<Url, u64>) -> SortChange {
+		self.sort_with(items, sizes, &Default::default(), &Default::default())
+	}
+
+	// Where `ranks` are the places of the files in the output of the `sort_command`, once it's run
+	pub(super) fn sort_with(
+		&self,
+		items: &mut [File],
+		sizes: &BTreeMap<Url, u64>,
+		children: &BTreeMap<Url, usize>,
+		ranks: &BTreeMap<Url, usize>,
+	) -> SortChange {
 		if items.is_empty() {
-			return false;
+			return SortChange::Unchanged;
+		}
+
+		if self.collation == Collation::Locale
+			&& matches!(self.by, SortBy::Alphabetical | SortBy::Natural)
+			&& collation::available()
+		{
+			return self.sort_collated(items, sizes, children);
 		}
 
 		match self.by {
-			SortBy::Alphabetical => {
-				items.sort_unstable_by(|a, b| self.cmp(&*a.url, &*b.url, self.promote(a, b)))
-			}
-			SortBy::Created => items.sort_unstable_by(|a, b| {
-				if let (Ok(aa), Ok(bb)) = (a.meta.created(), b.meta.created()) {
-					return self.cmp(aa, bb, self.promote(a, b));
+			SortBy::Alphabetical if self.sensitive => self.sort_by(items, |a, b| {
+				self
+					.cmp(&*a.url, &*b.url, self.promote(a, b))
+					.then_with(|| self.tiebreak(a, b, sizes, children))
+			}),
+			SortBy::Alphabetical => self.sort_alphabetically(items, sizes, children),
+			SortBy::Created => self.sort_by_time(items, sizes, children, |f| f.meta.created()),
+			SortBy::Modified => self.sort_by_time(items, sizes, children, |f| f.meta.modified()),
+			SortBy::Accessed => self.sort_by_time(items, sizes, children, |f| f.meta.accessed()),
+			SortBy::Extension => self.sort_by(items, |a, b| {
+				let promote = self.promote(a, b);
+				if promote != Ordering::Equal {
+					return promote;
 				}
-				Ordering::Equal
+
+				let ord = self.compare(SortBy::Extension, a, b, sizes, children);
+				let ord = if self.reverse { ord.reverse() } else { ord };
+				ord.then_with(|| self.tiebreak(a, b, sizes, children))
 			}),
-			SortBy::Modified => items.sort_unstable_by(|a, b| {
-				if let (Ok(aa), Ok(bb)) = (a.meta.modified(), b.meta.modified()) {
-					return self.cmp(aa, bb, self.promote(a, b));
+			SortBy::Natural => self.sort_naturally(items, sizes, children),
+			SortBy::Size => self.sort_by(items, |a, b| self.cmp_size(a, b, sizes, children)),
+			SortBy::Children => self.sort_by(items, |a, b| {
+				let promote = self.promote(a, b);
+				if promote != Ordering::Equal {
+					return promote;
 				}
-				Ordering::Equal
+
+				let ord = self.compare(SortBy::Children, a, b, sizes, children);
+				let ord = if self.reverse { ord.reverse() } else { ord };
+				ord.then_with(|| self.tiebreak(a, b, sizes, children))
 			}),
-			SortBy::Natural => self.sort_naturally(items),
-			SortBy::Size => items.sort_unstable_by(|a, b| {
-				let aa = if a.is_dir() { sizes.get(a.url()).copied() } else { None };
-				let bb = if b.is_dir() { sizes.get(b.url()).copied() } else { None };
-				self.cmp(aa.unwrap_or(a.length), bb.unwrap_or(b.length), self.promote(a, b))
+			SortBy::Random => self.sort_by(items, |a, b| {
+				self.promote(a, b).then_with(|| self.shuffled(a).cmp(&self.shuffled(b)))
 			}),
+			SortBy::External => self.sort_externally(items, sizes, children, ranks),
+		}
+	}
+
+	fn sort_alphabetically(
+		&self,
+		items: &mut [File],
+		sizes: &BTreeMap<Url, u64>,
+		children: &BTreeMap<Url, usize>,
+	) -> SortChange {
+		let mut indices = Vec::with_capacity(items.len());
+		let mut entities = Vec::with_capacity(items.len());
+		for (i, file) in items.iter().enumerate() {
+			indices.push(i);
+			entities.push((file.url.to_string_lossy().to_lowercase(), file));
 		}
-		true
+
+		let cmp = |&a: &usize, &b: &usize| {
+			self
+				.cmp(&entities[a].0, &entities[b].0, self.promote(entities[a].1, entities[b].1))
+				.then_with(|| self.tiebreak(entities[a].1, entities[b].1, sizes, children))
+		};
+		if self.stable { indices.sort_by(cmp) } else { indices.sort_unstable_by(cmp) }
+
+		drop(entities);
+		Self::permute(items, indices)
 	}
 
-	fn sort_naturally(&self, items: &mut Vec<File>) {
+	fn sort_collated(
+		&self,
+		items: &mut [File],
+		sizes: &BTreeMap<Url, u64>,
+		children: &BTreeMap<Url, usize>,
+	) -> SortChange {
 		let mut indices = Vec::with_capacity(items.len());
 		let mut entities = Vec::with_capacity(items.len());
 		for (i, file) in items.iter().enumerate() {
@@ -62,31 +202,72 @@ impl FilesSorter {
 			entities.push((file.url.to_string_lossy(), file));
 		}
 
-		indices.sort_unstable_by(|&a, &b| {
+		let numeric = self.by == SortBy::Natural;
+		let cmp = |&a: &usize, &b: &usize| {
 			let promote = self.promote(entities[a].1, entities[b].1);
 			if promote != Ordering::Equal {
-				promote
-			} else if self.reverse {
-				natord::compare(&entities[b].0, &entities[a].0)
-			} else {
-				natord::compare(&entities[a].0, &entities[b].0)
+				return promote;
+			}
+
+			let (x, y) = if self.reverse { (b, a) } else { (a, b) };
+			collation::collate(&entities[x].0, &entities[y].0, self.sensitive, numeric)
+				.then_with(|| self.tiebreak(entities[a].1, entities[b].1, sizes, children))
+		};
+		if self.stable { indices.sort_by(cmp) } else { indices.sort_unstable_by(cmp) }
+
+		drop(entities);
+		Self::permute(items, indices)
+	}
+
+	fn sort_naturally(
+		&self,
+		items: &mut [File],
+		sizes: &BTreeMap<Url, u64>,
+		children: &BTreeMap<Url, usize>,
+	) -> SortChange {
+		let mut indices = Vec::with_capacity(items.len());
+		let mut entities = Vec::with_capacity(items.len());
+		for (i, file) in items.iter().enumerate() {
+			indices.push(i);
+			entities.push((file.url.to_string_lossy(), file));
+		}
+
+		let cmp = |&a: &usize, &b: &usize| {
+			let promote = self.promote(entities[a].1, entities[b].1);
+			if promote != Ordering::Equal {
+				return promote;
 			}
-		});
-
-		let dummy = File {
-			url:       Default::default(),
-			meta:      items[0].meta.clone(),
-			length:    0,
-			link_to:   None,
-			is_link:   false,
-			is_hidden: false,
+
+			let ord = if self.reverse {
+				self.natord(&entities[b].0, &entities[a].0)
+			} else {
+				self.natord(&entities[a].0, &entities[b].0)
+			};
+			ord.then_with(|| self.tiebreak(entities[a].1, entities[b].1, sizes, children))
 		};
+		if self.stable { indices.sort_by(cmp) } else { indices.sort_unstable_by(cmp) }
+
+		drop(entities);
+		Self::permute(items, indices)
+	}
+
+	// Moves `items[indices[i]]` to `items[i]`, following each cycle of the permutation
+	fn permute(items: &mut [File], mut indices: Vec<usize>) -> SortChange {
+		if indices.iter().enumerate().all(|(i, &j)| i == j) {
+			return SortChange::Unchanged;
+		}
 
-		let mut new = Vec::with_capacity(indices.len());
-		for i in indices {
-			new.push(mem::replace(&mut items[i], dummy.clone()));
+		for i in 0..indices.len() {
+			let mut j = i;
+			while indices[j] != i {
+				let k = indices[j];
+				items.swap(j, k);
+				indices[j] = j;
+				j = k;
+			}
+			indices[j] = j;
 		}
-		*items = new;
+		SortChange::Reordered
 	}
 
 	#[inline]
@@ -99,8 +280,1043 @@ impl FilesSorter {
 		}
 	}
 
+	fn cmp_size(
+		&self,
+		a: &File,
+		b: &File,
+		sizes: &BTreeMap<Url, u64>,
+		children: &BTreeMap<Url, usize>,
+	) -> Ordering {
+		let promote = self.promote(a, b);
+		if promote != Ordering::Equal {
+			return promote;
+		}
+
+		// Directories whose size is still being calculated go last, ordered by name
+		match (self.size(a, sizes), self.size(b, sizes)) {
+			(Some(aa), Some(bb)) => {
+				let ord = if self.reverse { bb.cmp(&aa) } else { aa.cmp(&bb) };
+				ord.then_with(|| self.tiebreak(a, b, sizes, children))
+			}
+			(Some(_), None) => Ordering::Less,
+			(None, Some(_)) => Ordering::Greater,
+			(None, None) => self.natord(&a.url.to_string_lossy(), &b.url.to_string_lossy()),
+		}
+	}
+
+	// Whether the file at `idx` of the sorted `items` is still in order with the ones next to it,
+	// after its size changed, so they needn't be sorted again. Only known of `SortBy::Size`
+	pub(super) fn size_in_order(
+		&self,
+		items: &[File],
+		idx: usize,
+		sizes: &BTreeMap<Url, u64>,
+		children: &BTreeMap<Url, usize>,
+	) -> bool {
+		if self.by != SortBy::Size {
+			return false;
+		}
+
+		let cmp = |a: &File, b: &File| self.cmp_size(a, b, sizes, children) != Ordering::Greater;
+		(idx == 0 || cmp(&items[idx - 1], &items[idx]))
+			&& items.get(idx + 1).is_none_or(|next| cmp(&items[idx], next))
+	}
+
+	// The files whose time can't be read, e.g. the creation time on some filesystems, go last,
+	// in the order of the tiebreak, whether reversed or not, so they aren't scattered among the rest
+	fn sort_by_time(
+		&self,
+		items: &mut [File],
+		sizes: &BTreeMap<Url, u64>,
+		children: &BTreeMap<Url, usize>,
+		time: impl Fn(&File) -> Option<SystemTime>,
+	) -> SortChange {
+		self.sort_by(items, |a, b| {
+			let (aa, bb) = (time(a), time(b));
+			let ord = match (aa, bb) {
+				(Some(_), Some(_)) if self.reverse => Self::cmp_available(bb, aa),
+				_ => Self::cmp_available(aa, bb),
+			};
+			self.promote(a, b).then(ord).then_with(|| self.tiebreak(a, b, sizes, children))
+		})
+	}
+
+	// The ones unavailable go after the rest
+	#[inline]
+	fn cmp_available<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+		match (a, b) {
+			(Some(a), Some(b)) => a.cmp(&b),
+			(a, b) => b.is_some().cmp(&a.is_some()),
+		}
+	}
+
+	// The ones ranked in their order, and the rest after them naturally, as they all are until the
+	// command is done
+	fn sort_externally(
+		&self,
+		items: &mut [File],
+		sizes: &BTreeMap<Url, u64>,
+		children: &BTreeMap<Url, usize>,
+		ranks: &BTreeMap<Url, usize>,
+	) -> SortChange {
+		if ranks.is_empty() {
+			return self.sort_naturally(items, sizes, children);
+		}
+
+		let rank = |f: &File| ranks.get(&f.url).copied().unwrap_or(usize::MAX);
+		Self::sort_indices(items, true, |a, b| {
+			self.promote(a, b).then_with(|| rank(a).cmp(&rank(b))).then_with(|| {
+				self.compare(SortBy::Natural, a, b, sizes, children)
+			})
+		})
+	}
+
+	#[inline]
+	fn sort_by<F>(&self, items: &mut [File], f: F) -> SortChange
+	where
+		F: FnMut(&File, &File) -> Ordering,
+	{
+		Self::sort_indices(items, self.stable, f)
+	}
+
+	fn sort_indices<F>(items: &mut [File], stable: bool, mut f: F) -> SortChange
+	where
+		F: FnMut(&File, &File) -> Ordering,
+	{
+		let mut indices: Vec<_> = (0..items.len()).collect();
+		let cmp = |&a: &usize, &b: &usize| f(&items[a], &items[b]);
+		if stable { indices.sort_by(cmp) } else { indices.sort_unstable_by(cmp) }
+		Self::permute(items, indices)
+	}
+
+	fn compare(
+		&self,
+		by: SortBy,
+		a: &File,
+		b: &File,
+		sizes: &BTreeMap<Url, u64>,
+		children: &BTreeMap<Url, usize>,
+	) -> Ordering {
+		match by {
+			SortBy::Alphabetical => {
+				alphabetical_cmp(a.url.as_os_str(), b.url.as_os_str(), self.sensitive)
+			}
+			SortBy::Created => Self::cmp_available(a.meta.created(), b.meta.created()),
+			SortBy::Modified => Self::cmp_available(a.meta.modified(), b.meta.modified()),
+			SortBy::Accessed => Self::cmp_available(a.meta.accessed(), b.meta.accessed()),
+			SortBy::Extension => self
+				.extension(a)
+				.cmp(&self.extension(b))
+				.then_with(|| self.natord(&a.url.to_string_lossy(), &b.url.to_string_lossy())),
+			SortBy::Natural | SortBy::External => {
+				self.natord(&a.url.to_string_lossy(), &b.url.to_string_lossy())
+			}
+			SortBy::Size => Self::cmp_available(self.size(a, sizes), self.size(b, sizes)),
+			SortBy::Children => {
+				let aa = if a.is_dir() { children.get(a.url()).copied() } else { None };
+				let bb = if b.is_dir() { children.get(b.url()).copied() } else { None };
+				aa.unwrap_or(0)
+					.cmp(&bb.unwrap_or(0))
+					.then_with(|| self.natord(&a.url.to_string_lossy(), &b.url.to_string_lossy()))
+			}
+			SortBy::Random => self.shuffled(a).cmp(&self.shuffled(b)),
+		}
+	}
+
+	#[inline]
+	fn tiebreak(
+		&self,
+		a: &File,
+		b: &File,
+		sizes: &BTreeMap<Url, u64>,
+		children: &BTreeMap<Url, usize>,
+	) -> Ordering {
+		if self.tiebreak == self.by {
+			return Ordering::Equal;
+		}
+		self.compare(self.tiebreak, a, b, sizes, children)
+	}
+
+	#[inline]
+	fn size(&self, file: &File, sizes: &BTreeMap<Url, u64>) -> Option<u64> {
+		if !file.is_dir() {
+			return Some(file.length);
+		}
+		match sizes.get(file.url()) {
+			Some(&size) => Some(size),
+			None if self.size_recursive => None,
+			None => Some(file.length),
+		}
+	}
+
+	#[inline]
+	fn shuffled(&self, file: &File) -> u64 {
+		let mut h = DefaultHasher::new();
+		self.seed.hash(&mut h);
+		file.url.hash(&mut h);
+		h.finish()
+	}
+
+	#[inline]
+	fn natord(&self, a: &str, b: &str) -> Ordering {
+		let digits = Digits { leading_zeros: self.leading_zeros, thousands: self.thousands };
+		natord_str(a, b, self.sensitive, self.split_ext, digits)
+	}
+
+	#[inline]
+	fn extension(&self, file: &File) -> String {
+		if file.is_dir() {
+			return String::new();
+		}
+
+		let ext = file.url.extension().map(|e| e.to_string_lossy()).unwrap_or_default();
+		if self.sensitive { ext.into_owned() } else { ext.to_lowercase() }
+	}
+
 	#[inline]
 	fn promote(&self, a: &File, b: &File) -> Ordering {
-		if self.dir_first { b.is_dir().cmp(&a.is_dir()) } else { Ordering::Equal }
+		let hidden = match self.hidden_last {
+			Some(true) => a.is_hidden.cmp(&b.is_hidden),
+			Some(false) => b.is_hidden.cmp(&a.is_hidden),
+			None => Ordering::Equal,
+		};
+		hidden.then_with(|| {
+			if let Some(order) = self.kind_order {
+				let rank = |f: &File| order.iter().position(|&k| k == f.kind());
+				rank(a).cmp(&rank(b))
+			} else if self.dir_first {
+				b.is_dir().cmp(&a.is_dir())
+			} else {
+				Ordering::Equal
+			}
+		})
 	}
 }
+
+/// Builds a [`FilesSorter`] field by field, for the embedders not going through the config.
+#[derive(Clone, Copy)]
+pub struct FilesSorterBuilder(FilesSorter);
+
+impl Default for FilesSorterBuilder {
+	fn default() -> Self {
+		Self(FilesSorter {
+			by:             SortBy::Alphabetical,
+			sensitive:      false,
+			reverse:        false,
+			dir_first:      true,
+			hidden_last:    None,
+			kind_order:     None,
+			size_recursive: false,
+			stable:         false,
+			collation:      Collation::Codepoint,
+			tiebreak:       SortBy::Natural,
+			split_ext:      false,
+			leading_zeros:  false,
+			thousands:      false,
+			seed:           FilesSorter::roll(),
+		})
+	}
+}
+
+impl FilesSorterBuilder {
+	/// The order to sort by.
+	#[inline]
+	pub fn by(mut self, by: SortBy) -> Self {
+		self.0.by = by;
+		self
+	}
+
+	/// Whether the names are compared case-sensitively.
+	#[inline]
+	pub fn sensitive(mut self, sensitive: bool) -> Self {
+		self.0.sensitive = sensitive;
+		self
+	}
+
+	/// Whether the order is reversed, the directories staying first.
+	#[inline]
+	pub fn reverse(mut self, reverse: bool) -> Self {
+		self.0.reverse = reverse;
+		self
+	}
+
+	/// Whether the directories go before the files.
+	#[inline]
+	pub fn dir_first(mut self, dir_first: bool) -> Self {
+		self.0.dir_first = dir_first;
+		self
+	}
+
+	/// Whether the hidden files go last, or first, or among the others for `None`.
+	#[inline]
+	pub fn hidden_last(mut self, hidden_last: Option<bool>) -> Self {
+		self.0.hidden_last = hidden_last;
+		self
+	}
+
+	/// The kinds of files in the order they go, the missing ones after them, in place of
+	/// [`Self::dir_first`], or none if empty.
+	#[inline]
+	pub fn kind_order(mut self, kinds: &[FileKind]) -> Self {
+		self.0.kind_order = FilesSorter::normalize_kinds(kinds);
+		self
+	}
+
+	/// Whether the directories missing from the sizes go last for [`SortBy::Size`], rather than
+	/// being sorted by their own length.
+	#[inline]
+	pub fn size_recursive(mut self, size_recursive: bool) -> Self {
+		self.0.size_recursive = size_recursive;
+		self
+	}
+
+	/// Whether the files compared equal keep their order.
+	#[inline]
+	pub fn stable(mut self, stable: bool) -> Self {
+		self.0.stable = stable;
+		self
+	}
+
+	/// How the names are compared for [`SortBy::Alphabetical`] and [`SortBy::Natural`].
+	#[inline]
+	pub fn collation(mut self, collation: Collation) -> Self {
+		self.0.collation = collation;
+		self
+	}
+
+	/// The order the files compared equal are sorted by then.
+	#[inline]
+	pub fn tiebreak(mut self, tiebreak: SortBy) -> Self {
+		self.0.tiebreak = tiebreak;
+		self
+	}
+
+	/// Whether the names are compared by their stems first, then their extensions, in the natural
+	/// order.
+	#[inline]
+	pub fn split_ext(mut self, split_ext: bool) -> Self {
+		self.0.split_ext = split_ext;
+		self
+	}
+
+	/// Whether the leading zeros of a number only break the tie of the same number in the natural
+	/// order, e.g. `7` goes before `007`, and both before `8`, rather than the number with them
+	/// being compared as a fraction, digit by digit, as `007` before `7`.
+	#[inline]
+	pub fn leading_zeros(mut self, leading_zeros: bool) -> Self {
+		self.0.leading_zeros = leading_zeros;
+		self
+	}
+
+	/// Whether the thousands separators in a number are ignored in the natural order, e.g.
+	/// `1,000` is compared as `1000`, see [`natural_cmp`] for the ones known.
+	#[inline]
+	pub fn thousands(mut self, thousands: bool) -> Self {
+		self.0.thousands = thousands;
+		self
+	}
+
+	/// The seed of [`SortBy::Random`], the same one giving the same order.
+	#[inline]
+	pub fn seed(mut self, seed: u64) -> Self {
+		self.0.seed = seed;
+		self
+	}
+
+	#[inline]
+	pub fn build(self) -> FilesSorter { self.0 }
+
+	/// Builds the sorter and sorts `items` with it, see [`FilesSorter::sort`].
+	#[inline]
+	pub fn sort(self, items: &mut [File], sizes: &BTreeMap<Url, u64>) -> SortChange {
+		self.0.sort(items, sizes)
+	}
+}
+
+/// Compares two names, or paths, in the order of [`SortBy::Alphabetical`], ignoring the case unless
+/// `sensitive`, where a path is compared component by component.
+///
+/// The same as [`FilesSorter`] compares the names by, but on their own, so neither the directories
+/// go first nor the hidden files last, as it's only known of the files. Call
+/// [`Ordering::reverse`] on the result for the reversed order.
+pub fn alphabetical_cmp(a: &OsStr, b: &OsStr, sensitive: bool) -> Ordering {
+	if sensitive {
+		Path::new(a).cmp(Path::new(b))
+	} else {
+		a.to_string_lossy().to_lowercase().cmp(&b.to_string_lossy().to_lowercase())
+	}
+}
+
+/// Compares two names, or paths, in the order of [`SortBy::Natural`], where the digits in them
+/// are compared as numbers, e.g. `file2` goes before `file10`, ignoring the case unless
+/// `sensitive`, and by the stems first, then the extensions, if `split_ext`.
+///
+/// A number with leading zeros is compared as a fraction, digit by digit, e.g. `007` goes before
+/// `7`, and a thousands separator in it, any of `,`, `'`, `_`, and the no-break spaces, splits it
+/// in two, see [`FilesSorterBuilder::leading_zeros`] and [`FilesSorterBuilder::thousands`] for
+/// the other way. The `.` isn't taken as one, for the versions not to be joined up.
+///
+/// Like [`alphabetical_cmp`], there's no promotion of the directories or the hidden files.
+///
+/// ```
+/// use std::ffi::OsStr;
+///
+/// use core::files::natural_cmp;
+///
+/// let mut names = ["v1.10", "V1.9", "a.txt", "a-1.txt"].map(OsStr::new);
+/// names.sort_by(|a, b| natural_cmp(a, b, false, true));
+/// assert_eq!(names, ["a.txt", "a-1.txt", "V1.9", "v1.10"]);
+/// ```
+#[inline]
+pub fn natural_cmp(a: &OsStr, b: &OsStr, sensitive: bool, split_ext: bool) -> Ordering {
+	natord_str(&a.to_string_lossy(), &b.to_string_lossy(), sensitive, split_ext, Digits::default())
+}
+
+// How the runs of digits are taken in the natural order, apart from the numbers they are
+#[derive(Clone, Copy, Default)]
+struct Digits {
+	// The leading zeros only break the tie of the same number, rather than it being a fraction
+	leading_zeros: bool,
+	// The thousands separators in a number are ignored, e.g. `1,000` is `1000`
+	thousands:     bool,
+}
+
+impl Digits {
+	const SEPARATORS: [char; 5] = [',', '\'', '_', '\u{a0}', '\u{202f}'];
+
+	// The name with the separators in the numbers and the leading zeros of them taken out, as
+	// asked for, or as it is if there are none
+	fn normalize(self, s: &str) -> Cow<'_, str> {
+		if !self.leading_zeros && !self.thousands {
+			return Cow::Borrowed(s);
+		}
+
+		let chars: Vec<_> = s.chars().collect();
+		let mut out = String::with_capacity(s.len());
+		for (i, &c) in chars.iter().enumerate() {
+			let after_digit = out.ends_with(|c: char| c.is_ascii_digit());
+			let next_digit = chars.get(i + 1).is_some_and(char::is_ascii_digit);
+			let separator = Self::SEPARATORS.contains(&c) && grouped(&chars[i + 1..]);
+			if self.thousands && after_digit && separator {
+				continue;
+			} else if self.leading_zeros && c == '0' && !after_digit && next_digit {
+				continue;
+			}
+			out.push(c);
+		}
+
+		if out.len() == s.len() { Cow::Borrowed(s) } else { Cow::Owned(out) }
+	}
+
+	// The tie of the same numbers broken by the leading zeros, the fewer of them first, number by
+	// number, e.g. `7` goes before `007`
+	fn zeros(self, a: &str, b: &str) -> Ordering {
+		if !self.leading_zeros {
+			return Ordering::Equal;
+		}
+
+		let zeros = |s: &str| {
+			let (mut runs, mut run, mut leading) = (vec![], false, false);
+			for c in s.chars() {
+				if !c.is_ascii_digit() {
+					run = false;
+					continue;
+				} else if !run {
+					(run, leading) = (true, true);
+					runs.push(0);
+				}
+
+				leading &= c == '0';
+				if leading {
+					*runs.last_mut().unwrap() += 1;
+				}
+			}
+			runs
+		};
+		zeros(a).cmp(&zeros(b))
+	}
+}
+
+// Whether it's three digits, and no more, that follow a thousands separator
+#[inline]
+fn grouped(rest: &[char]) -> bool {
+	rest.len() >= 3
+		&& rest[..3].iter().all(char::is_ascii_digit)
+		&& !rest.get(3).is_some_and(char::is_ascii_digit)
+}
+
+// With `split_ext`, the stems are compared first, then the extensions, so the dot before an
+// extension is never taken as a part of the name, e.g. `a.txt` goes before `a-1.txt`
+fn natord_str(a: &str, b: &str, sensitive: bool, split_ext: bool, digits: Digits) -> Ordering {
+	let cmp = |a: &str, b: &str| {
+		let (x, y) = (digits.normalize(a), digits.normalize(b));
+		let ord = if sensitive { natord::compare(&x, &y) } else { natord::compare_ignore_case(&x, &y) };
+		ord.then_with(|| digits.zeros(a, b))
+	};
+	if !split_ext {
+		return cmp(a, b);
+	}
+
+	let ((a, aa), (b, bb)) = (split_ext_of(a), split_ext_of(b));
+	cmp(a, b).then_with(|| cmp(aa, bb))
+}
+
+// The stem and the extension of the name at the end of `path`, where the dot of a hidden file,
+// e.g. `.bashrc`, doesn't start an extension
+fn split_ext_of(path: &str) -> (&str, &str) {
+	let name = path.rfind('/').map_or(0, |i| i + 1);
+	match path[name..].rfind('.') {
+		Some(i) if i > 0 => (&path[..name + i], &path[name + i + 1..]),
+		_ => (path, ""),
+	}
+}
+
+#[cfg(test)]
+pub(super) fn test_files(names: &[(&str, u64)]) -> Vec<File> {
+	let file = std::fs::metadata(std::env::current_exe().unwrap()).unwrap().into();
+	let dir = std::fs::metadata(std::env::temp_dir()).unwrap().into();
+	names
+		.iter()
+		.map(|&(name, length)| {
+			let (name, is_dir) = name.strip_suffix('/').map_or((name, false), |n| (n, true));
+			let (name, is_link) = name.strip_suffix('@').map_or((name, false), |n| (n, true));
+			File {
+				url:        Url::from(name),
+				meta:       if is_dir { dir } else { file },
+				length,
+				link_to:    None,
+				is_link,
+				is_hidden:  name.starts_with('.'),
+				is_ignored: false,
+			}
+		})
+		.collect()
+}
+
+#[cfg(test)]
+pub(super) fn test_names(items: &[File]) -> Vec<&str> {
+	items.iter().map(|f| f.url.to_str().unwrap()).collect()
+}
+
+#[test]
+fn test_stable_sort() {
+	let sorter = FilesSorter::builder()
+		.by(SortBy::Size)
+		.sensitive(true)
+		.dir_first(false)
+		.stable(true)
+		.tiebreak(SortBy::Size)
+		.build();
+
+	let mut items = test_files(&[("d", 1), ("b", 1), ("e", 0), ("a", 1), ("c", 1)]);
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), ["e", "d", "b", "a", "c"]);
+}
+
+#[test]
+fn test_tiebreak() {
+	let mut sorter = FilesSorter::builder()
+		.by(SortBy::Size)
+		.dir_first(false)
+		.tiebreak(SortBy::Natural)
+		.build();
+
+	// The files of the same size by their names, which stay in that order once reversed
+	let mut items = test_files(&[("f10", 1), ("f2", 1), ("a", 2), ("f1", 1)]);
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), ["f1", "f2", "f10", "a"]);
+
+	sorter.reverse = true;
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), ["a", "f1", "f2", "f10"]);
+}
+
+#[test]
+fn test_insensitive_unicode_sort() {
+	let sorter = FilesSorter::builder()
+		.dir_first(false)
+		.stable(true)
+		.tiebreak(SortBy::Alphabetical)
+		.build();
+
+	let mut items =
+		test_files(&[("Über", 0), ("zebra", 0), ("äpfel", 0), ("Apfel", 0), ("Ärger", 0)]);
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), ["Apfel", "zebra", "äpfel", "Ärger", "Über"]);
+
+	let mut items = test_files(&[("ırmak", 0), ("İstanbul", 0), ("izmir", 0), ("Ilgaz", 0)]);
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), ["Ilgaz", "izmir", "İstanbul", "ırmak"]);
+}
+
+#[test]
+fn test_hidden_promote() {
+	let mut sorter = FilesSorter::builder()
+		.sensitive(true)
+		.hidden_last(Some(true))
+		.tiebreak(SortBy::Alphabetical)
+		.build();
+
+	let mut items = test_files(&[(".b", 0), ("d", 0), (".c/", 0), ("a/", 0), (".f", 0), ("e/", 0)]);
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), ["a", "e", "d", ".c", ".b", ".f"]);
+
+	sorter.hidden_last = Some(false);
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), [".c", ".b", ".f", "a", "e", "d"]);
+
+	sorter.hidden_last = None;
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), [".c", "a", "e", ".b", ".f", "d"]);
+}
+
+#[test]
+fn test_natural_sort() {
+	let mut sorter = FilesSorter::builder().by(SortBy::Natural).build();
+
+	let names = [
+		("file10.txt", 0),
+		("File2.txt", 0),
+		("b/", 0),
+		("file1.txt", 0),
+		("a10/", 0),
+		("img_003.png", 0),
+		("a2/", 0),
+		("img_1.png", 0),
+		("z", 0),
+		("file20.txt", 0),
+	];
+
+	let mut items = test_files(&names);
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), [
+		"a2",
+		"a10",
+		"b",
+		"file1.txt",
+		"File2.txt",
+		"file10.txt",
+		"file20.txt",
+		"img_003.png",
+		"img_1.png",
+		"z"
+	]);
+
+	sorter.reverse = true;
+	sorter.dir_first = false;
+	let mut items = test_files(&names);
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), [
+		"z",
+		"img_1.png",
+		"img_003.png",
+		"file20.txt",
+		"file10.txt",
+		"File2.txt",
+		"file1.txt",
+		"b",
+		"a10",
+		"a2"
+	]);
+}
+
+// The allocations made on each thread, for a test to count its own ones, whatever the others do
+#[cfg(test)]
+struct Counting;
+
+#[cfg(test)]
+thread_local! {
+	static ALLOCS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+#[cfg(test)]
+unsafe impl std::alloc::GlobalAlloc for Counting {
+	unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+		ALLOCS.try_with(|n| n.set(n.get() + 1)).ok();
+		unsafe { std::alloc::System.alloc(layout) }
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+		unsafe { std::alloc::System.dealloc(ptr, layout) }
+	}
+}
+
+#[cfg(test)]
+#[global_allocator]
+static COUNTING: Counting = Counting;
+
+#[test]
+#[ignore = "benchmark, run with `cargo test -p core --release -- --ignored --nocapture`"]
+fn bench_insensitive_sort() {
+	use std::time::Instant;
+
+	let allocs = || ALLOCS.with(|n| n.get());
+
+	let sorter = FilesSorter::builder()
+		.dir_first(false)
+		.stable(true)
+		.tiebreak(SortBy::Alphabetical)
+		.build();
+
+	let names: Vec<_> = (0..50_000).map(|i| (format!("Fïle_{}", (i * 7919) % 50_000), 0)).collect();
+	let names: Vec<_> = names.iter().map(|(n, l)| (n.as_str(), *l)).collect();
+
+	// Lowercased on each comparison, against once for each of the files
+	let mut expected = test_files(&names);
+	let (now, before) = (Instant::now(), allocs());
+	expected.sort_by(|a, b| {
+		a.url.to_string_lossy().to_lowercase().cmp(&b.url.to_string_lossy().to_lowercase())
+	});
+	let per_comparison = allocs() - before;
+	println!("per-comparison: {:?}, {per_comparison} allocations", now.elapsed());
+
+	let mut items = test_files(&names);
+	let (now, before) = (Instant::now(), allocs());
+	sorter.sort(&mut items, &Default::default());
+	let cached = allocs() - before;
+	println!("cached keys: {:?}, {cached} allocations", now.elapsed());
+
+	assert_eq!(test_names(&items), test_names(&expected));
+
+	// A few for each of the files, against a few for each of the about `n log n` comparisons
+	let n = names.len();
+	assert!(cached < 4 * n, "{cached} allocations for {n} files");
+	assert!(per_comparison > 10 * n, "{per_comparison} allocations for {n} files");
+}
+
+#[test]
+fn test_split_ext_sort() {
+	let mut sorter = FilesSorter::builder().by(SortBy::Natural).build();
+	let names = [
+		("app-1.10.0.tar", 0),
+		("a.txt", 0),
+		("app-1.9.tar", 0),
+		("a-1.txt", 0),
+		("a.10", 0),
+		(".bashrc", 0),
+		("app-1.9.2.tar", 0),
+		("a.md", 0),
+		("a", 0),
+		("a.2", 0),
+	];
+
+	// The dot before an extension is compared as any other char without it
+	let mut items = test_files(&names);
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(&test_names(&items)[..3], [".bashrc", "a", "a-1.txt"]);
+
+	sorter.split_ext = true;
+	let mut items = test_files(&names);
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), [
+		".bashrc",
+		"a",
+		"a.2",
+		"a.10",
+		"a.md",
+		"a.txt",
+		"a-1.txt",
+		"app-1.9.tar",
+		"app-1.9.2.tar",
+		"app-1.10.0.tar"
+	]);
+
+	assert_eq!(split_ext_of("dir.d/file"), ("dir.d/file", ""));
+	assert_eq!(split_ext_of("dir/.hidden.toml"), ("dir/.hidden", "toml"));
+}
+
+#[test]
+fn test_extension_sort() {
+	let mut sorter = FilesSorter::builder().by(SortBy::Extension).dir_first(false).build();
+	let names = [
+		("a10.txt", 0),
+		("z", 0),
+		("b.md", 0),
+		(".bashrc", 0),
+		("A2.TXT", 0),
+		("d.txt/", 0),
+		("c.tar.gz", 0),
+		(".x.md", 0),
+	];
+
+	// A dotfile and a directory have no extension, as a name without one, and the names of the
+	// same extension are sorted naturally
+	let mut items = test_files(&names);
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), [
+		".bashrc",
+		"d.txt",
+		"z",
+		"c.tar.gz",
+		".x.md",
+		"b.md",
+		"A2.TXT",
+		"a10.txt"
+	]);
+
+	sorter.sensitive = true;
+	let mut items = test_files(&names);
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), [
+		".bashrc",
+		"d.txt",
+		"z",
+		"A2.TXT",
+		"c.tar.gz",
+		".x.md",
+		"b.md",
+		"a10.txt"
+	]);
+}
+
+#[test]
+fn test_natural_digits() {
+	let mut sorter = FilesSorter::builder().by(SortBy::Natural).build();
+	let names = [("img_8", 0), ("img_007", 0), ("img_7", 0), ("img_07", 0)];
+
+	// The numbers with leading zeros are fractions, digit by digit, by default
+	let mut items = test_files(&names);
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), ["img_007", "img_07", "img_7", "img_8"]);
+
+	sorter.leading_zeros = true;
+	let mut items = test_files(&names);
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), ["img_7", "img_07", "img_007", "img_8"]);
+
+	let names = [("999", 0), ("1,000", 0), ("2", 0), ("1,5", 0)];
+	let mut items = test_files(&names);
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), ["1,000", "1,5", "2", "999"]);
+
+	sorter.thousands = true;
+	let mut items = test_files(&names);
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), ["1,5", "2", "999", "1,000"]);
+
+	let digits = Digits { leading_zeros: true, thousands: true };
+	assert_eq!(digits.normalize("v007 1'000'000 0 00 1,0000 a,000"), "v7 1000000 0 0 1,0 a,0");
+	assert_eq!(digits.zeros("7-07", "7-007"), Ordering::Less);
+	assert!(matches!(Digits::default().normalize("1,000 007"), Cow::Borrowed("1,000 007")));
+}
+
+#[test]
+fn test_name_cmp() {
+	let (a, b) = (OsStr::new("B/x"), OsStr::new("a/y"));
+	assert_eq!(alphabetical_cmp(a, b, true), Ordering::Less);
+	assert_eq!(alphabetical_cmp(a, b, false), Ordering::Greater);
+
+	// A path is compared by its components, where the separator goes before any other char
+	assert_eq!(alphabetical_cmp(OsStr::new("a/b"), OsStr::new("a-b"), true), Ordering::Less);
+
+	let (a, b) = (OsStr::new("File2"), OsStr::new("file10"));
+	assert_eq!(natural_cmp(a, b, false, false), Ordering::Less);
+	assert_eq!(natural_cmp(a, b, true, false), Ordering::Less);
+
+	let (a, b) = (OsStr::new("a-1.txt"), OsStr::new("a.txt"));
+	assert_eq!(natural_cmp(a, b, false, false), Ordering::Less);
+	assert_eq!(natural_cmp(a, b, false, true), Ordering::Greater);
+}
+
+#[test]
+fn test_sort_change() {
+	let mut sorter = FilesSorter::builder().by(SortBy::Size).sensitive(true).dir_first(false).build();
+
+	let (sizes, children, ranks) = (Default::default(), Default::default(), Default::default());
+	let mut items = test_files(&[("a", 3), ("b", 1), ("c", 2)]);
+	assert_eq!(sorter.sort_with(&mut items, &sizes, &children, &ranks), SortChange::Reordered);
+	assert_eq!(sorter.sort_with(&mut items, &sizes, &children, &ranks), SortChange::Unchanged);
+
+	sorter.reverse = true;
+	assert_eq!(sorter.sort_with(&mut items, &sizes, &children, &ranks), SortChange::Reordered);
+	assert_eq!(test_names(&items), ["a", "c", "b"]);
+	assert_eq!(sorter.sort_with(&mut [], &sizes, &children, &ranks), SortChange::Unchanged);
+}
+
+#[test]
+fn test_sort_randomly() {
+	let mut sorter = FilesSorter::builder().by(SortBy::Random).dir_first(true).seed(1).build();
+	let names = ["a", "b/", "c", "d", "e/", "f", "g", "h", "i/", "j", "k", "l"].map(|n| (n, 0));
+	let shuffled = |sorter: &FilesSorter| {
+		let mut items = test_files(&names);
+		sorter.sort(&mut items, &Default::default());
+		test_names(&items).into_iter().map(ToOwned::to_owned).collect::<Vec<_>>()
+	};
+
+	// The same seed gives the same order, with the directories still first
+	let order = shuffled(&sorter);
+	assert_eq!(shuffled(&sorter), order);
+	assert!(order[..3].iter().all(|n| ["b", "e", "i"].contains(&n.as_str())));
+	assert_ne!(order, test_names(&test_files(&names)));
+
+	// And another roll reshuffles them, of the 3! × 9! orders
+	sorter.seed = FilesSorter::roll();
+	assert_ne!(shuffled(&sorter), order);
+}
+
+#[test]
+fn test_kind_order() {
+	let mut sorter = FilesSorter::builder().sensitive(true).tiebreak(SortBy::Alphabetical).build();
+
+	let names = [("f", 0), ("lf@", 0), ("d/", 0), ("ld@/", 0), ("e", 0), ("c/", 0)];
+	let (sizes, children, ranks) = (Default::default(), Default::default(), Default::default());
+
+	let mut items = test_files(&names);
+	sorter.sort_with(&mut items, &sizes, &children, &ranks);
+	assert_eq!(test_names(&items), ["c", "d", "ld", "e", "f", "lf"]);
+
+	sorter.kind_order = FilesSorter::normalize_kinds(&[FileKind::Dir, FileKind::File]);
+	assert_eq!(
+		sorter.kind_order,
+		Some([FileKind::Dir, FileKind::File, FileKind::LinkDir, FileKind::LinkFile, FileKind::Other])
+	);
+	sorter.sort_with(&mut items, &sizes, &children, &ranks);
+	assert_eq!(test_names(&items), ["c", "d", "e", "f", "ld", "lf"]);
+}
+
+#[test]
+fn test_size_recursive() {
+	let mut sorter = FilesSorter::builder()
+		.by(SortBy::Size)
+		.sensitive(true)
+		.dir_first(false)
+		.size_recursive(true)
+		.build();
+
+	let names = [("f", 20), ("pending2/", 4096), ("d/", 4096), ("pending1/", 4096), ("g", 5)];
+	let sizes = BTreeMap::from_iter([(Url::from("d"), 10)]);
+	let (children, ranks) = (Default::default(), Default::default());
+
+	// Directories with a known size are mixed with files,
+	// while the ones still being calculated are placed last by name
+	let mut items = test_files(&names);
+	sorter.sort_with(&mut items, &sizes, &children, &ranks);
+	assert_eq!(test_names(&items), ["g", "d", "f", "pending1", "pending2"]);
+
+	// It stays at the bottom in reverse order as well
+	sorter.reverse = true;
+	sorter.sort_with(&mut items, &sizes, &children, &ranks);
+	assert_eq!(test_names(&items), ["f", "d", "g", "pending1", "pending2"]);
+
+	// And only at the bottom of the directory tier with `dir_first`
+	sorter.dir_first = true;
+	sorter.sort_with(&mut items, &sizes, &children, &ranks);
+	assert_eq!(test_names(&items), ["d", "pending1", "pending2", "f", "g"]);
+
+	// Without `size_recursive`, pending directories use the size of their own entry
+	sorter.size_recursive = false;
+	sorter.sort_with(&mut items, &sizes, &children, &ranks);
+	assert_eq!(test_names(&items), ["pending1", "pending2", "d", "f", "g"]);
+}
+
+#[test]
+fn test_sort_by_time() {
+	let mut sorter = FilesSorter::builder().by(SortBy::Modified).sensitive(true).build();
+
+	// The length stands for the time, where a file of `0` has none
+	let names = [("x", 0), ("b", 3), ("w", 0), ("a", 1), ("d/", 0), ("c", 2), ("e/", 5)];
+	let time = |f: &File| {
+		(f.length > 0).then(|| SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(f.length))
+	};
+	let (sizes, children) = (Default::default(), Default::default());
+
+	let mut items = test_files(&names);
+	sorter.sort_by_time(&mut items, &sizes, &children, time);
+	assert_eq!(test_names(&items), ["e", "d", "a", "c", "b", "w", "x"]);
+
+	// Still last, and in the same order, when reversed
+	sorter.reverse = true;
+	sorter.sort_by_time(&mut items, &sizes, &children, time);
+	assert_eq!(test_names(&items), ["e", "d", "b", "c", "a", "w", "x"]);
+
+	assert_eq!(FilesSorter::cmp_available(Some(1), None), Ordering::Less);
+	assert_eq!(FilesSorter::cmp_available::<u8>(None, None), Ordering::Equal);
+}
+
+#[test]
+fn test_sort_by_accessed() {
+	let mut sorter = FilesSorter::builder().by(SortBy::Accessed).sensitive(true).build();
+
+	// As above, the length stands for the time it's accessed, where it's modified the other way
+	// around, for the two not to be taken for one another
+	let names = [("x", 0), ("b", 3), ("w", 0), ("a", 1), ("d/", 0), ("c", 2), ("e/", 5)];
+	let mut items = test_files(&names);
+	for f in &mut items {
+		let mode = if f.is_dir() { 0o040755 } else { 0o100644 };
+		let times = (f.length > 0).then(|| (f.length as u32, 10 - f.length as u32));
+		f.meta = super::Meta::remote(mode, f.length, None, times);
+	}
+
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), ["e", "d", "a", "c", "b", "w", "x"]);
+
+	sorter.reverse = true;
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), ["e", "d", "b", "c", "a", "w", "x"]);
+
+	// Still reversed, by the times it's modified
+	sorter.by = SortBy::Modified;
+	sorter.sort(&mut items, &Default::default());
+	assert_eq!(test_names(&items), ["e", "d", "a", "c", "b", "w", "x"]);
+}
+
+#[test]
+fn test_size_in_order() {
+	let sorter = FilesSorter::builder()
+		.by(SortBy::Size)
+		.sensitive(true)
+		.dir_first(false)
+		.size_recursive(true)
+		.build();
+
+	let mut sizes = BTreeMap::from_iter([(Url::from("d"), 10)]);
+	let (children, ranks) = (Default::default(), Default::default());
+	let mut items = test_files(&[("a", 5), ("d/", 0), ("b", 20), ("e/", 0)]);
+	sorter.sort_with(&mut items, &sizes, &children, &ranks);
+	assert_eq!(test_names(&items), ["a", "d", "b", "e"]);
+	assert!((0..4).all(|i| sorter.size_in_order(&items, i, &sizes, &children)));
+
+	// Still between the two, or not any longer
+	sizes.insert(Url::from("d"), 15);
+	assert!(sorter.size_in_order(&items, 1, &sizes, &children));
+	sizes.insert(Url::from("d"), 30);
+	assert!(!sorter.size_in_order(&items, 1, &sizes, &children));
+	sizes.insert(Url::from("e"), 1);
+	assert!(!sorter.size_in_order(&items, 3, &sizes, &children));
+
+	let sorter = FilesSorter { by: SortBy::Natural, ..sorter };
+	assert!(!sorter.size_in_order(&items, 0, &sizes, &children));
+}
+
+#[test]
+fn test_sort_by_children() {
+	let mut sorter = FilesSorter::builder().by(SortBy::Children).build();
+	let names = [("g", 0), ("a/", 0), ("d2/", 0), ("c/", 0), ("f", 0), ("d10/", 0)];
+	let children = BTreeMap::from_iter([(Url::from("a"), 3), (Url::from("c"), 1)]);
+	let (sizes, ranks) = (Default::default(), Default::default());
+
+	// The directories not counted yet are taken for empty ones, as the files are, by their names
+	let mut items = test_files(&names);
+	sorter.sort_with(&mut items, &sizes, &children, &ranks);
+	assert_eq!(test_names(&items), ["d2", "d10", "c", "a", "f", "g"]);
+
+	sorter.reverse = true;
+	sorter.sort_with(&mut items, &sizes, &children, &ranks);
+	assert_eq!(test_names(&items), ["a", "c", "d10", "d2", "g", "f"]);
+}
+
+#[test]
+fn test_sort_externally() {
+	let sorter = FilesSorter::builder().by(SortBy::External).build();
+	let (sizes, children) = (Default::default(), Default::default());
+
+	// Naturally until the command is done
+	let mut items = test_files(&[("f10", 0), ("d/", 0), ("f2", 0), ("e", 0)]);
+	sorter.sort_with(&mut items, &sizes, &children, &BTreeMap::new());
+	assert_eq!(test_names(&items), ["d", "e", "f2", "f10"]);
+
+	// Then in the order it's printed, with the ones it leaves out after the rest
+	let ranks = BTreeMap::from_iter([(Url::from("f10"), 0), (Url::from("e"), 1)]);
+	sorter.sort_with(&mut items, &sizes, &children, &ranks);
+	assert_eq!(test_names(&items), ["d", "f10", "e", "f2"]);
+}
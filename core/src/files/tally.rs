@@ -0,0 +1,39 @@
+// How many files and directories are selected, and their total size, kept as they're selected
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SelectedTally {
+	pub files: usize,
+	pub dirs:  usize,
+	pub bytes: u64,
+}
+
+impl SelectedTally {
+	#[inline]
+	pub fn is_empty(&self) -> bool { self.files == 0 && self.dirs == 0 }
+
+	#[inline]
+	pub(super) fn add(&mut self, (is_dir, bytes): (bool, u64)) {
+		if is_dir { self.dirs += 1 } else { self.files += 1 }
+		self.bytes += bytes;
+	}
+
+	#[inline]
+	pub(super) fn sub(&mut self, (is_dir, bytes): (bool, u64)) {
+		if is_dir { self.dirs -= 1 } else { self.files -= 1 }
+		self.bytes -= bytes;
+	}
+}
+
+#[test]
+fn test_tally() {
+	let mut tally = SelectedTally::default();
+	tally.add((false, 10));
+	tally.add((true, 100));
+	tally.add((false, 5));
+	assert_eq!(tally, SelectedTally { files: 2, dirs: 1, bytes: 115 });
+
+	tally.sub((true, 100));
+	tally.sub((false, 10));
+	assert_eq!(tally, SelectedTally { files: 1, dirs: 0, bytes: 5 });
+	tally.sub((false, 5));
+	assert!(tally.is_empty());
+}
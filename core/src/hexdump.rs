@@ -0,0 +1,74 @@
+use std::fmt::Write;
+
+// How many bytes from the top of a file are looked at to tell if it's binary
+pub const HEX_SNIFF: usize = 8192;
+
+// A file is taken as binary if it has a NUL byte, or isn't valid UTF-8 in the bytes looked at,
+// except for a character cut at the end of them
+pub fn is_binary(head: &[u8]) -> bool {
+	if head.contains(&0) {
+		return true;
+	}
+	match std::str::from_utf8(head) {
+		Ok(_) => false,
+		Err(e) => e.error_len().is_some(),
+	}
+}
+
+// The most bytes per row that fit in `width` columns, in groups of 8, or 4 if even 8 doesn't fit
+pub fn hex_columns(width: usize) -> usize {
+	let row_width = |n: usize| 13 + 4 * n + (n - 1) / 8;
+	let mut n = (width.saturating_sub(13) / 4) / 8 * 8;
+	while n > 8 && row_width(n) > width {
+		n -= 8;
+	}
+	if n < 8 || row_width(n) > width { 4 } else { n }
+}
+
+// Dumps the bytes as rows of the offset, the bytes in hex, and those printable in ASCII,
+// where `offset` is the one of the first byte in the file
+pub fn hexdump(bytes: &[u8], offset: u64, columns: usize) -> String {
+	let mut s = String::new();
+	for (i, row) in bytes.chunks(columns).enumerate() {
+		write!(s, "{:08x}  ", offset + (i * columns) as u64).ok();
+		for j in 0..columns {
+			if j > 0 && j % 8 == 0 {
+				s.push(' ');
+			}
+			if let Some(b) = row.get(j) {
+				write!(s, "{b:02x} ").ok();
+			} else {
+				s.push_str("   ");
+			}
+		}
+
+		s.push_str(" |");
+		s.extend(row.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }));
+		s.push_str("|\n");
+	}
+	s
+}
+
+#[test]
+fn test_is_binary() {
+	assert!(!is_binary(b"fn main() {}\n"));
+	assert!(!is_binary("中文".as_bytes()));
+	assert!(!is_binary(&"中文".as_bytes()[..5]));
+	assert!(is_binary(b"\x7fELF\x02\x01\x01\x00"));
+	assert!(is_binary(b"\xff\xfe abc"));
+}
+
+#[test]
+fn test_hexdump() {
+	assert_eq!(hex_columns(78), 16);
+	assert_eq!(hex_columns(77), 8);
+	assert_eq!(hex_columns(150), 32);
+	assert_eq!(hex_columns(20), 4);
+
+	assert_eq!(
+		hexdump(b"\x7fELF\x02\x01\x01\x00hello, world", 16, 8),
+		"00000010  7f 45 4c 46 02 01 01 00  |.ELF....|\n\
+		 00000018  68 65 6c 6c 6f 2c 20 77  |hello, w|\n\
+		 00000020  6f 72 6c 64              |orld|\n"
+	);
+}
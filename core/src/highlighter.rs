@@ -1,24 +1,638 @@
-use std::{fs::File, io::BufReader, sync::OnceLock};
+use std::{fs::File, io::{self, BufRead, BufReader, Read, Seek, SeekFrom}, ops::Range, path::{Path, PathBuf}, sync::{mpsc, Arc, Once, OnceLock}, time::{Duration, Instant, SystemTime}};
 
-use anyhow::Result;
-use config::THEME;
-use syntect::{dumps::from_uncompressed_data, highlighting::{Theme, ThemeSet}, parsing::SyntaxSet};
+use anyhow::{anyhow, bail, Result};
+use config::{PREVIEW, THEME};
+use indexmap::IndexMap;
+use parking_lot::{Mutex, RwLock};
+use ratatui::style::{Color, Style};
+use shared::expand_path;
+use syntect::{dumps::from_uncompressed_data, highlighting::{HighlightIterator, HighlightState, Highlighter, Theme, ThemeSet}, parsing::{ParseState, ScopeStack, SyntaxSet}, util::as_24_bit_terminal_escaped};
+use tracing::warn;
+use unicode_width::UnicodeWidthChar;
 
 static SYNTECT_SYNTAX: OnceLock<SyntaxSet> = OnceLock::new();
-static SYNTECT_THEME: OnceLock<Theme> = OnceLock::new();
+static SYNTECT_BUNDLED: OnceLock<ThemeSet> = OnceLock::new();
+static SYNTECT_THEME: RwLock<Option<(String, Arc<Theme>)>> = RwLock::new(None);
+// The highlighting done on a thread of its own, with the cache of the files kept there, as the
+// parse states recorded along them hold the regions of onig, which can't go to another thread
+type HighlightJob = Box<dyn FnOnce(&mut HighlightCache) + Send>;
+static HIGHLIGHTING: OnceLock<Mutex<mpsc::Sender<HighlightJob>>> = OnceLock::new();
+
+const DEFAULT_THEME: &str = "base16-ocean.dark";
+
+// How often the states of the highlighting are recorded, in lines
+const CHECKPOINT: usize = 1000;
+
+// The line in place of the rest of a file not read, past the bytes of it read at most
+const CUTOFF: &str = "\x1b[0m\x1b[7m the rest isn't read, see `preview_full` \x1b[0m\n";
 
 #[inline]
-pub fn highlighter() -> (&'static SyntaxSet, &'static Theme) {
+pub fn highlighter() -> (&'static SyntaxSet, Arc<Theme>) {
 	let syntaxes =
 		SYNTECT_SYNTAX.get_or_init(|| from_uncompressed_data(yazi_prebuild::syntaxes()).unwrap());
 
-	let theme = SYNTECT_THEME.get_or_init(|| {
-		let from_file = || -> Result<Theme> {
-			let file = File::open(&THEME.preview.syntect_theme)?;
-			Ok(ThemeSet::load_from_reader(&mut BufReader::new(file))?)
+	if let Some((_, theme)) = &*SYNTECT_THEME.read() {
+		return (syntaxes, theme.clone());
+	}
+
+	let name = THEME.preview.syntect_theme.to_string_lossy();
+	let theme = Arc::new(load_theme(&name));
+	*SYNTECT_THEME.write() = Some((name.into_owned(), theme.clone()));
+	(syntaxes, theme)
+}
+
+// The name of the theme in use, or the path to its `.tmTheme` file
+#[inline]
+pub fn highlighter_theme() -> String {
+	highlighter();
+	SYNTECT_THEME.read().as_ref().map(|(name, _)| name.clone()).unwrap_or_default()
+}
+
+// Switches to another theme by its name or path, returns false if it's already in use
+pub fn set_highlighter_theme(name: &str) -> bool {
+	if highlighter_theme() == name {
+		return false;
+	}
+
+	*SYNTECT_THEME.write() = Some((name.to_owned(), Arc::new(load_theme(name))));
+	true
+}
+
+// Loads a theme bundled with syntect by its name, or a user-provided `.tmTheme` by its path,
+// and falls back to the default one if neither is found
+fn load_theme(name: &str) -> Theme {
+	let bundled = SYNTECT_BUNDLED.get_or_init(ThemeSet::load_defaults);
+	if let Some(theme) = bundled.themes.get(name) {
+		return theme.clone();
+	}
+
+	let from_file = |path: &Path| -> Result<Theme> {
+		let file = File::open(path)?;
+		Ok(ThemeSet::load_from_reader(&mut BufReader::new(file))?)
+	};
+	from_file(&expand_path(name)).unwrap_or_else(|e| {
+		static WARNED: Once = Once::new();
+		WARNED.call_once(|| {
+			warn!("failed to load syntect theme {name:?}, falling back to {DEFAULT_THEME}: {e}")
+		});
+		bundled.themes[DEFAULT_THEME].clone()
+	})
+}
+
+// The style of the line numbers, in the gutter colors of the theme if it has them
+pub fn highlighter_gutter() -> Style {
+	let (_, theme) = highlighter();
+	let rgb = |c: syntect::highlighting::Color| Color::Rgb(c.r, c.g, c.b);
+
+	let mut style = Style::new();
+	if let Some(fg) = theme.settings.gutter_foreground.or(theme.settings.foreground) {
+		style = style.fg(rgb(fg));
+	}
+	if let Some(bg) = theme.settings.gutter.or(theme.settings.background) {
+		style = style.bg(rgb(bg));
+	}
+	style
+}
+
+// A line with the matches of a content search, in the columns of the preview
+#[derive(Clone, Debug, PartialEq)]
+pub struct HighlightMatch {
+	pub line: usize,
+	pub cols: Vec<Range<usize>>,
+}
+
+impl HighlightMatch {
+	// Converts the byte spans in `text` to the columns where they are shown, as the tabs and
+	// control chars in it are by `expand_line`
+	pub fn new(line: usize, text: &str, spans: &[(usize, usize)], tab_size: usize) -> Self {
+		let mut col = 0;
+		let mut cols = Vec::with_capacity(text.len() + 1);
+		for (i, c) in text.char_indices() {
+			cols.resize(i + 1, col);
+			col += match c {
+				'\t' => tab_stop(col, tab_size),
+				c => visible(c).map_or_else(|| c.width().unwrap_or(0), |s| s.len()),
+			};
+		}
+		cols.resize(text.len() + 1, col);
+
+		let cols = spans
+			.iter()
+			.filter(|&&(start, end)| start < end && end <= text.len())
+			.map(|&(start, end)| cols[start]..cols[end])
+			.collect();
+		Self { line, cols }
+	}
+}
+
+// The line as it's shown in the preview, with each tab expanded to the next stop of `tab_size`
+// columns, and the other control chars made visible, e.g. `^M` for a carriage return, rather than
+// left to the terminal, so that an escape sequence in a file can't mess up the screen
+pub fn expand_line(line: &str, tab_size: usize) -> String {
+	let (mut s, mut col) = (String::with_capacity(line.len()), 0);
+	for c in line.chars() {
+		if c == '\t' {
+			let n = tab_stop(col, tab_size);
+			s.extend(std::iter::repeat_n(' ', n));
+			col += n;
+		} else if let Some(v) = visible(c) {
+			s.push_str(&v);
+			col += v.len();
+		} else {
+			s.push(c);
+			col += c.width().unwrap_or(0);
+		}
+	}
+	s
+}
+
+// The length of `buf` without the bytes of a char cut short at the end of it
+fn utf8_prefix(buf: &[u8]) -> usize {
+	let Some(i) = buf.iter().rev().take(4).position(|&b| b & 0xc0 != 0x80) else {
+		return buf.len();
+	};
+
+	let lead = buf.len() - 1 - i;
+	let width = match buf[lead] {
+		0xf0.. => 4,
+		0xe0.. => 3,
+		0xc0.. => 2,
+		_ => 1,
+	};
+	if lead + width > buf.len() { lead } else { buf.len() }
+}
+
+// The columns from `col` to the next tab stop
+#[inline]
+fn tab_stop(col: usize, tab_size: usize) -> usize { tab_size.max(1) - col % tab_size.max(1) }
+
+// How a control char is shown, in the caret notation of it for the C0 ones and DEL, or the code
+// of it for the C1 ones, none for any other char
+fn visible(c: char) -> Option<String> {
+	match c as u32 {
+		n @ (0..=0x1f | 0x7f) => Some(format!("^{}", char::from((n as u8) ^ 0x40))),
+		n @ 0x80..=0x9f => Some(format!("<{n:02x}>")),
+		_ => None,
+	}
+}
+
+// The states to resume highlighting a file from, at the start of a line
+#[derive(Clone)]
+struct Checkpoint {
+	offset:    u64,
+	parse:     ParseState,
+	highlight: HighlightState,
+}
+
+// How far a file is highlighted, past which its lines are shown as they are, so that a
+// pathological one can't hold up the preview, each of them `0` for no limit
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HighlightQuota {
+	// The bytes of a line, a longer one left out of it
+	pub line: usize,
+	// The time taken by a window, with the rest of the file left out once it's over
+	pub time: Duration,
+	// The bytes of the file, a larger one not highlighted at all
+	pub size: u64,
+	// The bytes of the file read at most, with the rest of it left out
+	pub read: u64,
+}
+
+// The limit of the quota hit in a file, the latest of them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HighlightLimit {
+	Line,
+	Time,
+	Size,
+}
+
+impl HighlightLimit {
+	// What the preview is marked with
+	pub fn mark(self) -> &'static str {
+		match self {
+			Self::Line => "long lines not highlighted",
+			Self::Time => "highlighting took too long",
+			Self::Size => "too large to highlight",
+		}
+	}
+
+	// Whether the lines from here on are left as they are, rather than only the one hitting it
+	#[inline]
+	fn is_for_good(self) -> bool { self != Self::Line }
+}
+
+// A window of the highlighted lines of a file, along with the states recorded on the way to it,
+// so that the next window is highlighted from the nearest line before it rather than the top
+#[derive(Default)]
+pub struct Highlighted {
+	checkpoints:   Vec<Checkpoint>,
+	pub start:     usize,
+	pub lines:     Vec<String>,
+	// The number of lines gone through so far, and whether it's all of them
+	pub scanned:   usize,
+	pub complete:  bool,
+	pub limit:     Option<HighlightLimit>,
+	// Whether it's cut short by the bytes read at most, with `CUTOFF` as the last line
+	pub truncated: bool,
+}
+
+impl Highlighted {
+	#[inline]
+	pub fn covers(&self, skip: usize, limit: usize) -> bool {
+		let end = self.start + self.lines.len();
+		skip >= self.start && (skip + limit <= end || self.complete && end == self.scanned)
+	}
+
+	// Highlights `limit` lines from the one at `skip`, reading only the bytes from the nearest
+	// checkpoint to them, and keeps at most `cap` bytes of each line, marking a cut one with `…`,
+	// where the ones past the `quota` are left plain
+	#[allow(clippy::too_many_arguments)]
+	pub fn window(
+		&mut self,
+		path: &Path,
+		(syntaxes, theme): (&SyntaxSet, &Theme),
+		skip: usize,
+		limit: usize,
+		cap: usize,
+		tab_size: usize,
+		quota: HighlightQuota,
+		cancelled: impl Fn() -> bool,
+	) -> Result<()> {
+		let (started, mut file) = (Instant::now(), File::open(path)?);
+		let len = file.metadata()?.len();
+		if quota.size > 0 && len > quota.size {
+			self.limit = Some(HighlightLimit::Size);
+		}
+
+		let highlighter = Highlighter::new(theme);
+		if self.checkpoints.is_empty() {
+			let syntax =
+				syntaxes.find_syntax_for_file(path)?.unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+			self.checkpoints.push(Checkpoint {
+				offset:    0,
+				parse:     ParseState::new(syntax),
+				highlight: HighlightState::new(&highlighter, ScopeStack::new()),
+			});
+		}
+
+		let mut i = (skip / CHECKPOINT).min(self.checkpoints.len() - 1) * CHECKPOINT;
+		let Checkpoint { mut offset, mut parse, mut highlight } =
+			self.checkpoints[i / CHECKPOINT].clone();
+
+		let truncated = quota.read > 0 && len > quota.read;
+		file.seek(SeekFrom::Start(offset))?;
+		let rest = if truncated { quota.read.saturating_sub(offset) } else { u64::MAX };
+		let mut reader = BufReader::new(file.take(rest));
+
+		let mut buf = Vec::new();
+		let mut lines = Vec::with_capacity(limit);
+		while i < skip + limit {
+			if cancelled() {
+				bail!("Highlighting cancelled");
+			}
+			if i.is_multiple_of(CHECKPOINT) && i / CHECKPOINT == self.checkpoints.len() {
+				let highlight = highlight.clone();
+				self.checkpoints.push(Checkpoint { offset, parse: parse.clone(), highlight });
+			}
+
+			buf.clear();
+			let (read, cut) = Self::read_line(&mut reader, &mut buf, cap)?;
+			if read == 0 {
+				if truncated {
+					if i >= skip {
+						lines.push(CUTOFF.to_owned());
+					}
+					self.scanned = self.scanned.max(i + 1);
+					self.truncated = true;
+				}
+				self.complete = true;
+				break;
+			}
+
+			// Not with a char cut in half at the end of the bytes kept of a line, or read of the file
+			if cut || (truncated && offset + read as u64 == quota.read) {
+				buf.truncate(utf8_prefix(&buf));
+			}
+
+			let mut line = expand_line(&String::from_utf8_lossy(&buf), tab_size);
+			line.push('\n');
+
+			if !quota.time.is_zero() && started.elapsed() > quota.time {
+				self.limit = Some(HighlightLimit::Time);
+			}
+			let long = quota.line > 0 && read > quota.line;
+			if long && self.limit.is_none_or(|l| !l.is_for_good()) {
+				self.limit = Some(HighlightLimit::Line);
+			}
+
+			// Left plain, without the states going on through it, for good once it's too slow
+			let s = if long || self.limit.is_some_and(HighlightLimit::is_for_good) {
+				(i >= skip).then(|| format!("\x1b[0m{line}"))
+			} else {
+				let ops = parse.parse_line(&line, syntaxes).map_err(|e| anyhow!(e))?;
+				let regions = HighlightIterator::new(&mut highlight, &ops, &line, &highlighter);
+				if i < skip {
+					regions.for_each(drop);
+					None
+				} else {
+					Some(as_24_bit_terminal_escaped(&regions.collect::<Vec<_>>(), false))
+				}
+			};
+			if let Some(mut s) = s {
+				if cut {
+					s.truncate(s.trim_end_matches('\n').len());
+					s.push_str("\x1b[0m…\n");
+				}
+				lines.push(s);
+			}
+
+			offset += read as u64;
+			i += 1;
+			self.scanned = self.scanned.max(i);
+		}
+
+		self.start = skip;
+		self.lines = lines;
+		Ok(())
+	}
+
+	// Reads a line without its newline into `buf`, keeping at most `cap` bytes of it, and returns
+	// the number of bytes consumed from the reader, and whether the line is cut
+	fn read_line(
+		reader: &mut impl BufRead,
+		buf: &mut Vec<u8>,
+		cap: usize,
+	) -> io::Result<(usize, bool)> {
+		let (mut read, mut cut) = (0, false);
+		loop {
+			let available = reader.fill_buf()?;
+			if available.is_empty() {
+				break;
+			}
+
+			let newline = available.iter().position(|&b| b == b'\n');
+			let end = newline.unwrap_or(available.len());
+			let keep = end.min(cap.saturating_sub(buf.len()));
+			buf.extend_from_slice(&available[..keep]);
+			cut |= keep < end;
+
+			let used = newline.map_or(end, |n| n + 1);
+			reader.consume(used);
+			read += used;
+			if newline.is_some() {
+				break;
+			}
+		}
+		Ok((read, cut))
+	}
+}
+
+// The lines of a unified diff highlighted as one, where the tabs of the lines of the changes are
+// expanded after the `+`, `-` or space they start with, to line up as they do in the files, and
+// the ones longer than `max_line` bytes are left plain
+pub fn highlight_diff(
+	(syntaxes, theme): (&SyntaxSet, &Theme),
+	lines: &[String],
+	tab_size: usize,
+	max_line: usize,
+) -> Result<String> {
+	let syntax =
+		syntaxes.find_syntax_by_extension("diff").unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+	let highlighter = Highlighter::new(theme);
+	let mut parse = ParseState::new(syntax);
+	let mut highlight = HighlightState::new(&highlighter, ScopeStack::new());
+
+	let mut buf = String::new();
+	for line in lines {
+		let mut line = match line.split_at_checked(1) {
+			Some((m @ ("+" | "-" | " "), rest)) => format!("{m}{}", expand_line(rest, tab_size)),
+			_ => expand_line(line, tab_size),
 		};
-		from_file().unwrap_or_else(|_| ThemeSet::load_defaults().themes["base16-ocean.dark"].clone())
+		line.push('\n');
+
+		if max_line > 0 && line.len() > max_line {
+			buf.push_str("\x1b[0m");
+			buf.push_str(&line);
+			continue;
+		}
+		let ops = parse.parse_line(&line, syntaxes).map_err(|e| anyhow!(e))?;
+		let regions = HighlightIterator::new(&mut highlight, &ops, &line, &highlighter);
+		buf.push_str(&as_24_bit_terminal_escaped(&regions.collect::<Vec<_>>(), false));
+	}
+	buf.push_str("\x1b[0m");
+	Ok(buf)
+}
+
+// A LRU cache of the highlighted files, whose entries are valid only for the same
+// modification time of the file and the same theme
+struct HighlightCache {
+	cap:     usize,
+	entries: IndexMap<PathBuf, (SystemTime, Arc<Theme>, Highlighted)>,
+}
+
+impl HighlightCache {
+	// The entry of the file, the one kept if it's still valid, or a new one, none if nothing is kept
+	fn entry(
+		&mut self,
+		path: &Path,
+		mtime: SystemTime,
+		theme: &Arc<Theme>,
+	) -> Option<&mut Highlighted> {
+		if self.cap == 0 {
+			return None;
+		}
+
+		// Move to the most recently used end
+		let kept = self.entries.shift_remove(path);
+		let h = kept.filter(|(m, t, _)| *m == mtime && Arc::ptr_eq(t, theme)).map(|(.., h)| h);
+		self.entries.insert(path.to_path_buf(), (mtime, theme.clone(), h.unwrap_or_default()));
+		while self.entries.len() > self.cap {
+			self.entries.shift_remove_index(0);
+		}
+		self.entries.last_mut().map(|(_, (.., h))| h)
+	}
+}
+
+// Runs `f` on the highlighted window of the file in the cache, or a new one put into it, or one not
+// kept without `mtime`, on the thread of the highlighting, and waits for what it returns
+pub async fn highlighted<T: Send + 'static>(
+	path: PathBuf,
+	mtime: Option<SystemTime>,
+	theme: Arc<Theme>,
+	f: impl FnOnce(&mut Highlighted) -> T + Send + 'static,
+) -> Result<T> {
+	let (tx, rx) = tokio::sync::oneshot::channel();
+	let job: HighlightJob = Box::new(move |cache| {
+		let mut fresh = Highlighted::default();
+		let h = mtime.and_then(|m| cache.entry(&path, m, &theme)).unwrap_or(&mut fresh);
+		tx.send(f(h)).ok();
 	});
 
-	(syntaxes, theme)
+	let jobs = HIGHLIGHTING.get_or_init(|| {
+		let (tx, rx) = mpsc::channel::<HighlightJob>();
+		std::thread::spawn(move || {
+			let mut cache = HighlightCache { cap: PREVIEW.highlight_cache, entries: Default::default() };
+			while let Ok(job) = rx.recv() {
+				job(&mut cache);
+			}
+		});
+		Mutex::new(tx)
+	});
+	jobs.lock().send(job).map_err(|_| anyhow!("the highlighting thread is gone"))?;
+	Ok(rx.await?)
+}
+
+#[test]
+fn test_load_theme() {
+	let bundled = ThemeSet::load_defaults();
+	let light = &bundled.themes["InspiredGitHub"];
+	let default = &bundled.themes[DEFAULT_THEME];
+
+	assert_eq!(load_theme("InspiredGitHub").name, light.name);
+	assert_eq!(load_theme("no-such-theme").name, default.name);
+	assert_eq!(load_theme("/no/such/path.tmTheme").name, default.name);
+}
+
+#[test]
+fn test_highlight_cache() {
+	let (a, b, c) = (Path::new("a"), Path::new("b"), Path::new("c"));
+	let (t1, t2) = (SystemTime::UNIX_EPOCH, SystemTime::now());
+	let (dark, light) = (Arc::new(Theme::default()), Arc::new(Theme::default()));
+
+	let mut cache = HighlightCache { cap: 2, entries: Default::default() };
+	let mut scan = |p, t, theme: &Arc<Theme>| {
+		let h = cache.entry(p, t, theme).unwrap();
+		h.scanned += 1;
+		h.scanned
+	};
+	assert_eq!(scan(a, t1, &dark), 1);
+	assert_eq!(scan(b, t1, &dark), 1);
+
+	// The least recently used one is evicted
+	assert_eq!(scan(a, t1, &dark), 2);
+	assert_eq!(scan(c, t1, &dark), 1);
+	assert_eq!(scan(b, t1, &dark), 1);
+	assert_eq!(scan(c, t1, &dark), 2);
+
+	// Made anew once the file is modified, or the theme is switched
+	assert_eq!(scan(c, t2, &dark), 1);
+	assert_eq!(scan(c, t2, &light), 1);
+	assert_eq!(cache.entries.len(), 2);
+
+	assert!(HighlightCache { cap: 0, entries: Default::default() }.entry(a, t1, &dark).is_none());
+}
+
+#[test]
+fn test_highlight_match() {
+	let cols = |text, spans| HighlightMatch::new(1, text, spans, 4).cols;
+	assert_eq!(cols("let a = 1;", &[(4, 5)]), [4..5]);
+	assert_eq!(cols("\tfoo\tfoo", &[(1, 4), (5, 8)]), [4..7, 8..11]);
+	assert_eq!(cols("ab\tc\x1b[0m", &[(3, 4), (5, 8)]), [4..5, 7..10]);
+	assert_eq!(cols("中文 ab", &[(7, 9)]), [5..7]);
+	assert!(cols("ab", &[(1, 9)]).is_empty());
+}
+
+#[test]
+fn test_expand_line() {
+	assert_eq!(expand_line("\ta\tbc\td", 4), "    a   bc  d");
+	assert_eq!(expand_line("abcd\te", 4), "abcd    e");
+	assert_eq!(expand_line("中\ta", 4), "中  a");
+	assert_eq!(expand_line("a\r\x1b[31mb\x7f\u{9b}", 4), "a^M^[[31mb^?<9b>");
+	assert_eq!(expand_line("\ta", 0), " a");
+}
+
+#[test]
+fn test_highlight_window() {
+	let dir = shared::TestDir::new("highlight");
+	let path = dir.join("a.rs");
+	let text: String = (0..2500).map(|i| format!("/* {i}\n */ let a = \"{i}\";\n")).collect();
+	std::fs::write(&path, text + &"x".repeat(100)).unwrap();
+
+	let syntaxes = from_uncompressed_data(yazi_prebuild::syntaxes()).unwrap();
+	let theme = &ThemeSet::load_defaults().themes[DEFAULT_THEME];
+	let window = |h: &mut Highlighted, skip, limit| {
+		h.window(&path, (&syntaxes, theme), skip, limit, 50, 2, Default::default(), || false).unwrap();
+		h.lines.clone()
+	};
+
+	// Resumed from the nearest checkpoint, in the same states as highlighted from the top
+	let mut h = Highlighted::default();
+	let top = window(&mut h, 0, 5002);
+	assert!(h.complete);
+	assert_eq!((h.scanned, h.checkpoints.len()), (5001, 6));
+	assert_eq!(window(&mut h, 4201, 10), top[4201..4211]);
+	assert!(h.covers(4203, 8) && !h.covers(4203, 9));
+
+	// Only gone through the lines up to the window
+	let mut h = Highlighted::default();
+	assert_eq!(window(&mut h, 3001, 10), top[3001..3011]);
+	assert_eq!((h.scanned, h.checkpoints.len(), h.complete), (3011, 4, false));
+
+	// The lines longer than the limit are cut
+	assert!(top[5000].ends_with("\x1b[0m…\n"));
+	assert!(!top[4999].contains('…'));
+}
+
+#[test]
+fn test_highlight_quota() {
+	let dir = shared::TestDir::new("quota");
+	let path = dir.join("a.rs");
+	let text = format!("let a = 1;\nlet b = \"{}\";\nlet c = 3;\n", "b".repeat(100));
+	std::fs::write(&path, text).unwrap();
+
+	let syntaxes = from_uncompressed_data(yazi_prebuild::syntaxes()).unwrap();
+	let theme = &ThemeSet::load_defaults().themes[DEFAULT_THEME];
+	let window = |quota| {
+		let mut h = Highlighted::default();
+		h.window(&path, (&syntaxes, theme), 0, 3, 1000, 4, quota, || false).unwrap();
+		(h.lines.iter().map(|l| l.starts_with("\x1b[0m")).collect::<Vec<_>>(), h.limit)
+	};
+
+	assert_eq!(window(HighlightQuota::default()), (vec![false; 3], None));
+
+	// Only the long line is left plain
+	let line = HighlightQuota { line: 50, ..Default::default() };
+	assert_eq!(window(line), (vec![false, true, false], Some(HighlightLimit::Line)));
+
+	// The whole file, for the size, or once the time is over
+	let size = HighlightQuota { size: 100, ..Default::default() };
+	assert_eq!(window(size), (vec![true; 3], Some(HighlightLimit::Size)));
+	let time = HighlightQuota { time: Duration::from_nanos(1), ..Default::default() };
+	assert_eq!(window(time).1, Some(HighlightLimit::Time));
+
+	// Cut short at the bytes read, in the middle of the line it's in, with the rest told of
+	let mut h = Highlighted::default();
+	let read = HighlightQuota { read: 14, ..Default::default() };
+	h.window(&path, (&syntaxes, theme), 0, 10, 1000, 4, read, || false).unwrap();
+	assert!(h.complete && h.truncated);
+	assert_eq!((h.lines.len(), h.scanned), (3, 3));
+	assert!(h.lines[1].contains("let") && !h.lines[1].contains('b'));
+	assert_eq!(h.lines[2], CUTOFF);
+}
+
+#[test]
+fn test_utf8_prefix() {
+	let s = "aé€😀".as_bytes();
+	assert_eq!(utf8_prefix(s), s.len());
+	assert_eq!(utf8_prefix(&s[..2]), 1);
+	assert_eq!(utf8_prefix(&s[..5]), 3);
+	assert_eq!(utf8_prefix(&s[..8]), 6);
+	assert_eq!(utf8_prefix(&s[..6]), 6);
+	assert_eq!(utf8_prefix(b"\x80\x80"), 2);
+	assert_eq!(utf8_prefix(b""), 0);
+}
+
+#[test]
+fn test_highlight_diff() {
+	let syntaxes = from_uncompressed_data(yazi_prebuild::syntaxes()).unwrap();
+	let theme = &ThemeSet::load_defaults().themes[DEFAULT_THEME];
+	let lines = ["@@ -1 +1 @@", "-\ta", "+\ta", " b\tc"].map(ToOwned::to_owned);
+
+	let s = highlight_diff((&syntaxes, theme), &lines, 4, 0).unwrap();
+	let colored: Vec<_> = s.lines().collect();
+	assert_eq!(colored.len(), 5);
+	assert!(colored[1].starts_with("\x1b[") && colored[1] != colored[2].replace('+', "-"));
+
+	let s = highlight_diff((&syntaxes, theme), &lines, 4, 1).unwrap();
+	assert_eq!(s, "\x1b[0m@@ -1 +1 @@\n\x1b[0m-    a\n\x1b[0m+    a\n\x1b[0m b   c\n\x1b[0m");
 }
@@ -0,0 +1,70 @@
+use config::HOOKS;
+use shared::{RoCell, Url};
+use tokio::{sync::mpsc::{self, UnboundedSender}, time::timeout};
+use tracing::warn;
+
+use crate::external::{self, ShellOpt};
+
+// What the commands of `[hooks]` are run for, each of them with the files it's about
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hook {
+	Enter,
+	Open,
+	Select,
+}
+
+static TX: RoCell<UnboundedSender<(Hook, Vec<Url>)>> = RoCell::new();
+
+// The hooks are run one at a time in the background, in the order they're emitted, so that one
+// taking long holds up the others but never the UI, and is killed once past the timeout
+pub(super) fn init_hooks() {
+	let (tx, mut rx) = mpsc::unbounded_channel();
+	TX.init(tx);
+
+	tokio::spawn(async move {
+		while let Some((hook, urls)) = rx.recv().await {
+			for cmd in hook.commands() {
+				Hook::run(cmd, &urls).await;
+			}
+		}
+	});
+}
+
+impl Hook {
+	#[inline]
+	fn commands(self) -> &'static [String] {
+		match self {
+			Self::Enter => &HOOKS.enter,
+			Self::Open => &HOOKS.open,
+			Self::Select => &HOOKS.select,
+		}
+	}
+
+	// Leaves it alone if there's no command for it, without the files being collected for nothing
+	#[inline]
+	pub fn enabled(self) -> bool { !self.commands().is_empty() }
+
+	#[inline]
+	pub fn emit(self, urls: Vec<Url>) {
+		if self.enabled() {
+			TX.send((self, urls)).ok();
+		}
+	}
+
+	async fn run(cmd: &str, urls: &[Url]) {
+		let args = urls.iter().map(|u| u.as_os_str().to_owned()).collect();
+		let child = match external::shell(ShellOpt { cmd: cmd.into(), args, piped: true }) {
+			Ok(child) => child,
+			Err(e) => return warn!("failed to run the hook `{cmd}`: {e}"),
+		};
+
+		match timeout(HOOKS.timeout, child.wait_with_output()).await {
+			Ok(Ok(output)) if output.status.success() => {}
+			Ok(Ok(output)) => {
+				warn!("the hook `{cmd}` failed: {}", String::from_utf8_lossy(&output.stderr).trim())
+			}
+			Ok(Err(e)) => warn!("failed to run the hook `{cmd}`: {e}"),
+			Err(_) => warn!("the hook `{cmd}` timed out, and was killed"),
+		}
+	}
+}
@@ -0,0 +1,237 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use shared::expand_path;
+
+use crate::files::natural_cmp;
+
+// Completes the path before the cursor against the filesystem, relative to `cwd`,
+// or the last word of it for a shell command, which is unquoted and quoted back
+#[derive(Default)]
+pub(super) struct InputCompletion {
+	cwd:   Option<PathBuf>,
+	shell: bool,
+	cycle: Option<Cycle>,
+}
+
+// The candidates of the word at `start`, the one gone to, and the value and the cursor after that,
+// the cycle goes on only if they're left untouched
+struct Cycle {
+	start:      usize,
+	candidates: Vec<String>,
+	index:      Option<usize>,
+	value:      String,
+	cursor:     usize,
+}
+
+impl InputCompletion {
+	pub(super) fn reset(&mut self, cwd: Option<PathBuf>, shell: bool) {
+		self.cwd = cwd;
+		self.shell = shell;
+		self.cycle = None;
+	}
+
+	// Returns where the word to replace starts, as a char index, and what to replace it with,
+	// which is the longest common prefix of the candidates first, then each of them in turn
+	pub(super) fn next(
+		&mut self,
+		value: &str,
+		cursor: usize,
+		step: isize,
+	) -> Option<(usize, String)> {
+		let cwd = self.cwd.as_ref()?;
+		if let Some(c) = self.cycle.as_mut().filter(|c| c.value == value && c.cursor == cursor) {
+			let len = c.candidates.len() as isize;
+			let i = match c.index {
+				Some(i) => (i as isize + step).rem_euclid(len),
+				None if step < 0 => len - 1,
+				None => 0,
+			} as usize;
+
+			c.index = Some(i);
+			return Some((c.start, c.candidates[i].clone()));
+		}
+
+		self.cycle = None;
+		let before: String = value.chars().take(cursor).collect();
+		let start = if self.shell { word_start(&before) } else { 0 };
+		let word: String = before.chars().skip(start).collect();
+		let typed = if self.shell { unquote(&word) } else { word };
+		if typed == "~" {
+			return Some((start, "~/".to_owned()));
+		}
+
+		let (dir, prefix) = typed.split_at(typed.rfind('/').map_or(0, |i| i + 1));
+		let names = Self::list(&cwd.join(expand_path(dir)), prefix);
+		let shell = self.shell;
+		let escape = |s: &str| if shell { quote(s) } else { s.to_owned() };
+		let candidates: Vec<_> = names.iter().map(|n| escape(&format!("{dir}{n}"))).collect();
+
+		match candidates.len() {
+			0 => None,
+			1 => Some((start, candidates[0].clone())),
+			_ => {
+				let common = common_prefix(&names);
+				let index = if common.len() > prefix.len() {
+					None
+				} else {
+					Some(if step < 0 { candidates.len() - 1 } else { 0 })
+				};
+
+				let word = match index {
+					Some(i) => candidates[i].clone(),
+					None if shell => quote_partial(&format!("{dir}{common}")),
+					None => format!("{dir}{common}"),
+				};
+				self.cycle = Some(Cycle { start, candidates, index, value: String::new(), cursor: 0 });
+				Some((start, word))
+			}
+		}
+	}
+
+	// Remembers the value and the cursor after a completion, to tell if the next one cycles
+	pub(super) fn applied(&mut self, value: &str, cursor: usize) {
+		if let Some(c) = &mut self.cycle {
+			c.value = value.to_owned();
+			c.cursor = cursor;
+		}
+	}
+
+	// How far the cycle has gone, shown in the title while the value is left untouched
+	pub(super) fn status(&self, value: &str, cursor: usize) -> Option<String> {
+		let c = self.cycle.as_ref().filter(|c| c.value == value && c.cursor == cursor)?;
+		Some(match c.index {
+			Some(i) => format!("{}/{}", i + 1, c.candidates.len()),
+			None => format!("{} candidates", c.candidates.len()),
+		})
+	}
+
+	// The entries starting with `prefix`, with a slash after the directories,
+	// and the hidden ones only if `prefix` starts with a dot
+	fn list(dir: &Path, prefix: &str) -> Vec<String> {
+		let Ok(it) = fs::read_dir(dir) else {
+			return vec![];
+		};
+
+		let mut names: Vec<_> = it
+			.flatten()
+			.filter_map(|e| {
+				let name = e.file_name().into_string().ok()?;
+				if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+					return None;
+				}
+				Some(if e.path().is_dir() { format!("{name}/") } else { name })
+			})
+			.collect();
+
+		names.sort_unstable_by(|a, b| natural_cmp(a.as_ref(), b.as_ref(), false, false));
+		names
+	}
+}
+
+fn common_prefix(names: &[String]) -> &str {
+	let first = &names[0];
+	let mut len = first.len();
+	for s in &names[1..] {
+		let diff = first[..len].char_indices().zip(s.chars()).find(|((_, a), b)| a != b);
+		len = diff.map_or(len.min(s.len()), |((i, _), _)| i);
+	}
+	&first[..len]
+}
+
+// Where the last word of a shell command starts, after the last whitespace out of quotes
+fn word_start(s: &str) -> usize {
+	let (mut start, mut quote, mut escaped) = (0, None, false);
+	for (i, c) in s.chars().enumerate() {
+		match (quote, c) {
+			_ if escaped => escaped = false,
+			(Some('\''), '\'') | (Some('"'), '"') => quote = None,
+			(Some('\''), _) => {}
+			(_, '\\') if cfg!(unix) => escaped = true,
+			(Some(_), _) => {}
+			(None, '\'' | '"') => quote = Some(c),
+			(None, c) if c.is_whitespace() => start = i + 1,
+			_ => {}
+		}
+	}
+	start
+}
+
+fn unquote(s: &str) -> String {
+	let (mut out, mut quote, mut escaped) = (String::with_capacity(s.len()), None, false);
+	for c in s.chars() {
+		match (quote, c) {
+			_ if escaped => {
+				escaped = false;
+				out.push(c);
+			}
+			(Some('\''), '\'') | (Some('"'), '"') => quote = None,
+			(Some('\''), _) => out.push(c),
+			(_, '\\') if cfg!(unix) => escaped = true,
+			(None, '\'' | '"') => quote = Some(c),
+			_ => out.push(c),
+		}
+	}
+	out
+}
+
+// Escapes the characters special to the shell with a backslash, which a partial word can be
+// typed on, while on Windows the whole word is wrapped in double quotes if it has a space
+#[cfg(unix)]
+fn quote_partial(s: &str) -> String {
+	let mut out = String::with_capacity(s.len());
+	for c in s.chars() {
+		if !c.is_whitespace() && !"'\"\\$`&;|()<>*?[]#!{}".contains(c) {
+			out.push(c);
+			continue;
+		}
+		out.push('\\');
+		out.push(c);
+	}
+	out
+}
+
+#[cfg(windows)]
+fn quote_partial(s: &str) -> String {
+	if s.contains(' ') { format!("\"{s}") } else { s.to_owned() }
+}
+
+#[cfg(unix)]
+#[inline]
+fn quote(s: &str) -> String { quote_partial(s) }
+
+#[cfg(windows)]
+fn quote(s: &str) -> String {
+	if s.contains(' ') { format!("\"{s}\"") } else { s.to_owned() }
+}
+
+#[test]
+fn test_completion() {
+	let dir = shared::TestDir::new("completion");
+	for p in ["Documents/a", "Downloads/b", "My Notes.txt", ".hidden"] {
+		let p = dir.join(p);
+		fs::create_dir_all(p.parent().unwrap()).unwrap();
+		fs::write(p, "").unwrap();
+	}
+
+	let mut c = InputCompletion::default();
+	c.reset(Some(dir.to_path_buf()), false);
+	assert_eq!(c.next("D", 1, 1), Some((0, "Do".to_owned())));
+	c.applied("Do", 2);
+	assert_eq!(c.status("Do", 2).as_deref(), Some("2 candidates"));
+	assert_eq!(c.next("Do", 2, 1), Some((0, "Documents/".to_owned())));
+	c.applied("Documents/", 10);
+	assert_eq!(c.next("Documents/", 10, 1), Some((0, "Downloads/".to_owned())));
+	c.applied("Downloads/", 10);
+	assert_eq!(c.next("Downloads/", 10, 1), Some((0, "Documents/".to_owned())));
+	assert_eq!(c.next("Documents/", 10, 1), Some((0, "Documents/a".to_owned())));
+	assert_eq!(c.next(".", 1, 1), Some((0, ".hidden".to_owned())));
+	assert_eq!(c.next("x", 1, 1), None);
+
+	#[cfg(unix)]
+	{
+		c.reset(Some(dir.to_path_buf()), true);
+		assert_eq!(c.next("cat My", 6, 1), Some((4, "My\\ Notes.txt".to_owned())));
+		assert_eq!(c.next("cat 'My N", 9, 1), Some((4, "My\\ Notes.txt".to_owned())));
+		assert_eq!(c.next("ls ~", 4, 1), Some((3, "~/".to_owned())));
+	}
+}
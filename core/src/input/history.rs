@@ -0,0 +1,71 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use config::BOOT;
+use tracing::warn;
+
+// The values submitted to each category of prompts, the latest last, kept across restarts
+#[derive(Default)]
+pub(super) struct InputHistory {
+	loaded: bool,
+	inner:  BTreeMap<String, Vec<String>>,
+}
+
+impl InputHistory {
+	#[inline]
+	fn path() -> PathBuf { BOOT.state_dir.join("input_history.json") }
+
+	pub(super) fn get(&mut self, category: &str) -> &[String] {
+		if !self.loaded {
+			self.loaded = true;
+			if let Ok(s) = std::fs::read_to_string(Self::path()) {
+				self.inner = serde_json::from_str(&s).unwrap_or_default();
+			}
+		}
+		self.inner.get(category).map_or(&[], |v| v.as_slice())
+	}
+
+	// Adds the value unless it's empty or the same as the latest one,
+	// and drops the oldest ones beyond `max`
+	pub(super) fn push(&mut self, category: &str, value: &str, max: usize) -> bool {
+		self.get(category);
+		let values = self.inner.entry(category.to_owned()).or_default();
+		if value.is_empty() || values.last().is_some_and(|v| v == value) {
+			return false;
+		}
+
+		values.push(value.to_owned());
+		values.drain(..values.len().saturating_sub(max));
+		true
+	}
+
+	pub(super) fn clear(&mut self, category: &str) -> bool {
+		self.get(category);
+		self.inner.remove(category).is_some_and(|v| !v.is_empty())
+	}
+
+	pub(super) fn save(&self) {
+		let Ok(s) = serde_json::to_string(&self.inner) else {
+			return;
+		};
+		tokio::spawn(async move {
+			if let Err(e) = tokio::fs::write(Self::path(), s).await {
+				warn!("failed to save the input history: {e}");
+			}
+		});
+	}
+}
+
+#[test]
+fn test_history_push() {
+	let mut h = InputHistory { loaded: true, ..Default::default() };
+	assert!(h.push("shell", "ls", 2));
+	assert!(!h.push("shell", "ls", 2));
+	assert!(!h.push("shell", "", 2));
+	assert!(h.push("shell", "make", 2));
+	assert!(h.push("shell", "ls", 2));
+	assert_eq!(h.get("shell"), ["make", "ls"]);
+
+	assert!(h.get("rename").is_empty());
+	assert!(h.clear("shell"));
+	assert!(!h.clear("shell"));
+}
@@ -1,12 +1,15 @@
 use std::ops::Range;
 
-use config::keymap::Key;
+use config::{keymap::Key, INPUT};
 use crossterm::event::KeyCode;
 use shared::{CharKind, InputError};
 use tokio::sync::mpsc::UnboundedSender;
 use unicode_width::UnicodeWidthStr;
 
-use super::{mode::InputMode, op::InputOp, InputOpt, InputSnap, InputSnaps};
+use super::{
+	mode::InputMode, op::InputOp, InputCompletion, InputHinter, InputHistory, InputKillRing, InputOpt,
+	InputSnap, InputSnaps, InputValidator,
+};
 use crate::{external, Position};
 
 #[derive(Default)]
@@ -23,6 +26,30 @@ pub struct Input {
 
 	// Shell
 	pub(super) highlight: bool,
+	multiline:            bool,
+	scroll:               usize,
+
+	// Obscure, with the value shown as `•`s
+	obscure: bool,
+
+	// History, with the index of the value gone to, and what was typed before going there
+	history:  InputHistory,
+	category: Option<String>,
+	browsing: Option<(usize, String)>,
+
+	// Completion
+	completion: InputCompletion,
+
+	// Kill ring, kept across prompts
+	kills: InputKillRing,
+
+	// Validation, with why the current value is invalid
+	validator:   Option<InputValidator>,
+	pub invalid: Option<String>,
+
+	// Hint, about the current value, and whether it's a warning
+	hinter:   Option<InputHinter>,
+	pub hint: Option<(String, bool)>,
 }
 
 impl Input {
@@ -40,15 +67,58 @@ impl Input {
 
 		// Shell
 		self.highlight = opt.highlight;
+		self.multiline = opt.multiline;
+		self.scroll = 0;
+
+		// Obscure
+		self.obscure = opt.obscure;
+
+		// History
+		self.category = opt.history.filter(|c| !opt.obscure && INPUT.remembers(c));
+		self.browsing = None;
+
+		// Completion
+		self.completion.reset(opt.completion, opt.highlight);
+
+		// Validation
+		self.validator = opt.validator;
+		self.hinter = opt.hinter;
+
+		// Selection
+		match opt.selected {
+			Some(range) if INPUT.vi_mode && !range.is_empty() => {
+				let snap = self.snaps.current_mut();
+				snap.mode = InputMode::Normal;
+				snap.op = InputOp::Select(range.start);
+				snap.cursor = range.end - 1;
+			}
+			Some(range) => self.snaps.current_mut().cursor = range.end,
+			None => {}
+		}
+		self.snaps.catch();
+		self.move_(0);
 	}
 
 	pub fn close(&mut self, submit: bool) -> bool {
+		if submit && !self.validate() {
+			return true;
+		}
+
 		if let Some(cb) = self.callback.take() {
 			let value = self.snap_mut().value.clone();
+			if let Some(category) = self.category.take().filter(|_| submit) {
+				if self.history.push(&category, &value, INPUT.history_size) {
+					self.history.save();
+				}
+			}
 			let _ = cb.send(if submit { Ok(value) } else { Err(InputError::Canceled(value)) });
 		}
 
 		self.visible = false;
+		self.validator = None;
+		self.invalid = None;
+		self.hinter = None;
+		self.hint = None;
 		true
 	}
 
@@ -61,6 +131,9 @@ impl Input {
 			InputMode::Normal => {
 				snap.op = InputOp::None;
 			}
+			InputMode::Insert if !INPUT.vi_mode => {
+				self.close(false);
+			}
 			InputMode::Insert => {
 				snap.mode = InputMode::Normal;
 				self.move_(-1);
@@ -91,6 +164,7 @@ impl Input {
 		if self.snap().mode == InputMode::Insert {
 			self.escape();
 		}
+		self.refresh();
 		true
 	}
 
@@ -99,6 +173,7 @@ impl Input {
 		if !self.snaps.redo() {
 			return false;
 		}
+		self.refresh();
 		true
 	}
 
@@ -127,9 +202,77 @@ impl Input {
 			}
 		}
 
+		self.refresh();
 		b
 	}
 
+	// Goes to the row above by a negative `step`, or below by a positive one, at the same column,
+	// in the prompts of multiple lines
+	pub fn move_row(&mut self, step: isize) -> bool {
+		if !self.multiline {
+			return false;
+		}
+
+		let snap = self.snap();
+		let rows = snap.rows();
+		let (row, col) = snap.locate(&rows, snap.cursor);
+		let new = (row as isize + step).clamp(0, rows.len() as isize - 1) as usize;
+		if new == row {
+			return false;
+		}
+
+		let step = snap.at(rows[new].clone(), col) as isize - snap.cursor as isize;
+		self.move_(step)
+	}
+
+	// Inserts a newline at the cursor in the prompts of multiple lines, or after the current line
+	// and enters insert mode if `below`, like `o` of vi
+	pub fn newline(&mut self, below: bool) -> bool {
+		if !self.multiline {
+			return false;
+		} else if below {
+			self.snap_mut().insert();
+			let snap = self.snap();
+			let n = snap.value.chars().skip(snap.cursor).take_while(|&c| c != '\n').count();
+			self.snaps.current_mut().cursor += n;
+		} else if self.mode() != InputMode::Insert {
+			return false;
+		}
+		self.type_char('\n')
+	}
+
+	// Checks the value again after it's changed, and tells if it can be submitted, along with
+	// the hint about it
+	fn validate(&mut self) -> bool {
+		if let Some(f) = &self.validator {
+			self.invalid = f(&self.snap().value).err();
+		}
+		if let Some(f) = &self.hinter {
+			self.hint = f(&self.snap().value);
+		}
+		self.invalid.is_none()
+	}
+
+	// Validates the value after it's changed, scrolls the prompts of multiple lines to where
+	// the cursor is, and fits the box to the rows
+	fn refresh(&mut self) {
+		self.validate();
+		if !self.multiline {
+			return;
+		}
+
+		let snap = self.snap();
+		let rows = snap.rows();
+		let (row, _) = snap.locate(&rows, snap.cursor);
+		let limit = rows.len().min(/* TODO: hardcode */ 5);
+
+		self.scroll = self.scroll.min(row).max((row + 1).saturating_sub(limit));
+		self.scroll = self.scroll.min(rows.len() - limit);
+		if let Position::Top(rect) | Position::Hovered(rect) = &mut self.position {
+			rect.height = 2 + limit as u16;
+		}
+	}
+
 	#[inline]
 	pub fn move_in_operating(&mut self, step: isize) -> bool {
 		if self.snap_mut().op == InputOp::None { false } else { self.move_(step) }
@@ -176,6 +319,8 @@ impl Input {
 			if b && !matches!(snap.op, InputOp::None | InputOp::Select(_)) {
 				return self.move_(i as isize);
 			} else if b {
+				// The end of a word is on its last character, or after it in insert mode
+				let end = end && snap.mode != InputMode::Insert;
 				return self.move_(if end { i - 1 } else { i } as isize);
 			}
 			prev = c;
@@ -330,6 +475,145 @@ impl Input {
 		true
 	}
 
+	// Cuts the text to the beginning of the line by "bol", to the end of it by "eol",
+	// or to the whitespace before the word before the cursor by "backward", into the kill ring,
+	// joined to the last cut right before it, like readline
+	pub fn kill(&mut self, kind: &str) -> bool {
+		let snap = self.snaps.current();
+		let cursor = snap.cursor;
+		let start = match kind {
+			"bol" | "backward" if cursor == 0 => return false,
+			"bol" => 0,
+			"backward" => {
+				let before: Vec<_> = snap.value.chars().take(cursor).collect();
+				let spaces = before.iter().rev().take_while(|c| c.is_whitespace()).count();
+				let word = before.iter().rev().skip(spaces).take_while(|c| !c.is_whitespace()).count();
+				cursor - spaces - word
+			}
+			"eol" if cursor >= snap.count() => return false,
+			"eol" => cursor,
+			_ => return false,
+		};
+
+		let value = snap.value.clone();
+		let snap = self.snaps.current_mut();
+		let end = if kind == "eol" { snap.count() } else { cursor };
+		let range = snap.idx(start).unwrap()..snap.idx(end).unwrap();
+		self.kills.kill(snap.value.drain(range).collect(), kind != "eol", &value, cursor);
+
+		self.flush_value();
+		self.move_(start as isize - cursor as isize);
+		self.snaps.tag();
+
+		let snap = self.snaps.current();
+		self.kills.settle(&snap.value, snap.cursor, None);
+		true
+	}
+
+	// Pastes the latest text cut by `kill` at the cursor, or replaces the one just pasted
+	// with the one cut before it if `older`
+	pub fn kill_paste(&mut self, older: bool) -> bool {
+		let snap = self.snaps.current();
+		let Some((from, index, text)) = self.kills.paste(older, &snap.value, snap.cursor) else {
+			return false;
+		};
+
+		let (text, cursor) = (text.to_owned(), snap.cursor);
+		let start = from.unwrap_or(cursor);
+		let snap = self.snaps.current_mut();
+		let range = snap.idx(start).unwrap()..snap.idx(cursor).unwrap_or(snap.len());
+		snap.value.replace_range(range, &text);
+
+		self.flush_value();
+		self.move_((start + text.chars().count()) as isize - cursor as isize);
+		self.snaps.tag();
+
+		let snap = self.snaps.current();
+		self.kills.settle(&snap.value, snap.cursor, Some((start, index)));
+		true
+	}
+
+	// Completes the path before the cursor, the longest common prefix of the candidates first,
+	// then each of them in turn, forward by a positive `step` or backward by a negative one
+	pub fn complete(&mut self, step: isize) -> bool {
+		let snap = self.snaps.current();
+		if snap.mode != InputMode::Insert {
+			return false;
+		}
+
+		let Some((start, word)) = self.completion.next(&snap.value, snap.cursor, step) else {
+			return false;
+		};
+
+		let snap = self.snaps.current_mut();
+		let range = snap.idx(start).unwrap()..snap.idx(snap.cursor).unwrap_or(snap.len());
+		snap.value.replace_range(range, &word);
+
+		let cursor = start + word.chars().count();
+		self.flush_value();
+		self.move_(cursor as isize - self.snap().cursor as isize);
+		let snap = self.snaps.current();
+		self.completion.applied(&snap.value, snap.cursor);
+		true
+	}
+
+	// Goes to an older value submitted to the same category of prompts by a negative `step`,
+	// or a newer one by a positive `step`, and back to what was typed after the latest one.
+	// In the prompts of multiple lines, it goes to the row above or below first, if there's one
+	pub fn history(&mut self, step: isize) -> bool {
+		if self.move_row(step) {
+			return true;
+		}
+
+		let Some(category) = &self.category else {
+			return false;
+		};
+
+		let values = self.history.get(category);
+		let old = self.browsing.as_ref().map_or(values.len(), |&(i, _)| i);
+		let new = (old as isize + step).clamp(0, values.len() as isize) as usize;
+		if new == old {
+			return false;
+		}
+
+		let value = values.get(new).cloned();
+		let typed = match self.browsing.take() {
+			Some((_, typed)) => typed,
+			None => self.snap().value.clone(),
+		};
+		let value = match value {
+			Some(value) => {
+				self.browsing = Some((new, typed));
+				value
+			}
+			None => typed,
+		};
+
+		let snap = self.snap_mut();
+		snap.value = value;
+		snap.op = InputOp::None;
+		(snap.cursor, snap.offset) = (usize::MAX, usize::MAX);
+		snap.reset();
+
+		self.snaps.tag();
+		self.flush_value();
+		self.refresh();
+		true
+	}
+
+	// Forgets the values submitted to the prompts of `category`, or of the current prompt
+	pub fn history_clear(&mut self, category: Option<&str>) -> bool {
+		let Some(category) = category.or(self.category.as_deref()).map(ToOwned::to_owned) else {
+			return false;
+		};
+
+		self.browsing = None;
+		if self.history.clear(&category) {
+			self.history.save();
+		}
+		false
+	}
+
 	#[inline]
 	fn flush_value(&self) {
 		if self.realtime {
@@ -341,34 +625,83 @@ impl Input {
 
 impl Input {
 	#[inline]
-	pub fn title(&self) -> String { self.title.clone() }
+	pub fn title(&self) -> String {
+		let snap = self.snap();
+		match self.completion.status(&snap.value, snap.cursor) {
+			Some(status) => format!("{} ({status})", self.title),
+			None => self.title.clone(),
+		}
+	}
 
 	#[inline]
 	pub fn value(&self) -> &str { self.snap().slice(self.snap().window()) }
 
+	// The visible part of the value, the rows of it in view for the prompts of multiple lines
+	pub fn lines(&self) -> String {
+		let snap = self.snap();
+		if self.obscure {
+			return "•".repeat(self.value().chars().count());
+		} else if !self.multiline {
+			return self.value().to_owned();
+		}
+
+		let rows = snap.rows();
+		let end = rows.len().min(self.scroll + /* TODO: hardcode */ 5);
+		rows[self.scroll..end].iter().map(|r| snap.expand(r.clone())).collect::<Vec<_>>().join("\n")
+	}
+
 	#[inline]
 	pub fn mode(&self) -> InputMode { self.snap().mode }
 
+	// The column and the row of the cursor in the box
 	#[inline]
-	pub fn cursor(&self) -> u16 {
+	pub fn cursor(&self) -> (u16, u16) {
 		let snap = self.snap();
-		snap.slice(snap.offset..snap.cursor).width() as u16
+		if !self.multiline {
+			return (self.width(snap.slice(snap.offset..snap.cursor)) as u16, 0);
+		}
+
+		let (row, col) = snap.locate(&snap.rows(), snap.cursor);
+		(col as u16, (row - self.scroll) as u16)
 	}
 
-	pub fn selected(&self) -> Option<Range<u16>> {
+	// The selected columns of each row in the box
+	pub fn selected(&self) -> Vec<(u16, Range<u16>)> {
 		let snap = self.snap();
-		let start = snap.op.start()?;
+		let Some(start) = snap.op.start() else {
+			return vec![];
+		};
 
 		let (start, end) =
 			if start < snap.cursor { (start, snap.cursor) } else { (snap.cursor + 1, start + 1) };
 
-		let win = snap.window();
-		let Range { start, end } = start.max(win.start)..end.min(win.end);
+		if !self.multiline {
+			let win = snap.window();
+			let Range { start, end } = start.max(win.start)..end.min(win.end);
+
+			let s = self.width(snap.slice(snap.offset..start)) as u16;
+			return vec![(0, s..s + self.width(snap.slice(start..end)) as u16)];
+		}
 
-		let s = snap.slice(snap.offset..start).width() as u16;
-		Some(s..s + snap.slice(start..end).width() as u16)
+		let rows = snap.rows();
+		let end_row = rows.len().min(self.scroll + /* TODO: hardcode */ 5);
+		(self.scroll..end_row)
+			.filter_map(|i| {
+				let Range { start, end } = start.max(rows[i].start)..end.min(rows[i].end);
+				if start >= end {
+					return None;
+				}
+
+				let s = snap.width(rows[i].start..start) as u16;
+				Some(((i - self.scroll) as u16, s..s + snap.width(start..end) as u16))
+			})
+			.collect()
 	}
 
+	// The columns `s` takes in the box, a `•` for each char if it's obscured
+	#[inline]
+	fn width(&self, s: &str) -> usize { if self.obscure { s.chars().count() } else { s.width() } }
+
 	#[inline]
 	fn snap(&self) -> &InputSnap { self.snaps.current() }
 
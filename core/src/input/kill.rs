@@ -0,0 +1,87 @@
+use std::collections::VecDeque;
+
+// How many of the texts cut by `kill` are kept
+const KILL_RING_SIZE: usize = 10;
+
+// The texts cut by `kill`, the latest last, which `kill_paste` puts back
+#[derive(Default)]
+pub(super) struct InputKillRing {
+	ring: VecDeque<String>,
+
+	last: Option<Last>,
+}
+
+// The value and the cursor after the last kill or paste, with where and which one was pasted,
+// the next kill is joined to the last one, and the next paste goes to an older one,
+// only if they're left untouched
+struct Last {
+	value:  String,
+	cursor: usize,
+	pasted: Option<(usize, usize)>,
+}
+
+impl InputKillRing {
+	// Keeps the text cut from before the cursor if `before`, or after it otherwise
+	pub(super) fn kill(&mut self, text: String, before: bool, value: &str, cursor: usize) {
+		let joins = self.untouched(value, cursor).is_some_and(|l| l.pasted.is_none());
+		match self.ring.back_mut() {
+			Some(latest) if joins && before => latest.insert_str(0, &text),
+			Some(latest) if joins => latest.push_str(&text),
+			_ => {
+				self.ring.push_back(text);
+				if self.ring.len() > KILL_RING_SIZE {
+					self.ring.pop_front();
+				}
+			}
+		}
+	}
+
+	// Which one to paste, with its index, and where the last pasted one starts to be replaced,
+	// which is the latest, or the one older than the last pasted if `older` right after a paste
+	pub(super) fn paste(
+		&self,
+		older: bool,
+		value: &str,
+		cursor: usize,
+	) -> Option<(Option<usize>, usize, &str)> {
+		let latest = self.ring.len().checked_sub(1)?;
+		if !older {
+			return Some((None, latest, &self.ring[latest]));
+		}
+
+		let (start, index) = self.untouched(value, cursor)?.pasted?;
+		let index = index.checked_sub(1).unwrap_or(latest);
+		Some((Some(start), index, &self.ring[index]))
+	}
+
+	// Remembers the value and the cursor after a kill, or a paste of the one at `pasted`
+	#[inline]
+	pub(super) fn settle(&mut self, value: &str, cursor: usize, pasted: Option<(usize, usize)>) {
+		self.last = Some(Last { value: value.to_owned(), cursor, pasted });
+	}
+
+	#[inline]
+	fn untouched(&self, value: &str, cursor: usize) -> Option<&Last> {
+		self.last.as_ref().filter(|l| l.value == value && l.cursor == cursor)
+	}
+}
+
+#[test]
+fn test_kill_ring() {
+	let mut k = InputKillRing::default();
+	k.kill("world".to_owned(), true, "hello world", 11);
+	k.settle("hello ", 6, None);
+	k.kill("hello ".to_owned(), true, "hello ", 6);
+	assert_eq!(k.paste(false, "", 0), Some((None, 0, "hello world")));
+
+	k.kill("foo".to_owned(), false, "foo", 0);
+	k.settle("foo", 3, Some((0, 1)));
+	assert_eq!(k.paste(true, "foo", 3), Some((Some(0), 0, "hello world")));
+	assert_eq!(k.paste(true, "fo", 2), None);
+
+	for i in 0..KILL_RING_SIZE {
+		k.kill(i.to_string(), false, "", 0);
+	}
+	assert_eq!(k.ring.len(), KILL_RING_SIZE);
+	assert_eq!(k.ring.front().map(|s| s.as_str()), Some("0"));
+}
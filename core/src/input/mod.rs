@@ -1,4 +1,7 @@
+mod completion;
+mod history;
 mod input;
+mod kill;
 mod mode;
 mod op;
 mod option;
@@ -6,7 +9,10 @@ mod shell;
 mod snap;
 mod snaps;
 
+use completion::*;
+use history::*;
 pub use input::*;
+use kill::*;
 pub use mode::*;
 use op::*;
 pub use option::*;
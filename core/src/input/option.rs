@@ -1,36 +1,65 @@
+use std::{ops::Range, path::{Path, PathBuf}};
+
 use ratatui::prelude::Rect;
 
 use crate::Position;
 
+// Tells why a value can't be submitted, checked each time it changes
+pub type InputValidator = Box<dyn Fn(&str) -> Result<(), String> + Send>;
+
+// A line shown below the box as the value changes, e.g. where a path ends up, in red if it warns
+pub type InputHinter = Box<dyn Fn(&str) -> Option<(String, bool)> + Send>;
+
 pub struct InputOpt {
-	pub title:     String,
-	pub value:     String,
-	pub position:  Position,
-	pub realtime:  bool,
-	pub highlight: bool,
+	pub title:      String,
+	pub value:      String,
+	pub selected:   Option<Range<usize>>,
+	pub position:   Position,
+	pub realtime:   bool,
+	pub highlight:  bool,
+	pub multiline:  bool,
+	pub obscure:    bool,
+	pub history:    Option<String>,
+	pub completion: Option<PathBuf>,
+	pub validator:  Option<InputValidator>,
+	pub hinter:     Option<InputHinter>,
 }
 
 impl InputOpt {
 	pub fn top(title: impl AsRef<str>) -> Self {
 		Self {
-			title:     title.as_ref().to_owned(),
-			value:     String::new(),
-			position:  Position::Top(/* TODO: hardcode */ Rect { x: 0, y: 2, width: 50, height: 3 }),
-			realtime:  false,
-			highlight: false,
+			title:      title.as_ref().to_owned(),
+			value:      String::new(),
+			selected:   None,
+			position:   Position::Top(/* TODO: hardcode */ Rect { x: 0, y: 2, width: 50, height: 3 }),
+			realtime:   false,
+			highlight:  false,
+			multiline:  false,
+			obscure:    false,
+			history:    None,
+			completion: None,
+			validator:  None,
+			hinter:     None,
 		}
 	}
 
 	pub fn hovered(title: impl AsRef<str>) -> Self {
 		Self {
-			title:     title.as_ref().to_owned(),
-			value:     String::new(),
-			position:  Position::Hovered(
+			title:      title.as_ref().to_owned(),
+			value:      String::new(),
+			selected:   None,
+			position:   Position::Hovered(
 				// TODO: hardcode
-				Rect { x: 0, y: 1, width: 50, height: 3 },
+				Rect { x: 0, y: 0, width: 50, height: 3 },
 			),
-			realtime:  false,
-			highlight: false,
+			realtime:   false,
+			highlight:  false,
+			multiline:  false,
+			obscure:    false,
+			history:    None,
+			completion: None,
+			validator:  None,
+			hinter:     None,
 		}
 	}
 
@@ -40,12 +69,66 @@ impl InputOpt {
 		self
 	}
 
+	// Selects the chars in `range` of the value to start with, or puts the cursor at the end of
+	// them if not in vi mode, e.g. the name without the extension to rename a file
+	#[inline]
+	pub fn with_selected(mut self, range: Range<usize>) -> Self {
+		self.selected = Some(range);
+		self
+	}
+
 	#[inline]
 	pub fn with_realtime(mut self) -> Self {
 		self.realtime = true;
 		self
 	}
 
+	// Remembers the submitted values along with the other prompts of the same `category`
+	#[inline]
+	pub fn with_history(mut self, category: &str) -> Self {
+		self.history = Some(category.to_owned());
+		self
+	}
+
+	// Completes the typed paths relative to `cwd`
+	#[inline]
+	pub fn with_completion(mut self, cwd: impl AsRef<Path>) -> Self {
+		self.completion = Some(cwd.as_ref().to_owned());
+		self
+	}
+
+	// Allows newlines in the value, with the box growing to the rows of it
+	#[inline]
+	pub fn with_multiline(mut self) -> Self {
+		self.multiline = true;
+		self
+	}
+
+	// Hides the value behind a `•` for each char of it, e.g. a password, never kept in history
+	#[inline]
+	pub fn with_obscure(mut self) -> Self {
+		self.obscure = true;
+		self
+	}
+
+	#[inline]
+	pub fn with_validator(
+		mut self,
+		f: impl Fn(&str) -> Result<(), String> + Send + 'static,
+	) -> Self {
+		self.validator = Some(Box::new(f));
+		self
+	}
+
+	#[inline]
+	pub fn with_hint(
+		mut self,
+		f: impl Fn(&str) -> Option<(String, bool)> + Send + 'static,
+	) -> Self {
+		self.hinter = Some(Box::new(f));
+		self
+	}
+
 	#[inline]
 	pub fn with_highlight(mut self) -> Self {
 		self.highlight = true;
@@ -12,9 +12,12 @@ impl Input {
 
 		let (syntaxes, theme) = highlighter();
 		if let Some(syntax) = syntaxes.find_syntax_by_name("Bourne Again Shell (bash)") {
-			let mut h = HighlightLines::new(syntax, theme);
-			let regions = h.highlight_line(self.value(), syntaxes)?;
-			return Ok(as_24_bit_terminal_escaped(&regions, false));
+			let mut h = HighlightLines::new(syntax, &theme);
+			let mut lines = Vec::new();
+			for line in self.lines().split('\n') {
+				lines.push(as_24_bit_terminal_escaped(&h.highlight_line(line, syntaxes)?, false));
+			}
+			return Ok(lines.join("\n"));
 		}
 
 		bail!("Failed to find syntax")
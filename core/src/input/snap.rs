@@ -1,6 +1,6 @@
 use std::ops::Range;
 
-use unicode_width::UnicodeWidthChar;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use super::{InputMode, InputOp};
 
@@ -88,6 +88,68 @@ impl InputSnap {
 	#[inline]
 	pub(super) fn window(&self) -> Range<usize> { Self::find_window(&self.value, self.offset) }
 
+	// Wraps the value into rows that fit in the box, by the ranges of chars in them,
+	// leaving out the newlines, for the prompts of multiple lines
+	pub(super) fn rows(&self) -> Vec<Range<usize>> {
+		let (mut rows, mut start, mut width) = (vec![], 0, 0);
+		for (i, c) in self.value.chars().enumerate() {
+			if c == '\n' {
+				rows.push(start..i);
+				(start, width) = (i + 1, 0);
+				continue;
+			}
+			if i > start && width + Self::char_width(c, width) >= /*TODO: hardcode*/ 50 - 2 {
+				rows.push(start..i);
+				(start, width) = (i, 0);
+			}
+			width += Self::char_width(c, width);
+		}
+		rows.push(start..self.count());
+		rows
+	}
+
+	// The row and the column of the char at `n`, which is on the next row at where a row wraps
+	pub(super) fn locate(&self, rows: &[Range<usize>], n: usize) -> (usize, usize) {
+		let row = rows.iter().rposition(|r| r.start <= n).unwrap_or(0);
+		(row, self.width(rows[row].start..n.min(rows[row].end)))
+	}
+
+	// The char in `row` at the column `col`, or the end of the row if it's shorter
+	pub(super) fn at(&self, row: Range<usize>, col: usize) -> usize {
+		let mut width = 0;
+		for (i, c) in self.value.chars().enumerate().skip(row.start).take(row.len()) {
+			width += Self::char_width(c, width);
+			if width > col {
+				return i;
+			}
+		}
+		row.end
+	}
+
+	// The text of `row` with the tabs expanded to spaces
+	pub(super) fn expand(&self, row: Range<usize>) -> String {
+		let mut s = String::new();
+		for c in self.value.chars().skip(row.start).take(row.len()) {
+			match c {
+				'\t' => s.push_str(&" ".repeat(Self::char_width(c, s.width()))),
+				c => s.push(c),
+			}
+		}
+		s
+	}
+
+	#[inline]
+	pub(super) fn width(&self, range: Range<usize>) -> usize {
+		let chars = self.value.chars().skip(range.start).take(range.len());
+		chars.fold(0, |w, c| w + Self::char_width(c, w))
+	}
+
+	// A tab goes to the next stop of every 4 columns
+	#[inline]
+	fn char_width(c: char, col: usize) -> usize {
+		if c == '\t' { 4 - col % 4 } else { c.width().unwrap_or(0) }
+	}
+
 	#[inline]
 	pub(super) fn find_window(s: &str, offset: usize) -> Range<usize> {
 		let mut width = 0;
@@ -107,3 +169,17 @@ impl InputSnap {
 		*v.first().unwrap()..v.last().unwrap() + 1
 	}
 }
+
+#[test]
+fn test_rows() {
+	let snap = InputSnap::new(format!("echo\tone\n{}中文\n", "a".repeat(46)));
+	let rows = snap.rows();
+	assert_eq!(rows, [0..8, 9..55, 55..57, 58..58]);
+	assert_eq!(snap.expand(rows[0].clone()), "echo    one");
+	assert_eq!(snap.locate(&rows, 5), (0, 8));
+	assert_eq!(snap.locate(&rows, 55), (2, 0));
+	assert_eq!(snap.locate(&rows, 57), (2, 4));
+	assert_eq!(snap.at(rows[2].clone(), 1), 55);
+	assert_eq!(snap.at(rows[2].clone(), 3), 56);
+	assert_eq!(snap.at(rows[0].clone(), 9), 6);
+}
@@ -7,21 +7,34 @@
 )]
 
 mod blocker;
+mod document;
 mod event;
 pub mod external;
 pub mod files;
 pub mod help;
+mod hexdump;
 mod highlighter;
+mod hooks;
 pub mod input;
 pub mod manager;
 pub mod position;
+mod reloader;
 pub mod select;
+pub mod sftp;
 pub mod tasks;
 pub mod which;
 
 pub use blocker::*;
+pub use document::*;
 pub use event::*;
+pub use hexdump::*;
 pub use highlighter::*;
+pub use hooks::*;
 pub use position::*;
+use reloader::*;
 
-pub fn init() { init_blocker(); }
+pub fn init() {
+	init_blocker();
+	init_hooks();
+	init_reloader();
+}
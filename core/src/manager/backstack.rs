@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+
+use config::BOOT;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+// How many of the directories left are kept to go back to, and how many of the latest of them
+// are kept across restarts
+const BACKSTACK_SIZE: usize = 100;
+const BACKSTACK_SAVED: usize = 20;
+
+// The directories visited, to go back and forward through like a browser,
+// each with the file hovered and the ones selected in it when it was left
+#[derive(Default)]
+pub(super) struct Backstack {
+	back:    Vec<Visit>,
+	forward: Vec<Visit>,
+	// Whether it's the one kept across restarts, of the tab it's loaded into, and the only one saved
+	kept:    bool,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub(super) struct Visit {
+	pub(super) cwd:      PathBuf,
+	pub(super) hovered:  Option<PathBuf>,
+	pub(super) selected: Vec<PathBuf>,
+}
+
+impl Backstack {
+	#[inline]
+	fn path() -> PathBuf { BOOT.state_dir.join("backstack.json") }
+
+	pub(super) fn load() -> Self {
+		let back = std::fs::read_to_string(Self::path())
+			.ok()
+			.and_then(|s| serde_json::from_str(&s).ok())
+			.unwrap_or_default();
+		Self { back, forward: vec![], kept: true }
+	}
+
+	// Leaves `here` for somewhere new, where the directories gone back from can't be gone forward to
+	pub(super) fn push(&mut self, here: Visit) {
+		self.forward.clear();
+		match self.back.last_mut() {
+			Some(last) if last.cwd == here.cwd => *last = here,
+			_ => self.back.push(here),
+		}
+		self.back.drain(..self.back.len().saturating_sub(BACKSTACK_SIZE));
+	}
+
+	// The latest directory left that still exists, leaving `here` to go forward to
+	#[inline]
+	pub(super) fn back(&mut self, here: Visit) -> Option<Visit> {
+		Self::travel(&mut self.back, &mut self.forward, here)
+	}
+
+	#[inline]
+	pub(super) fn forward(&mut self, here: Visit) -> Option<Visit> {
+		Self::travel(&mut self.forward, &mut self.back, here)
+	}
+
+	fn travel(from: &mut Vec<Visit>, to: &mut Vec<Visit>, here: Visit) -> Option<Visit> {
+		while let Some(there) = from.pop() {
+			if there.cwd != here.cwd && there.cwd.is_dir() {
+				to.push(here);
+				return Some(there);
+			}
+		}
+		None
+	}
+
+	pub(super) fn save(&self) {
+		if !self.kept {
+			return;
+		}
+
+		let tail = &self.back[self.back.len().saturating_sub(BACKSTACK_SAVED)..];
+		let Ok(s) = serde_json::to_string(tail) else {
+			return;
+		};
+		tokio::spawn(async move {
+			if let Err(e) = tokio::fs::write(Self::path(), s).await {
+				warn!("failed to save the directory history: {e}");
+			}
+		});
+	}
+}
+
+#[test]
+fn test_backstack() {
+	let root = shared::TestDir::new("backstack");
+	let [a, b, c] = ["a", "b", "c"].map(|s| root.join(s));
+	for d in [&a, &b, &c] {
+		std::fs::create_dir_all(d).unwrap();
+	}
+	let visit = |cwd: &PathBuf| Visit { cwd: cwd.clone(), hovered: None, selected: vec![] };
+
+	let mut s = Backstack::default();
+	s.push(visit(&a));
+	s.push(Visit { hovered: Some(a.join("x")), ..visit(&a) });
+	s.push(visit(&b));
+	assert_eq!(s.back.len(), 2);
+
+	// Back from `c` to `b`, then `a` with the file hovered when it was left
+	assert_eq!(s.back(visit(&c)), Some(visit(&b)));
+	assert_eq!(s.back(visit(&b)).and_then(|v| v.hovered), Some(a.join("x")));
+	assert_eq!(s.back(visit(&a)), None);
+	assert_eq!(s.forward(visit(&a)), Some(visit(&b)));
+
+	// Somewhere new from `b` drops `c` to go forward to
+	s.push(visit(&b));
+	assert_eq!(s.forward(visit(&root.to_path_buf())), None);
+
+	// The ones gone are skipped
+	std::fs::remove_dir(&b).unwrap();
+	assert_eq!(s.back(visit(&c)), Some(visit(&a)));
+
+	for _ in 0..BACKSTACK_SIZE {
+		s.push(visit(&a));
+		s.push(visit(&c));
+	}
+	assert_eq!(s.back.len(), BACKSTACK_SIZE);
+}
@@ -0,0 +1,120 @@
+use std::{collections::BTreeMap, path::{Path, PathBuf}};
+
+use config::BOOT;
+use tracing::warn;
+
+// The keys of the bookmarks kept on their own, to the directory left last,
+// and to where the files were removed last
+pub const BOOKMARK_PREVIOUS: char = '`';
+pub const BOOKMARK_REMOVED: char = '\'';
+
+// The locations marked under a single key, which `bookmark_jump` goes back to, kept across restarts
+#[derive(Default)]
+pub struct Bookmarks {
+	inner:   BTreeMap<char, PathBuf>,
+	visited: Option<PathBuf>,
+}
+
+impl Bookmarks {
+	#[inline]
+	fn path() -> PathBuf { BOOT.state_dir.join("bookmarks.json") }
+
+	pub fn load() -> Self {
+		let inner = std::fs::read_to_string(Self::path())
+			.ok()
+			.and_then(|s| serde_json::from_str(&s).ok())
+			.unwrap_or_default();
+		Self { inner, visited: None }
+	}
+
+	#[inline]
+	pub fn get(&self, key: char) -> Option<&Path> { self.inner.get(&key).map(|p| p.as_path()) }
+
+	// The bookmarks with whether the target still exists, the ones kept on their own first
+	pub fn list(&self) -> Vec<(char, &Path, bool)> {
+		let mut list: Vec<_> = self.inner.iter().map(|(&k, p)| (k, p.as_path(), p.exists())).collect();
+		list.sort_by_key(|&(k, ..)| (k != BOOKMARK_PREVIOUS && k != BOOKMARK_REMOVED, k));
+		list
+	}
+
+	pub fn set(&mut self, key: char, path: PathBuf) -> bool {
+		if self.inner.get(&key) == Some(&path) {
+			return false;
+		}
+		self.inner.insert(key, path);
+		true
+	}
+
+	#[inline]
+	pub fn delete(&mut self, key: char) -> bool { self.inner.remove(&key).is_some() }
+
+	// Forgets the ones whose target is gone
+	pub fn prune(&mut self) -> bool {
+		let len = self.inner.len();
+		self.inner.retain(|_, p| p.exists());
+		len != self.inner.len()
+	}
+
+	// Marks the directory left as the previous one, once the current one changes
+	pub fn visit(&mut self, cwd: &Path) -> bool {
+		match self.visited.replace(cwd.to_owned()) {
+			Some(prev) if prev != cwd => self.set(BOOKMARK_PREVIOUS, prev),
+			_ => false,
+		}
+	}
+
+	pub fn save(&self) {
+		let Ok(s) = serde_json::to_string(&self.inner) else {
+			return;
+		};
+		tokio::spawn(async move {
+			if let Err(e) = tokio::fs::write(Self::path(), s).await {
+				warn!("failed to save the bookmarks: {e}");
+			}
+		});
+	}
+
+	// A key to mark with by hand, which is a single character other than the ones kept on their own
+	pub fn validate_key(key: &str) -> Result<char, String> {
+		let mut it = key.chars();
+		match (it.next(), it.next()) {
+			(Some(BOOKMARK_PREVIOUS), None) => {
+				Err("The backtick is kept for the previous directory".to_owned())
+			}
+			(Some(BOOKMARK_REMOVED), None) => Err("The quote is kept for the removed files".to_owned()),
+			(Some(c), None) if c.is_whitespace() => Err("The key can't be a whitespace".to_owned()),
+			(Some(c), None) => Ok(c),
+			_ => Err("The key must be a single character".to_owned()),
+		}
+	}
+}
+
+#[test]
+fn test_bookmarks() {
+	let dir = shared::TestDir::new("bookmarks");
+
+	let mut b = Bookmarks::default();
+	assert!(!b.visit(&dir));
+	assert!(!b.visit(&dir));
+	assert!(b.visit(&dir.join("gone")));
+	assert_eq!(b.get(BOOKMARK_PREVIOUS), Some(&*dir));
+
+	assert!(b.set('a', dir.join("gone")));
+	assert!(!b.set('a', dir.join("gone")));
+	assert!(b.set(BOOKMARK_REMOVED, dir.to_path_buf()));
+	let keys: Vec<_> = b.list().into_iter().map(|(k, _, exists)| (k, exists)).collect();
+	assert_eq!(keys, [(BOOKMARK_REMOVED, true), (BOOKMARK_PREVIOUS, true), ('a', false)]);
+
+	let s = serde_json::to_string(&b.inner).unwrap();
+	let inner: BTreeMap<char, PathBuf> = serde_json::from_str(&s).unwrap();
+	assert_eq!(inner, b.inner);
+
+	assert!(b.prune());
+	assert_eq!(b.get('a'), None);
+	assert!(!b.prune());
+
+	assert_eq!(Bookmarks::validate_key("a"), Ok('a'));
+	assert!(Bookmarks::validate_key("ab").is_err());
+	assert!(Bookmarks::validate_key("`").is_err());
+	assert!(Bookmarks::validate_key("").is_err());
+}
@@ -1,8 +1,8 @@
 use config::MANAGER;
 use ratatui::layout::Rect;
-use shared::Url;
+use shared::{xattr_names, Url};
 
-use crate::{emit, files::{File, Files, FilesOp}};
+use crate::{emit, files::{File, Files, FilesOp, FilesSorter, Filter, QuickFilter}, Position};
 
 #[derive(Default)]
 pub struct Folder {
@@ -14,6 +14,7 @@ pub struct Folder {
 
 	pub page:    usize,
 	pub hovered: Option<File>,
+	pub branch:  Option<String>,
 }
 
 impl From<Url> for Folder {
@@ -26,16 +27,84 @@ impl From<&Url> for Folder {
 
 impl Folder {
 	pub fn update(&mut self, op: FilesOp) -> bool {
+		let order = self.files.order();
 		let b = match op {
 			FilesOp::Full(_, items) => self.files.update_full(items),
 			FilesOp::Part(_, ticket, items) => self.files.update_part(ticket, items),
 			FilesOp::Size(_, items) => self.files.update_size(items),
+			FilesOp::SizePartial(_, items) => self.files.update_size_partial(items),
+			FilesOp::SizeCut(_, items) => self.files.update_size_cut(items),
+			FilesOp::Children(_, items) => self.files.update_children(items),
+			FilesOp::Ranks(_, items) => self.files.update_ranks(items),
+			FilesOp::Git(_, items) => self.files.update_git(items),
+			FilesOp::Branch(_, branch) => return self.set_branch(branch),
+			FilesOp::Xattrs(_, items) => self.files.update_xattrs(items),
+			FilesOp::Expand(_, dir, items) => self.files.update_expand(dir, items),
 			_ => unreachable!(),
 		};
 		if !b {
 			return false;
 		}
 
+		self.repos();
+		self.sorted(order);
+		true
+	}
+
+	pub fn set_filter(&mut self, filter: Option<Filter>) -> bool {
+		let order = self.files.order();
+		if !self.files.set_filter(filter) {
+			return false;
+		}
+
+		self.repos();
+		self.sorted(order);
+		true
+	}
+
+	pub fn set_quick(&mut self, quick: Option<QuickFilter>) -> bool {
+		let order = self.files.order();
+		if !self.files.set_quick(quick) {
+			return false;
+		}
+
+		self.repos();
+		self.sorted(order);
+		true
+	}
+
+	pub fn set_files_attrs(
+		&mut self,
+		show_hidden: bool,
+		show_ignored: bool,
+		sorter: FilesSorter,
+	) -> bool {
+		let order = self.files.order();
+		let mut b = self.files.set_show_hidden(show_hidden);
+		b |= self.files.set_show_ignored(show_ignored);
+		b |= self.files.set_sorter(sorter);
+
+		self.sorted(order);
+		b
+	}
+
+	fn set_branch(&mut self, branch: Option<String>) -> bool {
+		if self.branch == branch {
+			return false;
+		}
+
+		self.branch = branch;
+		true
+	}
+
+	// Tells that the files are in another order than `order`, once they're sorted again
+	fn sorted(&self, order: u64) {
+		if self.files.order() != order {
+			emit!(Sorted(self.cwd.clone(), self.files.sorter().by, self.files.order()));
+		}
+	}
+
+	fn repos(&mut self) {
 		let max = self.files.len().saturating_sub(1);
 		self.offset = self.offset.min(max);
 		self.cursor = self.cursor.min(max);
@@ -43,6 +112,18 @@ impl Folder {
 
 		self.hover_repos();
 		self.hovered = self.files.duplicate(self.cursor);
+	}
+
+	// Collapses `dir` in the tree, or all of the directories if not given, with the cursor on it
+	pub fn collapse(&mut self, dir: Option<&Url>) -> bool {
+		if !self.files.collapse(dir) {
+			return false;
+		}
+
+		self.repos();
+		if let Some(dir) = dir {
+			self.hover(dir);
+		}
 		true
 	}
 
@@ -113,6 +194,13 @@ impl Folder {
 		self.hover(&self.hovered.as_ref().map(|h| h.url_owned()).unwrap_or_default())
 	}
 
+	// Puts the view back as it was, and the cursor on `file` once it's loaded,
+	// or on the one nearest to where it was if `file` is gone
+	pub fn hover_restore(&mut self, file: File, offset: usize, cursor: usize) {
+		(self.offset, self.cursor) = (offset, cursor);
+		self.hovered = Some(file);
+	}
+
 	pub fn hover_force(&mut self, file: File) -> bool {
 		if self.hover(file.url()) {
 			return true;
@@ -139,12 +227,40 @@ impl Folder {
 		&self.files[start..end]
 	}
 
-	pub fn rect_current(&self, url: &Url) -> Option<Rect> {
-		let y = self.files.position(url)? - self.offset;
+	// Probes the extended attributes of the files on the page that aren't yet,
+	// rather than all of them, for the directories with lots of files
+	pub fn probe_xattrs(&self) {
+		let targets: Vec<_> = self
+			.paginate()
+			.iter()
+			.filter(|f| self.files.xattrs(f.url()).is_none())
+			.map(|f| f.url_owned())
+			.collect();
+		if targets.is_empty() {
+			return;
+		}
+
+		let cwd = self.cwd.clone();
+		tokio::task::spawn_blocking(move || {
+			let items = targets
+				.into_iter()
+				.map(|u| {
+					let names = xattr_names(&u);
+					(u, names)
+				})
+				.collect();
+			emit!(Files(FilesOp::Xattrs(cwd, items)));
+		});
+	}
 
-		let mut rect = MANAGER.layout.folder_rect();
-		rect.y = rect.y.saturating_sub(1) + y as u16;
-		rect.height = 1;
-		Some(rect)
+	// The row on the screen of the file at `index`, none if it's scrolled out of view
+	#[inline]
+	pub fn rect_index(&self, index: usize) -> Option<Rect> {
+		Position::row(MANAGER.layout.folder_rect(), self.offset, index)
+	}
+
+	#[inline]
+	pub fn rect_current(&self, url: &Url) -> Option<Rect> {
+		self.rect_index(self.files.position(url)?)
 	}
 }
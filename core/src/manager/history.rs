@@ -0,0 +1,71 @@
+use indexmap::IndexMap;
+use shared::Url;
+
+use super::Folder;
+use crate::files::File;
+
+// How many of the folders left, or previewed, are kept with their files loaded,
+// and how many of the older ones are kept with only the file hovered and where the view was
+const HISTORY_FOLDERS: usize = 50;
+const HISTORY_HOVERED: usize = 1000;
+
+// The folders gone out of view, the latest last, so that the cursor is back on the same file
+// once they're entered again, or the one nearest to where it was if that's gone
+#[derive(Default)]
+pub struct History {
+	folders: IndexMap<Url, Folder>,
+	hovered: IndexMap<Url, (File, usize, usize)>,
+}
+
+impl History {
+	#[inline]
+	pub fn get(&self, url: &Url) -> Option<&Folder> { self.folders.get(url) }
+
+	#[inline]
+	pub fn get_mut(&mut self, url: &Url) -> Option<&mut Folder> { self.folders.get_mut(url) }
+
+	#[inline]
+	pub fn iter(&self) -> impl Iterator<Item = &Folder> { self.folders.values() }
+
+	#[inline]
+	pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Folder> { self.folders.values_mut() }
+
+	pub fn get_or_new(&mut self, url: &Url) -> &mut Folder {
+		if !self.folders.contains_key(url) {
+			let folder = self.take(url);
+			self.insert(folder);
+		}
+		self.folders.get_mut(url).unwrap()
+	}
+
+	pub fn insert(&mut self, folder: Folder) {
+		self.folders.shift_remove(&folder.cwd);
+		self.folders.insert(folder.cwd.clone(), folder);
+
+		while self.folders.len() > HISTORY_FOLDERS {
+			let Some((url, folder)) = self.folders.shift_remove_index(0) else {
+				break;
+			};
+			let (offset, cursor) = (folder.offset(), folder.cursor());
+			if let Some(h) = folder.hovered {
+				self.hovered.shift_remove(&url);
+				self.hovered.insert(url, (h, offset, cursor));
+			}
+		}
+		self.hovered.drain(..self.hovered.len().saturating_sub(HISTORY_HOVERED));
+	}
+
+	// The folder of `url` as it was left, or a new one to be loaded,
+	// with the cursor to go back on the file hovered in it before
+	pub fn take(&mut self, url: &Url) -> Folder {
+		if let Some(folder) = self.folders.shift_remove(url) {
+			return folder;
+		}
+
+		let mut folder = Folder::from(url);
+		if let Some((hovered, offset, cursor)) = self.hovered.shift_remove(url) {
+			folder.hover_restore(hovered, offset, cursor);
+		}
+		folder
+	}
+}
@@ -0,0 +1,140 @@
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local};
+use config::{keymap::{Exec, KeymapLayer}, MANAGER};
+
+use super::Manager;
+use crate::{emit, files::File, input::InputOpt};
+
+impl Manager {
+	// The details of the hovered file shown in place of its preview, as the names and the values,
+	// all from its metadata at hand but the names of its owner and group, looked up once each
+	pub fn info(&self) -> Vec<(&'static str, String)> {
+		let Some(file) = self.hovered() else {
+			return vec![];
+		};
+
+		let size = match file.is_dir() {
+			true => self.current().files.size(file.url()),
+			false => Some(file.length()),
+		};
+		let size = size.map_or_else(
+			|| "not calculated yet".to_owned(),
+			|n| format!("{n} bytes ({})", MANAGER.readable_size(n).trim()),
+		);
+		let mime = if file.is_dir() { None } else { self.mimetype.get(file.url()) };
+
+		let mut rows = vec![
+			("Path", file.url().display().to_string()),
+			("Size", size),
+			("Type", mime.cloned().unwrap_or_else(|| "unknown".to_owned())),
+		];
+		#[cfg(unix)]
+		{
+			let meta = file.meta();
+			let mode = meta.mode();
+			rows.push(("Mode", format!("{} ({:03o})", shared::file_mode(mode), mode & 0o7777)));
+			let (uid, gid) = (meta.uid(), meta.gid());
+			rows.push(("Owner", format!("{} ({uid}:{gid})", owner(uid, gid))));
+		}
+
+		let time = |t: Option<SystemTime>| {
+			t.map(|t| DateTime::<Local>::from(t).format("%Y-%m-%d %H:%M:%S").to_string())
+		};
+		for (name, t) in [
+			("Modified", time(file.meta().modified())),
+			("Accessed", time(file.meta().accessed())),
+			("Created", time(file.meta().created())),
+		] {
+			if let Some(t) = t {
+				rows.push((name, t));
+			}
+		}
+
+		if file.is_link() {
+			let to = file.link_to().map_or_else(|| "unreadable".to_owned(), |u| u.display().to_string());
+			let missing = if file.is_orphan() { " (missing)" } else { "" };
+			rows.push(("Link to", format!("{to}{missing}")));
+		}
+		#[cfg(unix)]
+		if !file.url().is_sftp() {
+			let meta = file.meta();
+			rows.push(("Inode", format!("{} on {}, {} links", meta.ino(), meta.dev(), meta.nlink())));
+		}
+		rows
+	}
+
+	// Changes the mode or the owner of the files `chmod` or `chown` works on, with those of the
+	// hovered file to start with
+	pub fn info_edit(&self, field: &str) -> bool {
+		let Some(file) = self.hovered() else {
+			return false;
+		};
+		let Some((cmd, title, value)) = Self::info_field(file, field) else {
+			return false;
+		};
+
+		tokio::spawn(async move {
+			let mut result = emit!(Input(InputOpt::top(title).with_value(value).with_history(cmd)));
+			if let Some(Ok(s)) = result.recv().await {
+				emit!(Call(Exec::call(cmd, vec![s]).vec(), KeymapLayer::Manager));
+			}
+		});
+		false
+	}
+
+	#[cfg(unix)]
+	fn info_field(file: &File, field: &str) -> Option<(&'static str, &'static str, String)> {
+		let meta = file.meta();
+		Some(match field {
+			"mode" => ("chmod", "Chmod (755, u+x, go-w):", format!("{:03o}", meta.mode() & 0o7777)),
+			"owner" => ("chown", "Chown (user:group):", owner(meta.uid(), meta.gid())),
+			_ => return None,
+		})
+	}
+
+	#[cfg(not(unix))]
+	fn info_field(_: &File, field: &str) -> Option<(&'static str, &'static str, String)> {
+		if matches!(field, "mode" | "owner") {
+			let text = "Changing the mode or the owner isn't supported on this platform".to_owned();
+			emit!(Message(text, true));
+		}
+		None
+	}
+}
+
+// The names of the user and the group, or their IDs for the ones unknown, remembered for the
+// lookups of the system can be slow, such as of a directory service
+#[cfg(unix)]
+pub(super) fn owner(uid: u32, gid: u32) -> String {
+	use std::{collections::BTreeMap, ffi::CStr};
+
+	use parking_lot::Mutex;
+
+	static NAMES: Mutex<BTreeMap<(bool, u32), String>> = Mutex::new(BTreeMap::new());
+
+	let mut names = NAMES.lock();
+	let mut name = |group: bool, id: u32| {
+		names
+			.entry((group, id))
+			.or_insert_with(|| unsafe {
+				let name = if group {
+					let gr = libc::getgrgid(id);
+					if gr.is_null() { None } else { Some(CStr::from_ptr((*gr).gr_name)) }
+				} else {
+					let pw = libc::getpwuid(id);
+					if pw.is_null() { None } else { Some(CStr::from_ptr((*pw).pw_name)) }
+				};
+				name.map_or_else(|| id.to_string(), |n| n.to_string_lossy().into_owned())
+			})
+			.clone()
+	};
+	format!("{}:{}", name(false, uid), name(true, gid))
+}
+
+#[cfg(unix)]
+#[test]
+fn test_owner() {
+	assert_eq!(owner(0, 0).split(':').next(), Some("root"));
+	assert_eq!(owner(4000000000, 4000000000), "4000000000:4000000000");
+}
@@ -0,0 +1,78 @@
+use anyhow::bail;
+use shared::MimeKind;
+
+use crate::files::File;
+
+// The kinds of files `arrow_kind` moves among, skipping the others
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JumpKind {
+	Dir,
+	File,
+	Image,
+	Archive,
+	Executable,
+}
+
+impl TryFrom<String> for JumpKind {
+	type Error = anyhow::Error;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		Ok(match s.as_str() {
+			"dir" => Self::Dir,
+			"file" => Self::File,
+			"image" => Self::Image,
+			"archive" => Self::Archive,
+			"executable" => Self::Executable,
+			_ => bail!("invalid kind to arrow to: {s}"),
+		})
+	}
+}
+
+impl JumpKind {
+	// Whether the file is of the kind, where the images and the archives are told by the type of
+	// them, as known so far, or by the extension if it isn't yet
+	pub fn matches(self, file: &File, mime: Option<&str>) -> bool {
+		match self {
+			Self::Dir => file.is_dir(),
+			Self::File => !file.is_dir(),
+			Self::Image => mime.is_some_and(|m| MimeKind::new(m) == MimeKind::Image),
+			Self::Archive => mime.is_some_and(|m| MimeKind::new(m) == MimeKind::Archive),
+			Self::Executable => file.is_executable(),
+		}
+	}
+}
+
+// The step from `cursor` to the next of the items that `f` tells, or the previous one, going on
+// from the other end if `wrap`, none if there isn't another one
+pub(super) fn jump_step<T>(
+	items: &[T],
+	cursor: usize,
+	prev: bool,
+	wrap: bool,
+	f: impl Fn(&T) -> bool,
+) -> Option<isize> {
+	let len = items.len() as isize;
+	let cursor = cursor as isize;
+	(1..len)
+		.map(|i| if prev { -i } else { i })
+		.take_while(|&step| wrap || (0..len).contains(&(cursor + step)))
+		.find(|&step| f(&items[(cursor + step).rem_euclid(len) as usize]))
+}
+
+#[test]
+fn test_jump_step() {
+	let items = [true, false, false, true, false];
+	let f = |b: &bool| *b;
+
+	assert_eq!(jump_step(&items, 0, false, false, f), Some(3));
+	assert_eq!(jump_step(&items, 3, false, false, f), None);
+	assert_eq!(jump_step(&items, 3, false, true, f), Some(2));
+	assert_eq!(jump_step(&items, 3, true, false, f), Some(-3));
+	assert_eq!(jump_step(&items, 0, true, false, f), None);
+	assert_eq!(jump_step(&items, 0, true, true, f), Some(-2));
+	assert_eq!(jump_step(&items, 2, true, true, f), Some(-2));
+
+	// The one hovered is never jumped to
+	assert_eq!(jump_step(&[true], 0, false, true, f), None);
+	assert_eq!(jump_step::<bool>(&[], 0, false, true, f), None);
+}
@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+
+use shared::Url;
+
+use super::Manager;
+use crate::emit;
+
+// The files a paste is to end up as, all in `dir`, to be selected as they're done and show up
+// in it, matched by their paths so it keeps up with the sorting after each refresh
+#[derive(Default)]
+pub struct Landing {
+	dir:    Url,
+	// Yet to be done, and done but yet to show up
+	todo:   HashSet<Url>,
+	ready:  HashSet<Url>,
+	landed: Vec<Url>,
+}
+
+impl Landing {
+	fn new(targets: Vec<Url>) -> Option<Self> {
+		let dir = targets.first()?.parent_url()?;
+		let todo = targets.into_iter().filter(|t| t.parent_url().as_ref() == Some(&dir)).collect();
+		Some(Self { dir, todo, ..Default::default() })
+	}
+
+	#[inline]
+	fn done(&mut self, target: &Url) -> bool {
+		self.todo.remove(target) && self.ready.insert(target.clone())
+	}
+
+	// Takes the ones done that `shown` tells are in view
+	fn take(&mut self, shown: impl Fn(&Url) -> bool) -> Vec<Url> {
+		let taken: Vec<_> = self.ready.iter().filter(|&u| shown(u)).cloned().collect();
+		for url in &taken {
+			self.ready.remove(url);
+		}
+		self.landed.extend(taken.iter().cloned());
+		taken
+	}
+
+	#[inline]
+	fn finished(&self) -> bool { self.todo.is_empty() && self.ready.is_empty() }
+}
+
+impl Manager {
+	#[inline]
+	pub fn land(&mut self, targets: Vec<Url>) { self.landing = Landing::new(targets); }
+
+	pub fn landed(&mut self, target: Url) -> bool {
+		if !self.landing.as_mut().is_some_and(|l| l.done(&target)) {
+			return false;
+		}
+		self.land_apply()
+	}
+
+	// Selects the files pasted that have shown up in the current directory, the ones selected
+	// before deselected, and hovers the first of them once all of them have
+	pub(super) fn land_apply(&mut self) -> bool {
+		let Some(mut landing) = self.landing.take() else {
+			return false;
+		};
+
+		let current = self.current_mut();
+		if landing.dir != current.cwd {
+			// Left for the directory to be entered, until the paste is done
+			if !landing.todo.is_empty() {
+				self.landing = Some(landing);
+			}
+			return false;
+		}
+
+		let first = landing.landed.is_empty();
+		let taken = landing.take(|u| current.files.position(u).is_some());
+		if taken.is_empty() {
+			self.landing = Some(landing);
+			return false;
+		}
+
+		if first {
+			current.files.select_all(Some(false));
+		}
+		for url in &taken {
+			current.files.select(url, Some(true));
+		}
+
+		if !landing.finished() {
+			self.landing = Some(landing);
+			return true;
+		}
+
+		let first = landing.landed.iter().filter_map(|u| current.files.position(u)).min();
+		if let Some(url) = first.map(|i| current.files[i].url_owned()) {
+			current.hover(&url);
+			emit!(Hover);
+		}
+		true
+	}
+}
+
+#[test]
+fn test_landing() {
+	let url = |s: &str| Url::from(std::path::PathBuf::from(s));
+	assert!(Landing::new(vec![]).is_none());
+
+	let mut l = Landing::new(vec![url("/d/a"), url("/d/b"), url("/e/c")]).unwrap();
+	assert_eq!(l.dir, url("/d"));
+	assert_eq!(l.todo.len(), 2);
+
+	assert!(!l.done(&url("/e/c")));
+	assert!(l.done(&url("/d/a")) && !l.done(&url("/d/a")));
+	assert!(l.take(|_| false).is_empty());
+	assert_eq!(l.take(|_| true), [url("/d/a")]);
+	assert!(!l.finished());
+
+	assert!(l.done(&url("/d/b")));
+	assert_eq!(l.take(|u| u == &url("/d/b")), [url("/d/b")]);
+	assert!(l.finished());
+	assert_eq!(l.landed, [url("/d/a"), url("/d/b")]);
+}
@@ -0,0 +1,174 @@
+use anyhow::{bail, Result};
+use config::MANAGER;
+use shared::{expand_path, Url};
+use unicode_width::UnicodeWidthStr;
+
+use super::Tab;
+use crate::{emit, external, files::File, input::InputOpt, sftp};
+
+// How the files are listed as text, one per line
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ListingFormat {
+	#[default]
+	Names,
+	Paths,
+	// A table like `ls -l` has it, with the mode, the number of links and the owner of each
+	Long,
+}
+
+impl TryFrom<String> for ListingFormat {
+	type Error = anyhow::Error;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		Ok(match s.as_str() {
+			"name" => Self::Names,
+			"path" => Self::Paths,
+			"long" => Self::Long,
+			_ => bail!("invalid listing format: {s}"),
+		})
+	}
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ListingOpt {
+	pub format: ListingFormat,
+	// Whether the directories are followed by a `/`
+	pub slash:  bool,
+	// Whether the table has the sizes, and the times they were modified, as well
+	pub size:   bool,
+	pub date:   bool,
+	// Whether it's saved to a file asked for, rather than copied to the clipboard
+	pub save:   bool,
+}
+
+impl Tab {
+	// The files of the current directory as text, the ones in view, as they're sorted and filtered
+	pub fn copy_listing(&self, opt: ListingOpt) -> bool {
+		let (files, cwd) = (&self.current.files, self.current.cwd.clone());
+		if files.is_empty() {
+			return false;
+		}
+
+		let (text, n) = (listing(files, &cwd, opt), files.len());
+		if !opt.save {
+			let (text, error) = match futures::executor::block_on(external::clipboard_set(text)) {
+				Ok(()) => (format!("Copied the listing of {n} files"), false),
+				Err(e) => (format!("Failed to copy the listing: {e}"), true),
+			};
+			emit!(Message(text, error));
+			return false;
+		}
+
+		tokio::spawn(async move {
+			let opt = InputOpt::top("Save the listing to:").with_value("listing.txt");
+			let Some(Ok(name)) = emit!(Input(opt)).recv().await else {
+				return;
+			};
+
+			let url = cwd.join(expand_path(&name));
+			let (text, error) = match save(&url, &text).await {
+				Ok(()) => (format!("Saved the listing of {n} files to {}", url.display()), false),
+				Err(e) => (format!("Failed to save the listing: {e}"), true),
+			};
+			emit!(Message(text, error));
+		});
+		false
+	}
+}
+
+// To a local file, or a remote one
+async fn save(url: &Url, text: &str) -> Result<()> {
+	let mut w = sftp::Writer::create(url).await?;
+	w.write(text.as_bytes()).await?;
+	w.close().await
+}
+
+// One line for each file, where those of the expanded directories are by their paths relative to
+// the directory, and the columns of the table are lined up, with the numbers on the right
+fn listing(files: &[File], cwd: &Url, opt: ListingOpt) -> String {
+	let name = |f: &File| {
+		let mut s = match opt.format {
+			ListingFormat::Paths => f.url().display().to_string(),
+			_ => f.url().strip_prefix(cwd).unwrap_or(f.url()).display().to_string(),
+		};
+		if opt.slash && f.is_dir() {
+			s.push('/');
+		}
+		s
+	};
+	if opt.format != ListingFormat::Long {
+		return files.iter().map(|f| name(f) + "\n").collect();
+	}
+
+	let rows: Vec<Vec<(String, bool)>> = files
+		.iter()
+		.map(|f| {
+			let mut row = Vec::with_capacity(5);
+			#[cfg(unix)]
+			{
+				let meta = f.meta();
+				row.push((shared::file_mode(meta.mode()), false));
+				row.push((meta.nlink().to_string(), true));
+				row.push((super::info::owner(meta.uid(), meta.gid()), false));
+			}
+			if opt.size {
+				row.push((MANAGER.readable_size(f.length()).trim().to_owned(), true));
+			}
+			if opt.date {
+				let date = f.meta().modified().map(|t| MANAGER.readable_date(t));
+				row.push((date.unwrap_or_default(), false));
+			}
+			row
+		})
+		.collect();
+
+	let columns = rows.first().map_or(0, Vec::len);
+	let widths: Vec<_> =
+		(0..columns).map(|i| rows.iter().map(|r| r[i].0.width()).max().unwrap_or(0)).collect();
+
+	let mut s = String::new();
+	for (f, row) in files.iter().zip(rows) {
+		for ((cell, right), width) in row.into_iter().zip(&widths) {
+			let pad = " ".repeat(width - cell.width());
+			if right {
+				s.push_str(&format!("{pad}{cell} "));
+			} else {
+				s.push_str(&format!("{cell}{pad} "));
+			}
+		}
+		s.push_str(&name(f));
+		if let Some(to) = f.link_to() {
+			s.push_str(&format!(" -> {}", to.display()));
+		}
+		s.push('\n');
+	}
+	s
+}
+
+#[test]
+fn test_listing() {
+	let dir = shared::TestDir::new("listing");
+	std::fs::create_dir_all(dir.join("b")).unwrap();
+	std::fs::write(dir.join("a"), "a").unwrap();
+
+	let files = shared::block_on(async {
+		vec![
+			File::from(Url::from(dir.join("a"))).await.unwrap(),
+			File::from(Url::from(dir.join("b"))).await.unwrap(),
+		]
+	});
+	let cwd = Url::from(&*dir);
+	let listed = |opt| listing(&files, &cwd, opt);
+
+	assert_eq!(listed(ListingOpt::default()), "a\nb\n");
+	assert_eq!(listed(ListingOpt { slash: true, ..Default::default() }), "a\nb/\n");
+	let paths = ListingOpt { format: ListingFormat::Paths, ..Default::default() };
+	assert_eq!(listed(paths), format!("{}\n{}\n", dir.join("a").display(), dir.join("b").display()));
+
+	// Lined up, with the names last
+	let long = listed(ListingOpt { format: ListingFormat::Long, slash: true, ..Default::default() });
+	let lines: Vec<_> = long.lines().collect();
+	assert!(lines[0].starts_with("-rw") && lines[0].ends_with(" a"));
+	assert!(lines[1].starts_with('d') && lines[1].ends_with(" b/"));
+	assert_eq!(lines[0].len() + 1, lines[1].len());
+}
@@ -1,46 +1,110 @@
-use std::{collections::{BTreeMap, BTreeSet, HashMap, HashSet}, env, ffi::OsStr, io::{stdout, BufWriter, Write}, path::PathBuf};
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, env, ffi::{OsStr, OsString}, io::{stdout, BufWriter, Write}, path::{Path, PathBuf}, time::{Duration, Instant}};
 
-use anyhow::{anyhow, bail, Error, Result};
-use config::{OPEN, PREVIEW};
-use shared::{max_common_root, Defer, Term, Url, MIME_DIR};
+use anyhow::{bail, Result};
+use config::{boot::Pick, keymap::{Exec, KeymapLayer}, manager::{LayoutMode, SortRule}, open::{OpenEnter, Opener}, BOOT, KEYMAP, MANAGER, OPEN, PREVIEW, THEME};
+use shared::{disk_space, max_common_root, readable_path, readable_size, Defer, MimeKind, Term, Url, MIME_DIR};
 use tokio::{fs::{self, OpenOptions}, io::{stdin, AsyncReadExt, AsyncWriteExt}};
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
 
-use super::{Tab, Tabs, Watcher};
-use crate::{emit, external::{self, ShellOpt}, files::{File, FilesOp}, input::InputOpt, manager::Folder, select::SelectOpt, tasks::Tasks, Event, BLOCKER};
+use super::{jump_step, ruled, Bookmarks, DirOverride, DirOverrides, JumpKind, Landing, Mouse, Places, PreviewDiff, PreviewPin, Tab, Tabs, Watcher, Yanked, BOOKMARK_REMOVED};
+use crate::{emit, external::{self, ShellOpt}, highlighter_theme, Hook, set_highlighter_theme, files::{mime_by_ext, mimetypes, File, Files, FilesOp}, input::InputOpt, manager::Folder, select::SelectOpt, tasks::{Plan, Tasks}, which::palette, Event, BLOCKER};
 
 pub struct Manager {
-	tabs:   Tabs,
-	yanked: (bool, HashSet<Url>),
+	tabs:      Tabs,
+	yanked:    Yanked,
+	bookmarks: Bookmarks,
+	overrides: DirOverrides,
 
+	pub layout:        LayoutMode,
+	pub(super) places: Places,
+
+	// The count typed before a motion, e.g. the `5` of `5j`, with when its last digit was typed
+	count: Option<(usize, Instant)>,
+	pub(super) mouse: Mouse,
+
+	// A brief report of the last command shown in the status bar for a while, with whether it failed
+	message: Option<(String, bool, Instant)>,
+
+	// Whether the directories in view are watched, off for the mounts where the events are unreliable
+	watch:        bool,
 	watcher:      Watcher,
 	pub mimetype: HashMap<Url, String>,
+
+	// What the hooks were last run for, the directory entered, and the selection made in it
+	entered:  Option<Url>,
+	selected: Option<(Url, u64)>,
+
+	// The free and the total space of the filesystem the current directory is on
+	space: Option<(u64, u64)>,
+
+	// The files of the last paste, to be selected as they show up, see `follow_results`
+	pub(super) landing: Option<Landing>,
+	// The last command run, with the values entered for it, to be run again by `repeat`
+	pub(super) repeat:  Option<Exec>,
 }
 
 impl Manager {
 	pub fn make() -> Self {
-		Self {
-			tabs:   Tabs::make(),
-			yanked: Default::default(),
+		let mut manager = Self {
+			tabs:      Tabs::make(),
+			yanked:    Default::default(),
+			bookmarks: Bookmarks::load(),
+			overrides: DirOverrides::load(),
+
+			layout: MANAGER.layout_mode,
+			places: Places::load(),
+			count:  None,
+			mouse:  Mouse::new(MANAGER.mouse),
+
+			message: None,
 
+			watch:    MANAGER.watch,
 			watcher:  Watcher::start(),
 			mimetype: Default::default(),
+
+			entered:  None,
+			selected: None,
+
+			space:   None,
+			landing: None,
+			repeat:  None,
+		};
+
+		if manager.layout == LayoutMode::Commander {
+			manager.tabs.pair();
+		}
+		MANAGER.layout.set_places_width(if MANAGER.places { MANAGER.places_width } else { 0 });
+		manager.visited(&manager.cwd().clone());
+		manager
+	}
+
+	// The directory entered, told to zoxide, and kept for the one left to be marked as the previous
+	pub fn visited(&mut self, cwd: &Url) {
+		external::zoxide_add(cwd);
+		if cwd.is_regular() && self.bookmarks.visit(cwd) {
+			self.bookmarks.save();
 		}
 	}
 
 	pub fn refresh(&mut self) {
-		env::set_current_dir(self.cwd()).ok();
+		if !self.cwd().is_sftp() {
+			env::set_current_dir(self.cwd()).ok();
+		}
 
+		self.override_apply();
 		self.active_mut().apply_files_attrs(false);
+		self.space_update();
 
+		// The ones never read before are streamed, for the first of their files to be shown soon
+		let mut dirs = vec![(self.cwd().clone(), self.current().files.is_empty())];
 		if let Some(f) = self.parent() {
-			self.watcher.trigger_dirs(&[self.cwd(), &f.cwd]);
-		} else {
-			self.watcher.trigger_dirs(&[self.cwd()]);
+			dirs.push((f.cwd.clone(), f.files.is_empty()));
 		}
+		self.watcher.trigger_dirs(&dirs);
 		emit!(Hover);
 
 		let mut to_watch = BTreeSet::new();
-		for tab in self.tabs.iter() {
+		for tab in self.tabs.iter().filter(|_| self.watch) {
 			to_watch.insert(&tab.current.cwd);
 			if let Some(ref h) = tab.current.hovered {
 				if h.is_dir() {
@@ -54,44 +118,245 @@ impl Manager {
 		self.watcher.watch(to_watch);
 	}
 
-	pub fn peek(&mut self, sequent: bool, show_image: bool) -> bool {
-		let Some(hovered) = self.hovered().cloned() else {
-			return self.active_mut().preview_reset();
-		};
+	// Whether the current directory is to be read again now and then, as it isn't watched, where
+	// a remote one never is
+	#[inline]
+	pub fn polling(&self) -> bool {
+		let cwd = self.cwd();
+		cwd.is_sftp() || (cwd.is_regular() && !self.watcher.watched(cwd))
+	}
 
-		let url = hovered.url();
-		if !show_image {
+	// Reads the current directory again, and updates it only if any file in it has changed
+	pub fn poll(&self) {
+		if !self.polling() {
+			return;
+		}
+
+		let (url, stamps) = (self.cwd().clone(), self.current().files.stamps());
+		tokio::spawn(async move {
+			let Ok(rx) = Files::from_dir(&url).await else {
+				emit!(Files(FilesOp::IOErr(url)));
+				return;
+			};
+
+			let files: Vec<_> = UnboundedReceiverStream::new(rx).collect().await;
+			if files.len() != stamps.len() || files.iter().any(|f| !stamps.contains(&f.stamp())) {
+				emit!(Files(FilesOp::Full(url.clone(), files)));
+				Watcher::git_changed(&url).await;
+			}
+		});
+	}
+
+	// Switches to `mode`, or toggles between the `miller` and `commander` layouts if not given
+	pub fn set_layout(&mut self, mode: Option<LayoutMode>) -> bool {
+		let mode = mode.unwrap_or(match self.layout {
+			LayoutMode::Miller => LayoutMode::Commander,
+			LayoutMode::Commander => LayoutMode::Miller,
+		});
+		if mode == self.layout {
+			return false;
+		}
+
+		self.layout = mode;
+		if mode == LayoutMode::Commander {
+			self.tabs.pair();
 			self.active_mut().preview_reset_image();
 		}
+		emit!(Peek);
+		true
+	}
 
-		if hovered.is_dir() {
-			let position = self.active().history(url).map(|f| (f.offset(), f.files.len()));
-			self.active_mut().preview.folder(url, position, sequent);
+	// Watches the directories in view, or stops on all of them so that they're only read on `refresh`
+	pub fn set_watch(&mut self, state: Option<bool>) -> bool {
+		let state = state.unwrap_or(!self.watch);
+		if state == self.watch {
 			return false;
 		}
 
-		let Some(mime) = self.mimetype.get(url).cloned() else {
+		self.watch = state;
+		emit!(Refresh);
+		emit!(Message(format!("Watching {}", if state { "on" } else { "off" }), false));
+		false
+	}
+
+	// Adds a digit to the count, which is given up if nothing follows it for a while
+	pub fn count_push(&mut self, digit: usize) -> bool {
+		let count = self.count_take().unwrap_or(0).saturating_mul(10).saturating_add(digit);
+		if count > 0 {
+			self.count = Some((count.min(999_999), Instant::now()));
+		}
+		false
+	}
+
+	#[inline]
+	pub fn count_take(&mut self) -> Option<usize> {
+		self.count.take().filter(|(_, at)| at.elapsed() < Duration::from_secs(2)).map(|(n, _)| n)
+	}
+
+	pub fn message_set(&mut self, text: String, error: bool) -> bool {
+		self.message = Some((text, error, Instant::now()));
+		tokio::spawn(async {
+			tokio::time::sleep(Duration::from_secs(3)).await;
+			emit!(Render);
+		});
+		true
+	}
+
+	// Moves to the next file of the kind in view, or the previous one, where the types of the files
+	// not known yet are told by their extensions
+	pub fn arrow_kind(&mut self, kind: JumpKind, prev: bool, wrap: bool) -> bool {
+		let current = self.active().current();
+		let step = jump_step(&current.files, current.cursor(), prev, wrap, |f| {
+			let mime = self.mimetype.get(f.url()).map(|m| m.as_str()).or_else(|| mime_by_ext(f.url()));
+			kind.matches(f, mime)
+		});
+		step.is_some_and(|step| self.active_mut().arrow(step))
+	}
+
+	pub fn peek(&mut self, sequent: bool, show_image: bool) -> bool {
+		// The pinned file stays in the preview, whatever's hovered
+		let pinned = self.active().preview.pin().map(|p| (p.url.clone(), p.mime.clone()));
+		let hovered = self.hovered().cloned();
+		if pinned.is_none() && hovered.is_none() {
+			return self.active_mut().preview_reset();
+		} else if self.active().info() {
 			return false;
+		}
+
+		if !show_image {
+			self.active_mut().preview_reset_image();
+		}
+
+		let (url, mime) = match (pinned, hovered) {
+			(Some(pinned), _) => pinned,
+			(None, Some(hovered)) if hovered.is_dir() => {
+				let url = hovered.url();
+				let position = self.active().history(url).map(|f| (f.offset(), f.files.len()));
+				self.active_mut().preview.folder(url, position, sequent);
+				return false;
+			}
+			(None, Some(hovered)) => match self.mimetype.get(hovered.url()) {
+				Some(mime) => (hovered.url_owned(), mime.clone()),
+				None => return false,
+			},
+			(None, None) => return false,
 		};
 
 		if sequent {
-			self.active_mut().preview.sequent(url, &mime, show_image);
+			self.active_mut().preview.sequent(&url, &mime, show_image);
 		} else {
-			self.active_mut().preview.go(url, &mime, show_image);
+			self.active_mut().preview.go(&url, &mime, show_image);
 		}
 		false
 	}
 
+	// Keeps the hovered file in the preview while the others are hovered, or releases the one kept,
+	// toggles it if `state` is omitted
+	pub fn preview_pin(&mut self, state: Option<bool>) -> bool {
+		let pinned = self.active().preview.pin().is_some();
+		if !state.unwrap_or(!pinned) {
+			return self.active_mut().preview.set_pin(None);
+		} else if pinned {
+			return false;
+		}
+
+		let Some(hovered) = self.hovered().filter(|h| !h.is_dir()) else {
+			emit!(Message("Only a file can be pinned to the preview".to_owned(), true));
+			return false;
+		};
+		let Some(mime) = self.mimetype.get(hovered.url()).cloned() else {
+			return false;
+		};
+
+		let url = hovered.url_owned();
+		self.active_mut().preview.set_pin(Some(PreviewPin { url, mime }))
+	}
+
+	// Switches the theme of the highlighted previews, or toggles between `syntect_theme`
+	// and `syntect_theme_alt` if no `name` is given, e.g. a dark and a light one
+	pub fn syntect_theme(&mut self, name: Option<&str>) -> bool {
+		let name = name.map(ToOwned::to_owned).unwrap_or_else(|| {
+			let (theme, alt) = (&THEME.preview.syntect_theme, &THEME.preview.syntect_theme_alt);
+			let next = if highlighter_theme() == theme.to_string_lossy() { alt } else { theme };
+			next.to_string_lossy().into_owned()
+		});
+
+		if !set_highlighter_theme(&name) {
+			return false;
+		}
+		for tab in self.tabs.iter_mut() {
+			tab.preview_reset_text();
+		}
+		emit!(Peek);
+		true
+	}
+
+	// Takes on the theme changed in `theme.toml`, or keeps the one in use if it's not valid
+	pub fn theme_reload(&mut self) -> bool {
+		if let Err(e) = config::reload_theme() {
+			emit!(Message(format!("Theme not reloaded: {e}"), true));
+			return false;
+		}
+
+		set_highlighter_theme(&THEME.preview.syntect_theme.to_string_lossy());
+		for tab in self.tabs.iter_mut() {
+			tab.preview_reset_text();
+		}
+		emit!(Peek);
+		true
+	}
+
 	pub fn yank(&mut self, cut: bool) -> bool {
-		self.yanked.0 = cut;
-		self.yanked.1 = self.selected().into_iter().map(|f| f.url_owned()).collect();
-		false
+		let urls = self.selected().into_iter().map(|f| f.url_owned()).collect();
+		self.yanked.push(cut, urls)
 	}
 
-	pub fn quit(&self, tasks: &Tasks) -> bool {
+	// Makes an older set of the files yanked the one to paste, or a newer one for a negative `step`
+	pub fn yank_cycle(&mut self, step: isize) -> bool {
+		if !self.yanked.cycle(step) {
+			return false;
+		}
+
+		let (idx, len) = self.yanked.position();
+		let Some(set) = self.yanked.active() else {
+			return false;
+		};
+		let what = if set.moved { "moved" } else if set.cut { "cut" } else { "copied" };
+		emit!(Message(format!("Yanked {idx}/{len}: {} files {what}", set.urls.len()), false));
+		true
+	}
+
+	// Exits, writing the cwd and the chosen files for the shell to pick up, unless `canceled`
+	// Runs the `enter` hook once the current directory has changed, other than to be shown while
+	// its path is typed, and the `select` hook once the files selected in it have
+	pub fn hooks(&mut self) {
+		let tab = self.tabs.active();
+		let cwd = &tab.current.cwd;
+		if !tab.previewing && self.entered.as_ref() != Some(cwd) {
+			self.entered = Some(cwd.clone());
+			Hook::Enter.emit(vec![cwd.clone()]);
+		}
+
+		let revision = tab.current.files.revision();
+		match &self.selected {
+			Some((u, r)) if u == cwd && *r == revision => {}
+			Some((u, _)) if u == cwd => Hook::Select.emit(tab.current.files.selected_urls()),
+			_ => {}
+		}
+		self.selected = Some((cwd.clone(), revision));
+	}
+
+	// Reads the space of the filesystem of the current directory again, for the files written to it
+	// in the meantime, whether it's changed
+	pub fn space_update(&mut self) -> bool {
+		let space = if self.cwd().is_sftp() { None } else { disk_space(self.cwd()) };
+		std::mem::replace(&mut self.space, space) != space
+	}
+
+	pub fn quit(&self, tasks: &Tasks, canceled: bool) -> bool {
 		let tasks = tasks.len();
 		if tasks == 0 {
-			emit!(Quit);
+			emit!(Quit(canceled));
 			return false;
 		}
 
@@ -102,7 +367,7 @@ impl Manager {
 
 			if let Some(Ok(choice)) = result.recv().await {
 				if choice == "y" || choice == "Y" {
-					emit!(Quit);
+					emit!(Quit(canceled));
 				}
 			}
 		});
@@ -113,7 +378,7 @@ impl Manager {
 		if self.tabs.len() > 1 {
 			return self.tabs.close(self.tabs.idx());
 		}
-		self.quit(tasks)
+		self.quit(tasks, false)
 	}
 
 	pub fn suspend(&mut self) -> bool {
@@ -125,7 +390,52 @@ impl Manager {
 		false
 	}
 
+	// Chooses a command of the manager from all of them in the keymap, by typing a part of it
+	pub fn palette(&self) -> bool {
+		let (execs, items) = palette(KEYMAP.get(KeymapLayer::Manager));
+		tokio::spawn(async move {
+			let result = emit!(Select(SelectOpt::top("Commands:", items).with_filter()));
+			if let Ok(choice) = result.await {
+				emit!(Call(execs[choice].clone(), KeymapLayer::Manager));
+			}
+		});
+		false
+	}
+
+	// Picks the selected files, or the hovered one, for `--pick`, of the kind picked, or the current
+	// directory if only directories are picked and none of them is one
+	pub fn pick(&self) -> bool {
+		let Some(pick) = BOOT.pick else {
+			return false;
+		};
+
+		let selected = self.selected();
+		let mut picked: Vec<_> =
+			selected.iter().filter(|f| pick.allows(f.is_dir())).map(|f| f.url_owned()).collect();
+		if picked.is_empty() && pick == Pick::Dirs {
+			picked.push(self.cwd().clone());
+		}
+
+		if !picked.is_empty() {
+			emit!(Pick(picked));
+		} else if !selected.is_empty() {
+			emit!(Message("Only files can be picked, not directories".to_owned(), true));
+		}
+		false
+	}
+
 	pub fn open(&mut self, interactive: bool) -> bool {
+		// What to do with the one file opened, by its extension, and whether it's executable
+		let enter = match self.selected()[..] {
+			[f] if !interactive && !f.is_dir() && BOOT.chooser_file.is_none() => {
+				Some((OPEN.enter(f.extension().as_deref()), f.is_executable()))
+			}
+			_ => None,
+		};
+		if enter.is_some_and(|(e, _)| e == OpenEnter::Nothing) {
+			return false;
+		}
+
 		let mut files: Vec<_> = self
 			.selected()
 			.into_iter()
@@ -143,7 +453,7 @@ impl Manager {
 
 		tokio::spawn(async move {
 			let todo: Vec<_> = files.iter().filter(|(_, m)| m.is_none()).map(|(u, _)| u).collect();
-			if let Ok(mut mimes) = external::file(&todo).await {
+			if let Ok(mut mimes) = mimetypes(&todo).await {
 				files = files
 					.into_iter()
 					.map(|(u, m)| {
@@ -156,53 +466,406 @@ impl Manager {
 			let files: Vec<_> =
 				files.into_iter().filter_map(|(u, m)| m.map(|m| (u.into_os_string(), m))).collect();
 
-			if !interactive {
+			if let Some((enter, executable)) = enter {
+				return Self::open_enter(files, enter, executable).await;
+			} else if !interactive {
 				emit!(Open(files, None));
 				return;
 			}
 
-			let openers = OPEN.common_openers(&files);
-			if openers.is_empty() {
+			// The type all of them are of, to list the applications of, and to remember the choice by
+			let mime =
+				files.first().map(|(_, m)| m.clone()).filter(|m| files.iter().all(|(_, n)| n == m));
+			let mut openers: Vec<_> = OPEN.common_openers(&files).into_iter().cloned().collect();
+			for opener in mime.as_deref().map(external::desktop_openers).unwrap_or_default() {
+				if openers.iter().all(|o| o.exec != opener.exec) {
+					openers.push(opener);
+				}
+			}
+
+			let mut always = false;
+			let choice = loop {
+				let mut items: Vec<_> = openers.iter().map(|o| o.display_name.clone()).collect();
+				items.push("Other command...".to_owned());
+
+				let title = match &mime {
+					Some(mime) if always => format!("Always open {mime} with:"),
+					Some(mime) => {
+						items.push(format!("Always use for {mime}..."));
+						"Open with:".to_owned()
+					}
+					None => "Open with:".to_owned(),
+				};
+				match emit!(Select(SelectOpt::hovered(&title, items))).await {
+					Ok(choice) if choice == openers.len() + 1 => always = true,
+					Ok(choice) => break choice,
+					Err(_) => return,
+				}
+			};
+
+			let opener = match openers.get(choice) {
+				Some(opener) => opener.clone(),
+				None => {
+					let mut result = emit!(Input(InputOpt::hovered("Open with:").with_history("open")));
+					let Some(Ok(exec)) = result.recv().await else {
+						return;
+					};
+					Self::open_command(exec)
+				}
+			};
+
+			if let Some(mime) = mime.filter(|_| always) {
+				emit!(Prefer(mime, opener.clone()));
+			}
+			emit!(Open(files, Some(opener)));
+		});
+		false
+	}
+
+	// Runs, edits or opens the one file by `enter`, where an executable is never run without asking
+	async fn open_enter(files: Vec<(OsString, String)>, mut enter: OpenEnter, executable: bool) {
+		if executable && matches!(enter, OpenEnter::Run | OpenEnter::Smart) {
+			let items = vec!["Run".to_owned(), "Open".to_owned()];
+			match emit!(Select(SelectOpt::hovered("Run or open?", items))).await {
+				Ok(0) => enter = OpenEnter::Run,
+				Ok(_) if enter == OpenEnter::Run => enter = OpenEnter::Open,
+				Ok(_) => {}
+				Err(_) => return,
+			}
+		} else if enter == OpenEnter::Run {
+			enter = OpenEnter::Open;
+		}
+
+		let text = files.first().is_some_and(|(_, m)| {
+			matches!(MimeKind::new(m), MimeKind::Text | MimeKind::JSON | MimeKind::Empty)
+		});
+		let edit = enter == OpenEnter::Edit || (enter == OpenEnter::Smart && text);
+		let opener = match enter {
+			OpenEnter::Run => Opener {
+				exec:         r#""$1"; echo "Press enter to exit"; read"#.to_owned(),
+				block:        true,
+				display_name: "Run".to_owned(),
+				spread:       false,
+			},
+			_ if edit => Opener {
+				exec:         r#"$EDITOR "$@""#.to_owned(),
+				block:        true,
+				display_name: "$EDITOR".to_owned(),
+				spread:       true,
+			},
+			_ => {
+				emit!(Open(files, None));
 				return;
 			}
+		};
+		emit!(Open(files, Some(opener)));
+	}
+
+	// Edits the hovered file in `$EDITOR`, at the line its preview is at, where it can tell
+	pub fn edit(&self) -> bool {
+		let Some(hovered) = self.hovered().filter(|h| !h.is_dir()) else {
+			return false;
+		};
+
+		let line = self.active().preview.line(hovered.url()).unwrap_or(1);
+		let editor = env::var("EDITOR").unwrap_or_default();
+		let opener = Opener {
+			exec:         OPEN.edit_at(&editor, line),
+			block:        true,
+			display_name: "$EDITOR".to_owned(),
+			spread:       false,
+		};
+
+		let mime = self.mimetype.get(hovered.url()).cloned().unwrap_or_default();
+		emit!(Open(vec![(hovered.url().as_os_str().to_owned(), mime)], Some(opener)));
+		false
+	}
+
+	// Compares the two files selected, or the one selected with the hovered one, in the preview of
+	// the hovered file, or stops comparing them if it already is
+	pub fn diff(&mut self) -> bool {
+		if self.active().preview.diff().is_some() {
+			return self.active_mut().preview.set_diff(None);
+		}
+		let Some(hovered) = self.hovered().map(|h| h.url_owned()) else {
+			return false;
+		};
+
+		let files: Vec<_> = self.selected().into_iter().map(|f| (f.url_owned(), f.is_dir())).collect();
+		let ((old, a), (new, b)) = match &files[..] {
+			[a, b] => (a.clone(), b.clone()),
+			[a] if a.0 != hovered => (a.clone(), (hovered.clone(), self.hovered().unwrap().is_dir())),
+			_ => {
+				let text = "Select two files to compare, or one to compare with the hovered one";
+				emit!(Message(text.to_owned(), true));
+				return false;
+			}
+		};
+		if a || b {
+			emit!(Message("Directories can't be compared".to_owned(), true));
+			return false;
+		}
+
+		self.active_mut().preview.set_diff(Some(PreviewDiff { old, new, hovered }))
+	}
+
+	// An opener of the command typed, with the files given at the end if it doesn't refer to them
+	fn open_command(exec: String) -> Opener {
+		let spread = exec.contains("$@") || exec.contains("$*");
+		let (exec, spread) = if spread || exec.contains("$1") {
+			(exec, spread)
+		} else {
+			(format!("{exec} \"$@\""), true)
+		};
+
+		let display_name = exec.split_whitespace().next().unwrap_or_default().to_owned();
+		Opener { exec, block: false, display_name, spread }
+	}
 
-			let result = emit!(Select(SelectOpt::hovered(
-				"Open with:",
-				openers.iter().map(|o| o.display_name.clone()).collect()
-			)));
+	// Marks the hovered file, or the current directory without one, under `key`,
+	// which is asked for if not given
+	pub fn bookmark_set(&mut self, key: Option<&str>) -> bool {
+		let Some(key) = key else {
+			tokio::spawn(async move {
+				let mut result = emit!(Input(
+					InputOpt::top("Bookmark as:")
+						.with_validator(|key| Bookmarks::validate_key(key).map(|_| ()))
+				));
+				if let Some(Ok(key)) = result.recv().await {
+					emit!(Call(Exec::call("bookmark_set", vec![key]).vec(), KeymapLayer::Manager));
+				}
+			});
+			return false;
+		};
+
+		let Ok(key) = Bookmarks::validate_key(key) else {
+			return false;
+		};
+		let target = self.hovered().map_or(self.cwd(), |h| h.url()).to_path_buf();
+		if self.bookmarks.set(key, target) {
+			self.bookmarks.save();
+		}
+		false
+	}
+
+	// Goes to the bookmark under `key`, revealing it if it's a file,
+	// or chooses one of them from a list of all, with the missing ones flagged
+	pub fn bookmark_jump(&self, key: Option<&str>) -> bool {
+		if let Some(key) = key {
+			if let Some(path) = key.chars().next().and_then(|k| self.bookmarks.get(k)) {
+				emit!(Cd(Url::from(path)));
+			}
+			return false;
+		}
+
+		let (keys, items): (Vec<_>, Vec<_>) = self
+			.bookmarks
+			.list()
+			.into_iter()
+			.map(|(k, p, exists)| {
+				let missing = if exists { "" } else { "  (missing)" };
+				(k, format!("{k}  {}{missing}", p.display()))
+			})
+			.unzip();
+		if keys.is_empty() {
+			return false;
+		}
+
+		tokio::spawn(async move {
+			let result = emit!(Select(SelectOpt::top("Bookmarks:", items)));
 			if let Ok(choice) = result.await {
-				emit!(Open(files, Some(openers[choice].clone())));
+				let exec = Exec::call("bookmark_jump", vec![keys[choice].to_string()]);
+				emit!(Call(exec.vec(), KeymapLayer::Manager));
 			}
 		});
 		false
 	}
 
-	pub fn create(&self) -> bool {
-		let cwd = self.cwd().to_owned();
+	#[inline]
+	pub fn bookmark_delete(&mut self, key: Option<&str>) -> bool {
+		if key.and_then(|k| k.chars().next()).is_some_and(|k| self.bookmarks.delete(k)) {
+			self.bookmarks.save();
+		}
+		false
+	}
+
+	#[inline]
+	pub fn bookmark_prune(&mut self) -> bool {
+		if self.bookmarks.prune() {
+			self.bookmarks.save();
+		}
+		false
+	}
+
+	// Keeps how the current directory is sorted and shown, to apply it each time it's entered
+	pub fn override_set(&mut self) -> bool {
+		let tab = self.tabs.active_mut();
+		let o = DirOverride::new(&tab.sorter, tab.show_hidden, self.layout);
+		tab.stashed.get_or_insert((tab.sorter, tab.show_hidden, self.layout));
+
+		let cwd = tab.current.cwd.to_regular();
+		if !cwd.is_dir() || !self.overrides.set(&cwd, o) {
+			return false;
+		}
+		self.overrides.save();
+		emit!(Message("Kept the sorting and view of this directory".to_owned(), false));
+		false
+	}
+
+	// Forgets the override of the current directory, going back to how it was before entering it
+	pub fn override_clear(&mut self) -> bool {
+		if !self.overrides.remove(&self.cwd().to_regular()) {
+			return false;
+		}
+		self.overrides.save();
+		emit!(Message("Cleared the sorting and view of this directory".to_owned(), false));
+
+		self.override_apply();
+		self.active_mut().apply_files_attrs(false)
+	}
+
+	// Applies the override of the current directory, or else the first of `sort_rules` it matches,
+	// or brings back the settings of the tab from before it entered the ones having them, once it's
+	// in a directory that has neither
+	fn override_apply(&mut self) {
+		let cwd = self.cwd().to_regular();
+		let o = self.overrides.get(&cwd).copied();
+		let rule = o.is_none().then(|| self.sort_rule(&cwd)).flatten();
+
+		let tab = self.tabs.active_mut();
+		let (sorter, show_hidden, layout) = match (o, rule) {
+			(Some(o), _) => {
+				tab.stashed.get_or_insert((tab.sorter, tab.show_hidden, self.layout));
+				(o.sorter(tab.sorter), o.show_hidden, o.layout)
+			}
+			// On top of the settings from before, not of another rule matched on the way
+			(None, Some(rule)) => {
+				let stashed = *tab.stashed.get_or_insert((tab.sorter, tab.show_hidden, self.layout));
+				(ruled(rule, stashed.0), stashed.1, stashed.2)
+			}
+			(None, None) => match tab.stashed.take() {
+				Some(stashed) => stashed,
+				None => return,
+			},
+		};
+
+		(tab.sorter, tab.show_hidden) = (sorter, show_hidden);
+		self.set_layout(Some(layout));
+	}
+
+	// The first of `sort_rules` the directory matches, by its path, or by the types of most of its
+	// files, where those not known yet are told by their extensions
+	fn sort_rule(&self, dir: &Path) -> Option<&'static SortRule> {
+		if MANAGER.sort_rules.is_empty() {
+			return None;
+		}
+
+		let mimes = self.current().files.iter().filter(|f| !f.is_dir()).map(|f| {
+			self.mimetype.get(f.url()).map(String::as_str).or_else(|| mime_by_ext(f.url()))
+		});
+		MANAGER.sort_rules.matched(dir, mimes)
+	}
+
+	// Where to paste the files yanked, the current directory, or the hovered one if `into`,
+	// none if it isn't a directory
+	pub fn paste_dest(&self, into: bool) -> Option<Url> {
+		if !into {
+			return Some(self.cwd().to_owned());
+		}
+
+		match self.hovered() {
+			Some(h) if h.is_dir() => Some(h.url_owned()),
+			Some(h) => {
+				let name = h.name_display().unwrap_or_default();
+				emit!(Message(format!("Can't paste into `{name}`, it isn't a directory"), true));
+				None
+			}
+			None => {
+				emit!(Message("No directory is hovered to paste into".to_owned(), true));
+				None
+			}
+		}
+	}
+
+	// Marks where the files are removed from, to go back to with the `'` bookmark
+	pub fn bookmark_removed(&mut self, targets: &[Url]) {
+		let Some(dir) = targets.first().and_then(|u| u.parent()) else {
+			return;
+		};
+		if self.bookmarks.set(BOOKMARK_REMOVED, dir.to_owned()) {
+			self.bookmarks.save();
+		}
+	}
+
+	// Lists the filesystems mounted and the removable devices, see `external::devices`, to go to
+	// where the chosen one is mounted, mounting it first if it isn't, or to unmount it
+	pub fn devices(&self) -> bool {
 		tokio::spawn(async move {
-			let mut result = emit!(Input(InputOpt::top("Create:")));
+			let devices = match external::devices().await {
+				Ok(devices) => devices,
+				Err(e) => {
+					emit!(Message(format!("Failed to list the devices: {e}"), true));
+					return;
+				}
+			};
 
-			if let Some(Ok(name)) = result.recv().await {
-				let path = cwd.join(&name);
-				let hovered = path.components().take(cwd.components().count() + 1).collect::<PathBuf>();
+			let items = devices.iter().map(Self::device_line).collect();
+			let Ok(choice) = emit!(Select(SelectOpt::top("Devices:", items))).await else {
+				return;
+			};
 
-				if name.ends_with('/') {
-					fs::create_dir_all(path).await?;
-				} else {
-					fs::create_dir_all(path.parent().unwrap()).await.ok();
-					fs::File::create(path).await?;
+			let device = &devices[choice];
+			let result = match &device.mount {
+				Some(mount) if device.is_block() => {
+					let items = vec!["Enter".to_owned(), "Unmount".to_owned()];
+					match emit!(Select(SelectOpt::hovered(&device.source, items))).await {
+						Ok(0) => Ok(Some(mount.clone())),
+						Ok(_) => external::udisks_unmount(&device.source).await.map(|_| None),
+						Err(_) => return,
+					}
 				}
+				Some(mount) => Ok(Some(mount.clone())),
+				None => external::udisks_mount(&device.source).await.map(Some),
+			};
 
-				if let Ok(file) = File::from(Url::from(hovered)).await {
-					emit!(Hover(file));
-					emit!(Refresh);
+			match result {
+				Ok(Some(mount)) => {
+					emit!(Cd(Url::from(mount)));
+				}
+				Ok(None) => {
+					emit!(Message(format!("Unmounted {}", device.source), false));
+				}
+				Err(e) => {
+					emit!(Message(e.to_string(), true));
 				}
 			}
-			Ok::<(), Error>(())
 		});
 		false
 	}
 
+	// Such as `/dev/sdb1 "USB" 16.0 GB vfat, at /media/USB with 3.2 GB free`
+	fn device_line(device: &external::Device) -> String {
+		let mut s = device.source.clone();
+		if let Some(label) = &device.label {
+			s.push_str(&format!(" \"{label}\""));
+		}
+		if let Some(size) = device.size {
+			s.push_str(&format!(" {}", readable_size(size)));
+		}
+		s.push_str(&format!(" {}", device.fstype));
+
+		match &device.mount {
+			Some(mount) => {
+				s.push_str(&format!(", at {}", mount.display()));
+				if let Some((free, _)) = disk_space(mount) {
+					s.push_str(&format!(" with {} free", readable_size(free)));
+				}
+			}
+			None => s.push_str(", not mounted"),
+		}
+		s
+	}
+
 	pub fn rename(&self) -> bool {
 		if self.active().in_selecting() {
 			return self.bulk_rename();
@@ -213,18 +876,101 @@ impl Manager {
 		};
 
 		tokio::spawn(async move {
+			let dir = hovered.parent().unwrap().to_owned();
+			let old = hovered.file_name().unwrap().to_owned();
+			let name = old.to_string_lossy();
+			let stem = if hovered.is_dir() { None } else { name.rfind('.').filter(|&i| i > 0) };
 			let mut result = emit!(Input(
-				InputOpt::hovered("Rename:").with_value(hovered.file_name().unwrap().to_string_lossy())
+				InputOpt::hovered("Rename:")
+					.with_value(&name)
+					.with_selected(0..name[..stem.unwrap_or(name.len())].chars().count())
+					.with_history("rename")
+					.with_completion(&dir)
+					.with_validator({
+						let (dir, old) = (dir.clone(), old.clone());
+						move |name| Self::validate_name(&dir, &old, name).map(|_| ())
+					})
+					.with_hint({
+						let (dir, old, hovered) = (dir.clone(), old.clone(), hovered.clone());
+						move |name| Self::hint_name(&dir, &old, &hovered, name)
+					})
 			));
 
-			if let Some(Ok(new)) = result.recv().await {
-				let to = hovered.parent().unwrap().join(new);
-				fs::rename(&hovered, to).await.ok();
+			let Some(Ok(new)) = result.recv().await else {
+				return;
+			};
+			let Ok(to) = Self::validate_name(&dir, &old, &new) else {
+				return;
+			};
+			if to == *hovered {
+				return;
+			}
+
+			let parent = to.parent().unwrap();
+			if fs::symlink_metadata(parent).await.is_err() {
+				if !Self::ask(format!("Create `{}`? (y/N)", readable_path(parent))).await {
+					return;
+				}
+				if let Err(e) = fs::create_dir_all(parent).await {
+					emit!(Message(format!("Failed to create `{}`: {e}", parent.display()), true));
+					return;
+				}
+			} else if fs::symlink_metadata(&to).await.is_ok()
+				&& !Self::ask(format!("Overwrite `{}`? (y/N)", readable_path(&to))).await
+			{
+				return;
+			}
+
+			if let Err(e) = fs::rename(&hovered, &to).await {
+				emit!(Message(format!("Failed to rename `{}`: {e}", name), true));
 			}
 		});
 		false
 	}
 
+	// Where `name` typed in the prompt of `rename` moves the file to, relative to `dir` it's in, to
+	// the directory of it with the same name if it ends with a separator. It can't be empty, or end
+	// up as `.` or `..`, or in what isn't a directory
+	fn validate_name(dir: &Path, old: &OsStr, name: &str) -> Result<PathBuf, String> {
+		let into = if cfg!(windows) { name.ends_with(['/', '\\']) } else { name.ends_with('/') };
+		let to = if into { dir.join(name).join(old) } else { dir.join(name) };
+		if name.is_empty() {
+			return Err("The name can't be empty".to_owned());
+		} else if !into && Path::new(name).file_name().is_none() {
+			return Err(format!("`{name}` isn't a valid name"));
+		}
+
+		match to.ancestors().skip(1).find(|p| p.symlink_metadata().is_ok()) {
+			Some(p) if !p.is_dir() => {
+				Err(format!("`{}` isn't a directory", p.strip_prefix(dir).unwrap_or(p).display()))
+			}
+			_ => Ok(to),
+		}
+	}
+
+	// What's shown below the prompt of `rename` as the name is typed, where the file ends up, or
+	// that it takes the place of another one
+	fn hint_name(dir: &Path, old: &OsStr, hovered: &Path, name: &str) -> Option<(String, bool)> {
+		let to = Self::validate_name(dir, old, name).ok()?;
+		if to == hovered {
+			return None;
+		} else if to.symlink_metadata().is_ok() {
+			return Some((format!("`{}` exists, it'll be overwritten", readable_path(&to)), true));
+		}
+
+		let parent = to.parent()?;
+		Some(if parent.symlink_metadata().is_err() {
+			(format!("→ {}, creating `{}`", readable_path(&to), readable_path(parent)), false)
+		} else {
+			(format!("→ {}", readable_path(&to)), false)
+		})
+	}
+
+	async fn ask(title: String) -> bool {
+		let mut result = emit!(Input(InputOpt::hovered(title)));
+		matches!(result.recv().await, Some(Ok(choice)) if choice == "y" || choice == "Y")
+	}
+
 	pub fn bulk_rename(&self) -> bool {
 		let old: Vec<_> = self.selected().into_iter().map(|f| f.url()).collect();
 
@@ -285,12 +1031,31 @@ impl Manager {
 			return Ok(());
 		}
 
+		let conflicts = Plan::rename_conflicts(&root, &todo).await;
+		if !conflicts.is_empty() {
+			{
+				let mut stdout = BufWriter::new(stdout().lock());
+				writeln!(stdout, "Nothing was renamed, the following names conflict:")?;
+				for n in conflicts {
+					writeln!(stdout, "{}", n.display())?;
+				}
+				writeln!(stdout, "\nPress ENTER to exit")?;
+				stdout.flush()?;
+			}
+
+			stdin().read_exact(&mut [0]).await?;
+			return Ok(());
+		}
+
+		let plan = Plan::rename(
+			todo.into_iter().map(|(o, n)| (Url::from(root.join(o)), Url::from(root.join(n)))).collect(),
+		);
 		{
 			let mut stdout = BufWriter::new(stdout().lock());
-			for (o, n) in &todo {
-				writeln!(stdout, "{} -> {}", o.display(), n.display())?;
+			for step in &plan.steps {
+				writeln!(stdout, "{step}")?;
 			}
-			write!(stdout, "Continue to rename? (y/N): ")?;
+			write!(stdout, "Continue to rename, {}? (y/N): ", plan.summary())?;
 			stdout.flush()?;
 		}
 
@@ -300,32 +1065,7 @@ impl Manager {
 			return Ok(());
 		}
 
-		let mut failed = Vec::new();
-		for (o, n) in todo {
-			if fs::metadata(&n).await.is_ok() {
-				failed.push((o, n, anyhow!("Destination already exists")));
-				continue;
-			}
-			if let Err(e) = fs::rename(root.join(&o), root.join(&n)).await {
-				failed.push((o, n, e.into()));
-			}
-		}
-		if failed.is_empty() {
-			return Ok(());
-		}
-
-		Term::clear(&mut stdout())?;
-		{
-			let mut stdout = BufWriter::new(stdout().lock());
-			writeln!(stdout, "Failed to rename:")?;
-			for (o, n, e) in failed {
-				writeln!(stdout, "{} -> {}: {e}", o.display(), n.display())?;
-			}
-			writeln!(stdout, "\nPress ENTER to exit")?;
-			stdout.flush()?;
-		}
-
-		stdin().read_exact(&mut [0]).await?;
+		emit!(Rename(plan.steps.into_iter().filter_map(|s| Some((s.from, s.to?))).collect()));
 		Ok(())
 	}
 
@@ -335,20 +1075,28 @@ impl Manager {
 		let hovered = self.hovered().map(|h| h.url_owned());
 
 		let mut b = if cwd == url {
-			self.current_mut().update(op)
+			// The rules by the types of the files are matched once the first of them are read
+			let first = self.current().files.is_empty();
+			let mut b = self.current_mut().update(op);
+			if first && !self.current().files.is_empty() && MANAGER.sort_rules.by_content() {
+				self.override_apply();
+				b |= self.active_mut().apply_files_attrs(false);
+			}
+			b
 		} else if matches!(self.parent(), Some(p) if p.cwd == url) {
 			self.active_mut().parent.as_mut().unwrap().update(op)
 		} else if matches!(self.hovered(), Some(h) if h.url() == &url) {
-			self.active_mut().history.entry(url.clone()).or_insert_with(|| Folder::from(&url));
+			self.active_mut().history.get_or_new(&url);
 			self.active_mut().apply_files_attrs(true);
 			self.active_mut().history.get_mut(&url).unwrap().update(op)
 		} else {
-			self.active_mut().history.entry(url.clone()).or_insert_with(|| Folder::from(&url)).update(op);
+			self.active_mut().history.get_or_new(&url).update(op);
 			false
 		};
 
 		b |= self.active_mut().parent.as_mut().map_or(false, |p| p.hover(&cwd));
 		b |= hovered.as_ref().map_or(false, |h| self.current_mut().hover(h));
+		b |= self.land_apply();
 
 		if hovered.as_ref() != self.hovered().map(|h| h.url()) {
 			emit!(Hover);
@@ -411,6 +1159,9 @@ impl Manager {
 	#[inline]
 	pub fn current(&self) -> &Folder { &self.tabs.active().current }
 
+	#[inline]
+	pub fn space(&self) -> Option<(u64, u64)> { self.space }
+
 	#[inline]
 	pub fn current_mut(&mut self) -> &mut Folder { &mut self.tabs.active_mut().current }
 
@@ -424,5 +1175,14 @@ impl Manager {
 	pub fn selected(&self) -> Vec<&File> { self.tabs.active().selected() }
 
 	#[inline]
-	pub fn yanked(&self) -> &(bool, HashSet<Url>) { &self.yanked }
+	pub fn message(&self) -> Option<(&str, bool)> {
+		let (text, error, at) = self.message.as_ref()?;
+		(at.elapsed() < Duration::from_secs(3)).then_some((text.as_str(), *error))
+	}
+
+	#[inline]
+	pub fn yanked(&self) -> &Yanked { &self.yanked }
+
+	#[inline]
+	pub fn yanked_mut(&mut self) -> &mut Yanked { &mut self.yanked }
 }
@@ -1,17 +1,41 @@
+mod backstack;
+mod bookmarks;
 mod finder;
 mod folder;
+mod history;
+mod info;
+mod jump;
+mod landing;
+mod listing;
 mod manager;
 mod mode;
+mod mouse;
+mod overrides;
+mod places;
 mod preview;
+mod repeat;
+mod session;
 mod tab;
 mod tabs;
 mod watcher;
+mod yanked;
 
+use backstack::*;
+use mouse::*;
+use session::*;
+pub use bookmarks::*;
 pub use finder::*;
 pub use folder::*;
+pub use history::*;
+pub use jump::*;
+pub use landing::*;
+pub use listing::*;
 pub use manager::*;
 pub use mode::*;
+pub use overrides::*;
+pub use places::*;
 pub use preview::*;
 pub use tab::*;
 pub use tabs::*;
 pub use watcher::*;
+pub use yanked::*;
@@ -0,0 +1,181 @@
+use std::time::{Duration, Instant};
+
+use config::{keymap::{Exec, KeymapLayer}, manager::LayoutMode, MANAGER};
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use shared::Term;
+use tracing::warn;
+
+use super::Manager;
+use crate::emit;
+
+// How far a notch of the wheel scrolls, and how soon the second click of a double-click must be
+const MOUSE_SCROLL: isize = 3;
+const MOUSE_DOUBLE: Duration = Duration::from_millis(400);
+
+// Where the clicks and the drags were, to tell a double-click apart,
+// and a range selected by dragging from a click on a file
+#[derive(Default)]
+pub(super) struct Mouse {
+	enabled: bool,
+	clicked: Option<(Instant, usize)>,
+	pressed: bool,
+	dragged: bool,
+}
+
+impl Mouse {
+	#[inline]
+	pub(super) fn new(enabled: bool) -> Self { Self { enabled, ..Default::default() } }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pane {
+	Places,
+	Parent,
+	Current,
+	Preview,
+	// The other pane of the `commander` layout
+	Other,
+}
+
+impl Manager {
+	#[inline]
+	pub fn mouse_enabled(&self) -> bool { self.mouse.enabled }
+
+	pub fn set_mouse(&mut self, state: Option<bool>) -> bool {
+		let state = state.unwrap_or(!self.mouse.enabled);
+		if state == self.mouse.enabled {
+			return false;
+		}
+
+		if let Err(e) = Term::set_mouse(state) {
+			warn!("failed to switch the mouse capture: {e}");
+			return false;
+		}
+		self.mouse = Mouse::new(state);
+		true
+	}
+
+	pub fn mouse(&mut self, event: MouseEvent) -> bool {
+		if !self.mouse.enabled {
+			return false;
+		}
+
+		// Let go anywhere, even out of the files
+		if event.kind == MouseEventKind::Up(MouseButton::Left) {
+			return self.mouse_release();
+		}
+
+		let Some(pane) = self.pane_at(event.column, event.row) else {
+			return false;
+		};
+		if pane == Pane::Other && !matches!(event.kind, MouseEventKind::Drag(_)) {
+			self.tabs_mut().switch_other();
+		}
+
+		let row = event.row as usize - 1;
+		match event.kind {
+			MouseEventKind::Down(MouseButton::Left) => self.mouse_click(pane, row),
+			MouseEventKind::Drag(MouseButton::Left) => self.mouse_drag(pane, row),
+			MouseEventKind::ScrollDown => self.mouse_scroll(pane, MOUSE_SCROLL),
+			MouseEventKind::ScrollUp => self.mouse_scroll(pane, -MOUSE_SCROLL),
+			_ => false,
+		}
+	}
+
+	// The pane under the pointer, if it's over the files and not the header or the status bar
+	fn pane_at(&self, col: u16, row: u16) -> Option<Pane> {
+		if row == 0 || row as usize > MANAGER.layout.folder_height() {
+			return None;
+		}
+
+		let places = MANAGER.layout.places_width();
+		if col < places {
+			return Some(Pane::Places);
+		}
+
+		if self.layout == LayoutMode::Commander {
+			// The panes keep the order of their tabs, see the layout of the app
+			let left = (Term::size().columns - places) / 2 + places > col;
+			let active_left = self.tabs().other_idx().is_none_or(|i| self.tabs().idx() < i);
+			return Some(if left == active_left { Pane::Current } else { Pane::Other });
+		}
+
+		let rect = MANAGER.layout.folder_rect();
+		Some(if col < rect.x {
+			Pane::Parent
+		} else if col < rect.x + rect.width {
+			Pane::Current
+		} else {
+			Pane::Preview
+		})
+	}
+
+	fn mouse_click(&mut self, pane: Pane, row: usize) -> bool {
+		let now = Instant::now();
+		let double = matches!(self.mouse.clicked, Some((at, r))
+			if r == row && now.duration_since(at) < MOUSE_DOUBLE);
+		self.mouse.clicked = if double { None } else { Some((now, row)) };
+
+		let tab = self.active();
+		let target = match pane {
+			Pane::Places => return self.place_jump(Some(row)),
+			Pane::Parent => tab.parent.as_ref().and_then(|p| p.window().get(row)),
+			Pane::Preview => self
+				.hovered()
+				.filter(|h| h.is_dir())
+				.and_then(|h| tab.history(h.url()))
+				.and_then(|p| p.window_for(tab.preview().skip()).get(row)),
+			Pane::Current | Pane::Other => {
+				let index = self.current().offset() + row;
+				if index >= self.current().files.len() {
+					return false;
+				}
+
+				self.mouse.pressed = true;
+				let b = self.active_mut().goto(index);
+				if !double {
+					return b;
+				} else if self.hovered().is_some_and(|h| h.is_dir()) {
+					return self.active_mut().enter(MANAGER.follow_symlink, MANAGER.smart_enter);
+				}
+				return self.open(false);
+			}
+		};
+
+		// The files of the parent and the preview are gone to as if with `cd`
+		if let Some(file) = target {
+			emit!(Cd(file.url_owned()));
+		}
+		false
+	}
+
+	// Selects the files from the one clicked on to the one under the pointer, like the visual mode
+	fn mouse_drag(&mut self, pane: Pane, row: usize) -> bool {
+		if !self.mouse.pressed || pane != Pane::Current {
+			return false;
+		}
+
+		let index = self.current().offset() + row;
+		if !self.mouse.dragged {
+			self.mouse.dragged = true;
+			self.active_mut().visual_mode(false);
+		}
+		self.active_mut().goto(index)
+	}
+
+	fn mouse_release(&mut self) -> bool {
+		let dragged = self.mouse.dragged;
+		(self.mouse.pressed, self.mouse.dragged) = (false, false);
+		dragged && self.active_mut().visual_end()
+	}
+
+	fn mouse_scroll(&mut self, pane: Pane, step: isize) -> bool {
+		if pane == Pane::Places {
+			return false;
+		} else if pane == Pane::Preview {
+			emit!(Call(Exec::call("peek", vec![step.to_string()]).vec(), KeymapLayer::Manager));
+			return false;
+		}
+		self.active_mut().arrow(step)
+	}
+}
@@ -0,0 +1,166 @@
+use std::{collections::BTreeMap, path::{Path, PathBuf}};
+
+use config::{manager::{LayoutMode, SortBy, SortRule}, BOOT};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::files::FilesSorter;
+
+// How a directory is sorted, whether its hidden files are shown, and the layout it's in,
+// kept for it and applied each time it's entered
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct DirOverride {
+	pub sort_by:        SortBy,
+	pub sort_sensitive: bool,
+	pub sort_reverse:   bool,
+	pub sort_dir_first: bool,
+	pub show_hidden:    bool,
+	pub layout:         LayoutMode,
+
+	// The inode of the directory, so that the one made at its path after it's moved or removed
+	// doesn't take it
+	#[serde(default)]
+	ino: Option<u64>,
+}
+
+impl DirOverride {
+	pub fn new(sorter: &FilesSorter, show_hidden: bool, layout: LayoutMode) -> Self {
+		Self {
+			sort_by: sorter.by,
+			sort_sensitive: sorter.sensitive,
+			sort_reverse: sorter.reverse,
+			sort_dir_first: sorter.dir_first,
+			show_hidden,
+			layout,
+			ino: None,
+		}
+	}
+
+	// The sorter with the options kept, and the others as they're in `sorter`
+	pub fn sorter(&self, sorter: FilesSorter) -> FilesSorter {
+		FilesSorter {
+			by: self.sort_by,
+			sensitive: self.sort_sensitive,
+			reverse: self.sort_reverse,
+			dir_first: self.sort_dir_first,
+			..sorter
+		}
+	}
+}
+
+// The sorter with the options of the rule, and the others as they're in `sorter`
+pub fn ruled(rule: &SortRule, sorter: FilesSorter) -> FilesSorter {
+	FilesSorter {
+		by: rule.by.unwrap_or(sorter.by),
+		sensitive: rule.sensitive.unwrap_or(sorter.sensitive),
+		reverse: rule.reverse.unwrap_or(sorter.reverse),
+		dir_first: rule.dir_first.unwrap_or(sorter.dir_first),
+		..sorter
+	}
+}
+
+// The overrides of the directories, by their path, kept across restarts
+#[derive(Default)]
+pub struct DirOverrides {
+	inner: BTreeMap<PathBuf, DirOverride>,
+}
+
+impl DirOverrides {
+	#[inline]
+	fn path() -> PathBuf { BOOT.state_dir.join("overrides.json") }
+
+	pub fn load() -> Self {
+		let mut overrides = Self {
+			inner: std::fs::read_to_string(Self::path())
+				.ok()
+				.and_then(|s| serde_json::from_str(&s).ok())
+				.unwrap_or_default(),
+		};
+		if overrides.prune() {
+			overrides.save();
+		}
+		overrides
+	}
+
+	// The override of `dir`, unless it's another directory than the one it was kept for
+	pub fn get(&self, dir: &Path) -> Option<&DirOverride> {
+		self.inner.get(dir).filter(|o| o.ino.is_none() || o.ino == Self::ino(dir))
+	}
+
+	pub fn set(&mut self, dir: &Path, mut o: DirOverride) -> bool {
+		o.ino = Self::ino(dir);
+		if self.inner.get(dir) == Some(&o) {
+			return false;
+		}
+		self.inner.insert(dir.to_owned(), o);
+		true
+	}
+
+	#[inline]
+	pub fn remove(&mut self, dir: &Path) -> bool { self.inner.remove(dir).is_some() }
+
+	// Forgets the ones whose directory is gone, or is another one now
+	fn prune(&mut self) -> bool {
+		let len = self.inner.len();
+		self.inner.retain(|p, o| p.is_dir() && (o.ino.is_none() || o.ino == Self::ino(p)));
+		len != self.inner.len()
+	}
+
+	pub fn save(&self) {
+		let Ok(s) = serde_json::to_string(&self.inner) else {
+			return;
+		};
+		tokio::spawn(async move {
+			if let Err(e) = tokio::fs::write(Self::path(), s).await {
+				warn!("failed to save the overrides of the directories: {e}");
+			}
+		});
+	}
+
+	#[cfg(unix)]
+	fn ino(dir: &Path) -> Option<u64> {
+		use std::os::unix::fs::MetadataExt;
+		std::fs::metadata(dir).ok().map(|m| m.ino())
+	}
+
+	#[cfg(not(unix))]
+	fn ino(_: &Path) -> Option<u64> { None }
+}
+
+#[test]
+fn test_dir_overrides() {
+	let dir = shared::TestDir::new("overrides");
+	let (a, b) = (dir.join("a"), dir.join("b"));
+	std::fs::create_dir_all(&a).unwrap();
+
+	let o = DirOverride {
+		sort_by:        SortBy::Modified,
+		sort_sensitive: false,
+		sort_reverse:   true,
+		sort_dir_first: true,
+		show_hidden:    true,
+		layout:         LayoutMode::Miller,
+		ino:            None,
+	};
+	let mut overrides = DirOverrides::default();
+	assert!(overrides.set(&a, o));
+	assert!(!overrides.set(&a, o));
+	assert_eq!(overrides.get(&b), None);
+	assert_eq!(overrides.get(&a).map(|o| o.sort_by), Some(SortBy::Modified));
+
+	let s = serde_json::to_string(&overrides.inner).unwrap();
+	let inner: BTreeMap<PathBuf, DirOverride> = serde_json::from_str(&s).unwrap();
+	assert_eq!(inner, overrides.inner);
+
+	// Another directory at the same path doesn't take the override of the one moved away
+	std::fs::rename(&a, &b).unwrap();
+	std::fs::create_dir(&a).unwrap();
+	#[cfg(unix)]
+	{
+		assert_eq!(overrides.get(&a), None);
+		assert!(overrides.prune());
+		assert!(overrides.inner.is_empty());
+	}
+
+	assert!(!overrides.remove(&a));
+}
@@ -0,0 +1,214 @@
+use std::path::{Path, PathBuf};
+
+use config::{keymap::{Exec, KeymapLayer}, BOOT, MANAGER};
+use shared::{expand_path, Url};
+use tracing::warn;
+
+use super::Manager;
+use crate::{emit, select::SelectOpt};
+
+// The directories pinned to the sidebar, in the order they're shown, kept across restarts
+#[derive(Default)]
+pub struct Places {
+	inner: Vec<PathBuf>,
+}
+
+impl Places {
+	#[inline]
+	fn path() -> PathBuf { BOOT.state_dir.join("places.json") }
+
+	// The ones saved, or the home and the downloads directory for a start
+	pub fn load() -> Self {
+		let inner = match std::fs::read_to_string(Self::path()) {
+			Ok(s) => serde_json::from_str(&s).unwrap_or_default(),
+			Err(_) => Self::seeds(),
+		};
+		Self { inner }
+	}
+
+	fn seeds() -> Vec<PathBuf> {
+		let (home, downloads) = (expand_path("~"), expand_path("~/Downloads"));
+		if home.as_os_str() == "~" {
+			return vec![];
+		}
+		if downloads.is_dir() { vec![home, downloads] } else { vec![home] }
+	}
+
+	#[inline]
+	pub fn get(&self, idx: usize) -> Option<&Path> { self.inner.get(idx).map(|p| p.as_path()) }
+
+	#[inline]
+	pub fn position(&self, path: &Path) -> Option<usize> { self.inner.iter().position(|p| p == path) }
+
+	// The places with whether the directory still exists
+	pub fn list(&self) -> Vec<(&Path, bool)> {
+		self.inner.iter().map(|p| (p.as_path(), p.is_dir())).collect()
+	}
+
+	pub fn add(&mut self, path: PathBuf) -> bool {
+		if self.inner.contains(&path) {
+			return false;
+		}
+		self.inner.push(path);
+		true
+	}
+
+	pub fn remove(&mut self, idx: usize) -> bool {
+		if idx >= self.inner.len() {
+			return false;
+		}
+		self.inner.remove(idx);
+		true
+	}
+
+	// Moves the place at `idx` by `step`, as far as it can go
+	pub fn shift(&mut self, idx: usize, step: isize) -> bool {
+		if idx >= self.inner.len() {
+			return false;
+		}
+
+		let to = (idx as isize + step).clamp(0, self.inner.len() as isize - 1) as usize;
+		if to == idx {
+			return false;
+		}
+		let path = self.inner.remove(idx);
+		self.inner.insert(to, path);
+		true
+	}
+
+	pub fn save(&self) {
+		let Ok(s) = serde_json::to_string(&self.inner) else {
+			return;
+		};
+		tokio::spawn(async move {
+			if let Err(e) = tokio::fs::write(Self::path(), s).await {
+				warn!("failed to save the places: {e}");
+			}
+		});
+	}
+
+	// The name a place is shown by, the last part of its path, or the whole path for the root
+	pub fn name(path: &Path) -> String {
+		if path == expand_path("~") {
+			return "~".to_owned();
+		}
+		match path.file_name() {
+			Some(name) => name.to_string_lossy().into_owned(),
+			None => path.display().to_string(),
+		}
+	}
+}
+
+impl Manager {
+	#[inline]
+	pub fn places(&self) -> &Places { &self.places }
+
+	#[inline]
+	pub fn places_visible(&self) -> bool { MANAGER.layout.places_width() > 0 }
+
+	// Shows or hides the sidebar, toggles it if not given
+	pub fn set_places(&mut self, state: Option<bool>) -> bool {
+		let state = state.unwrap_or(!self.places_visible());
+		if state == self.places_visible() {
+			return false;
+		}
+
+		MANAGER.layout.set_places_width(if state { MANAGER.places_width } else { 0 });
+		self.current_mut().set_page(true);
+		self.active_mut().preview_reset();
+		emit!(Peek);
+		true
+	}
+
+	// Goes to the place at `idx`, or chooses one of them from a list, with the missing ones flagged
+	pub fn place_jump(&self, idx: Option<usize>) -> bool {
+		if let Some(idx) = idx {
+			if let Some(path) = self.places.get(idx) {
+				emit!(Cd(Url::from(path)));
+			}
+			return false;
+		}
+
+		let items: Vec<_> = self
+			.places
+			.list()
+			.into_iter()
+			.enumerate()
+			.map(|(i, (p, exists))| {
+				let missing = if exists { "" } else { "  (missing)" };
+				format!("{}  {}{missing}", i + 1, p.display())
+			})
+			.collect();
+		if items.is_empty() {
+			return false;
+		}
+
+		tokio::spawn(async move {
+			let result = emit!(Select(SelectOpt::top("Places:", items)));
+			if let Ok(choice) = result.await {
+				let exec = Exec::call("place_jump", vec![choice.to_string()]);
+				emit!(Call(exec.vec(), KeymapLayer::Manager));
+			}
+		});
+		false
+	}
+
+	// Pins the current directory to the end of the places
+	pub fn place_add(&mut self) -> bool {
+		if !self.cwd().is_regular() || !self.places.add(self.cwd().to_path_buf()) {
+			return false;
+		}
+		self.places.save();
+		self.places_visible()
+	}
+
+	// Unpins the place at `idx`, or the one of the current directory if not given
+	pub fn place_remove(&mut self, idx: Option<usize>) -> bool {
+		let Some(idx) = idx.or_else(|| self.places.position(self.cwd())) else {
+			return false;
+		};
+		if !self.places.remove(idx) {
+			return false;
+		}
+		self.places.save();
+		self.places_visible()
+	}
+
+	// Moves the place of the current directory up or down the list by `step`
+	pub fn place_move(&mut self, step: isize) -> bool {
+		let Some(idx) = self.places.position(self.cwd()) else {
+			return false;
+		};
+		if !self.places.shift(idx, step) {
+			return false;
+		}
+		self.places.save();
+		self.places_visible()
+	}
+}
+
+#[test]
+fn test_places() {
+	let mut p = Places::default();
+	assert!(p.add(PathBuf::from("/a")));
+	assert!(p.add(PathBuf::from("/b")));
+	assert!(!p.add(PathBuf::from("/a")));
+	assert!(p.add(PathBuf::from("/c")));
+	assert_eq!(p.position(Path::new("/c")), Some(2));
+
+	// As far as it can go, and no further
+	assert!(p.shift(2, -5));
+	assert_eq!(p.get(0), Some(Path::new("/c")));
+	assert!(!p.shift(0, -1));
+	assert!(p.shift(0, 1));
+	assert_eq!(p.inner, [PathBuf::from("/a"), PathBuf::from("/c"), PathBuf::from("/b")]);
+	assert!(!p.shift(3, 1));
+
+	assert!(p.remove(1));
+	assert!(!p.remove(2));
+	assert_eq!(p.position(Path::new("/c")), None);
+	assert_eq!(p.list(), [(Path::new("/a"), false), (Path::new("/b"), false)]);
+
+	assert_eq!(Places::name(Path::new("/a/b")), "b");
+	assert_eq!(Places::name(Path::new("/")), "/");
+}
@@ -1,20 +1,37 @@
-use std::{sync::atomic::Ordering, time::Duration};
+use std::{collections::HashMap, io::{self, ErrorKind}, sync::atomic::Ordering, time::{Duration, Instant}};
 
 use adaptor::ADAPTOR;
-use config::MANAGER;
+use config::{MANAGER, PREVIEW};
 use shared::{MimeKind, PeekError, Url, MIME_DIR};
 use tokio::{pin, task::JoinHandle};
-use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+use tokio_stream::StreamExt;
 
 use super::{Provider, INCR};
-use crate::{emit, files::{Files, FilesOp}};
+use crate::{emit, files::{Files, FilesOp}, HighlightLimit, HighlightMatch};
+
+// The type the diff of two files is locked as, apart from the preview of the file hovered
+const MIME_DIFF: &str = "inode/x-diff";
+
+// How long the files of a directory previewed are good for, before they're read again
+const FOLDER_FRESH: Duration = Duration::from_secs(2);
 
 #[derive(Default)]
 pub struct Preview {
-	pub lock: Option<PreviewLock>,
-	skip:     usize,
+	pub lock:    Option<PreviewLock>,
+	skip:        usize,
+	pub matches: Option<PreviewMatches>,
+	hex:         bool,
+	diff:        Option<PreviewDiff>,
+	pin:         Option<PreviewPin>,
+	// Whether the keys moving the cursor scroll the preview instead
+	focused:     bool,
+	// The file read in full for its preview, rather than up to `max_read` of it
+	full:        Option<Url>,
 
 	handle: Option<JoinHandle<()>>,
+	last:   Option<Instant>,
+	// The directories previewed lately, with when they were read
+	read:   HashMap<Url, Instant>,
 }
 
 pub struct PreviewLock {
@@ -24,15 +41,58 @@ pub struct PreviewLock {
 	pub data: PreviewData,
 }
 
+// The lines with the matches of a content search in a file, and the one navigated to
+#[derive(Debug)]
+pub struct PreviewMatches {
+	pub url:     Url,
+	pub lines:   Vec<HighlightMatch>,
+	pub current: usize,
+}
+
+// The two files compared, `old` against `new`, in place of the preview of the hovered file,
+// for as long as it stays hovered
+#[derive(Debug)]
+pub struct PreviewDiff {
+	pub old:     Url,
+	pub new:     Url,
+	pub hovered: Url,
+}
+
+// The file kept in the preview while the other ones are hovered, until it's released
+#[derive(Debug)]
+pub struct PreviewPin {
+	pub url:  Url,
+	pub mime: String,
+}
+
 #[derive(Debug)]
 pub enum PreviewData {
 	Folder,
 	Text(String),
+	// The lines of a text file, the number of lines gone through in it so far, and the limit of the
+	// highlighting hit in it, if any
+	Code(String, usize, Option<HighlightLimit>),
+	// The rows of a hex dump
+	Hex(String),
 	Image,
+	// What's shown of a file with nothing else to show, rather than a blank pane
+	Placeholder(PreviewPlaceholder),
+}
+
+#[derive(Debug)]
+pub struct PreviewPlaceholder {
+	pub message: String,
+	// Whether it's there because the file can't be read, or previewed, styled as an error
+	pub error:   bool,
+	// The size and the type of the file
+	pub detail:  String,
 }
 
 impl Preview {
 	pub fn go(&mut self, url: &Url, mime: &str, show_image: bool) {
+		let diff = self.diff_of(url);
+		let mime = if diff.is_some() { MIME_DIFF } else { mime };
+
 		let kind = MimeKind::new(mime);
 		if !show_image && kind.show_as_image() {
 			return;
@@ -43,23 +103,49 @@ impl Preview {
 		self.reset(|_| true);
 		if !self.same_mime(url, mime) {
 			self.skip = 0;
+			if self.matches.as_ref().is_some_and(|m| &m.url == url) {
+				self.reveal_match();
+			} else {
+				self.matches = None;
+			}
 		}
 
-		let (url, mime, skip) = (url.clone(), mime.to_owned(), self.skip);
+		let delay = self.debounce();
+		let (url, mime, skip, hex) = (url.clone(), mime.to_owned(), self.skip, self.hex);
+		let full = self.full.as_ref() == Some(&url);
 		self.handle = Some(tokio::spawn(async move {
-			match Provider::auto(kind, &url, skip).await {
-				Ok(data) => {
-					emit!(Preview(PreviewLock { url, mime, skip, data }));
-				}
-				Err(PeekError::Exceed(max)) => {
-					emit!(Peek(max, url));
-				}
-				_ => {}
-			}
+			tokio::time::sleep(delay).await;
+			Self::provide(url, mime, skip, hex, full, diff).await;
 		}));
 	}
 
+	// The preview of the file, or the diff of the two files compared in place of it
+	async fn provide(
+		url: Url,
+		mime: String,
+		skip: usize,
+		hex: bool,
+		full: bool,
+		diff: Option<(Url, Url)>,
+	) {
+		let result = match diff {
+			Some((old, new)) => Provider::diff(&old, &new, skip).await,
+			None if url.is_sftp() => Provider::remote(&mime, &url, skip, hex, full).await,
+			None => Provider::auto(&mime, &url, skip, hex, full).await,
+		};
+		match result {
+			Ok(data) => {
+				emit!(Preview(PreviewLock { url, mime, skip, data }));
+			}
+			Err(PeekError::Exceed(max)) => {
+				emit!(Peek(max, url));
+			}
+			_ => {}
+		}
+	}
+
 	pub fn folder(&mut self, url: &Url, position: Option<(usize, usize)>, sequent: bool) {
+		self.diff = None;
 		if let Some((_, len)) = position {
 			self.skip = self.skip.min(len.saturating_sub(MANAGER.layout.preview_height()));
 		}
@@ -82,29 +168,62 @@ impl Preview {
 			return;
 		}
 
-		let url = url.clone();
+		// Read a moment ago, as it's hovered again while going back and forth over the directories
+		let now = Instant::now();
+		self.read.retain(|_, at| now.duration_since(*at) < FOLDER_FRESH);
+		if position.is_some() && self.read.contains_key(url) {
+			return;
+		}
+		self.read.insert(url.clone(), now);
+
+		let (url, skip, delay) = (url.clone(), self.skip, self.debounce());
 		self.handle = Some(tokio::spawn(async move {
-			let Ok(rx) = Files::from_dir(&url).await else {
-				emit!(Files(FilesOp::IOErr(url)));
-				return;
+			tokio::time::sleep(delay).await;
+			let rx = match Files::stream_dir(&url).await {
+				Ok(rx) => rx,
+				Err(e) => {
+					let denied = e.downcast_ref::<io::Error>().map(|e| e.kind());
+					let message = if denied == Some(ErrorKind::PermissionDenied) {
+						"(permission denied)".to_owned()
+					} else {
+						format!("(can't be read: {e})")
+					};
+
+					let detail = MIME_DIR.to_owned();
+					emit!(Files(FilesOp::IOErr(url.clone())));
+					emit!(Preview(PreviewLock {
+						url,
+						mime: MIME_DIR.to_owned(),
+						skip,
+						data: PreviewData::Placeholder(PreviewPlaceholder { message, error: true, detail }),
+					}));
+					return;
+				}
 			};
 
 			if position.is_some() {
-				emit!(Files(FilesOp::Full(url, UnboundedReceiverStream::new(rx).collect().await)));
+				let files: Vec<Vec<_>> = rx.collect().await;
+				emit!(Files(FilesOp::Full(url, files.concat())));
 				return;
 			}
-
-			let rx = UnboundedReceiverStream::new(rx).chunks_timeout(10000, Duration::from_millis(500));
 			pin!(rx);
 
-			let ticket = FilesOp::prepare(&url);
+			// Read to the end with none in it, to be told apart from one still being read
+			let (ticket, mut empty) = (FilesOp::prepare(&url), true);
 			while let Some(chunk) = rx.next().await {
+				empty = false;
 				emit!(Files(FilesOp::Part(url.clone(), ticket, chunk)));
 			}
+			if empty {
+				emit!(Files(FilesOp::Full(url, Vec::new())));
+			}
 		}));
 	}
 
 	pub fn sequent(&mut self, url: &Url, mime: &str, show_image: bool) {
+		let diff = self.diff_of(url);
+		let mime = if diff.is_some() { MIME_DIFF } else { mime };
+
 		let kind = MimeKind::new(mime);
 		if !show_image && kind.show_as_image() {
 			return;
@@ -115,32 +234,132 @@ impl Preview {
 		self.handle.take().map(|h| h.abort());
 		INCR.fetch_add(1, Ordering::Relaxed);
 
-		let (url, mime, skip) = (url.clone(), mime.to_owned(), self.skip);
-		self.handle = Some(tokio::spawn(async move {
-			match Provider::auto(kind, &url, skip).await {
-				Ok(data) => {
-					emit!(Preview(PreviewLock { url, mime, skip, data }));
-				}
-				Err(PeekError::Exceed(max)) => {
-					emit!(Peek(max, url));
-				}
-				_ => {}
-			}
-		}));
+		let (url, mime, skip, hex) = (url.clone(), mime.to_owned(), self.skip, self.hex);
+		let full = self.full.as_ref() == Some(&url);
+		self.handle = Some(tokio::spawn(Self::provide(url, mime, skip, hex, full, diff)));
+	}
+
+	// Compares two files in place of the preview of the hovered one, or stops it if not given
+	pub fn set_diff(&mut self, diff: Option<PreviewDiff>) -> bool {
+		if diff.is_none() && self.diff.is_none() {
+			return false;
+		}
+
+		self.diff = diff;
+		self.skip = 0;
+		self.reset(|_| true);
+		emit!(Peek);
+		true
+	}
+
+	// The files compared if it's `url` that's hovered, and forgets them once it isn't
+	fn diff_of(&mut self, url: &Url) -> Option<(Url, Url)> {
+		self.diff = self.diff.take().filter(|d| &d.hovered == url);
+		self.diff.as_ref().map(|d| (d.old.clone(), d.new.clone()))
+	}
+
+	// Keeps the file in the preview whatever's hovered, or releases the one kept if not given
+	pub fn set_pin(&mut self, pin: Option<PreviewPin>) -> bool {
+		if pin.is_none() && self.pin.is_none() {
+			return false;
+		}
+
+		self.pin = pin;
+		emit!(Peek);
+		true
+	}
+
+	// Gives the keys moving the cursor to the preview, or back to the list, toggles it if `state`
+	// is omitted
+	pub fn set_focus(&mut self, state: Option<bool>) -> bool {
+		let state = state.unwrap_or(!self.focused);
+		if state == self.focused {
+			return false;
+		}
+
+		self.focused = state;
+		true
 	}
 
 	pub fn arrow(&mut self, step: isize) -> bool {
-		let Some(kind) = self.lock.as_ref().map(|l| MimeKind::new(&l.mime)) else {
+		let Some(lock) = self.lock.as_ref().filter(|l| !matches!(l.data, PreviewData::Placeholder(_)))
+		else {
 			return false;
 		};
 
 		let old = self.skip;
-		let size = Provider::step_size(kind, step.unsigned_abs());
+		let text = matches!(lock.data, PreviewData::Text(_) | PreviewData::Hex(_));
+		let size = Provider::step_size(MimeKind::new(&lock.mime), step.unsigned_abs(), text);
 
 		self.skip = if step < 0 { old.saturating_sub(size) } else { old + size };
 		self.skip != old
 	}
 
+	// Turns the hex dump on or off for all files, toggles it if `state` is omitted
+	pub fn set_hex(&mut self, state: Option<bool>) -> bool {
+		let state = state.unwrap_or(!self.hex);
+		if state == self.hex {
+			return false;
+		}
+
+		self.hex = state;
+		self.skip = 0;
+		self.reset(|_| true);
+		emit!(Peek);
+		true
+	}
+
+	// Reads the whole of the text file previewed, rather than up to `max_read` of it
+	pub fn set_full(&mut self) -> bool {
+		let Some(url) = self.lock.as_ref().map(|l| l.url.clone()) else {
+			return false;
+		};
+		if self.full.as_ref() == Some(&url) {
+			return false;
+		}
+
+		self.full = Some(url);
+		self.reset(|_| true);
+		emit!(Peek);
+		true
+	}
+
+	pub fn set_matches(&mut self, matches: PreviewMatches) -> bool {
+		let same = self.same_path(&matches.url);
+		self.matches = Some(matches);
+		same && self.reveal_match()
+	}
+
+	// Moves to the next or previous match by `step`, or the first one in view if it's 0,
+	// and scrolls the match into view if it's out of it
+	pub fn jump_match(&mut self, step: isize) -> bool {
+		let Some(m) = self.matches.as_mut().filter(|m| !m.lines.is_empty()) else {
+			return false;
+		};
+
+		let height = MANAGER.layout.preview_height();
+		let in_view = |line: usize| line > self.skip && line <= self.skip + height;
+		if step == 0 {
+			if let Some(i) = m.lines.iter().position(|l| in_view(l.line)) {
+				m.current = i;
+			}
+		} else {
+			m.current = (m.current as isize + step).rem_euclid(m.lines.len() as isize) as usize;
+		}
+		self.reveal_match()
+	}
+
+	// Scrolls the match navigated to into view, if it's out of it, without moving to another one
+	fn reveal_match(&mut self) -> bool {
+		let Some(line) = self.matches.as_ref().and_then(|m| m.lines.get(m.current)).map(|l| l.line)
+		else {
+			return false;
+		};
+
+		self.skip = reveal(self.skip, line, MANAGER.layout.preview_height());
+		true
+	}
+
 	pub fn arrow_max(&mut self, max: usize) -> bool {
 		if self.skip > max {
 			self.skip = max;
@@ -150,6 +369,14 @@ impl Preview {
 		false
 	}
 
+	// How long to wait before previewing, the debounce if the last preview was asked for within it,
+	// so that one superseded while waiting, by the next file hovered, is aborted before it starts
+	fn debounce(&mut self) -> Duration {
+		let now = Instant::now();
+		let delay = debounce_delay(self.last.replace(now), now, PREVIEW.debounce);
+		Duration::from_millis(delay)
+	}
+
 	pub fn reset<F: FnOnce(&PreviewLock) -> bool>(&mut self, f: F) -> bool {
 		self.handle.take().map(|h| h.abort());
 		INCR.fetch_add(1, Ordering::Relaxed);
@@ -176,6 +403,32 @@ impl Preview {
 	#[inline]
 	pub fn skip(&self) -> usize { self.skip }
 
+	#[inline]
+	pub fn hex(&self) -> bool { self.hex }
+
+	#[inline]
+	pub fn diff(&self) -> Option<&PreviewDiff> { self.diff.as_ref() }
+
+	#[inline]
+	pub fn pin(&self) -> Option<&PreviewPin> { self.pin.as_ref() }
+
+	#[inline]
+	pub fn focused(&self) -> bool { self.focused }
+
+	// The line of the file at `url` to edit it at, the match navigated to if it's in view, or else
+	// the top one in view, where the file is previewed as it is, with its lines as they are
+	pub fn line(&self, url: &Url) -> Option<usize> {
+		let lock = self.lock.as_ref().filter(|l| &l.url == url)?;
+		if !matches!(lock.data, PreviewData::Code(..)) {
+			return None;
+		}
+
+		let height = MANAGER.layout.preview_height();
+		let hit = self.matches.as_ref().filter(|m| &m.url == url).and_then(|m| m.lines.get(m.current));
+		let hit = hit.map(|h| h.line).filter(|&l| l > lock.skip && l <= lock.skip + height);
+		Some(hit.unwrap_or(lock.skip + 1))
+	}
+
 	#[inline]
 	pub fn same(&self, url: &Url, mime: &str) -> bool {
 		if let Some(ref lock) = self.lock {
@@ -208,3 +461,43 @@ impl PreviewLock {
 	#[inline]
 	pub fn is_folder(&self) -> bool { matches!(self.data, PreviewData::Folder) }
 }
+
+#[inline]
+fn debounce_delay(last: Option<Instant>, now: Instant, debounce: u64) -> u64 {
+	match last {
+		Some(last) if now.duration_since(last) < Duration::from_millis(debounce) => debounce,
+		_ => 0,
+	}
+}
+
+// Where it's scrolled to from `skip` for `line` to be in view of `height` lines, to the middle of
+// the view if it's out of it
+fn reveal(skip: usize, line: usize, height: usize) -> usize {
+	if line > skip && line <= skip + height { skip } else { line.saturating_sub(1 + height / 2) }
+}
+
+#[test]
+fn test_debounce_delay() {
+	let now = Instant::now();
+	assert_eq!(debounce_delay(None, now, 50), 0);
+	assert_eq!(debounce_delay(Some(now), now + Duration::from_millis(10), 50), 50);
+	assert_eq!(debounce_delay(Some(now), now + Duration::from_millis(60), 50), 0);
+	assert_eq!(debounce_delay(Some(now), now, 0), 0);
+}
+
+#[test]
+fn test_reveal() {
+	assert_eq!(reveal(0, 1, 10), 0);
+	assert_eq!(reveal(0, 10, 10), 0);
+	assert_eq!(reveal(0, 11, 10), 5);
+	assert_eq!(reveal(20, 20, 10), 14);
+	assert_eq!(reveal(20, 3, 10), 0);
+}
+
+#[test]
+fn test_set_focus() {
+	let mut preview = Preview::default();
+	assert!(preview.set_focus(None) && preview.focused());
+	assert!(!preview.set_focus(Some(true)) && preview.focused());
+	assert!(preview.set_focus(Some(false)) && !preview.focused());
+}
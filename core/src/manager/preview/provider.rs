@@ -1,39 +1,145 @@
-use std::{io::BufRead, path::Path, sync::atomic::{AtomicUsize, Ordering}};
+use std::{io::{ErrorKind, SeekFrom}, path::Path, sync::atomic::{AtomicUsize, Ordering}, time::Duration};
 
 use adaptor::ADAPTOR;
-use anyhow::anyhow;
-use config::{MANAGER, PREVIEW};
+use config::{KEYMAP, MANAGER, PREVIEW};
 use futures::TryFutureExt;
-use shared::{MimeKind, PeekError};
-use syntect::{easy::HighlightFile, util::as_24_bit_terminal_escaped};
-use tokio::fs;
+use ratatui::layout::Rect;
+use shared::{MimeKind, PeekError, Url};
+use tokio::{fs, io::{AsyncReadExt, AsyncSeekExt}};
 
-use super::PreviewData;
-use crate::{external, highlighter};
+use super::{PreviewData, PreviewPlaceholder};
+use crate::{external::{self, Diffed, PreviewerOutput}, sftp, page_breaks, DocumentMeta, hex_columns, hexdump, highlight_diff, highlighted, highlighter, is_binary, HighlightLimit, HighlightQuota, HEX_SNIFF};
 
 pub(super) struct Provider;
 
 pub(super) static INCR: AtomicUsize = AtomicUsize::new(0);
 
+// How much of a remote file is downloaded for its preview at most
+const REMOTE_MAX: u64 = 16 * 1024 * 1024;
+
 impl Provider {
+	// The preview of the file, or a placeholder telling why there's none, e.g. it can't be read
 	pub(super) async fn auto(
-		kind: MimeKind,
+		mime: &str,
+		path: &Path,
+		skip: usize,
+		hex: bool,
+		full: bool,
+	) -> Result<PreviewData, PeekError> {
+		let ticket = INCR.load(Ordering::Relaxed);
+		match Self::peek(mime, path, skip, hex, full).await {
+			// Unless it's failed for a newer preview on the way, which it mustn't take the place of
+			Err(PeekError::Unexpected(e)) if ticket == INCR.load(Ordering::Relaxed) => {
+				let denied = fs::File::open(path).await.err().map(|e| e.kind());
+				Ok(if denied == Some(ErrorKind::PermissionDenied) {
+					Self::placeholder(path, mime, "(permission denied)", true).await
+				} else {
+					Self::placeholder(path, mime, format!("(can't be previewed: {e})"), true).await
+				})
+			}
+			result => result,
+		}
+	}
+
+	// The preview of a remote file, by the copy of it downloaded to the cache, which is only the head
+	// of one too large, good enough for text only
+	pub(super) async fn remote(
+		mime: &str,
+		url: &Url,
+		skip: usize,
+		hex: bool,
+		full: bool,
+	) -> Result<PreviewData, PeekError> {
+		let placeholder = |message: String, error: bool, detail: String| {
+			PreviewData::Placeholder(PreviewPlaceholder { message, error, detail })
+		};
+		let meta = match sftp::metadata(url, true).await {
+			Ok(meta) => meta,
+			Err(e) => return Ok(placeholder(format!("(can't be fetched: {e})"), true, mime.to_owned())),
+		};
+
+		let detail = format!("{}, {mime}", MANAGER.readable_size(meta.len()));
+		let text = hex || matches!(MimeKind::new(mime), MimeKind::Text | MimeKind::JSON);
+		if meta.len() > REMOTE_MAX && !text {
+			return Ok(placeholder("(too large to preview remotely)".to_owned(), false, detail));
+		}
+
+		match sftp::fetch(url, &meta, REMOTE_MAX).await {
+			Ok(path) => Self::auto(mime, &path, skip, hex, full).await,
+			Err(e) => Ok(placeholder(format!("(can't be fetched: {e})"), true, detail)),
+		}
+	}
+
+	// Of a text file, all of it is read if `full`, rather than up to `max_read` of it
+	async fn peek(
+		mime: &str,
 		path: &Path,
 		skip: usize,
+		hex: bool,
+		full: bool,
 	) -> Result<PreviewData, PeekError> {
+		let kind = MimeKind::new(mime);
+		if hex && kind != MimeKind::Empty {
+			return Provider::hex(path, skip).await.map(PreviewData::Hex);
+		}
+
+		// Falls back to the built-in previews if the previewer fails
+		if let Some(previewer) = PREVIEW.previewer(path, mime) {
+			match Provider::custom(&previewer.exec, path, skip).await {
+				Err(PeekError::Unexpected(_)) => {}
+				result => return result,
+			}
+		}
+
+		// A text file not in UTF-8 is rather told as one, for the hex dump of it to be asked for
+		if kind == MimeKind::Text && Self::binary(path).await? {
+			let hex = KEYMAP.manager.iter().find(|c| c.exec() == "hex").map(|c| c.on());
+			let message = hex.map_or("(not UTF-8, see `hex`)".into(), |on| {
+				format!("(not UTF-8, press {on} for hex)")
+			});
+			return Ok(Self::placeholder(path, mime, message, false).await);
+		} else if kind == MimeKind::Others && Self::binary(path).await? {
+			return Provider::hex(path, skip).await.map(PreviewData::Hex);
+		}
+
 		match kind {
-			MimeKind::Empty => Err("Empty file".into()),
+			MimeKind::Empty => Ok(Self::placeholder(path, mime, "(empty file)", false).await),
 			MimeKind::Archive => Provider::archive(path, skip).await.map(PreviewData::Text),
 			MimeKind::Image => Provider::image(path).await,
 			MimeKind::Video => Provider::video(path, skip).await,
-			MimeKind::JSON => Provider::json(path, skip).await.map(PreviewData::Text),
+			MimeKind::JSON => Provider::json(path, skip, full).await.map(PreviewData::Text),
+			MimeKind::PDF if PREVIEW.pdf_as_text => Provider::document(kind, path, skip).await,
 			MimeKind::PDF => Provider::pdf(path, skip).await,
-			MimeKind::Text => Provider::highlight(path, skip).await.map(PreviewData::Text),
-			MimeKind::Others => Err("Unsupported mimetype".into()),
+			MimeKind::Document => Provider::document(kind, path, skip).await,
+			MimeKind::Text => {
+				let code = Provider::highlight(path, skip, full).await;
+				code.map(|(s, total, l)| PreviewData::Code(s, total, l))
+			}
+			MimeKind::Others => {
+				Ok(Self::placeholder(path, mime, "(no preview for the type)", false).await)
+			}
 		}
 	}
 
-	pub(super) fn step_size(kind: MimeKind, step: usize) -> usize {
+	async fn placeholder(
+		path: &Path,
+		mime: &str,
+		message: impl Into<String>,
+		error: bool,
+	) -> PreviewData {
+		let detail = match fs::metadata(path).await {
+			Ok(meta) => format!("{}, {mime}", MANAGER.readable_size(meta.len())),
+			Err(_) => mime.to_owned(),
+		};
+		PreviewData::Placeholder(PreviewPlaceholder { message: message.into(), error, detail })
+	}
+
+	// Text is scrolled by a tenth of the screen whatever the file is, e.g. a hex dump
+	pub(super) fn step_size(kind: MimeKind, step: usize, text: bool) -> usize {
+		if text {
+			return step * MANAGER.layout.preview_height() / 10;
+		}
+
 		match kind {
 			MimeKind::Empty => 0,
 			MimeKind::Archive => step * MANAGER.layout.preview_height() / 10,
@@ -41,6 +147,7 @@ impl Provider {
 			MimeKind::Video => step,
 			MimeKind::JSON => step * MANAGER.layout.preview_height() / 10,
 			MimeKind::PDF => 1,
+			MimeKind::Document => step * MANAGER.layout.preview_height() / 10,
 			MimeKind::Text => step * MANAGER.layout.preview_height() / 10,
 			MimeKind::Others => step * MANAGER.layout.preview_height() / 10,
 		}
@@ -69,61 +176,188 @@ impl Provider {
 		Self::image(&cache).await
 	}
 
-	pub(super) async fn json(path: &Path, skip: usize) -> Result<String, PeekError> {
+	pub(super) async fn json(path: &Path, skip: usize, full: bool) -> Result<String, PeekError> {
 		external::jq(path, skip, MANAGER.layout.preview_height())
-			.or_else(|_| Provider::highlight(path, skip))
+			.or_else(|_| Provider::highlight(path, skip, full).map_ok(|(s, ..)| s))
 			.await
 	}
 
+	// The unified diff of the two files compared, highlighted as one, or a placeholder telling
+	// they're the same, or binary, or why they can't be compared
+	pub(super) async fn diff(old: &Path, new: &Path, skip: usize) -> Result<PreviewData, PeekError> {
+		let limit = MANAGER.layout.preview_height();
+		let (message, error) = match external::diff(old, new, skip, limit).await {
+			Ok(Diffed::Lines(lines)) => {
+				let s = tokio::task::spawn_blocking(move || {
+					let (syntaxes, theme) = highlighter();
+					let tab_size = PREVIEW.tab_size as usize;
+					highlight_diff((syntaxes, &theme), &lines, tab_size, PREVIEW.highlight_max_line)
+				});
+				return Ok(PreviewData::Text(s.await??));
+			}
+			Ok(Diffed::Same) => ("(the files are the same)".to_owned(), false),
+			Ok(Diffed::Binary) => ("(binary files differ)".to_owned(), false),
+			Err(PeekError::Unexpected(e)) => (format!("(can't be compared: {e})"), true),
+			Err(e) => return Err(e),
+		};
+
+		let detail = format!("{} and {}", old.display(), new.display());
+		Ok(PreviewData::Placeholder(PreviewPlaceholder { message, error, detail }))
+	}
+
+	// The files in the archive as a tree, or why they can't be listed
 	pub(super) async fn archive(path: &Path, skip: usize) -> Result<String, PeekError> {
-		Ok(
-			external::lsar(path, skip, MANAGER.layout.preview_height())
-				.await?
-				.into_iter()
-				.map(|f| f.name)
-				.collect::<Vec<_>>()
-				.join("\n"),
-		)
+		let files = match external::lsar(path).await {
+			Ok(files) => files,
+			Err(PeekError::Unexpected(e)) if skip == 0 => return Ok(e),
+			Err(e) => return Err(e),
+		};
+
+		let rect = MANAGER.layout.preview_rect();
+		let lines = external::lsar_tree(&files, rect.width as usize);
+		let limit = rect.height as usize;
+		if skip == 0 && lines.is_empty() {
+			return Ok("The archive is empty".to_owned());
+		} else if skip > 0 && lines.len() < skip + limit {
+			return Err(PeekError::Exceed(lines.len().saturating_sub(limit)));
+		}
+
+		Ok(lines.into_iter().skip(skip).take(limit).collect::<Vec<_>>().join("\n"))
+	}
+
+	// The text of a document, or what's known about it if the text can't be extracted,
+	// e.g. without the tools installed
+	pub(super) async fn document(
+		kind: MimeKind,
+		path: &Path,
+		skip: usize,
+	) -> Result<PreviewData, PeekError> {
+		let rect = MANAGER.layout.preview_rect();
+		let text = if kind == MimeKind::PDF {
+			external::pdftotext(path, PREVIEW.document_pages)
+				.await
+				.map(|s| page_breaks(&s, rect.width as usize))
+		} else {
+			external::pandoc(path).await
+		};
+
+		let text = match text {
+			Ok(s) => s,
+			Err(e) => {
+				let (path, pdf) = (path.to_path_buf(), kind == MimeKind::PDF);
+				let meta = tokio::task::spawn_blocking(move || DocumentMeta::new(&path, pdf)).await??;
+				meta.render(&e.to_string())
+			}
+		};
+		Self::window(&text, skip, rect.height as usize).map(PreviewData::Text)
 	}
 
-	pub(super) async fn highlight(path: &Path, skip: usize) -> Result<String, PeekError> {
+	// The output of a previewer in the `[preview]` config, either text to scroll through,
+	// or an image to show at where it wants, within the pane
+	pub(super) async fn custom(
+		exec: &str,
+		path: &Path,
+		skip: usize,
+	) -> Result<PreviewData, PeekError> {
+		let rect = MANAGER.layout.preview_rect();
+		let output = external::previewer(exec, path, (rect.width, rect.height)).await?;
+
+		match &*output {
+			PreviewerOutput::Text(s) => {
+				Self::window(s, skip, rect.height as usize).map(PreviewData::Text)
+			}
+			PreviewerOutput::Image(image, area) => {
+				let area = area.map_or(rect, |(x, y, width, height)| {
+					let (x, y) = (rect.x.saturating_add(x), rect.y.saturating_add(y));
+					Rect { x, y, width, height }.intersection(rect)
+				});
+				ADAPTOR.image_show(image, area).await?;
+				Ok(PreviewData::Image)
+			}
+		}
+	}
+
+	fn window(s: &str, skip: usize, limit: usize) -> Result<String, PeekError> {
+		let lines = s.lines().count();
+		if skip > 0 && lines < skip + limit {
+			return Err(PeekError::Exceed(lines.saturating_sub(limit)));
+		}
+		Ok(s.lines().skip(skip).take(limit).collect::<Vec<_>>().join("\n"))
+	}
+
+	async fn binary(path: &Path) -> Result<bool, PeekError> {
+		let mut head = Vec::with_capacity(HEX_SNIFF);
+		fs::File::open(path).await?.take(HEX_SNIFF as u64).read_to_end(&mut head).await?;
+		Ok(is_binary(&head))
+	}
+
+	// The rows of the bytes in view, as many per row as fit in the width of the preview
+	pub(super) async fn hex(path: &Path, skip: usize) -> Result<String, PeekError> {
+		let rect = MANAGER.layout.preview_rect();
+		let (columns, limit) = (hex_columns(rect.width as usize), rect.height as usize);
+
+		let mut file = fs::File::open(path).await?;
+		let rows = (file.metadata().await?.len() as usize).div_ceil(columns);
+		if skip > 0 && rows < skip + limit {
+			return Err(PeekError::Exceed(rows.saturating_sub(limit)));
+		}
+
+		let offset = (skip * columns) as u64;
+		let mut bytes = Vec::with_capacity(limit * columns);
+		file.seek(SeekFrom::Start(offset)).await?;
+		file.take((limit * columns) as u64).read_to_end(&mut bytes).await?;
+		Ok(hexdump(&bytes, offset, columns))
+	}
+
+	// The highlighted lines, the number of lines gone through in the file, and the limit of the
+	// quota hit in it, if any. Not more than the lines in view and one more screen ahead are read
+	// from their nearest checkpoint, rather than the whole file
+	pub(super) async fn highlight(
+		path: &Path,
+		skip: usize,
+		full: bool,
+	) -> Result<(String, usize, Option<HighlightLimit>), PeekError> {
 		let ticket = INCR.load(Ordering::Relaxed);
 		let path = path.to_path_buf();
-		let spaces = " ".repeat(PREVIEW.tab_size as usize);
+		let limit = MANAGER.layout.preview_height();
 
 		let (syntaxes, theme) = highlighter();
-		tokio::task::spawn_blocking(move || -> Result<String, PeekError> {
-			let mut h = HighlightFile::new(path, syntaxes, theme)?;
-			let mut line = String::new();
-			let mut buf = String::new();
-
-			let mut i = 0;
-			let limit = MANAGER.layout.preview_height();
-			while h.reader.read_line(&mut line)? > 0 {
-				if ticket != INCR.load(Ordering::Relaxed) {
-					return Err("Highlighting cancelled".into());
-				}
-
-				i += 1;
-				if i > skip + limit {
-					break;
-				}
-
-				line = line.replace('\t', &spaces);
-				let regions = h.highlight_lines.highlight_line(&line, syntaxes).map_err(|e| anyhow!(e))?;
-
-				if i > skip {
-					buf.push_str(&as_24_bit_terminal_escaped(&regions, false));
-				}
-				line.clear();
+		let mtime = fs::metadata(&path).await?.modified().ok();
+
+		let quota = HighlightQuota {
+			line: PREVIEW.highlight_max_line,
+			time: Duration::from_millis(PREVIEW.highlight_max_time),
+			size: PREVIEW.highlight_max_size,
+			read: if full { 0 } else { PREVIEW.max_read },
+		};
+		highlighted(path.clone(), mtime, theme.clone(), move |h| -> Result<_, PeekError> {
+			// Cut short before, and highlighted again from the top to be read to the end
+			if full && h.truncated {
+				*h = Default::default();
+			}
+			if !h.covers(skip, limit) {
+				// The memory limit is shared by the lines in view and ahead
+				let cap = (PREVIEW.max_bytes / (limit * 2).max(1)).max(1);
+				h.window(
+					&path,
+					(syntaxes, &theme),
+					skip,
+					limit * 2,
+					cap,
+					PREVIEW.tab_size as usize,
+					quota,
+					|| ticket != INCR.load(Ordering::Relaxed),
+				)?;
 			}
 
-			if skip > 0 && i < skip + limit {
-				return Err(PeekError::Exceed(i.saturating_sub(limit)));
+			if skip > 0 && h.complete && h.scanned < skip + limit {
+				return Err(PeekError::Exceed(h.scanned.saturating_sub(limit)));
 			}
 
+			let mut buf: String =
+				h.lines.iter().skip(skip - h.start).take(limit).map(String::as_str).collect();
 			buf.push_str("\x1b[0m");
-			Ok(buf)
+			Ok((buf, h.scanned, h.limit))
 		})
 		.await?
 	}
@@ -0,0 +1,42 @@
+use config::keymap::Exec;
+
+use super::Manager;
+
+// The commands `repeat` leaves out, the ones that move around, or show something, rather than
+// doing something to the files there, and `repeat` itself
+const SKIPPED: &[&str] = &[
+	"escape", "quit", "close", "suspend", "palette", "count", "arrow", "arrow_kind", "goto",
+	"peek", "peek_match", "preview_focus", "preview_pin", "preview_full", "leave", "enter", "tree",
+	"expand", "collapse", "back", "forward", "cd", "reveal", "visual_mode", "search", "jump",
+	"bookmark_jump", "place_jump", "find", "find_arrow", "tab_create", "tab_close", "tab_switch",
+	"tab_swap", "pane_switch", "tasks_show", "help", "repeat",
+];
+
+impl Manager {
+	#[inline]
+	pub fn repeat(&self) -> Option<&Exec> { self.repeat.as_ref() }
+
+	// Keeps `exec` for `repeat`, unless it's one of those left out, or given an empty value, like
+	// the filter cleared once its prompt is canceled, so the one before it is kept
+	pub fn repeat_record(&mut self, exec: &Exec) {
+		if repeatable(exec) {
+			self.repeat = Some(exec.clone());
+		}
+	}
+}
+
+fn repeatable(exec: &Exec) -> bool {
+	!SKIPPED.contains(&exec.cmd.as_str()) && !exec.args.first().is_some_and(|s| s.is_empty())
+}
+
+#[test]
+fn test_repeatable() {
+	let exec = |s: &str| Exec::try_from(s).unwrap();
+	assert!(repeatable(&exec("shell 'ls -l' --confirm")));
+	assert!(repeatable(&exec("filter foo")));
+	assert!(repeatable(&exec("remove")));
+
+	assert!(!repeatable(&exec("arrow 1")));
+	assert!(!repeatable(&exec("repeat")));
+	assert!(!repeatable(&exec("filter ''")));
+}
@@ -0,0 +1,108 @@
+use std::path::PathBuf;
+
+use config::{manager::SortBy, BOOT};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::Visit;
+
+// The tabs open, with the index of the active one, kept on exit for the next start to restore
+// them as they were, see `restore_tabs` in the `[manager]` config and `--restore`
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub(super) struct Session {
+	pub(super) idx:  usize,
+	pub(super) tabs: Vec<SessionTab>,
+}
+
+// A tab with its directory, the file hovered and the ones selected in it, and how it's sorted
+// and filtered
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub(super) struct SessionTab {
+	#[serde(flatten)]
+	pub(super) visit:          Visit,
+	pub(super) sort_by:        SortBy,
+	pub(super) sort_sensitive: bool,
+	pub(super) sort_reverse:   bool,
+	pub(super) sort_dir_first: bool,
+	pub(super) show_hidden:    bool,
+	pub(super) filter:         Option<SessionFilter>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub(super) struct SessionFilter {
+	pub(super) raw:       String,
+	pub(super) glob:      bool,
+	pub(super) sensitive: bool,
+}
+
+impl Session {
+	#[inline]
+	fn path() -> PathBuf { BOOT.state_dir.join("session.json") }
+
+	// The session saved, without the tabs of the directories gone since, which are told of. `None`
+	// if there's no session, or none of the tabs are left
+	pub(super) fn load() -> Option<(Self, Vec<PathBuf>)> {
+		let s = std::fs::read_to_string(Self::path()).ok()?;
+		let session: Self =
+			serde_json::from_str(&s).map_err(|e| warn!("failed to load the session: {e}")).ok()?;
+		Some(session.prune()).filter(|(s, _)| !s.tabs.is_empty())
+	}
+
+	// Drops the tabs gone, where the active one stays so, or the one after it takes the place of it
+	fn prune(self) -> (Self, Vec<PathBuf>) {
+		let (mut idx, mut tabs, mut gone) = (0, vec![], vec![]);
+		for (i, tab) in self.tabs.into_iter().enumerate() {
+			if !tab.visit.cwd.is_dir() {
+				gone.push(tab.visit.cwd);
+				continue;
+			}
+			idx += (i < self.idx) as usize;
+			tabs.push(tab);
+		}
+
+		let idx = idx.min(tabs.len().saturating_sub(1));
+		(Self { idx, tabs }, gone)
+	}
+
+	pub(super) fn save(&self) {
+		let result = serde_json::to_string(self).map_err(|e| e.to_string()).and_then(|s| {
+			std::fs::write(Self::path(), s).map_err(|e| e.to_string())
+		});
+		if let Err(e) = result {
+			warn!("failed to save the session: {e}");
+		}
+	}
+}
+
+#[test]
+fn test_session() {
+	let root = shared::TestDir::new("session");
+	let [a, b] = ["a", "b"].map(|s| root.join(s));
+	std::fs::create_dir_all(&a).unwrap();
+
+	let tab = |cwd: &PathBuf| SessionTab {
+		visit:          Visit { cwd: cwd.clone(), hovered: Some(cwd.join("x")), selected: vec![] },
+		sort_by:        SortBy::Modified,
+		sort_sensitive: false,
+		sort_reverse:   true,
+		sort_dir_first: true,
+		show_hidden:    false,
+		filter:         Some(SessionFilter { raw: "*.rs".to_owned(), glob: true, sensitive: false }),
+	};
+
+	let session = Session { idx: 2, tabs: vec![tab(&b), tab(&a), tab(&a)] };
+	let s = serde_json::to_string(&session).unwrap();
+	assert_eq!(serde_json::from_str::<Session>(&s).unwrap(), session);
+
+	// The tab of `b` is gone, and the active one is still the same
+	let (session, gone) = session.prune();
+	assert_eq!(gone, [b.clone()]);
+	assert_eq!(session.tabs, [tab(&a), tab(&a)]);
+	assert_eq!(session.idx, 1);
+
+	// Or the one after it, if it's the one gone
+	let session = Session { idx: 1, tabs: vec![tab(&a), tab(&b), tab(&a)] };
+	assert_eq!(session.prune().0.idx, 1);
+	let session = Session { idx: 1, tabs: vec![tab(&a), tab(&b)] };
+	assert_eq!(session.prune().0.idx, 0);
+}
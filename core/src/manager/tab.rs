@@ -1,26 +1,41 @@
-use std::{borrow::Cow, collections::{BTreeMap, BTreeSet}, ffi::{OsStr, OsString}, mem, time::Duration};
+use std::{borrow::Cow, collections::BTreeSet, env, ffi::{OsStr, OsString}, fs, mem, path::{Path, PathBuf}, time::Duration};
 
 use anyhow::{bail, Error, Result};
-use config::{keymap::{Exec, KeymapLayer}, open::Opener, MANAGER};
-use shared::{Debounce, Defer, InputError, Url};
-use tokio::{pin, task::JoinHandle};
-use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+use config::{keymap::{Exec, KeymapLayer}, manager::{ArrowEdge, LayoutMode, SortBy}, open::Opener, MANAGER, PREVIEW};
+use shared::{dir_id, expand_path, nearest_existing, readable_path, short_path, xattr_names, xattr_value, Debounce, Defer, InputError, Url};
+use tokio::{pin, process::Command, select, task::JoinHandle};
+use tokio_stream::{wrappers::UnboundedReceiverStream, Stream, StreamExt};
 
-use super::{Finder, Folder, Mode, Preview, PreviewLock};
-use crate::{emit, external::{self, FzfOpt, ZoxideOpt}, files::{File, FilesOp, FilesSorter}, input::InputOpt, Event, BLOCKER};
+use super::{Backstack, Finder, Folder, History, Mode, Preview, PreviewData, PreviewLock, PreviewMatches, Visit};
+use crate::{emit, external::{self, FzfOpt, Located, LocateOpt, ZoxideOpt}, HighlightMatch, files::{File, Files, FilesOp, FilesSorter, Filter, FilterKind, QuickFilter}, input::InputOpt, select::SelectOpt, sftp, Event, BLOCKER};
+
+// How many files at most the tree is expanded with at once, to stop short of walking a huge one
+const TREE_LIMIT: usize = 10000;
 
 pub struct Tab {
 	pub(super) mode:    Mode,
 	pub(super) current: Folder,
 	pub(super) parent:  Option<Folder>,
 
-	pub(super) history: BTreeMap<Url, Folder>,
-	pub(super) preview: Preview,
+	pub(super) history:   History,
+	pub(super) backstack: Backstack,
+	pub(super) preview:   Preview,
 
 	finder:                 Option<Finder>,
 	search:                 Option<JoinHandle<Result<()>>>,
-	pub(super) sorter:      FilesSorter,
-	pub(super) show_hidden: bool,
+	pub(super) sorter:           FilesSorter,
+	// The one of the parent directory on its own, or none to sort it by `sorter`
+	pub(super) parent_sorter:    Option<FilesSorter>,
+	pub(super) show_hidden:      bool,
+	pub(super) show_ignored:     bool,
+	pub(super) line_numbers:     bool,
+	pub(super) relative_numbers: bool,
+	// Showing the details of the hovered file in place of its preview
+	pub(super) info:             bool,
+	// The settings from before entering a directory having an override, to go back to after it
+	pub(super) stashed:          Option<(FilesSorter, bool, LayoutMode)>,
+	// Showing a directory typed in `cd --interactive`, not visited yet
+	pub(super) previewing:       bool,
 }
 
 impl From<Url> for Tab {
@@ -33,12 +48,22 @@ impl From<Url> for Tab {
 			parent,
 
 			history: Default::default(),
+			backstack: Default::default(),
 			preview: Default::default(),
 
 			finder: None,
 			search: None,
 			sorter: Default::default(),
+			parent_sorter: MANAGER
+				.sort_parent
+				.map(|by| FilesSorter { by, reverse: false, ..Default::default() }),
 			show_hidden: MANAGER.show_hidden,
+			show_ignored: MANAGER.show_ignored,
+			line_numbers: PREVIEW.line_numbers,
+			relative_numbers: MANAGER.relative_numbers,
+			info: false,
+			stashed: None,
+			previewing: false,
 		}
 	}
 }
@@ -54,9 +79,7 @@ impl Tab {
 			return true;
 		}
 
-		if let Some((_, indices)) = self.mode.visual() {
-			self.current.files.select_index(indices, Some(self.mode.is_select()));
-			self.mode = Mode::Normal;
+		if self.visual_end() {
 			return true;
 		}
 
@@ -64,6 +87,11 @@ impl Tab {
 			return true;
 		}
 
+		if self.current.set_filter(None) | self.current.set_quick(None) {
+			emit!(Hover);
+			return true;
+		}
+
 		self.search_stop()
 	}
 
@@ -74,7 +102,7 @@ impl Tab {
 			self.current.prev(step.unsigned_abs())
 		};
 		if !ok {
-			return false;
+			return step != 0 && self.arrow_edge(step > 0);
 		}
 
 		// Visual selection
@@ -91,7 +119,37 @@ impl Tab {
 		true
 	}
 
+	// Past the first file, or the last one if `down`, with the cursor there, see `arrow_edge`
+	fn arrow_edge(&mut self, down: bool) -> bool {
+		let len = self.current.files.len() as isize;
+		match MANAGER.arrow_edge {
+			ArrowEdge::Stop => false,
+			ArrowEdge::Wrap if len < 2 => false,
+			ArrowEdge::Wrap => self.arrow(if down { -len } else { len }),
+			ArrowEdge::Parent if !down => self.leave(),
+			ArrowEdge::Parent => {
+				let Some(parent) = &self.parent else {
+					return false;
+				};
+				let Some(i) = parent.files.position(&self.current.cwd) else {
+					return false;
+				};
+				if let Some(dir) = parent.files.iter().skip(i + 1).find(|f| f.is_dir()) {
+					emit!(Cd(dir.url_owned()));
+				}
+				false
+			}
+		}
+	}
+
 	pub async fn cd(&mut self, mut target: Url) -> bool {
+		if target.is_sftp() {
+			match Self::cd_remote(target).await {
+				Some(t) => target = t,
+				None => return false,
+			}
+		}
+
 		let Ok(file) = File::from(target.clone()).await else {
 			return false;
 		};
@@ -109,14 +167,51 @@ impl Tab {
 			return false;
 		}
 
+		self.record();
+		self.go(target, hovered);
+		self.arrive();
+		true
+	}
+
+	// The remote directory made absolute, e.g. the home of `sftp://host`, once the host is connected
+	// to, which is done aside the first time for the UI not to wait on it, and `cd` is called again
+	async fn cd_remote(target: Url) -> Option<Url> {
+		if !sftp::connected(&target) {
+			tokio::spawn(async move {
+				match sftp::canonicalize(&target).await {
+					Ok(target) => {
+						emit!(Cd(target));
+					}
+					// Failed to connect, which is told already, or after it has
+					Err(_) if !sftp::connected(&target) => {}
+					Err(e) => {
+						emit!(Message(format!("Can't change to {target:?}: {e}"), true));
+					}
+				}
+			});
+			return None;
+		}
+
+		match sftp::canonicalize(&target).await {
+			Ok(t) => Some(t),
+			Err(e) => {
+				emit!(Message(format!("Can't change to {target:?}: {e}"), true));
+				None
+			}
+		}
+	}
+
+	// Switches to `target`, hovering `hovered` if given, without leaving the current directory
+	// in the backstack
+	fn go(&mut self, target: Url, hovered: Option<File>) -> bool {
 		if let Some(rep) = self.parent.take() {
-			self.history.insert(rep.cwd.clone(), rep);
+			self.history.insert(rep);
 		}
 
 		let rep = self.history_new(&target);
 		let rep = mem::replace(&mut self.current, rep);
 		if rep.cwd.is_regular() {
-			self.history.insert(rep.cwd.clone(), rep);
+			self.history.insert(rep);
 		}
 
 		if let Some(parent) = target.parent_url() {
@@ -130,19 +225,94 @@ impl Tab {
 		true
 	}
 
+	// Asks for the path to change to, showing the directory typed in place of the current one as
+	// it's typed, and going back to where it was if canceled
 	pub fn cd_interactive(&mut self, target: Url) -> bool {
+		let origin = self.current.cwd.clone();
 		tokio::spawn(async move {
-			let mut result =
-				emit!(Input(InputOpt::top("Change directory:").with_value(target.to_string_lossy())));
+			let resolve = {
+				let origin = origin.clone();
+				move |s: &str| Url::parse_sftp(s).unwrap_or_else(|| origin.join(expand_path(s)))
+			};
+			// A remote one is told by its host, where the one typed isn't checked as it's typed
+			let arg = |u: &Url| if u.is_sftp() { format!("{u:?}") } else { u.to_string_lossy().into() };
+			let validate = resolve.clone();
+			let rx = emit!(Input(
+				InputOpt::top("Change directory:")
+					.with_value(target.to_string_lossy())
+					.with_realtime()
+					.with_history("cd")
+					.with_completion(&target)
+					.with_validator(move |s| match fs::metadata(validate(s)) {
+						_ if validate(s).is_sftp() => Ok(()),
+						Ok(_) => Ok(()),
+						Err(_) if s.is_empty() => Err("The path can't be empty".to_owned()),
+						Err(e) => Err(e.kind().to_string()),
+					})
+			));
 
-			if let Some(Ok(s)) = result.recv().await {
-				emit!(Cd(Url::from(s)));
+			let rx = Debounce::new(UnboundedReceiverStream::new(rx), Duration::from_millis(50));
+			pin!(rx);
+
+			while let Some(result) = rx.next().await {
+				// Relative to where it started, rather than to the directory shown
+				let (target, done) = match result {
+					Ok(s) => (resolve(&s), true),
+					Err(InputError::Typed(s)) if resolve(&s).is_dir() => (resolve(&s), false),
+					Err(InputError::Typed(_)) => continue,
+					Err(InputError::Canceled(_)) => (origin.clone(), true),
+				};
+
+				let exec = Exec::call("cd", vec![arg(&target)]);
+				let exec = if done { exec.with("origin", arg(&origin)) } else { exec };
+				emit!(Call(exec.with_bool("preview", !done).vec(), KeymapLayer::Manager));
+				if done {
+					break;
+				}
 			}
 		});
 		false
 	}
 
-	pub fn enter(&mut self) -> bool {
+	// Shows the directory in place of the current one, without visiting it
+	pub fn cd_preview(&mut self, target: Url) -> bool {
+		if !target.is_dir() || target == self.current.cwd {
+			return false;
+		}
+
+		self.previewing = true;
+		self.go(target, None)
+	}
+
+	// Back from the directory shown by `cd_preview` to `origin`, with the cursor as it was there
+	pub fn cd_unpreview(&mut self, origin: Url) -> bool {
+		if !mem::replace(&mut self.previewing, false) || origin == self.current.cwd {
+			return false;
+		}
+		self.go(origin, None)
+	}
+
+	// Goes to the parent of `target` with it hovered, or into it for a directory. If it's gone,
+	// to the nearest of its ancestors that exists instead, telling what's missing under it
+	pub fn reveal(&mut self, target: Url) -> bool {
+		let target = self.current.cwd.join(expand_path(&target));
+		match nearest_existing(&target) {
+			(_, None) => {
+				emit!(Cd(target));
+			}
+			(nearest, Some(rest)) => {
+				let text = format!("{} doesn't exist in {}", rest.display(), nearest.display());
+				emit!(Message(text, true));
+				emit!(Cd(Url::from(nearest)));
+			}
+		}
+		false
+	}
+
+	// Enters the hovered directory, or the one a symlink to it points to if `follow`, instead of
+	// the path of the link, and if `smart`, keeps going down while the one entered has nothing but a
+	// directory in it, as if `a/b/c` were collapsed into one
+	pub fn enter(&mut self, follow: bool, smart: bool) -> bool {
 		let Some(hovered) = self.current.hovered.clone() else {
 			return false;
 		};
@@ -150,21 +320,150 @@ impl Tab {
 			return false;
 		}
 
-		let rep = self.history_new(hovered.url());
+		let mut target = match fs::canonicalize(hovered.url()) {
+			Ok(p) if follow && hovered.is_link() && hovered.url().is_regular() => Url::from(p),
+			_ => hovered.url_owned(),
+		};
+		let smart = smart && target.is_regular();
+		while let Some(child) = smart.then(|| Self::only_child(&target)).flatten() {
+			target = Url::from(child);
+		}
+
+		self.record();
+		let rep = self.history_new(&target);
 		let rep = mem::replace(&mut self.current, rep);
 		if rep.cwd.is_regular() {
-			self.history.insert(rep.cwd.clone(), rep);
+			self.history.insert(rep);
 		}
 
 		if let Some(rep) = self.parent.take() {
-			self.history.insert(rep.cwd.clone(), rep);
+			self.history.insert(rep);
 		}
-		self.parent = Some(self.history_new(&hovered.parent().unwrap()));
+		self.parent = target.parent_url().map(|p| self.history_new(&p));
 
+		self.arrive();
 		emit!(Refresh);
 		true
 	}
 
+	// The only entry of `dir` if it's a directory, where a symlink to one doesn't count, so that
+	// going down through them never loops
+	fn only_child(dir: &Path) -> Option<PathBuf> {
+		let mut it = fs::read_dir(dir).ok()?;
+		let child = it.next()?.ok()?;
+		if it.next().is_some() || !child.file_type().ok()?.is_dir() {
+			return None;
+		}
+		Some(child.path())
+	}
+
+	// Expands the directories into a tree `depth` levels down, or collapses them if there's any,
+	// where the hidden ones and the symlinks aren't expanded
+	pub fn tree(&mut self, depth: Option<usize>) -> bool {
+		if self.current.files.has_expanded() {
+			return self.current.collapse(None);
+		}
+
+		let dirs = self.current.files.iter().filter(|f| Self::expandable(f)).map(|f| f.url_owned());
+		self.expand_dirs(dirs.collect(), depth.unwrap_or(MANAGER.tree_depth).min(MANAGER.max_depth));
+		false
+	}
+
+	// Expands the hovered directory a level down, so the files in it are listed under it, unless
+	// it's as deep as the tree goes, or a symlink back to a directory above it
+	pub fn expand(&mut self) -> bool {
+		let Some(h) = &self.current.hovered else {
+			return false;
+		};
+		if !h.is_dir() || self.current.files.is_expanded(h.url()) {
+			return false;
+		}
+
+		if self.current.files.depth(h.url()) >= MANAGER.max_depth {
+			emit!(Message("Not expanded (max depth)".to_owned(), false));
+		} else if h.is_link() && Self::loops(h.url()) {
+			emit!(Message("Not expanded (symlink loop)".to_owned(), false));
+		} else {
+			self.expand_dirs(vec![h.url_owned()], 1);
+		}
+		false
+	}
+
+	// Whether the symlink leads to a directory it's in, so expanding it would go round and round
+	fn loops(link: &Path) -> bool {
+		let (Ok(target), Some(parent)) = (fs::canonicalize(link), link.parent()) else {
+			return false;
+		};
+		fs::canonicalize(parent).is_ok_and(|p| p.starts_with(target))
+	}
+
+	// Collapses the hovered directory, or the one the hovered file is in, and keeps the cursor on it
+	pub fn collapse(&mut self) -> bool {
+		let Some(hovered) = self.current.hovered.as_ref().map(|h| h.url_owned()) else {
+			return false;
+		};
+
+		let files = &self.current.files;
+		let dir = if files.is_expanded(&hovered) {
+			hovered
+		} else if let Some(p) = hovered.parent_url().filter(|_| files.depth(&hovered) > 0) {
+			p
+		} else {
+			return false;
+		};
+
+		if !self.current.collapse(Some(&dir)) {
+			return false;
+		}
+		emit!(Hover);
+		true
+	}
+
+	#[inline]
+	fn expandable(file: &File) -> bool { file.is_dir() && !file.is_link() && !file.is_hidden() }
+
+	fn expand_dirs(&self, mut dirs: Vec<Url>, depth: usize) {
+		let cwd = self.current.cwd.clone();
+		tokio::spawn(async move {
+			let (mut total, mut visited) = (0, BTreeSet::new());
+			for _ in 0..depth {
+				let mut next = vec![];
+				for dir in dirs {
+					// Got to again, by a bind mount of a directory above it, which a remote one can't be
+					// told by, since the server doesn't say what device it's on
+					if dir.is_regular() {
+						let Ok(meta) = tokio::fs::metadata(&dir).await else {
+							continue;
+						};
+						if !visited.insert(dir_id(&meta, &dir).await) {
+							continue;
+						}
+					}
+					let Ok(rx) = Files::from_dir(&dir).await else {
+						continue;
+					};
+
+					let files: Vec<_> = UnboundedReceiverStream::new(rx).collect().await;
+					total += files.len();
+					next.extend(files.iter().filter(|f| Self::expandable(f)).map(|f| f.url_owned()));
+
+					emit!(Files(FilesOp::Expand(cwd.clone(), dir, files)));
+					if total > TREE_LIMIT {
+						return;
+					}
+				}
+				dirs = next;
+			}
+		});
+	}
+
+	// Moves the cursor to the file at `index`, or the last one if it's beyond
+	#[inline]
+	pub fn goto(&mut self, index: usize) -> bool {
+		let index = index.min(self.current.files.len().saturating_sub(1));
+		self.arrow(index as isize - self.current.cursor() as isize)
+	}
+
 	pub fn leave(&mut self) -> bool {
 		let current = self
 			.current
@@ -178,8 +477,9 @@ impl Tab {
 			return false;
 		};
 
+		self.record();
 		if let Some(rep) = self.parent.take() {
-			self.history.insert(rep.cwd.clone(), rep);
+			self.history.insert(rep);
 		}
 		if let Some(parent) = current.parent_url() {
 			self.parent = Some(self.history_new(&parent));
@@ -188,18 +488,68 @@ impl Tab {
 		let rep = self.history_new(&current);
 		let rep = mem::replace(&mut self.current, rep);
 		if rep.cwd.is_regular() {
-			self.history.insert(rep.cwd.clone(), rep);
+			self.history.insert(rep);
 		}
 
+		self.arrive();
 		emit!(Refresh);
 		true
 	}
 
-	// TODO
-	pub fn back(&mut self) -> bool { false }
+	pub async fn back(&mut self) -> bool {
+		match self.backstack.back(self.visit()) {
+			Some(there) => self.revisit(there).await,
+			None => false,
+		}
+	}
+
+	pub async fn forward(&mut self) -> bool {
+		match self.backstack.forward(self.visit()) {
+			Some(there) => self.revisit(there).await,
+			None => false,
+		}
+	}
+
+	// Leaves the current directory in the backstack, along with the files hovered and selected
+	fn record(&mut self) {
+		if self.current.cwd.is_regular() {
+			self.backstack.push(self.visit());
+			self.backstack.save();
+		}
+	}
+
+	pub(super) fn visit(&self) -> Visit {
+		Visit {
+			cwd:      self.current.cwd.to_path_buf(),
+			hovered:  self.current.hovered.as_ref().map(|h| h.url().to_path_buf()),
+			selected: self
+				.current
+				.files
+				.selected(&Default::default(), false)
+				.into_iter()
+				.map(|f| f.url().to_path_buf())
+				.collect(),
+		}
+	}
+
+	async fn revisit(&mut self, there: Visit) -> bool {
+		let hovered = match there.hovered {
+			Some(h) => File::from(Url::from(h)).await.ok(),
+			None => None,
+		};
+
+		self.go(Url::from(there.cwd), hovered);
+		for url in there.selected {
+			self.current.files.select(&Url::from(url), Some(true));
+		}
+		self.backstack.save();
+		self.arrive();
+		true
+	}
 
-	// TODO
-	pub fn forward(&mut self) -> bool { false }
+	// Tells the directory changed to is visited, unlike the ones shown in passing with `cd_preview`
+	#[inline]
+	fn arrive(&self) { emit!(Visited(self.current.cwd.clone())); }
 
 	pub fn select(&mut self, state: Option<bool>) -> bool {
 		if let Some(ref hovered) = self.current.hovered {
@@ -210,6 +560,64 @@ impl Tab {
 
 	pub fn select_all(&mut self, state: Option<bool>) -> bool { self.current.files.select_all(state) }
 
+	// The files selected in all of the directories, the current one, its parent, and the ones left
+	// with their selection kept in the history
+	pub fn selected_everywhere(&self) -> Vec<Url> {
+		let folders = [&self.current].into_iter().chain(&self.parent).chain(self.history.iter());
+		let mut urls: Vec<_> = folders.flat_map(|f| f.files.selected_urls()).collect();
+		urls.sort_unstable();
+		urls.dedup();
+		urls
+	}
+
+	// Unselects the files in all of the directories, see `selected_everywhere`
+	pub fn unselect_everywhere(&mut self) -> bool {
+		let mut b = self.current.files.select_all(Some(false));
+		for folder in self.parent.iter_mut().chain(self.history.iter_mut()) {
+			b |= folder.files.select_all(Some(false));
+		}
+		b
+	}
+
+	// Selects the files whose name matches `query`, or deselects them with `state` being false,
+	// where the pattern is asked for if not given
+	pub fn select_matches(
+		&mut self,
+		query: Option<&str>,
+		glob: bool,
+		sensitive: bool,
+		state: Option<bool>,
+	) -> bool {
+		let kind = if glob { FilterKind::Glob } else { FilterKind::Regex };
+		if let Some(query) = query {
+			return Filter::new(query, kind, sensitive)
+				.is_ok_and(|f| self.current.files.select_matches(&f, state));
+		}
+
+		let validator = move |s: &str| match Filter::new(s, kind, sensitive) {
+			_ if s.is_empty() => Err("The pattern can't be empty".to_owned()),
+			Ok(_) => Ok(()),
+			Err(e) => Err(e.to_string()),
+		};
+
+		tokio::spawn(async move {
+			let title = if state == Some(false) { "Deselect matching:" } else { "Select matching:" };
+			let mut result = emit!(Input(
+				InputOpt::top(title).with_history("select").with_validator(validator)
+			));
+
+			if let Some(Ok(s)) = result.recv().await {
+				let mut exec = Exec::call("select_matches", vec![s])
+					.with_bool("glob", glob)
+					.with_bool("sensitive", sensitive);
+				let state = state.map_or("none".to_owned(), |b| b.to_string());
+				exec.named.insert("state".to_owned(), state);
+				emit!(Call(exec.vec(), KeymapLayer::Manager));
+			}
+		});
+		false
+	}
+
 	pub fn visual_mode(&mut self, unset: bool) -> bool {
 		let idx = self.current.cursor();
 
@@ -221,9 +629,30 @@ impl Tab {
 		true
 	}
 
+	// Leaves the visual mode, selecting, or deselecting, the files gone over in it
+	pub fn visual_end(&mut self) -> bool {
+		let Some((_, indices)) = self.mode.visual() else {
+			return false;
+		};
+
+		self.current.files.select_index(indices, Some(self.mode.is_select()));
+		self.mode = Mode::Normal;
+		true
+	}
+
 	pub fn copy(&self, type_: &str) -> bool {
-		let mut s = OsString::new();
-		let mut it = self.selected().into_iter().peekable();
+		let (what, whats) = match type_ {
+			"path" => ("path", "paths"),
+			"dirname" => ("directory", "directories"),
+			"filename" | "name_without_ext" => ("name", "names"),
+			_ => return false,
+		};
+		let (mut s, selected) = (OsString::new(), self.selected());
+		if selected.is_empty() {
+			return false;
+		}
+
+		let mut it = selected.iter().peekable();
 		while let Some(f) = it.next() {
 			s.push(match type_ {
 				"path" => f.url_os_str(),
@@ -237,7 +666,57 @@ impl Tab {
 			}
 		}
 
-		futures::executor::block_on(external::clipboard_set(s)).ok();
+		let (text, error) = match futures::executor::block_on(external::clipboard_set(s)) {
+			Ok(()) if selected.len() == 1 => (format!("Copied the {what}"), false),
+			Ok(()) => (format!("Copied the {whats} of {} files", selected.len()), false),
+			Err(e) => (format!("Failed to copy the {what}: {e}"), true),
+		};
+		emit!(Message(text, error));
+		false
+	}
+
+	// Lists the extended attributes of the hovered file with their values, which are shown in hex
+	// unless they're text, and the entries of the ACLs as `getfacl` gives them if it's installed
+	pub fn xattrs(&self) -> bool {
+		let Some(hovered) = self.current.hovered.as_ref().map(|h| h.url_owned()) else {
+			return false;
+		};
+
+		tokio::spawn(async move {
+			let (mut items, mut acl) = (vec![], false);
+			for name in xattr_names(&hovered) {
+				acl |= name.starts_with("system.posix_acl_");
+				let value = xattr_value(&hovered, &name).unwrap_or_default();
+				let text = std::str::from_utf8(&value)
+					.ok()
+					.map(|s| s.trim_end_matches('\0'))
+					.filter(|s| !s.chars().any(char::is_control));
+				let hex = || value.iter().map(|b| format!("{b:02x}")).collect();
+				let value: String = text.map_or_else(hex, Into::into);
+				items.push(format!("{name}: {value}"));
+			}
+
+			if acl {
+				let output = Command::new("getfacl")
+					.args(["--omit-header", "--absolute-names", "--"])
+					.arg(&hovered)
+					.kill_on_drop(true)
+					.output()
+					.await;
+				if let Some(output) = output.ok().filter(|o| o.status.success()) {
+					let s = String::from_utf8_lossy(&output.stdout);
+					items.extend(s.lines().filter(|l| !l.is_empty()).map(|l| format!("acl: {l}")));
+				}
+			}
+
+			if items.is_empty() {
+				emit!(Message("No extended attributes".to_owned(), false));
+				return;
+			}
+			let name = hovered.file_name().unwrap_or_default().to_string_lossy();
+			let title = format!("Extended attributes of {name}");
+			emit!(Select(SelectOpt::hovered(&title, items))).await.ok();
+		});
 		false
 	}
 
@@ -256,7 +735,7 @@ impl Tab {
 		}
 
 		tokio::spawn(async move {
-			let rx = emit!(Input(InputOpt::top("Find:").with_realtime()));
+			let rx = emit!(Input(InputOpt::top("Find:").with_realtime().with_history("find")));
 
 			let rx = Debounce::new(UnboundedReceiverStream::new(rx), Duration::from_millis(50));
 			pin!(rx);
@@ -271,6 +750,99 @@ impl Tab {
 		false
 	}
 
+	pub fn filter(&mut self, query: Option<&str>, glob: bool, sensitive: bool) -> bool {
+		let kind = if glob { FilterKind::Glob } else { FilterKind::Regex };
+		let (title, value) = match query.map(|q| (q, Filter::new(q, kind, sensitive))) {
+			Some((_, Ok(filter))) => {
+				if !self.current.set_filter(Some(filter)) {
+					return false;
+				}
+				emit!(Hover);
+				return true;
+			}
+			Some((q, Err(e))) => (format!("Filter ({e}):"), q.to_owned()),
+			None => (
+				"Filter:".to_owned(),
+				self.current.files.filter().map(|f| f.raw().to_owned()).unwrap_or_default(),
+			),
+		};
+
+		let origin = self.current.hovered.clone();
+		tokio::spawn(async move {
+			let rx = emit!(Input(
+				InputOpt::top(title).with_value(value).with_realtime().with_history("filter")
+			));
+
+			let rx = Debounce::new(UnboundedReceiverStream::new(rx), Duration::from_millis(50));
+			pin!(rx);
+
+			let mut canceled = false;
+			while let Some(result) = rx.next().await {
+				let s = match result {
+					Ok(s) => s,
+					Err(InputError::Typed(s)) if Filter::new(&s, kind, sensitive).is_ok() => s,
+					Err(InputError::Typed(_)) => continue,
+					Err(InputError::Canceled(_)) => {
+						canceled = true;
+						String::new()
+					}
+				};
+
+				emit!(Call(
+					Exec::call("filter", vec![s])
+						.with_bool("glob", glob)
+						.with_bool("sensitive", sensitive)
+						.vec(),
+					KeymapLayer::Manager
+				));
+			}
+
+			// Go back to the file hovered before filtering
+			if let Some(file) = origin.filter(|_| canceled) {
+				emit!(Hover(file));
+			}
+		});
+		false
+	}
+
+	// Narrows the files down by their sizes or ages, e.g. `>10M <7d`, along with the filter by name
+	pub fn quick_filter(&mut self, query: Option<&str>) -> bool {
+		let (title, value) = match query.map(|q| (q, QuickFilter::new(q))) {
+			Some((_, Ok(quick))) => {
+				if !self.current.set_quick(Some(quick)) {
+					return false;
+				}
+				emit!(Hover);
+				return true;
+			}
+			Some((q, Err(e))) => (format!("Quick filter ({e}):"), q.to_owned()),
+			None => (
+				"Quick filter (>10M, <7d, 1M..1G):".to_owned(),
+				self.current.files.quick().map(|q| q.raw().to_owned()).unwrap_or_default(),
+			),
+		};
+
+		tokio::spawn(async move {
+			let rx = emit!(Input(
+				InputOpt::top(title).with_value(value).with_realtime().with_history("quick_filter")
+			));
+
+			let rx = Debounce::new(UnboundedReceiverStream::new(rx), Duration::from_millis(50));
+			pin!(rx);
+
+			while let Some(result) = rx.next().await {
+				let s = match result {
+					Ok(s) => s,
+					Err(InputError::Typed(s)) if QuickFilter::new(&s).is_ok() => s,
+					Err(InputError::Typed(_)) => continue,
+					Err(InputError::Canceled(_)) => String::new(),
+				};
+				emit!(Call(Exec::call("quick_filter", vec![s]).vec(), KeymapLayer::Manager));
+			}
+		});
+		false
+	}
+
 	pub fn find_arrow(&mut self, prev: bool) -> bool {
 		let Some(finder) = &mut self.finder else {
 			return false;
@@ -293,7 +865,8 @@ impl Tab {
 		let hidden = self.show_hidden;
 
 		self.search = Some(tokio::spawn(async move {
-			let Some(Ok(subject)) = emit!(Input(InputOpt::top("Search:"))).recv().await else {
+			let subject = emit!(Input(InputOpt::top("Search:").with_history("search"))).recv().await;
+			let Some(Ok(subject)) = subject else {
 				bail!("canceled")
 			};
 
@@ -321,6 +894,167 @@ impl Tab {
 		true
 	}
 
+	pub fn grep(&mut self) -> bool {
+		if let Some(handle) = self.search.take() {
+			handle.abort();
+		}
+
+		let cwd = self.current.cwd.to_regular();
+		let hidden = self.show_hidden;
+
+		self.search = Some(tokio::spawn(async move {
+			let subject = emit!(Input(InputOpt::top("Grep:").with_history("grep"))).recv().await;
+			let Some(Ok(subject)) = subject else {
+				bail!("canceled")
+			};
+
+			let rx = match external::rg_matches(external::RgOpt { cwd: cwd.clone(), hidden, subject }) {
+				Ok(rx) => rx,
+				Err(e) => {
+					emit!(Message(format!("Grep: {e}"), true));
+					return Err(e);
+				}
+			};
+
+			let rx = UnboundedReceiverStream::new(rx).chunks_timeout(100, Duration::from_millis(100));
+			pin!(rx);
+
+			let result = emit!(Select(SelectOpt::top("Grep:", Vec::new())));
+			pin!(result);
+
+			let mut matches = Vec::new();
+			let choice = loop {
+				select! {
+					biased;
+					r = &mut result => break r?,
+					Some(chunk) = rx.next() => {
+						emit!(SelectPush(
+							chunk
+								.iter()
+								.map(|m| format!("{}:{}: {}", short_path(&m.url, &cwd), m.line, m.text))
+								.collect()
+						));
+						matches.extend(chunk);
+					}
+				}
+			};
+
+			let Some(chosen) = matches.get(choice) else {
+				return Ok(());
+			};
+
+			// Along with the other matches in the same file, to navigate between them in the preview
+			let url = chosen.url.clone();
+			let mut lines = Vec::new();
+			let mut current = 0;
+			for (i, m) in matches.iter().enumerate().filter(|(_, m)| m.url == url) {
+				if i == choice {
+					current = lines.len();
+				}
+				let tab_size = PREVIEW.tab_size as usize;
+				lines.push(HighlightMatch::new(m.line as usize, &m.raw, &m.spans, tab_size));
+			}
+
+			emit!(Cd(url.clone()));
+			emit!(Matches(PreviewMatches { url, lines, current }));
+			Ok(())
+		}));
+		false
+	}
+
+	// Searches the whole disk by the name in the index of the system, see `external::locate`, and
+	// goes to the chosen file, or searches the home directory live with `fd` instead, if there's no
+	// index and that's chosen, or if it's stale
+	pub fn locate(&mut self) -> bool {
+		if let Some(handle) = self.search.take() {
+			handle.abort();
+		}
+
+		let hidden = self.show_hidden;
+		self.search = Some(tokio::spawn(async move {
+			let subject = emit!(Input(InputOpt::top("Locate:").with_history("locate"))).recv().await;
+			let Some(Ok(subject)) = subject else {
+				bail!("canceled")
+			};
+
+			let (choice, urls) = match external::locate(LocateOpt { subject: subject.clone() }) {
+				Ok(rx) => Self::locate_choose("Locate:", UnboundedReceiverStream::new(rx), true).await?,
+				Err(e) => {
+					let items = vec!["Search live with `fd` instead".to_owned()];
+					let title = format!("Locate: {e}");
+					(emit!(Select(SelectOpt::top(&title, items))).await?, vec![])
+				}
+			};
+			if let Some(url) = urls.into_iter().nth(choice) {
+				emit!(Cd(url));
+				return Ok(());
+			}
+
+			let cwd = Url::from(env::var_os("HOME").map_or_else(|| "/".into(), PathBuf::from));
+			let subject = regex::escape(&subject);
+			let rx = external::fd(external::FdOpt { cwd, hidden, glob: false, subject })?;
+			let rx = UnboundedReceiverStream::new(rx).map(|f| Located::Found(f.url_owned()));
+
+			let (choice, urls) = Self::locate_choose("Locate live:", rx, false).await?;
+			if let Some(url) = urls.into_iter().nth(choice) {
+				emit!(Cd(url));
+			}
+			Ok(())
+		}));
+		false
+	}
+
+	// Lists the files located as they're found, to choose one of, followed by what went wrong if
+	// anything and `offer` is set, after them all, to choose to search live instead
+	async fn locate_choose(
+		title: &str,
+		rx: impl Stream<Item = Located>,
+		offer: bool,
+	) -> Result<(usize, Vec<Url>)> {
+		let rx = rx.chunks_timeout(100, Duration::from_millis(100));
+		pin!(rx);
+
+		let result = emit!(Select(SelectOpt::top(title, Vec::new())));
+		pin!(result);
+
+		let (mut urls, mut gone, mut failed, mut done) = (Vec::new(), 0, None, false);
+		let choice = loop {
+			select! {
+				biased;
+				r = &mut result => break r?,
+				chunk = rx.next(), if !done => {
+					let Some(chunk) = chunk else {
+						done = true;
+						let note = match failed.take() {
+							Some(e) => Some(e),
+							None if gone > 0 => Some(format!("{gone} of them are gone, as the index is stale")),
+							None if urls.is_empty() => Some("Nothing is found in the index".to_owned()),
+							None => None,
+						};
+						if let Some(note) = note.filter(|_| offer) {
+							emit!(SelectPush(vec![format!("{note}, search live with `fd` instead")]));
+						}
+						continue;
+					};
+
+					let mut items = Vec::with_capacity(chunk.len());
+					for located in chunk {
+						match located {
+							Located::Found(url) => {
+								items.push(readable_path(&url));
+								urls.push(url);
+							}
+							Located::Gone(_) => gone += 1,
+							Located::Failed(e) => failed = Some(e),
+						}
+					}
+					emit!(SelectPush(items));
+				}
+			}
+		};
+		Ok((choice, urls))
+	}
+
 	pub fn search_stop(&mut self) -> bool {
 		if let Some(handle) = self.search.take() {
 			handle.abort();
@@ -354,6 +1088,22 @@ impl Tab {
 		false
 	}
 
+	pub fn zoxide(&self, query: Vec<String>) -> bool {
+		let cwd = self.current.cwd.clone();
+
+		tokio::spawn(async move {
+			match external::zoxide_query(ZoxideOpt { cwd }, &query).await {
+				Ok(target) => {
+					emit!(Cd(target));
+				}
+				Err(e) => {
+					emit!(Message(format!("Jump: {e}"), true));
+				}
+			}
+		});
+		false
+	}
+
 	pub fn shell(&self, exec: &str, block: bool, confirm: bool) -> bool {
 		let selected: Vec<_> = self
 			.selected()
@@ -361,16 +1111,29 @@ impl Tab {
 			.map(|f| (f.url_os_str().to_owned(), Default::default()))
 			.collect();
 
+		let cwd = self.current.cwd.clone();
+		let hovered = self.current.hovered.as_ref().map(|h| h.url_owned());
 		let mut exec = exec.to_owned();
 		tokio::spawn(async move {
 			if !confirm || exec.is_empty() {
-				let mut result = emit!(Input(InputOpt::top("Shell:").with_value(&exec).with_highlight()));
+				let mut result = emit!(Input(
+					InputOpt::top("Shell:")
+						.with_value(&exec)
+						.with_highlight()
+						.with_multiline()
+						.with_history("shell")
+						.with_completion(&cwd)
+				));
 				match result.recv().await {
 					Some(Ok(e)) => exec = e,
 					_ => return,
 				}
+				let record = Exec::call("shell", vec![exec.clone()]).with_bool("block", block);
+				emit!(Record(record.with_bool("confirm", true)));
 			}
 
+			let paths: Vec<_> = selected.iter().map(|(p, _)| Path::new(p)).collect();
+			let exec = external::shell_expand(&exec, hovered.as_ref().map(|h| h.as_path()), &paths, &cwd);
 			emit!(Open(
 				selected,
 				Some(Opener { exec, block, display_name: Default::default(), spread: true })
@@ -380,12 +1143,40 @@ impl Tab {
 		false
 	}
 
-	pub fn update_peek(&mut self, max: usize, url: Url) -> bool {
-		let Some(ref hovered) = self.current.hovered else {
-			return false;
+	// Drops into `$SHELL` in the current directory until it's exited, with the hovered file as `$f`,
+	// and the selected ones as `$fx`, one per line
+	pub fn subshell(&self) -> bool {
+		let join = |files: Vec<&File>| {
+			files.into_iter().map(|f| f.url_os_str()).collect::<Vec<_>>().join(OsStr::new("\n"))
 		};
+		let cwd = self.current.cwd.clone();
+		let hovered = join(self.current.hovered.iter().collect());
+		let selected = join(self.selected());
+
+		tokio::spawn(async move {
+			let _guard = BLOCKER.acquire().await.unwrap();
+			emit!(Stop(true)).await;
+
+			let level = env::var("YAZI_LEVEL").ok().and_then(|s| s.parse().ok()).unwrap_or(0u32);
+			let result = Command::new(external::login_shell())
+				.current_dir(&cwd)
+				.env("f", hovered)
+				.env("fx", selected)
+				.env("YAZI_LEVEL", (level + 1).to_string())
+				.kill_on_drop(true)
+				.status()
+				.await;
+
+			emit!(Stop(false)).await;
+			if let Err(e) = result {
+				emit!(Message(format!("Failed to spawn the shell: {e}"), true));
+			}
+		});
+		false
+	}
 
-		if &url != hovered.url() {
+	pub fn update_peek(&mut self, max: usize, url: Url) -> bool {
+		if self.previewed() != Some(&url) {
 			return false;
 		}
 
@@ -393,11 +1184,11 @@ impl Tab {
 	}
 
 	pub fn update_preview(&mut self, lock: PreviewLock) -> bool {
-		let Some(hovered) = self.current.hovered.as_ref().map(|h| h.url()) else {
+		let Some(previewed) = self.previewed() else {
 			return self.preview_reset();
 		};
 
-		if lock.url != *hovered {
+		if lock.url != *previewed {
 			return false;
 		}
 
@@ -417,6 +1208,12 @@ impl Tab {
 	}
 
 	// --- Current
+	#[inline]
+	pub fn cwd(&self) -> &Url { &self.current.cwd }
+
+	#[inline]
+	pub fn current(&self) -> &Folder { &self.current }
+
 	#[inline]
 	pub fn name(&self) -> &str {
 		self
@@ -446,37 +1243,143 @@ impl Tab {
 
 	#[inline]
 	pub fn history_new(&mut self, url: &Url) -> Folder {
-		self.history.remove(url).unwrap_or_else(|| Folder::from(url))
+		self.history.take(url)
 	}
 
 	// --- Preview
 	#[inline]
 	pub fn preview(&self) -> &Preview { &self.preview }
 
+	// The file in the preview, the pinned one if any, or else the hovered one
+	#[inline]
+	pub fn previewed(&self) -> Option<&Url> {
+		self.preview.pin().map(|p| &p.url).or_else(|| self.current.hovered.as_ref().map(|h| h.url()))
+	}
+
 	#[inline]
 	pub fn preview_reset(&mut self) -> bool { self.preview.reset(|_| true) }
 
 	#[inline]
 	pub fn preview_reset_image(&mut self) -> bool { self.preview.reset(|l| l.is_image()) }
 
+	#[inline]
+	pub fn preview_reset_text(&mut self) -> bool {
+		self.preview.reset(|l| matches!(l.data, PreviewData::Text(_) | PreviewData::Code(..)))
+	}
+
+	#[inline]
+	pub fn preview_matches(&mut self, matches: PreviewMatches) -> bool {
+		self.preview.set_matches(matches)
+	}
+
+	#[inline]
+	pub fn preview_jump_match(&mut self, step: isize) -> bool { self.preview.jump_match(step) }
+
+	#[inline]
+	pub fn line_numbers(&self) -> bool { self.line_numbers }
+
+	pub fn set_line_numbers(&mut self, state: Option<bool>) -> bool {
+		let state = state.unwrap_or(!self.line_numbers);
+		if state == self.line_numbers {
+			return false;
+		}
+
+		self.line_numbers = state;
+		true
+	}
+
+	#[inline]
+	pub fn relative_numbers(&self) -> bool { self.relative_numbers }
+
+	pub fn set_relative_numbers(&mut self, state: Option<bool>) -> bool {
+		let state = state.unwrap_or(!self.relative_numbers);
+		if state == self.relative_numbers {
+			return false;
+		}
+
+		self.relative_numbers = state;
+		true
+	}
+
+	#[inline]
+	pub fn info(&self) -> bool { self.info }
+
+	// Shows the details of the hovered file in place of its preview, or hides them, toggles if not
+	// given, where the preview isn't loaded meanwhile
+	pub fn set_info(&mut self, state: Option<bool>) -> bool {
+		let state = state.unwrap_or(!self.info);
+		if state == self.info {
+			return false;
+		}
+
+		self.info = state;
+		self.preview.reset(|_| true);
+		emit!(Peek);
+		true
+	}
+
+	#[inline]
+	pub fn preview_hex(&mut self, state: Option<bool>) -> bool { self.preview.set_hex(state) }
+
+	#[inline]
+	pub fn preview_full(&mut self) -> bool { self.preview.set_full() }
+
 	#[inline]
 	pub fn preview_arrow(&mut self, step: isize) -> bool { self.preview.arrow(step) }
 
+	#[inline]
+	pub fn preview_focus(&mut self, state: Option<bool>) -> bool { self.preview.set_focus(state) }
+
 	// --- Finder
 	#[inline]
 	pub fn finder(&self) -> Option<&Finder> { self.finder.as_ref() }
 
 	// --- Sorter
+	#[inline]
+	pub fn sorter(&self) -> &FilesSorter { &self.sorter }
+
+	// Whether the sorter changed, which is told in the status bar even if the order stays the same
 	pub fn set_sorter(&mut self, sorter: FilesSorter) -> bool {
 		if sorter == self.sorter {
 			return false;
 		}
 
 		self.sorter = sorter;
+		self.apply_files_attrs(false);
+		true
+	}
+
+	#[inline]
+	pub fn parent_sorter(&self) -> &FilesSorter {
+		self.parent_sorter.as_ref().unwrap_or(&self.sorter)
+	}
+
+	// Sorts the parent directory by `sorter` on its own, or by the one of the current if none
+	pub fn set_parent_sorter(&mut self, sorter: Option<FilesSorter>) -> bool {
+		if sorter == self.parent_sorter {
+			return false;
+		}
+
+		self.parent_sorter = sorter;
 		self.apply_files_attrs(false)
 	}
 
+	// Flips the order of the files, with the cursor staying on the file hovered
+	pub fn sort_reverse_toggle(&mut self) -> bool {
+		self.set_sorter(FilesSorter { reverse: !self.sorter.reverse, ..self.sorter })
+	}
+
+	// Sorts by the next method, or the previous one if `prev`, keeping the rest of the sorter
+	pub fn sort_cycle(&mut self, prev: bool) -> bool {
+		let by = self.sorter.by.cycle(prev, !MANAGER.sort_command.is_empty());
+		let seed = if by == SortBy::Random { FilesSorter::roll() } else { self.sorter.seed };
+		self.set_sorter(FilesSorter { by, seed, ..self.sorter })
+	}
+
 	// --- Show hidden
+	#[inline]
+	pub fn show_hidden(&self) -> bool { self.show_hidden }
+
 	pub fn set_show_hidden(&mut self, state: Option<bool>) -> bool {
 		let state = state.unwrap_or(!self.show_hidden);
 		if state == self.show_hidden {
@@ -487,6 +1390,17 @@ impl Tab {
 		self.apply_files_attrs(false)
 	}
 
+	// --- Show ignored
+	pub fn set_show_ignored(&mut self, state: Option<bool>) -> bool {
+		let state = state.unwrap_or(!self.show_ignored);
+		if state == self.show_ignored {
+			return false;
+		}
+
+		self.show_ignored = state;
+		self.apply_files_attrs(false)
+	}
+
 	pub fn apply_files_attrs(&mut self, only_hovered: bool) -> bool {
 		let mut b = false;
 		if let Some(f) = self
@@ -496,23 +1410,36 @@ impl Tab {
 			.filter(|h| h.is_dir())
 			.and_then(|h| self.history.get_mut(h.url()))
 		{
-			b |= f.files.set_show_hidden(self.show_hidden);
-			b |= f.files.set_sorter(self.sorter);
+			b |= f.set_files_attrs(self.show_hidden, self.show_ignored, self.sorter);
 		}
 
 		if only_hovered {
 			return b;
 		}
 
-		b |= self.current.files.set_show_hidden(self.show_hidden);
-		b |= self.current.files.set_sorter(self.sorter);
-
+		b |= self.current.set_files_attrs(self.show_hidden, self.show_ignored, self.sorter);
+		let parent_sorter = *self.parent_sorter();
 		if let Some(parent) = self.parent.as_mut() {
-			b |= parent.files.set_show_hidden(self.show_hidden);
-			b |= parent.files.set_sorter(self.sorter);
+			b |= parent.set_files_attrs(self.show_hidden, self.show_ignored, parent_sorter);
+			parent.hover_repos();
 		}
 
 		self.current.hover_repos();
 		b
 	}
 }
+
+#[cfg(unix)]
+#[test]
+fn test_loops() {
+	let dir = shared::TestDir::new("loops");
+	fs::create_dir_all(dir.join("a/b")).unwrap();
+	std::os::unix::fs::symlink(&dir, dir.join("a/b/up")).unwrap();
+	std::os::unix::fs::symlink(dir.join("a/b"), dir.join("a/b/itself")).unwrap();
+	std::os::unix::fs::symlink(dir.join("a/b"), dir.join("side")).unwrap();
+
+	assert!(Tab::loops(&dir.join("a/b/up")));
+	assert!(Tab::loops(&dir.join("a/b/itself")));
+	assert!(!Tab::loops(&dir.join("side")));
+	assert!(!Tab::loops(&dir.join("missing")));
+}
@@ -1,23 +1,111 @@
-use config::BOOT;
+use config::{BOOT, MANAGER};
+use futures::executor::block_on;
 use shared::Url;
 
-use super::Tab;
-use crate::emit;
+use super::{Backstack, Session, SessionFilter, SessionTab, Tab, Visit};
+use crate::{emit, files::{File, FilesSorter, Filter, FilterKind}};
 
 const MAX_TABS: usize = 9;
 
 pub struct Tabs {
 	idx:   usize,
+	last:  usize,
 	items: Vec<Tab>,
 }
 
 impl Tabs {
 	pub fn make() -> Self {
-		let mut tabs = Self { idx: usize::MAX, items: vec![Tab::from(Url::from(&BOOT.cwd))] };
-		tabs.set_idx(0);
+		let restore = BOOT.restore || (MANAGER.restore_tabs && !BOOT.cwd_given);
+		let (idx, items) = match Self::load().filter(|_| restore) {
+			Some((idx, items)) => (idx, items),
+			None => (0, vec![Tab::from(Url::from(&BOOT.cwd))]),
+		};
+
+		let mut tabs = Self { idx: usize::MAX, last: 0, items };
+		tabs.items[idx].backstack = Backstack::load();
+		tabs.set_idx(idx);
 		tabs
 	}
 
+	// The tabs of the session saved, telling of the ones dropped for their directories being gone
+	fn load() -> Option<(usize, Vec<Tab>)> {
+		let (session, gone) = Session::load()?;
+		if !gone.is_empty() {
+			let gone: Vec<_> = gone.iter().map(|p| p.to_string_lossy()).collect();
+			let s = if gone.len() > 1 { "s" } else { "" };
+			emit!(Message(format!("Dropped the tab{s} of {}, not there anymore", gone.join(", ")), true));
+		}
+
+		let items = session.tabs.into_iter().take(MAX_TABS).map(Self::restore_tab).collect();
+		Some((session.idx.min(MAX_TABS - 1), items))
+	}
+
+	fn restore_tab(saved: SessionTab) -> Tab {
+		let mut tab = Tab::from(Url::from(&saved.visit.cwd));
+		tab.set_sorter(FilesSorter {
+			by: saved.sort_by,
+			sensitive: saved.sort_sensitive,
+			reverse: saved.sort_reverse,
+			dir_first: saved.sort_dir_first,
+			..tab.sorter
+		});
+		tab.set_show_hidden(Some(saved.show_hidden));
+
+		if let Some(f) = saved.filter {
+			let kind = if f.glob { FilterKind::Glob } else { FilterKind::Regex };
+			tab.current.set_filter(Filter::new(&f.raw, kind, f.sensitive).ok());
+		}
+		for url in saved.visit.selected {
+			tab.current.files.select(&Url::from(url), Some(true));
+		}
+		if let Some(Ok(file)) = saved.visit.hovered.map(|h| block_on(File::from(Url::from(h)))) {
+			tab.current.hover_force(file);
+		}
+		tab
+	}
+
+	// Restores the session saved in place of the tabs open, e.g. the one saved on the last exit
+	pub fn restore(&mut self) -> bool {
+		let Some((idx, items)) = Self::load() else {
+			emit!(Message("No session to restore".to_owned(), true));
+			return false;
+		};
+
+		self.items = items;
+		(self.idx, self.last) = (usize::MAX, 0);
+		self.set_idx(idx);
+		true
+	}
+
+	// Keeps the tabs for the next start, or to be restored later, right away as it's about to exit
+	pub fn save(&self) {
+		// The remote ones aren't kept, to not connect to the hosts on the next start
+		let local = |t: &&Tab| !t.current.cwd.is_sftp();
+		if !self.items.iter().any(|t| local(&t)) {
+			return;
+		}
+
+		let idx = self.items.iter().take(self.idx).filter(local).count();
+		let tabs = self.items.iter().filter(local).map(|t| {
+			let filter = t.current.files.filter().map(|f| SessionFilter {
+				raw:       f.raw().to_owned(),
+				glob:      f.kind() == FilterKind::Glob,
+				sensitive: f.case_sensitive(),
+			});
+			SessionTab {
+				visit: Visit { cwd: t.current.cwd.to_regular().to_path_buf(), ..t.visit() },
+				sort_by: t.sorter.by,
+				sort_sensitive: t.sorter.sensitive,
+				sort_reverse: t.sorter.reverse,
+				sort_dir_first: t.sorter.dir_first,
+				show_hidden: t.show_hidden,
+				filter,
+			}
+		});
+		let tabs: Vec<_> = tabs.collect();
+		Session { idx: idx.min(tabs.len() - 1), tabs }.save();
+	}
+
 	pub fn create(&mut self, url: &Url) -> bool {
 		if self.items.len() >= MAX_TABS {
 			return false;
@@ -25,7 +113,11 @@ impl Tabs {
 
 		let mut tab = Tab::from(url);
 		tab.set_show_hidden(Some(self.active().show_hidden));
+		tab.set_show_ignored(Some(self.active().show_ignored));
+		tab.set_line_numbers(Some(self.active().line_numbers));
+		tab.set_relative_numbers(Some(self.active().relative_numbers));
 		tab.set_sorter(self.active().sorter);
+		tab.set_parent_sorter(self.active().parent_sorter);
 
 		self.items.insert(self.idx + 1, tab);
 		self.set_idx(self.idx + 1);
@@ -43,6 +135,24 @@ impl Tabs {
 		true
 	}
 
+	// Switches to the other pane of the `commander` layout, which is the tab active before
+	pub fn switch_other(&mut self) -> bool {
+		match self.other_idx() {
+			Some(idx) => self.switch(idx as isize, false),
+			None => false,
+		}
+	}
+
+	// Opens a second tab in the same directory if there's only one, staying on the first
+	pub fn pair(&mut self) -> bool {
+		if self.items.len() > 1 {
+			return false;
+		}
+
+		let url = self.active().current.cwd.clone();
+		self.create(&url) && self.switch(0, false)
+	}
+
 	pub fn swap(&mut self, rel: isize) -> bool {
 		let idx = self.absolute(rel);
 		if idx == self.idx {
@@ -77,8 +187,23 @@ impl Tabs {
 		}
 	}
 
+	// The tab active before, or the one next to the active one if it's gone
+	pub fn other_idx(&self) -> Option<usize> {
+		let len = self.items.len();
+		if len < 2 {
+			None
+		} else if self.last != self.idx && self.last < len {
+			Some(self.last)
+		} else {
+			Some(if self.idx + 1 < len { self.idx + 1 } else { self.idx - 1 })
+		}
+	}
+
 	#[inline]
 	fn set_idx(&mut self, idx: usize) {
+		if self.idx != usize::MAX {
+			self.last = self.idx;
+		}
 		self.idx = idx;
 		self.active_mut().preview_reset_image();
 		emit!(Refresh);
@@ -95,9 +220,21 @@ impl Tabs {
 	#[inline]
 	pub fn iter(&self) -> impl Iterator<Item = &Tab> { self.items.iter() }
 
+	#[inline]
+	pub(super) fn iter_mut(&mut self) -> impl Iterator<Item = &mut Tab> { self.items.iter_mut() }
+
 	#[inline]
 	pub fn active(&self) -> &Tab { &self.items[self.idx] }
 
+	#[inline]
+	pub fn other(&self) -> Option<&Tab> { self.other_idx().map(|i| &self.items[i]) }
+
+	// The tab at `idx`, or the one that far away from the active one if `rel`
+	#[inline]
+	pub fn get(&self, idx: isize, rel: bool) -> Option<&Tab> {
+		self.items.get(if rel { self.absolute(idx) } else { idx as usize })
+	}
+
 	#[inline]
 	pub(super) fn active_mut(&mut self) -> &mut Tab { &mut self.items[self.idx] }
 }
@@ -1,17 +1,22 @@
-use std::{collections::BTreeSet, sync::Arc, time::Duration};
+use std::{collections::{BTreeSet, HashMap}, path::Path, sync::Arc, time::Duration};
 
+use config::{manager::StatusVar, MANAGER};
 use indexmap::IndexMap;
 use notify::{event::{MetadataKind, ModifyKind}, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _Watcher};
 use parking_lot::RwLock;
 use shared::Url;
-use tokio::{fs, pin, sync::mpsc::{self, UnboundedReceiver}};
+use tokio::{fs, pin, sync::mpsc::{self, UnboundedReceiver}, task::JoinHandle};
 use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
 
-use crate::{emit, external, files::{File, Files, FilesOp}};
+use tracing::warn;
+
+use crate::{emit, external, files::{mimetypes, File, Files, FilesOp}};
 
 pub struct Watcher {
 	watcher: RecommendedWatcher,
 	watched: Arc<RwLock<IndexMap<Url, Option<Url>>>>,
+	// The directories being read, each given up on once it's left
+	reading: HashMap<Url, JoinHandle<()>>,
 }
 
 impl Watcher {
@@ -61,12 +66,25 @@ impl Watcher {
 			Default::default(),
 		);
 
-		let instance = Self { watcher: watcher.unwrap(), watched: Default::default() };
+		let instance =
+			Self { watcher: watcher.unwrap(), watched: Default::default(), reading: Default::default() };
 		tokio::spawn(Self::changed(rx, instance.watched.clone()));
 		instance
 	}
 
-	pub(super) fn watch(&mut self, mut watched: BTreeSet<&Url>) {
+	pub(super) fn watch(&mut self, watched: BTreeSet<&Url>) {
+		// The `.git` directories of the repositories they're in, for the changes to the index and
+		// the branch checked out
+		let gits: BTreeSet<_> = watched
+			.iter()
+			.filter(|u| (MANAGER.git_status || Self::branch_shown()) && !u.is_sftp())
+			.filter_map(|u| external::git_root(u))
+			.map(|r| Url::from(r.join(".git")))
+			.filter(|g| g.is_dir())
+			.collect();
+
+		let mut watched: BTreeSet<&Url> = watched;
+		watched.extend(&gits);
 		watched.retain(|&u| u.is_regular());
 		let (to_unwatch, to_watch): (BTreeSet<_>, BTreeSet<_>) = {
 			let guard = self.watched.read();
@@ -116,18 +134,36 @@ impl Watcher {
 		});
 	}
 
-	pub(super) fn trigger_dirs(&self, dirs: &[&Url]) {
-		let dirs: Vec<_> = dirs.iter().filter(|&u| u.is_regular()).map(|&u| u.clone()).collect();
-		if dirs.is_empty() {
-			return;
-		}
+	#[inline]
+	pub(super) fn watched(&self, url: &Url) -> bool { self.watched.read().contains_key(url) }
 
-		let watched = self.watched.clone();
-		tokio::spawn(async move {
-			for dir in dirs {
-				Self::dir_changed(&dir, watched.clone()).await;
+	// Reads the directories, each with whether it's read for the first time, to be streamed in
+	// batches, for a large one to be shown before it's read to the end, and where one is still being
+	// read, it's left to finish, rather than given up on and left half-read
+	pub(super) fn trigger_dirs(&mut self, dirs: &[(Url, bool)]) {
+		let dirs: Vec<_> = dirs.iter().filter(|(u, _)| u.is_regular() || u.is_sftp()).collect();
+		self.reading.retain(|url, h| {
+			let keep = !h.is_finished() && dirs.iter().any(|(u, _)| u == url);
+			if !keep {
+				h.abort();
 			}
+			keep
 		});
+
+		for (dir, first) in dirs {
+			if self.reading.contains_key(dir) {
+				continue;
+			}
+
+			let (url, watched, first) = (dir.clone(), self.watched.clone(), *first);
+			self.reading.insert(dir.clone(), tokio::spawn(async move {
+				if first {
+					Self::dir_streamed(&url, watched).await;
+				} else {
+					Self::dir_changed(&url, watched).await;
+				}
+			}));
+		}
 	}
 
 	async fn changed(rx: UnboundedReceiver<Url>, watched: Arc<RwLock<IndexMap<Url, Option<Url>>>>) {
@@ -135,8 +171,36 @@ impl Watcher {
 		pin!(rx);
 
 		while let Some(urls) = rx.next().await {
+			// The changes in a `.git` directory are to the status of the files of the repository
+			let is_git = |u: &Path| u.components().any(|c| c.as_os_str() == ".git");
+			let (gits, mut urls): (BTreeSet<_>, BTreeSet<_>) = urls.into_iter().partition(|u| is_git(u));
+			let roots: BTreeSet<_> = gits
+				.iter()
+				.filter_map(|g| g.ancestors().find(|p| p.ends_with(".git")).and_then(Path::parent))
+				.collect();
+			let repos: Vec<_> = watched
+				.read()
+				.keys()
+				.filter(|k| !is_git(k) && roots.iter().any(|r| k.starts_with(r)))
+				.cloned()
+				.collect();
+			for repo in repos {
+				Self::git_changed(&repo).await;
+			}
+
+			// The rules of a changed `.gitignore` also apply to the watched directories under it
+			let ignores: Vec<_> = urls
+				.iter()
+				.filter(|u| u.file_name().is_some_and(|n| n == ".gitignore"))
+				.filter_map(|u| u.parent_url())
+				.collect();
+			if !ignores.is_empty() {
+				let guard = watched.read();
+				urls.extend(guard.keys().filter(|k| ignores.iter().any(|i| k.starts_with(i))).cloned());
+			}
+
 			let (mut files, mut dirs): (Vec<_>, Vec<_>) = Default::default();
-			for url in urls.into_iter().collect::<BTreeSet<_>>() {
+			for url in urls {
 				if fs::metadata(&url).await.map(|m| !m.is_dir()).unwrap_or(false) {
 					files.push(url);
 				} else {
@@ -156,7 +220,7 @@ impl Watcher {
 	}
 
 	async fn file_changed(urls: &[Url], watched: Arc<RwLock<IndexMap<Url, Option<Url>>>>) {
-		let Ok(mut mimes) = external::file(urls).await else {
+		let Ok(mut mimes) = mimetypes(urls).await else {
 			return;
 		};
 
@@ -177,14 +241,7 @@ impl Watcher {
 	}
 
 	async fn dir_changed(url: &Url, watched: Arc<RwLock<IndexMap<Url, Option<Url>>>>) {
-		let linked: Vec<_> = watched
-			.read()
-			.iter()
-			.filter_map(|(k, v)| v.as_ref().map(|v| (k, v)))
-			.filter(|(_, v)| *v == url)
-			.map(|(k, _)| k.clone())
-			.collect();
-
+		let linked = Self::linked(url, &watched);
 		let Ok(rx) = Files::from_dir(url).await else {
 			emit!(Files(FilesOp::IOErr(url.clone())));
 			for ori in linked {
@@ -193,21 +250,80 @@ impl Watcher {
 			return;
 		};
 
-		let linked_files = |files: &[File], ori: &Url| -> Vec<File> {
-			let mut new = Vec::with_capacity(files.len());
-			for file in files {
-				let mut file = file.clone();
-				file.set_url(ori.join(file.url().strip_prefix(url).unwrap()));
-				new.push(file);
-			}
-			new
-		};
-
 		let files: Vec<_> = UnboundedReceiverStream::new(rx).collect().await;
 		for ori in linked {
-			let files = linked_files(&files, &ori);
+			let files = Self::linked_files(&files, url, &ori);
 			emit!(Files(FilesOp::Full(ori, files)));
 		}
 		emit!(Files(FilesOp::Full(url.clone(), files)));
+		Self::git_changed(url).await;
 	}
+
+	// As `dir_changed`, but with the files in batches as they're read
+	async fn dir_streamed(url: &Url, watched: Arc<RwLock<IndexMap<Url, Option<Url>>>>) {
+		let linked = Self::linked(url, &watched);
+		let Ok(rx) = Files::stream_dir(url).await else {
+			emit!(Files(FilesOp::IOErr(url.clone())));
+			for ori in linked {
+				emit!(Files(FilesOp::IOErr(ori)));
+			}
+			return;
+		};
+		pin!(rx);
+
+		let ticket = FilesOp::prepare(url);
+		let linked: Vec<_> = linked.into_iter().map(|ori| (FilesOp::prepare(&ori), ori)).collect();
+		while let Some(files) = rx.next().await {
+			for (ticket, ori) in &linked {
+				let files = Self::linked_files(&files, url, ori);
+				emit!(Files(FilesOp::Part(ori.clone(), *ticket, files)));
+			}
+			emit!(Files(FilesOp::Part(url.clone(), ticket, files)));
+		}
+		Self::git_changed(url).await;
+	}
+
+	// The watched symlinks that point to the directory
+	fn linked(url: &Url, watched: &RwLock<IndexMap<Url, Option<Url>>>) -> Vec<Url> {
+		watched
+			.read()
+			.iter()
+			.filter_map(|(k, v)| v.as_ref().map(|v| (k, v)))
+			.filter(|(_, v)| *v == url)
+			.map(|(k, _)| k.clone())
+			.collect()
+	}
+
+	// The files of the directory as the ones of the symlink `ori` pointing to it
+	fn linked_files(files: &[File], url: &Url, ori: &Url) -> Vec<File> {
+		let mut new = Vec::with_capacity(files.len());
+		for file in files {
+			let mut file = file.clone();
+			file.set_url(ori.join(file.url().strip_prefix(url).unwrap()));
+			new.push(file);
+		}
+		new
+	}
+
+	pub(super) async fn git_changed(url: &Url) {
+		if url.is_sftp() {
+			return;
+		}
+		if Self::branch_shown() {
+			emit!(Files(FilesOp::Branch(url.clone(), external::git_branch(url))));
+		}
+		if !MANAGER.git_status {
+			return;
+		}
+		match external::git_status(url).await {
+			Ok(items) => {
+				emit!(Files(FilesOp::Git(url.clone(), items)));
+			}
+			Err(e) => warn!("{e}"),
+		}
+	}
+
+	// Whether the branch is in the status bar, for it to be read only if it's shown
+	#[inline]
+	fn branch_shown() -> bool { MANAGER.status_line.contains(&[StatusVar::Branch]) }
 }
@@ -0,0 +1,95 @@
+use std::collections::{HashSet, VecDeque};
+
+use shared::Url;
+
+// How many sets of yanked files are remembered, the oldest one forgotten for a new one
+const YANKED_MAX: usize = 10;
+
+// The files yanked at once, whether they're cut, and if so, whether they've been moved already
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct YankedSet {
+	pub cut:   bool,
+	pub urls:  HashSet<Url>,
+	pub moved: bool,
+}
+
+// The sets yanked last, the latest first, with the one to paste, which is cycled through
+#[derive(Default)]
+pub struct Yanked {
+	ring:   VecDeque<YankedSet>,
+	active: usize,
+}
+
+impl Yanked {
+	pub fn push(&mut self, cut: bool, urls: HashSet<Url>) -> bool {
+		if urls.is_empty() {
+			return false;
+		}
+
+		self.ring.retain(|s| s.cut != cut || s.urls != urls);
+		self.ring.push_front(YankedSet { cut, urls, moved: false });
+		self.ring.truncate(YANKED_MAX);
+		self.active = 0;
+		true
+	}
+
+	// Makes the set `step` older than the active one active, going around past the oldest one
+	pub fn cycle(&mut self, step: isize) -> bool {
+		if self.ring.len() < 2 {
+			return false;
+		}
+
+		let len = self.ring.len() as isize;
+		let active = (self.active as isize + step).rem_euclid(len) as usize;
+		if active == self.active {
+			return false;
+		}
+		self.active = active;
+		true
+	}
+
+	// Marks the active set as moved, once it's pasted if it's cut
+	pub fn consume(&mut self) {
+		if let Some(s) = self.ring.get_mut(self.active).filter(|s| s.cut) {
+			s.moved = true;
+		}
+	}
+
+	#[inline]
+	pub fn active(&self) -> Option<&YankedSet> { self.ring.get(self.active) }
+
+	// The position of the active set, counted from the latest one, out of how many there are
+	#[inline]
+	pub fn position(&self) -> (usize, usize) { (self.active + 1, self.ring.len()) }
+}
+
+#[test]
+fn test_yanked() {
+	let urls = |names: &[&str]| names.iter().map(|&n| Url::from(n)).collect::<HashSet<_>>();
+	let mut y = Yanked::default();
+	assert!(!y.push(false, HashSet::new()));
+	assert!(!y.cycle(1));
+
+	assert!(y.push(false, urls(&["/a"])));
+	assert!(y.push(true, urls(&["/b", "/c"])));
+	assert!(y.push(false, urls(&["/d"])));
+	assert_eq!(y.position(), (1, 3));
+	assert_eq!(y.active().map(|s| s.urls.len()), Some(1));
+
+	assert!(y.cycle(1));
+	assert!(y.active().is_some_and(|s| s.cut && !s.moved));
+	y.consume();
+	assert!(y.active().is_some_and(|s| s.moved));
+	assert!(y.cycle(-2));
+	assert_eq!(y.position(), (3, 3));
+
+	// The same set yanked again goes to the front, and isn't moved anymore
+	assert!(y.push(true, urls(&["/c", "/b"])));
+	assert_eq!(y.position(), (1, 3));
+	assert!(y.active().is_some_and(|s| s.cut && !s.moved));
+
+	for i in 0..YANKED_MAX {
+		y.push(false, urls(&[&format!("/{i}")]));
+	}
+	assert_eq!(y.position(), (1, YANKED_MAX));
+}
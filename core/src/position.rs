@@ -1,4 +1,6 @@
+use crossterm::terminal::WindowSize;
 use ratatui::prelude::Rect;
+use shared::Term;
 
 #[derive(Default)]
 pub enum Position {
@@ -20,4 +22,91 @@ impl Position {
 
 	#[inline]
 	pub fn dimension(&self) -> Option<(u16, u16)> { self.rect().map(|r| (r.width, r.height)) }
+
+	// Where it's drawn on the screen: `Top` centered along the top, offset by its `x` and `y`, and
+	// `Hovered` below the row of the hovered file `hovered`, or over it if there's no room below,
+	// at the top without one. Kept on the screen, and shrunk to it if the screen is smaller
+	pub fn area(&self, hovered: Option<Rect>) -> Rect {
+		let screen = Self::screen();
+		let (columns, rows) = (screen.width, screen.height);
+
+		let (x, y) = match *self {
+			Position::None => return Rect::default(),
+			Position::Top(Rect { x, y, width, height }) => {
+				let (x, y) = (x.min(columns.saturating_sub(width)), y.min(rows.saturating_sub(height)));
+				((columns / 2).saturating_sub(width / 2) + x, y)
+			}
+			Position::Hovered(rect) => {
+				let Some(r) = hovered else {
+					return Position::Top(rect).area(None);
+				};
+				let a = Self::anchored(r, rect, screen);
+				(a.x, a.y)
+			}
+		};
+
+		let (width, height) = self.dimension().unwrap();
+		Self::clamp(Rect { x, y, width, height }, screen)
+	}
+
+	// The whole screen
+	#[inline]
+	pub fn screen() -> Rect {
+		let WindowSize { columns, rows, .. } = Term::size();
+		Rect { x: 0, y: 0, width: columns, height: rows }
+	}
+
+	// A popup of the size of `popup` next to `anchor`, offset by its `x` and `y`, right below the
+	// anchor, or right above it if it'd go past the bottom of `bound`
+	pub fn anchored(anchor: Rect, popup: Rect, bound: Rect) -> Rect {
+		let Rect { x, y, width, height } = popup;
+		let x = x.min(bound.width.saturating_sub(width)) + anchor.x;
+		let y = if anchor.bottom() + y + height > bound.bottom() {
+			anchor.y.saturating_sub(height)
+		} else {
+			anchor.bottom() + y
+		};
+		Rect { x, y, width, height }
+	}
+
+	// Shrinks `rect` to the size of `bound` if it's larger, and moves it into `bound` as little
+	// as it takes, e.g. for a popup larger than the terminal
+	pub fn clamp(rect: Rect, bound: Rect) -> Rect {
+		let (width, height) = (rect.width.min(bound.width), rect.height.min(bound.height));
+		Rect {
+			x: rect.x.clamp(bound.x, bound.right() - width),
+			y: rect.y.clamp(bound.y, bound.bottom() - height),
+			width,
+			height,
+		}
+	}
+
+	// The row of the entry at `index` of a list in `pane`, scrolled down by `offset` entries, none
+	// if it's out of view
+	pub fn row(pane: Rect, offset: usize, index: usize) -> Option<Rect> {
+		let y = index.checked_sub(offset).filter(|&y| y < pane.height as usize)?;
+		Some(Rect { y: pane.y + y as u16, height: 1, ..pane })
+	}
+}
+
+#[test]
+fn test_position() {
+	let screen = Rect { x: 0, y: 0, width: 80, height: 24 };
+	let clamp = |x, y, width, height| Position::clamp(Rect { x, y, width, height }, screen);
+
+	assert_eq!(clamp(10, 5, 50, 3), Rect { x: 10, y: 5, width: 50, height: 3 });
+	assert_eq!(clamp(70, 22, 20, 5), Rect { x: 60, y: 19, width: 20, height: 5 });
+	// Larger than the screen
+	assert_eq!(clamp(30, 10, 100, 30), screen);
+
+	let popup = Rect { x: 0, y: 0, width: 50, height: 3 };
+	let anchor = Rect { x: 20, y: 5, width: 40, height: 1 };
+	assert_eq!(Position::anchored(anchor, popup, screen), Rect { x: 20, y: 6, ..popup });
+	let anchor = Rect { y: 21, ..anchor };
+	assert_eq!(Position::anchored(anchor, popup, screen), Rect { x: 20, y: 18, ..popup });
+
+	let pane = Rect { x: 10, y: 1, width: 30, height: 20 };
+	assert_eq!(Position::row(pane, 5, 7), Some(Rect { x: 10, y: 3, width: 30, height: 1 }));
+	assert_eq!(Position::row(pane, 5, 4), None);
+	assert_eq!(Position::row(pane, 5, 25), None);
 }
@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::{sync::mpsc, time::sleep};
+use tracing::warn;
+
+use crate::emit;
+
+// The theme file is watched by the directory it's in, since it may not be there yet, or be replaced
+// by another one as it's saved by the editors, and the changes in a row are taken as one
+pub(super) fn init_reloader() {
+	let Some(file) = config::theme_file() else {
+		return;
+	};
+	let Some(dir) = file.parent().filter(|d| d.is_dir()).map(|d| d.to_owned()) else {
+		return;
+	};
+
+	let (tx, mut rx) = mpsc::unbounded_channel();
+	let watcher = RecommendedWatcher::new(
+		move |res: Result<notify::Event, notify::Error>| {
+			let Ok(event) = res else {
+				return;
+			};
+			if matches!(event.kind, EventKind::Access(_)) || !event.paths.contains(&file) {
+				return;
+			}
+			tx.send(()).ok();
+		},
+		Default::default(),
+	);
+	let mut watcher = match watcher {
+		Ok(w) => w,
+		Err(e) => return warn!("failed to watch the theme: {e}"),
+	};
+	if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+		return warn!("failed to watch the theme: {e}");
+	}
+
+	tokio::spawn(async move {
+		let _watcher = watcher;
+		while rx.recv().await.is_some() {
+			sleep(Duration::from_millis(100)).await;
+			while rx.try_recv().is_ok() {}
+			emit!(Theme);
+		}
+	});
+}
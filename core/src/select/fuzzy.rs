@@ -0,0 +1,114 @@
+// The score of each matched character, and the bonuses and penalties on top of it
+const SCORE_MATCH: i64 = 16;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CONSECUTIVE: i64 = 8;
+const PENALTY_GAP: i64 = 1;
+
+// Matches the characters of `query` in order against `s`, like fzf, the case ignored unless
+// `query` has an uppercase letter, and returns the score of the best match along with
+// the indices of the matched chars, or `None` if they aren't all there
+pub fn fuzzy(query: &str, s: &str) -> Option<(i64, Vec<usize>)> {
+	let sensitive = query.chars().any(|c| c.is_uppercase());
+	let fold = |c: char| if sensitive { c } else { c.to_lowercase().next().unwrap_or(c) };
+
+	let query: Vec<_> = query.chars().map(fold).collect();
+	let chars: Vec<_> = s.chars().collect();
+	if query.is_empty() {
+		return Some((0, vec![]));
+	}
+
+	let (m, n) = (query.len(), chars.len());
+	let bonus: Vec<_> = (0..n)
+		.map(|j| {
+			let boundary = j == 0
+				|| !chars[j - 1].is_alphanumeric()
+				|| (chars[j - 1].is_lowercase() && chars[j].is_uppercase());
+			if boundary { BONUS_BOUNDARY } else { 0 }
+		})
+		.collect();
+
+	// The best score of matching the first `i + 1` chars of the query, with the last one at `j`,
+	// and where the one before it is
+	let mut score = vec![vec![None::<i64>; n]; m];
+	let mut from = vec![vec![0; n]; m];
+	for i in 0..m {
+		// The best of the ones before the previous char, with the gap to `j` taken off
+		let mut best: Option<(i64, usize)> = None;
+		for j in i..n {
+			if i > 0 && j >= 2 {
+				if let Some(s) = score[i - 1][j - 2] {
+					let s = s + PENALTY_GAP * (j - 2) as i64;
+					if best.is_none_or(|(b, _)| s > b) {
+						best = Some((s, j - 2));
+					}
+				}
+			}
+			if fold(chars[j]) != query[i] {
+				continue;
+			}
+
+			let here = SCORE_MATCH + bonus[j];
+			if i == 0 {
+				score[i][j] = Some(here);
+				continue;
+			}
+
+			let consecutive = score[i - 1][j - 1].map(|s| (s + here + BONUS_CONSECUTIVE, j - 1));
+			let gapped = best.map(|(s, k)| (s - PENALTY_GAP * (j - 1) as i64 + here, k));
+			if let Some((s, k)) = consecutive.into_iter().chain(gapped).max_by_key(|&(s, _)| s) {
+				(score[i][j], from[i][j]) = (Some(s), k);
+			}
+		}
+	}
+
+	let (mut j, best) = (0..n).filter_map(|j| Some((j, score[m - 1][j]?))).max_by_key(|&(_, s)| s)?;
+	let mut indices = vec![0; m];
+	for i in (0..m).rev() {
+		indices[i] = j;
+		j = from[i][j];
+	}
+	Some((best, indices))
+}
+
+// Finds `query` in `s`, with the same case rule as `fuzzy`, and returns the indices of the chars
+pub fn exact(query: &str, s: &str) -> Option<Vec<usize>> {
+	let sensitive = query.chars().any(|c| c.is_uppercase());
+	let fold = |c: char| if sensitive { c } else { c.to_lowercase().next().unwrap_or(c) };
+
+	let query: Vec<_> = query.chars().map(fold).collect();
+	let chars: Vec<_> = s.chars().map(fold).collect();
+	if query.is_empty() {
+		return Some(vec![]);
+	}
+
+	let start = chars.windows(query.len()).position(|w| w == query)?;
+	Some((start..start + query.len()).collect())
+}
+
+#[test]
+fn test_fuzzy() {
+	assert_eq!(fuzzy("abc", "xaxbxc").map(|(_, i)| i), Some(vec![1, 3, 5]));
+	assert_eq!(fuzzy("abc", "acb"), None);
+	assert_eq!(fuzzy("", "abc"), Some((0, vec![])));
+
+	// The consecutive chars and the start of the words are preferred
+	assert_eq!(fuzzy("fb", "foo_bar").map(|(_, i)| i), Some(vec![0, 4]));
+	assert_eq!(fuzzy("bar", "b_a_r bar").map(|(_, i)| i), Some(vec![6, 7, 8]));
+	assert_eq!(fuzzy("fn", "FooName").map(|(_, i)| i), Some(vec![0, 3]));
+
+	let score = |q, s| fuzzy(q, s).unwrap().0;
+	assert!(score("rs", "main.rs") > score("rs", "rules"));
+	assert!(score("ab", "ab") > score("ab", "a_b"));
+	assert!(score("ab", "a_b") > score("ab", "a__b"));
+
+	// Smart case
+	assert!(fuzzy("A", "abc").is_none());
+	assert!(fuzzy("a", "ABC").is_some());
+}
+
+#[test]
+fn test_exact() {
+	assert_eq!(exact("oba", "FooBar"), Some(vec![2, 3, 4]));
+	assert_eq!(exact("oB", "foobar"), None);
+	assert_eq!(exact("", "foo"), Some(vec![]));
+}
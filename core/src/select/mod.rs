@@ -1,6 +1,8 @@
+mod fuzzy;
 mod option;
 mod select;
 
+pub use fuzzy::*;
 pub use option::*;
 pub use select::*;
 
@@ -6,6 +6,8 @@ pub struct SelectOpt {
 	pub title:    String,
 	pub items:    Vec<String>,
 	pub position: Position,
+	// Whether the filter is being typed in right away
+	pub filter:   bool,
 }
 
 impl SelectOpt {
@@ -15,6 +17,7 @@ impl SelectOpt {
 			title: title.to_owned(),
 			items,
 			position: Position::Top(/* TODO: hardcode */ Rect { x: 0, y: 2, width: 50, height }),
+			filter: false,
 		}
 	}
 
@@ -25,8 +28,15 @@ impl SelectOpt {
 			items,
 			position: Position::Hovered(
 				// TODO: hardcode
-				Rect { x: 0, y: 1, width: 50, height },
+				Rect { x: 0, y: 0, width: 50, height },
 			),
+			filter: false,
 		}
 	}
+
+	#[inline]
+	pub fn with_filter(mut self) -> Self {
+		self.filter = true;
+		self
+	}
 }
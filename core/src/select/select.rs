@@ -1,8 +1,9 @@
 use anyhow::{anyhow, Result};
+use config::{keymap::Key, select::SelectMatching, SELECT};
 use tokio::sync::oneshot::Sender;
 
-use super::SelectOpt;
-use crate::Position;
+use super::{exact, fuzzy, SelectOpt};
+use crate::{input::Input, Position};
 
 #[derive(Default)]
 pub struct Select {
@@ -10,6 +11,11 @@ pub struct Select {
 	items:        Vec<String>,
 	pub position: Position,
 
+	// Filter, with the index of each matched item, and of the chars matched in it
+	matches:   Vec<(usize, Vec<usize>)>,
+	keyword:   String,
+	in_filter: Option<Input>,
+
 	offset:   usize,
 	cursor:   usize,
 	callback: Option<Sender<Result<usize>>>,
@@ -25,23 +31,50 @@ impl Select {
 		self.items = opt.items;
 		self.position = opt.position;
 
+		self.keyword.clear();
+		self.in_filter = opt.filter.then(Input::default);
+		self.filter_apply();
+
 		self.callback = Some(tx);
 		self.visible = true;
 	}
 
 	pub fn close(&mut self, submit: bool) -> bool {
 		if let Some(cb) = self.callback.take() {
-			let _ = cb.send(if submit { Ok(self.cursor) } else { Err(anyhow!("canceled")) });
+			let chosen = self.matches.get(self.cursor).map(|&(i, _)| i).filter(|_| submit);
+			let _ = cb.send(chosen.ok_or_else(|| anyhow!("canceled")));
 		}
 
 		self.cursor = 0;
 		self.offset = 0;
+		self.in_filter = None;
 		self.visible = false;
 		true
 	}
 
+	pub fn escape(&mut self) -> bool {
+		if self.in_filter.is_none() && self.keyword.is_empty() {
+			return self.close(false);
+		}
+
+		self.keyword.clear();
+		self.in_filter = None;
+		(self.cursor, self.offset) = (0, 0);
+		self.filter_apply()
+	}
+
+	pub fn push(&mut self, items: Vec<String>) -> bool {
+		if !self.visible || items.is_empty() {
+			return false;
+		}
+
+		self.items.extend(items);
+		self.filter_apply();
+		true
+	}
+
 	pub fn next(&mut self, step: usize) -> bool {
-		let len = self.items.len();
+		let len = self.matches.len();
 		if len == 0 {
 			return false;
 		}
@@ -68,20 +101,93 @@ impl Select {
 		old != self.cursor
 	}
 
+	pub fn filter(&mut self) -> bool {
+		let mut input = Input::default();
+		input.type_str(&self.keyword);
+		self.in_filter = Some(input);
+		true
+	}
+
+	pub fn type_(&mut self, key: &Key) -> bool {
+		let Some(input) = &mut self.in_filter else {
+			return false;
+		};
+
+		// Left to the keymap, to submit the selection right away
+		if key.is_enter() {
+			return false;
+		}
+
+		if !input.type_(key) {
+			return false;
+		}
+
+		self.keyword = input.value().to_owned();
+		(self.cursor, self.offset) = (0, 0);
+		self.filter_apply()
+	}
+
+	// Matches the items against the keyword, fuzzy ones ordered by the score, then the length
+	fn filter_apply(&mut self) -> bool {
+		let kw = self.keyword.as_str();
+		self.matches = match SELECT.matching {
+			_ if kw.is_empty() => (0..self.items.len()).map(|i| (i, vec![])).collect(),
+			SelectMatching::Exact => {
+				self.items.iter().enumerate().filter_map(|(i, s)| Some((i, exact(kw, s)?))).collect()
+			}
+			SelectMatching::Fuzzy => {
+				let mut matches: Vec<_> = self
+					.items
+					.iter()
+					.enumerate()
+					.filter_map(|(i, s)| fuzzy(kw, s).map(|(score, m)| (score, s.chars().count(), i, m)))
+					.collect();
+
+				matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+				matches.into_iter().map(|(_, _, i, m)| (i, m)).collect()
+			}
+		};
+
+		self.cursor = self.cursor.min(self.matches.len().saturating_sub(1));
+		self.offset = self.offset.min(self.cursor);
+		let height = 2 + self.limit() as u16;
+		if let Position::Top(rect) | Position::Hovered(rect) = &mut self.position {
+			rect.height = height;
+		}
+		true
+	}
+
+	// The items in view, with the indices of the chars matched by the filter
 	#[inline]
-	pub fn window(&self) -> &[String] {
-		let end = (self.offset + self.limit()).min(self.items.len());
-		&self.items[self.offset..end]
+	pub fn window(&self) -> impl Iterator<Item = (&str, &[usize])> {
+		let end = (self.offset + self.limit()).min(self.matches.len());
+		self.matches[self.offset..end].iter().map(|(i, m)| (self.items[*i].as_str(), m.as_slice()))
 	}
 
 	#[inline]
-	pub fn limit(&self) -> usize { self.items.len().min(5) }
+	pub fn limit(&self) -> usize { self.matches.len().min(5) }
 }
 
 impl Select {
 	#[inline]
-	pub fn title(&self) -> String { self.title.clone() }
+	pub fn title(&self) -> String {
+		match &self.in_filter {
+			Some(_) => format!("{} /{}", self.title, self.keyword),
+			None if !self.keyword.is_empty() => format!("{} ({})", self.title, self.keyword),
+			None => self.title.clone(),
+		}
+	}
+
+	#[inline]
+	pub fn cursor(&self) -> usize { self.cursor }
 
 	#[inline]
 	pub fn rel_cursor(&self) -> usize { self.cursor - self.offset }
+
+	// The number of the items matched by the filter, or all of them without one
+	#[inline]
+	pub fn len(&self) -> usize { self.matches.len() }
+
+	#[inline]
+	pub fn in_filter(&self) -> bool { self.in_filter.is_some() }
 }
@@ -0,0 +1,169 @@
+use std::{collections::{HashMap, VecDeque}, fs, io::{self, Read, Seek, SeekFrom, Write}, os::unix::{ffi::OsStrExt, fs::{MetadataExt, PermissionsExt}}, time::{Duration, SystemTime}};
+
+use anyhow::Result;
+use tokio::{io::{duplex, split, AsyncWriteExt, DuplexStream}, task::JoinHandle};
+
+use super::{bytes_path, insert, read_packet, Attrs, Name, Packet, Reply, Session, FXF_CREAT, FXF_READ, FXF_TRUNC, FXF_WRITE, FXP_ATTRS, FXP_CLOSE, FXP_DATA, FXP_HANDLE, FXP_INIT, FXP_LSTAT, FXP_MKDIR, FXP_NAME, FXP_OPEN, FXP_OPENDIR, FXP_READ, FXP_READDIR, FXP_READLINK, FXP_REALPATH, FXP_REMOVE, FXP_RENAME, FXP_RMDIR, FXP_SETSTAT, FXP_STAT, FXP_STATUS, FXP_VERSION, FXP_WRITE, FX_EOF, FX_NO_SUCH_FILE};
+
+// The SFTP server of the tests, of the local files by their paths as they are, talked to over a
+// pipe in place of `ssh`. It gives back at most `read` bytes of a read, and `names` names of
+// a directory at once, for the reads cut short and the paging to be gone through
+pub(crate) struct Fake {
+	pub(crate) read:  usize,
+	pub(crate) names: usize,
+}
+
+enum Handle {
+	File(fs::File),
+	Dir(VecDeque<Name>),
+}
+
+impl Fake {
+	// Serves as `host`, until the task returned is aborted, or the runtime it's on is gone
+	pub(crate) async fn serve(self, host: &str) -> JoinHandle<()> {
+		let (client, server) = duplex(1 << 20);
+		let server = tokio::spawn(self.run(server));
+
+		let (stdout, stdin) = split(client);
+		let errors = tokio::spawn(async { String::new() });
+		insert(host, Session::start(host, stdin, stdout, errors, None).await.unwrap());
+		server
+	}
+
+	async fn run(self, pipe: DuplexStream) {
+		let (mut r, mut w) = split(pipe);
+		let mut handles = HashMap::new();
+		while let Ok(buf) = read_packet(&mut r).await {
+			let Ok(req) = Reply::parse(buf) else { break };
+			let id = req.id;
+			let res = self.respond(req, &mut handles).unwrap_or_else(|e| {
+				let code = match e.downcast_ref::<io::Error>().map(|e| e.kind()) {
+					Some(io::ErrorKind::NotFound) => FX_NO_SUCH_FILE,
+					Some(io::ErrorKind::PermissionDenied) => 3,
+					_ => 4,
+				};
+				status(id, code)
+			});
+			if w.write_all(&res.finish()).await.is_err() {
+				break;
+			}
+		}
+	}
+
+	fn respond(&self, mut req: Reply, handles: &mut HashMap<Vec<u8>, Handle>) -> Result<Packet> {
+		let id = req.id;
+		let ok = status(id, 0);
+		Ok(match req.kind {
+			FXP_INIT => Packet::new(FXP_VERSION, 3),
+			FXP_OPEN => {
+				let (path, flags) = (bytes_path(req.bytes()?), req.u32()?);
+				let file = fs::File::options()
+					.read(flags & FXF_READ != 0)
+					.write(flags & FXF_WRITE != 0)
+					.create(flags & FXF_CREAT != 0)
+					.truncate(flags & FXF_TRUNC != 0)
+					.open(path)?;
+				let handle = id.to_be_bytes().to_vec();
+				handles.insert(handle.clone(), Handle::File(file));
+				Packet::new(FXP_HANDLE, id).bytes(&handle)
+			}
+			FXP_OPENDIR => {
+				let path = bytes_path(req.bytes()?);
+				let mut names = VecDeque::from([(b".".to_vec(), attrs(&fs::metadata(&path)?))]);
+				for entry in fs::read_dir(&path)? {
+					let entry = entry?;
+					let name = entry.file_name().as_bytes().to_vec();
+					names.push_back((name, attrs(&fs::symlink_metadata(entry.path())?)));
+				}
+				let handle = id.to_be_bytes().to_vec();
+				handles.insert(handle.clone(), Handle::Dir(names));
+				Packet::new(FXP_HANDLE, id).bytes(&handle)
+			}
+			FXP_CLOSE => {
+				handles.remove(&req.bytes()?);
+				ok
+			}
+			FXP_READ => {
+				let (handle, offset, len) = (req.bytes()?, req.u64()?, req.u32()? as usize);
+				let Some(Handle::File(f)) = handles.get_mut(&handle) else { return Ok(status(id, 4)) };
+				f.seek(SeekFrom::Start(offset))?;
+				let mut buf = vec![0; len.min(self.read)];
+				let n = f.read(&mut buf)?;
+				if n == 0 {
+					return Ok(status(id, FX_EOF));
+				}
+				Packet::new(FXP_DATA, id).bytes(&buf[..n])
+			}
+			FXP_WRITE => {
+				let (handle, offset, data) = (req.bytes()?, req.u64()?, req.bytes()?);
+				let Some(Handle::File(f)) = handles.get_mut(&handle) else { return Ok(status(id, 4)) };
+				f.seek(SeekFrom::Start(offset))?;
+				f.write_all(&data)?;
+				ok
+			}
+			FXP_READDIR => {
+				let Some(Handle::Dir(names)) = handles.get_mut(&req.bytes()?) else {
+					return Ok(status(id, 4));
+				};
+				if names.is_empty() {
+					return Ok(status(id, FX_EOF));
+				}
+				let page: Vec<_> = names.drain(..self.names.min(names.len())).collect();
+				named(id, page)
+			}
+			FXP_STAT => Packet::new(FXP_ATTRS, id).attrs(&attrs(&fs::metadata(bytes_path(req.bytes()?))?)),
+			FXP_LSTAT => {
+				let meta = fs::symlink_metadata(bytes_path(req.bytes()?))?;
+				Packet::new(FXP_ATTRS, id).attrs(&attrs(&meta))
+			}
+			FXP_SETSTAT => {
+				let (path, attrs) = (bytes_path(req.bytes()?), req.attrs()?);
+				if let Some(perms) = attrs.perms {
+					fs::set_permissions(&path, fs::Permissions::from_mode(perms))?;
+				}
+				if let Some((atime, mtime)) = attrs.times {
+					let time = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs as u64);
+					let times = fs::FileTimes::new().set_accessed(time(atime)).set_modified(time(mtime));
+					fs::File::open(&path)?.set_times(times)?;
+				}
+				ok
+			}
+			FXP_MKDIR => fs::create_dir(bytes_path(req.bytes()?)).map(|_| ok)?,
+			FXP_RMDIR => fs::remove_dir(bytes_path(req.bytes()?)).map(|_| ok)?,
+			FXP_REMOVE => fs::remove_file(bytes_path(req.bytes()?)).map(|_| ok)?,
+			FXP_RENAME => {
+				let (from, to) = (bytes_path(req.bytes()?), bytes_path(req.bytes()?));
+				if fs::symlink_metadata(&to).is_ok() {
+					return Ok(status(id, 4));
+				}
+				fs::rename(from, to).map(|_| ok)?
+			}
+			FXP_REALPATH | FXP_READLINK => {
+				let path = bytes_path(req.bytes()?);
+				let path = if req.kind == FXP_REALPATH { fs::canonicalize(path)? } else { fs::read_link(path)? };
+				named(id, vec![(path.as_os_str().as_bytes().to_vec(), Attrs::default())])
+			}
+			_ => status(id, 8),
+		})
+	}
+}
+
+#[inline]
+fn status(id: u32, code: u32) -> Packet { Packet::new(FXP_STATUS, id).u32(code).bytes(b"").bytes(b"") }
+
+fn named(id: u32, names: Vec<Name>) -> Packet {
+	let mut p = Packet::new(FXP_NAME, id).u32(names.len() as u32);
+	for (name, attrs) in names {
+		p = p.bytes(&name).bytes(b"").attrs(&attrs);
+	}
+	p
+}
+
+fn attrs(meta: &fs::Metadata) -> Attrs {
+	Attrs {
+		size:  Some(meta.len()),
+		owner: Some((meta.uid(), meta.gid())),
+		perms: Some(meta.mode()),
+		times: Some((meta.atime() as u32, meta.mtime() as u32)),
+	}
+}
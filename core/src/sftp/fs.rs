@@ -0,0 +1,221 @@
+use std::{collections::hash_map::DefaultHasher, hash::{Hash, Hasher}, path::PathBuf, sync::Arc, time::{SystemTime, UNIX_EPOCH}};
+
+use anyhow::{anyhow, Result};
+use config::PREVIEW;
+use shared::Url;
+use tokio::{fs, io::AsyncWriteExt};
+
+use super::{bytes_path, session, Attrs, Packet, Session, Status, FXF_READ, FXP_CLOSE, FXP_LSTAT, FXP_MKDIR, FXP_OPEN, FXP_OPENDIR, FXP_READ, FXP_READDIR, FXP_READLINK, FXP_REALPATH, FXP_REMOVE, FXP_RENAME, FXP_RMDIR, FXP_SETSTAT, FXP_STAT};
+use crate::files::Meta;
+
+// How much is asked for in a read, which any server gives at once
+pub(super) const CHUNK: u32 = 32 * 1024;
+
+#[inline]
+pub(super) async fn session_of(url: &Url) -> Result<Arc<Session>> {
+	session(url.host().ok_or_else(|| anyhow!("not a remote url: {url:?}"))?).await
+}
+
+// Whether the host of `url` is connected to already, for the first connection not to be waited on
+#[inline]
+pub fn connected(url: &Url) -> bool { url.host().is_some_and(super::is_open) }
+
+// Whether the error is of a file that doesn't exist on the server
+#[inline]
+pub fn is_not_found(e: &anyhow::Error) -> bool {
+	e.downcast_ref::<Status>().is_some_and(|s| s.is_not_found())
+}
+
+pub async fn metadata(url: &Url, follow: bool) -> Result<Meta> {
+	let kind = if follow { FXP_STAT } else { FXP_LSTAT };
+	let s = session_of(url).await?;
+	Ok(s.request(|id| Packet::new(kind, id).path(url)).await?.attrs_of()?.meta())
+}
+
+// The path on the server `url` is of, made absolute, e.g. the home directory of `sftp://host`
+pub async fn canonicalize(url: &Url) -> Result<Url> {
+	let s = session_of(url).await?;
+	let path = s.request(|id| Packet::new(FXP_REALPATH, id).path(url)).await?.path()?;
+	Ok(url.join(path))
+}
+
+// Where the symlink points to, as a remote one, relative to the directory of the symlink
+pub async fn read_link(url: &Url) -> Result<Url> {
+	let s = session_of(url).await?;
+	let path = s.request(|id| Packet::new(FXP_READLINK, id).path(url)).await?.path()?;
+	Ok(url.parent_url().unwrap_or_else(|| url.clone()).join(path))
+}
+
+// The entries of the directory, with their own metadata, not of the files the symlinks point to
+pub async fn read_dir(url: &Url) -> Result<Vec<(Url, Meta)>> {
+	let s = session_of(url).await?;
+	let handle = s.request(|id| Packet::new(FXP_OPENDIR, id).path(url)).await?.handle()?;
+
+	let mut entries = vec![];
+	let result = async {
+		loop {
+			let reply = s.request(|id| Packet::new(FXP_READDIR, id).bytes(&handle)).await?;
+			let Some(names) = reply.names()? else { break };
+			let names = names.into_iter().filter(|(n, _)| n != b"." && n != b"..");
+			entries.extend(names.map(|(n, a)| (url.join(bytes_path(n)), a.meta())));
+		}
+		Ok::<_, anyhow::Error>(())
+	}
+	.await;
+
+	s.request(|id| Packet::new(FXP_CLOSE, id).bytes(&handle)).await.ok();
+	result.map(|_| entries)
+}
+
+#[inline]
+pub async fn create_dir(url: &Url) -> Result<()> {
+	let s = session_of(url).await?;
+	s.request(|id| Packet::new(FXP_MKDIR, id).path(url).attrs(&Attrs::default())).await?.status()
+}
+
+#[inline]
+pub async fn remove_file(url: &Url) -> Result<()> {
+	let s = session_of(url).await?;
+	s.request(|id| Packet::new(FXP_REMOVE, id).path(url)).await?.status()
+}
+
+#[inline]
+pub async fn remove_dir(url: &Url) -> Result<()> {
+	let s = session_of(url).await?;
+	s.request(|id| Packet::new(FXP_RMDIR, id).path(url)).await?.status()
+}
+
+// Removes the directory and the ones under it that are empty, deepest first, as the files in them
+// are removed already
+pub async fn remove_empty_dirs(url: &Url) {
+	let mut dirs = vec![url.clone()];
+	let mut stack = vec![url.clone()];
+	while let Some(dir) = stack.pop() {
+		let entries = read_dir(&dir).await.unwrap_or_default();
+		for (sub, _) in entries.into_iter().filter(|(_, m)| m.is_dir()) {
+			stack.push(sub.clone());
+			dirs.push(sub);
+		}
+	}
+	for dir in dirs.into_iter().rev() {
+		remove_dir(&dir).await.ok();
+	}
+}
+
+// Renames the file, which fails if `to` exists, as the servers of version 3 have it
+pub async fn rename(from: &Url, to: &Url) -> Result<()> {
+	let s = session_of(from).await?;
+	s.request(|id| Packet::new(FXP_RENAME, id).path(from).path(to)).await?.status()
+}
+
+// Gives the file the mode and the times of `meta`, the ones the server can be asked to set
+pub async fn set_meta(url: &Url, meta: &Meta, mode: bool, times: bool) -> Result<()> {
+	let secs = |t: Option<SystemTime>| {
+		t.and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs() as u32)
+	};
+	let attrs = Attrs {
+		perms: mode.then_some(meta.mode() & 0o7777),
+		times: times.then(|| secs(meta.accessed()).zip(secs(meta.modified()))).flatten(),
+		..Default::default()
+	};
+	if attrs == Attrs::default() {
+		return Ok(());
+	}
+
+	let s = session_of(url).await?;
+	s.request(|id| Packet::new(FXP_SETSTAT, id).path(url).attrs(&attrs)).await?.status()
+}
+
+// The first `n` bytes of the file at most, to tell the type of it by
+pub async fn head(url: &Url, n: u32) -> Result<Vec<u8>> {
+	let s = session_of(url).await?;
+	let open = |id| Packet::new(FXP_OPEN, id).path(url).u32(FXF_READ).attrs(&Attrs::default());
+	let handle = s.request(open).await?.handle()?;
+
+	let read = s.request(|id| Packet::new(FXP_READ, id).bytes(&handle).u64(0).u32(n)).await;
+	s.request(|id| Packet::new(FXP_CLOSE, id).bytes(&handle)).await.ok();
+	Ok(read?.data()?.unwrap_or_default())
+}
+
+// Downloads the file to the cache for a preview, up to `max` bytes of it, only once for the file as
+// long as it's the same on the server
+pub async fn fetch(url: &Url, meta: &Meta, max: u64) -> Result<PathBuf> {
+	let mut h = DefaultHasher::new();
+	(format!("{url:?}"), meta.len(), meta.modified()).hash(&mut h);
+
+	let name = url.file_name().map(PathBuf::from).unwrap_or_else(|| PathBuf::from("remote"));
+	let dir = PREVIEW.cache_dir.join(format!("sftp-{:x}", h.finish()));
+	let path = dir.join(name);
+	if fs::metadata(&path).await.is_ok() {
+		return Ok(path);
+	}
+
+	fs::create_dir_all(&dir).await?;
+	let part = PREVIEW.tmpfile("sftp");
+	let mut f = fs::File::create(&part).await?;
+	let mut reader = super::Reader::open(url).await?;
+	let mut written = 0;
+	while written < max {
+		let Some(chunk) = reader.next().await? else { break };
+		let n = chunk.len().min((max - written) as usize);
+		f.write_all(&chunk[..n]).await?;
+		written += n as u64;
+	}
+
+	reader.close().await.ok();
+	f.flush().await?;
+	fs::rename(part, &path).await?;
+	Ok(path)
+}
+
+// `url` if there's no file of it, or the one with the first number after its name of none
+pub async fn unique(mut url: Url) -> Url {
+	let Some(name) = url.file_name().map(|n| n.to_os_string()) else {
+		return url;
+	};
+
+	let mut i = 0;
+	while metadata(&url, false).await.is_ok() {
+		i += 1;
+		let mut name = name.clone();
+		name.push(format!("_{i}"));
+		url.set_file_name(name);
+	}
+	url
+}
+
+#[cfg(unix)]
+#[test]
+fn test_fs() {
+	let dir = shared::TestDir::new("sftp-fs");
+	for name in ["a", "b", "c", "d", "e"] {
+		std::fs::write(dir.join(name), name).unwrap();
+	}
+	std::fs::create_dir(dir.join("f")).unwrap();
+
+	shared::block_on(async {
+		// Two names a page, with `.` and `..` among them
+		let _server = super::Fake { read: usize::MAX, names: 2 }.serve("fs").await;
+		let url = |name: &str| Url::parse_sftp(&format!("sftp://fs{}", dir.join(name).display()));
+		let url = |name| url(name).unwrap();
+
+		let mut entries = read_dir(&url("")).await.unwrap();
+		entries.sort_by(|a, b| a.0.cmp(&b.0));
+		let names: Vec<_> = entries.iter().map(|(u, _)| u.file_name().unwrap().to_owned()).collect();
+		assert_eq!(names, ["a", "b", "c", "d", "e", "f"]);
+		assert!(entries.iter().all(|(u, _)| u.host() == Some("fs")));
+		assert!(entries[5].1.is_dir() && entries[0].1.len() == 1);
+
+		// Not over a file there, and not found once it's gone
+		assert!(rename(&url("a"), &url("b")).await.is_err());
+		rename(&url("a"), &url("f/a")).await.unwrap();
+		assert!(rename(&url("a"), &url("g")).await.is_err_and(|e| is_not_found(&e)));
+		assert!(metadata(&url("a"), false).await.is_err_and(|e| is_not_found(&e)));
+		assert!(metadata(&url("f/a"), false).await.unwrap().is_file());
+
+		create_dir(&url("g")).await.unwrap();
+		remove_file(&url("f/a")).await.unwrap();
+		remove_dir(&url("f")).await.unwrap();
+		assert!(canonicalize(&url("g/..")).await.unwrap() == url(""));
+	});
+}
@@ -0,0 +1,177 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use anyhow::Result;
+use shared::Url;
+use tokio::{fs, io::{AsyncReadExt, AsyncWriteExt}};
+
+use super::{session_of, Attrs, Packet, Pending, Session, CHUNK, FXF_CREAT, FXF_READ, FXF_TRUNC, FXF_WRITE, FXP_CLOSE, FXP_OPEN, FXP_READ, FXP_WRITE};
+
+// How many of the chunks are on the way at once, for the round trips not to add up
+const AHEAD: usize = 16;
+
+// A file read in chunks, a local one, or a remote one read ahead of the chunk asked for
+pub enum Reader {
+	Local(fs::File),
+	Remote(Remote),
+}
+
+// A file written in chunks, where a remote one isn't done until it's closed
+pub enum Writer {
+	Local(fs::File),
+	Remote(Remote),
+}
+
+pub struct Remote {
+	session: Arc<Session>,
+	handle:  Vec<u8>,
+	offset:  u64,
+	ahead:   VecDeque<(u64, Pending)>,
+	eof:     bool,
+}
+
+impl Remote {
+	async fn open(url: &Url, flags: u32) -> Result<Self> {
+		let session = session_of(url).await?;
+		let open = |id| Packet::new(FXP_OPEN, id).path(url).u32(flags).attrs(&Attrs::default());
+		let handle = session.request(open).await?.handle()?;
+		Ok(Self { session, handle, offset: 0, ahead: VecDeque::new(), eof: false })
+	}
+
+	async fn close(self) -> Result<()> {
+		let handle = &self.handle;
+		self.session.request(|id| Packet::new(FXP_CLOSE, id).bytes(handle)).await?.status()
+	}
+}
+
+impl Reader {
+	pub async fn open(url: &Url) -> Result<Self> {
+		if url.is_sftp() {
+			Ok(Self::Remote(Remote::open(url, FXF_READ).await?))
+		} else {
+			Ok(Self::Local(fs::File::open(url).await?))
+		}
+	}
+
+	// The next chunk, none at the end of the file
+	pub async fn next(&mut self) -> Result<Option<Vec<u8>>> {
+		let r = match self {
+			Self::Local(f) => {
+				let mut buf = vec![0; 256 * 1024];
+				let n = f.read(&mut buf).await?;
+				buf.truncate(n);
+				return Ok(Some(buf).filter(|b| !b.is_empty()));
+			}
+			Self::Remote(r) => r,
+		};
+
+		while !r.eof && r.ahead.len() < AHEAD {
+			let (handle, offset) = (&r.handle, r.offset);
+			let read = r.session.send(|id| {
+				Packet::new(FXP_READ, id).bytes(handle).u64(offset).u32(CHUNK)
+			})?;
+			r.ahead.push_back((offset, read));
+			r.offset += CHUNK as u64;
+		}
+
+		let Some((offset, read)) = r.ahead.pop_front() else {
+			return Ok(None);
+		};
+		let Some(data) = read.wait().await?.data()? else {
+			r.eof = true;
+			r.ahead.clear();
+			return Ok(None);
+		};
+
+		// Cut short, though not at the end, so the rest is asked for again from where it stopped
+		if data.len() < CHUNK as usize {
+			r.ahead.clear();
+			r.offset = offset + data.len() as u64;
+		}
+		Ok(Some(data))
+	}
+
+	pub async fn close(self) -> Result<()> {
+		match self {
+			Self::Local(_) => Ok(()),
+			Self::Remote(r) => r.close().await,
+		}
+	}
+}
+
+impl Writer {
+	pub async fn create(url: &Url) -> Result<Self> {
+		if url.is_sftp() {
+			Ok(Self::Remote(Remote::open(url, FXF_WRITE | FXF_CREAT | FXF_TRUNC).await?))
+		} else {
+			Ok(Self::Local(fs::File::create(url).await?))
+		}
+	}
+
+	pub async fn write(&mut self, data: &[u8]) -> Result<()> {
+		let r = match self {
+			Self::Local(f) => return Ok(f.write_all(data).await?),
+			Self::Remote(r) => r,
+		};
+
+		for chunk in data.chunks(CHUNK as usize) {
+			let (handle, offset) = (&r.handle, r.offset);
+			let write = r.session.send(|id| {
+				Packet::new(FXP_WRITE, id).bytes(handle).u64(offset).bytes(chunk)
+			})?;
+			r.ahead.push_back((offset, write));
+			r.offset += chunk.len() as u64;
+
+			if r.ahead.len() > AHEAD {
+				r.ahead.pop_front().unwrap().1.wait().await?.status()?;
+			}
+		}
+		Ok(())
+	}
+
+	// Waits for all of the chunks to be written, and closes the file
+	pub async fn close(self) -> Result<()> {
+		match self {
+			Self::Local(mut f) => Ok(f.flush().await?),
+			Self::Remote(mut r) => {
+				while let Some((_, write)) = r.ahead.pop_front() {
+					write.wait().await?.status()?;
+				}
+				r.close().await
+			}
+		}
+	}
+}
+
+#[cfg(unix)]
+#[test]
+fn test_reader_writer() {
+	let dir = shared::TestDir::new("sftp-io");
+	let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+	std::fs::write(dir.join("a"), &data).unwrap();
+
+	shared::block_on(async {
+		// Read whole, and cut short to far less than a chunk, to be asked for again from there
+		for (host, read) in [("io-full", usize::MAX), ("io-short", 10_000)] {
+			let _server = super::Fake { read, names: 100 }.serve(host).await;
+			let url = |name| Url::parse_sftp(&format!("sftp://{host}{}", dir.join(name).display()));
+
+			let mut reader = Reader::open(&url("a").unwrap()).await.unwrap();
+			let mut read = vec![];
+			while let Some(chunk) = reader.next().await.unwrap() {
+				read.extend(chunk);
+			}
+			reader.close().await.unwrap();
+			assert!(read == data, "{host}: {} bytes read of {}", read.len(), data.len());
+
+			// The chunks written at once are many more than the ones on the way
+			let mut writer = Writer::create(&url(host).unwrap()).await.unwrap();
+			writer.write(&data[..100]).await.unwrap();
+			writer.write(&data[100..]).await.unwrap();
+			writer.close().await.unwrap();
+			assert!(std::fs::read(dir.join(host)).unwrap() == data, "{host}");
+		}
+
+		let url = Url::parse_sftp(&format!("sftp://io-full{}", dir.join("none").display()));
+		assert!(Reader::open(&url.unwrap()).await.is_err_and(|e| super::is_not_found(&e)));
+	});
+}
@@ -0,0 +1,13 @@
+#[cfg(all(test, unix))]
+mod fake;
+mod fs;
+mod io;
+mod packet;
+mod session;
+
+#[cfg(all(test, unix))]
+pub(crate) use fake::Fake;
+pub use fs::*;
+pub use io::*;
+pub use packet::*;
+use session::*;
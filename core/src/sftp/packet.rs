@@ -0,0 +1,342 @@
+use std::{fmt::{self, Display}, path::{Path, PathBuf}};
+
+use anyhow::{bail, Result};
+
+use crate::files::Meta;
+
+// The types of the packets of version 3 of the protocol, the one OpenSSH speaks
+pub(super) const FXP_INIT: u8 = 1;
+pub(super) const FXP_VERSION: u8 = 2;
+pub(super) const FXP_OPEN: u8 = 3;
+pub(super) const FXP_CLOSE: u8 = 4;
+pub(super) const FXP_READ: u8 = 5;
+pub(super) const FXP_WRITE: u8 = 6;
+pub(super) const FXP_LSTAT: u8 = 7;
+pub(super) const FXP_SETSTAT: u8 = 9;
+pub(super) const FXP_OPENDIR: u8 = 11;
+pub(super) const FXP_READDIR: u8 = 12;
+pub(super) const FXP_REMOVE: u8 = 13;
+pub(super) const FXP_MKDIR: u8 = 14;
+pub(super) const FXP_RMDIR: u8 = 15;
+pub(super) const FXP_REALPATH: u8 = 16;
+pub(super) const FXP_STAT: u8 = 17;
+pub(super) const FXP_RENAME: u8 = 18;
+pub(super) const FXP_READLINK: u8 = 19;
+
+pub(super) const FXP_STATUS: u8 = 101;
+pub(super) const FXP_HANDLE: u8 = 102;
+pub(super) const FXP_DATA: u8 = 103;
+pub(super) const FXP_NAME: u8 = 104;
+pub(super) const FXP_ATTRS: u8 = 105;
+
+// The flags of opening a file
+pub(super) const FXF_READ: u32 = 0x01;
+pub(super) const FXF_WRITE: u32 = 0x02;
+pub(super) const FXF_CREAT: u32 = 0x08;
+pub(super) const FXF_TRUNC: u32 = 0x10;
+
+const ATTR_SIZE: u32 = 0x01;
+const ATTR_UIDGID: u32 = 0x02;
+const ATTR_PERMISSIONS: u32 = 0x04;
+const ATTR_ACMODTIME: u32 = 0x08;
+const ATTR_EXTENDED: u32 = 0x8000_0000;
+
+pub(super) const FX_EOF: u32 = 1;
+pub(super) const FX_NO_SUCH_FILE: u32 = 2;
+
+// A request, of its type and id followed by the fields of it, framed by its length once finished
+pub(super) struct Packet(Vec<u8>);
+
+impl Packet {
+	// The id of `FXP_INIT` is the version asked for
+	pub(super) fn new(kind: u8, id: u32) -> Self {
+		let mut buf = Vec::with_capacity(64);
+		buf.extend([0; 4]);
+		buf.push(kind);
+		buf.extend(id.to_be_bytes());
+		Self(buf)
+	}
+
+	#[inline]
+	pub(super) fn u32(mut self, n: u32) -> Self {
+		self.0.extend(n.to_be_bytes());
+		self
+	}
+
+	#[inline]
+	pub(super) fn u64(mut self, n: u64) -> Self {
+		self.0.extend(n.to_be_bytes());
+		self
+	}
+
+	pub(super) fn bytes(mut self, b: &[u8]) -> Self {
+		self.0.extend((b.len() as u32).to_be_bytes());
+		self.0.extend(b);
+		self
+	}
+
+	#[cfg(unix)]
+	#[inline]
+	pub(super) fn path(self, p: &Path) -> Self {
+		use std::os::unix::ffi::OsStrExt;
+		self.bytes(p.as_os_str().as_bytes())
+	}
+
+	// The paths on the server are separated by `/`, whatever the separator here is
+	#[cfg(not(unix))]
+	#[inline]
+	pub(super) fn path(self, p: &Path) -> Self {
+		self.bytes(p.to_string_lossy().replace('\\', "/").as_bytes())
+	}
+
+	pub(super) fn attrs(self, attrs: &Attrs) -> Self {
+		let mut flags = 0;
+		flags |= if attrs.size.is_some() { ATTR_SIZE } else { 0 };
+		flags |= if attrs.owner.is_some() { ATTR_UIDGID } else { 0 };
+		flags |= if attrs.perms.is_some() { ATTR_PERMISSIONS } else { 0 };
+		flags |= if attrs.times.is_some() { ATTR_ACMODTIME } else { 0 };
+
+		let mut p = self.u32(flags);
+		if let Some(size) = attrs.size {
+			p = p.u64(size);
+		}
+		if let Some((uid, gid)) = attrs.owner {
+			p = p.u32(uid).u32(gid);
+		}
+		if let Some(perms) = attrs.perms {
+			p = p.u32(perms);
+		}
+		if let Some((atime, mtime)) = attrs.times {
+			p = p.u32(atime).u32(mtime);
+		}
+		p
+	}
+
+	pub(super) fn finish(mut self) -> Vec<u8> {
+		let len = (self.0.len() - 4) as u32;
+		self.0[..4].copy_from_slice(&len.to_be_bytes());
+		self.0
+	}
+}
+
+// A name in a directory, as the bytes of it, with its attributes
+pub(super) type Name = (Vec<u8>, Attrs);
+
+// A response, without the length it was framed by
+pub(super) struct Reply {
+	pub(super) kind: u8,
+	pub(super) id:   u32,
+	buf:             Vec<u8>,
+	pos:             usize,
+}
+
+impl Reply {
+	pub(super) fn parse(buf: Vec<u8>) -> Result<Self> {
+		if buf.len() < 5 {
+			bail!("a packet too short");
+		}
+		let id = u32::from_be_bytes(buf[1..5].try_into()?);
+		Ok(Self { kind: buf[0], id, buf, pos: 5 })
+	}
+
+	fn take(&mut self, n: usize) -> Result<&[u8]> {
+		let Some(b) = self.buf.get(self.pos..self.pos + n) else {
+			bail!("a packet cut short");
+		};
+		self.pos += n;
+		Ok(b)
+	}
+
+	#[inline]
+	pub(super) fn u32(&mut self) -> Result<u32> { Ok(u32::from_be_bytes(self.take(4)?.try_into()?)) }
+
+	#[inline]
+	pub(super) fn u64(&mut self) -> Result<u64> { Ok(u64::from_be_bytes(self.take(8)?.try_into()?)) }
+
+	pub(super) fn bytes(&mut self) -> Result<Vec<u8>> {
+		let n = self.u32()? as usize;
+		Ok(self.take(n)?.to_vec())
+	}
+
+	pub(super) fn attrs(&mut self) -> Result<Attrs> {
+		let flags = self.u32()?;
+		let mut attrs = Attrs::default();
+		if flags & ATTR_SIZE != 0 {
+			attrs.size = Some(self.u64()?);
+		}
+		if flags & ATTR_UIDGID != 0 {
+			attrs.owner = Some((self.u32()?, self.u32()?));
+		}
+		if flags & ATTR_PERMISSIONS != 0 {
+			attrs.perms = Some(self.u32()?);
+		}
+		if flags & ATTR_ACMODTIME != 0 {
+			attrs.times = Some((self.u32()?, self.u32()?));
+		}
+		if flags & ATTR_EXTENDED != 0 {
+			for _ in 0..self.u32()? {
+				self.bytes()?;
+				self.bytes()?;
+			}
+		}
+		Ok(attrs)
+	}
+
+	// The status of a request that gets nothing else back, an error unless it's done
+	pub(super) fn status(mut self) -> Result<()> {
+		if self.kind != FXP_STATUS {
+			bail!("an unexpected packet of type {}", self.kind);
+		}
+		match self.u32()? {
+			0 => Ok(()),
+			code => Err(Status { code, message: String::from_utf8_lossy(&self.bytes()?).into() })?,
+		}
+	}
+
+	// The status is taken for an error, where the end of a file or a directory is none
+	fn expect(self, kind: u8) -> Result<Option<Self>> {
+		if self.kind == kind {
+			return Ok(Some(self));
+		}
+		match self.status() {
+			Err(e) if e.downcast_ref::<Status>().is_some_and(|s| s.code == FX_EOF) => Ok(None),
+			Err(e) => Err(e),
+			Ok(()) => bail!("an unexpected status of success"),
+		}
+	}
+
+	pub(super) fn version(self) -> Result<u32> {
+		if self.kind != FXP_VERSION {
+			bail!("an unexpected packet of type {} to start with", self.kind);
+		}
+		Ok(self.id)
+	}
+
+	pub(super) fn handle(self) -> Result<Vec<u8>> {
+		match self.expect(FXP_HANDLE)? {
+			Some(mut r) => r.bytes(),
+			None => bail!("an unexpected end of file"),
+		}
+	}
+
+	// The data read, none at the end of the file
+	pub(super) fn data(self) -> Result<Option<Vec<u8>>> {
+		self.expect(FXP_DATA)?.map(|mut r| r.bytes()).transpose()
+	}
+
+	pub(super) fn attrs_of(self) -> Result<Attrs> {
+		match self.expect(FXP_ATTRS)? {
+			Some(mut r) => r.attrs(),
+			None => bail!("an unexpected end of file"),
+		}
+	}
+
+	// The names with their attributes, none at the end of a directory
+	pub(super) fn names(self) -> Result<Option<Vec<Name>>> {
+		let Some(mut r) = self.expect(FXP_NAME)? else {
+			return Ok(None);
+		};
+
+		let n = r.u32()?;
+		let mut names = Vec::with_capacity(n.min(1024) as usize);
+		for _ in 0..n {
+			let name = r.bytes()?;
+			r.bytes()?; // The long name, as `ls -l` has it
+			names.push((name, r.attrs()?));
+		}
+		Ok(Some(names))
+	}
+
+	// The one name of `FXP_REALPATH` or `FXP_READLINK`
+	pub(super) fn path(self) -> Result<PathBuf> {
+		match self.names()?.and_then(|n| n.into_iter().next()) {
+			Some((name, _)) => Ok(bytes_path(name)),
+			None => bail!("no path in the response"),
+		}
+	}
+}
+
+#[cfg(unix)]
+pub(super) fn bytes_path(b: Vec<u8>) -> PathBuf {
+	use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+	PathBuf::from(OsString::from_vec(b))
+}
+
+#[cfg(not(unix))]
+pub(super) fn bytes_path(b: Vec<u8>) -> PathBuf { PathBuf::from(String::from_utf8_lossy(&b).as_ref()) }
+
+// The attributes of a file, the ones the server tells or is asked to set
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(super) struct Attrs {
+	pub(super) size:  Option<u64>,
+	pub(super) owner: Option<(u32, u32)>,
+	pub(super) perms: Option<u32>,
+	pub(super) times: Option<(u32, u32)>,
+}
+
+impl Attrs {
+	#[inline]
+	pub(super) fn meta(&self) -> Meta {
+		Meta::remote(self.perms.unwrap_or(0), self.size.unwrap_or(0), self.owner, self.times)
+	}
+}
+
+// A request that failed on the server, with the code and the message of it
+#[derive(Debug)]
+pub struct Status {
+	code:    u32,
+	message: String,
+}
+
+impl Status {
+	#[inline]
+	pub fn is_not_found(&self) -> bool { self.code == FX_NO_SUCH_FILE }
+}
+
+impl Display for Status {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if !self.message.is_empty() {
+			return f.write_str(&self.message);
+		}
+		f.write_str(match self.code {
+			FX_EOF => "End of file",
+			FX_NO_SUCH_FILE => "No such file",
+			3 => "Permission denied",
+			5 => "Bad message",
+			6 => "No connection",
+			7 => "Connection lost",
+			8 => "Operation unsupported",
+			_ => "Failure",
+		})
+	}
+}
+
+impl std::error::Error for Status {}
+
+#[test]
+fn test_packet() {
+	let attrs = Attrs { size: Some(5), owner: None, perms: Some(0o100644), times: Some((1, 2)) };
+	let buf = Packet::new(FXP_OPEN, 7).path(Path::new("/a")).u32(FXF_READ).attrs(&attrs).finish();
+	assert_eq!(&buf[..9], [0, 0, 0, 39, FXP_OPEN, 0, 0, 0, 7]);
+	assert_eq!(buf.len(), 4 + 39);
+
+	// Read back as the attributes of a response
+	let mut reply = Reply::parse([&[FXP_ATTRS, 0, 0, 0, 7][..], &buf[19..]].concat()).unwrap();
+	assert_eq!((reply.kind, reply.id), (FXP_ATTRS, 7));
+	assert_eq!(reply.attrs().unwrap(), attrs);
+	assert!(reply.take(1).is_err());
+
+	let name = |name: &[u8]| Packet::new(FXP_NAME, 1).u32(1).bytes(name).bytes(b"").u32(0);
+	let reply = Reply::parse(name(b"/home/me").finish()[4..].to_vec()).unwrap();
+	assert_eq!(reply.path().unwrap(), Path::new("/home/me"));
+
+	let status = |code, msg: &[u8]| Packet::new(FXP_STATUS, 1).u32(code).bytes(msg).bytes(b"");
+	let reply = |p: Packet| Reply::parse(p.finish()[4..].to_vec()).unwrap();
+	assert!(reply(status(0, b"")).status().is_ok());
+	assert!(reply(status(FX_EOF, b"")).data().unwrap().is_none());
+	let e = reply(status(FX_NO_SUCH_FILE, b"")).attrs_of().unwrap_err();
+	assert!(e.downcast_ref::<Status>().unwrap().is_not_found());
+	assert_eq!(e.to_string(), "No such file");
+	let e = reply(status(3, b"Permission denied by the server")).status().unwrap_err();
+	assert_eq!(e.to_string(), "Permission denied by the server");
+}
@@ -0,0 +1,241 @@
+use std::{collections::{BTreeMap, HashMap}, process::Stdio, sync::{atomic::{AtomicU32, Ordering}, Arc}, time::Duration};
+
+use anyhow::{anyhow, bail, Result};
+use parking_lot::Mutex;
+use tokio::{io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt}, process::{Child, Command}, sync::{mpsc, oneshot, OnceCell}, task::JoinHandle, time::timeout};
+use tracing::warn;
+
+use super::{Packet, Reply, FXP_INIT};
+use crate::emit;
+
+// The longest a packet can be, far more than the data of a read asked for
+const PACKET_MAX: usize = 4 * 1024 * 1024;
+
+// One connection to each host, shared by all the files on it, and made again once it's lost, where
+// the lock is held only to look one up, for a host slow to connect to not to hold up the others
+static SESSIONS: Mutex<BTreeMap<String, Arc<OnceCell<Arc<Session>>>>> =
+	parking_lot::const_mutex(BTreeMap::new());
+
+// A connection to the SFTP server on a host, through `ssh` run with the `sftp` subsystem, where
+// the requests are sent as they come, and their responses are handed back by their ids
+pub(super) struct Session {
+	tx:      mpsc::UnboundedSender<Vec<u8>>,
+	waiting: Arc<Mutex<HashMap<u32, oneshot::Sender<Reply>>>>,
+	next:    AtomicU32,
+	// Why the connection is closed, none while it's open
+	closed:  Arc<Mutex<Option<String>>>,
+
+	_child: Option<Child>,
+}
+
+// A request sent, whose response is waited for later, for several of them to be on the way
+pub(super) struct Pending {
+	rx:     oneshot::Receiver<Reply>,
+	closed: Arc<Mutex<Option<String>>>,
+}
+
+impl Pending {
+	pub(super) async fn wait(self) -> Result<Reply> {
+		match self.rx.await {
+			Ok(reply) => Ok(reply),
+			Err(_) => Err(anyhow!(self.closed.lock().clone().unwrap_or_default())),
+		}
+	}
+}
+
+// The session to `host`, the one open already, or a new one, where the failure to connect, such as
+// of a host key not known or of no key accepted, is told as the error of `ssh` it was
+//
+// The ones asking for it while it's being connected wait for that same connection, and one of them
+// tries again if it fails
+pub(super) async fn session(host: &str) -> Result<Arc<Session>> {
+	let cell = {
+		let mut sessions = SESSIONS.lock();
+		let cell = sessions.entry(host.to_owned()).or_default();
+		if cell.get().is_some_and(|s| s.closed.lock().is_some()) {
+			*cell = Default::default();
+		}
+		cell.clone()
+	};
+
+	let connect = || async { Session::connect(host).await.map(Arc::new) };
+	match cell.get_or_try_init(connect).await {
+		Ok(s) => Ok(s.clone()),
+		Err(e) => {
+			emit!(Message(e.to_string(), true));
+			Err(e)
+		}
+	}
+}
+
+// Whether there's a session to `host` open, without waiting for one being connected
+pub(super) fn is_open(host: &str) -> bool {
+	let sessions = SESSIONS.lock();
+	sessions.get(host).and_then(|c| c.get()).is_some_and(|s| s.closed.lock().is_none())
+}
+
+// Takes `session` for the one to `host`, of the fake server of the tests
+#[cfg(test)]
+pub(super) fn insert(host: &str, session: Session) {
+	let cell = OnceCell::new_with(Some(Arc::new(session)));
+	SESSIONS.lock().insert(host.to_owned(), Arc::new(cell));
+}
+
+impl Session {
+	async fn connect(host: &str) -> Result<Self> {
+		// `[user@]host[:port]`, where an IPv6 address is in brackets if it's followed by a port
+		let (dest, port) = match host.rsplit_once(':') {
+			Some((h, p)) if p.parse::<u16>().is_ok() && (!h.contains(':') || h.ends_with(']')) => {
+				(h.replace(['[', ']'], ""), Some(p))
+			}
+			_ => (host.to_owned(), None),
+		};
+
+		// Never prompted for, with the screen taken by the UI, so the keys or the agent are used
+		let mut child = Command::new("ssh")
+			.args(["-o", "BatchMode=yes", "-o", "ConnectTimeout=10", "-o", "ServerAliveInterval=15"])
+			.args(["-o", "ForwardX11=no", "-o", "ClearAllForwardings=yes"])
+			.args(port.map(|p| ["-p", p]).into_iter().flatten())
+			.args(["-s", "--", &dest, "sftp"])
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.kill_on_drop(true)
+			.spawn()
+			.map_err(|e| anyhow!("Can't connect to {host}: failed to run `ssh`, {e}"))?;
+
+		let (stdin, stdout) = (child.stdin.take().unwrap(), child.stdout.take().unwrap());
+		let mut stderr = child.stderr.take().unwrap();
+		let errors = tokio::spawn(async move {
+			let mut s = String::new();
+			stderr.read_to_string(&mut s).await.ok();
+			s
+		});
+		Self::start(host, stdin, stdout, errors, Some(child)).await
+	}
+
+	// The session over the pipe to the server, `ssh` or the fake one of the tests, with what's told
+	// of the errors of it once it's closed
+	pub(super) async fn start(
+		host: &str,
+		mut stdin: impl AsyncWrite + Unpin + Send + 'static,
+		mut stdout: impl AsyncRead + Unpin + Send + 'static,
+		errors: JoinHandle<String>,
+		child: Option<Child>,
+	) -> Result<Self> {
+		let handshake = async {
+			stdin.write_all(&Packet::new(FXP_INIT, 3).finish()).await?;
+			stdin.flush().await?;
+			Reply::parse(read_packet(&mut stdout).await?)?.version()
+		};
+		let version = match timeout(Duration::from_secs(30), handshake).await {
+			Ok(Ok(v)) => v,
+			Ok(Err(_)) => {
+				let errors = timeout(Duration::from_secs(1), errors).await.ok().and_then(|r| r.ok());
+				bail!("Can't connect to {host}: {}", last_line(errors.as_deref()));
+			}
+			Err(_) => bail!("Can't connect to {host}: timed out"),
+		};
+		if version < 3 {
+			bail!("Can't connect to {host}: version {version} of SFTP isn't supported");
+		}
+
+		let (tx, mut rx) = mpsc::unbounded_channel::<Vec<u8>>();
+		tokio::spawn(async move {
+			while let Some(buf) = rx.recv().await {
+				if stdin.write_all(&buf).await.is_err() || stdin.flush().await.is_err() {
+					break;
+				}
+			}
+		});
+
+		let (waiting, closed) = (Arc::new(Mutex::new(HashMap::new())), Arc::new(Mutex::new(None)));
+		tokio::spawn({
+			let (waiting, closed, host) = (waiting.clone(), closed.clone(), host.to_owned());
+			async move {
+				while let Ok(buf) = read_packet(&mut stdout).await {
+					match Reply::parse(buf) {
+						Ok(reply) => {
+							let tx: Option<oneshot::Sender<_>> = waiting.lock().remove(&reply.id);
+							tx.map(|tx| tx.send(reply).ok());
+						}
+						Err(e) => warn!("sftp: {host}: {e}"),
+					}
+				}
+
+				// Closed first, for a request sent from now on not to wait for a response forever
+				let errors = timeout(Duration::from_secs(1), errors).await.ok().and_then(|r| r.ok());
+				let why = format!("Connection to {host} lost: {}", last_line(errors.as_deref()));
+				*closed.lock() = Some(why);
+				waiting.lock().clear();
+			}
+		});
+
+		Ok(Self { tx, waiting, next: AtomicU32::new(1), closed, _child: child })
+	}
+
+	// Sends the request made of its id, whose response is waited for by what's returned
+	pub(super) fn send(&self, packet: impl FnOnce(u32) -> Packet) -> Result<Pending> {
+		let id = self.next.fetch_add(1, Ordering::Relaxed);
+		let (tx, rx) = oneshot::channel();
+		self.waiting.lock().insert(id, tx);
+
+		if let Some(why) = self.closed.lock().clone() {
+			self.waiting.lock().remove(&id);
+			bail!(why);
+		}
+		self.tx.send(packet(id).finish()).ok();
+		Ok(Pending { rx, closed: self.closed.clone() })
+	}
+
+	#[inline]
+	pub(super) async fn request(&self, packet: impl FnOnce(u32) -> Packet) -> Result<Reply> {
+		self.send(packet)?.wait().await
+	}
+}
+
+pub(super) async fn read_packet(r: &mut (impl AsyncRead + Unpin)) -> Result<Vec<u8>> {
+	let len = r.read_u32().await? as usize;
+	if len > PACKET_MAX {
+		bail!("a packet too long, of {len} bytes");
+	}
+
+	let mut buf = vec![0; len];
+	r.read_exact(&mut buf).await?;
+	Ok(buf)
+}
+
+// The last line `ssh` wrote to the standard error, the one telling why it failed
+fn last_line(errors: Option<&str>) -> &str {
+	let line = errors.and_then(|s| s.lines().rev().map(str::trim).find(|l| !l.is_empty()));
+	line.unwrap_or("the connection is closed")
+}
+
+#[test]
+fn test_last_line() {
+	let errors = "Warning: Permanently added 'a'.\r\nme@a: Permission denied (publickey).\r\n\n";
+	assert_eq!(last_line(Some(errors)), "me@a: Permission denied (publickey).");
+	assert_eq!(last_line(Some("")), "the connection is closed");
+	assert_eq!(last_line(None), "the connection is closed");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_session() {
+	shared::block_on(async {
+		let server = super::Fake { read: usize::MAX, names: 100 }.serve("session").await;
+		let (a, b) = (session("session").await.unwrap(), session("session").await.unwrap());
+		assert!(Arc::ptr_eq(&a, &b));
+		assert!(is_open("session"));
+		assert!(!is_open("session-none"));
+
+		// Lost with the server, telling so to the requests from then on
+		server.abort();
+		while is_open("session") {
+			tokio::task::yield_now().await;
+		}
+		let realpath = a.request(|id| Packet::new(super::FXP_REALPATH, id).bytes(b"/")).await;
+		let e = realpath.map(|_| ()).unwrap_err();
+		assert!(e.to_string().starts_with("Connection to session lost"), "{e}");
+	});
+}
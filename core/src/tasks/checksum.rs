@@ -0,0 +1,76 @@
+use std::{fs::File, io::{self, Read}, path::{Path, PathBuf}};
+
+use anyhow::bail;
+use sha2::{Digest, Sha256};
+use tokio::task;
+use xxhash_rust::xxh3::Xxh3;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Checksum {
+	#[default]
+	Xxhash,
+	Sha256,
+}
+
+impl TryFrom<String> for Checksum {
+	type Error = anyhow::Error;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		Ok(match s.as_str() {
+			"" | "xxhash" => Self::Xxhash,
+			"sha256" => Self::Sha256,
+			_ => bail!("invalid checksum value: {s}"),
+		})
+	}
+}
+
+impl Checksum {
+	// Returns the digest of the file, and the number of bytes hashed
+	pub(super) async fn digest(self, path: &Path) -> io::Result<(Vec<u8>, u64)> {
+		let path = path.to_path_buf();
+		task::spawn_blocking(move || self.digest_sync(path)).await?
+	}
+
+	fn digest_sync(self, path: PathBuf) -> io::Result<(Vec<u8>, u64)> {
+		let mut file = File::open(path)?;
+		let mut buf = vec![0; 64 * 1024];
+		let (mut xxh3, mut sha256) = (Xxh3::new(), Sha256::new());
+
+		let mut total = 0;
+		loop {
+			let n = file.read(&mut buf)?;
+			if n == 0 {
+				break;
+			}
+
+			match self {
+				Self::Xxhash => xxh3.update(&buf[..n]),
+				Self::Sha256 => sha256.update(&buf[..n]),
+			}
+			total += n as u64;
+		}
+
+		Ok(match self {
+			Self::Xxhash => (xxh3.digest128().to_be_bytes().to_vec(), total),
+			Self::Sha256 => (sha256.finalize().to_vec(), total),
+		})
+	}
+}
+
+#[test]
+fn test_checksum() {
+	let dir = shared::TestDir::new("checksum");
+	std::fs::write(dir.join("a"), "abc").unwrap();
+	std::fs::write(dir.join("b"), "abc").unwrap();
+	std::fs::write(dir.join("c"), "abd").unwrap();
+
+	for kind in [Checksum::Xxhash, Checksum::Sha256] {
+		let (a, len) = kind.digest_sync(dir.join("a")).unwrap();
+		assert_eq!(len, 3);
+		assert_eq!(a, kind.digest_sync(dir.join("b")).unwrap().0);
+		assert_ne!(a, kind.digest_sync(dir.join("c")).unwrap().0);
+	}
+
+	let (sha256, _) = Checksum::Sha256.digest_sync(dir.join("a")).unwrap();
+	assert!(sha256.starts_with(&[0xba, 0x78, 0x16, 0xbf]));
+}
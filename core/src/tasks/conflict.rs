@@ -0,0 +1,197 @@
+use std::{collections::HashSet, time::SystemTime};
+
+use config::tasks::ConflictPolicy;
+use shared::{numbered_name, unique_path, Url};
+use tokio::sync::Mutex;
+
+use crate::{emit, files::Meta, select::SelectOpt, sftp};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Resolution {
+	Overwrite,
+	Skip,
+	Rename,
+	Number,
+	Newer,
+	Older,
+}
+
+// Pastes running at the same time take turns to ask
+static ASKING: Mutex<()> = Mutex::const_new(());
+
+const RESOLUTIONS: [(Resolution, &str); 6] = [
+	(Resolution::Overwrite, "Overwrite"),
+	(Resolution::Skip, "Skip"),
+	(Resolution::Rename, "Rename"),
+	(Resolution::Number, "Keep both, numbered"),
+	(Resolution::Newer, "Overwrite if newer"),
+	(Resolution::Older, "Overwrite if older"),
+];
+
+pub(super) struct Conflicts {
+	policy: ConflictPolicy,
+	all:    Option<Resolution>,
+}
+
+impl Conflicts {
+	#[inline]
+	pub(super) fn new(policy: ConflictPolicy) -> Self { Self { policy, all: None } }
+
+	// Returns the destination to paste `from` to, or `None` to skip it
+	pub(super) async fn resolve(&mut self, from: &Url, to: Url) -> Option<Url> {
+		if Meta::read(&to, false).await.is_err() {
+			return Some(to);
+		}
+
+		let name = to.file_name().unwrap_or_default().to_string_lossy();
+		match self.resolution(&format!("`{name}` already exists:")).await {
+			Resolution::Overwrite => (from != &to).then_some(to),
+			Resolution::Skip => None,
+			Resolution::Rename if to.is_sftp() => Some(sftp::unique(to).await),
+			Resolution::Rename => Some(unique_path(to).await),
+			Resolution::Number => Some(numbered(from, to, &HashSet::new()).await),
+			Resolution::Newer => is_newer(from, &to).await.then_some(to),
+			Resolution::Older => is_newer(&to, from).await.then_some(to),
+		}
+	}
+
+	// Which of the two sources of the same name is pasted to `to`, where `other` is the one going
+	// there so far, and `taken` are the destinations of the others: `to` for `from` to take the
+	// place of `other`, skipped then, another name for `from` not taken by any file or any of the
+	// others, or `None` to skip `from`
+	pub(super) async fn resolve_sources(
+		&mut self,
+		from: &Url,
+		other: &Url,
+		to: Url,
+		taken: &HashSet<Url>,
+	) -> Option<Url> {
+		let name = to.file_name().unwrap_or_default().to_string_lossy();
+		match self.resolution(&format!("`{name}` is pasted from more than one place:")).await {
+			Resolution::Overwrite => Some(to),
+			Resolution::Skip => None,
+			Resolution::Rename => Some(unique_among(to, taken).await),
+			Resolution::Number => Some(numbered(from, to, taken).await),
+			Resolution::Newer => is_newer(from, other).await.then_some(to),
+			Resolution::Older => is_newer(other, from).await.then_some(to),
+		}
+	}
+
+	async fn resolution(&mut self, title: &str) -> Resolution {
+		match (self.all, self.policy) {
+			(Some(r), _) => r,
+			(None, ConflictPolicy::Overwrite) => Resolution::Overwrite,
+			(None, ConflictPolicy::Skip) => Resolution::Skip,
+			(None, ConflictPolicy::Rename) => Resolution::Rename,
+			(None, ConflictPolicy::Number) => Resolution::Number,
+			(None, ConflictPolicy::Ask) => self.ask(title).await,
+		}
+	}
+
+	async fn ask(&mut self, title: &str) -> Resolution {
+		let _guard = ASKING.lock().await;
+		let items = RESOLUTIONS
+			.iter()
+			.map(|(_, s)| s.to_string())
+			.chain(RESOLUTIONS.iter().map(|(_, s)| format!("{s} (apply to all)")))
+			.collect();
+
+		let result = emit!(Select(SelectOpt::hovered(title, items)));
+		let Ok(choice) = result.await else {
+			// Canceling skips the rest of the paste as well
+			self.all = Some(Resolution::Skip);
+			return Resolution::Skip;
+		};
+
+		let resolution = RESOLUTIONS[choice % RESOLUTIONS.len()].0;
+		if choice >= RESOLUTIONS.len() {
+			self.all = Some(resolution);
+		}
+		resolution
+	}
+}
+
+// `to` with the first number after its name that's neither a file, nor in `taken`
+pub(super) async fn unique_among(to: Url, taken: &HashSet<Url>) -> Url {
+	let Some(name) = to.file_name().map(|n| n.to_os_string()) else {
+		return to;
+	};
+
+	let mut url = to.clone();
+	for i in 1.. {
+		let mut name = name.clone();
+		name.push(format!("_{i}"));
+		url.set_file_name(name);
+		if !taken.contains(&url) && Meta::read(&url, false).await.is_err() {
+			break;
+		}
+	}
+	url
+}
+
+// `to` with the first number before its extension, as `name (1).ext`, that's neither a file, nor
+// in `taken`, where that of a directory is at the end of its name
+pub(super) async fn numbered(from: &Url, to: Url, taken: &HashSet<Url>) -> Url {
+	let Some(name) = to.file_name().map(|n| n.to_os_string()) else {
+		return to;
+	};
+
+	let is_dir = Meta::read(from, false).await.is_ok_and(|m| m.is_dir());
+	let mut url = to;
+	for i in 1.. {
+		url.set_file_name(numbered_name(&name, is_dir, i));
+		if !taken.contains(&url) && Meta::read(&url, false).await.is_err() {
+			break;
+		}
+	}
+	url
+}
+
+// Whether `a` was modified later than `b`, false if either of them cannot be read
+pub(super) async fn is_newer(a: &Url, b: &Url) -> bool {
+	async fn modified(u: &Url) -> Option<SystemTime> { Meta::read(u, true).await.ok()?.modified() }
+
+	match (modified(a).await, modified(b).await) {
+		(Some(a), Some(b)) => a > b,
+		_ => false,
+	}
+}
+
+#[test]
+fn test_conflicts() {
+	let dir = shared::TestDir::new("conflict");
+	std::fs::write(dir.join("a"), "a").unwrap();
+	std::fs::write(dir.join("b"), "b").unwrap();
+
+	let (a, b, c) = (Url::from(dir.join("a")), Url::from(dir.join("b")), Url::from(dir.join("c")));
+	shared::block_on(async {
+		let mut overwrite = Conflicts::new(ConflictPolicy::Overwrite);
+		assert_eq!(overwrite.resolve(&a, c.clone()).await, Some(c.clone()));
+		assert_eq!(overwrite.resolve(&a, b.clone()).await, Some(b.clone()));
+		assert_eq!(overwrite.resolve(&a, a.clone()).await, None);
+
+		let mut skip = Conflicts::new(ConflictPolicy::Skip);
+		assert_eq!(skip.resolve(&a, b.clone()).await, None);
+
+		let mut rename = Conflicts::new(ConflictPolicy::Rename);
+		assert_eq!(rename.resolve(&a, b.clone()).await, Some(Url::from(dir.join("b_1"))));
+
+		// One after another, each the first number not taken by a file, or another source
+		std::fs::write(dir.join("b.txt"), "").unwrap();
+		std::fs::write(dir.join("b (1).txt"), "").unwrap();
+		let (txt, to) = (Url::from(dir.join("b.txt")), Url::from(dir.join("b (2).txt")));
+		let mut number = Conflicts::new(ConflictPolicy::Number);
+		assert_eq!(number.resolve(&a, txt.clone()).await, Some(to.clone()));
+		let taken = HashSet::from([to]);
+		let to = number.resolve_sources(&a, &b, txt, &taken).await;
+		assert_eq!(to, Some(Url::from(dir.join("b (3).txt"))));
+
+		// A directory is numbered at the end of its name
+		std::fs::create_dir_all(dir.join("d.1")).unwrap();
+		let d = Url::from(dir.join("d.1"));
+		assert_eq!(number.resolve(&d, d.clone()).await, Some(Url::from(dir.join("d.1 (1)"))));
+
+		let mut all = Conflicts { policy: ConflictPolicy::Ask, all: Some(Resolution::Newer) };
+		assert_eq!(all.resolve(&a, a.clone()).await, None);
+	});
+}
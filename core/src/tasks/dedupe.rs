@@ -0,0 +1,137 @@
+use std::{collections::{BTreeMap, HashSet}, fs, io::{self, Read}, path::{Path, PathBuf}};
+
+use shared::Switch;
+
+// The files under `root` of the same size as another one, the sizes the duplicates are found
+// among, where the empty files and the symlinks are left out, and hardlinks of one file are taken
+// as one, the first of them found
+pub(super) fn dedupe_sizes(root: &Path, hidden: bool, cancel: &Switch) -> Vec<(u64, Vec<PathBuf>)> {
+	let mut sizes: BTreeMap<u64, Vec<PathBuf>> = BTreeMap::new();
+	let mut inodes = HashSet::new();
+
+	let mut dirs = vec![root.to_path_buf()];
+	while let Some(dir) = dirs.pop() {
+		if cancel.get() {
+			return vec![];
+		}
+		let Ok(it) = fs::read_dir(&dir) else {
+			continue;
+		};
+
+		for entry in it.flatten() {
+			if !hidden && entry.file_name().to_string_lossy().starts_with('.') {
+				continue;
+			}
+			let Ok(meta) = entry.metadata() else {
+				continue;
+			};
+
+			if meta.is_dir() {
+				dirs.push(entry.path());
+			} else if meta.is_file() && meta.len() > 0 && inodes.insert(inode(&meta, &entry.path())) {
+				sizes.entry(meta.len()).or_default().push(entry.path());
+			}
+		}
+	}
+
+	sizes.into_iter().filter(|(_, files)| files.len() > 1).collect()
+}
+
+#[cfg(unix)]
+#[inline]
+fn inode(meta: &fs::Metadata, _: &Path) -> (u64, u64) {
+	use std::os::unix::fs::MetadataExt;
+	(meta.dev(), meta.ino())
+}
+
+// Without the inodes at hand, every file is one of its own
+#[cfg(not(unix))]
+#[inline]
+fn inode(_: &fs::Metadata, path: &Path) -> PathBuf { path.to_path_buf() }
+
+// Splits the files of the same hash into the sets of the same content, compared byte by byte,
+// so that a collision of the hash never has different files taken as duplicates
+pub(super) fn dedupe_split(files: Vec<PathBuf>) -> Vec<Vec<PathBuf>> {
+	let mut sets: Vec<Vec<PathBuf>> = vec![];
+	'files: for file in files {
+		for set in &mut sets {
+			if same_content(&set[0], &file).unwrap_or(false) {
+				set.push(file);
+				continue 'files;
+			}
+		}
+		sets.push(vec![file]);
+	}
+
+	sets.retain(|s| s.len() > 1);
+	sets
+}
+
+fn same_content(a: &Path, b: &Path) -> io::Result<bool> {
+	let (mut a, mut b) = (fs::File::open(a)?, fs::File::open(b)?);
+	let (mut buf_a, mut buf_b) = (vec![0; 64 * 1024], vec![0; 64 * 1024]);
+	loop {
+		let (n, m) = (fill(&mut a, &mut buf_a)?, fill(&mut b, &mut buf_b)?);
+		if buf_a[..n] != buf_b[..m] {
+			return Ok(false);
+		} else if n == 0 {
+			return Ok(true);
+		}
+	}
+}
+
+// Reads into `buf` until it's full or the file ends, as a read may stop short of either
+fn fill(file: &mut fs::File, buf: &mut [u8]) -> io::Result<usize> {
+	let mut n = 0;
+	while n < buf.len() {
+		match file.read(&mut buf[n..])? {
+			0 => break,
+			m => n += m,
+		}
+	}
+	Ok(n)
+}
+
+#[test]
+fn test_dedupe() {
+	let dir = shared::TestDir::new("dedupe");
+	std::fs::create_dir_all(dir.join("sub")).unwrap();
+	for (name, content) in [
+		("a", "same"),
+		("sub/b", "same"),
+		("c", "diff"),
+		("d", "longer"),
+		(".e", "same"),
+		("empty1", ""),
+		("empty2", ""),
+	] {
+		std::fs::write(dir.join(name), content).unwrap();
+	}
+	#[cfg(unix)]
+	std::fs::hard_link(dir.join("a"), dir.join("sub/a-link")).unwrap();
+
+	let cancel = Switch::default();
+	let names = |files: &[PathBuf]| {
+		let mut names: Vec<_> =
+			files.iter().map(|f| f.strip_prefix(&dir).unwrap().to_string_lossy().into_owned()).collect();
+		names.sort();
+		names
+	};
+
+	// Of the same size, without the hidden, the empty, and more than one hardlink of a file
+	let sizes = dedupe_sizes(&dir, false, &cancel);
+	assert_eq!(sizes.len(), 1);
+	assert_eq!(sizes[0].0, 4);
+	let files = &sizes[0].1;
+	assert_eq!(files.len(), 3);
+	assert!(names(files).contains(&"c".to_owned()));
+	assert_eq!(dedupe_sizes(&dir, true, &cancel)[0].1.len(), 4);
+
+	let sets = dedupe_split(files.clone());
+	assert_eq!(sets.len(), 1);
+	assert!(!names(&sets[0]).contains(&"c".to_owned()));
+	assert_eq!(sets[0].len(), 2);
+
+	assert!(same_content(&dir.join("d"), &dir.join("d")).unwrap());
+	assert!(!same_content(&dir.join("a"), &dir.join("d")).unwrap());
+}
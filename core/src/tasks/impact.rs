@@ -0,0 +1,212 @@
+use std::{env, path::{Path, PathBuf}};
+
+use config::TASKS;
+use shared::{calculate_size_with, disk_space, readable_path, readable_size, Url};
+use tokio::fs;
+
+use crate::{emit, input::InputOpt};
+
+const SAMPLES: usize = 3;
+
+// The limits past which an operation is confirmed first, see `confirm_*` in the `[tasks]` config
+pub(super) struct Thresholds {
+	count: usize,
+	size:  u64,
+	home:  Option<PathBuf>,
+}
+
+impl Thresholds {
+	pub(super) fn new() -> Self {
+		Self {
+			count: TASKS.confirm_count,
+			size:  TASKS.confirm_size,
+			home:  env::var_os("HOME").filter(|_| TASKS.confirm_outside_home).map(PathBuf::from),
+		}
+	}
+
+	#[inline]
+	pub(super) fn outside(&self, p: &Path) -> bool {
+		self.home.as_ref().is_some_and(|h| !p.starts_with(h))
+	}
+}
+
+// How much an operation on the files touches
+#[derive(Debug, Default)]
+pub(super) struct Impact {
+	count:       usize,
+	bytes:       u64,
+	// The size stopped being summed once it was past the threshold
+	more:        bool,
+	pub outside: bool,
+	samples:     Vec<String>,
+}
+
+impl Impact {
+	pub(super) async fn measure<'a>(
+		targets: impl IntoIterator<Item = &'a Url>,
+		th: &Thresholds,
+	) -> Self {
+		let mut impact = Self::default();
+		for target in targets {
+			impact.count += 1;
+			impact.outside |= !target.is_sftp() && th.outside(target);
+			if impact.samples.len() < SAMPLES {
+				impact.samples.push(readable_path(target));
+			}
+
+			// Walking a remote one over the connection would take a while
+			if th.size == 0 || impact.more || target.is_sftp() {
+				continue;
+			}
+			let base = impact.bytes;
+			let (bytes, walked) =
+				calculate_size_with(target, true, false, usize::MAX, |n| base + n <= th.size).await;
+			impact.bytes += bytes;
+			impact.more = walked.stopped();
+		}
+		impact
+	}
+
+	pub(super) fn exceeds(&self, th: &Thresholds) -> bool {
+		(th.count > 0 && self.count > th.count) || (th.size > 0 && self.bytes > th.size) || self.outside
+	}
+
+	// e.g. "500 files, over 1.0 GB, outside home, ~/a, ~/b, ~/c, …"
+	pub(super) fn summary(&self) -> String {
+		let mut s = format!("{} file{}", self.count, if self.count == 1 { "" } else { "s" });
+		if self.bytes > 0 {
+			let over = if self.more { "over " } else { "" };
+			s.push_str(&format!(", {over}{}", readable_size(self.bytes)));
+		}
+		if self.outside {
+			s.push_str(", outside home");
+		}
+		for sample in &self.samples {
+			s.push_str(", ");
+			s.push_str(sample);
+		}
+		if self.count > self.samples.len() {
+			s.push_str(", …");
+		}
+		s
+	}
+
+	#[inline]
+	pub(super) async fn confirm(&self, action: &str) -> bool {
+		ask(format!("{action}: {}? (y/N)", self.summary())).await
+	}
+
+	// Whether deleting the files permanently is to be confirmed by typing, see `confirm_typed`
+	#[inline]
+	pub(super) fn typed(&self) -> bool {
+		TASKS.confirm_typed && self.count > TASKS.confirm_typed_count
+	}
+
+	// Asks to type the number of the files, or "delete", where anything else does nothing
+	pub(super) async fn confirm_typed(&self) -> bool {
+		let title = format!("Delete permanently: {}? Type {} or `delete`", self.summary(), self.count);
+		let mut result = emit!(Input(InputOpt::hovered(title)));
+		match result.recv().await {
+			Some(Ok(s)) if typed_matches(&s, self.count) => true,
+			Some(Ok(s)) if !s.is_empty() => {
+				emit!(Message(format!("Nothing is deleted, `{s}` doesn't match"), true));
+				false
+			}
+			_ => false,
+		}
+	}
+}
+
+// Whether it's exactly the number of the files, or "delete", that's typed, without any space
+#[inline]
+fn typed_matches(s: &str, count: usize) -> bool { s == "delete" || s == count.to_string() }
+
+// The size of the files to paste into `dest`, and its free space, if they take more than it has.
+// The moves within its filesystem take none of it, and nothing is told if it can't be read
+pub(super) async fn shortfall<'a>(
+	src: impl IntoIterator<Item = &'a Url>,
+	dest: &Url,
+	cut: bool,
+) -> Option<(u64, u64)> {
+	let (free, _) = disk_space(dest)?;
+	let dev = device(dest).await;
+
+	let mut bytes = 0;
+	for from in src {
+		if cut && dev.is_some() && device(from).await == dev {
+			continue;
+		}
+
+		let base = bytes;
+		let (n, walked) =
+			calculate_size_with(from, true, false, usize::MAX, |n| base + n <= free).await;
+		bytes += n;
+		if walked.stopped() {
+			break;
+		}
+	}
+	(bytes > free).then_some((bytes, free))
+}
+
+#[cfg(unix)]
+async fn device(p: &Url) -> Option<u64> {
+	use std::os::unix::fs::MetadataExt;
+	fs::symlink_metadata(p).await.ok().map(|m| m.dev())
+}
+
+#[cfg(not(unix))]
+async fn device(_: &Url) -> Option<u64> { None }
+
+pub(super) async fn ask(title: String) -> bool {
+	let mut result = emit!(Input(InputOpt::hovered(title)));
+	matches!(result.recv().await, Some(Ok(choice)) if choice == "y" || choice == "Y")
+}
+
+#[test]
+fn test_typed_matches() {
+	assert!(typed_matches("delete", 3) && typed_matches("3", 3));
+	for s in ["", "y", "Delete", "delete ", " 3", "03", "4", "3 files"] {
+		assert!(!typed_matches(s, 3), "{s:?}");
+	}
+}
+
+#[test]
+fn test_impact() {
+	let dir = shared::TestDir::new("impact");
+	std::fs::create_dir_all(dir.join("sub")).unwrap();
+	for (name, len) in [("a", 100), ("b", 200), ("sub/c", 300), ("sub/d", 400)] {
+		std::fs::write(dir.join(name), vec![0; len]).unwrap();
+	}
+
+	let targets: Vec<_> = ["a", "b", "sub"].iter().map(|&n| Url::from(dir.join(n))).collect();
+	let th = |count, size, home: &str| Thresholds { count, size, home: Some(home.into()) };
+	shared::block_on(async {
+		// Below all of them
+		let t = th(5, 2000, "/");
+		let impact = Impact::measure(&targets, &t).await;
+		assert_eq!((impact.count, impact.bytes, impact.more, impact.outside), (3, 1000, false, false));
+		assert!(!impact.exceeds(&t));
+
+		// Too many files, too large, and outside home, independently of each other
+		assert!(Impact::measure(&targets, &th(2, 0, "/")).await.exceeds(&th(2, 0, "/")));
+		let t = th(0, 250, "/");
+		let impact = Impact::measure(&targets, &t).await;
+		assert!(impact.more && impact.exceeds(&t));
+		assert!(impact.summary().starts_with("3 files, over "));
+		let t = th(0, 0, "/nonexistent-home");
+		let impact = Impact::measure(&targets, &t).await;
+		assert!(impact.outside && impact.exceeds(&t));
+		assert_eq!(impact.bytes, 0);
+
+		// Plenty of room for them, and none told for a destination that's gone
+		let dest = Url::from(dir.join("sub"));
+		assert_eq!(shortfall(&targets, &dest, false).await, None);
+		assert_eq!(shortfall(&targets, &Url::from(dir.join("gone/x")), false).await, None);
+	});
+
+	let impact = Impact { count: 5, samples: vec!["~/a".to_owned()], ..Default::default() };
+	assert_eq!(impact.summary(), "5 files, ~/a, …");
+	let samples = vec!["~/a".to_owned()];
+	let impact = Impact { count: 1, bytes: 2048, samples, ..Default::default() };
+	assert_eq!(impact.summary(), "1 file, 2.0 KB, ~/a");
+}
@@ -0,0 +1,211 @@
+use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+
+use config::TASKS;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::TaskKind;
+
+// A semaphore whose number of permits can be changed while it's in use
+pub(super) struct Limiter {
+	sem:   Arc<Semaphore>,
+	limit: AtomicUsize,
+	// The permits still to be taken back as they're released, since the limit was lowered
+	owed:  Arc<AtomicUsize>,
+}
+
+impl Limiter {
+	pub(super) fn new(limit: usize) -> Self {
+		Self {
+			sem:   Arc::new(Semaphore::new(limit)),
+			limit: AtomicUsize::new(limit),
+			owed:  Default::default(),
+		}
+	}
+
+	#[inline]
+	pub(super) fn limit(&self) -> usize { self.limit.load(Ordering::Relaxed) }
+
+	pub(super) fn set(&self, limit: usize) {
+		let limit = limit.max(1);
+		let old = self.limit.swap(limit, Ordering::Relaxed);
+		if limit > old {
+			// The ones still owed are let off first, with only the rest of them added
+			let more = limit - old;
+			self.sem.add_permits(more - settle(&self.owed, more));
+		} else if limit < old && self.owed.fetch_add(old - limit, Ordering::Relaxed) == 0 {
+			// The permits in use are taken back once they are released, by the one task doing so
+			// while any are owed, which gives back the ones no longer owed by the time it gets them
+			let (sem, owed) = (self.sem.clone(), self.owed.clone());
+			tokio::spawn(async move {
+				while owed.load(Ordering::Relaxed) > 0 {
+					let Ok(permit) = sem.acquire().await else {
+						break;
+					};
+					if settle(&owed, 1) == 1 {
+						permit.forget();
+					}
+				}
+			});
+		}
+	}
+
+	#[inline]
+	pub(super) async fn acquire(&self) -> OwnedSemaphorePermit {
+		self.sem.clone().acquire_owned().await.unwrap()
+	}
+}
+
+// Takes up to `n` off the permits owed, returning how many of them were
+fn settle(owed: &AtomicUsize, n: usize) -> usize {
+	let old = owed.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |o| Some(o - o.min(n)));
+	old.unwrap().min(n)
+}
+
+// The limits of the work running at the same time, in total and per category.
+// Both are fair, so a category that's waiting for the total one gets its turn in order
+pub(super) struct Limits {
+	pub(super) total:    Limiter,
+	pub(super) file:     Limiter,
+	pub(super) precache: Limiter,
+}
+
+impl Default for Limits {
+	fn default() -> Self {
+		Self {
+			total:    Limiter::new(TASKS.macro_workers as usize),
+			file:     Limiter::new(TASKS.file_workers as usize),
+			precache: Limiter::new(TASKS.precache_workers as usize),
+		}
+	}
+}
+
+impl Limits {
+	#[inline]
+	pub(super) fn get(&self, kind: Option<TaskKind>) -> &Limiter {
+		match kind {
+			Some(TaskKind::File) => &self.file,
+			Some(TaskKind::Precache) => &self.precache,
+			Some(TaskKind::Process) | None => &self.total,
+		}
+	}
+
+	// Waits for the permits of the category first, and then the total one
+	pub(super) async fn admit(&self, kind: TaskKind) -> [OwnedSemaphorePermit; 2] {
+		let category = self.get(Some(kind)).acquire().await;
+		[category, self.total.acquire().await]
+	}
+}
+
+#[test]
+fn test_limits() {
+	use std::time::Duration;
+
+	use parking_lot::Mutex;
+	use tokio::{sync::mpsc, time::sleep};
+
+	let limits = Arc::new(Limits {
+		total:    Limiter::new(2),
+		file:     Limiter::new(2),
+		precache: Limiter::new(1),
+	});
+	let order = Arc::new(Mutex::new(Vec::new()));
+	let running = Arc::new(Mutex::new([0, 0]));
+
+	// A stub of the dispatcher in the scheduler, that runs each job for 10ms
+	let dispatch = |kind: TaskKind, mut rx: mpsc::UnboundedReceiver<usize>| {
+		let (limits, order, running) = (limits.clone(), order.clone(), running.clone());
+		async move {
+			let mut jobs = Vec::new();
+			while let Some(job) = rx.recv().await {
+				let permits = limits.admit(kind).await;
+				let (order, running) = (order.clone(), running.clone());
+				jobs.push(tokio::spawn(async move {
+					let i = (kind == TaskKind::Precache) as usize;
+					{
+						let mut count = running.lock();
+						count[i] += 1;
+						order.lock().push((kind, job, count[i], count[0] + count[1]));
+					}
+					sleep(Duration::from_millis(10)).await;
+					running.lock()[i] -= 1;
+					drop(permits);
+				}));
+			}
+			for job in jobs {
+				job.await.ok();
+			}
+		}
+	};
+
+	shared::block_on(async {
+		let (file_tx, file_rx) = mpsc::unbounded_channel();
+		let (precache_tx, precache_rx) = mpsc::unbounded_channel();
+		for i in 0..6 {
+			file_tx.send(i).unwrap();
+			precache_tx.send(i).unwrap();
+		}
+		drop((file_tx, precache_tx));
+
+		tokio::join!(dispatch(TaskKind::File, file_rx), dispatch(TaskKind::Precache, precache_rx));
+	});
+
+	let order = order.lock();
+	assert_eq!(order.len(), 12);
+	for kind in [TaskKind::File, TaskKind::Precache] {
+		// First in, first out within a category
+		let jobs: Vec<_> = order.iter().filter(|o| o.0 == kind).map(|o| o.1).collect();
+		assert_eq!(jobs, (0..6).collect::<Vec<_>>());
+	}
+
+	// Both limits are held, and the file jobs don't take over the total one
+	assert!(order.iter().all(|o| o.3 <= 2 && (o.0 != TaskKind::Precache || o.2 == 1)));
+	assert!(order.iter().position(|o| o.0 == TaskKind::Precache) < Some(3));
+}
+
+#[test]
+fn test_limiter_set() {
+	use std::time::Duration;
+
+	use tokio::time::sleep;
+
+	// The most of the 8 jobs, each of 10ms, that are run at the same time with the limiter
+	async fn most(limiter: &Arc<Limiter>) -> usize {
+		let (running, most) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+		let jobs: Vec<_> = (0..8)
+			.map(|_| {
+				let (limiter, running, most) = (limiter.clone(), running.clone(), most.clone());
+				tokio::spawn(async move {
+					let _permit = limiter.acquire().await;
+					most.fetch_max(running.fetch_add(1, Ordering::Relaxed) + 1, Ordering::Relaxed);
+					sleep(Duration::from_millis(10)).await;
+					running.fetch_sub(1, Ordering::Relaxed);
+				})
+			})
+			.collect();
+		for job in jobs {
+			job.await.ok();
+		}
+		most.load(Ordering::Relaxed)
+	}
+
+	shared::block_on(async {
+		let limiter = Arc::new(Limiter::new(4));
+
+		// Lowered while all of the permits are in use, and raised again before they're released
+		let mut busy = Vec::new();
+		for _ in 0..4 {
+			busy.push(limiter.acquire().await);
+		}
+		limiter.set(2);
+		tokio::task::yield_now().await;
+		limiter.set(4);
+		drop(busy);
+		assert_eq!((limiter.limit(), most(&limiter).await), (4, 4));
+
+		limiter.set(2);
+		assert_eq!(most(&limiter).await, 2);
+		limiter.set(1);
+		limiter.set(3);
+		assert_eq!(most(&limiter).await, 3);
+	});
+}
@@ -0,0 +1,125 @@
+use std::collections::VecDeque;
+
+// How many of the first and the last lines of the output of a task are kept, where the ones in
+// between are left out once there are more, and how long a line can be
+const LOGS_HEAD: usize = 100;
+const LOGS_TAIL: usize = 1000;
+const LOGS_LINE: usize = 1024;
+
+// The output of a task, the start and the end of it for a chatty one, so it's bounded in memory
+#[derive(Clone, Debug, Default)]
+pub struct TaskLogs {
+	head:    Vec<String>,
+	tail:    VecDeque<String>,
+	dropped: usize,
+}
+
+impl TaskLogs {
+	pub fn push(&mut self, mut line: String) {
+		if line.len() > LOGS_LINE {
+			let mut i = LOGS_LINE;
+			while !line.is_char_boundary(i) {
+				i -= 1;
+			}
+			line.truncate(i);
+			line.push('…');
+		}
+
+		if self.head.len() < LOGS_HEAD {
+			return self.head.push(line);
+		}
+		if self.tail.len() == LOGS_TAIL {
+			self.tail.pop_front();
+			self.dropped += 1;
+		}
+		self.tail.push_back(line);
+	}
+
+	#[inline]
+	pub fn is_empty(&self) -> bool { self.head.is_empty() }
+
+	// The lines kept, with one in place of the ones left out telling how many they are
+	pub fn lines(&self) -> Vec<String> {
+		let mut lines = Vec::with_capacity(self.head.len() + self.tail.len() + 1);
+		lines.extend(self.head.iter().cloned());
+		if self.dropped > 0 {
+			lines.push(format!("… {} lines left out …", self.dropped));
+		}
+		lines.extend(self.tail.iter().cloned());
+		lines
+	}
+
+	// The last `n` lines, the latest last
+	pub fn last(&self, n: usize) -> Vec<&str> {
+		let mut last: Vec<_> = self.tail.iter().rev().take(n).map(|s| s.as_str()).collect();
+		last.extend(self.head.iter().rev().take(n - last.len()).map(|s| s.as_str()));
+		last.reverse();
+		last
+	}
+}
+
+// The output of a finished task read in the task manager, from the line at `offset`
+pub struct TaskViewer {
+	pub title:  String,
+	pub lines:  Vec<String>,
+	pub offset: usize,
+}
+
+impl TaskViewer {
+	// Scrolls by `step` lines, as far as the last line is at the bottom of the `height` in view
+	pub fn scroll(&mut self, step: isize, height: usize) -> bool {
+		let max = self.lines.len().saturating_sub(height);
+		let old = self.offset;
+		self.offset = self.offset.saturating_add_signed(step).min(max);
+		old != self.offset
+	}
+
+	#[inline]
+	pub fn window(&self, height: usize) -> &[String] {
+		let end = (self.offset + height).min(self.lines.len());
+		&self.lines[self.offset.min(end)..end]
+	}
+}
+
+impl std::fmt::Display for TaskLogs {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		for line in self.lines() {
+			writeln!(f, "{line}")?;
+		}
+		Ok(())
+	}
+}
+
+#[test]
+fn test_task_logs() {
+	let mut logs = TaskLogs::default();
+	assert!(logs.is_empty() && logs.last(2).is_empty());
+
+	logs.push("a".to_owned());
+	logs.push("b".to_owned());
+	assert_eq!(logs.last(5), ["a", "b"]);
+	assert_eq!(logs.to_string(), "a\nb\n");
+
+	for i in 2..LOGS_HEAD + LOGS_TAIL + 10 {
+		logs.push(i.to_string());
+	}
+	let lines = logs.lines();
+	assert_eq!(lines.len(), LOGS_HEAD + LOGS_TAIL + 1);
+	assert_eq!(lines[LOGS_HEAD - 1], (LOGS_HEAD - 1).to_string());
+	assert_eq!(lines[LOGS_HEAD], "… 10 lines left out …");
+	assert_eq!(lines[LOGS_HEAD + 1], (LOGS_HEAD + 10).to_string());
+	let last = [LOGS_HEAD + LOGS_TAIL + 8, LOGS_HEAD + LOGS_TAIL + 9].map(|i| i.to_string());
+	assert_eq!(logs.last(2), last);
+
+	let mut viewer = TaskViewer { title: String::new(), lines: logs.lines(), offset: 0 };
+	assert!(!viewer.scroll(-1, 10));
+	assert!(viewer.scroll(isize::MAX, 10));
+	assert_eq!(viewer.window(10).len(), 10);
+	assert_eq!(viewer.window(10)[9], (LOGS_HEAD + LOGS_TAIL + 9).to_string());
+	assert!(!viewer.scroll(1, 10));
+
+	// Cut at a char boundary
+	let mut logs = TaskLogs::default();
+	logs.push(format!("a{}", "é".repeat(LOGS_LINE)));
+	assert_eq!(logs.last(1)[0].len(), LOGS_LINE - 1 + '…'.len_utf8());
+}
@@ -1,13 +1,44 @@
+mod checksum;
+mod conflict;
+mod dedupe;
+mod impact;
+mod limiter;
+mod logs;
+mod option;
+#[cfg(unix)]
+mod permission;
+mod plan;
+mod preferred;
+mod queue;
+mod rate;
+mod replace;
 mod running;
 mod scheduler;
 mod task;
 mod tasks;
+mod touch;
 mod workers;
 
+pub use checksum::*;
+use conflict::*;
+use dedupe::*;
+use impact::*;
+use limiter::*;
+pub use logs::*;
+pub use option::*;
+#[cfg(unix)]
+use permission::*;
+pub use plan::*;
+use preferred::*;
+use queue::*;
+use rate::*;
+use replace::*;
 use running::*;
 use scheduler::*;
 use task::*;
+pub use task::{TaskKind, TaskState, TaskSummary};
 pub use tasks::*;
+use touch::*;
 
 pub const TASKS_PADDING: u16 = 2;
 pub const TASKS_PERCENT: u16 = 80;
@@ -0,0 +1,44 @@
+use config::{tasks::ConflictPolicy, TASKS};
+
+use super::Checksum;
+
+#[derive(Clone, Copy, Debug)]
+pub struct PasteOpt {
+	pub conflict: ConflictPolicy,
+	pub follow:   bool,
+	pub verify:   Option<Checksum>,
+	// Bytes per second, 0 for unlimited
+	pub limit:    u64,
+	// Only work out what'd be done, and ask whether to do it
+	pub dry_run:  bool,
+	// Clone the files where the filesystem can, rather than copy their content
+	pub reflink:  bool,
+	pub preserve: Preserve,
+}
+
+// The metadata of the sources kept on the copies, all of it unless opted out of,
+// and always for the moves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Preserve {
+	pub mode:   bool,
+	pub times:  bool,
+	pub xattrs: bool,
+}
+
+impl Default for Preserve {
+	fn default() -> Self { Self { mode: true, times: true, xattrs: true } }
+}
+
+impl Default for PasteOpt {
+	fn default() -> Self {
+		Self {
+			conflict: TASKS.conflict,
+			follow:   false,
+			verify:   None,
+			limit:    TASKS.bandwidth,
+			dry_run:  false,
+			reflink:  true,
+			preserve: Default::default(),
+		}
+	}
+}
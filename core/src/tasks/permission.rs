@@ -0,0 +1,175 @@
+use std::ffi::CString;
+
+use anyhow::{anyhow, bail, Result};
+
+// A change of the mode, as octal digits such as `755`, or symbolic clauses such as `u+x,go-w`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ModeSpec {
+	Octal(u32),
+	Symbolic(Vec<ModeClause>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ModeClause {
+	who:   u32,
+	op:    char,
+	perms: String,
+}
+
+impl TryFrom<&str> for ModeSpec {
+	type Error = anyhow::Error;
+
+	fn try_from(s: &str) -> Result<Self, Self::Error> {
+		let s = s.trim();
+		if !s.is_empty() && s.len() <= 4 && s.bytes().all(|b| (b'0'..=b'7').contains(&b)) {
+			return Ok(Self::Octal(u32::from_str_radix(s, 8)?));
+		}
+
+		let mut clauses = vec![];
+		for part in s.split(',') {
+			let ops = part.trim_start_matches(['u', 'g', 'o', 'a']);
+			// Everyone if no one is given
+			let who = part[..part.len() - ops.len()].chars().fold(0, |acc, c| {
+				acc | match c {
+					'u' => 0o4700,
+					'g' => 0o2070,
+					'o' => 0o1007,
+					_ => 0o7777,
+				}
+			});
+			let who = if who == 0 { 0o7777 } else { who };
+
+			// Each of the operations, e.g. `u+x-w` is `u+x`, then `u-w`
+			let mut rest = ops;
+			if rest.is_empty() {
+				bail!("invalid mode `{s}`, neither octal digits nor clauses like `u+x`");
+			}
+			while let Some(op) = rest.chars().next().filter(|c| matches!(c, '+' | '-' | '=')) {
+				rest = &rest[1..];
+				let perms = rest.split(['+', '-', '=']).next().unwrap_or_default();
+				if let Some(c) = perms.chars().find(|c| !"rwxXst".contains(*c)) {
+					bail!("invalid permission `{c}` in the mode `{s}`, one of `rwxXst`");
+				}
+				rest = &rest[perms.len()..];
+				clauses.push(ModeClause { who, op, perms: perms.to_owned() });
+			}
+			if !rest.is_empty() {
+				bail!("invalid mode `{s}`, `{rest}` isn't preceded by one of `+`, `-` and `=`");
+			}
+		}
+		Ok(Self::Symbolic(clauses))
+	}
+}
+
+impl ModeSpec {
+	// The mode of a file after the change, from its permission bits `mode`. `X` gives the execute
+	// permission only to the directories and the files executable by someone already
+	pub(crate) fn apply(&self, mode: u32, is_dir: bool) -> u32 {
+		let clauses = match self {
+			Self::Octal(m) => return *m,
+			Self::Symbolic(clauses) => clauses,
+		};
+
+		let mut mode = mode & 0o7777;
+		for ModeClause { who, op, perms } in clauses {
+			let bits = perms.chars().fold(0, |acc, c| {
+				acc | match c {
+					'r' => 0o444,
+					'w' => 0o222,
+					'x' => 0o111,
+					'X' if is_dir || mode & 0o111 != 0 => 0o111,
+					's' => 0o6000,
+					't' => 0o1000,
+					_ => 0,
+				}
+			}) & who;
+
+			mode = match op {
+				'+' => mode | bits,
+				'-' => mode & !bits,
+				_ => mode & !who | bits,
+			};
+		}
+		mode
+	}
+}
+
+// A change of the owner, as `user:group`, `user` or `:group`, by the names or the IDs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct OwnerSpec {
+	pub uid: Option<u32>,
+	pub gid: Option<u32>,
+}
+
+impl TryFrom<&str> for OwnerSpec {
+	type Error = anyhow::Error;
+
+	fn try_from(s: &str) -> Result<Self, Self::Error> {
+		let (user, group) = s.trim().split_once(':').unwrap_or((s.trim(), ""));
+		let uid = match user {
+			"" => None,
+			u => Some(u.parse().ok().or_else(|| Self::uid(u)).ok_or_else(|| anyhow!("no user `{u}`"))?),
+		};
+		let gid = match group {
+			"" => None,
+			g => Some(g.parse().ok().or_else(|| Self::gid(g)).ok_or_else(|| anyhow!("no group `{g}`"))?),
+		};
+
+		if uid.is_none() && gid.is_none() {
+			bail!("invalid owner `{s}`, neither `user:group`, `user` nor `:group`");
+		}
+		Ok(Self { uid, gid })
+	}
+}
+
+impl OwnerSpec {
+	fn uid(name: &str) -> Option<u32> {
+		let name = CString::new(name).ok()?;
+		let pw = unsafe { libc::getpwnam(name.as_ptr()) };
+		if pw.is_null() { None } else { Some(unsafe { (*pw).pw_uid }) }
+	}
+
+	fn gid(name: &str) -> Option<u32> {
+		let name = CString::new(name).ok()?;
+		let gr = unsafe { libc::getgrnam(name.as_ptr()) };
+		if gr.is_null() { None } else { Some(unsafe { (*gr).gr_gid }) }
+	}
+}
+
+#[test]
+fn test_mode_spec() {
+	let apply = |spec: &str, mode, is_dir| ModeSpec::try_from(spec).unwrap().apply(mode, is_dir);
+
+	assert_eq!(apply("755", 0o600, false), 0o755);
+	assert_eq!(apply("0644", 0o777, true), 0o644);
+	assert_eq!(apply("u+x", 0o644, false), 0o744);
+	assert_eq!(apply("go-w", 0o666, false), 0o644);
+	assert_eq!(apply("+x", 0o644, false), 0o755);
+	assert_eq!(apply("a=r", 0o4755, false), 0o444);
+	assert_eq!(apply("u=rwx,g=rx,o=", 0o000, false), 0o750);
+	assert_eq!(apply("u+x-w", 0o644, false), 0o544);
+	assert_eq!(apply("g+s,o+t", 0o755, true), 0o3755);
+
+	// Only for the directories and the files executable already
+	assert_eq!(apply("a+X", 0o644, false), 0o644);
+	assert_eq!(apply("a+X", 0o744, false), 0o755);
+	assert_eq!(apply("a+X", 0o644, true), 0o755);
+
+	assert!(ModeSpec::try_from("").is_err());
+	assert!(ModeSpec::try_from("8").is_err());
+	assert!(ModeSpec::try_from("u+q").is_err());
+	assert!(ModeSpec::try_from("ux").is_err());
+	assert!(ModeSpec::try_from("u+x,").is_err());
+}
+
+#[test]
+fn test_owner_spec() {
+	let owner = |s: &str| OwnerSpec::try_from(s).ok();
+
+	assert_eq!(owner("1000:100"), Some(OwnerSpec { uid: Some(1000), gid: Some(100) }));
+	assert_eq!(owner("1000"), Some(OwnerSpec { uid: Some(1000), gid: None }));
+	assert_eq!(owner(":100"), Some(OwnerSpec { uid: None, gid: Some(100) }));
+	assert_eq!(owner("root"), Some(OwnerSpec { uid: Some(0), gid: None }));
+	assert_eq!(owner("no-such-user-of-yazi"), None);
+	assert_eq!(owner(":"), None);
+}
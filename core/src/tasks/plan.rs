@@ -0,0 +1,307 @@
+use std::{collections::HashSet, fmt::{self, Display}, mem, path::{Path, PathBuf}, time::SystemTime};
+
+use config::MANAGER;
+use shared::Url;
+use tokio::fs;
+use tracing::trace;
+
+use super::{unique_among, Conflicts, PasteOpt, Scheduler};
+use crate::{emit, files::Meta, select::SelectOpt};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlanKind {
+	Copy,
+	Move,
+	Trash,
+	Delete,
+	Rename,
+	Hardlink,
+	Skip,
+}
+
+impl Display for PlanKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Copy => "copy",
+			Self::Move => "move",
+			Self::Trash => "trash",
+			Self::Delete => "delete",
+			Self::Rename => "rename",
+			Self::Hardlink => "hardlink",
+			Self::Skip => "skip",
+		})
+	}
+}
+
+// What's done to `from`, with where it goes if anywhere, and whether a file there is replaced
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlanStep {
+	pub kind:      PlanKind,
+	pub from:      Url,
+	pub to:        Option<Url>,
+	pub overwrite: bool,
+}
+
+impl Display for PlanStep {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} {}", self.kind, self.from.display())?;
+		// Only the name of the destination if it's next to the source, as most of them are
+		match &self.to {
+			Some(to) if to.parent() == self.from.parent() => {
+				write!(f, " → {}", to.file_name().unwrap_or_default().to_string_lossy())?
+			}
+			Some(to) => write!(f, " → {}", to.display())?,
+			None => {}
+		}
+		if self.overwrite {
+			write!(f, " (overwrite)")?;
+		}
+		Ok(())
+	}
+}
+
+// The steps of an operation on the files, worked out all at once before any of them is taken,
+// so that it can be reviewed, and then run as it is
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Plan {
+	pub steps: Vec<PlanStep>,
+}
+
+impl Plan {
+	// Where each of `src` would be pasted in `dest`, the conflicts resolved as they'd be in a paste
+	pub(super) async fn paste(
+		src: HashSet<Url>,
+		dest: &Url,
+		cut: bool,
+		conflicts: &mut Conflicts,
+	) -> Self {
+		// In the same order every time, for the same one of the sources of a name to go first
+		let mut src: Vec<_> = src.into_iter().collect();
+		src.sort_unstable();
+
+		// Where the sources so far go, for two of the same name, from different directories,
+		// not to be pasted to the same place
+		let mut taken = HashSet::new();
+		let kind = if cut { PlanKind::Move } else { PlanKind::Copy };
+
+		let mut plan = Self::default();
+		for from in src {
+			let to = dest.join(from.file_name().unwrap());
+			if let Some(i) = plan.steps.iter().position(|s| s.to.as_ref() == Some(&to)) {
+				let other = plan.steps[i].from.clone();
+				match conflicts.resolve_sources(&from, &other, to.clone(), &taken).await {
+					Some(resolved) if resolved == to => {
+						let overwrite = mem::take(&mut plan.steps[i].overwrite);
+						(plan.steps[i].kind, plan.steps[i].to) = (PlanKind::Skip, None);
+						plan.push(kind, from, Some(to), overwrite);
+					}
+					Some(resolved) => {
+						taken.insert(resolved.clone());
+						plan.push(kind, from, Some(resolved), false);
+					}
+					None => plan.push(PlanKind::Skip, from, None, false),
+				}
+				continue;
+			}
+
+			let exists = Meta::read(&to, false).await.is_ok();
+			let Some(mut resolved) = conflicts.resolve(&from, to.clone()).await else {
+				plan.push(PlanKind::Skip, from, None, false);
+				continue;
+			};
+
+			// Renamed to the name another one is renamed to already
+			if resolved != to && taken.contains(&resolved) {
+				resolved = unique_among(to.clone(), &taken).await;
+			}
+			taken.insert(resolved.clone());
+
+			let overwrite = exists && resolved == to;
+			plan.push(kind, from, Some(resolved), overwrite);
+		}
+		plan
+	}
+
+	pub(super) fn remove(targets: Vec<Url>, permanently: bool) -> Self {
+		let kind = if permanently { PlanKind::Delete } else { PlanKind::Trash };
+		let steps = targets.into_iter().map(|from| PlanStep { kind, from, to: None, overwrite: false });
+		Self { steps: steps.collect() }
+	}
+
+	pub fn rename(targets: Vec<(Url, Url)>) -> Self {
+		let steps = targets.into_iter().map(|(from, to)| PlanStep {
+			kind: PlanKind::Rename,
+			from,
+			to: Some(to),
+			overwrite: false,
+		});
+		Self { steps: steps.collect() }
+	}
+
+	// The new names of the renames relative to `root`, that are empty, taken by another of them,
+	// or by a file that isn't renamed, for none of them to be done
+	pub async fn rename_conflicts(root: &Path, todo: &[(PathBuf, PathBuf)]) -> Vec<PathBuf> {
+		let sources: HashSet<_> = todo.iter().map(|(o, _)| o).collect();
+
+		let mut seen = HashSet::new();
+		let mut conflicts = Vec::new();
+		for (_, n) in todo {
+			if !seen.insert(n) || n.as_os_str().is_empty() {
+				conflicts.push(n.clone());
+			} else if !sources.contains(n) && fs::symlink_metadata(root.join(n)).await.is_ok() {
+				conflicts.push(n.clone());
+			}
+		}
+		conflicts
+	}
+
+	// Each of `targets` replaced with a hardlink of `to`
+	pub(super) fn hardlink(targets: Vec<Url>, to: Url) -> Self {
+		let steps = targets.into_iter().map(|from| PlanStep {
+			kind: PlanKind::Hardlink,
+			from,
+			to: Some(to.clone()),
+			overwrite: true,
+		});
+		Self { steps: steps.collect() }
+	}
+
+	#[inline]
+	fn push(&mut self, kind: PlanKind, from: Url, to: Option<Url>, overwrite: bool) {
+		self.steps.push(PlanStep { kind, from, to, overwrite });
+	}
+
+	// How many of the steps do something, rather than skipping the file
+	#[inline]
+	pub fn todo(&self) -> usize { self.steps.iter().filter(|s| s.kind != PlanKind::Skip).count() }
+
+	#[inline]
+	pub fn overwrites(&self) -> usize { self.steps.iter().filter(|s| s.overwrite).count() }
+
+	pub fn summary(&self) -> String {
+		let (todo, skip) = (self.todo(), self.steps.len() - self.todo());
+		let mut s = format!("{todo} to do");
+		if self.overwrites() > 0 {
+			s.push_str(&format!(", {} to overwrite", self.overwrites()));
+		}
+		if skip > 0 {
+			s.push_str(&format!(", {skip} skipped"));
+		}
+		s
+	}
+
+	// Shows the steps, and tells whether they're to be run
+	pub(super) async fn review(&self) -> bool {
+		if self.todo() == 0 {
+			emit!(Message(format!("Dry run: nothing to do, {}", self.summary()), false));
+			return false;
+		}
+
+		let items = [format!("Run it, {}", self.summary())]
+			.into_iter()
+			.chain(self.steps.iter().map(|s| s.to_string()))
+			.collect();
+		emit!(Select(SelectOpt::top("Dry run:", items))).await.is_ok_and(|choice| choice == 0)
+	}
+
+	pub(super) fn run(self, scheduler: &Scheduler, opt: &PasteOpt) {
+		if MANAGER.follow_results {
+			let pasted = self.steps.iter().filter(|s| matches!(s.kind, PlanKind::Copy | PlanKind::Move));
+			let pasted: Vec<_> = pasted.filter_map(|s| s.to.clone()).collect();
+			if !pasted.is_empty() {
+				emit!(Land(pasted));
+			}
+		}
+
+		let batch = SystemTime::now();
+		let (mut renames, mut links) = (Vec::new(), Vec::new());
+		for PlanStep { kind, from, to, .. } in self.steps {
+			match (kind, to) {
+				(PlanKind::Copy, Some(to)) => scheduler.file_copy(from, to, opt),
+				(PlanKind::Move, Some(to)) => scheduler.file_cut(from, to, opt),
+				(PlanKind::Rename, Some(to)) => renames.push((from, to)),
+				(PlanKind::Hardlink, Some(to)) => links.push((to, from)),
+				(PlanKind::Trash, _) => scheduler.file_trash(from, batch),
+				(PlanKind::Delete, _) => scheduler.file_delete(from),
+				_ => trace!("plan: skipping {:?}", from),
+			}
+		}
+		if !renames.is_empty() {
+			scheduler.file_rename(renames);
+		}
+		if !links.is_empty() {
+			scheduler.file_relink(links);
+		}
+	}
+}
+
+#[test]
+fn test_plan() {
+	use config::tasks::ConflictPolicy;
+
+	let dir = shared::TestDir::new("plan");
+	let (src, dest) = (dir.join("src"), dir.join("dest"));
+	std::fs::create_dir_all(&src).unwrap();
+	std::fs::create_dir_all(&dest).unwrap();
+	for name in ["a", "b"] {
+		std::fs::write(src.join(name), name).unwrap();
+	}
+	std::fs::write(dest.join("b"), "b").unwrap();
+
+	let files: HashSet<_> = ["a", "b"].iter().map(|n| Url::from(src.join(n))).collect();
+	let dest_url = Url::from(&dest);
+	shared::block_on(async {
+		let mut conflicts = Conflicts::new(ConflictPolicy::Overwrite);
+		let mut plan = Plan::paste(files.clone(), &dest_url, true, &mut conflicts).await;
+		plan.steps.sort_by(|a, b| a.from.cmp(&b.from));
+		assert_eq!(plan.steps[0].to, Some(Url::from(dest.join("a"))));
+		assert!(!plan.steps[0].overwrite);
+		assert!(plan.steps[1].overwrite && plan.steps[1].kind == PlanKind::Move);
+		assert_eq!(plan.summary(), "2 to do, 1 to overwrite");
+
+		let mut conflicts = Conflicts::new(ConflictPolicy::Skip);
+		let plan = Plan::paste(files.clone(), &dest_url, false, &mut conflicts).await;
+		assert_eq!((plan.todo(), plan.overwrites()), (1, 0));
+		assert_eq!(plan.summary(), "1 to do, 1 skipped");
+
+		let mut conflicts = Conflicts::new(ConflictPolicy::Rename);
+		let plan = Plan::paste(files, &dest_url, false, &mut conflicts).await;
+		let renamed = plan.steps.iter().find(|s| s.from.ends_with("b")).unwrap();
+		assert_eq!(renamed.to, Some(Url::from(dest.join("b_1"))));
+		let (from, to) = (src.join("b"), dest.join("b_1"));
+		assert_eq!(renamed.to_string(), format!("copy {} → {}", from.display(), to.display()));
+	});
+
+	// Two of the same name from different directories don't go to the same place
+	let other = dir.join("other");
+	std::fs::create_dir_all(&other).unwrap();
+	std::fs::write(other.join("a"), "a").unwrap();
+	let files: HashSet<_> = [src.join("a"), other.join("a")].into_iter().map(Url::from).collect();
+	shared::block_on(async {
+		let mut conflicts = Conflicts::new(ConflictPolicy::Rename);
+		let plan = Plan::paste(files.clone(), &dest_url, false, &mut conflicts).await;
+		let to: Vec<_> = plan.steps.iter().map(|s| s.to.clone()).collect();
+		assert_eq!(to, [Some(Url::from(dest.join("a"))), Some(Url::from(dest.join("a_1")))]);
+
+		let mut conflicts = Conflicts::new(ConflictPolicy::Skip);
+		let plan = Plan::paste(files.clone(), &dest_url, false, &mut conflicts).await;
+		assert_eq!(plan.summary(), "1 to do, 1 skipped");
+
+		// The one going there later takes the place of the other
+		let mut conflicts = Conflicts::new(ConflictPolicy::Overwrite);
+		let plan = Plan::paste(files, &dest_url, true, &mut conflicts).await;
+		assert_eq!(plan.steps[0].kind, PlanKind::Skip);
+		assert_eq!(plan.steps[1].from, Url::from(src.join("a")));
+		assert_eq!(plan.steps[1].to, Some(Url::from(dest.join("a"))));
+	});
+
+	// Nothing was touched by the plans
+	assert!(!dest.join("a").exists() && src.join("a").exists());
+
+	let plan = Plan::remove(vec![Url::from(src.join("a"))], true);
+	assert_eq!(plan.steps[0].kind, PlanKind::Delete);
+	assert_eq!(plan.todo(), 1);
+
+	let plan = Plan::rename(vec![(Url::from(src.join("a")), Url::from(src.join("c")))]);
+	assert_eq!(plan.steps[0].to_string(), format!("rename {} → c", src.join("a").display()));
+}
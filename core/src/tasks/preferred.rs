@@ -0,0 +1,46 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use config::{open::Opener, BOOT};
+use tracing::warn;
+
+// The openers chosen with `open --interactive` to always open the files of a type with,
+// before the ones of the rules in the config, kept across restarts
+#[derive(Default)]
+pub(super) struct Preferred {
+	inner: BTreeMap<String, Opener>,
+}
+
+impl Preferred {
+	#[inline]
+	fn path() -> PathBuf { BOOT.state_dir.join("openers.json") }
+
+	pub(super) fn load() -> Self {
+		let inner = std::fs::read_to_string(Self::path())
+			.ok()
+			.and_then(|s| serde_json::from_str(&s).ok())
+			.unwrap_or_default();
+		Self { inner }
+	}
+
+	#[inline]
+	pub(super) fn get(&self, mime: &str) -> Option<&Opener> { self.inner.get(mime) }
+
+	pub(super) fn set(&mut self, mime: String, opener: Opener) -> bool {
+		if self.inner.get(&mime) == Some(&opener) {
+			return false;
+		}
+		self.inner.insert(mime, opener);
+		true
+	}
+
+	pub(super) fn save(&self) {
+		let Ok(s) = serde_json::to_string(&self.inner) else {
+			return;
+		};
+		tokio::spawn(async move {
+			if let Err(e) = tokio::fs::write(Self::path(), s).await {
+				warn!("failed to save the preferred openers: {e}");
+			}
+		});
+	}
+}
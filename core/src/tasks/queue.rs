@@ -0,0 +1,73 @@
+use std::collections::{BTreeMap, VecDeque};
+
+// The work waiting for its turn, by the tasks it's of. The tasks that have started take their
+// turns in the order their work came in, and so do the pending ones, except that a turn of them
+// goes to the one first in the order they're given, so they can be moved up and down the line
+pub(super) struct Queue<T> {
+	seq: u64,
+	ops: BTreeMap<usize, VecDeque<(u64, T)>>,
+}
+
+impl<T> Default for Queue<T> {
+	fn default() -> Self { Self { seq: 0, ops: Default::default() } }
+}
+
+impl<T> Queue<T> {
+	pub(super) fn push(&mut self, id: usize, op: T) {
+		self.seq += 1;
+		self.ops.entry(id).or_default().push_back((self.seq, op));
+	}
+
+	#[inline]
+	pub(super) fn is_empty(&self) -> bool { self.ops.is_empty() }
+
+	// The next of the work, where `pending` are the tasks that haven't started yet, in line
+	pub(super) fn pop(&mut self, pending: &[usize]) -> Option<(usize, T)> {
+		let (mut started, mut waiting) = (None::<(u64, usize)>, None::<u64>);
+		for (&id, ops) in &self.ops {
+			let Some(&(seq, _)) = ops.front() else { continue };
+			if !pending.contains(&id) {
+				started = started.filter(|&(s, _)| s < seq).or(Some((seq, id)));
+			} else if waiting.is_none_or(|w| seq < w) {
+				waiting = Some(seq);
+			}
+		}
+
+		let id = match (started, waiting) {
+			(Some((s, id)), Some(w)) if s < w => id,
+			(_, Some(_)) => *pending.iter().find(|id| self.ops.contains_key(id))?,
+			(Some((_, id)), None) => id,
+			(None, None) => return None,
+		};
+
+		let ops = self.ops.get_mut(&id)?;
+		let (_, op) = ops.pop_front()?;
+		if ops.is_empty() {
+			self.ops.remove(&id);
+		}
+		Some((id, op))
+	}
+}
+
+#[test]
+fn test_queue() {
+	let mut queue = Queue::default();
+	for (id, op) in [(1, "a1"), (2, "b1"), (1, "a2"), (3, "c1"), (3, "c2"), (2, "b2")] {
+		queue.push(id, op);
+	}
+
+	// The turn of `2` goes to `3` moved up before it, and then `2` has the next one
+	let (mut pending, mut order) = (vec![3, 2], vec![]);
+	while let Some((id, op)) = queue.pop(&pending) {
+		pending.retain(|&p| p != id);
+		order.push(op);
+	}
+	assert_eq!(order, ["a1", "c1", "b1", "a2", "c2", "b2"]);
+	assert!(queue.is_empty());
+
+	queue.push(1, "a1");
+	queue.push(2, "b1");
+	assert_eq!(queue.pop(&[]), Some((1, "a1")));
+	assert_eq!(queue.pop(&[2]), Some((2, "b1")));
+	assert_eq!(queue.pop(&[]), None);
+}
@@ -0,0 +1,65 @@
+use std::time::{Duration, Instant};
+
+// An exponential moving average of the throughput in bytes per second. It's sampled by
+// the progress ticker instead of on every chunk, so the copy loop doesn't pay for it
+#[derive(Debug, Default)]
+pub struct Rate {
+	speed: Option<f64>,
+	last:  Option<(Instant, u64)>,
+}
+
+impl Rate {
+	const SMOOTHING: f64 = 0.3;
+
+	pub(super) fn sample(&mut self, done: u64) { self.sample_at(Instant::now(), done) }
+
+	fn sample_at(&mut self, now: Instant, done: u64) {
+		if let Some((at, before)) = self.last {
+			let secs = now.saturating_duration_since(at).as_secs_f64();
+			if secs <= 0.0 {
+				return;
+			}
+
+			let current = done.saturating_sub(before) as f64 / secs;
+			self.speed = Some(match self.speed {
+				Some(speed) => speed + Self::SMOOTHING * (current - speed),
+				None => current,
+			});
+		}
+		self.last = Some((now, done));
+	}
+
+	#[inline]
+	pub fn speed(&self) -> u64 { self.speed.unwrap_or(0.0) as u64 }
+
+	// The time it takes for the `left` bytes at the current speed
+	pub fn eta(&self, left: u64) -> Option<Duration> {
+		match self.speed {
+			Some(speed) if speed >= 1.0 => Some(Duration::from_secs_f64(left as f64 / speed)),
+			_ => None,
+		}
+	}
+}
+
+#[test]
+fn test_rate() {
+	let start = Instant::now();
+	let mut rate = Rate::default();
+	assert_eq!((rate.speed(), rate.eta(100)), (0, None));
+
+	// 100 bytes per second
+	rate.sample_at(start, 0);
+	rate.sample_at(start + Duration::from_secs(1), 100);
+	assert_eq!(rate.speed(), 100);
+	assert_eq!(rate.eta(250), Some(Duration::from_millis(2500)));
+
+	// Averaged towards 200 bytes per second, instead of jumping to it
+	rate.sample_at(start + Duration::from_secs(2), 300);
+	assert_eq!(rate.speed(), 130);
+
+	// Stalled, e.g. paused
+	for i in 3..40 {
+		rate.sample_at(start + Duration::from_secs(i), 300);
+	}
+	assert_eq!((rate.speed(), rate.eta(100)), (0, None));
+}
@@ -0,0 +1,61 @@
+use anyhow::{bail, Result};
+use regex::{NoExpand, Regex};
+
+// What `replace` does to the names of the files, every match of `find` in them replaced `with`,
+// where `{n}` is the number of the file among the ones matched, and `$1` or `${name}` the groups
+// captured by `find` if it's a regex, rather than the literal text to find
+pub(super) struct Replace {
+	find:  Regex,
+	with:  String,
+	regex: bool,
+}
+
+impl Replace {
+	pub(super) fn new(find: &str, with: &str, regex: bool) -> Result<Self> {
+		if find.is_empty() {
+			bail!("Nothing to find");
+		}
+
+		let find = if regex { Regex::new(find)? } else { Regex::new(&regex::escape(find))? };
+		Ok(Self { find, with: with.to_owned(), regex })
+	}
+
+	// The new names of `names`, each of them the same as it was if there's no match in it
+	pub(super) fn apply<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+		let mut n = 0;
+		let mut apply = |name: &str| {
+			if !self.find.is_match(name) {
+				return name.to_owned();
+			}
+
+			n += 1;
+			let with = self.with.replace("{n}", &n.to_string());
+			if self.regex {
+				self.find.replace_all(name, with.as_str()).into_owned()
+			} else {
+				self.find.replace_all(name, NoExpand(&with)).into_owned()
+			}
+		};
+		names.into_iter().map(&mut apply).collect()
+	}
+}
+
+#[test]
+fn test_replace() {
+	let names = ["IMG_0042.JPG", "notes.txt", "IMG_0007.JPG", "a$b.JPG"];
+
+	let r = Replace::new(".JPG", ".jpg", false).unwrap();
+	assert_eq!(r.apply(names), ["IMG_0042.jpg", "notes.txt", "IMG_0007.jpg", "a$b.jpg"]);
+
+	// Numbered among the matched names only
+	let r = Replace::new(r"^IMG_(\d+)", "photo-{n}-$1", true).unwrap();
+	assert_eq!(r.apply(names), ["photo-1-0042.JPG", "notes.txt", "photo-2-0007.JPG", "a$b.JPG"]);
+
+	// Neither `$` nor `.` is special in a literal
+	let r = Replace::new("$b.", "$1", false).unwrap();
+	assert_eq!(r.apply(names)[3], "a$1JPG");
+	assert_eq!(r.apply(names)[0], "IMG_0042.JPG");
+
+	assert!(Replace::new("", "x", false).is_err());
+	assert!(Replace::new("(", "x", true).is_err());
+}
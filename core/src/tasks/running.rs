@@ -1,8 +1,11 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
 
 use futures::future::BoxFuture;
+use tracing::info;
 
-use super::{Task, TaskStage};
+use super::{Task, TaskKind, TaskLogs, TaskStage, TaskState, TaskSummary};
+
+const HISTORY: usize = 10;
 
 #[derive(Default)]
 pub(super) struct Running {
@@ -11,15 +14,34 @@ pub(super) struct Running {
 	pub(super) hooks:
 		BTreeMap<usize, Box<dyn (FnOnce(bool) -> BoxFuture<'static, ()>) + Send + Sync>>,
 	pub(super) all:   BTreeMap<usize, Task>,
+	// The file tasks that haven't started yet, in the order they're going to
+	pub(super) pending: Vec<usize>,
+
+	// The latest finished tasks, except the precache ones, with their logs to inspect
+	pub(super) history: VecDeque<(TaskSummary, TaskLogs)>,
 }
 
 impl Running {
-	pub(super) fn add(&mut self, name: String) -> usize {
+	pub(super) fn add(&mut self, kind: TaskKind, name: String) -> usize {
 		self.incr += 1;
-		self.all.insert(self.incr, Task::new(self.incr, name));
+		self.all.insert(self.incr, Task::new(self.incr, kind, name));
+		if kind == TaskKind::File {
+			self.pending.push(self.incr);
+		}
 		self.incr
 	}
 
+	pub(super) fn remove(&mut self, id: usize, state: TaskState) -> Option<Task> {
+		let mut task = self.all.remove(&id)?;
+		self.pending.retain(|&p| p != id);
+		if task.kind != TaskKind::Precache {
+			let summary = TaskSummary { state, ..(&task).into() };
+			self.history.push_front((summary, std::mem::take(&mut task.logs)));
+			self.history.truncate(HISTORY);
+		}
+		Some(task)
+	}
+
 	#[inline]
 	pub(super) fn get(&self, id: usize) -> Option<&Task> { self.all.get(&id) }
 
@@ -35,12 +57,41 @@ impl Running {
 	#[inline]
 	pub(super) fn exists(&self, id: usize) -> bool { self.all.contains_key(&id) }
 
+	// The tasks that have started, followed by the pending ones in the order they're going to
+	pub(super) fn values(&self) -> impl Iterator<Item = &Task> {
+		let started = self.all.values().filter(|t| !self.is_pending(t.id));
+		started.chain(self.pending.iter().filter_map(|id| self.all.get(id)))
+	}
+
 	#[inline]
-	pub(super) fn values(&self) -> impl Iterator<Item = &Task> { self.all.values() }
+	pub(super) fn values_mut(&mut self) -> impl Iterator<Item = &mut Task> {
+		self.all.values_mut()
+	}
 
 	#[inline]
 	pub(super) fn is_empty(&self) -> bool { self.all.is_empty() }
 
+	#[inline]
+	pub(super) fn is_pending(&self, id: usize) -> bool { self.pending.contains(&id) }
+
+	#[inline]
+	pub(super) fn start(&mut self, id: usize) { self.pending.retain(|&p| p != id); }
+
+	// Moves a pending task by `step` in line, as far as it can go
+	pub(super) fn shift(&mut self, id: usize, step: isize) -> bool {
+		let Some(idx) = self.pending.iter().position(|&p| p == id) else {
+			return false;
+		};
+
+		let to = (idx as isize).saturating_add(step).clamp(0, self.pending.len() as isize - 1);
+		if to as usize == idx {
+			return false;
+		}
+		self.pending.remove(idx);
+		self.pending.insert(to as usize, id);
+		true
+	}
+
 	pub(super) fn try_remove(
 		&mut self,
 		id: usize,
@@ -54,7 +105,12 @@ impl Running {
 			match task.stage {
 				TaskStage::Pending => return None,
 				TaskStage::Dispatched => {
-					if task.processed < task.found {
+					if task.processed + task.failed < task.found {
+						return None;
+					}
+					if task.failed > 0 {
+						// Kept for the inspection without running the hook, until it's canceled
+						self.hooks.remove(&id);
 						return None;
 					}
 					if let Some(hook) = self.hooks.remove(&id) {
@@ -64,8 +120,61 @@ impl Running {
 				TaskStage::Hooked => {}
 			}
 
-			self.all.remove(&id);
+			if let Some(task) = self.remove(id, TaskState::Completed).filter(|t| t.verified > 0) {
+				info!("{}: verified {} files, hashed {} bytes", task.name, task.verified, task.hashed);
+			}
 		}
 		None
 	}
 }
+
+#[test]
+fn test_running_history() {
+	let mut running = Running::default();
+	let a = running.add(TaskKind::File, "a".to_owned());
+	let b = running.add(TaskKind::File, "b".to_owned());
+	let c = running.add(TaskKind::Precache, "c".to_owned());
+
+	for id in [a, b, c] {
+		let task = running.get_mut(id).unwrap();
+		(task.found, task.processed) = (2, 1);
+	}
+
+	// One of the files failed, so it's kept
+	running.get_mut(a).unwrap().failed = 1;
+	assert!(running.try_remove(a, TaskStage::Dispatched).is_none());
+	assert!(running.get(a).unwrap().is_failed());
+
+	// All files are processed
+	running.get_mut(b).unwrap().processed = 2;
+	running.try_remove(b, TaskStage::Dispatched);
+	running.get_mut(c).unwrap().processed = 2;
+	running.try_remove(c, TaskStage::Dispatched);
+
+	running.remove(a, TaskState::Failed);
+	assert!(running.is_empty());
+
+	let history: Vec<_> = running.history.iter().map(|(t, _)| (t.name.as_str(), t.state)).collect();
+	assert_eq!(history, [("a", TaskState::Failed), ("b", TaskState::Completed)]);
+}
+
+#[test]
+fn test_running_pending() {
+	let mut running = Running::default();
+	let [a, b, c, d] = ["a", "b", "c", "d"].map(|s| running.add(TaskKind::File, s.to_owned()));
+	let p = running.add(TaskKind::Process, "p".to_owned());
+	let names = |r: &Running| r.values().map(|t| t.name.clone()).collect::<Vec<_>>().concat();
+
+	// Only the pending ones are moved, and the others are listed first
+	running.start(a);
+	assert!(!running.shift(a, 1) && !running.shift(p, 1));
+	assert_eq!(names(&running), "apbcd");
+	assert!(running.shift(d, isize::MIN));
+	assert!(running.shift(b, 1));
+	assert!(!running.shift(b, isize::MAX));
+	assert_eq!(running.pending, [d, c, b]);
+	assert_eq!(running.get_id(1), Some(p));
+
+	running.remove(c, TaskState::Canceled);
+	assert_eq!(running.pending, [d, b]);
+}
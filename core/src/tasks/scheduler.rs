@@ -1,23 +1,26 @@
-use std::{ffi::OsStr, sync::Arc, time::Duration};
+use std::{ffi::OsStr, mem, sync::Arc, time::{Duration, SystemTime}};
 
 use async_channel::{Receiver, Sender};
-use config::{open::Opener, TASKS};
+use config::{open::Opener, MANAGER, TASKS};
 use futures::{future::BoxFuture, FutureExt};
 use parking_lot::RwLock;
-use shared::{unique_path, Throttle, Url};
+use shared::{Throttle, Url};
 use tokio::{fs, select, sync::{mpsc::{self, UnboundedReceiver}, oneshot}, time::sleep};
 use tracing::{info, trace};
 
-use super::{workers::{File, FileOpDelete, FileOpPaste, FileOpTrash, Precache, PrecacheOpMime, PrecacheOpSize, Process, ProcessOpOpen}, Running, TaskOp, TaskStage};
-use crate::emit;
+use super::{workers::{File, FileOpArchive, FileOpCreate, FileOpExtract, FileOpDedupe, FileOpDelete, FileOpMakeLink, FileOpPaste, FileOpRelink, FileOpRename, FileOpRestore, FileOpTouch, FileOpTrash, FileTrashed, Precache, PrecacheOpChildren, PrecacheOpMime, PrecacheOpRanks, PrecacheOpSize, Process, ProcessOpOpen, SizeBatch}, Limits, PasteOpt, Preserve, Running, TaskKind, TaskOp, TaskStage, TaskState};
+#[cfg(unix)]
+use super::{workers::{FileOpChmod, FileOpChown}, ModeSpec, OwnerSpec};
+use crate::{emit, external::{ArchiveOpt, ExtractOpt}, files, sftp};
 
 pub struct Scheduler {
-	file:     Arc<File>,
-	precache: Arc<Precache>,
-	process:  Arc<Process>,
+	file:                Arc<File>,
+	pub(super) precache: Arc<Precache>,
+	process:             Arc<Process>,
 
 	todo:               Sender<BoxFuture<'static, ()>>,
 	pub(super) running: Arc<RwLock<Running>>,
+	pub(super) limits:  Arc<Limits>,
 }
 
 impl Scheduler {
@@ -32,14 +35,14 @@ impl Scheduler {
 
 			todo:    todo_tx,
 			running: Default::default(),
+			limits:  Default::default(),
 		};
 
 		for _ in 0..TASKS.micro_workers {
 			scheduler.schedule_micro(todo_rx.clone());
 		}
-		for _ in 0..TASKS.macro_workers {
-			scheduler.schedule_macro(todo_rx.clone());
-		}
+		scheduler.schedule_file();
+		scheduler.schedule_precache();
 		scheduler.progress(prog_rx);
 		scheduler
 	}
@@ -54,41 +57,81 @@ impl Scheduler {
 		});
 	}
 
-	fn schedule_macro(&self, rx: Receiver<BoxFuture<'static, ()>>) {
+	fn schedule_file(&self) {
 		let file = self.file.clone();
-		let precache = self.precache.clone();
 		let running = self.running.clone();
+		let limits = self.limits.clone();
 
 		tokio::spawn(async move {
-			loop {
-				if let Ok(fut) = rx.try_recv() {
-					fut.await;
+			while file.ready().await.is_ok() {
+				let permits = limits.admit(TaskKind::File).await;
+
+				// Picked once it's got the permits, so that a task moved up in the meantime goes first
+				let picked = {
+					let mut running = running.write();
+					file.pick(&running.pending).inspect(|&(id, _)| running.start(id))
+				};
+				let Some((id, mut op)) = picked else {
+					continue;
+				};
+
+				let switches = running.read().get(id).map(|t| (t.pause.clone(), t.cancel.clone()));
+				let Some((pause, cancel)) = switches else {
+					trace!("Skipping task {:?} as it was removed", op);
+					continue;
+				};
+				if pause.get() {
+					file.stash(id, op);
 					continue;
 				}
 
-				select! {
-					Ok(fut) = rx.recv() => {
-						fut.await;
-					}
-					Ok((id, mut op)) = file.recv() => {
-						if !running.read().exists(id) {
-							trace!("Skipping task {:?} as it was removed", op);
-							continue;
-						}
-						if let Err(e) = file.work(&mut op).await {
-							info!("Failed to work on task {:?}: {e}", op);
-						}
-					}
-					Ok((id, mut op)) = precache.recv() => {
-						if !running.read().exists(id) {
-							trace!("Skipping task {:?} as it was removed", op);
-							continue;
-						}
-						if let Err(e) = precache.work(&mut op).await {
-							info!("Failed to work on task {:?}: {e}", op);
+				let (file, limits) = (file.clone(), limits.clone());
+				tokio::spawn(async move {
+					let work = file.work(&mut op);
+					tokio::pin!(work);
+
+					// Give the permits to others while it's paused, and take them back once it's
+					// resumed, before the work goes on, unless it's canceled in the meantime
+					let mut permits = Some(permits);
+					let result = loop {
+						select! {
+							biased;
+							_ = pause.until(true), if permits.is_some() => permits = None,
+							_ = pause.until(false), if permits.is_none() => select! {
+								p = limits.admit(TaskKind::File) => permits = Some(p),
+								_ = cancel.until(true) => break work.await,
+							},
+							result = &mut work => break result,
 						}
+					};
+					if let Err(e) = result {
+						info!("Failed to work on task {id}: {e}");
 					}
+				});
+			}
+		});
+	}
+
+	fn schedule_precache(&self) {
+		let precache = self.precache.clone();
+		let running = self.running.clone();
+		let limits = self.limits.clone();
+
+		tokio::spawn(async move {
+			while let Ok((id, mut op)) = precache.recv().await {
+				let permits = limits.admit(TaskKind::Precache).await;
+				if !running.read().exists(id) {
+					trace!("Skipping task {:?} as it was removed", op);
+					continue;
 				}
+
+				let precache = precache.clone();
+				tokio::spawn(async move {
+					if let Err(e) = precache.work(&mut op).await {
+						info!("Failed to work on task {:?}: {e}", op);
+					}
+					drop(permits);
+				});
 			}
 		});
 	}
@@ -99,6 +142,7 @@ impl Scheduler {
 
 		tokio::spawn(async move {
 			while let Some(op) = rx.recv().await {
+				let id = op.id();
 				match op {
 					TaskOp::New(id, size) => {
 						if let Some(task) = running.write().get_mut(id) {
@@ -108,12 +152,10 @@ impl Scheduler {
 					}
 					TaskOp::Log(id, line) => {
 						if let Some(task) = running.write().get_mut(id) {
-							task.logs.push_str(&line);
-							task.logs.push('\n');
-
 							if let Some(logger) = &task.logger {
-								logger.send(line).ok();
+								logger.send(line.clone()).ok();
 							}
+							task.logs.push(line);
 						}
 					}
 					TaskOp::Adv(id, processed, size) => {
@@ -128,12 +170,44 @@ impl Scheduler {
 							}
 						}
 					}
+					TaskOp::Fail(id, line) => {
+						let mut running = running.write();
+						if let Some(task) = running.get_mut(id) {
+							task.failed += 1;
+							if let Some(logger) = &task.logger {
+								logger.send(line.clone()).ok();
+							}
+							task.logs.push(line);
+						}
+						if let Some(fut) = running.try_remove(id, TaskStage::Pending) {
+							todo.send_blocking(fut).ok();
+						}
+					}
+					TaskOp::Verify(id, verified, hashed) => {
+						if let Some(task) = running.write().get_mut(id) {
+							task.verified += verified;
+							task.hashed += hashed;
+						}
+					}
 					TaskOp::Done(id) => {
 						if let Some(fut) = running.write().try_remove(id, TaskStage::Dispatched) {
 							todo.send_blocking(fut).ok();
 						}
 					}
 				}
+
+				// Told of once it fails, with the last of its output, so it's noticed without the
+				// task manager open
+				if let Some(task) = running.write().get_mut(id).filter(|t| t.is_failed() && !t.surfaced) {
+					task.surfaced = true;
+					let last = task.logs.last(2).join(" | ");
+					let text = if last.is_empty() {
+						format!("{} failed", task.name)
+					} else {
+						format!("{} failed: {last}", task.name)
+					};
+					emit!(Message(text, true));
+				}
 			}
 		});
 
@@ -153,7 +227,8 @@ impl Scheduler {
 				let mut tasks = 0u32;
 				let mut left = 0;
 				let mut progress = (0, 0);
-				for task in running.read().values() {
+				for task in running.write().values_mut().filter(|t| !t.is_failed()) {
+					task.rate.sample(task.done);
 					tasks += 1;
 					left += task.found.saturating_sub(task.processed);
 					progress = (progress.0 + task.done, progress.1 + task.todo);
@@ -179,26 +254,68 @@ impl Scheduler {
 
 	pub(super) fn cancel(&self, id: usize) -> bool {
 		let mut running = self.running.write();
-		let b = running.all.remove(&id).is_some();
+		let state = match running.get(id) {
+			Some(task) if task.is_failed() => TaskState::Failed,
+			Some(_) => TaskState::Canceled,
+			None => return false,
+		};
+
+		// Abort the ongoing work, and let the paused one run into the cancellation
+		let task = running.remove(id, state).unwrap();
+		task.cancel.set(true);
+		task.pause.set(false);
+		self.file.unstash(id, false);
 
 		if let Some(hook) = running.hooks.remove(&id) {
 			self.todo.send_blocking(hook(true)).ok();
 		}
-		b
+		true
+	}
+
+	pub(super) fn pause(&self, id: usize, state: Option<bool>) -> bool {
+		let Some(pause) = self.running.read().get(id).map(|t| t.pause.clone()) else {
+			return false;
+		};
+
+		let state = state.unwrap_or(!pause.get());
+		if !pause.set(state) {
+			return false;
+		}
+		if !state {
+			self.file.unstash(id, true);
+		}
+		true
+	}
+
+	// Moves a file task that hasn't started by `step` in line, or to the front of it if not given
+	pub(super) fn shift(&self, id: usize, step: Option<isize>) -> bool {
+		self.running.write().shift(id, step.unwrap_or(isize::MIN))
 	}
 
-	pub(super) fn file_cut(&self, from: Url, mut to: Url, force: bool) {
+	pub(super) fn file_cut(&self, from: Url, to: Url, opt: &PasteOpt) {
 		let mut running = self.running.write();
-		let id = running.add(format!("Cut {:?} to {:?}", from, to));
+		let id = running.add(TaskKind::File, format!("Cut {:?} to {:?}", from, to));
+
+		let t = running.get_mut(id).unwrap();
+		let opt = PasteOpt { follow: false, preserve: Preserve::default(), ..*opt };
+		let task = FileOpPaste::new(t, from.clone(), to, true, &opt);
+		t.bandwidth = Some(task.bandwidth.clone());
 
 		running.hooks.insert(id, {
-			let from = from.clone();
-			let running = self.running.clone();
+			let (dirs, moved) = (task.dirs.clone(), task.moved.clone().unwrap_or_default());
+			let (to, running) = (task.to.clone(), self.running.clone());
 
 			Box::new(move |canceled: bool| {
 				async move {
 					if !canceled {
-						File::remove_empty_dirs(&from).await;
+						let (dirs, moved) = (mem::take(&mut *dirs.lock()), mem::take(&mut *moved.lock()));
+						File::pasted(dirs, Preserve::default(), true).await;
+						File::moved(&from, moved).await;
+						// Read again, as a remote directory isn't watched
+						if from.is_sftp() || to.is_sftp() {
+							emit!(Refresh);
+						}
+						emit!(Landed(to));
 					}
 					running.write().try_remove(id, TaskStage::Hooked);
 				}
@@ -209,26 +326,44 @@ impl Scheduler {
 		let _ = self.todo.send_blocking({
 			let file = self.file.clone();
 			async move {
-				if !force {
-					to = unique_path(to).await;
-				}
-				file.paste(FileOpPaste { id, from, to, cut: true, follow: false, retry: 0 }).await.ok();
+				file.paste(task).await.ok();
 			}
 			.boxed()
 		});
 	}
 
-	pub(super) fn file_copy(&self, from: Url, mut to: Url, force: bool, follow: bool) {
-		let name = format!("Copy {:?} to {:?}", from, to);
-		let id = self.running.write().add(name);
+	pub(super) fn file_copy(&self, from: Url, to: Url, opt: &PasteOpt) {
+		let mut running = self.running.write();
+		let id = running.add(TaskKind::File, format!("Copy {:?} to {:?}", from, to));
+
+		let t = running.get_mut(id).unwrap();
+		let task = FileOpPaste::new(t, from, to, false, opt);
+		t.bandwidth = Some(task.bandwidth.clone());
+
+		running.hooks.insert(id, {
+			let (dirs, preserve) = (task.dirs.clone(), task.preserve);
+			let (to, running) = (task.to.clone(), self.running.clone());
+
+			Box::new(move |canceled: bool| {
+				async move {
+					if !canceled {
+						let dirs = mem::take(&mut *dirs.lock());
+						File::pasted(dirs, preserve, false).await;
+						if to.is_sftp() {
+							emit!(Refresh);
+						}
+						emit!(Landed(to));
+					}
+					running.write().try_remove(id, TaskStage::Hooked);
+				}
+				.boxed()
+			})
+		});
 
 		let _ = self.todo.send_blocking({
 			let file = self.file.clone();
 			async move {
-				if !force {
-					to = unique_path(to).await;
-				}
-				file.paste(FileOpPaste { id, from, to, cut: false, follow, retry: 0 }).await.ok();
+				file.paste(task).await.ok();
 			}
 			.boxed()
 		});
@@ -236,7 +371,7 @@ impl Scheduler {
 
 	pub(super) fn file_delete(&self, target: Url) {
 		let mut running = self.running.write();
-		let id = running.add(format!("Delete {:?}", target));
+		let id = running.add(TaskKind::File, format!("Delete {:?}", target));
 
 		running.hooks.insert(id, {
 			let target = target.clone();
@@ -245,7 +380,12 @@ impl Scheduler {
 			Box::new(move |canceled: bool| {
 				async move {
 					if !canceled {
-						fs::remove_dir_all(target).await.ok();
+						if target.is_sftp() {
+							sftp::remove_empty_dirs(&target).await;
+							emit!(Refresh);
+						} else {
+							fs::remove_dir_all(target).await.ok();
+						}
 					}
 					running.write().try_remove(id, TaskStage::Hooked);
 				}
@@ -262,19 +402,196 @@ impl Scheduler {
 		});
 	}
 
-	pub(super) fn file_trash(&self, target: Url) {
+	pub(super) fn file_trash(&self, target: Url, batch: SystemTime) {
 		let name = format!("Trash {:?}", target);
-		let id = self.running.write().add(name);
+		let id = self.running.write().add(TaskKind::File, name);
+
+		let _ = self.todo.send_blocking({
+			let file = self.file.clone();
+			async move {
+				file.trash(FileOpTrash { id, target, length: 0, batch }).await.ok();
+			}
+			.boxed()
+		});
+	}
+
+	pub(super) fn file_link(
+		&self,
+		targets: Vec<(Url, Url)>,
+		hard: bool,
+		relative: bool,
+		force: bool,
+	) {
+		let name = format!("{} {} files", if hard { "Hardlink" } else { "Symlink" }, targets.len());
+		let id = self.running.write().add(TaskKind::File, name);
+
+		let _ = self.todo.send_blocking({
+			let file = self.file.clone();
+			async move {
+				file.make_link(FileOpMakeLink { id, targets, hard, relative, force }).await.ok();
+			}
+			.boxed()
+		});
+	}
+
+	pub(super) fn file_create(&self, cwd: Url, target: Url, dir: bool) {
+		let name = format!("Create {:?}", target);
+		let id = self.running.write().add(TaskKind::File, name);
+
+		let _ = self.todo.send_blocking({
+			let file = self.file.clone();
+			async move {
+				if let Ok(Some(hovered)) = file.create(FileOpCreate { id, cwd, target, dir }).await {
+					if !MANAGER.follow_results {
+						emit!(Refresh);
+					} else if let Ok(file) = files::File::from(hovered).await {
+						emit!(Hover(file));
+						emit!(Refresh);
+					}
+				}
+			}
+			.boxed()
+		});
+	}
+
+	pub(super) fn file_touch(&self, targets: Vec<Url>, time: SystemTime, atime: bool) {
+		let name = format!("Touch {} files", targets.len());
+		let id = self.running.write().add(TaskKind::File, name);
+
+		let _ = self.todo.send_blocking({
+			let file = self.file.clone();
+			async move {
+				file.touch(FileOpTouch { id, targets, time, atime }).await.ok();
+				// The watcher doesn't notice the times changing
+				emit!(Refresh);
+			}
+			.boxed()
+		});
+	}
+
+	#[cfg(unix)]
+	pub(super) fn file_chmod(&self, targets: Vec<Url>, mode: ModeSpec, recursive: bool) {
+		let name = format!("Chmod {} files", targets.len());
+		let id = self.running.write().add(TaskKind::File, name);
+
+		let _ = self.todo.send_blocking({
+			let file = self.file.clone();
+			async move {
+				file.chmod(FileOpChmod { id, targets, mode, recursive }).await.ok();
+			}
+			.boxed()
+		});
+	}
+
+	#[cfg(unix)]
+	pub(super) fn file_chown(&self, targets: Vec<Url>, owner: OwnerSpec, recursive: bool) {
+		let name = format!("Chown {} files", targets.len());
+		let id = self.running.write().add(TaskKind::File, name);
+
+		let _ = self.todo.send_blocking({
+			let file = self.file.clone();
+			async move {
+				file.chown(FileOpChown { id, targets, owner, recursive }).await.ok();
+			}
+			.boxed()
+		});
+	}
+
+	pub(super) fn file_rename(&self, targets: Vec<(Url, Url)>) {
+		let name = format!("Rename {} files", targets.len());
+		let id = self.running.write().add(TaskKind::File, name);
+
+		let _ = self.todo.send_blocking({
+			let file = self.file.clone();
+			async move {
+				file.rename(FileOpRename { id, targets }).await.ok();
+			}
+			.boxed()
+		});
+	}
+
+	pub(super) fn file_relink(&self, targets: Vec<(Url, Url)>) {
+		let name = format!("Hardlink {} files", targets.len());
+		let id = self.running.write().add(TaskKind::File, name);
+
+		let _ = self.todo.send_blocking({
+			let file = self.file.clone();
+			async move {
+				file.relink(FileOpRelink { id, targets }).await.ok();
+			}
+			.boxed()
+		});
+	}
+
+	// The sets of the duplicate files under `root` with their sizes once found, none if canceled
+	pub(super) fn file_dedupe(
+		&self,
+		root: Url,
+		hidden: bool,
+	) -> oneshot::Receiver<Vec<(u64, Vec<Url>)>> {
+		let mut running = self.running.write();
+		let id = running.add(TaskKind::File, format!("Find the duplicates in {:?}", root));
+		let cancel = running.get(id).unwrap().cancel.clone();
+
+		let (tx, rx) = oneshot::channel();
+		let _ = self.todo.send_blocking({
+			let file = self.file.clone();
+			async move {
+				if let Ok(sets) = file.dedupe(FileOpDedupe { id, root, hidden, cancel }).await {
+					tx.send(sets).ok();
+				}
+			}
+			.boxed()
+		});
+		rx
+	}
+
+	pub(super) fn file_archive(&self, opt: ArchiveOpt) {
+		let name = format!("Archive {} files into {:?}", opt.targets.len(), opt.output);
+		let mut running = self.running.write();
+		let id = running.add(TaskKind::File, name);
+		let cancel = running.get(id).unwrap().cancel.clone();
+
+		let _ = self.todo.send_blocking({
+			let file = self.file.clone();
+			async move {
+				file.archive(FileOpArchive { id, opt, cancel }).await.ok();
+			}
+			.boxed()
+		});
+	}
+
+	pub(super) fn file_extract(&self, opt: ExtractOpt) {
+		let name = format!("Extract {:?} into {:?}", opt.archive, opt.to);
+		let mut running = self.running.write();
+		let id = running.add(TaskKind::File, name);
+		let cancel = running.get(id).unwrap().cancel.clone();
+
+		let _ = self.todo.send_blocking({
+			let file = self.file.clone();
+			async move {
+				file.extract(FileOpExtract { id, opt, cancel }).await.ok();
+			}
+			.boxed()
+		});
+	}
+
+	pub(super) fn file_restore(&self, targets: Vec<FileTrashed>) {
+		let name = format!("Restore {} files", targets.len());
+		let id = self.running.write().add(TaskKind::File, name);
 
 		let _ = self.todo.send_blocking({
 			let file = self.file.clone();
 			async move {
-				file.trash(FileOpTrash { id, target, length: 0 }).await.ok();
+				file.restore(FileOpRestore { id, targets }).await.ok();
 			}
 			.boxed()
 		});
 	}
 
+	#[inline]
+	pub(super) fn last_trashed(&self) -> Vec<FileTrashed> { self.file.last_trashed() }
+
 	pub(super) fn process_open(&self, opener: &Opener, args: &[impl AsRef<OsStr>]) {
 		let name = {
 			let s = format!("Execute `{}`", opener.exec);
@@ -283,7 +600,7 @@ impl Scheduler {
 		};
 
 		let mut running = self.running.write();
-		let id = running.add(name);
+		let id = running.add(TaskKind::Process, name);
 
 		let (cancel_tx, mut cancel_rx) = oneshot::channel();
 		running.hooks.insert(id, {
@@ -318,34 +635,81 @@ impl Scheduler {
 		});
 	}
 
-	pub(super) fn precache_size(&self, targets: Vec<&Url>) {
+	pub(super) fn precache_size(&self, targets: Vec<&Url>, hidden: bool) {
 		let throttle = Arc::new(Throttle::new(targets.len(), Duration::from_millis(300)));
+		let partial = Arc::new(SizeBatch::default());
 		let mut handing = self.precache.size_handing.lock();
 		let mut running = self.running.write();
 
 		for target in targets {
-			if !handing.contains(target) {
-				handing.insert(target.clone());
-			} else {
+			if handing.contains_key(target) {
 				continue;
 			}
 
-			let id = running.add(format!("Calculate the size of {:?}", target));
+			let id = running.add(TaskKind::Precache, format!("Calculate the size of {:?}", target));
+			handing.insert(target.clone(), id);
+			self.precache.size_partial.lock().remove(target);
+
 			let _ = self.todo.send_blocking({
 				let precache = self.precache.clone();
 				let target = target.clone();
-				let throttle = throttle.clone();
+				let follow = MANAGER.sort_size_follow;
+				let cancel = running.get(id).unwrap().cancel.clone();
+				let (throttle, partial) = (throttle.clone(), partial.clone());
 				async move {
-					precache.size(PrecacheOpSize { id, target, throttle }).await.ok();
+					let op = PrecacheOpSize { id, target, hidden, follow, cancel, throttle, partial };
+					precache.size(op).await.ok();
 				}
 				.boxed()
 			});
 		}
 	}
 
+	// Cancels the size calculations of the directories outside of `cwd`
+	pub(super) fn precache_size_cancel(&self, cwd: &Url) {
+		let stale: Vec<_> = self
+			.precache
+			.size_handing
+			.lock()
+			.iter()
+			.filter(|(target, _)| target.parent_url().as_ref() != Some(cwd))
+			.map(|(_, &id)| id)
+			.collect();
+
+		for id in stale {
+			self.cancel(id);
+		}
+	}
+
+	pub(super) fn precache_children(&self, targets: Vec<Url>) {
+		let name = format!("Count the children of {} directories", targets.len());
+		let id = self.running.write().add(TaskKind::Precache, name);
+
+		let _ = self.todo.send_blocking({
+			let precache = self.precache.clone();
+			async move {
+				precache.children(PrecacheOpChildren { id, targets }).await.ok();
+			}
+			.boxed()
+		});
+	}
+
+	pub(super) fn precache_ranks(&self, root: Url, cwd: Url, targets: Vec<Url>) {
+		let name = format!("Sort {} files of {} externally", targets.len(), cwd.display());
+		let id = self.running.write().add(TaskKind::Precache, name);
+
+		let _ = self.todo.send_blocking({
+			let precache = self.precache.clone();
+			async move {
+				precache.ranks(PrecacheOpRanks { id, root, cwd, targets }).await.ok();
+			}
+			.boxed()
+		});
+	}
+
 	pub(super) fn precache_mime(&self, targets: Vec<Url>) {
 		let name = format!("Preload mimetype for {} files", targets.len());
-		let id = self.running.write().add(name);
+		let id = self.running.write().add(TaskKind::Precache, name);
 
 		let _ = self.todo.send_blocking({
 			let precache = self.precache.clone();
@@ -358,21 +722,21 @@ impl Scheduler {
 
 	pub(super) fn precache_image(&self, targets: Vec<Url>) {
 		let name = format!("Precache of {} image files", targets.len());
-		let id = self.running.write().add(name);
+		let id = self.running.write().add(TaskKind::Precache, name);
 
 		self.precache.image(id, targets).ok();
 	}
 
 	pub(super) fn precache_video(&self, targets: Vec<Url>) {
 		let name = format!("Precache of {} video files", targets.len());
-		let id = self.running.write().add(name);
+		let id = self.running.write().add(TaskKind::Precache, name);
 
 		self.precache.video(id, targets).ok();
 	}
 
 	pub(super) fn precache_pdf(&self, targets: Vec<Url>) {
 		let name = format!("Precache of {} PDF files", targets.len());
-		let id = self.running.write().add(name);
+		let id = self.running.write().add(TaskKind::Precache, name);
 
 		self.precache.pdf(id, targets).ok();
 	}
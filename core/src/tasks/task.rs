@@ -1,61 +1,129 @@
+use std::{sync::Arc, time::Duration};
+
+use shared::{Bandwidth, Switch};
 use tokio::sync::mpsc;
 
+use super::{Rate, TaskLogs};
+
 #[derive(Debug)]
 pub struct Task {
 	pub id:    usize,
+	pub kind:  TaskKind,
 	pub name:  String,
 	pub stage: TaskStage,
 
 	pub found:     u32,
 	pub processed: u32,
+	pub failed:    u32,
 
 	pub todo: u64,
 	pub done: u64,
+	pub rate: Rate,
+
+	pub verified: u32,
+	pub hashed:   u64,
+
+	pub bandwidth: Option<Arc<Bandwidth>>,
+	pub pause:     Arc<Switch>,
+	pub cancel:    Arc<Switch>,
 
-	pub logs:   String,
-	pub logger: Option<mpsc::UnboundedSender<String>>,
+	pub logs:     TaskLogs,
+	pub logger:   Option<mpsc::UnboundedSender<String>>,
+	// Whether it's been told of in the status bar, once it failed
+	pub surfaced: bool,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TaskSummary {
-	pub name: String,
+	pub name:  String,
+	pub state: TaskState,
 
 	pub found:     u32,
 	pub processed: u32,
+	pub failed:    u32,
 
-	pub todo: u64,
-	pub done: u64,
+	pub todo:    u64,
+	pub done:    u64,
+	pub percent: Option<u8>,
+	pub speed:   u64,
+	pub eta:     Option<Duration>,
+
+	pub verified: u32,
+	pub hashed:   u64,
 }
 
 impl Task {
-	pub fn new(id: usize, name: String) -> Self {
+	pub fn new(id: usize, kind: TaskKind, name: String) -> Self {
 		Self {
 			id,
+			kind,
 			name,
 			stage: Default::default(),
 
 			found: 0,
 			processed: 0,
+			failed: 0,
 
 			todo: 0,
 			done: 0,
+			rate: Default::default(),
+
+			verified: 0,
+			hashed: 0,
+
+			bandwidth: None,
+			pause: Default::default(),
+			cancel: Default::default(),
 
 			logs: Default::default(),
 			logger: Default::default(),
+			surfaced: false,
+		}
+	}
+
+	// The percentage of the bytes done, unknown until all of its files are found
+	pub fn percent(&self) -> Option<u8> {
+		if self.stage < TaskStage::Dispatched || self.todo == 0 {
+			return None;
 		}
+		Some(100.min(self.done * 100 / self.todo) as u8)
+	}
+
+	// Whether all of its files are done, and some of them failed
+	#[inline]
+	pub fn is_failed(&self) -> bool {
+		self.failed > 0
+			&& self.stage >= TaskStage::Dispatched
+			&& self.processed + self.failed >= self.found
 	}
 }
 
 impl From<&Task> for TaskSummary {
 	fn from(task: &Task) -> Self {
+		let state = if task.is_failed() {
+			TaskState::Failed
+		} else if task.pause.get() {
+			TaskState::Paused
+		} else {
+			TaskState::Running
+		};
+
 		TaskSummary {
 			name: task.name.clone(),
+			state,
 
 			found:     task.found,
 			processed: task.processed,
+			failed:    task.failed,
+
+			todo:    task.todo,
+			done:    task.done,
+			percent: task.percent(),
+			speed:   task.rate.speed(),
+			eta:     task.rate.eta(task.todo.saturating_sub(task.done)),
 
-			todo: task.todo,
-			done: task.done,
+			verified: task.verified,
+			hashed:   task.hashed,
 		}
 	}
 }
@@ -68,10 +136,28 @@ pub enum TaskOp {
 	Log(usize, String),
 	// task_id, processed, size
 	Adv(usize, u32, u64),
+	// task_id, line
+	Fail(usize, String),
+	// task_id, verified, hashed
+	Verify(usize, u32, u64),
 	// task_id
 	Done(usize),
 }
 
+impl TaskOp {
+	#[inline]
+	pub fn id(&self) -> usize {
+		match *self {
+			Self::New(id, ..)
+			| Self::Log(id, ..)
+			| Self::Adv(id, ..)
+			| Self::Fail(id, ..)
+			| Self::Verify(id, ..)
+			| Self::Done(id) => id,
+		}
+	}
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
 pub enum TaskStage {
 	#[default]
@@ -79,3 +165,20 @@ pub enum TaskStage {
 	Dispatched,
 	Hooked,
 }
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TaskKind {
+	File,
+	Process,
+	Precache,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TaskState {
+	Queued,
+	Running,
+	Paused,
+	Failed,
+	Completed,
+	Canceled,
+}
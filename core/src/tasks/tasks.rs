@@ -1,29 +1,34 @@
-use std::{collections::{BTreeMap, HashMap, HashSet}, ffi::OsStr, io::{stdout, Write}, path::Path, sync::Arc};
+use std::{collections::{BTreeMap, HashMap, HashSet}, ffi::OsStr, io::{stdout, Write}, path::Path, sync::Arc, time::SystemTime};
 
-use config::{manager::SortBy, open::Opener, OPEN};
+use config::{keymap::Exec, manager::SortBy, open::Opener, MANAGER, OPEN, PREVIEW, TASKS};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
-use shared::{Defer, MimeKind, Term, Url};
-use tokio::{io::{stdin, AsyncReadExt}, select, sync::mpsc, time};
-use tracing::trace;
+use shared::{max_common_root, readable_size, short_path, Defer, MimeKind, Term, Url};
+use tokio::{fs, io::{stdin, AsyncReadExt}, select, sync::mpsc, time};
 
-use super::{task::TaskSummary, Scheduler, TASKS_PADDING, TASKS_PERCENT};
-use crate::{emit, files::{File, Files}, input::InputOpt, Event, BLOCKER};
+use super::{ask, shortfall, Conflicts, Impact, PasteOpt, Plan, Preferred, Replace, Scheduler, TaskKind, TaskState, TaskSummary, TaskViewer, Thresholds, touch_time, TASKS_PADDING, TASKS_PERCENT};
+#[cfg(unix)]
+use super::{ModeSpec, OwnerSpec};
+use crate::{emit, external::{archive_list, archive_stem, archive_test, is_safe_entry, is_safe_link, ArchiveFormat, ArchiveOpt, ExtractOpt, Extractor, Listed}, files::{File, Files}, input::InputOpt, select::SelectOpt, Event, BLOCKER};
 
 pub struct Tasks {
 	scheduler: Arc<Scheduler>,
+	preferred: Preferred,
 
 	pub visible:  bool,
 	pub cursor:   usize,
 	pub progress: (u8, u32),
+	pub viewer:   Option<TaskViewer>,
 }
 
 impl Tasks {
 	pub fn start() -> Self {
 		Self {
 			scheduler: Arc::new(Scheduler::start()),
+			preferred: Preferred::load(),
 			visible:   false,
 			cursor:    0,
 			progress:  (100, 0),
+			viewer:    None,
 		}
 	}
 
@@ -34,13 +39,33 @@ impl Tasks {
 
 	pub fn toggle(&mut self) -> bool {
 		self.visible = !self.visible;
+		self.viewer = None;
 		emit!(Peek); // Show/hide preview for images
 		true
 	}
 
+	// Goes back from the output of a task to the list, or hides the task manager
+	pub fn close(&mut self) -> bool {
+		if self.viewer.take().is_some() {
+			return true;
+		}
+		self.toggle()
+	}
+
+	// Scrolls the output of the task being read, or moves the cursor
+	pub fn arrow(&mut self, step: isize) -> bool {
+		if let Some(viewer) = &mut self.viewer {
+			viewer.scroll(step, Self::limit())
+		} else if step > 0 {
+			self.next()
+		} else {
+			self.prev()
+		}
+	}
+
 	#[allow(clippy::should_implement_trait)]
 	pub fn next(&mut self) -> bool {
-		let limit = Self::limit().min(self.len());
+		let limit = Self::limit().min(self.len() + self.scheduler.running.read().history.len());
 
 		let old = self.cursor;
 		self.cursor = limit.saturating_sub(1).min(self.cursor + 1);
@@ -54,14 +79,25 @@ impl Tasks {
 		old != self.cursor
 	}
 
+	// The running tasks, followed by the latest finished ones
 	pub fn paginate(&self) -> Vec<TaskSummary> {
 		let running = self.scheduler.running.read();
-		running.values().take(Self::limit()).map(Into::into).collect()
+		running
+			.values()
+			.map(|t| match TaskSummary::from(t) {
+				s if s.state == TaskState::Running && running.is_pending(t.id) => {
+					TaskSummary { state: TaskState::Queued, ..s }
+				}
+				s => s,
+			})
+			.chain(running.history.iter().map(|(t, _)| t.clone()))
+			.take(Self::limit())
+			.collect()
 	}
 
-	pub fn inspect(&self) -> bool {
+	pub fn inspect(&mut self) -> bool {
 		let Some(id) = self.scheduler.running.read().get_id(self.cursor) else {
-			return false;
+			return self.inspect_finished(self.cursor - self.len());
 		};
 
 		let scheduler = self.scheduler.clone();
@@ -74,7 +110,7 @@ impl Tasks {
 				let Some(task) = running.get_mut(id) else { return };
 
 				task.logger = Some(tx);
-				task.logs.clone()
+				task.logs.to_string()
 			};
 
 			emit!(Stop(true)).await;
@@ -120,6 +156,24 @@ impl Tasks {
 		false
 	}
 
+	// Reads the logs of a finished task in place of the list, e.g. the output of a shell command
+	// run in the background, scrolled to the end of it
+	fn inspect_finished(&mut self, idx: usize) -> bool {
+		let running = self.scheduler.running.read();
+		let Some((summary, logs)) = running.history.get(idx) else {
+			return false;
+		};
+
+		let lines = logs.lines();
+		let title = format!("{} ({} lines)", summary.name, lines.len());
+		let mut viewer = TaskViewer { title, lines, offset: 0 };
+		viewer.scroll(isize::MAX, Self::limit());
+
+		drop(running);
+		self.viewer = Some(viewer);
+		true
+	}
+
 	pub fn cancel(&mut self) -> bool {
 		let id = self.scheduler.running.read().get_id(self.cursor);
 		if id.map(|id| self.scheduler.cancel(id)) != Some(true) {
@@ -131,10 +185,81 @@ impl Tasks {
 		true
 	}
 
+	// Moves the task under the cursor by `step` among the ones that haven't started, or to
+	// the front of them so it's the next to, with the cursor along with it
+	pub fn shift(&mut self, step: Option<isize>) -> bool {
+		let Some(id) = self.scheduler.running.read().get_id(self.cursor) else {
+			return false;
+		};
+		if !self.scheduler.shift(id, step) {
+			return false;
+		}
+
+		let running = self.scheduler.running.read();
+		self.cursor = running.values().position(|t| t.id == id).unwrap_or(self.cursor);
+		true
+	}
+
+	// Sets the number of the work running at the same time, in total or of a category.
+	// A `+` or `-` prefix of `value` changes it relatively
+	pub fn concurrency(&self, kind: Option<TaskKind>, value: &str) -> bool {
+		let limiter = self.scheduler.limits.get(kind);
+		let old = limiter.limit();
+
+		let new = match value.as_bytes().first() {
+			Some(b'+') => value[1..].parse().map(|n: usize| old.saturating_add(n)),
+			Some(b'-') => value[1..].parse().map(|n: usize| old.saturating_sub(n)),
+			_ => value.parse(),
+		};
+		if let Ok(new) = new.map(|n| n.max(1)) {
+			limiter.set(new);
+			return new != old;
+		}
+		false
+	}
+
+	#[inline]
+	pub fn concurrencies(&self) -> [usize; 3] {
+		let limits = &self.scheduler.limits;
+		[limits.total.limit(), limits.file.limit(), limits.precache.limit()]
+	}
+
+	pub fn pause(&self, state: Option<bool>) -> bool {
+		let id = self.scheduler.running.read().get_id(self.cursor);
+		id.is_some_and(|id| self.scheduler.pause(id, state))
+	}
+
+	pub fn throttle(&self, limit: Option<u64>) -> bool {
+		let bandwidth = {
+			let running = self.scheduler.running.read();
+			running.get_id(self.cursor).and_then(|id| running.get(id)?.bandwidth.clone())
+		};
+		let Some(bandwidth) = bandwidth else {
+			return false;
+		};
+
+		if let Some(limit) = limit {
+			bandwidth.set_limit(limit);
+			return false;
+		}
+
+		tokio::spawn(async move {
+			let mut result = emit!(Input(
+				InputOpt::top("Limit (bytes per second, 0 for unlimited):")
+					.with_value(bandwidth.limit().to_string())
+			));
+			if let Some(Ok(Ok(limit))) = result.recv().await.map(|r| r.map(|s| s.parse())) {
+				bandwidth.set_limit(limit);
+			}
+		});
+		false
+	}
+
 	pub fn file_open(&self, targets: &[(impl AsRef<Path>, impl AsRef<str>)]) -> bool {
 		let mut openers = BTreeMap::new();
 		for (path, mime) in targets {
-			if let Some(opener) = OPEN.openers(path, mime).and_then(|o| o.first()) {
+			let opener = self.preferred.get(mime.as_ref());
+			if let Some(opener) = opener.or_else(|| OPEN.openers(path, mime).and_then(|o| o.first())) {
 				openers.entry(opener).or_insert_with(Vec::new).push(path.as_ref().as_os_str());
 			}
 		}
@@ -144,6 +269,14 @@ impl Tasks {
 		false
 	}
 
+	// Opens the files of `mime` with `opener` from now on
+	pub fn file_open_prefer(&mut self, mime: String, opener: Opener) -> bool {
+		if self.preferred.set(mime, opener) {
+			self.preferred.save();
+		}
+		false
+	}
+
 	pub fn file_open_with(&self, opener: &Opener, args: &[impl AsRef<OsStr>]) -> bool {
 		if opener.spread {
 			self.scheduler.process_open(opener, args);
@@ -155,72 +288,638 @@ impl Tasks {
 		false
 	}
 
-	pub fn file_cut(&self, src: &HashSet<Url>, dest: Url, force: bool) -> bool {
-		for p in src {
-			let to = dest.join(p.file_name().unwrap());
-			if force && p == &to {
-				trace!("file_cut: same file, skipping {:?}", to);
-			} else {
-				self.scheduler.file_cut(p.clone(), to, force);
+	#[inline]
+	pub fn file_cut(&self, src: &HashSet<Url>, dest: Url, opt: PasteOpt) -> bool {
+		self.file_paste(src, dest, true, opt)
+	}
+
+	#[inline]
+	pub fn file_copy(&self, src: &HashSet<Url>, dest: Url, opt: PasteOpt) -> bool {
+		self.file_paste(src, dest, false, opt)
+	}
+
+	fn file_paste(&self, src: &HashSet<Url>, dest: Url, cut: bool, opt: PasteOpt) -> bool {
+		let src = src.clone();
+		let scheduler = self.scheduler.clone();
+		tokio::spawn(async move {
+			// The dry run has a review of its own
+			if !opt.dry_run {
+				let th = Thresholds::new();
+				let mut impact = Impact::measure(&src, &th).await;
+				impact.outside = (!dest.is_sftp() && th.outside(&dest)) || (cut && impact.outside);
+				if impact.exceeds(&th) && !impact.confirm(if cut { "Move" } else { "Copy" }).await {
+					return;
+				}
 			}
-		}
+
+			// There's no telling the free space of a remote one
+			let remote = dest.is_sftp() || src.iter().any(|u| u.is_sftp());
+			if TASKS.confirm_space && !remote {
+				if let Some((bytes, free)) = shortfall(&src, &dest, cut).await {
+					let (bytes, free) = (readable_size(bytes), readable_size(free));
+					let title = format!("Over {bytes} to paste, only {free} free, go on? (y/N)");
+					if !ask(title).await {
+						return;
+					}
+				}
+			}
+
+			// The "apply to all" choice is kept for the rest of this paste
+			let mut conflicts = Conflicts::new(opt.conflict);
+
+			let plan = Plan::paste(src, &dest, cut, &mut conflicts).await;
+			if !opt.dry_run || plan.review().await {
+				plan.run(&scheduler, &opt);
+			}
+		});
 		false
 	}
 
-	pub fn file_copy(&self, src: &HashSet<Url>, dest: Url, force: bool, follow: bool) -> bool {
-		for p in src {
-			let to = dest.join(p.file_name().unwrap());
-			if force && p == &to {
-				trace!("file_copy: same file, skipping {:?}", to);
-			} else {
-				self.scheduler.file_copy(p.clone(), to, force, follow);
+	pub fn file_remove(&self, targets: Vec<Url>, permanently: bool, dry_run: bool) -> bool {
+		let permanently = permanently || !TASKS.trash;
+		if !permanently && targets.iter().any(|u| u.is_sftp()) {
+			emit!(Message("The remote files can't be trashed, delete them permanently".to_owned(), true));
+			return false;
+		}
+
+		let scheduler = self.scheduler.clone();
+		tokio::spawn(async move {
+			let plan = Plan::remove(targets, permanently);
+			let th = Thresholds::new();
+			let targets: Vec<_> = plan.steps.iter().map(|s| &s.from).collect();
+			let impact = Impact::measure(targets, &th).await;
+
+			// The permanent deletes of many files are typed out, even after the review of a dry run
+			let typed = permanently && impact.typed();
+			if dry_run {
+				if plan.review().await && (!typed || impact.confirm_typed().await) {
+					plan.run(&scheduler, &PasteOpt::default());
+				}
+				return;
 			}
+
+			// Below the thresholds only the permanent deletes are asked, and only if `confirm_delete`
+			if typed {
+				if !impact.confirm_typed().await {
+					return;
+				}
+			} else if (permanently && TASKS.confirm_delete) || impact.exceeds(&th) {
+				let action = if permanently { "Delete permanently" } else { "Move to trash" };
+				if !impact.confirm(action).await {
+					return;
+				}
+			}
+			plan.run(&scheduler, &PasteOpt::default());
+		});
+		false
+	}
+
+	#[inline]
+	pub fn last_trashed(&self) -> Vec<Url> {
+		self.scheduler.last_trashed().into_iter().map(|t| t.origin).collect()
+	}
+
+	pub fn file_restore(&self) -> bool {
+		let targets = self.scheduler.last_trashed();
+		if !targets.is_empty() {
+			self.scheduler.file_restore(targets);
 		}
 		false
 	}
 
-	pub fn file_remove(&self, targets: Vec<Url>, permanently: bool) -> bool {
+	// Creates hard or symbolic links to `src` in `dest`, an existing symlink is replaced
+	// by the symbolic one after a confirmation
+	pub fn file_link(&self, src: &HashSet<Url>, dest: Url, hard: bool, relative: bool) -> bool {
+		let src = src.clone();
 		let scheduler = self.scheduler.clone();
 		tokio::spawn(async move {
-			let s = if targets.len() > 1 { "s" } else { "" };
-			let mut result = emit!(Input(InputOpt::hovered(if permanently {
-				format!("Delete selected file{s} permanently? (y/N)")
-			} else {
-				format!("Move selected file{s} to trash? (y/N)")
-			})));
+			let targets: Vec<_> = src
+				.into_iter()
+				.filter_map(|p| p.file_name().map(|n| dest.join(n)).map(|to| (p, to)))
+				.collect();
+
+			let mut existing = 0;
+			for (_, to) in &targets {
+				if !hard && fs::symlink_metadata(to).await.is_ok_and(|m| m.is_symlink()) {
+					existing += 1;
+				}
+			}
+
+			let mut force = false;
+			if existing > 0 {
+				let s = if existing > 1 { "s" } else { "" };
+				let mut result =
+					emit!(Input(InputOpt::top(format!("Overwrite {existing} existing symlink{s}? (y/N)"))));
+				force = matches!(result.recv().await, Some(Ok(c)) if c == "y" || c == "Y");
+			}
+
+			if !targets.is_empty() {
+				scheduler.file_link(targets, hard, relative, force);
+			}
+		});
+		false
+	}
+
+	#[inline]
+	pub fn file_create(&self, cwd: &Url) -> bool {
+		let cwd = cwd.clone();
+		let scheduler = self.scheduler.clone();
+		tokio::spawn(async move {
+			let dir = cwd.clone();
+			let mut result = emit!(Input(
+				InputOpt::top("Create:").with_history("create").with_completion(&cwd).with_validator(
+					move |name| match name {
+						"" => Err("The name can't be empty".to_owned()),
+						_ if dir.join(name).exists() => Err(format!("`{name}` already exists")),
+						_ => Ok(()),
+					}
+				)
+			));
+			if let Some(Ok(name)) = result.recv().await {
+				if name.is_empty() {
+					return;
+				}
+
+				let target = cwd.join(&name);
+				scheduler.file_create(cwd, target, name.ends_with('/'));
+			}
+		});
+		false
+	}
+
+	// Touches the targets with the time of `spec`, see `touch_time`, or the one entered if `None`
+	pub fn file_touch(&self, targets: Vec<Url>, spec: Option<String>, atime: bool) -> bool {
+		if targets.is_empty() {
+			return false;
+		}
+
+		let (scheduler, prompted) = (self.scheduler.clone(), spec.is_none());
+		tokio::spawn(async move {
+			let title = "Touch (now, +1h, -2d, 2024-01-02 03:04):";
+			let Some(spec) = Self::spec(spec, title, "touch", |s| {
+				touch_time(s, SystemTime::now()).map(|_| ())
+			})
+			.await
+			else {
+				return;
+			};
+			if prompted {
+				emit!(Record(Exec::call("touch", vec![]).with("time", &spec).with_bool("atime", atime)));
+			}
+
+			match touch_time(&spec, SystemTime::now()) {
+				Ok(time) => scheduler.file_touch(targets, time, atime),
+				Err(e) => {
+					emit!(Message(e.to_string(), true));
+				}
+			}
+		});
+		false
+	}
+
+	// Changes the mode of the targets by `spec`, see `ModeSpec`, or by the one entered if `None`
+	#[cfg(unix)]
+	pub fn file_chmod(&self, targets: Vec<Url>, spec: Option<String>, recursive: bool) -> bool {
+		if targets.is_empty() {
+			return false;
+		}
+
+		let (scheduler, prompted) = (self.scheduler.clone(), spec.is_none());
+		tokio::spawn(async move {
+			let Some(spec) = Self::spec(spec, "Chmod (755, u+x, go-w):", "chmod", |s| {
+				ModeSpec::try_from(s).map(|_| ())
+			})
+			.await
+			else {
+				return;
+			};
+			if prompted {
+				emit!(Record(Exec::call("chmod", vec![spec.clone()]).with_bool("recursive", recursive)));
+			}
+
+			match ModeSpec::try_from(spec.as_str()) {
+				Ok(mode) => scheduler.file_chmod(targets, mode, recursive),
+				Err(e) => {
+					emit!(Message(e.to_string(), true));
+				}
+			}
+		});
+		false
+	}
+
+	// Changes the owner of the targets to `spec`, see `OwnerSpec`, or to the one entered if `None`
+	#[cfg(unix)]
+	pub fn file_chown(&self, targets: Vec<Url>, spec: Option<String>, recursive: bool) -> bool {
+		if targets.is_empty() {
+			return false;
+		}
+
+		let (scheduler, prompted) = (self.scheduler.clone(), spec.is_none());
+		tokio::spawn(async move {
+			let Some(spec) = Self::spec(spec, "Chown (user:group):", "chown", |s| {
+				OwnerSpec::try_from(s).map(|_| ())
+			})
+			.await
+			else {
+				return;
+			};
+			if prompted {
+				emit!(Record(Exec::call("chown", vec![spec.clone()]).with_bool("recursive", recursive)));
+			}
+
+			match OwnerSpec::try_from(spec.as_str()) {
+				Ok(owner) => scheduler.file_chown(targets, owner, recursive),
+				Err(e) => {
+					emit!(Message(e.to_string(), true));
+				}
+			}
+		});
+		false
+	}
+
+	// Renames the targets by replacing `find` in their names `with` the text, see `Replace`, both
+	// entered if `None`, after the new names are reviewed, and none of them if any one collides
+	pub fn file_replace(
+		&self,
+		targets: Vec<Url>,
+		find: Option<String>,
+		with: Option<String>,
+		regex: bool,
+	) -> bool {
+		if targets.is_empty() {
+			return false;
+		}
+
+		let (scheduler, prompted) = (self.scheduler.clone(), find.is_none() || with.is_none());
+		tokio::spawn(async move {
+			let title = if regex { "Find (regex):" } else { "Find:" };
+			let validate = move |s: &str| Replace::new(s, "", regex).map(|_| ());
+			let Some(find) = Self::spec(find, title, "replace", validate).await else {
+				return;
+			};
+			let title = if regex { "Replace with ($1, {n}):" } else { "Replace with ({n}):" };
+			let Some(with) = Self::spec(with, title, "replace", |_| Ok(())).await else {
+				return;
+			};
+			if prompted {
+				let exec = Exec::call("replace", vec![find.clone(), with.clone()]);
+				emit!(Record(exec.with_bool("regex", regex)));
+			}
 
-			if let Some(Ok(choice)) = result.recv().await {
-				if choice != "y" && choice != "Y" {
+			let replace = match Replace::new(&find, &with, regex) {
+				Ok(r) => r,
+				Err(e) => {
+					emit!(Message(e.to_string(), true));
 					return;
 				}
-				for p in targets {
-					if permanently {
-						scheduler.file_delete(p);
+			};
+			let old: Vec<_> =
+				targets.iter().map(|u| u.file_name().unwrap_or_default().to_string_lossy()).collect();
+			let new = replace.apply(old.iter().map(|s| s.as_ref()));
+
+			let root = max_common_root(&targets);
+			let (mut todo, mut invalid, mut unchanged) = (vec![], vec![], vec![]);
+			for (url, name) in targets.iter().zip(new) {
+				let rel = url.strip_prefix(&root).unwrap().to_owned();
+				if url.file_name() == Some(name.as_ref()) {
+					unchanged.push(rel);
+				} else if Path::new(&name).file_name() != Some(name.as_ref()) {
+					// Not a name but a path, or `.` or `..`
+					invalid.push((url, name));
+				} else {
+					todo.push((rel.clone(), rel.with_file_name(name)));
+				}
+			}
+			if todo.is_empty() && invalid.is_empty() {
+				emit!(Message("Nothing to rename, none of the names has a match".to_owned(), false));
+				return;
+			}
+
+			let conflicts = Plan::rename_conflicts(&root, &todo).await;
+			let plan = Plan::rename(
+				todo.into_iter().map(|(o, n)| (Url::from(root.join(o)), Url::from(root.join(n)))).collect(),
+			);
+
+			let blocked = !conflicts.is_empty() || !invalid.is_empty();
+			let first = if blocked {
+				format!("Nothing is renamed, {} of the names collide", conflicts.len() + invalid.len())
+			} else {
+				format!("Run it, {}, {} unchanged", plan.summary(), unchanged.len())
+			};
+			let items = [first]
+				.into_iter()
+				.chain(plan.steps.iter().map(|step| match &step.to {
+					Some(to) if conflicts.iter().any(|c| root.join(c) == **to) => {
+						format!("{step} (collides)")
+					}
+					_ => step.to_string(),
+				}))
+				.chain(invalid.iter().map(|(u, n)| format!("rename {} → {n} (invalid)", u.display())))
+				.chain(unchanged.iter().map(|u| format!("unchanged {}", root.join(u).display())))
+				.collect();
+
+			let choice = emit!(Select(SelectOpt::top("Replace:", items))).await;
+			if !blocked && choice.is_ok_and(|c| c == 0) {
+				let targets = plan.steps.into_iter().filter_map(|s| Some((s.from, s.to?)));
+				scheduler.file_rename(targets.collect());
+			}
+		});
+		false
+	}
+
+	// Archives the targets into `name` in `cwd`, asked for if `None`, in the format told by the
+	// extension of it, see `ArchiveFormat`, with the paths in it relative to `cwd`
+	pub fn file_archive(&self, cwd: Url, targets: Vec<Url>, name: Option<String>) -> bool {
+		if targets.is_empty() {
+			return false;
+		}
+
+		let scheduler = self.scheduler.clone();
+		tokio::spawn(async move {
+			let validate = {
+				let cwd = cwd.clone();
+				move |name: &str| {
+					if name.is_empty() {
+						Err("The name can't be empty".to_owned())
+					} else if ArchiveFormat::from_name(name).is_none() {
+						Err("The extension must be one of .zip, .tar.gz, .tar.zst and .7z".to_owned())
+					} else if cwd.join(name).symlink_metadata().is_ok() {
+						Err(format!("`{name}` already exists"))
+					} else {
+						Ok(())
+					}
+				}
+			};
+
+			let name = match name {
+				Some(name) => name,
+				None => {
+					let stem = match &targets[..] {
+						[target] => target.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+						_ => cwd.file_name().map_or("archive".into(), |n| n.to_string_lossy().into_owned()),
+					};
+					let opt = InputOpt::top("Archive (.zip, .tar.gz, .tar.zst, .7z):")
+						.with_value(format!("{stem}.zip"))
+						.with_selected(0..stem.chars().count())
+						.with_history("archive")
+						.with_validator(validate.clone());
+					match emit!(Input(opt)).recv().await {
+						Some(Ok(name)) => name,
+						_ => return,
+					}
+				}
+			};
+			if let Err(e) = validate(&name) {
+				emit!(Message(e, true));
+				return;
+			}
+
+			let targets = targets
+				.iter()
+				.map(|t| t.strip_prefix(&cwd).map_or_else(|| t.to_path_buf(), |p| p.to_owned()))
+				.collect();
+			let format = ArchiveFormat::from_name(&name).unwrap();
+			scheduler.file_archive(ArchiveOpt { output: cwd.join(&name), cwd, format, targets });
+		});
+		false
+	}
+
+	// Extracts the archives into `cwd`, or a directory named after each of them in it if `sub`,
+	// asking for the password of an encrypted one, and whether to overwrite the files there
+	pub fn file_extract(&self, cwd: Url, targets: Vec<Url>, sub: bool) -> bool {
+		let scheduler = self.scheduler.clone();
+		tokio::spawn(async move {
+			for archive in targets {
+				let name = archive.file_name().unwrap_or_default().to_string_lossy().into_owned();
+				let Some(by) = Extractor::of(&archive).await else {
+					emit!(Message(format!("`{name}` isn't an archive"), true));
+					continue;
+				};
+
+				let mut password: Option<String> = None;
+				let listed = loop {
+					match archive_list(&archive, by, password.as_deref()).await {
+						Ok(Listed::Entries { names, links, encrypted }) if !encrypted => {
+							break Some((names, links));
+						}
+						// Listed with any password, where only the data is encrypted, so it's tried out
+						Ok(Listed::Entries { names, links, .. }) if password.is_some() => {
+							match archive_test(&archive, password.as_deref().unwrap()).await {
+								Ok(true) => break Some((names, links)),
+								Ok(false) => {}
+								Err(e) => {
+									emit!(Message(format!("Failed to test `{name}`: {e}"), true));
+									break None;
+								}
+							}
+						}
+						Ok(_) => {}
+						Err(e) => {
+							emit!(Message(format!("Failed to list `{name}`: {e}"), true));
+							break None;
+						}
+					}
+
+					let title = if password.is_some() {
+						format!("Wrong password of `{name}`, again:")
 					} else {
-						scheduler.file_trash(p);
+						format!("Password of `{name}`:")
+					};
+					match emit!(Input(InputOpt::top(title).with_obscure())).recv().await {
+						Some(Ok(p)) => password = Some(p),
+						_ => break None,
 					}
+				};
+				let Some((names, links)) = listed else {
+					continue;
+				};
+
+				if let Some(entry) = names.iter().find(|n| !is_safe_entry(n)) {
+					let e = format!("Refused to extract `{name}`, `{entry}` in it escapes the directory");
+					emit!(Message(e, true));
+					continue;
 				}
+				// Refused as well where the target isn't told, e.g. of a zip, which keeps it as the data
+				let link = links.iter().find(|(l, to)| !to.as_ref().is_some_and(|to| is_safe_link(l, to)));
+				if let Some((link, to)) = link {
+					let e = match to {
+						Some(to) => format!("the symlink `{link}` in it to `{to}` escapes the directory"),
+						None => format!("the target of the symlink `{link}` in it is unknown"),
+					};
+					emit!(Message(format!("Refused to extract `{name}`, {e}"), true));
+					continue;
+				}
+
+				let to = if sub { cwd.join(archive_stem(&name)) } else { cwd.clone() };
+				let mut existing = 0;
+				for n in names.iter().filter(|n| !n.ends_with('/')) {
+					existing += fs::symlink_metadata(to.join(n)).await.is_ok() as usize;
+				}
+				if existing > 0 {
+					let s = if existing > 1 { "s" } else { "" };
+					let title = format!("Overwrite {existing} existing file{s} with `{name}`? (y/N)");
+					if !ask(title).await {
+						continue;
+					}
+				}
+
+				let entries = names.len();
+				scheduler.file_extract(ExtractOpt { archive, to, by, password, entries });
 			}
 		});
 		false
 	}
 
+	// Finds the duplicate files under `root` in a task, see `File::dedupe`, the sets of which are
+	// then listed to choose from, for one file of the set to be kept, and the rest of it trashed,
+	// deleted, or replaced with hardlinks of it, once the plan of that is reviewed
+	pub fn file_dedupe(&self, root: Url, hidden: bool) -> bool {
+		let scheduler = self.scheduler.clone();
+		tokio::spawn(async move {
+			let Ok(mut sets) = scheduler.file_dedupe(root.clone(), hidden).await else {
+				return;
+			};
+			if sets.is_empty() {
+				emit!(Message(format!("No duplicates in {}", root.display()), false));
+				return;
+			}
+
+			// The sets that waste the most space first
+			sets.sort_by_key(|(size, urls)| std::cmp::Reverse(size * (urls.len() as u64 - 1)));
+			while !sets.is_empty() {
+				let wasted = sets.iter().map(|(size, urls)| size * (urls.len() as u64 - 1)).sum();
+				let title = format!("Duplicates, {} to free:", readable_size(wasted));
+				let items = sets
+					.iter()
+					.map(|(size, urls)| {
+						let names: Vec<_> = urls.iter().map(|u| short_path(u, &root)).collect();
+						format!("{} × {}: {}", urls.len(), readable_size(*size), names.join(", "))
+					})
+					.collect();
+				let Ok(i) = emit!(Select(SelectOpt::top(&title, items))).await else {
+					break;
+				};
+
+				let urls = &sets[i].1;
+				let items = urls.iter().map(|u| short_path(u, &root)).collect();
+				let Ok(keep) = emit!(Select(SelectOpt::top("Keep:", items))).await else {
+					continue;
+				};
+
+				let items = ["Trash the rest", "Delete the rest permanently", "Hardlink the rest to it"];
+				let items = items.into_iter().map(|s| s.to_owned()).collect();
+				let Ok(action) = emit!(Select(SelectOpt::top("The rest:", items))).await else {
+					continue;
+				};
+
+				let rest = urls.iter().enumerate().filter(|&(j, _)| j != keep).map(|(_, u)| u.clone());
+				let plan = match action {
+					0 => Plan::remove(rest.collect(), !TASKS.trash),
+					1 => Plan::remove(rest.collect(), true),
+					_ => Plan::hardlink(rest.collect(), urls[keep].clone()),
+				};
+				if plan.review().await {
+					plan.run(&scheduler, &PasteOpt::default());
+					sets.remove(i);
+				}
+			}
+		});
+		false
+	}
+
+	// The spec given, or else the one entered in a prompt of `title`, checked by `validate`,
+	// `None` if the prompt is canceled
+	async fn spec(
+		spec: Option<String>,
+		title: &str,
+		history: &str,
+		validate: impl Fn(&str) -> anyhow::Result<()> + Send + 'static,
+	) -> Option<String> {
+		if spec.is_some() {
+			return spec;
+		}
+
+		let opt = InputOpt::top(title)
+			.with_history(history)
+			.with_validator(move |s| validate(s).map_err(|e| e.to_string()));
+		match emit!(Input(opt)).recv().await {
+			Some(Ok(s)) => Some(s),
+			_ => None,
+		}
+	}
+
+	pub fn file_rename(&self, targets: Vec<(Url, Url)>) -> bool {
+		if !targets.is_empty() {
+			self.scheduler.file_rename(targets);
+		}
+		false
+	}
+
 	#[inline]
 	pub fn precache_size(&self, targets: &Files) -> bool {
-		if targets.sorter().by != SortBy::Size {
+		if targets.sorter().by != SortBy::Size || !targets.sorter().size_recursive {
 			return false;
 		}
 
-		let targets: Vec<_> = targets
+		let partial = self.scheduler.precache.size_partial.lock();
+		let urls: Vec<_> = targets
 			.iter()
-			.filter(|f| f.is_dir() && targets.size(f.url()).is_none())
+			.filter(|f| f.is_dir() && !f.url().is_sftp())
+			.filter(|f| targets.size(f.url()).is_none() || partial.contains(f.url()))
 			.map(|f| f.url())
 			.collect();
+		drop(partial);
+
+		if !urls.is_empty() {
+			self.scheduler.precache_size(urls, targets.show_hidden());
+		}
+
+		false
+	}
+
+	#[inline]
+	pub fn precache_size_cancel(&self, cwd: &Url) { self.scheduler.precache_size_cancel(cwd) }
+
+	#[inline]
+	pub fn precache_children(&self, targets: &Files) -> bool {
+		if targets.sorter().by != SortBy::Children {
+			return false;
+		}
+
+		let targets: Vec<_> = targets
+			.iter()
+			.filter(|f| f.is_dir() && !f.url().is_sftp() && targets.children(f.url()).is_none())
+			.map(|f| f.url_owned())
+			.collect();
 
 		if !targets.is_empty() {
-			self.scheduler.precache_size(targets);
+			self.scheduler.precache_children(targets);
+		}
+		false
+	}
+
+	// Runs the `sort_command` on the files of each directory in view, where some aren't ranked yet,
+	// the ones of the expanded directories as well, for the ranks of all to go to the files
+	pub fn precache_ranks(&self, targets: &Files) -> bool {
+		if targets.sorter().by != SortBy::External || MANAGER.sort_command.is_empty() {
+			return false;
+		}
+		let Some(root) = targets.first().and_then(|f| f.url().parent_url()) else {
+			return false;
+		};
+
+		let mut dirs: BTreeMap<Url, Vec<Url>> = BTreeMap::new();
+		for f in targets.iter().filter(|f| !f.url().is_sftp()) {
+			if let Some(parent) = f.url().parent_url() {
+				dirs.entry(parent).or_default().push(f.url_owned());
+			}
 		}
 
+		for (dir, urls) in dirs {
+			if urls.iter().any(|u| targets.rank(u).is_none()) {
+				self.scheduler.precache_ranks(root.clone(), dir, urls);
+			}
+		}
 		false
 	}
 
@@ -241,7 +940,7 @@ impl Tasks {
 	pub fn precache_image(&self, mimetype: &BTreeMap<Url, String>) -> bool {
 		let targets: Vec<_> = mimetype
 			.iter()
-			.filter(|(_, m)| MimeKind::new(m) == MimeKind::Image)
+			.filter(|(u, m)| MimeKind::new(m) == MimeKind::Image && !u.is_sftp())
 			.map(|(u, _)| u.clone())
 			.collect();
 
@@ -254,7 +953,7 @@ impl Tasks {
 	pub fn precache_video(&self, mimetype: &BTreeMap<Url, String>) -> bool {
 		let targets: Vec<_> = mimetype
 			.iter()
-			.filter(|(_, m)| MimeKind::new(m) == MimeKind::Video)
+			.filter(|(u, m)| MimeKind::new(m) == MimeKind::Video && !u.is_sftp())
 			.map(|(u, _)| u.clone())
 			.collect();
 
@@ -265,9 +964,13 @@ impl Tasks {
 	}
 
 	pub fn precache_pdf(&self, mimetype: &BTreeMap<Url, String>) -> bool {
+		if PREVIEW.pdf_as_text {
+			return false;
+		}
+
 		let targets: Vec<_> = mimetype
 			.iter()
-			.filter(|(_, m)| MimeKind::new(m) == MimeKind::PDF)
+			.filter(|(u, m)| MimeKind::new(m) == MimeKind::PDF && !u.is_sftp())
 			.map(|(u, _)| u.clone())
 			.collect();
 
@@ -0,0 +1,87 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+
+const FORMATS: [&str; 4] =
+	["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"];
+
+// The time to touch the files with, `now` or empty for the current time, an offset from it such as
+// `+1h` or `-2d12h`, or an ISO 8601 date and time, in the local time zone unless it has an offset
+pub(super) fn touch_time(spec: &str, now: SystemTime) -> Result<SystemTime> {
+	let spec = spec.trim();
+	if spec.is_empty() || spec == "now" {
+		return Ok(now);
+	}
+
+	if let Some(rest) = spec.strip_prefix('+') {
+		return Ok(now + touch_offset(rest)?);
+	} else if let Some(rest) = spec.strip_prefix('-') {
+		return Ok(now - touch_offset(rest)?);
+	}
+
+	if let Ok(dt) = DateTime::parse_from_rfc3339(spec) {
+		return Ok(dt.into());
+	}
+	let naive = FORMATS
+		.iter()
+		.find_map(|f| NaiveDateTime::parse_from_str(spec, f).ok())
+		.or_else(|| NaiveDate::parse_from_str(spec, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0));
+	match naive.map(|n| Local.from_local_datetime(&n).earliest()) {
+		Some(Some(dt)) => Ok(dt.into()),
+		// Skipped over by a change of the daylight saving time
+		Some(None) => bail!("`{spec}` doesn't exist in the local time zone"),
+		None => bail!("invalid time `{spec}`, neither `now`, an offset like `+1h`, nor an ISO date"),
+	}
+}
+
+fn touch_offset(s: &str) -> Result<Duration> {
+	let (mut total, mut n) = (0u64, None::<u64>);
+	for c in s.chars() {
+		if let Some(d) = c.to_digit(10) {
+			n = Some(n.unwrap_or(0).saturating_mul(10).saturating_add(d as u64));
+			continue;
+		}
+
+		let unit = match c {
+			's' => 1,
+			'm' => 60,
+			'h' => 3600,
+			'd' => 86400,
+			'w' => 604800,
+			_ => bail!("invalid unit `{c}` of the offset, one of `s`, `m`, `h`, `d` and `w`"),
+		};
+		let Some(n) = n.take() else {
+			bail!("a number is missing before `{c}` in the offset")
+		};
+		total = total.saturating_add(n.saturating_mul(unit));
+	}
+
+	// The seconds can go without a unit at the end
+	Ok(Duration::from_secs(total.saturating_add(n.unwrap_or(0))))
+}
+
+#[test]
+fn test_touch_time() {
+	let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+	let t = |s: &str| touch_time(s, now).ok();
+
+	assert_eq!(t(""), Some(now));
+	assert_eq!(t("now"), Some(now));
+	assert_eq!(t("+1h"), Some(now + Duration::from_secs(3600)));
+	assert_eq!(t("-2d12h"), Some(now - Duration::from_secs(2 * 86400 + 12 * 3600)));
+	assert_eq!(t("+90"), Some(now + Duration::from_secs(90)));
+	assert_eq!(t("+1x"), None);
+	assert_eq!(t("+h"), None);
+
+	let utc = SystemTime::UNIX_EPOCH + Duration::from_secs(1_704_164_645);
+	assert_eq!(t("2024-01-02T03:04:05Z"), Some(utc));
+	assert_eq!(t("2024-01-02T05:04:05+02:00"), Some(utc));
+
+	let local = |s| Local.from_local_datetime(&NaiveDateTime::parse_from_str(s, FORMATS[1]).unwrap());
+	assert_eq!(t("2024-01-02 03:04:05"), Some(local("2024-01-02 03:04:05").unwrap().into()));
+	assert_eq!(t("2024-01-02T03:04"), Some(local("2024-01-02 03:04:00").unwrap().into()));
+	assert_eq!(t("2024-01-02"), Some(local("2024-01-02 00:00:00").unwrap().into()));
+	assert_eq!(t("2024-13-02"), None);
+	assert_eq!(t("yesterday"), None);
+}
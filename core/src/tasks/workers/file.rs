@@ -1,19 +1,30 @@
-use std::{collections::VecDeque, fs::Metadata, path::{Path, PathBuf}};
+use std::{collections::{BTreeMap, HashSet, VecDeque}, ffi::OsString, fs::{FileTimes, Metadata}, path::{Path, PathBuf}, sync::Arc, time::SystemTime};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use config::TASKS;
 use futures::{future::BoxFuture, FutureExt};
-use shared::{calculate_size, copy_with_progress, Url};
-use tokio::{fs, io::{self, ErrorKind::{AlreadyExists, NotFound}}, sync::mpsc};
+use parking_lot::Mutex;
+use shared::{calculate_size, copy_in_chunks, copy_with_progress, relative_path, unique_path, xattr_copy, Bandwidth, Switch, Url};
+use tokio::{fs, io::{self, AsyncBufReadExt, BufReader, ErrorKind::{self, AlreadyExists, NotFound}}, select, sync::mpsc};
 use tracing::trace;
 
-use crate::tasks::TaskOp;
+#[cfg(unix)]
+use crate::tasks::{ModeSpec, OwnerSpec};
+use crate::{external::{self, ArchiveOpt, ExtractOpt}, files::Meta, sftp::{self, Reader, Writer}, tasks::{dedupe_sizes, dedupe_split, Checksum, PasteOpt, Preserve, Queue, Task, TaskOp}};
 
 pub(crate) struct File {
 	rx: async_channel::Receiver<FileOp>,
 	tx: async_channel::Sender<FileOp>,
 
 	sch: mpsc::UnboundedSender<TaskOp>,
+
+	trashed: Mutex<Vec<FileTrashed>>,
+	stashed: Mutex<BTreeMap<usize, Vec<FileOp>>>,
+	queued:  Mutex<Queue<FileOp>>,
+
+	// Whether a move is taken for one across the filesystems, for the tests to copy it
+	#[cfg(all(test, unix))]
+	cross_device: std::sync::atomic::AtomicBool,
 }
 
 #[derive(Debug)]
@@ -24,14 +35,39 @@ pub(crate) enum FileOp {
 	Trash(FileOpTrash),
 }
 
+impl FileOp {
+	#[inline]
+	fn id(&self) -> usize {
+		match self {
+			Self::Paste(t) => t.id,
+			Self::Link(t) => t.id,
+			Self::Delete(t) => t.id,
+			Self::Trash(t) => t.id,
+		}
+	}
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct FileOpPaste {
-	pub id:     usize,
-	pub from:   Url,
-	pub to:     Url,
-	pub cut:    bool,
-	pub follow: bool,
-	pub retry:  u8,
+	pub id:       usize,
+	pub from:     Url,
+	pub to:       Url,
+	pub cut:      bool,
+	pub follow:   bool,
+	pub verify:   Option<Checksum>,
+	pub retry:    u8,
+	pub reverify: bool,
+	pub reflink:  bool,
+	pub preserve: Preserve,
+	// The directories created, to be given the attributes of their sources at the end,
+	// see `File::pasted`
+	pub dirs:     Arc<Mutex<Vec<(Url, Url)>>>,
+	// The sources copied for a move, removed only once all of them are, see `File::moved`
+	pub moved:    Option<Arc<Mutex<Vec<Url>>>>,
+
+	pub bandwidth: Arc<Bandwidth>,
+	pub pause:     Arc<Switch>,
+	pub cancel:    Arc<Switch>,
 }
 
 #[derive(Clone, Debug)]
@@ -39,7 +75,7 @@ pub(crate) struct FileOpLink {
 	pub id:     usize,
 	pub from:   Url,
 	pub to:     Url,
-	pub cut:    bool,
+	pub moved:  Option<Arc<Mutex<Vec<Url>>>>,
 	pub length: u64,
 }
 
@@ -50,43 +86,190 @@ pub(crate) struct FileOpDelete {
 	pub length: u64,
 }
 
+#[derive(Clone, Debug)]
+pub(crate) struct FileOpMakeLink {
+	pub id:       usize,
+	pub targets:  Vec<(Url, Url)>,
+	pub hard:     bool,
+	pub relative: bool,
+	pub force:    bool,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct FileOpCreate {
+	pub id:     usize,
+	pub cwd:    Url,
+	pub target: Url,
+	pub dir:    bool,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct FileOpTouch {
+	pub id:      usize,
+	pub targets: Vec<Url>,
+	pub time:    SystemTime,
+	pub atime:   bool,
+}
+
+#[cfg(unix)]
+#[derive(Clone, Debug)]
+pub(crate) struct FileOpChmod {
+	pub id:        usize,
+	pub targets:   Vec<Url>,
+	pub mode:      ModeSpec,
+	pub recursive: bool,
+}
+
+#[cfg(unix)]
+#[derive(Clone, Debug)]
+pub(crate) struct FileOpChown {
+	pub id:        usize,
+	pub targets:   Vec<Url>,
+	pub owner:     OwnerSpec,
+	pub recursive: bool,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct FileOpRename {
+	pub id:      usize,
+	pub targets: Vec<(Url, Url)>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct FileOpRelink {
+	pub id:      usize,
+	pub targets: Vec<(Url, Url)>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct FileOpDedupe {
+	pub id:     usize,
+	pub root:   Url,
+	pub hidden: bool,
+	pub cancel: Arc<Switch>,
+}
+
+pub(crate) struct FileOpArchive {
+	pub id:     usize,
+	pub opt:    ArchiveOpt,
+	pub cancel: Arc<Switch>,
+}
+
+pub(crate) struct FileOpExtract {
+	pub id:     usize,
+	pub opt:    ExtractOpt,
+	pub cancel: Arc<Switch>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct FileOpRestore {
+	pub id:      usize,
+	pub targets: Vec<FileTrashed>,
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct FileOpTrash {
 	pub id:     usize,
 	pub target: Url,
 	pub length: u64,
+	pub batch:  SystemTime,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct FileTrashed {
+	pub origin: Url,
+	pub batch:  SystemTime,
 }
 
 impl File {
 	pub(crate) fn new(sch: mpsc::UnboundedSender<TaskOp>) -> Self {
 		let (tx, rx) = async_channel::unbounded();
-		Self { tx, rx, sch }
+		Self {
+			tx,
+			rx,
+			sch,
+			trashed: Default::default(),
+			stashed: Default::default(),
+			queued: Default::default(),
+			#[cfg(all(test, unix))]
+			cross_device: Default::default(),
+		}
 	}
 
-	#[inline]
-	pub(crate) async fn recv(&self) -> Result<(usize, FileOp)> {
-		Ok(match self.rx.recv().await? {
-			FileOp::Paste(t) => (t.id, FileOp::Paste(t)),
-			FileOp::Link(t) => (t.id, FileOp::Link(t)),
-			FileOp::Delete(t) => (t.id, FileOp::Delete(t)),
-			FileOp::Trash(t) => (t.id, FileOp::Trash(t)),
-		})
+	// Waits until there's any of the work to pick
+	pub(crate) async fn ready(&self) -> Result<()> {
+		if !self.queued.lock().is_empty() {
+			return Ok(());
+		}
+		let op = self.rx.recv().await?;
+		self.queued.lock().push(op.id(), op);
+		Ok(())
+	}
+
+	// The next of the work in line, with the ones that came in by now, see `Queue`
+	pub(crate) fn pick(&self, pending: &[usize]) -> Option<(usize, FileOp)> {
+		let mut queued = self.queued.lock();
+		while let Ok(op) = self.rx.try_recv() {
+			queued.push(op.id(), op);
+		}
+		queued.pop(pending)
 	}
 
 	pub(crate) async fn work(&self, op: &mut FileOp) -> Result<()> {
+		let result = self.work_do(op).await;
+		if let Err(e) = &result {
+			self.sch.send(TaskOp::Fail(op.id(), format!("Failed to work on {:?}: {e}", op)))?;
+		}
+		result
+	}
+
+	async fn work_do(&self, op: &mut FileOp) -> Result<()> {
 		match op {
+			FileOp::Paste(task) if task.from.is_sftp() || task.to.is_sftp() => {
+				self.transfer(task).await?;
+			}
 			FileOp::Paste(task) => {
 				match fs::remove_file(&task.to).await {
 					Err(e) if e.kind() != NotFound => Err(e)?,
 					_ => {}
 				}
 
-				let mut it = copy_with_progress(&task.from, &task.to);
-				while let Some(res) = it.recv().await {
+				// Copied at once by the system where nothing of it is to hold it back, as it's faster,
+				// and in chunks where it's limited, paused, or to be cloned
+				let mut it = if task.bandwidth.limit() == 0 && !task.pause.get() && !task.reflink {
+					copy_with_progress(&task.from, &task.to)
+				} else {
+					let (bandwidth, pause) = (task.bandwidth.clone(), task.pause.clone());
+					copy_in_chunks(&task.from, &task.to, bandwidth, pause, task.reflink)
+				};
+				loop {
+					let res = select! {
+						res = it.recv() => res,
+						_ = task.cancel.until(true) => {
+							// Wait for the copying to stop before cleaning up
+							it.close();
+							while it.recv().await.is_some() {}
+							if !TASKS.keep_partial {
+								fs::remove_file(&task.to).await.ok();
+							}
+							return self.log(task.id, format!("Paste task canceled: {:?}", task));
+						}
+					};
+
+					let Some(res) = res else { break };
 					match res {
 						Ok(0) => {
-							if task.cut {
-								fs::remove_file(&task.from).await.ok();
+							if let Some(checksum) = task.verify {
+								if !self.verify(task, checksum).await? {
+									return Ok(());
+								}
+							}
+							let owner = task.moved.is_some();
+							if let Err(e) = Self::preserve(&task.from, &task.to, task.preserve, owner).await {
+								self.log(task.id, format!("Paste task kept no attributes: {:?}, {e}", task))?;
+							}
+							if let Some(moved) = &task.moved {
+								moved.lock().push(task.from.clone());
 							}
 							break;
 						}
@@ -108,7 +291,11 @@ impl File {
 							task.retry += 1;
 							return Ok(self.tx.send(FileOp::Paste(task.clone())).await?);
 						}
-						Err(e) => Err(e)?,
+						Err(e) => {
+							// Not to leave a partial copy behind, the source is still there
+							fs::remove_file(&task.to).await.ok();
+							Err(e)?
+						}
 					}
 				}
 				self.sch.send(TaskOp::Adv(task.id, 1, 0))?;
@@ -137,11 +324,21 @@ impl File {
 					}
 				}
 
-				if task.cut {
-					fs::remove_file(&task.from).await.ok();
+				if let Some(moved) = &task.moved {
+					Self::preserve(&task.from, &task.to, Preserve::default(), true).await.ok();
+					moved.lock().push(task.from.clone());
 				}
 				self.sch.send(TaskOp::Adv(task.id, 1, task.length))?;
 			}
+			FileOp::Delete(task) if task.target.is_sftp() => {
+				if let Err(e) = sftp::remove_file(&task.target).await {
+					if !sftp::is_not_found(&e) {
+						self.log(task.id, format!("Delete task failed: {:?}, {e}", task))?;
+						Err(e)?
+					}
+				}
+				self.sch.send(TaskOp::Adv(task.id, 1, task.length))?
+			}
 			FileOp::Delete(task) => {
 				if let Err(e) = fs::remove_file(&task.target).await {
 					if e.kind() != NotFound && fs::symlink_metadata(&task.target).await.is_ok() {
@@ -159,28 +356,134 @@ impl File {
 					ctx.set_delete_method(DeleteMethod::NsFileManager);
 					ctx.delete(&task.target)?;
 				}
-				#[cfg(not(target_os = "macos"))]
+				#[cfg(target_os = "windows")]
 				{
 					trash::delete(&task.target)?;
 				}
+				#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+				if let Err(e) = trash::delete(&task.target) {
+					self.log(task.id, format!("Trash task falls back to the home trash: {:?}, {e}", task))?;
+					super::xdg_trash::trash(&task.target).await?;
+				}
+
+				self.trashed.lock().push(FileTrashed { origin: task.target.clone(), batch: task.batch });
 				self.sch.send(TaskOp::Adv(task.id, 1, task.length))?;
 			}
 		}
 		Ok(())
 	}
 
+	// Compares the hashes of the source and the copy, and retries the copy once if they differ
+	async fn verify(&self, task: &mut FileOpPaste, checksum: Checksum) -> Result<bool> {
+		let (a, b) = tokio::join!(checksum.digest(&task.from), checksum.digest(&task.to));
+		let ((a, a_len), (b, b_len)) = (a?, b?);
+
+		let matched = a == b;
+		self.sch.send(TaskOp::Verify(task.id, matched as u32, a_len + b_len))?;
+		if matched {
+			return Ok(true);
+		} else if !task.reverify {
+			self.log(task.id, format!("Paste task checksum mismatch, retry: {:?}", task))?;
+			task.reverify = true;
+			self.tx.send(FileOp::Paste(task.clone())).await?;
+			return Ok(false);
+		}
+
+		self.log(task.id, format!("Paste task failed: checksum mismatch: {:?}", task))?;
+		bail!("checksum mismatch between {:?} and {:?}", task.from, task.to)
+	}
+
+	// Copies the file to or from a remote host, in chunks of it read and written as they come, where
+	// the copy isn't read back for a checksum, nor made by reflinking
+	async fn transfer(&self, task: &mut FileOpPaste) -> Result<()> {
+		let mut reader = Reader::open(&task.from).await?;
+		let mut writer = Writer::create(&task.to).await?;
+
+		let copied = async {
+			while !task.cancel.get() {
+				task.pause.until(false).await;
+				let Some(chunk) = reader.next().await? else {
+					return Ok(true);
+				};
+
+				task.bandwidth.consume(chunk.len() as u64).await;
+				writer.write(&chunk).await?;
+				self.sch.send(TaskOp::Adv(task.id, 0, chunk.len() as u64))?;
+			}
+			Ok::<_, anyhow::Error>(false)
+		}
+		.await;
+
+		reader.close().await.ok();
+		let closed = writer.close().await;
+		match copied.and_then(|done| closed.map(|_| done)) {
+			Ok(true) => {}
+			Ok(false) => {
+				if !TASKS.keep_partial {
+					Self::remove_any(&task.to).await.ok();
+				}
+				return self.log(task.id, format!("Paste task canceled: {:?}", task));
+			}
+			Err(e) => {
+				// Not to leave a partial copy behind, the source is still there
+				Self::remove_any(&task.to).await.ok();
+				Err(e)?
+			}
+		}
+
+		if task.verify.is_some() {
+			self.log(task.id, format!("Paste task not verified, as it's remote: {:?}", task))?;
+		}
+		if let Err(e) = Self::preserve_any(&task.from, &task.to, task.preserve, false).await {
+			self.log(task.id, format!("Paste task kept no attributes: {:?}, {e}", task))?;
+		}
+		if let Some(moved) = &task.moved {
+			moved.lock().push(task.from.clone());
+		}
+		Ok(self.sch.send(TaskOp::Adv(task.id, 1, 0))?)
+	}
+
+	// Puts aside an operation of a paused task, until the task is resumed or canceled
+	#[inline]
+	pub(crate) fn stash(&self, id: usize, op: FileOp) {
+		self.stashed.lock().entry(id).or_default().push(op);
+	}
+
+	pub(crate) fn unstash(&self, id: usize, requeue: bool) {
+		let ops = self.stashed.lock().remove(&id).unwrap_or_default();
+		if requeue {
+			ops.into_iter().for_each(|op| _ = self.tx.try_send(op));
+		}
+	}
+
 	#[inline]
 	fn log(&self, id: usize, line: String) -> Result<()> { Ok(self.sch.send(TaskOp::Log(id, line))?) }
 
+	#[inline]
+	fn fail(&self, id: usize, line: String) -> Result<()> {
+		Ok(self.sch.send(TaskOp::Fail(id, line))?)
+	}
+
 	#[inline]
 	fn done(&self, id: usize) -> Result<()> { Ok(self.sch.send(TaskOp::Done(id))?) }
 
 	pub(crate) async fn paste(&self, mut task: FileOpPaste) -> Result<()> {
+		if task.from.is_sftp() || task.to.is_sftp() {
+			return self.paste_remote(task).await;
+		}
+
 		if task.cut {
-			match fs::rename(&task.from, &task.to).await {
+			match self.rename_path(&task.from, &task.to).await {
 				Ok(_) => return self.done(task.id),
 				Err(e) if e.kind() == NotFound => return self.done(task.id),
-				_ => {}
+				// Copied and then removed across the filesystems, or merged into the existing directory
+				Err(e) if Self::is_cross_device(&e) || fs::symlink_metadata(&task.to).await.is_ok() => {
+					self.log(task.id, format!("Paste task falls back to copying: {:?}, {e}", task))?;
+				}
+				Err(e) => {
+					self.fail(task.id, format!("Paste task failed to move: {:?}, {e}", task))?;
+					return self.done(task.id);
+				}
 			}
 		}
 
@@ -202,8 +505,13 @@ impl File {
 		let mut dirs = VecDeque::from([task.from]);
 
 		while let Some(src) = dirs.pop_front() {
+			if task.cancel.get() {
+				break;
+			}
+
 			let dest = root.join(src.components().skip(skip).collect::<PathBuf>());
 			match fs::create_dir(&dest).await {
+				Ok(_) => task.dirs.lock().push((src.clone(), dest.clone())),
 				Err(e) if e.kind() != AlreadyExists => {
 					self.log(task.id, format!("Create dir failed: {:?}, {e}", dest))?;
 					continue;
@@ -244,7 +552,90 @@ impl File {
 		self.done(task.id)
 	}
 
+	// Pastes to or from a remote host, or between two of them, where a move is a rename on the same
+	// host, else the files are copied one by one, following the symlinks to the files, as there's
+	// no making them on the other end, and leaving out the ones to the directories
+	async fn paste_remote(&self, mut task: FileOpPaste) -> Result<()> {
+		if task.cut && task.from.host() == task.to.host() {
+			match sftp::rename(&task.from, &task.to).await {
+				Ok(_) => return self.done(task.id),
+				Err(e) => self.log(task.id, format!("Paste task falls back to copying: {:?}, {e}", task))?,
+			}
+		}
+
+		// Done where the source is gone, and not where the parent of the destination is, as the
+		// rename failing tells either of them the same way
+		let meta = match Meta::read(&task.from, true).await {
+			Ok(meta) => meta,
+			Err(e) if task.cut && sftp::is_not_found(&e) => return self.done(task.id),
+			Err(e) => Err(e)?,
+		};
+		if !meta.is_dir() {
+			let id = task.id;
+			self.sch.send(TaskOp::New(id, meta.len()))?;
+			if meta.is_file() {
+				self.tx.send(FileOp::Paste(task)).await?;
+			}
+			return self.done(id);
+		}
+
+		let root = task.to.clone();
+		let skip = task.from.components().count();
+		let mut dirs = VecDeque::from([task.from.clone()]);
+
+		while let Some(src) = dirs.pop_front() {
+			if task.cancel.get() {
+				break;
+			}
+
+			let dest = root.join(src.components().skip(skip).collect::<PathBuf>());
+			match Self::create_dir_any(&dest).await {
+				Ok(true) => task.dirs.lock().push((src.clone(), dest.clone())),
+				Ok(false) => {}
+				Err(e) => {
+					self.log(task.id, format!("Create dir failed: {:?}, {e}", dest))?;
+					continue;
+				}
+			}
+
+			let entries = match Self::read_dir_any(&src).await {
+				Ok(entries) => entries,
+				Err(e) => {
+					self.log(task.id, format!("Read dir failed: {:?}, {e}", src))?;
+					continue;
+				}
+			};
+
+			for (src, meta) in entries {
+				if meta.is_symlink() {
+					match Meta::read(&src, true).await {
+						Ok(m) if m.is_file() => {}
+						_ => {
+							self.log(task.id, format!("Paste task left out the symlink: {:?}", src))?;
+							continue;
+						}
+					}
+				} else if meta.is_dir() {
+					dirs.push_back(src);
+					continue;
+				} else if !meta.is_file() {
+					continue;
+				}
+
+				task.to = dest.join(src.file_name().unwrap());
+				task.from = src;
+				self.sch.send(TaskOp::New(task.id, meta.len()))?;
+				self.tx.send(FileOp::Paste(task.clone())).await?;
+			}
+		}
+		self.done(task.id)
+	}
+
 	pub(crate) async fn delete(&self, mut task: FileOpDelete) -> Result<()> {
+		if task.target.is_sftp() {
+			return self.delete_remote(task).await;
+		}
+
 		let meta = fs::symlink_metadata(&task.target).await?;
 		if !meta.is_dir() {
 			let id = task.id;
@@ -281,6 +672,39 @@ impl File {
 		self.done(task.id)
 	}
 
+	// Deletes the remote files one by one, where the directories left empty are removed at the end,
+	// see `Scheduler::file_delete`
+	async fn delete_remote(&self, mut task: FileOpDelete) -> Result<()> {
+		let meta = sftp::metadata(&task.target, false).await?;
+		if !meta.is_dir() {
+			let id = task.id;
+			task.length = meta.len();
+			self.sch.send(TaskOp::New(id, meta.len()))?;
+			self.tx.send(FileOp::Delete(task)).await?;
+			return self.done(id);
+		}
+
+		let mut dirs = VecDeque::from([task.target.clone()]);
+		while let Some(target) = dirs.pop_front() {
+			let Ok(entries) = sftp::read_dir(&target).await else {
+				continue;
+			};
+
+			for (url, meta) in entries {
+				if meta.is_dir() {
+					dirs.push_front(url);
+					continue;
+				}
+
+				task.target = url;
+				task.length = meta.len();
+				self.sch.send(TaskOp::New(task.id, meta.len()))?;
+				self.tx.send(FileOp::Delete(task.clone())).await?;
+			}
+		}
+		self.done(task.id)
+	}
+
 	pub(crate) async fn trash(&self, mut task: FileOpTrash) -> Result<()> {
 		let id = task.id;
 		task.length = calculate_size(&task.target).await;
@@ -290,6 +714,589 @@ impl File {
 		self.done(id)
 	}
 
+	pub(crate) async fn make_link(&self, task: FileOpMakeLink) -> Result<()> {
+		let id = task.id;
+		for _ in &task.targets {
+			self.sch.send(TaskOp::New(id, 0))?;
+		}
+
+		for (from, to) in task.targets {
+			// Only an existing symlink can be replaced, and only by a symlink
+			match fs::symlink_metadata(&to).await {
+				Ok(m) if !task.hard && task.force && m.is_symlink() => {
+					if let Err(e) = fs::remove_file(&to).await {
+						self.fail(id, format!("Link failed: {:?} -> {:?}, {e}", from, to))?;
+						continue;
+					}
+				}
+				Ok(_) => {
+					self.fail(id, format!("Link failed: {:?} -> {:?}, it already exists", from, to))?;
+					continue;
+				}
+				Err(_) => {}
+			}
+
+			let result = if task.hard {
+				fs::hard_link(&from, &to).await
+			} else {
+				let src = match to.parent() {
+					Some(parent) if task.relative => relative_path(&from, parent),
+					_ => from.to_path_buf(),
+				};
+				Self::symlink(src, &to, from.is_dir()).await
+			};
+
+			match result {
+				Ok(_) => {
+					self.log(id, format!("Linked: {:?} -> {:?}", to, from))?;
+					self.sch.send(TaskOp::Adv(id, 1, 0))?;
+				}
+				Err(e) if task.hard && Self::is_cross_device(&e) => self.fail(
+					id,
+					format!("Hardlink failed: {:?} -> {:?}, they are on different filesystems", from, to),
+				)?,
+				Err(e) => self.fail(id, format!("Link failed: {:?} -> {:?}, {e}", from, to))?,
+			}
+		}
+		self.done(id)
+	}
+
+	// Creates the missing components of the target one by one, and returns the first component of
+	// it, i.e. the one shown in `cwd`, to be hovered, if all of them are created
+	pub(crate) async fn create(&self, task: FileOpCreate) -> Result<Option<Url>> {
+		let id = task.id;
+		self.sch.send(TaskOp::New(id, 0))?;
+
+		let mut missing = vec![];
+		for path in task.target.ancestors().take_while(|&p| p != task.cwd.as_path()) {
+			match fs::symlink_metadata(path).await {
+				Ok(_) if missing.is_empty() => {
+					self.fail(id, format!("Create failed: {:?}, it already exists", path))?;
+					return self.done(id).map(|_| None);
+				}
+				Ok(m) if m.is_dir() => break,
+				Ok(_) => {
+					self.fail(id, format!("Create failed: {:?}, it's not a directory", path))?;
+					return self.done(id).map(|_| None);
+				}
+				Err(e) if e.kind() == NotFound => missing.push(path.to_path_buf()),
+				Err(e) => {
+					self.fail(id, format!("Create failed: {:?}, {e}", path))?;
+					return self.done(id).map(|_| None);
+				}
+			}
+		}
+
+		let mut created = Vec::with_capacity(missing.len());
+		while let Some(path) = missing.pop() {
+			let result = if missing.is_empty() && !task.dir {
+				fs::OpenOptions::new().write(true).create_new(true).open(&path).await.map(|_| ())
+			} else {
+				fs::create_dir(&path).await
+			};
+
+			if let Err(e) = result {
+				self.fail(id, format!("Create failed: {:?}, {e}", path))?;
+				break;
+			}
+			created.push(path);
+		}
+
+		if !created.is_empty() {
+			self.log(id, format!("Created: {:?}", created))?;
+		}
+		if missing.is_empty() {
+			self.sch.send(TaskOp::Adv(id, 1, 0))?;
+		}
+
+		self.done(id)?;
+		if created.is_empty() || !missing.is_empty() {
+			return Ok(None);
+		}
+		let first = task.target.strip_prefix(&task.cwd).and_then(|p| p.components().next());
+		Ok(first.map(|c| task.cwd.join(c)))
+	}
+
+	// Sets the modification time of each of the targets, and the access time if `atime`,
+	// creating the missing ones as empty files
+	pub(crate) async fn touch(&self, task: FileOpTouch) -> Result<()> {
+		let id = task.id;
+		let mut times = FileTimes::new().set_modified(task.time);
+		if task.atime {
+			times = times.set_accessed(task.time);
+		}
+
+		for target in task.targets {
+			self.sch.send(TaskOp::New(id, 0))?;
+			let path = target.to_path_buf();
+			let touched = tokio::task::spawn_blocking(move || match std::fs::metadata(&path) {
+				// Read-only is enough for the owner, and it's the only way to open a directory
+				Ok(_) => std::fs::File::open(&path).and_then(|f| f.set_times(times)),
+				Err(e) if e.kind() == NotFound => std::fs::File::options()
+					.write(true)
+					.create_new(true)
+					.open(&path)
+					.and_then(|f| f.set_times(times)),
+				Err(e) => Err(e),
+			});
+
+			match touched.await.map_err(io::Error::from).and_then(|r| r) {
+				Ok(_) => {
+					self.log(id, format!("Touched: {:?}", target))?;
+					self.sch.send(TaskOp::Adv(id, 1, 0))?;
+				}
+				Err(e) => self.fail(id, format!("Touch failed: {:?}, {e}", target))?,
+			}
+		}
+		self.done(id)
+	}
+
+	#[cfg(unix)]
+	pub(crate) async fn chmod(&self, task: FileOpChmod) -> Result<()> {
+		use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+
+		let id = task.id;
+		for (target, meta) in self.expand(id, task.targets, task.recursive).await? {
+			// The symlinks have no mode of their own, only the files they point to
+			if meta.is_symlink() {
+				self.sch.send(TaskOp::Adv(id, 1, 0))?;
+				continue;
+			}
+
+			let mode = task.mode.apply(meta.permissions().mode(), meta.is_dir());
+			match fs::set_permissions(&target, Permissions::from_mode(mode)).await {
+				Ok(_) => {
+					self.log(id, format!("Chmod {mode:o}: {:?}", target))?;
+					self.sch.send(TaskOp::Adv(id, 1, 0))?;
+				}
+				Err(e) => self.fail(id, format!("Chmod failed: {:?}, {e}", target))?,
+			}
+		}
+		self.done(id)
+	}
+
+	#[cfg(unix)]
+	pub(crate) async fn chown(&self, task: FileOpChown) -> Result<()> {
+		use std::os::unix::fs::{chown, lchown};
+
+		let (id, OwnerSpec { uid, gid }) = (task.id, task.owner);
+		for (target, meta) in self.expand(id, task.targets, task.recursive).await? {
+			let result =
+				if meta.is_symlink() { lchown(&target, uid, gid) } else { chown(&target, uid, gid) };
+			match result {
+				Ok(_) => {
+					self.log(id, format!("Chown: {:?}", target))?;
+					self.sch.send(TaskOp::Adv(id, 1, 0))?;
+				}
+				Err(e) => self.fail(id, format!("Chown failed: {:?}, {e}", target))?,
+			}
+		}
+		self.done(id)
+	}
+
+	// The targets to change, following them if they're symlinks, and everything under the
+	// directories if `recursive`, where they aren't followed. Ordered for the entries in
+	// a directory to go before it, so that they're still reachable if it's made inaccessible
+	#[cfg(unix)]
+	async fn expand(
+		&self,
+		id: usize,
+		targets: Vec<Url>,
+		recursive: bool,
+	) -> Result<Vec<(Url, Metadata)>> {
+		let mut all = vec![];
+		for target in targets {
+			let meta = match fs::metadata(&target).await {
+				Ok(m) => m,
+				Err(e) => {
+					self.sch.send(TaskOp::New(id, 0))?;
+					self.fail(id, format!("Failed to read {:?}, {e}", target))?;
+					continue;
+				}
+			};
+
+			let mut dirs = VecDeque::new();
+			if recursive && meta.is_dir() {
+				dirs.push_back(target.clone());
+			}
+			all.push((target, meta));
+
+			while let Some(dir) = dirs.pop_front() {
+				let mut it = match fs::read_dir(&dir).await {
+					Ok(it) => it,
+					Err(e) => {
+						self.log(id, format!("Read dir failed: {:?}, {e}", dir))?;
+						continue;
+					}
+				};
+				while let Ok(Some(entry)) = it.next_entry().await {
+					let Ok(meta) = entry.metadata().await else { continue };
+					let url = Url::from(entry.path());
+					if meta.is_dir() {
+						dirs.push_back(url.clone());
+					}
+					all.push((url, meta));
+				}
+			}
+		}
+
+		for _ in &all {
+			self.sch.send(TaskOp::New(id, 0))?;
+		}
+		all.reverse();
+		Ok(all)
+	}
+
+	pub(crate) async fn rename(&self, task: FileOpRename) -> Result<()> {
+		let id = task.id;
+		for _ in &task.targets {
+			self.sch.send(TaskOp::New(id, 0))?;
+		}
+
+		// Files whose destination is taken by another file of the same batch, e.g. a swap,
+		// are moved to an intermediate name first, and to their destination at the end
+		let sources: HashSet<_> = task.targets.iter().map(|(from, _)| from.clone()).collect();
+		let (direct, chained): (Vec<_>, Vec<_>) =
+			task.targets.into_iter().partition(|(_, to)| !sources.contains(to));
+
+		let mut staged = Vec::with_capacity(chained.len());
+		for (from, to) in chained {
+			let mut name = OsString::from(".");
+			name.push(from.file_name().unwrap_or_default());
+			name.push(".rename");
+
+			let tmp = unique_path(from.with_file_name(name).into()).await;
+			match fs::rename(&from, &tmp).await {
+				Ok(_) => staged.push((from, tmp, to)),
+				Err(e) => self.fail(id, format!("Rename failed: {:?} -> {:?}, {e}", from, to))?,
+			}
+		}
+
+		for (from, to) in direct {
+			self.rename_do(id, &from, &from, &to).await?;
+		}
+		for (from, tmp, to) in staged {
+			if !self.rename_do(id, &from, &tmp, &to).await? {
+				fs::rename(&tmp, &from).await.ok();
+			}
+		}
+		self.done(id)
+	}
+
+	async fn rename_do(&self, id: usize, from: &Url, src: &Url, to: &Url) -> Result<bool> {
+		if let Err(e) = fs::rename(src, to).await {
+			self.fail(id, format!("Rename failed: {:?} -> {:?}, {e}", from, to))?;
+			return Ok(false);
+		}
+
+		self.log(id, format!("Renamed: {:?} -> {:?}", from, to))?;
+		self.sch.send(TaskOp::Adv(id, 1, 0))?;
+		Ok(true)
+	}
+
+	// Replaces each file of the second of the targets with a hardlink of the first, by way of
+	// a temporary name next to it, so that it's never missing if the linking fails
+	pub(crate) async fn relink(&self, task: FileOpRelink) -> Result<()> {
+		let id = task.id;
+		for _ in &task.targets {
+			self.sch.send(TaskOp::New(id, 0))?;
+		}
+
+		for (from, to) in task.targets {
+			let mut name = OsString::from(".");
+			name.push(to.file_name().unwrap_or_default());
+			name.push(".link");
+
+			let tmp = unique_path(to.with_file_name(name).into()).await;
+			let result = match fs::hard_link(&from, &tmp).await {
+				Ok(_) => fs::rename(&tmp, &to).await,
+				Err(e) => Err(e),
+			};
+
+			match result {
+				Ok(_) => {
+					self.log(id, format!("Hardlinked: {:?} -> {:?}", to, from))?;
+					self.sch.send(TaskOp::Adv(id, 1, 0))?;
+				}
+				Err(e) => {
+					fs::remove_file(&tmp).await.ok();
+					self.fail(id, format!("Hardlink failed: {:?} -> {:?}, {e}", to, from))?;
+				}
+			}
+		}
+		self.done(id)
+	}
+
+	// The sets of the duplicate files under the root, where each file of the same size as another,
+	// see `dedupe_sizes`, is hashed as one to be processed, for the bytes read to be the progress
+	pub(crate) async fn dedupe(&self, task: FileOpDedupe) -> Result<Vec<(u64, Vec<Url>)>> {
+		let id = task.id;
+		let (root, hidden, cancel) = (task.root.to_path_buf(), task.hidden, task.cancel.clone());
+		let sizes =
+			tokio::task::spawn_blocking(move || dedupe_sizes(&root, hidden, &cancel)).await?;
+		for (size, files) in &sizes {
+			for _ in files {
+				self.sch.send(TaskOp::New(id, *size))?;
+			}
+		}
+
+		let mut sets = vec![];
+		for (size, files) in sizes {
+			let mut hashes: BTreeMap<Vec<u8>, Vec<PathBuf>> = BTreeMap::new();
+			for file in files {
+				if task.cancel.get() {
+					bail!("dedupe task canceled: {:?}", task.root);
+				}
+				match Checksum::Xxhash.digest(&file).await {
+					Ok((hash, len)) => {
+						hashes.entry(hash).or_default().push(file);
+						self.sch.send(TaskOp::Adv(id, 1, len))?;
+					}
+					Err(e) => self.fail(id, format!("Failed to read {:?}, {e}", file))?,
+				}
+			}
+
+			for files in hashes.into_values().filter(|f| f.len() > 1) {
+				let split = tokio::task::spawn_blocking(move || dedupe_split(files)).await?;
+				sets.extend(split.into_iter().map(|s| (size, s.into_iter().map(Url::from).collect())));
+			}
+		}
+
+		self.done(id)?;
+		Ok(sets)
+	}
+
+	// Archives the targets with the tool of the format, where the bytes of the files it tells
+	// are added are the progress, and each line it writes to stderr is an entry that failed
+	pub(crate) async fn archive(&self, task: FileOpArchive) -> Result<()> {
+		let (id, ArchiveOpt { cwd, output, format, targets }) = (task.id, &task.opt);
+		let mut total = 0;
+		for target in targets {
+			total += calculate_size(&cwd.join(target)).await;
+		}
+		self.sch.send(TaskOp::New(id, total))?;
+
+		if fs::symlink_metadata(output).await.is_ok() {
+			self.fail(id, format!("Archive failed: {:?}, it already exists", output))?;
+			return self.done(id);
+		}
+		let mut child = match external::archive(&task.opt) {
+			Ok(child) => child,
+			Err(e) => {
+				self.fail(id, format!("Archive failed: {:?}, {e}", output))?;
+				return self.done(id);
+			}
+		};
+
+		let mut stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+		let mut stderr = BufReader::new(child.stderr.take().unwrap()).lines();
+		let (mut out_done, mut err_done) = (false, false);
+		while !out_done || !err_done {
+			select! {
+				_ = task.cancel.until(true) => {
+					child.start_kill().ok();
+					child.wait().await.ok();
+					fs::remove_file(output).await.ok();
+					return self.log(id, format!("Archive task canceled: {:?}", output));
+				}
+				line = stdout.next_line(), if !out_done => {
+					let Ok(Some(line)) = line else {
+						out_done = true;
+						continue;
+					};
+					let Some(entry) = format.entry(&line) else {
+						continue;
+					};
+
+					let meta = fs::symlink_metadata(cwd.join(entry)).await;
+					let size = meta.map_or(0, |m| if m.is_file() { m.len() } else { 0 });
+					self.log(id, format!("Added: {entry}"))?;
+					self.sch.send(TaskOp::Adv(id, 0, size))?;
+				}
+				line = stderr.next_line(), if !err_done => match line {
+					Ok(Some(line)) if line.trim().is_empty() => {}
+					Ok(Some(line)) => self.fail(id, format!("Archive: {}", line.trim()))?,
+					_ => err_done = true,
+				},
+			}
+		}
+
+		match child.wait().await {
+			Ok(status) if status.success() => {
+				self.log(id, format!("Archived: {:?}", output))?;
+				self.sch.send(TaskOp::Adv(id, 1, 0))?;
+			}
+			Ok(status) => {
+				let tool = format.tool();
+				self.fail(id, format!("Archive failed: {:?}, `{tool}` exited with {status}", output))?
+			}
+			Err(e) => self.fail(id, format!("Archive failed: {:?}, {e}", output))?,
+		}
+		self.done(id)
+	}
+
+	pub(crate) async fn extract(&self, task: FileOpExtract) -> Result<()> {
+		let (id, ExtractOpt { archive, to, by, entries, .. }) = (task.id, &task.opt);
+		for _ in 0..*entries {
+			self.sch.send(TaskOp::New(id, 0))?;
+		}
+
+		if let Err(e) = fs::create_dir_all(to).await {
+			self.fail(id, format!("Extract failed: {:?}, {e}", archive))?;
+			return self.done(id);
+		}
+		let mut child = match external::extract(&task.opt) {
+			Ok(child) => child,
+			Err(e) => {
+				self.fail(id, format!("Extract failed: {:?}, {e}", archive))?;
+				return self.done(id);
+			}
+		};
+
+		let mut stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+		let mut stderr = BufReader::new(child.stderr.take().unwrap()).lines();
+		let (mut out_done, mut err_done) = (false, false);
+		while !out_done || !err_done {
+			select! {
+				_ = task.cancel.until(true) => {
+					child.start_kill().ok();
+					child.wait().await.ok();
+					return self.log(id, format!("Extract task canceled: {:?}", archive));
+				}
+				line = stdout.next_line(), if !out_done => {
+					let Ok(Some(line)) = line else {
+						out_done = true;
+						continue;
+					};
+					match by.extracted(&line) {
+						Some((entry, true)) => self.log(id, format!("Extracted: {entry}"))?,
+						Some(_) => self.fail(id, format!("Extract: {}", line.trim()))?,
+						None => continue,
+					}
+					self.sch.send(TaskOp::Adv(id, 1, 0))?;
+				}
+				line = stderr.next_line(), if !err_done => match line {
+					Ok(Some(line)) if line.trim().is_empty() => {}
+					Ok(Some(line)) => self.fail(id, format!("Extract: {}", line.trim()))?,
+					_ => err_done = true,
+				},
+			}
+		}
+
+		match child.wait().await {
+			Ok(status) if status.success() => {
+				self.log(id, format!("Extracted: {:?} into {:?}", archive, to))?;
+			}
+			Ok(status) => {
+				let tool = by.tool();
+				self.fail(id, format!("Extract failed: {:?}, `{tool}` exited with {status}", archive))?
+			}
+			Err(e) => self.fail(id, format!("Extract failed: {:?}, {e}", archive))?,
+		}
+		self.done(id)
+	}
+
+	pub(crate) async fn restore(&self, task: FileOpRestore) -> Result<()> {
+		let id = task.id;
+		for _ in &task.targets {
+			self.sch.send(TaskOp::New(id, 0))?;
+		}
+
+		#[cfg(target_os = "macos")]
+		for target in &task.targets {
+			self.fail(id, format!("Restore failed: {:?}, not supported on macOS", target.origin))?;
+		}
+
+		#[cfg(not(target_os = "macos"))]
+		{
+			let items = match tokio::task::spawn_blocking(trash::os_limited::list).await? {
+				Ok(items) => items,
+				Err(e) => {
+					for target in &task.targets {
+						let line = format!("Restore failed: {:?}, cannot list the trash, {e}", target.origin);
+						self.fail(id, line)?;
+					}
+					return self.done(id);
+				}
+			};
+
+			for target in task.targets {
+				// Pick the newest entry of the same origin, that was trashed in this batch
+				let since = target.batch.duration_since(SystemTime::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+				let since = since as i64;
+				let Some(item) = items
+					.iter()
+					.filter(|i| i.time_deleted >= since && i.original_path() == *target.origin)
+					.max_by_key(|i| i.time_deleted)
+				else {
+					self.fail(id, format!("Restore failed: {:?}, not found in the trash", target.origin))?;
+					continue;
+				};
+
+				#[cfg(target_os = "windows")]
+				let result = trash::os_limited::restore_all([item.clone()])
+					.map(|_| item.original_path())
+					.map_err(anyhow::Error::from);
+				#[cfg(not(target_os = "windows"))]
+				let result = super::xdg_trash::restore(item).await;
+
+				match result {
+					Ok(to) => {
+						self.log(id, format!("Restored: {:?} -> {:?}", target.origin, to))?;
+						self.trashed.lock().retain(|t| t.batch != target.batch || t.origin != target.origin);
+						self.sch.send(TaskOp::Adv(id, 1, 0))?;
+					}
+					Err(e) => self.fail(id, format!("Restore failed: {:?}, {e}", target.origin))?,
+				}
+			}
+		}
+		self.done(id)
+	}
+
+	pub(crate) fn last_trashed(&self) -> Vec<FileTrashed> {
+		let trashed = self.trashed.lock();
+		let Some(batch) = trashed.iter().map(|t| t.batch).max() else {
+			return Vec::new();
+		};
+		trashed.iter().filter(|t| t.batch == batch).cloned().collect()
+	}
+
+	#[inline]
+	async fn symlink(src: PathBuf, to: &Path, dir: bool) -> io::Result<()> {
+		#[cfg(target_os = "windows")]
+		{
+			if dir { fs::symlink_dir(src, to).await } else { fs::symlink_file(src, to).await }
+		}
+		#[cfg(not(target_os = "windows"))]
+		{
+			_ = dir;
+			fs::symlink(src, to).await
+		}
+	}
+
+	// A rename, which fails as one across the filesystems where the tests tell it to
+	#[inline]
+	async fn rename_path(&self, from: &Path, to: &Path) -> io::Result<()> {
+		#[cfg(all(test, unix))]
+		if self.cross_device.load(std::sync::atomic::Ordering::Relaxed) {
+			return Err(io::Error::from_raw_os_error(libc::EXDEV));
+		}
+		fs::rename(from, to).await
+	}
+
+	#[inline]
+	fn is_cross_device(e: &io::Error) -> bool {
+		// EXDEV on Unix, and ERROR_NOT_SAME_DEVICE on Windows
+		#[cfg(target_os = "windows")]
+		{
+			e.raw_os_error() == Some(17)
+		}
+		#[cfg(not(target_os = "windows"))]
+		{
+			e.raw_os_error() == Some(libc::EXDEV)
+		}
+	}
+
 	async fn metadata(path: &Path, follow: bool) -> io::Result<Metadata> {
 		if !follow {
 			return fs::symlink_metadata(path).await;
@@ -299,6 +1306,143 @@ impl File {
 		if meta.is_ok() { meta } else { fs::symlink_metadata(path).await }
 	}
 
+	// Gives `to` the metadata of `from` that's to be kept, and the owner for the moves, where it's
+	// permitted to. The mode goes last, for a read-only file or directory can't be changed after
+	async fn preserve(from: &Path, to: &Path, preserve: Preserve, owner: bool) -> io::Result<()> {
+		let meta = fs::symlink_metadata(from).await?;
+		#[cfg(unix)]
+		if owner {
+			use std::os::unix::fs::{lchown, MetadataExt};
+
+			// Only root can give a file away, or a user change the group to one they aren't in
+			match lchown(to, Some(meta.uid()), Some(meta.gid())) {
+				Err(e) if e.kind() != ErrorKind::PermissionDenied => return Err(e),
+				_ => {}
+			}
+		}
+		if preserve.xattrs {
+			xattr_copy(from, to);
+		}
+		if meta.is_symlink() {
+			return Ok(());
+		}
+
+		if preserve.times && (cfg!(unix) || !meta.is_dir()) {
+			let times = FileTimes::new().set_accessed(meta.accessed()?).set_modified(meta.modified()?);
+			Self::set_times(to, times).await?;
+		}
+		// Also for the moves, since changing the owner clears the setuid and setgid bits
+		if preserve.mode {
+			fs::set_permissions(to, meta.permissions()).await?;
+		}
+		Ok(())
+	}
+
+	// As `preserve`, where either of them may be a remote one, which only the mode and the times are
+	// kept across, as the owner and the extended attributes are of no use on another host
+	async fn preserve_any(from: &Url, to: &Url, preserve: Preserve, owner: bool) -> Result<()> {
+		if !from.is_sftp() && !to.is_sftp() {
+			return Ok(Self::preserve(from, to, preserve, owner).await?);
+		}
+
+		let meta = Meta::read(from, false).await?;
+		if meta.is_symlink() {
+			return Ok(());
+		} else if to.is_sftp() {
+			return sftp::set_meta(to, &meta, preserve.mode, preserve.times).await;
+		}
+
+		if let (true, Some(atime), Some(mtime)) = (preserve.times, meta.accessed(), meta.modified()) {
+			if cfg!(unix) || !meta.is_dir() {
+				Self::set_times(to, FileTimes::new().set_accessed(atime).set_modified(mtime)).await?;
+			}
+		}
+		#[cfg(unix)]
+		if preserve.mode {
+			use std::os::unix::fs::PermissionsExt;
+			fs::set_permissions(to, std::fs::Permissions::from_mode(meta.mode() & 0o7777)).await?;
+		}
+		Ok(())
+	}
+
+	// Sets the times of the copy, which has the mode of its source already, so it's opened without
+	// being written to, as a read-only one can't be, where that's enough for the owner
+	async fn set_times(path: &Path, times: FileTimes) -> io::Result<()> {
+		let path = path.to_owned();
+		tokio::task::spawn_blocking(move || {
+			#[cfg(not(target_os = "windows"))]
+			let file = std::fs::File::open(path)?;
+			#[cfg(target_os = "windows")]
+			let file = {
+				use std::os::windows::fs::OpenOptionsExt;
+				// `FILE_WRITE_ATTRIBUTES`, which the read-only attribute doesn't stop
+				std::fs::File::options().access_mode(0x100).open(path)?
+			};
+			file.set_times(times)
+		})
+		.await?
+	}
+
+	// Creates the local or the remote directory, telling whether it's new rather than there already
+	async fn create_dir_any(url: &Url) -> Result<bool> {
+		if !url.is_sftp() {
+			return match fs::create_dir(url).await {
+				Ok(_) => Ok(true),
+				Err(e) if e.kind() == AlreadyExists => Ok(false),
+				Err(e) => Err(e)?,
+			};
+		}
+
+		match sftp::create_dir(url).await {
+			Ok(_) => Ok(true),
+			Err(_) if sftp::metadata(url, true).await.is_ok_and(|m| m.is_dir()) => Ok(false),
+			Err(e) => Err(e),
+		}
+	}
+
+	// The entries of the local or the remote directory, with their own metadata
+	async fn read_dir_any(url: &Url) -> Result<Vec<(Url, Meta)>> {
+		if url.is_sftp() {
+			return sftp::read_dir(url).await;
+		}
+
+		let mut it = fs::read_dir(url).await?;
+		let mut entries = vec![];
+		while let Ok(Some(entry)) = it.next_entry().await {
+			if let Ok(meta) = fs::symlink_metadata(entry.path()).await {
+				entries.push((Url::from(entry.path()), meta.into()));
+			}
+		}
+		Ok(entries)
+	}
+
+	#[inline]
+	async fn remove_any(url: &Url) -> Result<()> {
+		if url.is_sftp() { sftp::remove_file(url).await } else { Ok(fs::remove_file(url).await?) }
+	}
+
+	// Finishes a paste, by giving the directories created the metadata of their sources, only now
+	// that all the files in them are done, which a read-only directory wouldn't have let in
+	pub(crate) async fn pasted(dirs: Vec<(Url, Url)>, preserve: Preserve, owner: bool) {
+		for (from, to) in dirs.into_iter().rev() {
+			Self::preserve_any(&from, &to, preserve, owner).await.ok();
+		}
+	}
+
+	// Finishes a move that fell back to copying, by removing the sources of the copies. It's run by
+	// the hook of the task, which isn't run once any of them failed, see `Running::try_remove`, so
+	// that a failure leaves all the originals as they were
+	pub(crate) async fn moved(from: &Url, moved: Vec<Url>) {
+		for src in moved {
+			Self::remove_any(&src).await.ok();
+		}
+		if from.is_sftp() {
+			sftp::remove_empty_dirs(from).await;
+		} else {
+			Self::remove_empty_dirs(from).await;
+		}
+	}
+
 	pub(crate) fn remove_empty_dirs(dir: &Path) -> BoxFuture<()> {
 		async move {
 			let mut it = match fs::read_dir(dir).await {
@@ -321,7 +1465,398 @@ impl File {
 }
 
 impl FileOpPaste {
+	pub(crate) fn new(task: &Task, from: Url, to: Url, cut: bool, opt: &PasteOpt) -> Self {
+		Self {
+			id: task.id,
+			from,
+			to,
+			cut,
+			follow: opt.follow,
+			verify: opt.verify,
+			retry: 0,
+			reverify: false,
+			reflink: opt.reflink,
+			preserve: opt.preserve,
+			dirs: Default::default(),
+			moved: cut.then(Default::default),
+
+			bandwidth: Arc::new(Bandwidth::new(opt.limit)),
+			pause: task.pause.clone(),
+			cancel: task.cancel.clone(),
+		}
+	}
+
 	fn to_link(&self, length: u64) -> FileOpLink {
-		FileOpLink { id: self.id, from: self.from.clone(), to: self.to.clone(), cut: self.cut, length }
+		let (from, to, moved) = (self.from.clone(), self.to.clone(), self.moved.clone());
+		FileOpLink { id: self.id, from, to, moved, length }
+	}
+}
+
+// The failed and the advanced of the operations sent to the scheduler so far
+#[cfg(test)]
+fn tally(rx: &mut mpsc::UnboundedReceiver<TaskOp>) -> (usize, usize) {
+	let (mut fails, mut advs) = (0, 0);
+	while let Ok(op) = rx.try_recv() {
+		fails += matches!(op, TaskOp::Fail(..)) as usize;
+		advs += matches!(op, TaskOp::Adv(..)) as usize;
 	}
+	(fails, advs)
+}
+
+#[test]
+fn test_rename_swap() {
+	let dir = shared::TestDir::new("rename");
+	for name in ["a", "b", "c"] {
+		std::fs::write(dir.join(name), name).unwrap();
+	}
+
+	let (tx, _rx) = mpsc::unbounded_channel();
+	let url = |name: &str| Url::from(dir.join(name));
+	let targets = vec![(url("a"), url("b")), (url("b"), url("a")), (url("c"), url("d"))];
+	shared::block_on(File::new(tx).rename(FileOpRename { id: 0, targets })).unwrap();
+
+	assert_eq!(std::fs::read_to_string(dir.join("a")).unwrap(), "b");
+	assert_eq!(std::fs::read_to_string(dir.join("b")).unwrap(), "a");
+	assert_eq!(std::fs::read_to_string(dir.join("d")).unwrap(), "c");
+	assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+}
+
+#[test]
+fn test_create() {
+	let dir = shared::TestDir::new("create");
+	std::fs::create_dir_all(dir.join("a")).unwrap();
+	std::fs::write(dir.join("f"), "").unwrap();
+
+	let (tx, mut rx) = mpsc::unbounded_channel();
+	let file = File::new(tx);
+	let create = |target: &str, is_dir: bool| {
+		let (cwd, target) = (dir.to_path_buf().into(), dir.join(target).into());
+		shared::block_on(file.create(FileOpCreate { id: 0, cwd, target, dir: is_dir })).unwrap()
+	};
+
+	// Only the missing components are created, and the one of them in `cwd` is returned
+	assert_eq!(create("a/b/c/d.rs", false), Some(Url::from(dir.join("a"))));
+	assert!(dir.join("a/b/c/d.rs").is_file());
+	assert_eq!(create("e/f/", true), Some(Url::from(dir.join("e"))));
+	assert!(dir.join("e/f").is_dir());
+
+	// Never clobbers
+	assert_eq!(create("a/b/c/d.rs", false), None);
+	assert_eq!(create("f/g", false), None);
+
+	assert_eq!(tally(&mut rx).0, 2);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_make_link() {
+	let dir = shared::TestDir::new("link");
+	std::fs::create_dir_all(dir.join("a")).unwrap();
+	std::fs::write(dir.join("f"), "f").unwrap();
+	std::fs::write(dir.join("a/g"), "g").unwrap();
+	std::os::unix::fs::symlink("g", dir.join("a/old")).unwrap();
+
+	let (tx, _rx) = mpsc::unbounded_channel();
+	let file = File::new(tx);
+	let link = |from: &str, to: &str, hard, force| {
+		let targets = vec![(Url::from(dir.join(from)), Url::from(dir.join(to)))];
+		let op = FileOpMakeLink { id: 0, targets, hard, relative: true, force };
+		shared::block_on(file.make_link(op)).unwrap();
+		std::fs::read_link(dir.join(to)).ok()
+	};
+
+	assert_eq!(link("f", "a/rel", false, false), Some(PathBuf::from("../f")));
+	assert_eq!(link("f", "a/hard", true, false), None);
+	assert_eq!(std::fs::read_to_string(dir.join("a/hard")).unwrap(), "f");
+
+	// An existing symlink is replaced only if forced, and a real file never is
+	assert_eq!(link("f", "a/old", false, false), Some(PathBuf::from("g")));
+	assert_eq!(link("f", "a/old", false, true), Some(PathBuf::from("../f")));
+	assert_eq!(link("f", "a/g", false, true), None);
+	assert_eq!(std::fs::read_to_string(dir.join("a/g")).unwrap(), "g");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_paste_move() {
+	use std::time::Duration;
+
+	use config::tasks::ConflictPolicy;
+
+	let dir = shared::TestDir::new("move");
+	for d in ["from/d/sub", "to/d/sub", "to/e/a", "from/e", "from/c/sub"] {
+		std::fs::create_dir_all(dir.join(d)).unwrap();
+	}
+	for f in ["from/x", "from/d/a", "from/d/sub/b", "from/e/a", "from/e/b", "from/c/a"] {
+		std::fs::write(dir.join(f), f).unwrap();
+	}
+	std::os::unix::fs::symlink("a", dir.join("from/d/link")).unwrap();
+	let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1 << 30);
+	let a = std::fs::File::options().write(true).open(dir.join("from/d/a")).unwrap();
+	a.set_modified(old).unwrap();
+
+	let (tx, mut rx) = mpsc::unbounded_channel();
+	let file = File::new(tx);
+	let conflict = ConflictPolicy::Overwrite;
+	let preserve = Default::default();
+	let opt = PasteOpt {
+		conflict,
+		follow: false,
+		verify: None,
+		limit: 0,
+		dry_run: false,
+		reflink: true,
+		preserve,
+	};
+	let mut fails = 0;
+	let mut paste = |name: &str| {
+		let t = Task::new(0, crate::tasks::TaskKind::File, String::new());
+		let (from, to) = (dir.join("from").join(name), dir.join("to").join(name));
+		let task = FileOpPaste::new(&t, from.into(), to.into(), true, &opt);
+		let moved = task.moved.clone().unwrap();
+		shared::block_on(async {
+			file.paste(task).await.unwrap();
+			while let Ok(mut op) = file.rx.try_recv() {
+				file.work(&mut op).await.ok();
+			}
+		});
+		fails += tally(&mut rx).0;
+		let moved = std::mem::take(&mut *moved.lock());
+		moved
+	};
+
+	// Renamed on the same filesystem, with nothing to copy
+	assert!(paste("x").is_empty());
+	assert!(!dir.join("from/x").exists());
+	assert_eq!(std::fs::read_to_string(dir.join("to/x")).unwrap(), "from/x");
+
+	// Merged into the existing directory by copying, keeping the times and the symlinks,
+	// and the originals not removed until all of them are copied
+	let moved = paste("d");
+	assert_eq!(moved.len(), 3);
+	assert!(dir.join("from/d/a").exists());
+	shared::block_on(File::moved(&Url::from(dir.join("from/d")), moved));
+	assert!(!dir.join("from/d").exists());
+	assert_eq!(std::fs::read_to_string(dir.join("to/d/sub/b")).unwrap(), "from/d/sub/b");
+	assert_eq!(std::fs::metadata(dir.join("to/d/a")).unwrap().modified().unwrap(), old);
+	assert_eq!(std::fs::read_link(dir.join("to/d/link")).unwrap(), PathBuf::from("a"));
+
+	// Across the filesystems, all of them copied before any of the originals is removed
+	std::fs::write(dir.join("from/c/sub/b"), "from/c/sub/b").unwrap();
+	file.cross_device.store(true, std::sync::atomic::Ordering::Relaxed);
+	let moved = paste("c");
+	assert_eq!(moved.len(), 2);
+	for f in ["c/a", "c/sub/b"] {
+		assert_eq!(std::fs::read_to_string(dir.join("to").join(f)).unwrap(), format!("from/{f}"));
+		assert!(dir.join("from").join(f).exists());
+	}
+	shared::block_on(File::moved(&Url::from(dir.join("from/c")), moved));
+	assert!(!dir.join("from/c").exists());
+	file.cross_device.store(false, std::sync::atomic::Ordering::Relaxed);
+
+	// A file that fails to be copied is left, without a partial copy
+	assert_eq!(paste("e"), [Url::from(dir.join("from/e/b"))]);
+	assert_eq!(fails, 1);
+	assert!(dir.join("from/e/a").is_file() && dir.join("to/e/a").is_dir());
+
+	assert!(File::is_cross_device(&io::Error::from_raw_os_error(libc::EXDEV)));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_paste_preserve() {
+	use std::{os::unix::fs::PermissionsExt, time::Duration};
+
+	use config::tasks::ConflictPolicy;
+
+	let dir = shared::TestDir::new("preserve");
+	std::fs::create_dir_all(dir.join("from/d")).unwrap();
+	std::fs::write(dir.join("from/d/a"), "a").unwrap();
+	std::os::unix::fs::symlink("a", dir.join("from/d/link")).unwrap();
+
+	let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1 << 30);
+	let a = std::fs::File::options().write(true).open(dir.join("from/d/a")).unwrap();
+	a.set_modified(old).unwrap();
+	let mode = |p: &str| std::fs::metadata(dir.join(p)).unwrap().permissions().mode() & 0o7777;
+	let chmod = |p: &str, m| std::fs::set_permissions(dir.join(p), PermissionsExt::from_mode(m));
+	chmod("from/d/a", 0o551).unwrap();
+	chmod("from/d", 0o550).unwrap();
+
+	let (tx, _rx) = mpsc::unbounded_channel();
+	let file = File::new(tx);
+	let (conflict, preserve) = (ConflictPolicy::Overwrite, Default::default());
+	let opt = PasteOpt {
+		conflict,
+		follow: false,
+		verify: None,
+		limit: 0,
+		dry_run: false,
+		reflink: true,
+		preserve,
+	};
+	let copy = |to: &str, preserve| {
+		let t = Task::new(0, crate::tasks::TaskKind::File, String::new());
+		let opt = PasteOpt { preserve, ..opt };
+		let task = FileOpPaste::new(&t, dir.join("from/d").into(), dir.join(to).into(), false, &opt);
+		shared::block_on(async {
+			let dirs = task.dirs.clone();
+			file.paste(task).await.unwrap();
+			while let Ok(mut op) = file.rx.try_recv() {
+				file.work(&mut op).await.unwrap();
+			}
+			let dirs = std::mem::take(&mut *dirs.lock());
+			File::pasted(dirs, preserve, false).await;
+		});
+	};
+	let mtime = |p: &str| std::fs::metadata(dir.join(p)).unwrap().modified().unwrap();
+
+	// The mode and the times of the files and the directories, and the symlinks as they are, where
+	// the times are set on a copy made read-only by its mode
+	copy("all", Preserve::default());
+	assert_eq!((mode("all/a"), mode("all")), (0o551, 0o550));
+	assert_eq!(mtime("all/a"), old);
+	assert_eq!(mtime("all"), mtime("from/d"));
+	assert_eq!(std::fs::read_link(dir.join("all/link")).unwrap(), PathBuf::from("a"));
+
+	// Each of them can be opted out of, but for the mode of the files, copied with the content
+	copy("none", Preserve { mode: false, times: false, xattrs: false });
+	assert_eq!(mode("none/a"), 0o551);
+	assert_ne!(mode("none"), 0o550);
+	assert_ne!(mtime("none/a"), old);
+
+	chmod("from/d", 0o755).unwrap();
+	chmod("all", 0o755).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_paste_remote() {
+	use config::tasks::ConflictPolicy;
+
+	let dir = shared::TestDir::new("paste-remote");
+	std::fs::create_dir_all(dir.join("local/d/sub/deep")).unwrap();
+	std::fs::create_dir_all(dir.join("remote")).unwrap();
+	std::fs::create_dir_all(dir.join("back")).unwrap();
+	for f in ["local/d/a", "local/d/sub/b", "local/d/sub/deep/c"] {
+		std::fs::write(dir.join(f), f.repeat(10_000)).unwrap();
+	}
+
+	let (tx, mut rx) = mpsc::unbounded_channel();
+	let file = File::new(tx);
+	let (conflict, preserve) = (ConflictPolicy::Overwrite, Default::default());
+	let opt = PasteOpt {
+		conflict,
+		follow: false,
+		verify: None,
+		limit: 0,
+		dry_run: false,
+		reflink: true,
+		preserve,
+	};
+	let local = |p: &str| Url::from(dir.join(p));
+	let remote = |p: &str| Url::parse_sftp(&format!("sftp://paste{}", dir.join(p).display()));
+	let remote = |p| remote(p).unwrap();
+	let read = |p: &str| std::fs::read_to_string(dir.join(p)).unwrap();
+
+	shared::block_on(async {
+		let _server = sftp::Fake { read: 10_000, names: 2 }.serve("paste").await;
+		let paste = |from: Url, to: Url, cut| {
+			let t = Task::new(0, crate::tasks::TaskKind::File, String::new());
+			let task = FileOpPaste::new(&t, from, to, cut, &opt);
+			let file = &file;
+			async move {
+				file.paste(task).await?;
+				while let Ok(mut op) = file.rx.try_recv() {
+					file.work(&mut op).await?;
+				}
+				Ok::<_, anyhow::Error>(())
+			}
+		};
+
+		// Up, and back down again, with the directories in between made on the way
+		paste(local("local/d"), remote("remote/d"), false).await.unwrap();
+		paste(remote("remote/d"), local("back/d"), false).await.unwrap();
+		for f in ["d/a", "d/sub/b", "d/sub/deep/c"] {
+			assert_eq!(read(&format!("remote/{f}")), read(&format!("local/{f}")));
+			assert_eq!(read(&format!("back/{f}")), read(&format!("local/{f}")));
+		}
+
+		// Renamed on the same host, and done already where the source is gone, but not where the
+		// parent of the destination is
+		paste(remote("remote/d/a"), remote("remote/a"), true).await.unwrap();
+		assert!(!dir.join("remote/d/a").exists() && dir.join("remote/a").exists());
+		paste(remote("remote/gone"), remote("remote/b"), true).await.unwrap();
+		assert!(paste(remote("remote/a"), remote("none/a"), true).await.is_err());
+		assert!(dir.join("remote/a").exists());
+	});
+	assert_eq!(tally(&mut rx).0, 1);
+}
+
+#[test]
+fn test_touch() {
+	use std::time::Duration;
+
+	let dir = shared::TestDir::new("touch");
+	std::fs::create_dir_all(dir.join("d")).unwrap();
+	std::fs::write(dir.join("a"), "a").unwrap();
+
+	let (tx, mut rx) = mpsc::unbounded_channel();
+	let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1 << 30);
+	let targets = ["a", "b", "d", "none/c"].iter().map(|n| Url::from(dir.join(n))).collect();
+	let op = FileOpTouch { id: 0, targets, time, atime: false };
+	shared::block_on(File::new(tx).touch(op)).unwrap();
+
+	// The missing file is created, but not the missing directory of it
+	let meta = |n: &str| std::fs::metadata(dir.join(n)).unwrap();
+	assert_eq!(meta("a").modified().unwrap(), time);
+	assert_ne!(meta("a").accessed().unwrap(), time);
+	assert_eq!(meta("b").modified().unwrap(), time);
+	assert_eq!(std::fs::read_to_string(dir.join("a")).unwrap(), "a");
+	#[cfg(unix)]
+	assert_eq!(meta("d").modified().unwrap(), time);
+	assert!(!dir.join("none").exists());
+
+	assert_eq!(tally(&mut rx).0, if cfg!(unix) { 1 } else { 2 });
+}
+
+#[cfg(unix)]
+#[test]
+fn test_chmod() {
+	use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+	let dir = shared::TestDir::new("chmod");
+	std::fs::create_dir_all(dir.join("d/sub")).unwrap();
+	std::fs::write(dir.join("d/a"), "").unwrap();
+	std::fs::write(dir.join("d/sub/b"), "").unwrap();
+	std::os::unix::fs::symlink("a", dir.join("d/link")).unwrap();
+
+	let (tx, mut rx) = mpsc::unbounded_channel();
+	let file = File::new(tx);
+	let mode = |n: &str| std::fs::metadata(dir.join(n)).unwrap().permissions().mode() & 0o7777;
+	let targets = |names: &[&str]| names.iter().map(|n| Url::from(dir.join(n))).collect();
+
+	// The entries of a directory before it, even though it's made inaccessible to others
+	let op = FileOpChmod {
+		id:        0,
+		targets:   targets(&["d", "missing"]),
+		mode:      ModeSpec::try_from("u=rwX,go=").unwrap(),
+		recursive: true,
+	};
+	shared::block_on(file.chmod(op)).unwrap();
+	assert_eq!((mode("d"), mode("d/sub")), (0o700, 0o700));
+	assert_eq!((mode("d/a"), mode("d/sub/b")), (0o600, 0o600));
+
+	// Not recursive, and the symlink is followed
+	let octal = ModeSpec::Octal(0o644);
+	let op = FileOpChmod { id: 0, targets: targets(&["d/link"]), mode: octal, recursive: false };
+	shared::block_on(file.chmod(op)).unwrap();
+	assert_eq!((mode("d/a"), mode("d/sub/b")), (0o644, 0o600));
+
+	// Giving the files to their owner is always permitted
+	let meta = std::fs::metadata(dir.join("d")).unwrap();
+	let owner = OwnerSpec { uid: Some(meta.uid()), gid: Some(meta.gid()) };
+	let op = FileOpChown { id: 0, targets: targets(&["d"]), owner, recursive: true };
+	shared::block_on(file.chown(op)).unwrap();
+
+	assert_eq!(tally(&mut rx), (1, 5 + 1 + 5));
 }
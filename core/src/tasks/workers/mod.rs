@@ -1,6 +1,8 @@
 mod file;
 mod precache;
 mod process;
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod xdg_trash;
 
 pub(super) use file::*;
 pub(super) use precache::*;
@@ -1,13 +1,14 @@
-use std::{collections::{BTreeMap, BTreeSet}, sync::Arc};
+use std::{collections::{BTreeMap, BTreeSet}, sync::Arc, time::{Duration, Instant}};
 
 use adaptor::Image;
 use anyhow::Result;
-use config::PREVIEW;
+use config::{MANAGER, PREVIEW};
 use parking_lot::Mutex;
-use shared::{calculate_size, Throttle, Url};
+use shared::{calculate_size_with, Switch, Throttle, Url, Walked};
 use tokio::{fs, sync::mpsc};
+use tracing::warn;
 
-use crate::{emit, external, files::FilesOp, tasks::TaskOp};
+use crate::{emit, external, files::{mimetypes, FilesOp}, tasks::TaskOp};
 
 pub(crate) struct Precache {
 	rx: async_channel::Receiver<PrecacheOp>,
@@ -15,7 +16,8 @@ pub(crate) struct Precache {
 
 	sch: mpsc::UnboundedSender<TaskOp>,
 
-	pub(crate) size_handing: Mutex<BTreeSet<Url>>,
+	pub(crate) size_handing: Mutex<BTreeMap<Url, usize>>,
+	pub(crate) size_partial: Mutex<BTreeSet<Url>>,
 }
 
 #[derive(Debug)]
@@ -29,7 +31,54 @@ pub(crate) enum PrecacheOp {
 pub(crate) struct PrecacheOpSize {
 	pub id:       usize,
 	pub target:   Url,
-	pub throttle: Arc<Throttle<(Url, u64)>>,
+	pub hidden:   bool,
+	pub follow:   bool,
+	pub cancel:   Arc<Switch>,
+	pub throttle: Arc<Throttle<(Url, u64, Walked)>>,
+	pub partial:  Arc<SizeBatch>,
+}
+
+// The sizes so far of the directories calculated together, sent for all of them at once every
+// so often, rather than each on its own, so the files are sorted again at most that often
+#[derive(Debug)]
+pub(crate) struct SizeBatch {
+	inner: Mutex<(Instant, BTreeMap<Url, u64>)>,
+}
+
+impl Default for SizeBatch {
+	fn default() -> Self { Self { inner: Mutex::new((Instant::now(), BTreeMap::new())) } }
+}
+
+impl SizeBatch {
+	fn push(&self, url: &Url, size: u64) -> Option<BTreeMap<Url, u64>> {
+		let mut inner = self.inner.lock();
+		inner.1.insert(url.clone(), size);
+		if inner.0.elapsed() < Duration::from_millis(500) {
+			return None;
+		}
+		inner.0 = Instant::now();
+		Some(std::mem::take(&mut inner.1))
+	}
+
+	// Done with it, so a size so far never comes after the final one
+	#[inline]
+	fn remove(&self, url: &Url) { self.inner.lock().1.remove(url); }
+}
+
+#[derive(Debug)]
+pub(crate) struct PrecacheOpChildren {
+	pub id:      usize,
+	pub targets: Vec<Url>,
+}
+
+#[derive(Debug)]
+pub(crate) struct PrecacheOpRanks {
+	pub id:      usize,
+	// The directory of the files the ranks go to, and the one the command runs in, one of them
+	// expanded if it's not the same
+	pub root:    Url,
+	pub cwd:     Url,
+	pub targets: Vec<Url>,
 }
 
 #[derive(Debug)]
@@ -59,7 +108,7 @@ pub(crate) struct PrecacheOpPDF {
 impl Precache {
 	pub(crate) fn new(sch: mpsc::UnboundedSender<TaskOp>) -> Self {
 		let (tx, rx) = async_channel::unbounded();
-		Self { tx, rx, sch, size_handing: Default::default() }
+		Self { tx, rx, sch, size_handing: Default::default(), size_partial: Default::default() }
 	}
 
 	#[inline]
@@ -110,7 +159,7 @@ impl Precache {
 
 	pub(crate) async fn mime(&self, task: PrecacheOpMime) -> Result<()> {
 		self.sch.send(TaskOp::New(task.id, 0))?;
-		if let Ok(mimes) = external::file(&task.targets).await {
+		if let Ok(mimes) = mimetypes(&task.targets).await {
 			emit!(Mimetype(mimes));
 		}
 
@@ -121,21 +170,103 @@ impl Precache {
 	pub(crate) async fn size(&self, task: PrecacheOpSize) -> Result<()> {
 		self.sch.send(TaskOp::New(task.id, 0))?;
 
-		let length = calculate_size(&task.target).await;
-		task.throttle.done((task.target, length), |buf| {
+		// Stream the total so far, for the sort and the UI to update incrementally
+		let parent = task.target.parent_url().unwrap();
+		let (target, depth) = (&task.target, MANAGER.max_depth);
+		let (length, walked) = calculate_size_with(target, task.hidden, task.follow, depth, |partial| {
+			if task.cancel.get() {
+				return false;
+			}
+			if let Some(sizes) = task.partial.push(&task.target, partial) {
+				emit!(Files(FilesOp::SizePartial(parent.clone(), sizes)));
+			}
+			true
+		})
+		.await;
+		task.partial.remove(&task.target);
+
+		// Calculated again once it's requested, if it's canceled halfway
+		if walked.stopped() {
+			self.size_partial.lock().insert(task.target.clone());
+		}
+
+		task.throttle.done((task.target, length, walked), |buf| {
 			let mut handing = self.size_handing.lock();
-			for (path, _) in &buf {
+			for (path, ..) in &buf {
 				handing.remove(path);
 			}
 
 			let parent = buf[0].0.parent_url().unwrap();
-			emit!(Files(FilesOp::Size(parent, BTreeMap::from_iter(buf))));
+			let cut: BTreeMap<_, _> =
+				buf.iter().filter(|(.., w)| w.mark().is_some()).map(|(u, _, w)| (u.clone(), *w)).collect();
+			let sizes = buf.into_iter().map(|(u, s, _)| (u, s)).collect();
+			emit!(Files(FilesOp::Size(parent.clone(), sizes)));
+			if !cut.is_empty() {
+				emit!(Files(FilesOp::SizeCut(parent, cut)));
+			}
 		});
 
 		self.sch.send(TaskOp::Adv(task.id, 1, 0))?;
 		self.done(task.id)
 	}
 
+	pub(crate) async fn children(&self, task: PrecacheOpChildren) -> Result<()> {
+		self.sch.send(TaskOp::New(task.id, 0))?;
+
+		let mut counts = BTreeMap::new();
+		for target in task.targets {
+			let mut count = 0;
+			if let Ok(mut it) = fs::read_dir(&target).await {
+				while let Ok(Some(_)) = it.next_entry().await {
+					count += 1;
+				}
+			}
+			counts.insert(target, count);
+		}
+
+		if let Some(parent) = counts.keys().next().and_then(|u| u.parent_url()) {
+			emit!(Files(FilesOp::Children(parent, counts)));
+		}
+
+		self.sch.send(TaskOp::Adv(task.id, 1, 0))?;
+		self.done(task.id)
+	}
+
+	// The files ranked by where they're in the output of the command, the first time for those
+	// printed more than once, while none are if it fails, and they're kept in the natural order
+	pub(crate) async fn ranks(&self, task: PrecacheOpRanks) -> Result<()> {
+		self.sch.send(TaskOp::New(task.id, 0))?;
+
+		let names: Vec<_> = task
+			.targets
+			.iter()
+			.filter_map(|u| u.file_name())
+			.map(|n| n.to_string_lossy().into_owned())
+			.collect();
+
+		match external::sort(&MANAGER.sort_command, &task.cwd, &names).await {
+			Ok(lines) => {
+				let targets: BTreeSet<_> = task.targets.into_iter().collect();
+				let mut ranks = BTreeMap::new();
+				for (i, line) in lines.into_iter().enumerate() {
+					let url = task.cwd.join(line);
+					if targets.contains(&url) {
+						ranks.entry(url).or_insert(i);
+					}
+				}
+				if ranks.is_empty() {
+					warn!("external sort returned no known files, falling back to natural");
+				} else {
+					emit!(Files(FilesOp::Ranks(task.root, ranks)));
+				}
+			}
+			Err(e) => warn!("external sort failed, falling back to natural: {e}"),
+		}
+
+		self.sch.send(TaskOp::Adv(task.id, 1, 0))?;
+		self.done(task.id)
+	}
+
 	pub(crate) fn image(&self, id: usize, targets: Vec<Url>) -> Result<()> {
 		for target in targets {
 			self.sch.send(TaskOp::New(id, 0))?;
@@ -62,13 +62,16 @@ impl Process {
 					self.log(task.id, line)?;
 				}
 				Ok(status) = child.wait() => {
-					self.log(task.id, match status.code() {
+					let line = match status.code() {
 						Some(code) => format!("Exited with status code: {code}"),
 						None => "Process terminated by signal".to_string(),
-					})?;
+					};
+					// Kept in the list as failed, for its output to be inspected
 					if !status.success() {
-						return Ok(());
+						self.sch.send(TaskOp::Fail(task.id, line))?;
+						return self.done(task.id);
 					}
+					self.log(task.id, line)?;
 					break;
 				}
 			}
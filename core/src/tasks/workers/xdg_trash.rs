@@ -0,0 +1,170 @@
+use std::{env, fmt::Write, os::unix::ffi::OsStrExt, path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}};
+
+use anyhow::{bail, Result};
+use shared::{unique_path, Url};
+use tokio::{fs::{self, OpenOptions}, io::{AsyncWriteExt, ErrorKind::AlreadyExists}};
+use trash::TrashItem;
+
+// Moving to the home trash as described in the freedesktop.org Trash specification,
+// copying and deleting when the target lives on another filesystem
+pub(super) async fn trash(target: &Path) -> Result<()> {
+	let Some(root) = home() else {
+		bail!("Cannot locate the home trash");
+	};
+	let Some(name) = target.file_name() else {
+		bail!("Cannot trash {:?}", target);
+	};
+
+	let (files, info) = (root.join("files"), root.join("info"));
+	fs::create_dir_all(&files).await?;
+	fs::create_dir_all(&info).await?;
+
+	// Reserve an unique name by creating its `.trashinfo` exclusively
+	let mut i = 0;
+	let (dest, info) = loop {
+		let mut name = name.to_os_string();
+		if i > 0 {
+			name.push(format!("_{i}"));
+		}
+
+		let mut info = info.join(&name).into_os_string();
+		info.push(".trashinfo");
+		match OpenOptions::new().write(true).create_new(true).open(&info).await {
+			Ok(mut f) => {
+				f.write_all(trashinfo(target).as_bytes()).await?;
+				break (files.join(name), PathBuf::from(info));
+			}
+			Err(e) if e.kind() == AlreadyExists => i += 1,
+			Err(e) => Err(e)?,
+		}
+	};
+
+	if fs::rename(target, &dest).await.is_err() {
+		// Not to leave a partial copy in the trash, with no original gone in its place
+		if let Err(e) = copy_all(target, &dest).await {
+			remove_all(&dest).await.ok();
+			fs::remove_file(info).await.ok();
+			Err(e)?
+		}
+		if let Err(e) = remove_all(target).await {
+			bail!("Trashed a copy of {:?}, but it can't be removed: {e}", target);
+		}
+	}
+	Ok(())
+}
+
+// Moving an item listed by `trash::os_limited::list()` back to its original location,
+// under an unique name if that is occupied now
+pub(super) async fn restore(item: &TrashItem) -> Result<PathBuf> {
+	let info = Path::new(&item.id);
+	let (Some(root), Some(name)) = (info.parent().and_then(|p| p.parent()), info.file_stem()) else {
+		bail!("Invalid trash info {:?}", info);
+	};
+
+	let src = root.join("files").join(name);
+	let dest = unique_path(Url::from(item.original_path())).await;
+	if let Some(parent) = dest.parent() {
+		fs::create_dir_all(parent).await?;
+	}
+
+	if fs::rename(&src, &dest).await.is_err() {
+		if let Err(e) = copy_all(&src, &dest).await {
+			remove_all(&dest).await.ok();
+			Err(e)?
+		}
+		remove_all(&src).await?;
+	}
+
+	fs::remove_file(info).await?;
+	Ok(dest.to_path_buf())
+}
+
+fn home() -> Option<PathBuf> {
+	env::var_os("XDG_DATA_HOME")
+		.filter(|s| !s.is_empty())
+		.map(PathBuf::from)
+		.or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")))
+		.map(|p| p.join("Trash"))
+}
+
+async fn copy_all(from: &Path, to: &Path) -> Result<()> {
+	let mut stack = vec![(from.to_path_buf(), to.to_path_buf())];
+	while let Some((src, dest)) = stack.pop() {
+		let meta = fs::symlink_metadata(&src).await?;
+		if meta.is_symlink() {
+			fs::symlink(fs::read_link(&src).await?, &dest).await?;
+		} else if meta.is_dir() {
+			fs::create_dir(&dest).await?;
+			let mut it = fs::read_dir(&src).await?;
+			while let Some(entry) = it.next_entry().await? {
+				stack.push((entry.path(), dest.join(entry.file_name())));
+			}
+		} else {
+			fs::copy(&src, &dest).await?;
+		}
+	}
+	Ok(())
+}
+
+async fn remove_all(path: &Path) -> Result<()> {
+	if fs::symlink_metadata(path).await?.is_dir() {
+		fs::remove_dir_all(path).await?;
+	} else {
+		fs::remove_file(path).await?;
+	}
+	Ok(())
+}
+
+fn trashinfo(target: &Path) -> String {
+	let mut path = String::new();
+	for &b in target.as_os_str().as_bytes() {
+		if b.is_ascii_alphanumeric() || b"/-_.~".contains(&b) {
+			path.push(b as char);
+		} else {
+			write!(path, "%{b:02X}").ok();
+		}
+	}
+
+	let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+	let mut tm = unsafe { std::mem::zeroed::<libc::tm>() };
+	unsafe { libc::localtime_r(&(secs as libc::time_t), &mut tm) };
+
+	format!(
+		"[Trash Info]\nPath={path}\nDeletionDate={:04}-{:02}-{:02}T{:02}:{:02}:{:02}\n",
+		tm.tm_year + 1900,
+		tm.tm_mon + 1,
+		tm.tm_mday,
+		tm.tm_hour,
+		tm.tm_min,
+		tm.tm_sec
+	)
+}
+
+#[test]
+fn test_trashinfo() {
+	let info = trashinfo(Path::new("/tmp/a b/100%.txt"));
+	assert!(info.starts_with("[Trash Info]\nPath=/tmp/a%20b/100%25.txt\nDeletionDate="));
+}
+
+#[test]
+fn test_restore_occupied() {
+	let dir = shared::TestDir::new("restore");
+	let trash = dir.join("Trash");
+	std::fs::create_dir_all(trash.join("files")).unwrap();
+	std::fs::create_dir_all(trash.join("info")).unwrap();
+	std::fs::write(trash.join("files/a"), "trashed").unwrap();
+	std::fs::write(trash.join("info/a.trashinfo"), "").unwrap();
+	std::fs::write(dir.join("a"), "new").unwrap();
+
+	let item = TrashItem {
+		id:              trash.join("info/a.trashinfo").into(),
+		name:            "a".to_owned(),
+		original_parent: dir.to_path_buf(),
+		time_deleted:    0,
+	};
+	assert_eq!(shared::block_on(restore(&item)).unwrap(), dir.join("a_1"));
+
+	assert_eq!(std::fs::read_to_string(dir.join("a")).unwrap(), "new");
+	assert_eq!(std::fs::read_to_string(dir.join("a_1")).unwrap(), "trashed");
+	assert!(!trash.join("info/a.trashinfo").exists());
+}
@@ -0,0 +1,32 @@
+// The keys to type the labels with, the ones easier to reach first
+const LABEL_KEYS: &[char] = &['a', 's', 'd', 'f', 'g', 'h', 'j', 'k', 'l'];
+
+// The labels of `n` entries, a single key each while there are enough of them, otherwise the last
+// keys are taken as the first of two, as few as needed, so that no label starts another one
+pub fn labels(n: usize) -> Vec<String> {
+	let k = LABEL_KEYS.len();
+	let prefixes = if n <= k { 0 } else { (n - k).div_ceil(k - 1).min(k) };
+
+	let singles = LABEL_KEYS[..k - prefixes].iter().map(|c| c.to_string());
+	let doubles = LABEL_KEYS[k - prefixes..]
+		.iter()
+		.flat_map(|&a| LABEL_KEYS.iter().map(move |&b| format!("{a}{b}")));
+	singles.chain(doubles).take(n).collect()
+}
+
+#[test]
+fn test_labels() {
+	assert_eq!(labels(3), ["a", "s", "d"]);
+	assert_eq!(labels(9).last().map(|s| s.as_str()), Some("l"));
+
+	let ten = labels(10);
+	assert_eq!(ten[..8].concat(), "asdfghjk");
+	assert_eq!(&ten[8..], ["la", "ls"]);
+
+	for n in [17, 18, 40, 81] {
+		let l = labels(n);
+		assert_eq!(l.len(), n);
+		assert!(l.iter().all(|a| l.iter().all(|b| a == b || !b.starts_with(a.as_str()))));
+	}
+	assert_eq!(labels(100).len(), 81);
+}
@@ -1,3 +1,7 @@
+mod labels;
+mod palette;
 mod which;
 
+pub use labels::*;
+pub use palette::*;
 pub use which::*;
@@ -0,0 +1,46 @@
+use config::keymap::{Control, Exec};
+
+// The commands of the keymap, each with all of the keys it's bound to, the ones bound to none
+// included, as the items of a menu beside what to call for each of them
+pub fn palette(controls: &[Control]) -> (Vec<Vec<Exec>>, Vec<String>) {
+	let mut entries: Vec<(&Control, Vec<String>)> = Vec::new();
+	for c in controls {
+		let exec = c.exec();
+		let keys = Some(c.on()).filter(|s| !s.is_empty());
+		match entries.iter_mut().find(|(e, _)| e.exec() == exec) {
+			Some((_, v)) => v.extend(keys),
+			None => entries.push((c, keys.into_iter().collect())),
+		}
+	}
+
+	let keys: Vec<_> = entries.iter().map(|(_, v)| v.join(", ")).collect();
+	let width = keys.iter().map(|k| k.chars().count()).max().unwrap_or(0);
+	entries
+		.iter()
+		.zip(keys)
+		.map(|((c, _), k)| (c.to_call(), format!("{k:<width$}  {}", c.desc_or_exec())))
+		.unzip()
+}
+
+#[test]
+fn test_palette() {
+	use config::keymap::Key;
+	use crossterm::event::KeyCode;
+
+	let control = |on: &[char], exec: &str, desc: Option<&str>| Control {
+		on:   on.iter().map(|&c| Key { code: KeyCode::Char(c), ..Default::default() }).collect(),
+		exec: vec![Exec::try_from(exec).unwrap()],
+		desc: desc.map(Into::into),
+	};
+	let controls = [
+		control(&['k'], "arrow -1", Some("Move up")),
+		control(&['g', 'g'], "arrow -99999999", Some("Move to the top")),
+		control(&['K'], "arrow -1", Some("Move up again")),
+		control(&[], "hidden toggle", None),
+	];
+
+	let (execs, items) = palette(&controls);
+	assert_eq!(execs.len(), 3);
+	assert_eq!(execs[2][0].cmd, "hidden");
+	assert_eq!(items, ["k, K  Move up", "gg    Move to the top", "      hidden toggle"]);
+}
@@ -1,56 +1,239 @@
-use std::mem;
+use std::{mem, sync::atomic::{AtomicUsize, Ordering}, time::Instant};
 
-use config::{keymap::{Control, Key, KeymapLayer}, KEYMAP};
+use config::{keymap::{Control, Exec, Key, KeymapLayer}, which::WhichPopup, KEYMAP, WHICH};
+use crossterm::event::KeyEvent;
 
+use super::labels;
 use crate::emit;
 
+// Bumped on each sequence, so that the popup of one finished already isn't shown, and on each key
+// of it, so that only the wait after the last key times out
+static TICKET: AtomicUsize = AtomicUsize::new(0);
+static TIMEOUT: AtomicUsize = AtomicUsize::new(0);
+
 pub struct Which {
 	layer:     KeymapLayer,
 	pub times: usize,
 	pub cands: Vec<Control>,
+	since:     Instant,
+	// The binding the keys so far make up, where there are longer ones starting with them as well,
+	// run once it's clear none of those is being typed
+	exact:     Option<Control>,
+
+	// The labels over the files in view, with the one hovered, and what's typed of a label
+	labels: Vec<String>,
+	cursor: usize,
+	typed:  String,
 
 	pub visible: bool,
 }
 
 impl Default for Which {
 	fn default() -> Self {
-		Self { layer: KeymapLayer::Manager, times: 0, cands: Default::default(), visible: false }
+		Self {
+			layer:   KeymapLayer::Manager,
+			times:   0,
+			cands:   Default::default(),
+			since:   Instant::now(),
+			exact:   None,
+			labels:  Default::default(),
+			cursor:  0,
+			typed:   Default::default(),
+			visible: false,
+		}
 	}
 }
 
 impl Which {
 	pub fn show(&mut self, key: &Key, layer: KeymapLayer) -> bool {
 		self.layer = layer;
+		(self.cands, self.exact) = Self::narrow(KEYMAP.get(layer).to_vec(), 0, key);
 		self.times = 1;
-		self.cands =
-			KEYMAP.get(layer).iter().filter(|s| s.on.len() > 1 && &s.on[0] == key).cloned().collect();
+		self.since = Instant::now();
+		self.switch(true);
+		self.wait();
+
+		if WHICH.popup == WhichPopup::Delayed {
+			let ticket = TICKET.load(Ordering::Relaxed);
+			tokio::spawn(async move {
+				tokio::time::sleep(WHICH.delay).await;
+				if TICKET.load(Ordering::Relaxed) == ticket {
+					emit!(Render);
+					emit!(Peek);
+				}
+			});
+		}
+		true
+	}
+
+	// Labels the `count` files in view, to move the cursor from the one at `cursor` to any of them
+	pub fn show_labels(&mut self, count: usize, cursor: usize) -> bool {
+		if count == 0 {
+			return false;
+		}
+
+		self.layer = KeymapLayer::Manager;
+		self.cands.clear();
+		self.labels = labels(count);
+		self.cursor = cursor;
+		self.typed.clear();
 		self.switch(true);
 		true
 	}
 
 	pub fn press(&mut self, key: Key) -> bool {
-		self.cands = mem::take(&mut self.cands)
-			.into_iter()
-			.filter(|s| s.on.len() > self.times && s.on[self.times] == key)
-			.collect();
+		if !self.labels.is_empty() {
+			return self.press_label(key);
+		}
+		if key.is_esc() {
+			self.exact = None;
+			self.switch(false);
+			return true;
+		}
 
-		if self.cands.is_empty() {
+		let (cands, exact) = Self::narrow(mem::take(&mut self.cands), self.times, &key);
+		if !cands.is_empty() {
+			// Still going, even where the keys so far are a binding of their own
+			(self.cands, self.exact) = (cands, exact);
+			self.times += 1;
+			self.wait();
+		} else if let Some(exact) = exact {
+			self.exact = None;
 			self.switch(false);
-		} else if self.cands.len() == 1 {
+			emit!(Call(exact.to_call(), self.layer));
+		} else if let Some(exact) = self.exact.take() {
+			// The binding before it is run, and the key goes on as one of its own
 			self.switch(false);
-			emit!(Call(self.cands[0].to_call(), self.layer));
-		} else if let Some(i) = self.cands.iter().position(|c| c.on.len() == self.times + 1) {
+			emit!(Call(exact.to_call(), self.layer));
+			emit!(Key(KeyEvent::from(&key)));
+		} else {
 			self.switch(false);
-			emit!(Call(self.cands[i].to_call(), self.layer));
 		}
+		true
+	}
 
-		self.times += 1;
+	// After the keys pressed so far, with no more of them in time, the binding they make up is run
+	// if there's one, or the sequence is given up
+	pub fn timeout(&mut self, ticket: usize) -> bool {
+		if !self.visible || self.in_labels() || TIMEOUT.load(Ordering::Relaxed) != ticket {
+			return false;
+		}
+
+		self.switch(false);
+		if let Some(exact) = self.exact.take() {
+			emit!(Call(exact.to_call(), self.layer));
+		}
+		true
+	}
+
+	// The bindings going on with `key` after the `times` keys typed of them, and the one it ends,
+	// if any, apart from them
+	fn narrow(cands: Vec<Control>, times: usize, key: &Key) -> (Vec<Control>, Option<Control>) {
+		let (mut exact, mut longer) = (None, vec![]);
+		for c in cands.into_iter().filter(|c| c.on.len() > times && &c.on[times] == key) {
+			if c.on.len() > times + 1 {
+				longer.push(c);
+			} else if exact.is_none() {
+				exact = Some(c);
+			}
+		}
+		(longer, exact)
+	}
+
+	fn wait(&self) {
+		let ticket = TIMEOUT.fetch_add(1, Ordering::Relaxed) + 1;
+		if WHICH.timeout.is_zero() {
+			return;
+		}
+		tokio::spawn(async move {
+			tokio::time::sleep(WHICH.timeout).await;
+			if TIMEOUT.load(Ordering::Relaxed) == ticket {
+				let exec = Exec::call("timeout", vec![ticket.to_string()]);
+				emit!(Call(exec.vec(), KeymapLayer::Which));
+			}
+		});
+	}
+
+	fn press_label(&mut self, key: Key) -> bool {
+		let Some(c) = key.plain() else {
+			return self.hide_labels();
+		};
+
+		self.typed.push(c);
+		if let Some(i) = self.labels.iter().position(|l| *l == self.typed) {
+			let step = i as isize - self.cursor as isize;
+			emit!(Call(Exec::call("arrow", vec![step.to_string()]).vec(), KeymapLayer::Manager));
+			return self.hide_labels();
+		}
+		if !self.labels.iter().any(|l| l.starts_with(&self.typed)) {
+			return self.hide_labels();
+		}
+		true
+	}
+
+	fn hide_labels(&mut self) -> bool {
+		self.labels.clear();
+		self.switch(false);
 		true
 	}
 
 	#[inline]
 	fn switch(&mut self, state: bool) {
 		self.visible = state;
+		TICKET.fetch_add(1, Ordering::Relaxed);
 		emit!(Peek); // Show/hide preview for images
 	}
 }
+
+impl Which {
+	// Whether the keys that can follow are shown, besides being waited for
+	pub fn popup(&self) -> bool {
+		if !self.visible || self.in_labels() {
+			return self.visible;
+		}
+		WHICH.popup != WhichPopup::Never && self.since.elapsed() >= WHICH.delay
+	}
+
+	#[inline]
+	pub fn in_labels(&self) -> bool { !self.labels.is_empty() }
+
+	// The label of the file at `i` in view, and how much of it is typed, unless it's ruled out
+	#[inline]
+	pub fn label(&self, i: usize) -> Option<(&str, usize)> {
+		let label = self.labels.get(i).filter(|l| l.starts_with(&self.typed))?;
+		Some((label, self.typed.len()))
+	}
+}
+
+#[test]
+fn test_narrow() {
+	use crossterm::event::KeyCode;
+
+	let key = |c| Key { code: KeyCode::Char(c), ..Default::default() };
+	let control = |on: &str, exec: &str| Control {
+		on:   on.chars().map(key).collect(),
+		exec: vec![Exec::try_from(exec).unwrap()],
+		desc: None,
+	};
+	let cands =
+		vec![control("g", "g"), control("gg", "gg"), control("ggx", "ggx"), control("gh", "gh")];
+	let execs = |c: &[Control]| c.iter().map(|c| c.exec[0].cmd.clone()).collect::<Vec<_>>();
+
+	// Both a binding of its own and the start of longer ones
+	let (longer, exact) = Which::narrow(cands.clone(), 0, &key('g'));
+	assert_eq!(execs(&longer), ["gg", "ggx", "gh"]);
+	assert_eq!(exact.unwrap().exec[0].cmd, "g");
+
+	let (longer, exact) = Which::narrow(longer, 1, &key('g'));
+	assert_eq!(execs(&longer), ["ggx"]);
+	assert_eq!(exact.unwrap().exec[0].cmd, "gg");
+
+	// The end of the only one left
+	let (longer, exact) = Which::narrow(longer, 2, &key('x'));
+	assert!(longer.is_empty());
+	assert_eq!(exact.unwrap().exec[0].cmd, "ggx");
+
+	// None of them, where it's up to the one before it
+	let (longer, exact) = Which::narrow(cands, 1, &key('z'));
+	assert!(longer.is_empty() && exact.is_none());
+}
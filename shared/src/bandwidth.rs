@@ -0,0 +1,70 @@
+use std::{sync::atomic::{AtomicU64, Ordering}, time::{Duration, Instant}};
+
+use parking_lot::Mutex;
+use tokio::time::sleep;
+
+// A token bucket that holds at most one second of transfer, in bytes per second
+#[derive(Debug)]
+pub struct Bandwidth {
+	limit:  AtomicU64,
+	bucket: Mutex<(f64, Instant)>,
+}
+
+impl Bandwidth {
+	pub fn new(limit: u64) -> Self {
+		Self { limit: AtomicU64::new(limit), bucket: Mutex::new((limit as f64, Instant::now())) }
+	}
+
+	#[inline]
+	pub fn limit(&self) -> u64 { self.limit.load(Ordering::Relaxed) }
+
+	#[inline]
+	pub fn set_limit(&self, limit: u64) { self.limit.store(limit, Ordering::Relaxed) }
+
+	// Takes `n` bytes out of the bucket, and waits until the debt is paid off if it's overdrawn.
+	// The limit is re-read while waiting, so that changing it applies to the ongoing transfers
+	pub async fn consume(&self, n: u64) {
+		let mut n = n as f64;
+		loop {
+			let wait = {
+				let limit = self.limit() as f64;
+				let mut bucket = self.bucket.lock();
+				let now = Instant::now();
+
+				if limit == 0.0 {
+					*bucket = (0.0, now);
+					return;
+				}
+
+				let elapsed = now.duration_since(bucket.1).as_secs_f64();
+				bucket.0 = limit.min(bucket.0 + elapsed * limit) - n;
+				bucket.1 = now;
+				n = 0.0;
+
+				if bucket.0 >= 0.0 {
+					return;
+				}
+				Duration::from_secs_f64(-bucket.0 / limit)
+			};
+			sleep(wait.min(Duration::from_millis(100))).await;
+		}
+	}
+}
+
+#[test]
+fn test_bandwidth() {
+	crate::block_on(async {
+		let bw = Bandwidth::new(1000);
+		let start = Instant::now();
+		bw.consume(1000).await;
+		assert!(start.elapsed() < Duration::from_millis(100));
+
+		bw.consume(300).await;
+		assert!(start.elapsed() >= Duration::from_millis(250));
+
+		bw.set_limit(0);
+		let start = Instant::now();
+		bw.consume(1 << 30).await;
+		assert!(start.elapsed() < Duration::from_millis(100));
+	});
+}
@@ -1,4 +1,4 @@
-use std::{env, path::{Path, PathBuf}};
+use std::{env, ffi::{OsStr, OsString}, path::{Path, PathBuf}, time::Duration};
 
 use tokio::fs;
 
@@ -14,12 +14,29 @@ pub fn expand_path(p: impl AsRef<Path>) -> PathBuf {
 	p.to_path_buf()
 }
 
-#[inline]
+// A path typed as `sftp://host/path` is taken for the remote one, whose `~` isn't the local home
 pub fn expand_url(mut u: Url) -> Url {
+	if u.is_sftp() {
+		return u;
+	} else if let Some(remote) = u.to_str().and_then(Url::parse_sftp) {
+		return remote;
+	}
 	u.set_path(expand_path(&u));
 	u
 }
 
+// The nearest of `p` and its ancestors that exists, along with the rest of `p` after it if that
+// isn't `p` itself
+pub fn nearest_existing(p: &Path) -> (PathBuf, Option<PathBuf>) {
+	for a in p.ancestors().filter(|a| !a.as_os_str().is_empty()) {
+		if std::fs::symlink_metadata(a).is_ok() {
+			let rest = p.strip_prefix(a).unwrap_or(p);
+			return (a.to_owned(), Some(rest.to_owned()).filter(|r| !r.as_os_str().is_empty()));
+		}
+	}
+	(PathBuf::from("."), Some(p.to_owned()))
+}
+
 pub fn short_path(p: &Path, base: &Path) -> String {
 	if let Ok(p) = p.strip_prefix(base) {
 		return p.display().to_string();
@@ -47,6 +64,15 @@ pub fn readable_size(size: u64) -> String {
 	format!("{:.1} {}", size, units[i])
 }
 
+pub fn readable_duration(d: Duration) -> String {
+	let secs = d.as_secs();
+	match secs {
+		0..=59 => format!("{secs}s"),
+		60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+		_ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+	}
+}
+
 pub async fn unique_path(mut p: Url) -> Url {
 	let Some(name) = p.file_name().map(|n| n.to_os_string()) else {
 		return p;
@@ -62,6 +88,35 @@ pub async fn unique_path(mut p: Url) -> Url {
 	p
 }
 
+// The name with ` (n)` before its extension, as the file managers keep both of two files of the
+// same name, e.g. `a (1).txt`, where the `.tar` of `a.tar.gz` goes with the extension, and the
+// number of a directory, or a dotfile like `.bashrc`, is at the end
+pub fn numbered_name(name: &OsStr, is_dir: bool, n: usize) -> OsString {
+	let path = Path::new(name);
+	let mut s = OsString::with_capacity(name.len() + 6);
+	let Some(ext) = path.extension().filter(|_| !is_dir) else {
+		s.push(name);
+		s.push(format!(" ({n})"));
+		return s;
+	};
+
+	let stem = Path::new(path.file_stem().unwrap_or_default());
+	match stem.extension() {
+		Some(tar) if tar.eq_ignore_ascii_case("tar") => {
+			s.push(stem.file_stem().unwrap_or_default());
+			s.push(format!(" ({n})."));
+			s.push(tar);
+		}
+		_ => {
+			s.push(stem);
+			s.push(format!(" ({n})"));
+		}
+	}
+	s.push(".");
+	s.push(ext);
+	s
+}
+
 #[inline]
 pub fn optional_bool(s: &str) -> Option<bool> {
 	if s == "true" {
@@ -72,3 +127,29 @@ pub fn optional_bool(s: &str) -> Option<bool> {
 		None
 	}
 }
+
+#[test]
+fn test_nearest_existing() {
+	let dir = crate::TestDir::new("nearest");
+	std::fs::create_dir_all(dir.join("a")).unwrap();
+	std::fs::write(dir.join("a/f"), "").unwrap();
+
+	assert_eq!(nearest_existing(&dir.join("a")), (dir.join("a"), None));
+	assert_eq!(nearest_existing(&dir.join("a/f")), (dir.join("a/f"), None));
+	assert_eq!(
+		nearest_existing(&dir.join("a/b/c")),
+		(dir.join("a"), Some(PathBuf::from("b/c")))
+	);
+}
+
+#[test]
+fn test_numbered_name() {
+	let numbered = |name: &str, is_dir, n| numbered_name(OsStr::new(name), is_dir, n);
+	assert_eq!(numbered("a.txt", false, 1), "a (1).txt");
+	assert_eq!(numbered("a.b.txt", false, 2), "a.b (2).txt");
+	assert_eq!(numbered("a", false, 1), "a (1)");
+	assert_eq!(numbered("a.tar.gz", false, 3), "a (3).tar.gz");
+	assert_eq!(numbered(".bashrc", false, 1), ".bashrc (1)");
+	assert_eq!(numbered(".config.toml", false, 1), ".config (1).toml");
+	assert_eq!(numbered("v1.2", true, 1), "v1.2 (1)");
+}
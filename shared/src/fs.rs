@@ -1,13 +1,62 @@
-use std::{collections::VecDeque, path::{Path, PathBuf}};
+use std::{collections::{BTreeSet, VecDeque}, path::{Component, Path, PathBuf}, sync::Arc};
 
 use anyhow::Result;
-use tokio::{fs, io, select, sync::{mpsc, oneshot}, time};
+use tokio::{fs, io::{self, AsyncReadExt, AsyncWriteExt}, select, sync::{mpsc, oneshot}, time};
 
+use crate::{Bandwidth, Switch};
+
+#[inline]
 pub async fn calculate_size(path: &Path) -> u64 {
-	let mut total = 0;
-	let mut stack = VecDeque::from([path.to_path_buf()]);
-	while let Some(path) = stack.pop_front() {
-		let Ok(meta) = fs::symlink_metadata(&path).await else {
+	calculate_size_with(path, true, false, usize::MAX, |_| true).await.0
+}
+
+// How a walk through a directory ended, where the ones cut short of some of it still count the
+// rest of it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Walked {
+	#[default]
+	Done,
+	// By the caller, before it's been through all of it
+	Stopped,
+	// With a directory it had been through already left out, got to again by a symlink or a mount
+	Loop,
+	// With the directories deeper than it goes left out
+	Deep,
+}
+
+impl Walked {
+	#[inline]
+	pub fn stopped(self) -> bool { self == Self::Stopped }
+
+	// What a walk cut short is marked by
+	pub fn mark(self) -> Option<&'static str> {
+		match self {
+			Self::Loop => Some("(symlink loop)"),
+			Self::Deep => Some("(max depth)"),
+			_ => None,
+		}
+	}
+}
+
+// Sums the lengths of the files under `path`, skipping the hidden ones unless `hidden`,
+// following the symlinks if `follow`, and going at most `depth` directories down. The total so
+// far is reported after each directory, and the walk stops once `report` returns false
+pub async fn calculate_size_with(
+	path: &Path,
+	hidden: bool,
+	follow: bool,
+	depth: usize,
+	mut report: impl FnMut(u64) -> bool,
+) -> (u64, Walked) {
+	let metadata = |p: PathBuf| async move {
+		if follow { fs::metadata(p).await } else { fs::symlink_metadata(p).await }
+	};
+
+	let (mut total, mut walked) = (0, Walked::Done);
+	let mut visited = BTreeSet::new();
+	let mut stack = VecDeque::from([(path.to_path_buf(), 0)]);
+	while let Some((path, level)) = stack.pop_front() {
+		let Ok(meta) = metadata(path.clone()).await else {
 			continue;
 		};
 
@@ -15,24 +64,54 @@ pub async fn calculate_size(path: &Path) -> u64 {
 			total += meta.len();
 			continue;
 		}
+		// Symlinks, and bind mounts, can make a loop
+		if !visited.insert(dir_id(&meta, &path).await) {
+			walked = Walked::Loop;
+			continue;
+		}
 
 		let Ok(mut it) = fs::read_dir(path).await else {
 			continue;
 		};
 
 		while let Ok(Some(entry)) = it.next_entry().await {
-			let Ok(meta) = entry.metadata().await else {
+			if !hidden && entry.file_name().to_string_lossy().starts_with('.') {
+				continue;
+			}
+
+			let Ok(meta) = metadata(entry.path()).await else {
 				continue;
 			};
 
-			if meta.is_dir() {
-				stack.push_back(entry.path());
-			} else {
+			if !meta.is_dir() {
 				total += meta.len();
+			} else if level < depth {
+				stack.push_back((entry.path(), level + 1));
+			} else if walked == Walked::Done {
+				walked = Walked::Deep;
 			}
 		}
+
+		if !report(total) {
+			return (total, Walked::Stopped);
+		}
 	}
-	total
+	(total, walked)
+}
+
+// What tells a directory from the others, however it's got to
+#[cfg(unix)]
+#[inline]
+pub async fn dir_id(meta: &std::fs::Metadata, _: &Path) -> (u64, u64) {
+	use std::os::unix::fs::MetadataExt;
+	(meta.dev(), meta.ino())
+}
+
+// Without the inodes at hand, a directory is told by where its path leads in the end
+#[cfg(not(unix))]
+#[inline]
+pub async fn dir_id(_: &std::fs::Metadata, path: &Path) -> PathBuf {
+	fs::canonicalize(path).await.unwrap_or_else(|_| path.to_path_buf())
 }
 
 pub fn copy_with_progress(from: &Path, to: &Path) -> mpsc::Receiver<Result<u64, io::Error>> {
@@ -91,6 +170,99 @@ pub fn copy_with_progress(from: &Path, to: &Path) -> mpsc::Receiver<Result<u64,
 	rx
 }
 
+// Same as `copy_with_progress`, but copies in chunks, throttled by the given bandwidth,
+// and holds on at the chunk boundary while it's paused. Only the content and the permissions are
+// copied, as `fs::copy` does, which of the rest of the metadata is kept being up to the caller.
+// With `reflink`, it's cloned at once where the filesystem can, see `reflink`, and copied as above
+// where it can't
+pub fn copy_in_chunks(
+	from: &Path,
+	to: &Path,
+	bandwidth: Arc<Bandwidth>,
+	pause: Arc<Switch>,
+	reflink: bool,
+) -> mpsc::Receiver<Result<u64, io::Error>> {
+	let (tx, rx) = mpsc::channel(1);
+	let (from, to) = (from.to_path_buf(), to.to_path_buf());
+
+	tokio::spawn(async move {
+		if reflink {
+			let (f, t) = (from.clone(), to.clone());
+			if let Ok(Ok(len)) = tokio::task::spawn_blocking(move || self::reflink(&f, &t)).await {
+				tx.send(Ok(len)).await.ok();
+				tx.send(Ok(0)).await.ok();
+				return;
+			}
+		}
+
+		let result = async {
+			let mut src = fs::File::open(&from).await?;
+			let mut dest = fs::File::create(&to).await?;
+
+			let mut buf = vec![0; 256 * 1024];
+			loop {
+				pause.until(false).await;
+				let n = src.read(&mut buf).await?;
+				if n == 0 {
+					break;
+				}
+
+				bandwidth.consume(n as u64).await;
+				dest.write_all(&buf[..n]).await?;
+				if tx.send(Ok(n as u64)).await.is_err() {
+					return Ok(());
+				}
+			}
+
+			dest.flush().await?;
+			fs::set_permissions(&to, src.metadata().await?.permissions()).await?;
+			tx.send(Ok(0)).await.ok();
+			Ok(())
+		};
+
+		if let Err(e) = result.await {
+			tx.send(Err(e)).await.ok();
+		}
+	});
+
+	rx
+}
+
+// Clones `from` to `to`, a file that mustn't exist, on a filesystem of copy-on-write, e.g. Btrfs,
+// XFS or APFS, sharing the blocks until either is written, and tells the length. Fails on the other
+// filesystems, across them, and on the other platforms, leaving no `to` behind
+#[cfg(target_os = "linux")]
+pub fn reflink(from: &Path, to: &Path) -> io::Result<u64> {
+	use std::os::fd::AsRawFd;
+
+	let src = std::fs::File::open(from)?;
+	let dest = std::fs::OpenOptions::new().write(true).create_new(true).open(to)?;
+	if unsafe { libc::ioctl(dest.as_raw_fd(), libc::FICLONE, src.as_raw_fd()) } == 0 {
+		return src.metadata().map(|m| m.len());
+	}
+
+	let e = io::Error::last_os_error();
+	drop(dest);
+	std::fs::remove_file(to).ok();
+	Err(e)
+}
+
+#[cfg(target_os = "macos")]
+pub fn reflink(from: &Path, to: &Path) -> io::Result<u64> {
+	use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+	let cstr = |p: &Path| CString::new(p.as_os_str().as_bytes()).map_err(io::Error::other);
+	let (src, dest) = (cstr(from)?, cstr(to)?);
+	// Not to follow a symlink given as `from`, with `CLONE_NOFOLLOW`
+	if unsafe { libc::clonefile(src.as_ptr(), dest.as_ptr(), 0x0001) } != 0 {
+		return Err(io::Error::last_os_error());
+	}
+	std::fs::metadata(to).map(|m| m.len())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn reflink(_: &Path, _: &Path) -> io::Result<u64> { Err(io::ErrorKind::Unsupported.into()) }
+
 // Convert a file mode to a string representation
 #[cfg(not(target_os = "windows"))]
 #[allow(clippy::collapsible_else_if)]
@@ -145,6 +317,24 @@ pub fn file_mode(mode: u32) -> String {
 	s
 }
 
+// The space left for the user, and the total size, of the filesystem `path` is on, none if it can't
+// be told, such as for a mount that's gone
+#[cfg(unix)]
+pub fn disk_space(path: &Path) -> Option<(u64, u64)> {
+	use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+	let p = CString::new(path.as_os_str().as_bytes()).ok()?;
+	let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+	if unsafe { libc::statvfs(p.as_ptr(), &mut st) } != 0 {
+		return None;
+	}
+	let frsize = st.f_frsize as u64;
+	Some((st.f_bavail as u64 * frsize, st.f_blocks as u64 * frsize))
+}
+
+#[cfg(not(unix))]
+pub fn disk_space(_: &Path) -> Option<(u64, u64)> { None }
+
 // Find the max common root of a list of files
 // e.g. /a/b/c, /a/b/d       -> /a/b
 //      /aa/bb/cc, /aa/dd/ee -> /aa
@@ -168,6 +358,20 @@ pub fn max_common_root(files: &[impl AsRef<Path>]) -> PathBuf {
 	root
 }
 
+// The path of `path` relative to the directory `base`, both are absolute
+// e.g. /a/b/c, /a/d -> ../b/c
+pub fn relative_path(path: &Path, base: &Path) -> PathBuf {
+	let (mut p, mut b) = (path.components().peekable(), base.components().peekable());
+	while p.peek().is_some() && p.peek() == b.peek() {
+		p.next();
+		b.next();
+	}
+
+	let mut rel: PathBuf = b.map(|_| Component::ParentDir).collect();
+	rel.extend(p);
+	if rel.as_os_str().is_empty() { PathBuf::from(".") } else { rel }
+}
+
 #[test]
 fn test_max_common_root() {
 	assert_eq!(max_common_root(&[] as &[PathBuf]).as_os_str(), "");
@@ -191,3 +395,114 @@ fn test_max_common_root() {
 		"/aa/bb"
 	);
 }
+
+#[test]
+fn test_relative_path() {
+	let rel = |p: &str, b: &str| relative_path(Path::new(p), Path::new(b));
+	assert_eq!(rel("/a/b/c", "/a/d"), Path::new("../b/c"));
+	assert_eq!(rel("/a/b", "/a"), Path::new("b"));
+	assert_eq!(rel("/a", "/a/b/c"), Path::new("../.."));
+	assert_eq!(rel("/a", "/a"), Path::new("."));
+}
+
+#[test]
+fn test_copy_in_chunks() {
+	let dir = crate::TestDir::new("chunks");
+	std::fs::write(dir.join("a"), vec![7; 600 * 1024]).unwrap();
+	let mut perm = std::fs::metadata(dir.join("a")).unwrap().permissions();
+	perm.set_readonly(true);
+	std::fs::set_permissions(dir.join("a"), perm).unwrap();
+
+	crate::block_on(async {
+		let pause = Arc::new(Switch::default());
+		pause.set(true);
+
+		let bandwidth = Arc::new(Bandwidth::new(0));
+		let mut rx = copy_in_chunks(&dir.join("a"), &dir.join("b"), bandwidth, pause.clone(), false);
+		assert!(time::timeout(time::Duration::from_millis(100), rx.recv()).await.is_err());
+
+		pause.set(false);
+		let mut total = 0;
+		while let Some(n) = rx.recv().await {
+			match n.unwrap() {
+				0 => break,
+				n => total += n,
+			}
+		}
+		assert_eq!(total, 600 * 1024);
+	});
+
+	assert_eq!(std::fs::read(dir.join("a")).unwrap(), std::fs::read(dir.join("b")).unwrap());
+	assert!(std::fs::metadata(dir.join("b")).unwrap().permissions().readonly());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_disk_space() {
+	let (free, total) = disk_space(&std::env::temp_dir()).unwrap();
+	assert!(total > 0 && free <= total);
+	assert_eq!(disk_space(Path::new("/nonexistent/yazi")), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_calculate_size_with() {
+	let dir = crate::TestDir::new("size");
+	std::fs::create_dir_all(dir.join("a/b")).unwrap();
+	std::fs::create_dir_all(dir.join(".c")).unwrap();
+	std::fs::write(dir.join("a/1"), [0; 10]).unwrap();
+	std::fs::write(dir.join("a/b/2"), [0; 20]).unwrap();
+	std::fs::write(dir.join(".c/3"), [0; 40]).unwrap();
+	// Counted as the length of its target path, unless it's followed
+	std::os::unix::fs::symlink(&dir, dir.join("a/loop")).unwrap();
+	let link = dir.as_os_str().len() as u64;
+
+	crate::block_on(async {
+		let mut reports = vec![];
+		let size = calculate_size_with(&dir, false, false, usize::MAX, |n| {
+			reports.push(n);
+			true
+		});
+		assert_eq!(size.await.0, 30 + link);
+		assert_eq!(reports, [0, 10 + link, 30 + link]);
+
+		let size = |hidden, follow, depth, report: fn(u64) -> bool| {
+			calculate_size_with(&dir, hidden, follow, depth, report)
+		};
+		assert_eq!(size(true, false, usize::MAX, |_| true).await, (70 + link, Walked::Done));
+		assert_eq!(size(true, false, usize::MAX, |_| false).await, (0, Walked::Stopped));
+
+		// The loop through the symlink is cut, once it's back at the directory
+		assert_eq!(size(true, true, usize::MAX, |_| true).await, (70, Walked::Loop));
+		assert_eq!(size(true, false, 1, |_| true).await, (50 + link, Walked::Deep));
+		assert_eq!(Walked::Loop.mark(), Some("(symlink loop)"));
+	});
+}
+
+#[test]
+fn test_reflink() {
+	let dir = crate::TestDir::new("reflink");
+	let content: Vec<u8> = (0..600 * 1024).map(|i| (i % 251) as u8).collect();
+	std::fs::write(dir.join("from"), &content).unwrap();
+
+	// A reflink fails for a file that exists already, and leaves it as it was
+	std::fs::write(dir.join("exists"), "a").unwrap();
+	assert!(reflink(&dir.join("from"), &dir.join("exists")).is_err());
+	assert_eq!(std::fs::read(dir.join("exists")).unwrap(), b"a");
+
+	// Identical either way, cloned, or copied in chunks where the filesystem can't clone, as the
+	// temporary directory usually can't, or when the reflink fails for the file that exists
+	for (to, reflink) in [("plain", false), ("cloned", true), ("exists", true)] {
+		let total = crate::block_on(async {
+			let (bandwidth, pause) = (Arc::new(Bandwidth::new(0)), Arc::new(Switch::default()));
+			let mut rx = copy_in_chunks(&dir.join("from"), &dir.join(to), bandwidth, pause, reflink);
+			let mut total = 0;
+			while let Some(n) = rx.recv().await {
+				total += n.unwrap();
+			}
+			total
+		});
+		assert_eq!(total, content.len() as u64);
+		assert_eq!(std::fs::read(dir.join(to)).unwrap(), content);
+	}
+}
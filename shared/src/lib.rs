@@ -1,5 +1,6 @@
 #![allow(clippy::option_map_unit_fn)]
 
+mod bandwidth;
 mod chars;
 mod debounce;
 mod defer;
@@ -8,11 +9,16 @@ mod fns;
 mod fs;
 mod mime;
 mod ro_cell;
+mod switch;
 mod term;
+#[cfg(any(test, feature = "testing"))]
+mod testing;
 mod throttle;
 mod time;
 mod url;
+mod xattr;
 
+pub use bandwidth::*;
 pub use chars::*;
 pub use debounce::*;
 pub use defer::*;
@@ -21,7 +27,11 @@ pub use fns::*;
 pub use fs::*;
 pub use mime::*;
 pub use ro_cell::*;
+pub use switch::*;
 pub use term::*;
+#[cfg(any(test, feature = "testing"))]
+pub use testing::*;
 pub use throttle::*;
 pub use time::*;
 pub use url::*;
+pub use xattr::*;
@@ -11,6 +11,7 @@ pub enum MimeKind {
 
 	JSON,
 	PDF,
+	Document,
 	Text,
 
 	Others,
@@ -30,6 +31,10 @@ impl MimeKind {
 			Self::JSON
 		} else if s == "application/pdf" {
 			Self::PDF
+		} else if s == "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+			|| s == "application/vnd.oasis.opendocument.text"
+		{
+			Self::Document
 		} else if s == "application/zip"
 			|| s == "application/gzip"
 			|| s == "application/x-tar"
@@ -37,6 +42,7 @@ impl MimeKind {
 			|| s == "application/x-bzip2"
 			|| s == "application/x-7z-compressed"
 			|| s == "application/x-rar"
+			|| s == "application/x-xz"
 		{
 			Self::Archive
 		} else {
@@ -0,0 +1,25 @@
+use tokio::sync::watch;
+
+// A boolean state that can be waited on, e.g. whether a task is paused
+#[derive(Debug)]
+pub struct Switch(watch::Sender<bool>);
+
+impl Default for Switch {
+	fn default() -> Self { Self(watch::channel(false).0) }
+}
+
+impl Switch {
+	#[inline]
+	pub fn get(&self) -> bool { *self.0.borrow() }
+
+	// Returns whether the state was changed
+	#[inline]
+	pub fn set(&self, state: bool) -> bool {
+		self.0.send_if_modified(|s| std::mem::replace(s, state) != state)
+	}
+
+	// Resolves once the state is `state`
+	pub async fn until(&self, state: bool) {
+		self.0.subscribe().wait_for(|&s| s == state).await.ok();
+	}
+}
@@ -1,9 +1,13 @@
-use std::{io::{stdout, Stdout, Write}, mem, ops::{Deref, DerefMut}};
+use std::{io::{stdout, Stdout, Write}, mem, ops::{Deref, DerefMut}, sync::atomic::{AtomicU32, Ordering}};
 
 use anyhow::Result;
-use crossterm::{event::{DisableBracketedPaste, DisableFocusChange, EnableBracketedPaste, EnableFocusChange, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags}, execute, queue, terminal::{disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, WindowSize}};
+use crossterm::{event::{DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste, EnableFocusChange, EnableMouseCapture, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags}, execute, queue, terminal::{disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, WindowSize}};
 use ratatui::{backend::CrosstermBackend, Terminal};
 
+// The size taken for the terminal, as the columns in the high half and the rows in the low one, for
+// the app driven without one, zero while there's a real one
+static HEADLESS: AtomicU32 = AtomicU32::new(0);
+
 pub struct Term {
 	inner: Terminal<CrosstermBackend<Stdout>>,
 	csi_u: bool,
@@ -33,6 +37,12 @@ impl Term {
 	}
 
 	pub fn size() -> WindowSize {
+		let headless = HEADLESS.load(Ordering::Relaxed);
+		if headless != 0 {
+			let (columns, rows) = ((headless >> 16) as u16, headless as u16);
+			return WindowSize { rows, columns, width: 0, height: 0 };
+		}
+
 		let mut size = WindowSize { rows: 0, columns: 0, width: 0, height: 0 };
 		if let Ok(s) = crossterm::terminal::window_size() {
 			let _ = mem::replace(&mut size, s);
@@ -51,6 +61,12 @@ impl Term {
 		size
 	}
 
+	// Takes the size of the terminal to be this from now on, as there's none, e.g. in the tests
+	#[inline]
+	pub fn set_headless(columns: u16, rows: u16) {
+		HEADLESS.store((columns as u32) << 16 | rows as u32, Ordering::Relaxed);
+	}
+
 	#[inline]
 	pub fn ratio() -> Option<(f64, f64)> {
 		let s = Self::size();
@@ -60,6 +76,17 @@ impl Term {
 		Some((f64::from(s.width) / f64::from(s.columns), f64::from(s.height) / f64::from(s.rows)))
 	}
 
+	// Captures the mouse events, which takes the text selection away from the terminal
+	#[inline]
+	pub fn set_mouse(state: bool) -> Result<()> {
+		if state {
+			execute!(stdout(), EnableMouseCapture)?;
+		} else {
+			execute!(stdout(), DisableMouseCapture)?;
+		}
+		Ok(())
+	}
+
 	#[inline]
 	pub fn clear(stdout: &mut impl Write) -> Result<()> {
 		queue!(stdout, Clear(ClearType::All))?;
@@ -75,7 +102,13 @@ impl Drop for Term {
 				execute!(stdout(), PopKeyboardEnhancementFlags)?;
 			}
 
-			execute!(stdout(), DisableFocusChange, DisableBracketedPaste, LeaveAlternateScreen)?;
+			execute!(
+				stdout(),
+				DisableMouseCapture,
+				DisableFocusChange,
+				DisableBracketedPaste,
+				LeaveAlternateScreen
+			)?;
 
 			Self::set_cursor_default()?;
 			self.show_cursor()?;
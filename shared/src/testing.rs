@@ -0,0 +1,33 @@
+use std::{future::Future, ops::Deref, path::{Path, PathBuf}};
+
+// A directory of the test's own, made empty, and removed along with what's in it once it's dropped,
+// so it's gone as well when an assertion fails
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+	pub fn new(name: &str) -> Self {
+		let dir = std::env::temp_dir().join(format!("yazi-{name}-{}", std::process::id()));
+		std::fs::remove_dir_all(&dir).ok();
+		std::fs::create_dir_all(&dir).unwrap();
+		Self(dir)
+	}
+}
+
+impl Deref for TestDir {
+	type Target = Path;
+
+	fn deref(&self) -> &Self::Target { &self.0 }
+}
+
+impl AsRef<Path> for TestDir {
+	fn as_ref(&self) -> &Path { &self.0 }
+}
+
+impl Drop for TestDir {
+	fn drop(&mut self) { std::fs::remove_dir_all(&self.0).ok(); }
+}
+
+// Runs `f` to the end on a runtime of the current thread, with the time and the IO enabled
+pub fn block_on<F: Future>(f: F) -> F::Output {
+	tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(f)
+}
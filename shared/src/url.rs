@@ -5,6 +5,8 @@ pub struct Url {
 	scheme: UrlScheme,
 	path:   PathBuf,
 	frag:   Option<String>,
+	// The `[user@]host[:port]` of a remote one, e.g. `sftp://me@example.com/home/me`
+	host:   Option<String>,
 }
 
 #[derive(Clone, Copy, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -13,6 +15,7 @@ pub enum UrlScheme {
 	Regular,
 	Search,
 	Archive,
+	Sftp,
 }
 
 impl Deref for Url {
@@ -26,7 +29,12 @@ impl DerefMut for Url {
 }
 
 impl Debug for Url {
-	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result { write!(f, "{}", self.path.display()) }
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match &self.host {
+			Some(host) => write!(f, "sftp://{host}{}", self.path.display()),
+			None => write!(f, "{}", self.path.display()),
+		}
+	}
 }
 
 impl From<PathBuf> for Url {
@@ -73,6 +81,7 @@ impl Url {
 			UrlScheme::Regular => url,
 			UrlScheme::Search => url,
 			UrlScheme::Archive => url.into_archive(),
+			UrlScheme::Sftp => url.into_sftp(self.host.clone()),
 		}
 	}
 
@@ -84,10 +93,25 @@ impl Url {
 				UrlScheme::Regular => url,
 				UrlScheme::Search => url,
 				UrlScheme::Archive => url,
+				UrlScheme::Sftp => url.into_sftp(self.host.clone()),
 			}
 		})
 	}
 
+	// A remote one of `sftp://[user@]host[:port][/path]`, where the path is the home directory on
+	// the host if it's left out, none if `s` isn't of the form
+	pub fn parse_sftp(s: &str) -> Option<Self> {
+		let rest = s.strip_prefix("sftp://")?;
+		let (host, path) = match rest.find('/') {
+			Some(i) => (&rest[..i], &rest[i..]),
+			None => (rest, "."),
+		};
+		if host.is_empty() || host.starts_with('-') {
+			return None;
+		}
+		Some(Self::from(path).into_sftp(Some(host.to_owned())))
+	}
+
 	#[inline]
 	pub fn strip_prefix(&self, base: impl AsRef<Path>) -> Option<&Path> {
 		self.path.strip_prefix(base).ok()
@@ -136,6 +160,20 @@ impl Url {
 		self
 	}
 
+	#[inline]
+	pub fn is_sftp(&self) -> bool { self.scheme == UrlScheme::Sftp }
+
+	#[inline]
+	fn into_sftp(mut self, host: Option<String>) -> Self {
+		self.scheme = UrlScheme::Sftp;
+		self.host = host;
+		self
+	}
+
+	// --- Host
+	#[inline]
+	pub fn host(&self) -> Option<&str> { self.host.as_deref() }
+
 	// --- Path
 	#[inline]
 	pub fn set_path(&mut self, path: PathBuf) { self.path = path; }
@@ -144,3 +182,18 @@ impl Url {
 	#[inline]
 	pub fn frag(&self) -> Option<&str> { self.frag.as_deref() }
 }
+
+#[test]
+fn test_parse_sftp() {
+	let url = Url::parse_sftp("sftp://me@example.com:2222/srv/a b").unwrap();
+	assert!(url.is_sftp());
+	assert_eq!(url.host(), Some("me@example.com:2222"));
+	assert_eq!(url.as_path(), Path::new("/srv/a b"));
+	assert_eq!(format!("{:?}", url.join("c")), "sftp://me@example.com:2222/srv/a b/c");
+	assert_eq!(url.parent_url().unwrap().host(), url.host());
+
+	assert_eq!(Url::parse_sftp("sftp://example.com").unwrap().as_path(), Path::new("."));
+	assert!(Url::parse_sftp("sftp:///srv").is_none());
+	assert!(Url::parse_sftp("sftp://-oProxyCommand=x/").is_none());
+	assert!(Url::parse_sftp("/srv").is_none());
+}
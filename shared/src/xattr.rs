@@ -0,0 +1,120 @@
+use std::path::Path;
+
+// The names of the extended attributes of `path`, not following it if it's a symlink.
+// Empty on the filesystems that don't support them, or if they can't be read
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn xattr_names(path: &Path) -> Vec<String> {
+	use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+	let Ok(p) = CString::new(path.as_os_str().as_bytes()) else {
+		return vec![];
+	};
+	let list = |buf: *mut libc::c_char, size: usize| unsafe {
+		#[cfg(target_os = "linux")]
+		return libc::llistxattr(p.as_ptr(), buf, size);
+		#[cfg(target_os = "macos")]
+		return libc::listxattr(p.as_ptr(), buf, size, libc::XATTR_NOFOLLOW);
+	};
+
+	let len = list(std::ptr::null_mut(), 0);
+	if len <= 0 {
+		return vec![];
+	}
+	let mut buf = vec![0u8; len as usize];
+	let len = list(buf.as_mut_ptr().cast(), buf.len());
+	if len <= 0 {
+		return vec![];
+	}
+
+	buf.truncate(len as usize);
+	buf
+		.split(|&b| b == 0)
+		.filter(|s| !s.is_empty())
+		.map(|s| String::from_utf8_lossy(s).into_owned())
+		.collect()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn xattr_names(_: &Path) -> Vec<String> { vec![] }
+
+// The value of the extended attribute `name` of `path`, not following it if it's a symlink
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn xattr_value(path: &Path, name: &str) -> Option<Vec<u8>> {
+	use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+	let (p, n) = (CString::new(path.as_os_str().as_bytes()).ok()?, CString::new(name).ok()?);
+	let get = |buf: *mut libc::c_void, size: usize| unsafe {
+		#[cfg(target_os = "linux")]
+		return libc::lgetxattr(p.as_ptr(), n.as_ptr(), buf, size);
+		#[cfg(target_os = "macos")]
+		return libc::getxattr(p.as_ptr(), n.as_ptr(), buf, size, 0, libc::XATTR_NOFOLLOW);
+	};
+
+	let len = get(std::ptr::null_mut(), 0);
+	if len < 0 {
+		return None;
+	}
+	let mut buf = vec![0u8; len as usize];
+	let len = get(buf.as_mut_ptr().cast(), buf.len());
+	if len < 0 {
+		return None;
+	}
+
+	buf.truncate(len as usize);
+	Some(buf)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn xattr_value(_: &Path, _: &str) -> Option<Vec<u8>> { None }
+
+// Copies the extended attributes of `from` to `to`, each of them that can be, leaving out the
+// ones the filesystem of `to` doesn't support or that aren't permitted to be set
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn xattr_copy(from: &Path, to: &Path) {
+	use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+	let Ok(p) = CString::new(to.as_os_str().as_bytes()) else {
+		return;
+	};
+	for name in xattr_names(from) {
+		let (Ok(n), Some(value)) = (CString::new(name.as_str()), xattr_value(from, &name)) else {
+			continue;
+		};
+		let (buf, size) = (value.as_ptr().cast(), value.len());
+		unsafe {
+			#[cfg(target_os = "linux")]
+			libc::lsetxattr(p.as_ptr(), n.as_ptr(), buf, size, 0);
+			#[cfg(target_os = "macos")]
+			libc::setxattr(p.as_ptr(), n.as_ptr(), buf, size, 0, libc::XATTR_NOFOLLOW);
+		}
+	}
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn xattr_copy(_: &Path, _: &Path) {}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_xattr() {
+	let dir = crate::TestDir::new("xattr");
+	let path = dir.join("a");
+	std::fs::write(&path, "").unwrap();
+
+	let (p, n) = (
+		std::ffi::CString::new(path.to_str().unwrap()).unwrap(),
+		std::ffi::CString::new("user.yazi").unwrap(),
+	);
+	let set = unsafe { libc::setxattr(p.as_ptr(), n.as_ptr(), b"1".as_ptr().cast(), 1, 0) };
+
+	// Not every filesystem of the temporary directory supports the user namespace
+	if set == 0 {
+		assert_eq!(xattr_names(&path), ["user.yazi"]);
+		assert_eq!(xattr_value(&path, "user.yazi").as_deref(), Some(&b"1"[..]));
+
+		let copy = path.with_extension("copy");
+		std::fs::write(&copy, "").unwrap();
+		xattr_copy(&path, &copy);
+		assert_eq!(xattr_value(&copy, "user.yazi").as_deref(), Some(&b"1"[..]));
+	}
+	assert_eq!(xattr_value(&path, "user.none"), None);
+}